@@ -0,0 +1,49 @@
+-- Limit order bot runs, the orders each placed, and position tracker snapshots
+
+CREATE TABLE bot_runs (
+    id TEXT PRIMARY KEY,
+    created_at TEXT NOT NULL,
+    wallet_address TEXT NOT NULL,
+    market_slug TEXT,
+    mode TEXT NOT NULL,
+    dry_run INTEGER NOT NULL,
+    orders_placed INTEGER NOT NULL,
+    orders_failed INTEGER NOT NULL,
+    summary TEXT NOT NULL,
+    -- JSON array of log lines
+    logs TEXT NOT NULL
+);
+
+CREATE INDEX bot_runs_created_at ON bot_runs (created_at);
+
+CREATE TABLE orders (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    run_id TEXT NOT NULL REFERENCES bot_runs (id) ON DELETE CASCADE,
+    token_id TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    side TEXT NOT NULL,
+    price REAL NOT NULL,
+    size REAL NOT NULL,
+    order_id TEXT,
+    status TEXT NOT NULL,
+    error TEXT,
+    market_slug TEXT,
+    created_at TEXT NOT NULL
+);
+
+CREATE INDEX orders_run_id ON orders (run_id);
+CREATE INDEX orders_order_id ON orders (order_id);
+
+CREATE TABLE position_snapshots (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    taken_at TEXT NOT NULL,
+    wallet_address TEXT NOT NULL,
+    market_slug TEXT,
+    token_id TEXT NOT NULL,
+    outcome TEXT NOT NULL,
+    shares REAL NOT NULL,
+    avg_price REAL NOT NULL,
+    current_price REAL NOT NULL
+);
+
+CREATE INDEX position_snapshots_wallet ON position_snapshots (wallet_address, taken_at);
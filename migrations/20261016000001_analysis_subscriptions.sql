@@ -0,0 +1,24 @@
+-- Scheduled re-analysis subscriptions and the runs each has made
+
+CREATE TABLE analysis_subscriptions (
+    id TEXT PRIMARY KEY,
+    market_url TEXT NOT NULL,
+    cadence TEXT NOT NULL,
+    model TEXT,
+    webhook_url TEXT,
+    min_confidence_change REAL NOT NULL,
+    created_at TEXT NOT NULL,
+    next_run_at TEXT NOT NULL
+);
+
+CREATE TABLE subscription_runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    subscription_id TEXT NOT NULL REFERENCES analysis_subscriptions (id) ON DELETE CASCADE,
+    analyzed_at TEXT NOT NULL,
+    analysis_id TEXT NOT NULL,
+    recommendation TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    change TEXT
+);
+
+CREATE INDEX subscription_runs_subscription_id ON subscription_runs (subscription_id, id);
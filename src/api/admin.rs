@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use std::sync::Arc;
+
+use crate::api::extract::AppJson;
+use crate::api::runtime_config::{RuntimeSettings, RuntimeSettingsUpdate};
+use crate::api::AppState;
+use crate::clients::recorder::{load_recording, UpstreamRecording};
+use crate::config::ConfigSummary;
+use crate::{AppError, Result};
+
+const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+/// Optional operator name recorded against changes such as disabling trading.
+const ADMIN_ACTOR_HEADER: &str = "x-admin-actor";
+
+/// Admin routes are disabled unless `ADMIN_API_TOKEN` is set, and then
+/// require it in the `X-Admin-Token` header.
+pub fn require_admin(state: &AppState, headers: &HeaderMap) -> Result<()> {
+    let expected = state.config.admin_api_token.as_deref().ok_or_else(|| {
+        AppError::Unauthorized(
+            "Admin API is disabled; set ADMIN_API_TOKEN to enable it".to_string(),
+        )
+    })?;
+
+    let provided = headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+
+    if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::Unauthorized("Invalid admin token".to_string()));
+    }
+
+    Ok(())
+}
+
+pub async fn get_runtime_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<RuntimeSettings>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.runtime_config.snapshot()))
+}
+
+/// The settings the server started with, secrets reduced to whether they
+/// are set.
+pub async fn get_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<ConfigSummary>> {
+    require_admin(&state, &headers)?;
+    Ok(Json(state.config.summary()))
+}
+
+pub async fn update_runtime_config(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AppJson(update): AppJson<RuntimeSettingsUpdate>,
+) -> Result<Json<RuntimeSettings>> {
+    require_admin(&state, &headers)?;
+    let actor = headers
+        .get(ADMIN_ACTOR_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|a| !a.is_empty())
+        .unwrap_or("admin");
+    let settings = state.runtime_config.apply(update, actor);
+    tracing::info!("Runtime config updated: {:?}", settings);
+    Ok(Json(settings))
+}
+
+pub async fn get_recording(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<UpstreamRecording>> {
+    require_admin(&state, &headers)?;
+    load_recording(&id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Recording {} not found", id)))
+}
+
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,84 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::clients::AiRequestOptions;
+use crate::types::{AiAnalysis, MarketData, MarketRef};
+
+/// Completed analyses kept for `/api/analyze-event-markets/refresh`.
+const MAX_STORED_ANALYSES: usize = 500;
+
+/// Prices and volume at the time an analysis was produced.
+#[derive(Debug, Clone)]
+pub struct MarketSnapshot {
+    pub prices: Vec<(String, f64)>,
+    pub volume: Option<f64>,
+}
+
+impl MarketSnapshot {
+    pub fn capture(market: &MarketData) -> Self {
+        Self {
+            prices: market
+                .outcomes
+                .iter()
+                .map(|o| (o.id.clone(), o.price.value()))
+                .collect(),
+            volume: market.volume,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredAnalysis {
+    pub id: String,
+    /// The request's URL, or the market's page for identifier requests
+    pub url: String,
+    /// Used to refetch the market without parsing `url`
+    pub market: MarketRef,
+    pub question: Option<String>,
+    pub model: Option<String>,
+    pub custom_prompt: Option<String>,
+    /// Replayed on refresh so the rerun uses the same model settings
+    pub ai_options: AiRequestOptions,
+    pub snapshot: MarketSnapshot,
+    pub analysis: AiAnalysis,
+    pub created_at: DateTime<Utc>,
+}
+
+/// In-memory store of recent analyses, evicting the oldest past a fixed cap.
+#[derive(Debug, Default)]
+pub struct AnalysisStore {
+    analyses: Mutex<HashMap<String, StoredAnalysis>>,
+}
+
+impl AnalysisStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, analysis: StoredAnalysis) {
+        let mut analyses = self.analyses.lock().unwrap_or_else(|e| e.into_inner());
+        if analyses.len() >= MAX_STORED_ANALYSES {
+            if let Some(oldest) = analyses
+                .values()
+                .min_by_key(|a| a.created_at)
+                .map(|a| a.id.clone())
+            {
+                analyses.remove(&oldest);
+            }
+        }
+        analyses.insert(analysis.id.clone(), analysis);
+    }
+
+    pub fn get(&self, id: &str) -> Option<StoredAnalysis> {
+        self.analyses
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(id)
+            .cloned()
+    }
+}
+
+pub fn new_analysis_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
@@ -0,0 +1,404 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use url::Url;
+
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::analyze_event_markets::{resolve_provider, run_analysis};
+use crate::api::capabilities::Capability;
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::clients::ai::prompts::PromptEvidence;
+use crate::clients::webhook::EventKind;
+use crate::clients::AiRequestOptions;
+use crate::request_id;
+use crate::types::{
+    AnalysisDrift, AnalysisSubscription, AnalysisSubscriptionResponse,
+    CreateAnalysisSubscriptionRequest, Recommendation, ResponseMetadata, SubscriptionCadence,
+    SubscriptionHistoryResponse, SubscriptionRunPoint,
+};
+use crate::{AppError, Result};
+
+const MAX_SUBSCRIPTIONS: usize = 100;
+/// Runs kept per subscription (weekly runs cover ~10 years).
+const MAX_RUNS_PER_SUBSCRIPTION: usize = 520;
+const DEFAULT_MIN_CONFIDENCE_CHANGE: f64 = 0.15;
+pub const DEFAULT_TICK_SECS: u64 = 60;
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<CreateAnalysisSubscriptionRequest>,
+) -> Result<Json<AnalysisSubscriptionResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    Url::parse(&request.market_url)
+        .map_err(|e| AppError::Validation(format!("Invalid market_url: {}", e)))?;
+    if let Some(webhook_url) = &request.webhook_url {
+        state.webhooks.check_url(webhook_url).await?;
+    }
+    let min_confidence_change = request
+        .min_confidence_change
+        .unwrap_or(DEFAULT_MIN_CONFIDENCE_CHANGE);
+    if !(min_confidence_change > 0.0 && min_confidence_change <= 1.0) {
+        return Err(AppError::Validation(
+            "min_confidence_change must be in (0, 1]".to_string(),
+        ));
+    }
+
+    state.capabilities.require(Capability::Dome)?;
+    state
+        .capabilities
+        .require(Capability::ai(&resolve_provider(request.model.as_deref())))?;
+
+    // The first run happens on the next scheduler tick and sets the baseline
+    let now = Utc::now();
+    let subscription = Subscription {
+        id: format!("sub_{}", uuid::Uuid::new_v4().simple()),
+        market_url: request.market_url,
+        cadence: request.cadence,
+        model: request.model,
+        webhook_url: request.webhook_url,
+        min_confidence_change,
+        created_at: now,
+        next_run_at: now,
+        history: Vec::new(),
+    };
+    let view = subscription.view();
+    state
+        .analysis_subscriptions
+        .insert(subscription.clone())
+        .map_err(AppError::Validation)?;
+
+    let mut metadata = metadata(&state, start);
+    // Kept in memory when it can't be stored, so it still runs until a restart
+    if let Some(storage) = &state.storage {
+        if let Err(e) = storage.save_subscription(&subscription).await {
+            tracing::warn!("Failed to store subscription {}: {}", subscription.id, e);
+            metadata.degraded_features.push("persistence".to_string());
+        }
+    }
+
+    Ok(Json(AnalysisSubscriptionResponse {
+        subscription: view,
+        metadata,
+    }))
+}
+
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<SubscriptionHistoryResponse>> {
+    let start = Instant::now();
+
+    let (subscription, points) = state
+        .analysis_subscriptions
+        .history(&id)
+        .ok_or_else(|| AppError::NotFound(format!("Subscription {} not found", id)))?;
+
+    Ok(Json(SubscriptionHistoryResponse {
+        subscription_id: subscription.id,
+        market_url: subscription.market_url,
+        points,
+        metadata: metadata(&state, start),
+    }))
+}
+
+fn metadata(state: &AppState, start: Instant) -> ResponseMetadata {
+    ResponseMetadata {
+        timestamp: Utc::now().to_rfc3339(),
+        execution_time_ms: start.elapsed().as_millis() as u64,
+        model_used: None,
+        retries: 0,
+        // Without persistence, subscriptions and their history are lost on restart
+        degraded_features: if state.capabilities.persistence {
+            Vec::new()
+        } else {
+            vec!["persistence".to_string()]
+        },
+        custom_prompt: false,
+        dry_run: false,
+        cache_hit: None,
+        request_id: request_id::current(),
+        ai_usage: None,
+        ai_provider: None,
+        query_compression: None,
+        timeout_budget: None,
+    }
+}
+
+impl SubscriptionCadence {
+    pub fn period(self) -> Duration {
+        match self {
+            SubscriptionCadence::Daily => Duration::days(1),
+            SubscriptionCadence::Weekly => Duration::weeks(1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Subscription {
+    pub id: String,
+    pub market_url: String,
+    pub cadence: SubscriptionCadence,
+    pub model: Option<String>,
+    pub webhook_url: Option<String>,
+    pub min_confidence_change: f64,
+    pub created_at: DateTime<Utc>,
+    pub next_run_at: DateTime<Utc>,
+    /// Oldest first
+    pub history: Vec<SubscriptionRunPoint>,
+}
+
+impl Subscription {
+    pub fn view(&self) -> AnalysisSubscription {
+        AnalysisSubscription {
+            id: self.id.clone(),
+            market_url: self.market_url.clone(),
+            cadence: self.cadence,
+            model: self.model.clone(),
+            webhook_url: self.webhook_url.clone(),
+            min_confidence_change: self.min_confidence_change,
+            created_at: self.created_at.to_rfc3339(),
+            next_run_at: self.next_run_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Compares a run against the previous one. The first run is a baseline and
+/// never counts as a change; a flipped recommendation takes precedence over
+/// a confidence move of at least `min_confidence_change`.
+pub fn detect_change(
+    previous: Option<&SubscriptionRunPoint>,
+    recommendation: &Recommendation,
+    confidence: f64,
+    min_confidence_change: f64,
+) -> Option<AnalysisDrift> {
+    let previous = previous?;
+    if previous.recommendation != *recommendation {
+        Some(AnalysisDrift::RecommendationChanged)
+    } else if (confidence - previous.confidence).abs() >= min_confidence_change - 1e-9 {
+        Some(AnalysisDrift::ConfidenceShift)
+    } else {
+        None
+    }
+}
+
+/// Subscriptions keyed by id. With storage enabled they are also written
+/// to the database and reloaded from it at startup.
+#[derive(Debug, Default)]
+pub struct SubscriptionStore {
+    subscriptions: Mutex<HashMap<String, Subscription>>,
+}
+
+impl SubscriptionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A store holding `subscriptions`, e.g. as loaded from storage.
+    pub fn with_subscriptions(subscriptions: Vec<Subscription>) -> Self {
+        Self {
+            subscriptions: Mutex::new(
+                subscriptions
+                    .into_iter()
+                    .map(|s| (s.id.clone(), s))
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn insert(&self, subscription: Subscription) -> std::result::Result<(), String> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        if subscriptions.len() >= MAX_SUBSCRIPTIONS {
+            return Err(format!(
+                "Subscription limit of {} reached",
+                MAX_SUBSCRIPTIONS
+            ));
+        }
+        subscriptions.insert(subscription.id.clone(), subscription);
+        Ok(())
+    }
+
+    pub fn history(&self, id: &str) -> Option<(AnalysisSubscription, Vec<SubscriptionRunPoint>)> {
+        let subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        subscriptions.get(id).map(|s| (s.view(), s.history.clone()))
+    }
+
+    /// Subscriptions due at `now`, returned already moved to their next
+    /// slot, so a failed run waits a full period instead of retrying every
+    /// tick.
+    pub fn claim_due(&self, now: DateTime<Utc>) -> Vec<Subscription> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        subscriptions
+            .values_mut()
+            .filter(|s| s.next_run_at <= now)
+            .map(|s| {
+                s.next_run_at = now + s.cadence.period();
+                s.clone()
+            })
+            .collect()
+    }
+
+    /// Appends a run, tagged with any change against the previous run.
+    /// Returns the previous and recorded points, or `None` if the
+    /// subscription no longer exists.
+    pub fn record(
+        &self,
+        id: &str,
+        mut point: SubscriptionRunPoint,
+    ) -> Option<(Option<SubscriptionRunPoint>, SubscriptionRunPoint)> {
+        let mut subscriptions = self.subscriptions.lock().unwrap_or_else(|e| e.into_inner());
+        let subscription = subscriptions.get_mut(id)?;
+        let previous = subscription.history.last().cloned();
+        point.change = detect_change(
+            previous.as_ref(),
+            &point.recommendation,
+            point.confidence,
+            subscription.min_confidence_change,
+        );
+        subscription.history.push(point.clone());
+        if subscription.history.len() > MAX_RUNS_PER_SUBSCRIPTION {
+            let excess = subscription.history.len() - MAX_RUNS_PER_SUBSCRIPTION;
+            subscription.history.drain(..excess);
+        }
+        Some((previous, point))
+    }
+}
+
+/// Body POSTed to a subscription's webhook when a run changes materially.
+#[derive(Debug, Serialize)]
+struct DriftAlert<'a> {
+    event: EventKind,
+    event_id: String,
+    created_at: String,
+    subscription_id: &'a str,
+    market_url: &'a str,
+    change: AnalysisDrift,
+    previous: &'a SubscriptionRunPoint,
+    current: &'a SubscriptionRunPoint,
+}
+
+/// Runs due subscriptions every `ANALYSIS_SUBSCRIPTION_TICK_SECS` until
+/// shutdown.
+pub fn spawn_scheduler(state: Arc<AppState>) {
+    let tick = state.config.analysis_subscription_tick;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = state.shutdown.cancelled() => return,
+            }
+            run_due(&state, Utc::now()).await;
+        }
+    });
+}
+
+/// Analyzes each subscription due at `now` and alerts its webhook when the
+/// run changed materially against the previous one.
+pub async fn run_due(state: &AppState, now: DateTime<Utc>) {
+    for subscription in state.analysis_subscriptions.claim_due(now) {
+        if let Some(storage) = &state.storage {
+            if let Err(e) = storage
+                .reschedule_subscription(&subscription.id, subscription.next_run_at)
+                .await
+            {
+                tracing::warn!("Failed to store schedule of {}: {}", subscription.id, e);
+            }
+        }
+        if let Err(e) = run_subscription(state, &subscription).await {
+            tracing::warn!("Scheduled analysis failed for {}: {}", subscription.id, e);
+        }
+    }
+}
+
+async fn run_subscription(state: &AppState, subscription: &Subscription) -> Result<()> {
+    let dome = state.dome()?;
+    let market_ref = dome.market_ref_from_url(&subscription.market_url)?;
+    let market = dome
+        .get_market(market_ref.platform, &market_ref.identifier)
+        .await?;
+    let provider = resolve_provider(subscription.model.as_deref());
+    let run = run_analysis(
+        state,
+        &market,
+        None,
+        None,
+        &PromptEvidence::default(),
+        provider,
+        &AiRequestOptions::default(),
+        true,
+    )
+    .await?;
+
+    let analysis_id = new_analysis_id();
+    let analyzed_at = Utc::now();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: subscription.market_url.clone(),
+        market: market_ref,
+        question: None,
+        model: Some(run.provider.to_string()),
+        custom_prompt: None,
+        ai_options: AiRequestOptions::default(),
+        snapshot: MarketSnapshot::capture(&market),
+        analysis: run.analysis.clone(),
+        created_at: analyzed_at,
+    });
+
+    let point = SubscriptionRunPoint {
+        analyzed_at: analyzed_at.to_rfc3339(),
+        analysis_id,
+        recommendation: run.analysis.recommendation,
+        confidence: run.analysis.confidence,
+        change: None,
+    };
+    let Some((previous, current)) = state.analysis_subscriptions.record(&subscription.id, point)
+    else {
+        return Ok(());
+    };
+    if let Some(storage) = &state.storage {
+        if let Err(e) = storage
+            .record_subscription_run(&subscription.id, &current, MAX_RUNS_PER_SUBSCRIPTION)
+            .await
+        {
+            tracing::warn!("Failed to store run of {}: {}", subscription.id, e);
+        }
+    }
+    let (Some(previous), Some(change), Some(webhook_url)) =
+        (previous, current.change, &subscription.webhook_url)
+    else {
+        return Ok(());
+    };
+
+    tracing::info!(
+        "Subscription {} changed ({:?}): {:?} {:.2} -> {:?} {:.2}",
+        subscription.id,
+        change,
+        previous.recommendation,
+        previous.confidence,
+        current.recommendation,
+        current.confidence
+    );
+    let alert = DriftAlert {
+        event: EventKind::AnalysisChanged,
+        event_id: format!("evt_{}", uuid::Uuid::new_v4().simple()),
+        created_at: Utc::now().to_rfc3339(),
+        subscription_id: &subscription.id,
+        market_url: &subscription.market_url,
+        change,
+        previous: &previous,
+        current: &current,
+    };
+    state.webhooks.deliver(webhook_url, &alert).await;
+
+    Ok(())
+}
@@ -0,0 +1,168 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::analyze_event_markets::{
+    apply_risk_gate, resolve_provider, resolve_target, run_analysis,
+};
+use crate::api::capabilities::Capability;
+use crate::api::extract::AppJson;
+use crate::api::limit_order_bot::{run_bot, validate_request, BotMarket};
+use crate::api::market_cache::CacheQuery;
+use crate::api::AppState;
+use crate::clients::ai::prompts::{analysis_warnings, PromptEvidence};
+use crate::clients::dome::parse_market_url;
+use crate::clients::AiRequestOptions;
+use crate::request_id;
+use crate::types::{
+    AnalyzeAndTradeRequest, AnalyzeAndTradeResponse, MarketData, OutcomeTarget, Platform,
+    ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+/// Analyzes a Polymarket market and, when the model recommends a side with
+/// at least `min_confidence`, buys it with the limit order bot on the same
+/// market data. Dry runs, trading mode and exposure caps apply as they do
+/// for the bot; NO_TRADE returns before anything is planned.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(cache): Query<CacheQuery>,
+    AppJson(request): AppJson<AnalyzeAndTradeRequest>,
+) -> Result<Json<AnalyzeAndTradeResponse>> {
+    let start = Instant::now();
+    let dry_run = request.bot.dry_run.unwrap_or(false);
+
+    // Refuse before the analysis what the bot would refuse after it
+    if !dry_run {
+        state.runtime_config.ensure_trading_enabled()?;
+    }
+    validate_request(&request.bot, state.config.wallet.as_ref())?;
+
+    let market_ref = parse_market_url(&request.url).map_err(AppError::Validation)?;
+    if market_ref.platform != Platform::Polymarket {
+        return Err(AppError::Validation(
+            "Trading is only supported on Polymarket markets".to_string(),
+        ));
+    }
+    let provider = resolve_provider(request.model.as_deref());
+    state.capabilities.require(Capability::ai(&provider))?;
+
+    let slug = market_ref.identifier.clone();
+    let cached = state
+        .market_cache
+        .polymarket(&slug, cache.fresh(), || {
+            state.polymarket_client.get_market_by_slug(&slug)
+        })
+        .await?;
+    let market = MarketData::clone(&cached.market);
+
+    let run = run_analysis(
+        &state,
+        &market,
+        request.question.as_ref(),
+        None,
+        &PromptEvidence::default(),
+        provider,
+        &AiRequestOptions::default(),
+        true,
+    )
+    .await?;
+    let ai_provider = run.provider_used();
+    let mut analysis = run.analysis;
+    let mut overrides = apply_risk_gate(&mut analysis, request.min_confidence);
+    let target = resolve_target(&mut analysis, &market, &mut overrides);
+    // A flagged analysis is reported but never traded
+    let warnings = analysis_warnings(&analysis, &market);
+    let target = target.filter(|_| warnings.is_empty());
+
+    let analysis_id = new_analysis_id();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: request.url.clone(),
+        market: market_ref,
+        question: request.question.clone(),
+        model: request.model.clone(),
+        custom_prompt: None,
+        ai_options: AiRequestOptions::default(),
+        snapshot: MarketSnapshot::capture(&market),
+        analysis: analysis.clone(),
+        created_at: Utc::now(),
+    });
+
+    let mut metadata = ResponseMetadata {
+        timestamp: Utc::now().to_rfc3339(),
+        execution_time_ms: 0,
+        model_used: Some(run.model_used),
+        retries: run.retries,
+        degraded_features: Vec::new(),
+        custom_prompt: false,
+        dry_run,
+        cache_hit: Some(cached.hit),
+        request_id: request_id::current(),
+        ai_usage: Some(run.usage),
+        ai_provider: Some(ai_provider),
+        query_compression: None,
+        timeout_budget: None,
+    };
+
+    let Some(target) = target else {
+        let skip_reason = match warnings.first() {
+            Some(warning) => format!("Not trading a flagged analysis: {}", warning),
+            None => overrides
+                .iter()
+                .rev()
+                .find(|o| o.contains("downgraded to NO_TRADE"))
+                .cloned()
+                .unwrap_or_else(|| "The analysis recommends NO_TRADE".to_string()),
+        };
+        metadata.execution_time_ms = start.elapsed().as_millis() as u64;
+        return Ok(Json(AnalyzeAndTradeResponse {
+            analysis,
+            market_data: market,
+            analysis_id,
+            traded: false,
+            skip_reason: Some(skip_reason),
+            overrides,
+            warnings,
+            orders: Vec::new(),
+            order_ids: Vec::new(),
+            logs: Vec::new(),
+            run_id: None,
+            metadata,
+        }));
+    };
+
+    let mut bot = request.bot;
+    bot.market_slug = Some(market.slug.clone().unwrap_or(slug));
+    bot.outcomes = Some(vec![OutcomeTarget {
+        outcome: target.outcome.id,
+        weight: None,
+    }]);
+    let fetched = BotMarket::Fetched {
+        market: cached.market.clone(),
+        cache_hit: cached.hit,
+    };
+    let run = run_bot(&state, &bot, None, fetched).await?;
+
+    metadata.degraded_features = run.metadata.degraded_features;
+    metadata.execution_time_ms = start.elapsed().as_millis() as u64;
+    Ok(Json(AnalyzeAndTradeResponse {
+        analysis,
+        market_data: market,
+        analysis_id,
+        traded: true,
+        skip_reason: None,
+        overrides,
+        warnings,
+        orders: run.orders,
+        order_ids: run.order_ids,
+        logs: run.logs,
+        run_id: run.run_id,
+        metadata,
+    }))
+}
@@ -1,95 +1,958 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
 use chrono::Utc;
 use std::sync::Arc;
 use std::time::Instant;
 
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::capabilities::Capability;
+use crate::api::chart::{downsample_lttb, MAX_CHART_POINTS};
+use crate::api::construct_portfolio::kelly_fraction;
+use crate::api::event_analysis;
+use crate::api::extract::AppJson;
+use crate::api::market_cache::{CacheQuery, CachedMarket};
+use crate::api::openapi::ErrorResponse;
+use crate::api::polyfactual_research;
 use crate::api::AppState;
-use crate::clients::ai::prompts::build_analysis_prompt;
-use crate::clients::{create_ai_client, AiProvider, DomeClient};
-use crate::types::{AnalyzeEventMarketsRequest, AnalyzeEventMarketsResponse, ResponseMetadata};
-use crate::Result;
+use crate::clients::ai::prompts::{
+    analysis_warnings, build_analysis_prompt, build_analysis_prompt_with_evidence,
+    build_custom_prompt, detect_question_focus, validate_custom_prompt, FewShot, MarketDepth,
+    OutcomeDepth, PromptEvidence, PromptMessage, ResearchEvidence, MAX_PROMPT_BOOK_OUTCOMES,
+};
+use crate::clients::ai::TokenUsage;
+use crate::clients::dome::parse_market_url;
+use crate::clients::polymarket::MARKET_TRADES_LIMIT;
+use crate::clients::{AiClient, AiProvider, AiRequestOptions};
+use crate::request_id;
+use crate::types::{
+    AiAnalysis, AiProviderUsed, AiUsage, AnalysisComparison, AnalyzeEventMarketsOutput,
+    AnalyzeEventMarketsRequest, AnalyzeEventMarketsResponse, CandleInterval, CandleSummary,
+    Consensus, ConsensusAgreement, MarketData, MarketRef, Outcome, Platform, ProviderAnalysis,
+    Recommendation, ResponseMetadata, TargetMatch, TargetOutcome, TimeoutBudget,
+};
+use crate::{AppError, Result};
 
+pub const DEFAULT_RESEARCH_TIMEOUT_SECS: u64 = 30;
+/// Price history summarized into the prompt with `include_history`
+const PROMPT_HISTORY_HOURS: i64 = 2;
+
+/// Analyzes a market with the chosen AI provider, or with
+/// `analyze_all_markets` every market of a Polymarket event.
+#[utoipa::path(
+    post,
+    path = "/api/analyze-event-markets",
+    tag = "analysis",
+    params(CacheQuery),
+    request_body = AnalyzeEventMarketsRequest,
+    responses(
+        (status = 200, description = "One market's analysis; an `AnalyzeEventResponse` with `analyze_all_markets`", body = AnalyzeEventMarketsResponse),
+        (status = 400, description = "Invalid request or missing integration", body = ErrorResponse),
+        (status = 502, description = "Upstream failure", body = ErrorResponse),
+    )
+)]
 pub async fn handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<AnalyzeEventMarketsRequest>,
-) -> Result<Json<AnalyzeEventMarketsResponse>> {
+    Query(cache): Query<CacheQuery>,
+    AppJson(request): AppJson<AnalyzeEventMarketsRequest>,
+) -> Result<Json<AnalyzeEventMarketsOutput>> {
     let start = Instant::now();
-    let mut retries = 0;
 
     // Validate request
-    if request.url.is_empty() {
-        return Err(crate::AppError::Validation("URL is required".to_string()));
+    if let Some(custom_prompt) = request.custom_prompt.as_deref() {
+        validate_custom_prompt(custom_prompt).map_err(crate::AppError::Validation)?;
     }
 
-    // Determine AI provider
-    let provider = match request.model.as_deref() {
-        Some("openai") => AiProvider::OpenAi,
-        _ => AiProvider::Grok, // Default to Grok
+    let include_research = request.include_research.unwrap_or(false);
+    if let Some(research_query) = &request.research_query {
+        let max_length = state.config.polyfactual_max_query_length;
+        if research_query.trim().is_empty() || research_query.chars().count() > max_length {
+            return Err(crate::AppError::Validation(format!(
+                "research_query must be 1 to {} characters",
+                max_length
+            )));
+        }
+    }
+
+    // Determine AI provider; `compare` always runs Grok and OpenAI
+    let compare = request.compare.unwrap_or(false);
+    let provider = if compare {
+        AiProvider::Grok
+    } else {
+        resolve_provider(request.model.as_deref())
     };
+    let ai_options = AiRequestOptions {
+        model_name: request.model_name.clone(),
+        temperature: request.temperature,
+        max_tokens: request.max_tokens,
+        timeout_secs: request.timeout_secs,
+    };
+    ai_options
+        .validate(&provider)
+        .map_err(crate::AppError::Validation)?;
+    // A comparison can go ahead with OpenAI alone
+    if !(compare && state.capabilities.openai) {
+        state.capabilities.require(Capability::ai(&provider))?;
+    }
 
-    // Fetch market data from Dome API
-    let market_data = state
-        .dome_clients
-        .dome
-        .get_market_by_url(&request.url)
+    if request.analyze_all_markets.unwrap_or(false) {
+        let response =
+            event_analysis::analyze_all_markets(&state, &request, provider, ai_options, start)
+                .await?;
+        return Ok(Json(AnalyzeEventMarketsOutput::Event(Box::new(response))));
+    }
+
+    // Fetch market data, from Kalshi directly when configured and from Dome
+    // otherwise; identifiers skip URL parsing
+    let market_ref = match (&request.url, &request.market) {
+        (Some(url), _) => parse_market_url(url).map_err(crate::AppError::Validation)?,
+        (None, Some(market)) => market.clone(),
+        (None, None) => unreachable!("validated above"),
+    };
+    let cached = fetch_market(&state, &market_ref, cache.fresh())
         .await
         .map_err(|e| {
             tracing::error!("Failed to fetch market data: {}", e);
             e
         })?;
+    let market_data = MarketData::clone(&cached.market);
 
-    // Build AI prompt
-    let prompt = build_analysis_prompt(&market_data, request.question.as_ref());
-    println!("prompt ------------> {:?}", prompt);
-    // Call AI with retry logic (handled in client)
-    println!("provider ------------> {:?}", provider);
-    let ai_client = create_ai_client(provider.clone())?;
+    let mut degraded_features = Vec::new();
+    let research = if include_research {
+        let query = request
+            .research_query
+            .clone()
+            .unwrap_or_else(|| market_data.question.clone());
+        let research = fetch_research(&state, query).await;
+        if research.is_none() {
+            degraded_features.push("research".to_string());
+        }
+        research
+    } else {
+        None
+    };
+    let history = if request.include_history.unwrap_or(false) {
+        let history = fetch_history(&state, &market_data).await;
+        if history.is_none() {
+            degraded_features.push("history".to_string());
+        }
+        history
+    } else {
+        None
+    };
+    let depth = if request.include_orderbook.unwrap_or(false) {
+        let depth = fetch_depth(&state, &market_data).await;
+        if depth.is_none() {
+            degraded_features.push("orderbook".to_string());
+        }
+        depth
+    } else {
+        None
+    };
+    let evidence = PromptEvidence {
+        research,
+        history,
+        depth,
+    };
 
-    tracing::info!("ai_client ------------> {}", ai_client.provider_name());
-    let analysis = match ai_client.analyze_markets(prompt).await {
-        Ok(analysis) => analysis,
-        Err(e) => {
-            // Retry once with different provider if Grok fails
-            if matches!(provider, AiProvider::Grok) {
-                retries = 1;
-                tracing::warn!("Grok failed, retrying with OpenAI");
-                let openai_client = create_ai_client(AiProvider::OpenAi)?;
-                openai_client
-                    .analyze_markets(build_analysis_prompt(
-                        &market_data,
-                        request.question.as_ref(),
-                    ))
-                    .await?
-            } else {
-                return Err(e);
-            }
+    let (run, comparison) = if compare {
+        let (run, comparison) = run_comparison(
+            &state,
+            &market_data,
+            request.question.as_ref(),
+            request.custom_prompt.as_deref(),
+            &evidence,
+            &ai_options,
+        )
+        .await?;
+        if comparison.grok.is_none() {
+            degraded_features.push("grok_analysis".to_string());
+        }
+        if comparison.openai.is_none() {
+            degraded_features.push("openai_analysis".to_string());
+        }
+        (run, Some(comparison))
+    } else {
+        let run = run_analysis(
+            &state,
+            &market_data,
+            request.question.as_ref(),
+            request.custom_prompt.as_deref(),
+            &evidence,
+            provider,
+            &ai_options,
+            request.allow_fallback.unwrap_or(true),
+        )
+        .await?;
+        (run, None)
+    };
+    let timeout_budget = run.timeout_budget(&ai_options);
+    let ai_provider = run.provider_used();
+    let mut analysis = run.analysis;
+    let mut overrides = apply_risk_gate(&mut analysis, request.min_confidence.unwrap_or(0.0));
+    let target = resolve_target(&mut analysis, &market_data, &mut overrides);
+    let mut warnings = run.warnings;
+    warnings.extend(analysis_warnings(&analysis, &market_data));
+    let suggested_size_usd = request
+        .max_position_usd
+        .map(|max_position_usd| suggested_size(&analysis, &market_data, max_position_usd));
+
+    let chart = if request.include_chart.unwrap_or(false) {
+        let chart = fetch_chart(&state, &market_data).await;
+        if chart.is_none() {
+            degraded_features.push("chart".to_string());
         }
+        chart
+    } else {
+        None
     };
 
+    let question_focus = request
+        .question
+        .as_deref()
+        .and_then(|q| detect_question_focus(q, &market_data.outcomes));
+
+    let analysis_id = new_analysis_id();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: request.url.clone().unwrap_or_else(|| market_ref.page_url()),
+        market: market_ref,
+        question: request.question.clone(),
+        model: request.model.clone(),
+        custom_prompt: request.custom_prompt.clone(),
+        ai_options,
+        snapshot: MarketSnapshot::capture(&market_data),
+        analysis: analysis.clone(),
+        created_at: Utc::now(),
+    });
+
     let execution_time = start.elapsed().as_millis() as u64;
 
     let recommendation = analysis.recommendation.clone();
-    Ok(Json(AnalyzeEventMarketsResponse {
+    let response = AnalyzeEventMarketsResponse {
         recommendation,
         analysis,
         market_data,
+        question_focus,
+        analysis_id,
+        chart,
+        research_citations: evidence.research.map(|r| r.citations),
+        comparison,
+        overrides,
+        warnings,
+        suggested_size_usd,
+        target_token_id: target.as_ref().map(|t| t.outcome.id.clone()),
+        target_match: target.map(|t| t.matched),
         metadata: ResponseMetadata {
             timestamp: Utc::now().to_rfc3339(),
             execution_time_ms: execution_time,
-            model_used: Some(ai_client.provider_name().to_string()),
-            retries,
+            model_used: Some(run.model_used),
+            retries: run.retries,
+            degraded_features,
+            custom_prompt: request.custom_prompt.is_some(),
+            dry_run: false,
+            cache_hit: Some(cached.hit),
+            request_id: request_id::current(),
+            query_compression: None,
+            timeout_budget: Some(timeout_budget),
+            ai_usage: Some(run.usage),
+            ai_provider: Some(ai_provider),
         },
-    }))
+    };
+    Ok(Json(AnalyzeEventMarketsOutput::Market(Box::new(response))))
+}
+
+/// Checks the model's answer before anyone trades on it: a confidence
+/// outside [0, 1] is clamped (NaN counts as 0), and a trade recommended
+/// below `min_confidence` becomes NO_TRADE. Returns what was changed.
+pub fn apply_risk_gate(analysis: &mut AiAnalysis, min_confidence: f64) -> Vec<String> {
+    let mut overrides = Vec::new();
+
+    let confidence = analysis.confidence;
+    if !(0.0..=1.0).contains(&confidence) {
+        analysis.confidence = if confidence.is_nan() {
+            0.0
+        } else {
+            confidence.clamp(0.0, 1.0)
+        };
+        overrides.push(format!(
+            "Confidence {} was outside [0, 1]; clamped to {:.2}",
+            confidence, analysis.confidence
+        ));
+    }
+
+    if analysis.recommendation != Recommendation::NoTrade && analysis.confidence < min_confidence {
+        overrides.push(format!(
+            "{} downgraded to NO_TRADE: confidence {:.2} is below min_confidence {:.2}",
+            recommendation_label(&analysis.recommendation),
+            analysis.confidence,
+            min_confidence
+        ));
+        analysis.recommendation = Recommendation::NoTrade;
+    }
+    overrides
+}
+
+/// The outcome an analysis recommends buying, from the model's
+/// `target_outcome`: the name as listed, else the one outcome it matches
+/// ignoring case or as a prefix either way. Without a name, BUY_YES and
+/// BUY_NO pick a side of a two-outcome market.
+///
+/// A name that matches no outcome, or several, downgrades the analysis to
+/// NO_TRADE with the reason as a key factor, and the reason is pushed onto
+/// `overrides`. On a two-outcome market the recommendation is realigned
+/// with the side the name picks, so BUY_YES always means the first one.
+pub fn resolve_target(
+    analysis: &mut AiAnalysis,
+    market: &MarketData,
+    overrides: &mut Vec<String>,
+) -> Option<TargetOutcome> {
+    if analysis.recommendation == Recommendation::NoTrade {
+        return None;
+    }
+    let binary = market
+        .binary_outcomes()
+        .filter(|_| market.outcomes.len() == 2);
+
+    let Some(name) = analysis.target_outcome.as_deref().map(str::trim) else {
+        let (yes, no) = binary?;
+        let outcome = match analysis.recommendation {
+            Recommendation::BuyYes => yes,
+            _ => no,
+        };
+        return Some(TargetOutcome {
+            outcome: outcome.clone(),
+            matched: TargetMatch::Inferred,
+        });
+    };
+
+    let matched = match market.outcomes.iter().find(|o| o.name.trim() == name) {
+        Some(outcome) => Ok((outcome, TargetMatch::Exact)),
+        None => fuzzy_outcome(&market.outcomes, name).map(|o| (o, TargetMatch::Fuzzy)),
+    };
+    let (outcome, matched) = match matched {
+        Ok(found) => found,
+        Err(reason) => {
+            let reason = format!(
+                "{} downgraded to NO_TRADE: {}",
+                recommendation_label(&analysis.recommendation),
+                reason
+            );
+            analysis.recommendation = Recommendation::NoTrade;
+            analysis.key_factors.push(reason.clone());
+            overrides.push(reason);
+            return None;
+        }
+    };
+
+    if let Some((yes, _)) = binary {
+        let side = if outcome.id == yes.id {
+            Recommendation::BuyYes
+        } else {
+            Recommendation::BuyNo
+        };
+        if side != analysis.recommendation {
+            overrides.push(format!(
+                "{} changed to {} to match target outcome '{}'",
+                recommendation_label(&analysis.recommendation),
+                recommendation_label(&side),
+                outcome.name
+            ));
+            analysis.recommendation = side;
+        }
+    }
+    Some(TargetOutcome {
+        outcome: outcome.clone(),
+        matched,
+    })
+}
+
+/// The one outcome named `name` ignoring case, else the one whose name and
+/// `name` start one another.
+fn fuzzy_outcome<'a>(
+    outcomes: &'a [Outcome],
+    name: &str,
+) -> std::result::Result<&'a Outcome, String> {
+    let lowered = name.to_lowercase();
+    if let Some(outcome) = outcomes
+        .iter()
+        .find(|o| o.name.trim().to_lowercase() == lowered)
+    {
+        return Ok(outcome);
+    }
+
+    let mut candidates = outcomes.iter().filter(|o| {
+        let outcome = o.name.trim().to_lowercase();
+        !lowered.is_empty() && (outcome.starts_with(&lowered) || lowered.starts_with(&outcome))
+    });
+    let names = || {
+        outcomes
+            .iter()
+            .map(|o| format!("'{}'", o.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match (candidates.next(), candidates.next()) {
+        (Some(outcome), None) => Ok(outcome),
+        (Some(_), Some(_)) => Err(format!(
+            "target outcome '{}' matches more than one of {}",
+            name,
+            names()
+        )),
+        (None, _) => Err(format!(
+            "target outcome '{}' is not one of {}",
+            name,
+            names()
+        )),
+    }
 }
 
-pub struct Clients {
-    pub dome: DomeClient,
+/// `max_position_usd` times the Kelly fraction for the recommended outcome
+/// at its price, reading the confidence as the probability it resolves
+/// true; rounded down to whole cents.
+pub fn suggested_size(analysis: &AiAnalysis, market: &MarketData, max_position_usd: f64) -> f64 {
+    let Some((yes, no)) = market.binary_outcomes() else {
+        return 0.0;
+    };
+    let outcome = match analysis.recommendation {
+        Recommendation::BuyYes => yes,
+        Recommendation::BuyNo => no,
+        Recommendation::NoTrade => return 0.0,
+    };
+    let stake = max_position_usd * kelly_fraction(analysis.confidence, outcome.price.value());
+    (stake.min(max_position_usd) * 100.0).floor() / 100.0
 }
 
-impl Clients {
-    pub fn new() -> Result<Self> {
-        Ok(Self {
-            dome: DomeClient::new()?,
-        })
+/// Order books of the first [`MAX_PROMPT_BOOK_OUTCOMES`] outcomes, with
+/// how many times each traded in the last hour. Polymarket only, and
+/// best-effort like the chart: if any book fails the prompt goes without
+/// them. Trade counts are optional; without them the books still go in.
+/// A market through the market cache, from Kalshi directly when configured
+/// and from Dome otherwise.
+pub(crate) async fn fetch_market(
+    state: &AppState,
+    market: &MarketRef,
+    fresh: bool,
+) -> Result<CachedMarket> {
+    match (market.platform, state.kalshi_client.as_deref()) {
+        (Platform::Kalshi, Some(kalshi)) => {
+            state
+                .market_cache
+                .kalshi(kalshi, &market.identifier, fresh)
+                .await
+        }
+        _ => {
+            state
+                .market_cache
+                .dome(state.dome()?, market.platform, &market.identifier, fresh)
+                .await
+        }
+    }
+}
+
+async fn fetch_depth(state: &AppState, market: &MarketData) -> Option<MarketDepth> {
+    if !matches!(market.platform, Platform::Polymarket) {
+        tracing::warn!("Order books are only available for Polymarket markets");
+        return None;
+    }
+    let outcomes = &market.outcomes[..market.outcomes.len().min(MAX_PROMPT_BOOK_OUTCOMES)];
+
+    let books = async {
+        let mut books = Vec::with_capacity(outcomes.len());
+        for outcome in outcomes {
+            books.push(state.polymarket_client.get_order_book(&outcome.id).await?);
+        }
+        Ok::<_, crate::AppError>(books)
+    };
+    let trades = async {
+        let condition_id = market.condition_id.as_deref()?;
+        match state
+            .polymarket_client
+            .get_market_trades(condition_id)
+            .await
+        {
+            Ok(trades) => Some(trades),
+            Err(e) => {
+                tracing::warn!("Failed to fetch trades for {}: {}", condition_id, e);
+                None
+            }
+        }
+    };
+    let (books, trades) = tokio::join!(books, trades);
+    let books = match books {
+        Ok(books) => books,
+        Err(e) => {
+            tracing::warn!("Failed to fetch order books for {}: {}", market.id, e);
+            return None;
+        }
+    };
+
+    let since = Utc::now().timestamp() - 3600;
+    let recent: Option<Vec<_>> = trades.map(|trades| {
+        trades
+            .into_iter()
+            .filter(|t| t.timestamp >= since)
+            .collect()
+    });
+    let trades_truncated = recent
+        .as_ref()
+        .is_some_and(|recent| recent.len() >= MARKET_TRADES_LIMIT);
+    Some(MarketDepth {
+        outcomes: outcomes
+            .iter()
+            .zip(books)
+            .map(|(outcome, book)| OutcomeDepth {
+                outcome: outcome.name.clone(),
+                book,
+                trades_1h: recent
+                    .as_ref()
+                    .map(|recent| recent.iter().filter(|t| t.asset == outcome.id).count()),
+            })
+            .collect(),
+        trades_truncated,
+    })
+}
+
+/// Downsampled price history of the primary (first canonical) outcome. Chart
+/// data is best-effort: failures are logged and the field is left out.
+async fn fetch_chart(state: &AppState, market: &MarketData) -> Option<Vec<(i64, f64)>> {
+    if !matches!(market.platform, Platform::Polymarket) {
+        tracing::warn!("Price history is only available for Polymarket markets");
+        return None;
+    }
+    let primary = market.outcomes.first()?;
+
+    match state.polymarket_client.get_price_history(&primary.id).await {
+        Ok(history) => Some(downsample_lttb(&history, MAX_CHART_POINTS)),
+        Err(e) => {
+            tracing::warn!("Failed to fetch price history for {}: {}", primary.id, e);
+            None
+        }
+    }
+}
+
+/// Research for the prompt, bounded by `ANALYSIS_RESEARCH_TIMEOUT_SECS` so a
+/// slow run can't hold up the analysis. Like the chart it is best-effort: on
+/// failure or timeout the analysis falls back to the plain prompt.
+async fn fetch_research(state: &AppState, query: String) -> Option<ResearchEvidence> {
+    let client = match state.polyfactual() {
+        Ok(client) => client,
+        Err(e) => {
+            tracing::warn!("Skipping research: {}", e);
+            return None;
+        }
+    };
+    let research = polyfactual_research::research(state, client, query, false, None);
+    match tokio::time::timeout(state.config.analysis_research_timeout, research).await {
+        Ok(Ok(response)) => Some(ResearchEvidence::new(response.answer, response.citations)),
+        Ok(Err(e)) => {
+            tracing::warn!("Research failed: {}", e);
+            None
+        }
+        Err(_) => {
+            tracing::warn!(
+                "Research timed out after {}s",
+                state.config.analysis_research_timeout.as_secs()
+            );
+            None
+        }
+    }
+}
+
+/// Summary of the last [`PROMPT_HISTORY_HOURS`] of one-minute candles.
+/// Best-effort like the chart: failures are logged and the prompt goes
+/// without it.
+async fn fetch_history(state: &AppState, market: &MarketData) -> Option<CandleSummary> {
+    let Some(condition_id) = market.condition_id.as_deref() else {
+        tracing::warn!("Price history is only available for Polymarket markets");
+        return None;
+    };
+    let dome = state.dome().ok()?;
+    let end = Utc::now();
+    let start = end - chrono::Duration::hours(PROMPT_HISTORY_HOURS);
+
+    match dome
+        .get_candles(
+            market.platform,
+            condition_id,
+            CandleInterval::OneMinute,
+            start,
+            end,
+        )
+        .await
+    {
+        Ok(candles) => CandleSummary::from_candles(&candles),
+        Err(e) => {
+            tracing::warn!("Failed to fetch candles for {}: {}", condition_id, e);
+            None
+        }
+    }
+}
+
+pub(crate) struct AnalysisRun {
+    pub analysis: AiAnalysis,
+    /// Provider that produced the analysis, e.g. `openai`
+    pub provider: &'static str,
+    /// Concrete model name, e.g. `gpt-4o`
+    pub model_used: String,
+    pub retries: u32,
+    /// Tokens billed across every provider call, the fallback included
+    pub usage: AiUsage,
+    /// Whether a provider call ran out of the request's time limit
+    pub timed_out: bool,
+    /// Provider the request asked for; `provider` differs after a fallback
+    pub requested_provider: &'static str,
+    /// What failed when another provider had to answer instead
+    pub warnings: Vec<String>,
+}
+
+impl AnalysisRun {
+    /// The run's time limit, from `options`, and whether it was hit.
+    pub fn timeout_budget(&self, options: &AiRequestOptions) -> TimeoutBudget {
+        TimeoutBudget {
+            limit_secs: options.timeout().as_secs(),
+            timed_out: self.timed_out,
+        }
+    }
+
+    /// The requested and answering providers, for response metadata.
+    pub fn provider_used(&self) -> AiProviderUsed {
+        AiProviderUsed {
+            requested_provider: self.requested_provider.to_string(),
+            provider: self.provider.to_string(),
+        }
+    }
+}
+
+/// Runs the AI analysis for a market, falling back from Grok to OpenAI once
+/// when OpenAI is configured and `allow_fallback` is set; the run's
+/// `warnings` then say what failed. A validated `custom_prompt` replaces the
+/// built-in template. The fallback keeps the sampling options but not
+/// `model_name`, which names a Grok model.
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn run_analysis(
+    state: &AppState,
+    market_data: &MarketData,
+    question: Option<&String>,
+    custom_prompt: Option<&str>,
+    evidence: &PromptEvidence,
+    provider: AiProvider,
+    options: &AiRequestOptions,
+    allow_fallback: bool,
+) -> Result<AnalysisRun> {
+    let build_prompt = || {
+        select_prompt(
+            market_data,
+            question,
+            custom_prompt,
+            evidence,
+            &state.config.ai_few_shot,
+        )
+    };
+
+    // Build AI prompt
+    let prompt = build_prompt();
+    tracing::debug!("Analysis prompt: {:?}", prompt);
+    // Call AI with retry logic (handled in client)
+    tracing::debug!("Analysis provider: {:?}", provider);
+    let ai_client = state.ai_client(provider.clone(), options)?;
+
+    tracing::debug!("Analyzing with {}", ai_client.provider_name());
+    let outcome = ai_client.analyze_markets(prompt).await;
+    log_retries(ai_client.as_ref());
+    match outcome {
+        Ok(result) => Ok(AnalysisRun {
+            usage: priced_usage(state, &result.model, result.usage),
+            analysis: result.analysis,
+            provider: ai_client.provider_name(),
+            model_used: result.model,
+            retries: ai_client.retries(),
+            timed_out: ai_client.timed_out(),
+            requested_provider: provider.name(),
+            warnings: Vec::new(),
+        }),
+        Err(e) => {
+            // Retry once with different provider if Grok fails
+            if matches!(provider, AiProvider::Grok) && state.capabilities.openai && allow_fallback {
+                let failure = describe_failure(ai_client.as_ref(), &e);
+                tracing::warn!("{}; retrying with OpenAI", failure);
+                let fallback_options = AiRequestOptions {
+                    model_name: None,
+                    ..options.clone()
+                };
+                // Grok's failed attempts were billed all the same
+                let spent = priced_usage(state, ai_client.model_name(), ai_client.usage());
+                let openai_client = state.ai_client(AiProvider::OpenAi, &fallback_options)?;
+                let outcome = openai_client.analyze_markets(build_prompt()).await;
+                log_retries(openai_client.as_ref());
+                let result = outcome?;
+                let warning = format!(
+                    "{}; analyzed with {} ({}) instead",
+                    failure,
+                    openai_client.provider_name(),
+                    result.model
+                );
+                // The switch to OpenAI counts as one more retry
+                Ok(AnalysisRun {
+                    usage: spent.combine(priced_usage(state, &result.model, result.usage)),
+                    analysis: result.analysis,
+                    provider: openai_client.provider_name(),
+                    model_used: result.model,
+                    retries: ai_client.retries() + 1 + openai_client.retries(),
+                    timed_out: ai_client.timed_out() || openai_client.timed_out(),
+                    requested_provider: provider.name(),
+                    warnings: vec![warning],
+                })
+            } else {
+                Err(e)
+            }
+        }
+    }
+}
+
+/// What went wrong with a provider, for a fallback warning, e.g.
+/// `grok: 429 rate limited after 3 attempts`.
+fn describe_failure(client: &dyn AiClient, error: &AppError) -> String {
+    let attempts = client.attempts();
+    let reason = match (error, attempts.last().and_then(|attempt| attempt.status)) {
+        (AppError::RateLimit { .. }, _) => "429 rate limited".to_string(),
+        (AppError::Timeout(_), _) => "timed out".to_string(),
+        (_, Some(status)) => format!("failed with status {}", status),
+        (_, None) => error.to_string(),
+    };
+    let provider = client.provider_name();
+    match attempts.len() {
+        0 => format!("{}: {}", provider, reason),
+        1 => format!("{}: {} after 1 attempt", provider, reason),
+        n => format!("{}: {} after {} attempts", provider, reason, n),
+    }
+}
+
+/// Runs Grok and OpenAI concurrently on the same prompt. If one fails, the
+/// other's analysis is returned alone; if both fail, Grok's error is.
+pub(crate) async fn run_comparison(
+    state: &AppState,
+    market_data: &MarketData,
+    question: Option<&String>,
+    custom_prompt: Option<&str>,
+    evidence: &PromptEvidence,
+    options: &AiRequestOptions,
+) -> Result<(AnalysisRun, AnalysisComparison)> {
+    let prompt = select_prompt(
+        market_data,
+        question,
+        custom_prompt,
+        evidence,
+        &state.config.ai_few_shot,
+    );
+
+    let (grok_run, openai_run) = tokio::join!(
+        analyze_with(state, AiProvider::Grok, prompt.clone(), options),
+        analyze_with(state, AiProvider::OpenAi, prompt, options),
+    );
+
+    let (grok, openai) = match (grok_run.analysis, openai_run.analysis) {
+        (Ok(grok), Ok(openai)) => (Some(grok), Some(openai)),
+        (Ok(grok), Err(e)) => {
+            tracing::warn!("OpenAI failed during comparison: {}", e);
+            (Some(grok), None)
+        }
+        (Err(e), Ok(openai)) => {
+            tracing::warn!("Grok failed during comparison: {}", e);
+            (None, Some(openai))
+        }
+        (Err(grok_error), Err(openai_error)) => {
+            tracing::warn!("OpenAI failed during comparison: {}", openai_error);
+            return Err(grok_error);
+        }
+    };
+
+    let (consensus, analysis) = match (&grok, &openai) {
+        (Some(grok), Some(openai)) => derive_consensus(&grok.analysis, &openai.analysis),
+        (Some(single), None) | (None, Some(single)) => (
+            Consensus {
+                agreement: ConsensusAgreement::SingleProvider,
+                recommendation: single.analysis.recommendation.clone(),
+                confidence: None,
+            },
+            single.analysis.clone(),
+        ),
+        (None, None) => unreachable!("both providers failing returns early"),
+    };
+
+    let model_used = grok
+        .iter()
+        .chain(openai.iter())
+        .map(|p| p.model.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    let run = AnalysisRun {
+        analysis,
+        provider: "grok+openai",
+        model_used,
+        retries: grok_run.retries + openai_run.retries,
+        usage: grok_run.usage.combine(openai_run.usage),
+        timed_out: grok_run.timed_out || openai_run.timed_out,
+        requested_provider: "grok+openai",
+        warnings: Vec::new(),
+    };
+    Ok((
+        run,
+        AnalysisComparison {
+            grok,
+            openai,
+            consensus,
+        },
+    ))
+}
+
+/// Research, history and order books are only offered with the built-in
+/// template; the handler rejects them alongside a custom prompt.
+fn select_prompt(
+    market_data: &MarketData,
+    question: Option<&String>,
+    custom_prompt: Option<&str>,
+    evidence: &PromptEvidence,
+    few_shot: &FewShot,
+) -> Vec<PromptMessage> {
+    match custom_prompt {
+        Some(custom_prompt) => build_custom_prompt(custom_prompt, market_data),
+        None if evidence.is_empty() => build_analysis_prompt(market_data, question, few_shot),
+        None => build_analysis_prompt_with_evidence(market_data, question, evidence, few_shot),
+    }
+}
+
+/// One provider's analysis, and the tokens it billed and whether a call
+/// ran out of time, whether or not it succeeded.
+/// One provider's side of a comparison, with what it cost whether or not
+/// it succeeded.
+struct ProviderRun {
+    analysis: Result<ProviderAnalysis>,
+    usage: AiUsage,
+    timed_out: bool,
+    retries: u32,
+}
+
+async fn analyze_with(
+    state: &AppState,
+    provider: AiProvider,
+    prompt: Vec<PromptMessage>,
+    options: &AiRequestOptions,
+) -> ProviderRun {
+    let client = match state.ai_client(provider, options) {
+        Ok(client) => client,
+        Err(e) => {
+            return ProviderRun {
+                analysis: Err(e),
+                usage: AiUsage::default(),
+                timed_out: false,
+                retries: 0,
+            }
+        }
+    };
+    let analysis = client
+        .analyze_markets(prompt)
+        .await
+        .map(|result| ProviderAnalysis {
+            model: result.model,
+            analysis: result.analysis,
+        });
+    log_retries(client.as_ref());
+    ProviderRun {
+        analysis,
+        usage: priced_usage(state, client.model_name(), client.usage()),
+        timed_out: client.timed_out(),
+        retries: client.retries(),
+    }
+}
+
+/// Logs a client's attempt history when any call had to be retried.
+fn log_retries(client: &dyn AiClient) {
+    if client.retries() > 0 {
+        tracing::info!(
+            "{} took {} retries: {:?}",
+            client.provider_name(),
+            client.retries(),
+            client.attempts()
+        );
+    }
+}
+
+/// `tokens` billed on `model`, priced with `AI_MODEL_PRICES`.
+pub(crate) fn priced_usage(state: &AppState, model: &str, tokens: TokenUsage) -> AiUsage {
+    if tokens == TokenUsage::default() {
+        return AiUsage::default();
+    }
+    AiUsage {
+        prompt_tokens: tokens.prompt_tokens,
+        completion_tokens: tokens.completion_tokens,
+        estimated_cost_usd: state.config.ai_model_prices.cost(model, tokens),
+    }
+}
+
+/// Combines two analyses. Agreeing providers keep their recommendation at
+/// their mean confidence; conflicting ones become `NoTrade` at zero
+/// confidence, since neither call is corroborated.
+pub fn derive_consensus(grok: &AiAnalysis, openai: &AiAnalysis) -> (Consensus, AiAnalysis) {
+    let mut key_factors = grok.key_factors.clone();
+    for factor in &openai.key_factors {
+        if !key_factors.contains(factor) {
+            key_factors.push(factor.clone());
+        }
+    }
+
+    if grok.recommendation == openai.recommendation {
+        let confidence = (grok.confidence + openai.confidence) / 2.0;
+        let consensus = Consensus {
+            agreement: ConsensusAgreement::Agree,
+            recommendation: grok.recommendation.clone(),
+            confidence: Some(confidence),
+        };
+        let analysis = AiAnalysis {
+            recommendation: grok.recommendation.clone(),
+            confidence,
+            reasoning: format!("Grok: {}\n\nOpenAI: {}", grok.reasoning, openai.reasoning),
+            key_factors,
+            target_outcome: grok.target_outcome.clone(),
+        };
+        (consensus, analysis)
+    } else {
+        let consensus = Consensus {
+            agreement: ConsensusAgreement::Disagree,
+            recommendation: Recommendation::NoTrade,
+            confidence: None,
+        };
+        let analysis = AiAnalysis {
+            recommendation: Recommendation::NoTrade,
+            confidence: 0.0,
+            reasoning: format!(
+                "Providers disagree: Grok recommends {} ({:.0}% confidence), OpenAI recommends {} ({:.0}% confidence).",
+                recommendation_label(&grok.recommendation),
+                grok.confidence * 100.0,
+                recommendation_label(&openai.recommendation),
+                openai.confidence * 100.0
+            ),
+            key_factors,
+            target_outcome: None,
+        };
+        (consensus, analysis)
+    }
+}
+
+fn recommendation_label(recommendation: &Recommendation) -> &'static str {
+    match recommendation {
+        Recommendation::BuyYes => "BUY_YES",
+        Recommendation::BuyNo => "BUY_NO",
+        Recommendation::NoTrade => "NO_TRADE",
+    }
+}
+
+/// Maps the request's `model` field onto a provider, defaulting to Grok.
+pub(crate) fn resolve_provider(model: Option<&str>) -> AiProvider {
+    match model {
+        Some("openai") => AiProvider::OpenAi,
+        Some("claude") => AiProvider::Claude,
+        _ => AiProvider::Grok, // Default to Grok
     }
 }
@@ -0,0 +1,235 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{
+    ArbLeg, ArbOpportunity, ArbPairWarning, ArbScanRequest, ArbScanResponse, MarketData, MarketRef,
+    Outcome, Platform, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+const MAX_PAIRS: usize = 25;
+const DEFAULT_POLYMARKET_FEE_BPS: u32 = 0;
+/// Kalshi charges up to 7% of `price × (1 - price)` per contract
+const DEFAULT_KALSHI_FEE_BPS: u32 = 700;
+
+/// Fee rates assumed per platform, in basis points.
+#[derive(Debug, Clone, Copy)]
+pub struct FeeAssumptions {
+    pub polymarket_bps: u32,
+    pub kalshi_bps: u32,
+}
+
+impl FeeAssumptions {
+    /// Fees follow the CLOB's `rate × min(price, 1 - price)` per share on
+    /// both platforms, which bounds Kalshi's `price × (1 - price)` curve.
+    pub fn per_share(&self, platform: Platform, price: f64) -> f64 {
+        let bps = match platform {
+            Platform::Polymarket => self.polymarket_bps,
+            Platform::Kalshi => self.kalshi_bps,
+        };
+        f64::from(bps) / 10_000.0 * price.min(1.0 - price)
+    }
+}
+
+/// Prices pairs of markets on the same question against each other.
+/// Markets come from Dome, which normalizes both platforms; a pair that
+/// can't be looked up or aligned becomes a warning and the rest still run.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<ArbScanRequest>,
+) -> Result<Json<ArbScanResponse>> {
+    let start = Instant::now();
+
+    let fees = FeeAssumptions {
+        polymarket_bps: request
+            .polymarket_fee_bps
+            .unwrap_or(DEFAULT_POLYMARKET_FEE_BPS),
+        kalshi_bps: request.kalshi_fee_bps.unwrap_or(DEFAULT_KALSHI_FEE_BPS),
+    };
+    let min_edge_bps = request.min_edge_bps.unwrap_or(0.0);
+    let pairs = request.into_pairs();
+
+    // Validate request
+    if pairs.len() > MAX_PAIRS {
+        return Err(AppError::Validation(format!(
+            "Scan contains {} pairs; the maximum is {}",
+            pairs.len(),
+            MAX_PAIRS
+        )));
+    }
+
+    // Look up both markets of every pair in one bounded batch
+    let dome = state.dome()?;
+    let refs: Vec<Result<(MarketRef, MarketRef)>> = pairs
+        .iter()
+        .map(|pair| {
+            Ok((
+                dome.market_ref_from_url(&pair.first_url)?,
+                dome.market_ref_from_url(&pair.second_url)?,
+            ))
+        })
+        .collect();
+    let lookups: Vec<MarketRef> = refs
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .flat_map(|(first, second)| [first.clone(), second.clone()])
+        .collect();
+    let mut fetched = dome.get_markets(&lookups).await.into_iter();
+
+    let mut opportunities = Vec::new();
+    let mut warnings = Vec::new();
+    for (pair_index, (pair, pair_refs)) in pairs.iter().zip(refs).enumerate() {
+        let markets = pair_refs.and_then(|_| {
+            let mut next = || {
+                fetched.next().unwrap_or_else(|| {
+                    Err(AppError::Internal(anyhow::anyhow!("Missing market lookup")))
+                })
+            };
+            let (first, second) = (next(), next());
+            Ok((first?, second?))
+        });
+        let priced = markets
+            .map_err(|e| e.to_string())
+            .and_then(|(first, second)| {
+                price_pair(
+                    pair_index,
+                    &first,
+                    &second,
+                    pair.outcome_map.as_ref(),
+                    fees,
+                    min_edge_bps,
+                )
+            });
+        match priced {
+            Ok(found) => opportunities.extend(found),
+            Err(message) => warnings.push(ArbPairWarning {
+                pair_index,
+                message,
+            }),
+        }
+    }
+    opportunities.sort_by(|a, b| b.edge.total_cmp(&a.edge));
+
+    Ok(Json(ArbScanResponse {
+        flagged: opportunities.iter().filter(|o| o.flagged).count(),
+        opportunities,
+        warnings,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+/// Both ways of buying opposite sides of two binary markets: each outcome
+/// of `first`, with the outcome of `second` that pays when it doesn't.
+///
+/// Outcomes align by name, ignoring case, after renaming `first`'s outcomes
+/// through `outcome_map`. Fails with the reason when the markets aren't two
+/// open binary markets whose outcomes line up.
+pub fn price_pair(
+    pair_index: usize,
+    first: &MarketData,
+    second: &MarketData,
+    outcome_map: Option<&HashMap<String, String>>,
+    fees: FeeAssumptions,
+    min_edge_bps: f64,
+) -> std::result::Result<Vec<ArbOpportunity>, String> {
+    if first.outcomes.len() != 2 || second.outcomes.len() != 2 {
+        return Err(format!(
+            "Outcome counts don't line up ({} vs {}); only binary markets can be paired",
+            first.outcomes.len(),
+            second.outcomes.len()
+        ));
+    }
+    if let Some(closed) = [first, second].into_iter().find(|m| m.closed) {
+        return Err(format!("Market {} is closed", closed.id));
+    }
+
+    // Index into `second` of each of `first`'s outcomes
+    let mut aligned = [0; 2];
+    for (slot, outcome) in aligned.iter_mut().zip(&first.outcomes) {
+        let name = outcome_map
+            .and_then(|map| map.get(&outcome.name))
+            .unwrap_or(&outcome.name);
+        *slot = second
+            .outcomes
+            .iter()
+            .position(|o| o.name.trim().eq_ignore_ascii_case(name.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "Outcome '{}' has no match among {}; map it with outcome_map",
+                    name,
+                    outcome_names(&second.outcomes)
+                )
+            })?;
+    }
+    if aligned[0] == aligned[1] {
+        return Err(format!(
+            "Both outcomes map to '{}'",
+            second.outcomes[aligned[0]].name
+        ));
+    }
+
+    let max_size_usd = first.liquidity.zip(second.liquidity).map(|(a, b)| a.min(b));
+    let leg = |market: &MarketData, outcome: &Outcome| ArbLeg {
+        market_id: market.id.clone(),
+        platform: market.platform,
+        question: market.question.clone(),
+        outcome: outcome.name.clone(),
+        price: outcome.price,
+        fee: fees.per_share(market.platform, outcome.price.value()),
+    };
+
+    Ok(first
+        .outcomes
+        .iter()
+        .zip(aligned)
+        .map(|(outcome, matched)| {
+            let first_leg = leg(first, outcome);
+            let second_leg = leg(second, &second.outcomes[1 - matched]);
+            let cost_per_set =
+                first_leg.price.value() + second_leg.price.value() + first_leg.fee + second_leg.fee;
+            let edge = 1.0 - cost_per_set;
+            let edge_bps = edge * 10_000.0;
+            ArbOpportunity {
+                pair_index,
+                first: first_leg,
+                second: second_leg,
+                cost_per_set,
+                edge,
+                edge_bps,
+                max_size_usd,
+                max_sets: max_size_usd
+                    .filter(|_| cost_per_set > 0.0)
+                    .map(|size| size / cost_per_set),
+                flagged: edge > 0.0 && edge_bps >= min_edge_bps,
+            }
+        })
+        .collect())
+}
+
+fn outcome_names(outcomes: &[Outcome]) -> String {
+    outcomes
+        .iter()
+        .map(|o| format!("'{}'", o.name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
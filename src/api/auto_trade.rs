@@ -0,0 +1,370 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::{DateTime, Utc};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::api::admin::require_admin;
+use crate::api::limit_order_bot::{run_bot, BotMarket};
+use crate::api::AppState;
+use crate::clients::PolymarketClient;
+use crate::request_id;
+use crate::types::{
+    AutoTradeRun, AutoTradeRunStatus, AutoTradeSettings, AutoTradeStatusResponse,
+    LimitOrderBotRequest, LimitOrderBotResponse, MarketInterval, OrderMode, ResponseMetadata,
+    Secret,
+};
+use crate::{AppError, Result};
+
+pub const DEFAULT_START_DELAY_SECS: u64 = 5;
+pub const DEFAULT_GRACE_SECS: u64 = 120;
+/// Keeps every retry inside the window it started in, so they all target
+/// the same market.
+pub const MAX_GRACE_SECS: u64 = 600;
+/// A day of 15-minute windows.
+const MAX_RUN_HISTORY: usize = 96;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(2);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(15);
+
+/// What the scheduler trades each window, from the `AUTO_TRADE_*` settings
+/// in [`crate::config::Config`].
+#[derive(Clone)]
+pub struct AutoTradeConfig {
+    pub asset: &'static str,
+    pub mode: OrderMode,
+    pub bankroll_usd: f64,
+    pub wallet_private_key: String,
+    pub dry_run: bool,
+    /// Wait after a window opens before trading it
+    pub start_delay: Duration,
+    /// How long a market that isn't listed yet is retried
+    pub grace_period: Duration,
+}
+
+impl fmt::Debug for AutoTradeConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AutoTradeConfig")
+            .field("asset", &self.asset)
+            .field("mode", &self.mode)
+            .field("bankroll_usd", &self.bankroll_usd)
+            .field("dry_run", &self.dry_run)
+            .field("start_delay", &self.start_delay)
+            .field("grace_period", &self.grace_period)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AutoTradeConfig {
+    fn settings(&self) -> AutoTradeSettings {
+        AutoTradeSettings {
+            asset: self.asset.to_string(),
+            mode: self.mode,
+            bankroll_usd: self.bankroll_usd,
+            dry_run: self.dry_run,
+            start_delay_secs: self.start_delay.as_secs(),
+            grace_period_secs: self.grace_period.as_secs(),
+        }
+    }
+
+    fn request(&self, market_slug: String) -> LimitOrderBotRequest {
+        LimitOrderBotRequest {
+            wallet_private_key: Some(Secret::new(self.wallet_private_key.clone())),
+            clob_api_key: None,
+            clob_secret: None,
+            clob_passphrase: None,
+            wallet_address: None,
+            market_slug: Some(market_slug),
+            asset: Some(self.asset.to_string()),
+            interval: None,
+            mode: self.mode,
+            bankroll_usd: self.bankroll_usd,
+            price_levels: None,
+            verify_placement: None,
+            verify_delay_ms: None,
+            ai_summary: None,
+            pricing: None,
+            improvement_ticks: None,
+            max_spread_cents: None,
+            strict_spread: None,
+            max_combined_price: None,
+            max_spread: None,
+            dry_run: Some(self.dry_run),
+            outcomes: None,
+            ladder_min_price: None,
+            ladder_max_price: None,
+            ladder_spacing: None,
+            ladder_profile: None,
+            weights: None,
+            exit_target_pct: None,
+            use_orderbook_price: None,
+            idempotency_key: None,
+            override_caps: None,
+            webhook_url: None,
+            expiration: None,
+            expires_at: None,
+            structured_logs: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct TraderState {
+    active: bool,
+    running: bool,
+    next_run_at: Option<DateTime<Utc>>,
+    last_result: Option<LimitOrderBotResponse>,
+    /// Newest first
+    history: VecDeque<AutoTradeRun>,
+}
+
+/// Scheduler state shared with the auto-trade endpoints.
+#[derive(Debug, Default)]
+pub struct AutoTrader {
+    config: Option<AutoTradeConfig>,
+    state: Mutex<TraderState>,
+}
+
+impl AutoTrader {
+    /// Starts active when configured.
+    pub fn new(config: Option<AutoTradeConfig>) -> Self {
+        Self {
+            state: Mutex::new(TraderState {
+                active: config.is_some(),
+                ..TraderState::default()
+            }),
+            config,
+        }
+    }
+
+    /// Pauses or resumes the scheduler. A run already in progress finishes.
+    pub fn set_active(&self, active: bool) -> Result<()> {
+        if self.config.is_none() {
+            return Err(AppError::Validation(
+                "Auto-trading is not configured; set AUTO_TRADE_ENABLED=true and restart"
+                    .to_string(),
+            ));
+        }
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).active = active;
+        Ok(())
+    }
+
+    fn schedule(&self, at: DateTime<Utc>) {
+        self.state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .next_run_at = Some(at);
+    }
+
+    /// Claims the next run; false while paused or if a run is in progress.
+    fn begin_run(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.active || state.running {
+            return false;
+        }
+        state.running = true;
+        true
+    }
+
+    fn finish_run(&self, run: AutoTradeRun, result: Option<LimitOrderBotResponse>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.running = false;
+        if result.is_some() {
+            state.last_result = result;
+        }
+        state.history.push_front(run);
+        state.history.truncate(MAX_RUN_HISTORY);
+    }
+
+    fn status(&self, metadata: ResponseMetadata) -> AutoTradeStatusResponse {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        AutoTradeStatusResponse {
+            configured: self.config.is_some(),
+            active: state.active,
+            running: state.running,
+            next_run_at: state
+                .next_run_at
+                .filter(|_| state.active)
+                .map(|at| at.to_rfc3339()),
+            settings: self.config.as_ref().map(AutoTradeConfig::settings),
+            last_result: state.last_result.clone(),
+            history: state.history.iter().cloned().collect(),
+            metadata,
+        }
+    }
+}
+
+pub async fn start(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<AutoTradeStatusResponse>> {
+    let start = Instant::now();
+    require_admin(&state, &headers)?;
+    state.auto_trader.set_active(true)?;
+    tracing::info!("Auto-trading resumed");
+    Ok(Json(state.auto_trader.status(metadata(start))))
+}
+
+pub async fn stop(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<Json<AutoTradeStatusResponse>> {
+    let start = Instant::now();
+    require_admin(&state, &headers)?;
+    state.auto_trader.set_active(false)?;
+    tracing::info!("Auto-trading paused");
+    Ok(Json(state.auto_trader.status(metadata(start))))
+}
+
+pub async fn status(State(state): State<Arc<AppState>>) -> Json<AutoTradeStatusResponse> {
+    let start = Instant::now();
+    Json(state.auto_trader.status(metadata(start)))
+}
+
+fn metadata(start: Instant) -> ResponseMetadata {
+    ResponseMetadata {
+        timestamp: Utc::now().to_rfc3339(),
+        execution_time_ms: start.elapsed().as_millis() as u64,
+        model_used: None,
+        retries: 0,
+        degraded_features: Vec::new(),
+        custom_prompt: false,
+        dry_run: false,
+        cache_hit: None,
+        request_id: request_id::current(),
+        ai_usage: None,
+        ai_provider: None,
+        query_compression: None,
+        timeout_budget: None,
+    }
+}
+
+/// Trades each new 15-minute window shortly after its predecessor opens.
+/// Runs one at a time: a run that overruns the next boundary skips that
+/// window. Stops on shutdown; a run in progress is tracked so the drain
+/// waits for it. A no-op unless auto-trading is configured.
+pub fn spawn_scheduler(state: Arc<AppState>) {
+    let Some(config) = state.auto_trader.config.clone() else {
+        return;
+    };
+    tracing::info!("Auto-trading enabled: {:?}", config);
+
+    tokio::spawn(async move {
+        loop {
+            let wake_at = PolymarketClient::calculate_next_15min_market_timestamp()
+                + chrono::Duration::from_std(config.start_delay).unwrap_or_default();
+            state.auto_trader.schedule(wake_at);
+            tokio::select! {
+                _ = tokio::time::sleep((wake_at - Utc::now()).to_std().unwrap_or_default()) => {}
+                _ = state.shutdown.cancelled() => {
+                    tracing::info!("Auto-trade scheduler stopped");
+                    return;
+                }
+            }
+
+            if !state.auto_trader.begin_run() {
+                continue;
+            }
+            let _in_flight = state
+                .in_flight
+                .track(format!("auto-trade run for {}", wake_at.to_rfc3339()));
+            let (run, result) = run_window(&state, &config).await;
+            match &run.error {
+                Some(error) => {
+                    tracing::warn!("Auto-trade run for {} failed: {}", run.window_start, error)
+                }
+                None => tracing::info!(
+                    "Auto-trade run for {}: {} placed, {} failed",
+                    run.window_start,
+                    run.orders_placed,
+                    run.orders_failed
+                ),
+            }
+            state.auto_trader.finish_run(run, result);
+        }
+    });
+}
+
+/// Waits for the upcoming window's market to be listed, then runs the bot
+/// against it once.
+async fn run_window(
+    state: &Arc<AppState>,
+    config: &AutoTradeConfig,
+) -> (AutoTradeRun, Option<LimitOrderBotResponse>) {
+    let started_at = Utc::now();
+    let window_start = PolymarketClient::calculate_next_15min_market_timestamp();
+    let slug = PolymarketClient::build_updown_slug(
+        config.asset,
+        MarketInterval::FifteenMinutes,
+        window_start,
+    );
+    let deadline = Instant::now() + config.grace_period;
+
+    let mut attempts = 0;
+    let mut delay = RETRY_BASE_DELAY;
+    let market = loop {
+        attempts += 1;
+        match state
+            .polymarket_client
+            .resolve_updown_market(&slug, window_start)
+            .await
+        {
+            Err(e) if e.is_not_found() && Instant::now() + delay < deadline => {
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(RETRY_MAX_DELAY);
+            }
+            other => break other,
+        }
+    };
+
+    let mut run = AutoTradeRun {
+        window_start: window_start.to_rfc3339(),
+        started_at: started_at.to_rfc3339(),
+        finished_at: String::new(),
+        market_slug: None,
+        attempts,
+        status: AutoTradeRunStatus::Failed,
+        orders_placed: 0,
+        orders_failed: 0,
+        summary: None,
+        error: None,
+    };
+
+    // Runs against the market found, so a late listing can't shift the target
+    let result = match market {
+        Ok(market) => {
+            let market_slug = market.slug.unwrap_or(slug);
+            run.market_slug = Some(market_slug.clone());
+            match run_bot(
+                state,
+                &config.request(market_slug),
+                None,
+                BotMarket::Fetch { fresh: false },
+            )
+            .await
+            {
+                Ok(response) => {
+                    run.status = AutoTradeRunStatus::Completed;
+                    run.orders_placed = response.orders_placed;
+                    run.orders_failed = response.orders_failed;
+                    run.summary = Some(response.summary.clone());
+                    Some(response)
+                }
+                Err(e) => {
+                    run.error = Some(e.to_string());
+                    None
+                }
+            }
+        }
+        Err(e) => {
+            if e.is_not_found() {
+                run.status = AutoTradeRunStatus::MarketNotFound;
+            }
+            run.error = Some(e.to_string());
+            None
+        }
+    };
+
+    run.finished_at = Utc::now().to_rfc3339();
+    (run, result)
+}
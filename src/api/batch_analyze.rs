@@ -0,0 +1,358 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tokio_stream::wrappers::ReceiverStream;
+
+use crate::api::analyze_event_markets::{resolve_provider, run_analysis};
+use crate::api::capabilities::Capability;
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::clients::ai::parse_combined_analyses;
+use crate::clients::ai::prompts::{build_combined_analysis_prompt, PromptEvidence};
+use crate::clients::{AiProvider, AiRequestOptions};
+use crate::request_id;
+use crate::types::{
+    BatchAnalyzeItem, BatchAnalyzeRequest, BatchAnalyzeResponse, BatchAnalyzeSummary,
+    BatchStreamEvent, MarketData, MarketRef, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+const MAX_BATCH_SIZE: usize = 25;
+const BATCH_CONCURRENCY: usize = 5;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BatchQuery {
+    pub stream: Option<bool>,
+}
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<BatchQuery>,
+    AppJson(request): AppJson<BatchAnalyzeRequest>,
+) -> Result<Response> {
+    let start = Instant::now();
+
+    // Validate request
+    if request.urls.len() > MAX_BATCH_SIZE {
+        return Err(AppError::Validation(format!(
+            "Batch contains {} URLs; the maximum is {}",
+            request.urls.len(),
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    state
+        .capabilities
+        .require(Capability::ai(&resolve_provider(request.model.as_deref())))?;
+
+    // Fetch every market up front in one bounded batch; a bad URL or failed
+    // lookup only fails its own item
+    let dome = state.dome()?;
+    let refs: Vec<Result<MarketRef>> = request
+        .urls
+        .iter()
+        .map(|url| dome.market_ref_from_url(url))
+        .collect();
+    let lookups: Vec<MarketRef> = refs
+        .iter()
+        .filter_map(|r| r.as_ref().ok())
+        .cloned()
+        .collect();
+    let mut fetched = dome.get_markets(&lookups).await.into_iter();
+    let markets: Vec<Result<MarketData>> = refs
+        .into_iter()
+        .map(|r| {
+            r.and_then(|_| {
+                fetched.next().unwrap_or_else(|| {
+                    Err(AppError::Internal(anyhow::anyhow!("Missing market lookup")))
+                })
+            })
+        })
+        .collect();
+
+    let total = request.urls.len();
+    let items = spawn_batch(state, request, markets);
+
+    if query.stream.unwrap_or(false) {
+        return Ok(stream_response(items, total, start));
+    }
+
+    let mut results = collect(items).await;
+    results.sort_by_key(|item| item.index);
+    let summary = summarize(&results, start);
+
+    Ok(Json(BatchAnalyzeResponse {
+        results,
+        summary,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    })
+    .into_response())
+}
+
+/// Runs every analysis concurrently (bounded), or all markets in one
+/// combined prompt, and yields items in completion order. Dropping the
+/// receiver — e.g. when a streaming client disconnects — aborts any analyses
+/// still in flight.
+fn spawn_batch(
+    state: Arc<AppState>,
+    request: BatchAnalyzeRequest,
+    markets: Vec<Result<MarketData>>,
+) -> mpsc::Receiver<BatchAnalyzeItem> {
+    // Capacity covers every item so finished workers never wait on a slow reader
+    let (tx, rx) = mpsc::channel(request.urls.len());
+    let provider = resolve_provider(request.model.as_deref());
+    let combined = request.combined.unwrap_or(false);
+
+    tokio::spawn(async move {
+        let semaphore = Arc::new(Semaphore::new(BATCH_CONCURRENCY));
+        let mut workers = JoinSet::new();
+        let mut combined_markets = Vec::new();
+
+        for (index, (url, market)) in request.urls.into_iter().zip(markets).enumerate() {
+            let market_data = match market {
+                Ok(market_data) => market_data,
+                Err(e) => {
+                    let item = failed_item(index, url, e);
+                    workers.spawn(async move { vec![item] });
+                    continue;
+                }
+            };
+            if combined {
+                combined_markets.push((index, url, market_data));
+                continue;
+            }
+
+            let semaphore = semaphore.clone();
+            let state = state.clone();
+            let question = request.question.clone();
+            let provider = provider.clone();
+            workers.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                vec![analyze_one(&state, index, url, market_data, question, provider).await]
+            });
+        }
+        if !combined_markets.is_empty() {
+            let question = request.question.clone();
+            let provider = provider.clone();
+            workers.spawn(analyze_combined(
+                state.clone(),
+                combined_markets,
+                question,
+                provider,
+            ));
+        }
+
+        loop {
+            tokio::select! {
+                _ = tx.closed() => {
+                    tracing::info!("Batch consumer went away; cancelling {} analyses", workers.len());
+                    workers.abort_all();
+                    break;
+                }
+                joined = workers.join_next() => match joined {
+                    Some(Ok(items)) => {
+                        for item in items {
+                            if tx.send(item).await.is_err() {
+                                workers.abort_all();
+                                return;
+                            }
+                        }
+                    }
+                    Some(Err(e)) => tracing::error!("Batch analysis task failed: {}", e),
+                    None => break,
+                },
+            }
+        }
+    });
+
+    rx
+}
+
+async fn analyze_one(
+    state: &AppState,
+    index: usize,
+    url: String,
+    market_data: MarketData,
+    question: Option<String>,
+    provider: AiProvider,
+) -> BatchAnalyzeItem {
+    let result = run_analysis(
+        state,
+        &market_data,
+        question.as_ref(),
+        None,
+        &PromptEvidence::default(),
+        provider,
+        &AiRequestOptions::default(),
+        true,
+    )
+    .await;
+
+    match result {
+        Ok(run) => BatchAnalyzeItem {
+            index,
+            url,
+            analysis: Some(run.analysis),
+            market_data: Some(market_data),
+            model_used: Some(run.model_used),
+            error: None,
+        },
+        Err(e) => failed_item(index, url, e),
+    }
+}
+
+/// Analyzes all markets in one prompt. A failed call or unparseable reply
+/// fails every market in it.
+async fn analyze_combined(
+    state: Arc<AppState>,
+    markets: Vec<(usize, String, MarketData)>,
+    question: Option<String>,
+    provider: AiProvider,
+) -> Vec<BatchAnalyzeItem> {
+    let market_refs: Vec<&MarketData> = markets.iter().map(|(_, _, m)| m).collect();
+    let prompt = build_combined_analysis_prompt(&market_refs, question.as_ref());
+
+    let result = async {
+        let client = state.ai_client(provider, &AiRequestOptions::default())?;
+        let content = client.complete(prompt).await?;
+        let analyses =
+            parse_combined_analyses(&content, markets.len()).map_err(AppError::ExternalApi)?;
+        Ok::<_, AppError>((client.model_name().to_string(), analyses))
+    }
+    .await;
+
+    match result {
+        Ok((model, analyses)) => markets
+            .into_iter()
+            .zip(analyses)
+            .map(|((index, url, market_data), analysis)| BatchAnalyzeItem {
+                index,
+                url,
+                analysis: Some(analysis),
+                market_data: Some(market_data),
+                model_used: Some(model.clone()),
+                error: None,
+            })
+            .collect(),
+        Err(e) => {
+            let error = e.to_string();
+            markets
+                .into_iter()
+                .map(|(index, url, _)| {
+                    failed_item(index, url, AppError::ExternalApi(error.clone()))
+                })
+                .collect()
+        }
+    }
+}
+
+fn failed_item(index: usize, url: String, error: AppError) -> BatchAnalyzeItem {
+    tracing::warn!("Batch analysis failed for {}: {}", url, error);
+    BatchAnalyzeItem {
+        index,
+        url,
+        analysis: None,
+        market_data: None,
+        model_used: None,
+        error: Some(error.to_string()),
+    }
+}
+
+async fn collect(mut items: mpsc::Receiver<BatchAnalyzeItem>) -> Vec<BatchAnalyzeItem> {
+    let mut results = Vec::new();
+    while let Some(item) = items.recv().await {
+        results.push(item);
+    }
+    results
+}
+
+fn summarize(results: &[BatchAnalyzeItem], start: Instant) -> BatchAnalyzeSummary {
+    let failed = results.iter().filter(|r| r.error.is_some()).count();
+    BatchAnalyzeSummary {
+        total: results.len(),
+        succeeded: results.len() - failed,
+        failed,
+        execution_time_ms: start.elapsed().as_millis() as u64,
+    }
+}
+
+/// NDJSON body: one `result` line per market as soon as it finishes, then a
+/// final `summary` line.
+fn stream_response(
+    mut items: mpsc::Receiver<BatchAnalyzeItem>,
+    total: usize,
+    start: Instant,
+) -> Response {
+    let (tx, rx) = mpsc::channel::<std::result::Result<Bytes, std::io::Error>>(16);
+
+    tokio::spawn(async move {
+        let mut succeeded = 0;
+        let mut failed = 0;
+
+        while let Some(item) = items.recv().await {
+            if item.error.is_some() {
+                failed += 1;
+            } else {
+                succeeded += 1;
+            }
+            if tx
+                .send(Ok(ndjson_line(&BatchStreamEvent::Result(Box::new(item)))))
+                .await
+                .is_err()
+            {
+                // Client disconnected; dropping `items` cancels the remaining work
+                return;
+            }
+        }
+
+        let summary = BatchStreamEvent::Summary(BatchAnalyzeSummary {
+            total,
+            succeeded,
+            failed,
+            execution_time_ms: start.elapsed().as_millis() as u64,
+        });
+        let _ = tx.send(Ok(ndjson_line(&summary))).await;
+    });
+
+    (
+        [(header::CONTENT_TYPE, "application/x-ndjson")],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response()
+}
+
+fn ndjson_line(event: &BatchStreamEvent) -> Bytes {
+    let mut line = serde_json::to_vec(event).unwrap_or_else(|e| {
+        format!(
+            r#"{{"type":"error","error":"Failed to serialize event: {}"}}"#,
+            e
+        )
+        .into_bytes()
+    });
+    line.push(b'\n');
+    Bytes::from(line)
+}
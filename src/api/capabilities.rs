@@ -0,0 +1,206 @@
+use serde::Serialize;
+
+use crate::clients::AiProvider;
+use crate::config::Config;
+use crate::AppError;
+
+/// Optional integrations. Each is enabled by an env var at startup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Capability {
+    Dome,
+    Polyfactual,
+    Kalshi,
+    Grok,
+    OpenAi,
+    Anthropic,
+    OnchainRpc,
+    UserStream,
+    Persistence,
+}
+
+impl Capability {
+    pub fn name(self) -> &'static str {
+        match self {
+            Capability::Dome => "dome",
+            Capability::Polyfactual => "polyfactual",
+            Capability::Kalshi => "kalshi",
+            Capability::Grok => "grok",
+            Capability::OpenAi => "openai",
+            Capability::Anthropic => "anthropic",
+            Capability::OnchainRpc => "onchain_rpc",
+            Capability::UserStream => "user_stream",
+            Capability::Persistence => "persistence",
+        }
+    }
+
+    pub fn env_var(self) -> &'static str {
+        match self {
+            Capability::Dome => "DOME_API_KEY",
+            Capability::Polyfactual => "POLYFACTUAL_API_KEY",
+            Capability::Kalshi => "KALSHI_API_KEY",
+            Capability::Grok => "GROK_API_KEY",
+            Capability::OpenAi => "OPENAI_API_KEY",
+            Capability::Anthropic => "ANTHROPIC_API_KEY",
+            Capability::OnchainRpc => "POLYGON_RPC_URL",
+            Capability::UserStream => "POLYMARKET_API_KEY",
+            Capability::Persistence => "DATABASE_URL",
+        }
+    }
+
+    /// The integration an AI provider runs on.
+    pub fn ai(provider: &AiProvider) -> Self {
+        match provider {
+            AiProvider::Grok => Capability::Grok,
+            AiProvider::OpenAi => Capability::OpenAi,
+            AiProvider::Claude => Capability::Anthropic,
+        }
+    }
+
+    /// The single early error for a request that needs this capability.
+    pub fn missing(self) -> AppError {
+        AppError::MissingApiKey {
+            variable: self.env_var(),
+            message: format!(
+                "This request needs the '{}' integration, which is not configured on this server; set {} to enable it",
+                self.name(),
+                self.env_var()
+            ),
+        }
+    }
+}
+
+/// Any one AI provider; which is needed depends on the request's model.
+const ANY_AI: &[Capability] = &[Capability::Grok, Capability::OpenAi, Capability::Anthropic];
+
+/// Routes that can't serve any request without these integrations. Each
+/// group is satisfied by any one of its capabilities.
+const ENDPOINT_REQUIREMENTS: &[(&str, &[&[Capability]])] = &[
+    // Kalshi markets can be fetched directly instead of through Dome
+    (
+        "/api/analyze-event-markets",
+        &[&[Capability::Dome, Capability::Kalshi], ANY_AI],
+    ),
+    ("/api/analyze-and-trade", &[ANY_AI]),
+    (
+        "/api/analyze-event-markets/batch",
+        &[&[Capability::Dome], ANY_AI],
+    ),
+    (
+        "/api/analyze-event-markets/refresh",
+        &[&[Capability::Dome], ANY_AI],
+    ),
+    (
+        "/api/analysis-subscriptions",
+        &[&[Capability::Dome], ANY_AI],
+    ),
+    ("/api/arb-scan", &[&[Capability::Dome]]),
+    ("/api/construct-portfolio", &[&[Capability::Dome], ANY_AI]),
+    ("/api/jobs/analyze", &[&[Capability::Dome], ANY_AI]),
+    ("/api/jobs/research", &[&[Capability::Polyfactual]]),
+    ("/api/market-history", &[&[Capability::Dome]]),
+    ("/api/polyfactual-research", &[&[Capability::Polyfactual]]),
+    (
+        "/api/polyfactual-research/stream",
+        &[&[Capability::Polyfactual]],
+    ),
+    ("/api/runs", &[&[Capability::Persistence]]),
+];
+
+/// Whether a route that depends on optional integrations can be used.
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointStatus {
+    pub path: &'static str,
+    pub enabled: bool,
+    /// What to set to enable it, e.g. `GROK_API_KEY or OPENAI_API_KEY`
+    pub missing: Vec<String>,
+}
+
+/// Which optional integrations are available, fixed at startup.
+///
+/// Handlers call [`Capabilities::require`] up front for anything a request
+/// cannot do without, and record purely enhancing features they had to skip
+/// in `metadata.degraded_features` instead of failing.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Capabilities {
+    pub dome: bool,
+    pub polyfactual: bool,
+    pub kalshi: bool,
+    pub grok: bool,
+    pub openai: bool,
+    pub anthropic: bool,
+    pub onchain_rpc: bool,
+    pub user_stream: bool,
+    pub persistence: bool,
+}
+
+impl Capabilities {
+    /// `dome`, `polyfactual`, `kalshi` and `persistence` reflect whether
+    /// their clients were actually constructed; AI providers are enabled by
+    /// their keys in `config`, and the rest are read from their env vars.
+    pub fn detect(
+        config: &Config,
+        dome: bool,
+        polyfactual: bool,
+        kalshi: bool,
+        persistence: bool,
+    ) -> Self {
+        Self {
+            dome,
+            polyfactual,
+            kalshi,
+            grok: config.grok_api_key.is_some(),
+            openai: config.openai_api_key.is_some(),
+            anthropic: config.anthropic_api_key.is_some(),
+            onchain_rpc: config.polygon_rpc_url.is_some(),
+            user_stream: config.polymarket_api_credentials.is_some(),
+            persistence,
+        }
+    }
+
+    pub fn has(&self, capability: Capability) -> bool {
+        match capability {
+            Capability::Dome => self.dome,
+            Capability::Polyfactual => self.polyfactual,
+            Capability::Kalshi => self.kalshi,
+            Capability::Grok => self.grok,
+            Capability::OpenAi => self.openai,
+            Capability::Anthropic => self.anthropic,
+            Capability::OnchainRpc => self.onchain_rpc,
+            Capability::UserStream => self.user_stream,
+            Capability::Persistence => self.persistence,
+        }
+    }
+
+    pub fn require(&self, capability: Capability) -> crate::Result<()> {
+        if self.has(capability) {
+            Ok(())
+        } else {
+            Err(capability.missing())
+        }
+    }
+
+    /// Every route with integration requirements, and whether they are met.
+    pub fn endpoints(&self) -> Vec<EndpointStatus> {
+        ENDPOINT_REQUIREMENTS
+            .iter()
+            .map(|(path, groups)| {
+                let missing: Vec<String> = groups
+                    .iter()
+                    .filter(|group| !group.iter().any(|c| self.has(*c)))
+                    .map(|group| {
+                        group
+                            .iter()
+                            .map(|c| c.env_var())
+                            .collect::<Vec<_>>()
+                            .join(" or ")
+                    })
+                    .collect();
+                EndpointStatus {
+                    path,
+                    enabled: missing.is_empty(),
+                    missing,
+                }
+            })
+            .collect()
+    }
+}
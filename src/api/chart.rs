@@ -0,0 +1,53 @@
+/// Most points embedded in an analysis response chart.
+pub const MAX_CHART_POINTS: usize = 100;
+
+/// Downsamples a `(unix_seconds, price)` series to at most `threshold` points
+/// with largest-triangle-three-buckets, which keeps the spikes and turns a
+/// naive average would flatten. Endpoints are always kept.
+pub fn downsample_lttb(points: &[(i64, f64)], threshold: usize) -> Vec<(i64, f64)> {
+    let n = points.len();
+    if n <= threshold {
+        return points.to_vec();
+    }
+    if threshold < 3 {
+        // Not enough room for interior buckets; keep the endpoints that fit
+        return [points[0], points[n - 1]]
+            .into_iter()
+            .take(threshold)
+            .collect();
+    }
+
+    let bucket_size = (n - 2) as f64 / (threshold - 2) as f64;
+    let mut sampled = Vec::with_capacity(threshold);
+    sampled.push(points[0]);
+    let mut selected = 0;
+
+    for bucket in 0..threshold - 2 {
+        // Average of the next bucket is the third triangle vertex
+        let next_start = ((bucket + 1) as f64 * bucket_size) as usize + 1;
+        let next_end = (((bucket + 2) as f64 * bucket_size) as usize + 1).min(n);
+        let next = &points[next_start..next_end];
+        let avg_t = next.iter().map(|p| p.0 as f64).sum::<f64>() / next.len() as f64;
+        let avg_p = next.iter().map(|p| p.1).sum::<f64>() / next.len() as f64;
+
+        let start = (bucket as f64 * bucket_size) as usize + 1;
+        let end = next_start;
+        let (at, ap) = (points[selected].0 as f64, points[selected].1);
+
+        let mut best = start;
+        let mut best_area = -1.0;
+        for (i, point) in points.iter().enumerate().take(end).skip(start) {
+            let area = ((at - avg_t) * (point.1 - ap) - (at - point.0 as f64) * (avg_p - ap)).abs();
+            if area > best_area {
+                best_area = area;
+                best = i;
+            }
+        }
+
+        sampled.push(points[best]);
+        selected = best;
+    }
+
+    sampled.push(points[n - 1]);
+    sampled
+}
@@ -0,0 +1,486 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::analyze_event_markets::{resolve_provider, run_analysis};
+use crate::api::capabilities::Capability;
+use crate::api::extract::AppJson;
+use crate::api::limit_order_bot::{run_bot, validate_request, BotMarket};
+use crate::api::AppState;
+use crate::clients::ai::prompts::PromptEvidence;
+use crate::clients::dome::parse_market_url;
+use crate::clients::{AiProvider, AiRequestOptions};
+use crate::request_id;
+use crate::types::{
+    AiAnalysis, ConstructPortfolioRequest, ConstructPortfolioResponse, LimitOrderBotRequest,
+    MarketData, OrderMode, OutcomeTarget, Platform, PortfolioConstraint, PortfolioExecution,
+    PortfolioMarketRef, PortfolioPosition, Recommendation, ResponseMetadata, SkippedMarket,
+};
+use crate::{AppError, Result};
+
+const MAX_PORTFOLIO_MARKETS: usize = 25;
+const PORTFOLIO_CONCURRENCY: usize = 5;
+const DEFAULT_MAX_PER_MARKET_PCT: f64 = 25.0;
+const DEFAULT_MIN_CONFIDENCE: f64 = 0.6;
+const DEFAULT_KELLY_FRACTION: f64 = 0.25;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    AppJson(mut request): AppJson<ConstructPortfolioRequest>,
+) -> Result<Json<ConstructPortfolioResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    if request.analyses.len() > MAX_PORTFOLIO_MARKETS {
+        return Err(AppError::Validation(format!(
+            "Request contains {} markets; the maximum is {}",
+            request.analyses.len(),
+            MAX_PORTFOLIO_MARKETS
+        )));
+    }
+
+    let max_per_market_pct = request
+        .max_per_market_pct
+        .unwrap_or(DEFAULT_MAX_PER_MARKET_PCT);
+    if !(max_per_market_pct > 0.0 && max_per_market_pct <= 100.0) {
+        return Err(AppError::Validation(
+            "max_per_market_pct must be in (0, 100]".to_string(),
+        ));
+    }
+    let min_confidence = request.min_confidence.unwrap_or(DEFAULT_MIN_CONFIDENCE);
+    if !(0.0..=1.0).contains(&min_confidence) {
+        return Err(AppError::Validation(
+            "min_confidence must be between 0 and 1".to_string(),
+        ));
+    }
+    let kelly_multiplier = request.kelly_fraction.unwrap_or(DEFAULT_KELLY_FRACTION);
+    if !(kelly_multiplier > 0.0 && kelly_multiplier <= 1.0) {
+        return Err(AppError::Validation(
+            "kelly_fraction must be in (0, 1]".to_string(),
+        ));
+    }
+
+    let execute = request.execute.unwrap_or(false);
+    let dry_run = execute && request.dry_run.unwrap_or(false);
+    // Refuse before the analyses what the bot would refuse after them
+    if execute {
+        if !dry_run {
+            state.runtime_config.ensure_trading_enabled()?;
+        }
+        validate_request(
+            &bot_request(&request, request.bankroll_usd),
+            state.config.wallet.as_ref(),
+        )?;
+    }
+
+    state.capabilities.require(Capability::Dome)?;
+
+    let provider = resolve_provider(request.model.as_deref());
+    state.capabilities.require(Capability::ai(&provider))?;
+    let references = std::mem::take(&mut request.analyses);
+    let resolved = resolve_all(state.clone(), references, provider).await;
+
+    let mut skipped = Vec::new();
+    let mut picks = Vec::new();
+    for item in resolved {
+        match pick_outcome(&item, min_confidence) {
+            Ok(pick) => picks.push(pick),
+            Err(reason) => skipped.push(item.skip(reason)),
+        }
+    }
+
+    let candidates: Vec<Candidate> = picks
+        .iter()
+        .map(|p| Candidate {
+            probability: p.probability,
+            price: p.position.price.value(),
+        })
+        .collect();
+    let allocations = allocate(
+        &candidates,
+        request.bankroll_usd,
+        max_per_market_pct,
+        kelly_multiplier,
+    );
+
+    let positions: Vec<PortfolioPosition> = picks
+        .into_iter()
+        .zip(allocations)
+        .map(|(pick, allocation)| PortfolioPosition {
+            kelly: allocation.kelly,
+            target_stake_usd: allocation.stake,
+            limiting_constraint: allocation.constraint,
+            ..pick.position
+        })
+        .collect();
+
+    let total_allocated_usd = round_cents(positions.iter().map(|p| p.target_stake_usd).sum());
+
+    // One at a time, so each run's exposure check sees the orders before it
+    let mut executions = Vec::new();
+    if execute {
+        for position in positions.iter().filter(|p| p.target_stake_usd > 0.0) {
+            executions.push(execute_position(&state, &request, position).await);
+        }
+    }
+
+    Ok(Json(ConstructPortfolioResponse {
+        positions,
+        skipped,
+        bankroll_usd: request.bankroll_usd,
+        total_allocated_usd,
+        unallocated_usd: round_cents(request.bankroll_usd - total_allocated_usd),
+        executions,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+/// Buys a position's outcome for its stake in simple mode.
+async fn execute_position(
+    state: &Arc<AppState>,
+    request: &ConstructPortfolioRequest,
+    position: &PortfolioPosition,
+) -> PortfolioExecution {
+    let mut execution = PortfolioExecution {
+        analysis_id: position.analysis_id.clone(),
+        stake_usd: position.target_stake_usd,
+        orders: Vec::new(),
+        orders_placed: 0,
+        order_ids: Vec::new(),
+        run_id: None,
+        error: None,
+    };
+
+    let run = match parse_market_url(&position.market_url) {
+        Ok(market_ref) if market_ref.platform == Platform::Polymarket => {
+            let mut bot = bot_request(request, position.target_stake_usd);
+            bot.market_slug = Some(market_ref.identifier);
+            bot.outcomes = Some(vec![OutcomeTarget {
+                outcome: position.token_id.clone(),
+                weight: None,
+            }]);
+            run_bot(state, &bot, None, BotMarket::Fetch { fresh: false }).await
+        }
+        Ok(_) => Err(AppError::Validation(
+            "Trading is only supported on Polymarket markets".to_string(),
+        )),
+        Err(e) => Err(AppError::Validation(e)),
+    };
+
+    match run {
+        Ok(run) => {
+            execution.orders = run.orders;
+            execution.orders_placed = run.orders_placed;
+            execution.order_ids = run.order_ids;
+            execution.run_id = run.run_id;
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Portfolio position {} was not placed: {}",
+                position.analysis_id,
+                e
+            );
+            execution.error = Some(e.to_string());
+        }
+    }
+    execution
+}
+
+/// A simple-mode bot run for `stake` with the portfolio request's wallet and
+/// dry-run setting; the market and outcome are filled in per position.
+fn bot_request(request: &ConstructPortfolioRequest, stake: f64) -> LimitOrderBotRequest {
+    LimitOrderBotRequest {
+        wallet_private_key: request.wallet_private_key.clone(),
+        clob_api_key: None,
+        clob_secret: None,
+        clob_passphrase: None,
+        wallet_address: None,
+        market_slug: None,
+        asset: None,
+        interval: None,
+        mode: OrderMode::Simple,
+        bankroll_usd: stake,
+        price_levels: None,
+        verify_placement: None,
+        verify_delay_ms: None,
+        ai_summary: None,
+        pricing: None,
+        improvement_ticks: None,
+        max_spread_cents: None,
+        strict_spread: None,
+        max_combined_price: None,
+        max_spread: None,
+        dry_run: request.dry_run,
+        outcomes: None,
+        ladder_min_price: None,
+        ladder_max_price: None,
+        ladder_spacing: None,
+        ladder_profile: None,
+        weights: None,
+        exit_target_pct: None,
+        use_orderbook_price: None,
+        idempotency_key: None,
+        override_caps: None,
+        webhook_url: None,
+        expiration: None,
+        expires_at: None,
+        structured_logs: None,
+    }
+}
+
+/// An analysis paired with the market's current data.
+struct ResolvedMarket {
+    index: usize,
+    reference: PortfolioMarketRef,
+    result: Result<(String, String, AiAnalysis, MarketData)>,
+}
+
+impl ResolvedMarket {
+    fn skip(self, reason: String) -> SkippedMarket {
+        SkippedMarket {
+            analysis_id: self.reference.analysis_id,
+            market_url: self.reference.market_url,
+            reason,
+        }
+    }
+}
+
+struct Pick {
+    probability: f64,
+    position: PortfolioPosition,
+}
+
+/// Loads stored analyses (with fresh market data) and analyzes URLs, bounded
+/// like the batch endpoint. Results come back in request order.
+async fn resolve_all(
+    state: Arc<AppState>,
+    references: Vec<PortfolioMarketRef>,
+    provider: AiProvider,
+) -> Vec<ResolvedMarket> {
+    let semaphore = Arc::new(Semaphore::new(PORTFOLIO_CONCURRENCY));
+    let mut workers = JoinSet::new();
+
+    for (index, reference) in references.into_iter().enumerate() {
+        let state = state.clone();
+        let semaphore = semaphore.clone();
+        let provider = provider.clone();
+        workers.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let result = resolve_one(&state, &reference, provider).await;
+            ResolvedMarket {
+                index,
+                reference,
+                result,
+            }
+        });
+    }
+
+    let mut resolved = Vec::new();
+    while let Some(joined) = workers.join_next().await {
+        match joined {
+            Ok(item) => resolved.push(item),
+            Err(e) => tracing::error!("Portfolio analysis task failed: {}", e),
+        }
+    }
+    resolved.sort_by_key(|item| item.index);
+    resolved
+}
+
+async fn resolve_one(
+    state: &AppState,
+    reference: &PortfolioMarketRef,
+    provider: AiProvider,
+) -> Result<(String, String, AiAnalysis, MarketData)> {
+    if let Some(analysis_id) = &reference.analysis_id {
+        let stored = state
+            .analysis_store
+            .get(analysis_id)
+            .ok_or_else(|| AppError::NotFound(format!("Analysis {} not found", analysis_id)))?;
+        let market = state
+            .dome()?
+            .get_market(stored.market.platform, &stored.market.identifier)
+            .await?;
+        return Ok((stored.id, stored.url, stored.analysis, market));
+    }
+
+    let url = reference.market_url.clone().unwrap_or_default();
+    let dome = state.dome()?;
+    let market_ref = dome.market_ref_from_url(&url)?;
+    let market = dome
+        .get_market(market_ref.platform, &market_ref.identifier)
+        .await?;
+    let run = run_analysis(
+        state,
+        &market,
+        None,
+        None,
+        &PromptEvidence::default(),
+        provider,
+        &AiRequestOptions::default(),
+        true,
+    )
+    .await?;
+
+    let analysis_id = new_analysis_id();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: url.clone(),
+        market: market_ref,
+        question: None,
+        model: Some(run.provider.to_string()),
+        custom_prompt: None,
+        ai_options: AiRequestOptions::default(),
+        snapshot: MarketSnapshot::capture(&market),
+        analysis: run.analysis.clone(),
+        created_at: Utc::now(),
+    });
+
+    Ok((analysis_id, url, run.analysis, market))
+}
+
+/// The outcome an actionable analysis says to buy, or why it was left out.
+fn pick_outcome(item: &ResolvedMarket, min_confidence: f64) -> std::result::Result<Pick, String> {
+    let (analysis_id, url, analysis, market) = item.result.as_ref().map_err(|e| e.to_string())?;
+
+    if analysis.recommendation == Recommendation::NoTrade {
+        return Err("Recommendation is NO_TRADE".to_string());
+    }
+    if analysis.confidence < min_confidence {
+        return Err(format!(
+            "Confidence {:.2} is below min_confidence {:.2}",
+            analysis.confidence, min_confidence
+        ));
+    }
+
+    let (yes, no) = market
+        .binary_outcomes()
+        .ok_or_else(|| "Market does not have two outcomes".to_string())?;
+    let outcome = match analysis.recommendation {
+        Recommendation::BuyNo => no,
+        _ => yes,
+    };
+
+    // The analysis confidence is read as the probability that the
+    // recommended outcome resolves true.
+    if kelly_fraction(analysis.confidence, outcome.price.value()) <= 0.0 {
+        return Err(format!(
+            "No edge: confidence {:.2} does not beat price {:.2}",
+            analysis.confidence,
+            outcome.price.value()
+        ));
+    }
+
+    Ok(Pick {
+        probability: analysis.confidence,
+        position: PortfolioPosition {
+            analysis_id: analysis_id.clone(),
+            market_url: url.clone(),
+            question: market.question.clone(),
+            recommendation: analysis.recommendation.clone(),
+            confidence: analysis.confidence,
+            outcome: outcome.name.clone(),
+            token_id: outcome.id.clone(),
+            price: outcome.price,
+            kelly: 0.0,
+            target_stake_usd: 0.0,
+            limiting_constraint: PortfolioConstraint::Kelly,
+        },
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candidate {
+    /// Estimated probability the purchased outcome resolves true
+    pub probability: f64,
+    /// Price paid per share
+    pub price: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Allocation {
+    pub kelly: f64,
+    pub stake: f64,
+    pub constraint: PortfolioConstraint,
+}
+
+/// Full-Kelly fraction of bankroll for a binary share bought at `price` that
+/// pays 1 with `probability`: `(p - q) / (1 - q)`, or 0 without an edge.
+pub fn kelly_fraction(probability: f64, price: f64) -> f64 {
+    if !(price > 0.0 && price < 1.0) {
+        return 0.0;
+    }
+    ((probability - price) / (1.0 - price)).max(0.0)
+}
+
+/// Sizes each candidate at `kelly_multiplier` × Kelly, capped at
+/// `max_per_market_pct` of the bankroll, then scales everything down
+/// proportionally if the total would exceed the bankroll.
+///
+/// Stakes are rounded down to whole cents, so the total never exceeds
+/// `bankroll`; when caps bind, part of the bankroll is left unallocated.
+pub fn allocate(
+    candidates: &[Candidate],
+    bankroll: f64,
+    max_per_market_pct: f64,
+    kelly_multiplier: f64,
+) -> Vec<Allocation> {
+    let cap = bankroll * max_per_market_pct / 100.0;
+
+    let mut allocations: Vec<Allocation> = candidates
+        .iter()
+        .map(|c| {
+            let kelly = kelly_fraction(c.probability, c.price);
+            let stake = bankroll * kelly * kelly_multiplier;
+            if stake > cap {
+                Allocation {
+                    kelly,
+                    stake: cap,
+                    constraint: PortfolioConstraint::MarketCap,
+                }
+            } else {
+                Allocation {
+                    kelly,
+                    stake,
+                    constraint: PortfolioConstraint::Kelly,
+                }
+            }
+        })
+        .collect();
+
+    let total: f64 = allocations.iter().map(|a| a.stake).sum();
+    if total > bankroll {
+        let scale = bankroll / total;
+        for allocation in allocations.iter_mut() {
+            allocation.stake *= scale;
+            allocation.constraint = PortfolioConstraint::Bankroll;
+        }
+    }
+
+    for allocation in allocations.iter_mut() {
+        allocation.stake = (allocation.stake * 100.0 + 1e-9).floor() / 100.0;
+    }
+
+    allocations
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,121 @@
+use axum::http::{header, HeaderName, HeaderValue, Method};
+use std::fmt;
+use std::str::FromStr;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+use url::Url;
+
+use crate::api::orders::WALLET_KEY_HEADER;
+use crate::error::RESPONSE_VERSION_HEADER;
+use crate::request_id::REQUEST_ID_HEADER;
+
+const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Browser origins allowed to call the API (`CORS_ALLOWED_ORIGINS`).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum CorsOrigins {
+    /// Not configured: any origin, with a warning at startup
+    #[default]
+    Unset,
+    /// An explicit `*`, for development
+    Any,
+    /// Normalized `scheme://host[:port]` origins
+    List(Vec<String>),
+}
+
+impl FromStr for CorsOrigins {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let entries: Vec<&str> = value
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .collect();
+        if entries.is_empty() {
+            return Ok(CorsOrigins::Unset);
+        }
+        if entries.contains(&"*") {
+            return if entries.len() == 1 {
+                Ok(CorsOrigins::Any)
+            } else {
+                Err("'*' can't be combined with specific origins".to_string())
+            };
+        }
+        entries
+            .into_iter()
+            .map(parse_origin)
+            .collect::<Result<_, _>>()
+            .map(CorsOrigins::List)
+    }
+}
+
+/// Accepts `https://app.example.com` (a trailing slash is tolerated) and
+/// returns it as browsers send it in `Origin`.
+fn parse_origin(entry: &str) -> Result<String, String> {
+    let invalid = |reason: &str| {
+        format!(
+            "'{}' is not an origin ({}); expected scheme://host[:port], e.g. https://app.example.com",
+            entry, reason
+        )
+    };
+    let url = Url::parse(entry).map_err(|e| invalid(&e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("scheme must be http or https"));
+    }
+    if url.host().is_none() {
+        return Err(invalid("no host"));
+    }
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("has a path, query or fragment"));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(invalid("has credentials"));
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+impl fmt::Display for CorsOrigins {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorsOrigins::Unset => f.write_str("unset (any origin)"),
+            CorsOrigins::Any => f.write_str("*"),
+            CorsOrigins::List(origins) => f.write_str(&origins.join(",")),
+        }
+    }
+}
+
+impl CorsOrigins {
+    /// The CORS layer for these origins. Configured origins may send GET,
+    /// POST and DELETE with the `Authorization`, `Content-Type`,
+    /// `Idempotency-Key`, `If-None-Match`, `X-Wallet-Private-Key`,
+    /// `X-Request-Id` and `X-Response-Version` headers, exposing `ETag`,
+    /// `X-Request-Id` and `Retry-After`; unset stays fully permissive.
+    pub fn layer(&self) -> CorsLayer {
+        let allow_origin = match self {
+            CorsOrigins::Unset => return CorsLayer::permissive(),
+            CorsOrigins::Any => AllowOrigin::any(),
+            CorsOrigins::List(origins) => AllowOrigin::list(
+                origins
+                    .iter()
+                    .map(|origin| HeaderValue::from_str(origin).expect("origins are ASCII")),
+            ),
+        };
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([
+                header::AUTHORIZATION,
+                header::CONTENT_TYPE,
+                IDEMPOTENCY_KEY,
+                header::IF_NONE_MATCH,
+                HeaderName::from_static(WALLET_KEY_HEADER),
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                HeaderName::from_static(RESPONSE_VERSION_HEADER),
+            ])
+            .expose_headers([
+                header::ETAG,
+                HeaderName::from_static(REQUEST_ID_HEADER),
+                header::RETRY_AFTER,
+            ])
+    }
+}
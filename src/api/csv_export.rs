@@ -0,0 +1,158 @@
+use axum::{
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::borrow::Cow;
+use utoipa::{IntoParams, ToSchema};
+
+/// `?format=csv` asks an exporting endpoint for a spreadsheet instead of
+/// JSON; so does `Accept: text/csv`. The query parameter wins over the
+/// header.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FormatQuery {
+    /// `csv` for a download, `json` (default) otherwise
+    #[param(inline)]
+    pub format: Option<ExportFormat>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+}
+
+impl FormatQuery {
+    pub fn csv(&self, headers: &HeaderMap) -> bool {
+        match self.format {
+            Some(format) => format == ExportFormat::Csv,
+            None => headers
+                .get_all(header::ACCEPT)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(|value| value.split(','))
+                .any(|range| {
+                    let media = range.split(';').next().unwrap_or_default().trim();
+                    media.eq_ignore_ascii_case("text/csv")
+                }),
+        }
+    }
+}
+
+/// A type exported as one CSV record per value.
+pub trait CsvSerializable {
+    /// Column names, in the order [`Self::record`] fills them
+    const HEADER: &'static [&'static str];
+
+    fn record(&self) -> Vec<String>;
+}
+
+/// `rows` as an RFC 4180 CSV download named `<name>-<YYYY-MM-DD>.csv`:
+/// a header line, then one record per row, fields quoted where needed.
+pub fn csv_response<T: CsvSerializable>(rows: &[T], name: &str) -> Response {
+    let mut body = String::new();
+    write_record(&mut body, T::HEADER.iter().copied());
+    for row in rows {
+        let record = row.record();
+        write_record(&mut body, record.iter().map(String::as_str));
+    }
+
+    let filename = format!("{}-{}.csv", name, Utc::now().format("%Y-%m-%d"));
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", filename),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn write_record<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str(&escape(field));
+    }
+    out.push_str("\r\n");
+}
+
+/// Quotes fields holding a separator, quote or line break, or padded with
+/// spaces a spreadsheet would trim; quotes inside are doubled.
+fn escape(field: &str) -> Cow<'_, str> {
+    let needs_quotes = field.contains([',', '"', '\r', '\n']) || field.trim() != field;
+    match needs_quotes {
+        true => Cow::Owned(format!("\"{}\"", field.replace('"', "\"\""))),
+        false => Cow::Borrowed(field),
+    }
+}
+
+/// `value` to exactly `places` decimals; never `-0.00`, and empty when
+/// missing or not a number.
+pub fn decimal(value: Option<f64>, places: usize) -> String {
+    match value.filter(|value| value.is_finite()) {
+        Some(value) => {
+            let formatted = format!("{:.*}", places, value);
+            match formatted.strip_prefix('-') {
+                Some(unsigned) if unsigned.bytes().all(|b| b == b'0' || b == b'.') => {
+                    unsigned.to_string()
+                }
+                _ => formatted,
+            }
+        }
+        None => String::new(),
+    }
+}
+
+/// One line of a positions or trades export. Holdings leave `side` empty;
+/// fills leave the current price and P&L empty, with their price as
+/// `avg_price`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerRow {
+    /// The market's question
+    pub market: String,
+    pub outcome: String,
+    pub side: Option<String>,
+    pub shares: f64,
+    pub avg_price: f64,
+    pub current_price: Option<f64>,
+    pub realized_pnl: Option<f64>,
+    pub unrealized_pnl: Option<f64>,
+    /// RFC 3339; when the fill happened, or when the holding was read
+    pub timestamp: String,
+}
+
+impl CsvSerializable for LedgerRow {
+    const HEADER: &'static [&'static str] = &[
+        "market",
+        "outcome",
+        "side",
+        "shares",
+        "avg_price",
+        "current_price",
+        "realized_pnl",
+        "unrealized_pnl",
+        "timestamp",
+    ];
+
+    fn record(&self) -> Vec<String> {
+        vec![
+            self.market.clone(),
+            self.outcome.clone(),
+            self.side.clone().unwrap_or_default(),
+            decimal(Some(self.shares), 4),
+            decimal(Some(self.avg_price), 4),
+            decimal(self.current_price, 4),
+            decimal(self.realized_pnl, 2),
+            decimal(self.unrealized_pnl, 2),
+            self.timestamp.clone(),
+        ]
+    }
+}
@@ -0,0 +1,20 @@
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api::capabilities::Capabilities;
+use crate::api::AppState;
+use crate::clients::salt::SaltAllocatorStats;
+
+#[derive(Debug, Serialize)]
+pub struct DiagnosticsResponse {
+    pub salt_allocator: SaltAllocatorStats,
+    pub capabilities: Capabilities,
+}
+
+pub async fn handler(State(state): State<Arc<AppState>>) -> Json<DiagnosticsResponse> {
+    Json(DiagnosticsResponse {
+        salt_allocator: state.salt_allocator.stats(),
+        capabilities: state.capabilities,
+    })
+}
@@ -0,0 +1,236 @@
+use chrono::Utc;
+use std::cmp::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::analyze_event_markets::{
+    apply_risk_gate, resolve_target, run_analysis, suggested_size, AnalysisRun,
+};
+use crate::api::event_mispricing::event_slug_from_url;
+use crate::api::AppState;
+use crate::clients::ai::prompts::{analysis_warnings, PromptEvidence};
+use crate::clients::{AiProvider, AiRequestOptions};
+use crate::request_id;
+use crate::types::{
+    AiUsage, AnalyzeEventMarketsRequest, AnalyzeEventResponse, EventMarketAnalysis, MarketData,
+    MarketRef, Platform, ResponseMetadata, SkippedEventMarket, TimeoutBudget,
+};
+use crate::Result;
+
+/// Market analyses in flight at once for one event.
+const EVENT_ANALYSIS_CONCURRENCY: usize = 5;
+
+/// Analyzes every open market of the request url's Polymarket event, up to
+/// `EVENT_ANALYSIS_MAX_MARKETS` of them (the most traded first), and ranks
+/// them by confidence-weighted edge. A market whose analysis fails is
+/// reported with its error instead of failing the event.
+pub(crate) async fn analyze_all_markets(
+    state: &Arc<AppState>,
+    request: &AnalyzeEventMarketsRequest,
+    provider: AiProvider,
+    ai_options: AiRequestOptions,
+    start: Instant,
+) -> Result<AnalyzeEventResponse> {
+    let slug = event_slug_from_url(request.url.as_deref().unwrap_or_default())?;
+    let event = state.polymarket_client.get_event_by_slug(&slug).await?;
+
+    let (mut open, closed): (Vec<MarketData>, Vec<MarketData>) = event
+        .markets
+        .into_iter()
+        .partition(|m| !m.closed && m.resolved_outcome.is_none());
+    open.sort_by(|a, b| b.volume.unwrap_or(0.0).total_cmp(&a.volume.unwrap_or(0.0)));
+    let max_markets = state.config.event_analysis_max_markets;
+    let over_cap = open.split_off(open.len().min(max_markets));
+    let skipped = closed
+        .iter()
+        .map(|m| skipped_market(m, "Closed to trading".to_string()))
+        .chain(over_cap.iter().map(|m| {
+            skipped_market(
+                m,
+                format!(
+                    "Beyond the {}-market cap (EVENT_ANALYSIS_MAX_MARKETS)",
+                    max_markets
+                ),
+            )
+        }))
+        .collect();
+
+    let allow_fallback = request.allow_fallback.unwrap_or(true);
+    let semaphore = Arc::new(Semaphore::new(EVENT_ANALYSIS_CONCURRENCY));
+    let mut workers = JoinSet::new();
+    for (index, market) in open.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        let state = state.clone();
+        let question = request.question.clone();
+        let custom_prompt = request.custom_prompt.clone();
+        let provider = provider.clone();
+        let ai_options = ai_options.clone();
+        workers.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let run = run_analysis(
+                &state,
+                &market,
+                question.as_ref(),
+                custom_prompt.as_deref(),
+                &PromptEvidence::default(),
+                provider,
+                &ai_options,
+                allow_fallback,
+            )
+            .await;
+            (index, market, run)
+        });
+    }
+
+    let mut runs = Vec::new();
+    while let Some(joined) = workers.join_next().await {
+        match joined {
+            Ok(run) => runs.push(run),
+            Err(e) => tracing::error!("Event market analysis task failed: {}", e),
+        }
+    }
+    // Completion order varies; keep the event's order so the reported model
+    // is the first market's
+    runs.sort_by_key(|(index, _, _)| *index);
+
+    let mut usage = AiUsage::default();
+    let mut model_used = None;
+    let mut retries = 0;
+    let mut timed_out = false;
+    let mut markets: Vec<EventMarketAnalysis> = runs
+        .into_iter()
+        .map(|(_, market, run)| match run {
+            Ok(run) => {
+                usage = usage.combine(run.usage);
+                model_used.get_or_insert_with(|| run.model_used.clone());
+                retries += run.retries;
+                timed_out |= run.timed_out;
+                rank_market(state, request, &ai_options, market, run)
+            }
+            Err(e) => {
+                tracing::warn!("Event market analysis failed for {}: {}", market.id, e);
+                EventMarketAnalysis {
+                    market_data: market,
+                    analysis: None,
+                    analysis_id: None,
+                    overrides: Vec::new(),
+                    warnings: Vec::new(),
+                    target_token_id: None,
+                    target_match: None,
+                    edge: None,
+                    score: None,
+                    suggested_size_usd: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        })
+        .collect();
+    markets.sort_by(|a, b| {
+        ranking(b)
+            .partial_cmp(&ranking(a))
+            .unwrap_or(Ordering::Equal)
+    });
+    let best_opportunity = markets
+        .first()
+        .filter(|m| m.edge.is_some_and(|edge| edge > 0.0))
+        .cloned();
+
+    Ok(AnalyzeEventResponse {
+        event_slug: event.slug,
+        title: event.title,
+        markets,
+        best_opportunity,
+        skipped,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used,
+            retries,
+            degraded_features: Vec::new(),
+            custom_prompt: request.custom_prompt.is_some(),
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: Some(usage),
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: Some(TimeoutBudget {
+                limit_secs: ai_options.timeout().as_secs(),
+                timed_out,
+            }),
+        },
+    })
+}
+
+/// Gates and resolves one market's analysis as the single-market endpoint
+/// does, stores it for refresh, and scores it.
+fn rank_market(
+    state: &AppState,
+    request: &AnalyzeEventMarketsRequest,
+    ai_options: &AiRequestOptions,
+    market: MarketData,
+    run: AnalysisRun,
+) -> EventMarketAnalysis {
+    let mut analysis = run.analysis;
+    let mut overrides = apply_risk_gate(&mut analysis, request.min_confidence.unwrap_or(0.0));
+    let target = resolve_target(&mut analysis, &market, &mut overrides);
+    let mut warnings = run.warnings;
+    warnings.extend(analysis_warnings(&analysis, &market));
+    let edge = target
+        .as_ref()
+        .map(|t| analysis.confidence - t.outcome.price.value());
+
+    let market_ref = MarketRef {
+        platform: Platform::Polymarket,
+        identifier: market.slug.clone().unwrap_or_else(|| market.id.clone()),
+    };
+    let analysis_id = new_analysis_id();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: market_ref.page_url(),
+        market: market_ref,
+        question: request.question.clone(),
+        model: request.model.clone(),
+        custom_prompt: request.custom_prompt.clone(),
+        ai_options: ai_options.clone(),
+        snapshot: MarketSnapshot::capture(&market),
+        analysis: analysis.clone(),
+        created_at: Utc::now(),
+    });
+
+    EventMarketAnalysis {
+        suggested_size_usd: request
+            .max_position_usd
+            .map(|max_position_usd| suggested_size(&analysis, &market, max_position_usd)),
+        score: edge.map(|edge| edge * analysis.confidence),
+        edge,
+        target_token_id: target.as_ref().map(|t| t.outcome.id.clone()),
+        target_match: target.map(|t| t.matched),
+        overrides,
+        warnings,
+        analysis_id: Some(analysis_id),
+        analysis: Some(analysis),
+        market_data: market,
+        error: None,
+    }
+}
+
+/// Scored markets by score, then NO_TRADE, then failures.
+fn ranking(market: &EventMarketAnalysis) -> (u8, f64) {
+    match (market.score, &market.analysis) {
+        (Some(score), _) => (2, score),
+        (None, Some(_)) => (1, 0.0),
+        (None, None) => (0, 0.0),
+    }
+}
+
+fn skipped_market(market: &MarketData, reason: String) -> SkippedEventMarket {
+    SkippedEventMarket {
+        market_id: market.id.clone(),
+        question: market.question.clone(),
+        reason,
+    }
+}
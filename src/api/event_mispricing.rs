@@ -0,0 +1,342 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Instant;
+use url::Url;
+
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{
+    BucketContribution, EventMispricingRequest, EventMispricingResponse, EventStructure,
+    MarketData, MispricingDirection, MispricingTrade, ResponseMetadata, TradeLeg,
+};
+use crate::{AppError, Result};
+
+/// Polymarket rejects orders below 5 shares or $1 notional.
+const MIN_ORDER_SHARES: f64 = 5.0;
+const MIN_ORDER_NOTIONAL: f64 = 1.0;
+/// Closed buckets priced at or below this resolved NO and are left out.
+const RESOLVED_NO_MAX_PRICE: f64 = 0.01;
+/// A sum further than this from 1 usually means buckets are missing or
+/// overlap, not that the market is that far off.
+const MAX_PLAUSIBLE_DEVIATION: f64 = 0.25;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<EventMispricingRequest>,
+) -> Result<Json<EventMispricingResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    let slug = event_slug_from_url(&request.url)?;
+
+    let event = state.polymarket_client.get_event_by_slug(&slug).await?;
+
+    let mut response = EventMispricingResponse {
+        event_slug: event.slug,
+        title: event.title,
+        structure: EventStructure::StructureUnknown,
+        structure_reason: None,
+        price_sum: None,
+        deviation: None,
+        buckets: Vec::new(),
+        trade: None,
+        no_trade_reason: None,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: 0,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    };
+
+    match detect_structure(event.neg_risk, &event.markets) {
+        Ok(buckets) => {
+            let (sum, contributions) = price_sum(&buckets);
+            response.structure = EventStructure::MutuallyExclusive;
+            response.price_sum = Some(sum);
+            response.deviation = Some(sum - 1.0);
+            response.buckets = contributions;
+
+            match direction(sum) {
+                Some(direction) => {
+                    let mut fee_rates = Vec::with_capacity(buckets.len());
+                    for bucket in &buckets {
+                        let token_id = &bucket.leg(direction).token_id;
+                        let params = state.polymarket_client.get_market_params(token_id).await;
+                        fee_rates.push(params.fee_rate_bps);
+                    }
+                    match size_trade(&buckets, direction, &fee_rates, request.budget_usd) {
+                        Ok(trade) => response.trade = Some(trade),
+                        Err(reason) => response.no_trade_reason = Some(reason),
+                    }
+                }
+                None => response.no_trade_reason = Some("Buckets sum to exactly 1".to_string()),
+            }
+        }
+        Err((structure, reason)) => {
+            response.structure = structure;
+            response.no_trade_reason =
+                Some("Event is not a set of mutually exclusive buckets".to_string());
+            response.structure_reason = Some(reason);
+        }
+    }
+
+    response.metadata.execution_time_ms = start.elapsed().as_millis() as u64;
+    Ok(Json(response))
+}
+
+/// Event slug from `https://polymarket.com/event/<slug>[/<market>]`.
+pub(crate) fn event_slug_from_url(url: &str) -> Result<String> {
+    let parsed =
+        Url::parse(url).map_err(|e| AppError::Validation(format!("Invalid URL: {}", e)))?;
+    if !parsed.host_str().unwrap_or("").contains("polymarket") {
+        return Err(AppError::Validation(
+            "Only Polymarket event URLs are supported".to_string(),
+        ));
+    }
+    parsed
+        .path()
+        .strip_prefix("/event/")
+        .and_then(|rest| rest.split('/').next())
+        .filter(|slug| !slug.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| {
+            AppError::Validation(format!("Could not extract event slug from URL: {}", url))
+        })
+}
+
+/// One bucket of a mutually exclusive event.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bucket {
+    pub market_id: String,
+    pub question: String,
+    pub yes: Quote,
+    pub no: Quote,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quote {
+    pub outcome: String,
+    pub token_id: String,
+    pub price: f64,
+}
+
+impl Bucket {
+    fn leg(&self, direction: MispricingDirection) -> &Quote {
+        match direction {
+            MispricingDirection::BuyYes => &self.yes,
+            MispricingDirection::BuyNo => &self.no,
+        }
+    }
+}
+
+/// Treats an event as mutually exclusive buckets only when Gamma flags it
+/// neg-risk, every open market is Yes/No, and the YES prices land near 1.
+/// Anything else is reported with a reason instead of guessed at.
+pub fn detect_structure(
+    neg_risk: Option<bool>,
+    markets: &[MarketData],
+) -> std::result::Result<Vec<Bucket>, (EventStructure, String)> {
+    let unknown = |reason: String| (EventStructure::StructureUnknown, reason);
+
+    match neg_risk {
+        Some(true) => {}
+        Some(false) => {
+            return Err((
+                EventStructure::Independent,
+                "Event markets resolve independently".to_string(),
+            ))
+        }
+        None => {
+            return Err(unknown(
+                "Gamma did not say whether the event's markets are mutually exclusive".to_string(),
+            ))
+        }
+    }
+
+    let mut buckets = Vec::new();
+    for market in markets {
+        if market.outcome_ordering != "yes_no" {
+            return Err(unknown(format!(
+                "Market '{}' is not a Yes/No market",
+                market.question
+            )));
+        }
+        let (yes, no) = (&market.outcomes[0], &market.outcomes[1]);
+        if market.closed {
+            if yes.price.value() <= RESOLVED_NO_MAX_PRICE {
+                continue;
+            }
+            return Err(unknown(format!(
+                "Bucket '{}' is closed but not resolved NO",
+                market.question
+            )));
+        }
+        buckets.push(Bucket {
+            market_id: market.id.clone(),
+            question: market.question.clone(),
+            yes: Quote {
+                outcome: yes.name.clone(),
+                token_id: yes.id.clone(),
+                price: yes.price.value(),
+            },
+            no: Quote {
+                outcome: no.name.clone(),
+                token_id: no.id.clone(),
+                price: no.price.value(),
+            },
+        });
+    }
+
+    if buckets.len() < 2 {
+        return Err(unknown(format!(
+            "Event has {} open bucket(s); at least 2 are needed",
+            buckets.len()
+        )));
+    }
+
+    let (sum, _) = price_sum(&buckets);
+    if (sum - 1.0).abs() > MAX_PLAUSIBLE_DEVIATION {
+        return Err(unknown(format!(
+            "Bucket prices sum to {:.3}; buckets are likely missing or overlapping",
+            sum
+        )));
+    }
+
+    Ok(buckets)
+}
+
+/// Sum of YES prices and each bucket's contribution to it.
+pub fn price_sum(buckets: &[Bucket]) -> (f64, Vec<BucketContribution>) {
+    let sum: f64 = buckets.iter().map(|b| b.yes.price).sum();
+    let contributions = buckets
+        .iter()
+        .map(|b| BucketContribution {
+            market_id: b.market_id.clone(),
+            question: b.question.clone(),
+            yes_price: b.yes.price,
+            no_price: b.no.price,
+            share_of_sum: if sum > 0.0 { b.yes.price / sum } else { 0.0 },
+        })
+        .collect();
+    (sum, contributions)
+}
+
+/// Overpriced buckets are shorted by buying every NO; underpriced ones are
+/// bought outright.
+pub fn direction(sum: f64) -> Option<MispricingDirection> {
+    if sum > 1.0 {
+        Some(MispricingDirection::BuyNo)
+    } else if sum < 1.0 {
+        Some(MispricingDirection::BuyYes)
+    } else {
+        None
+    }
+}
+
+/// Sizes the full trade set: the same number of shares on one side of every
+/// bucket. A YES set pays 1 per share at resolution; a NO set over `n`
+/// buckets pays `n - 1`.
+///
+/// Fees follow the CLOB's `rate × min(price, 1 - price)` per share. The set
+/// is sized to `budget` (cost plus fees), or to the smallest size every leg
+/// accepts. Fails with a reason when the edge doesn't cover fees or the
+/// budget is below the minimum.
+pub fn size_trade(
+    buckets: &[Bucket],
+    direction: MispricingDirection,
+    fee_rates_bps: &[u32],
+    budget: Option<f64>,
+) -> std::result::Result<MispricingTrade, String> {
+    let legs: Vec<TradeLeg> = buckets
+        .iter()
+        .zip(fee_rates_bps)
+        .map(|(bucket, fee)| {
+            let quote = bucket.leg(direction);
+            TradeLeg {
+                market_id: bucket.market_id.clone(),
+                outcome: quote.outcome.clone(),
+                token_id: quote.token_id.clone(),
+                price: quote.price,
+                fee_rate_bps: *fee,
+            }
+        })
+        .collect();
+
+    let cost_per_set: f64 = legs.iter().map(|l| l.price).sum();
+    let fee_per_set: f64 = legs
+        .iter()
+        .map(|l| f64::from(l.fee_rate_bps) / 10_000.0 * l.price.min(1.0 - l.price))
+        .sum();
+    let payout_per_set = match direction {
+        MispricingDirection::BuyYes => 1.0,
+        MispricingDirection::BuyNo => (legs.len() - 1) as f64,
+    };
+
+    let net_per_set = payout_per_set - cost_per_set - fee_per_set;
+    if net_per_set <= 0.0 {
+        return Err(format!(
+            "Edge does not cover costs: pays {:.4} per set against {:.4} in prices and {:.4} in fees",
+            payout_per_set, cost_per_set, fee_per_set
+        ));
+    }
+
+    let min_shares = legs
+        .iter()
+        .filter(|l| l.price > 0.0)
+        .map(|l| ceil_cents(MIN_ORDER_NOTIONAL / l.price))
+        .fold(MIN_ORDER_SHARES, f64::max);
+
+    let shares = match budget {
+        Some(budget) => {
+            let shares = floor_cents(budget / (cost_per_set + fee_per_set));
+            if shares < min_shares {
+                return Err(format!(
+                    "Budget ${:.2} is below the minimum trade set of {} shares (${:.2})",
+                    budget,
+                    min_shares,
+                    min_shares * (cost_per_set + fee_per_set)
+                ));
+            }
+            shares
+        }
+        None => min_shares,
+    };
+
+    let cost_usd = round_cents(shares * cost_per_set);
+    let fees_usd = round_cents(shares * fee_per_set);
+    let payout_usd = round_cents(shares * payout_per_set);
+
+    Ok(MispricingTrade {
+        direction,
+        legs,
+        shares_per_leg: shares,
+        cost_usd,
+        fees_usd,
+        payout_usd,
+        net_profit_usd: round_cents(payout_usd - cost_usd - fees_usd),
+    })
+}
+
+fn floor_cents(value: f64) -> f64 {
+    (value * 100.0 + 1e-9).floor() / 100.0
+}
+
+fn ceil_cents(value: f64) -> f64 {
+    (value * 100.0 - 1e-9).ceil() / 100.0
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
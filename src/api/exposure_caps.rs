@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::api::limit_order_bot::{BotLog, PlannedOrder};
+use crate::api::AppState;
+use crate::clients::clob_signing::WalletAuth;
+use crate::clients::polymarket::ClobOrder;
+use crate::types::{BotLogEventKind, MarketData};
+use crate::{AppError, Result};
+
+/// Operator limits on how much a bot run may buy, set via
+/// `MAX_ORDER_NOTIONAL_USD`, `MAX_MARKET_EXPOSURE_USD` and
+/// `MAX_TOTAL_EXPOSURE_USD`. Unset caps are not enforced.
+#[derive(Debug, Clone, Default)]
+pub struct ExposureCaps {
+    pub max_order_notional: Option<f64>,
+    pub max_market_exposure: Option<f64>,
+    pub max_total_exposure: Option<f64>,
+    /// Lets a request skip the caps with `override_caps`.
+    pub allow_override: bool,
+}
+
+impl ExposureCaps {
+    pub fn is_empty(&self) -> bool {
+        self.max_order_notional.is_none()
+            && self.max_market_exposure.is_none()
+            && self.max_total_exposure.is_none()
+    }
+
+    /// Refuses a run whose buys would break a cap. Existing positions count
+    /// at their current value and resting buy orders at their remaining
+    /// notional, except those in `cancelling`, which the run cancels before
+    /// placing; sells never count against a cap. Buys of other runs for the
+    /// wallet that passed but haven't finished count too, and this run's are
+    /// reserved the same way until the returned reservation is dropped.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn enforce(
+        &self,
+        state: &AppState,
+        auth: &WalletAuth,
+        market: &MarketData,
+        planned: &[PlannedOrder],
+        cancelling: &[String],
+        override_caps: bool,
+        logs: &mut BotLog,
+    ) -> Result<ExposureReservation> {
+        if override_caps && !self.allow_override {
+            return Err(AppError::Validation(
+                "override_caps is not allowed; set ALLOW_CAP_OVERRIDE=true to enable it"
+                    .to_string(),
+            ));
+        }
+        if override_caps && !self.is_empty() {
+            logs.push("Exposure caps overridden for this run".to_string());
+        }
+
+        let buys: Vec<&PlannedOrder> = planned.iter().filter(|o| o.side == "buy").collect();
+        if buys.is_empty() || self.is_empty() {
+            return Ok(ExposureReservation::default());
+        }
+        let run_notional: f64 = buys.iter().map(|o| o.price.value() * o.size).sum();
+        let wallet = auth.address().to_checksum(None);
+        let in_flight = &state.exposure_in_flight;
+
+        if override_caps {
+            return in_flight.reserve(&wallet, &market.id, run_notional, |_, _| Ok(()));
+        }
+
+        if let Some(limit) = self.max_order_notional {
+            for order in &buys {
+                let notional = order.price.value() * order.size;
+                check_cap(
+                    logs,
+                    "MAX_ORDER_NOTIONAL_USD",
+                    Some(&format!("{} @ {}", order.outcome, order.price)),
+                    limit,
+                    notional,
+                )?;
+            }
+        }
+
+        let mut market_exposure = None;
+        if let Some(limit) = self.max_market_exposure {
+            let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+            let held: f64 = state
+                .polymarket_client
+                .get_market_position(&wallet, market.condition_id.as_deref(), &token_ids)
+                .await?
+                .iter()
+                .map(|p| p.shares.max(0.0) * p.current_price)
+                .sum();
+            let resting = resting_notional(
+                state
+                    .polymarket_client
+                    .get_open_orders(auth, &token_ids)
+                    .await?,
+                cancelling,
+            );
+            market_exposure = Some((limit, held, resting));
+        }
+
+        let mut total_exposure = None;
+        if let Some(limit) = self.max_total_exposure {
+            let held: f64 = state
+                .polymarket_client
+                .get_wallet_positions(&wallet)
+                .await?
+                .iter()
+                .map(|p| p.size.max(0.0) * p.cur_price)
+                .sum();
+            let resting = resting_notional(
+                state.polymarket_client.get_wallet_open_orders(auth).await?,
+                cancelling,
+            );
+            total_exposure = Some((limit, held, resting));
+        }
+
+        // Checked and reserved under one lock so concurrent runs see each other
+        in_flight.reserve(&wallet, &market.id, run_notional, |in_market, in_total| {
+            if let Some((limit, held, resting)) = market_exposure {
+                logs.push(format!(
+                    "Market exposure: ${:.2} held + ${:.2} resting + ${:.2} in flight + \
+                     ${:.2} planned (cap ${:.2})",
+                    held, resting, in_market, run_notional, limit
+                ));
+                check_cap(
+                    logs,
+                    "MAX_MARKET_EXPOSURE_USD",
+                    None,
+                    limit,
+                    held + resting + in_market + run_notional,
+                )?;
+            }
+            if let Some((limit, held, resting)) = total_exposure {
+                logs.push(format!(
+                    "Total exposure: ${:.2} held + ${:.2} resting + ${:.2} in flight + \
+                     ${:.2} planned (cap ${:.2})",
+                    held, resting, in_total, run_notional, limit
+                ));
+                check_cap(
+                    logs,
+                    "MAX_TOTAL_EXPOSURE_USD",
+                    None,
+                    limit,
+                    held + resting + in_total + run_notional,
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+/// Buy notional of bot runs that passed the caps and haven't finished, so
+/// runs for one wallet checked at the same time can't each fit under a cap
+/// that together they break.
+#[derive(Debug, Default)]
+pub struct InFlightExposure {
+    next_id: AtomicU64,
+    runs: Mutex<HashMap<u64, Reserved>>,
+}
+
+#[derive(Debug)]
+struct Reserved {
+    wallet: String,
+    market_id: String,
+    notional: f64,
+}
+
+impl InFlightExposure {
+    /// Runs `check` with the wallet's in-flight notional in `market_id` and
+    /// in total, and reserves `notional` if it passes.
+    fn reserve<F>(
+        self: &Arc<Self>,
+        wallet: &str,
+        market_id: &str,
+        notional: f64,
+        check: F,
+    ) -> Result<ExposureReservation>
+    where
+        F: FnOnce(f64, f64) -> Result<()>,
+    {
+        let mut runs = self.runs.lock().unwrap_or_else(|e| e.into_inner());
+        let wallet_runs = || runs.values().filter(|r| r.wallet == wallet);
+        let in_market = wallet_runs()
+            .filter(|r| r.market_id == market_id)
+            .map(|r| r.notional)
+            .sum();
+        let in_total = wallet_runs().map(|r| r.notional).sum();
+        check(in_market, in_total)?;
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        runs.insert(
+            id,
+            Reserved {
+                wallet: wallet.to_string(),
+                market_id: market_id.to_string(),
+                notional,
+            },
+        );
+        Ok(ExposureReservation {
+            held: Some((self.clone(), id)),
+        })
+    }
+}
+
+/// Releases a run's reserved notional from [`InFlightExposure`] when
+/// dropped. Empty when nothing was reserved.
+#[derive(Debug, Default)]
+#[must_use = "the reservation is released when dropped"]
+pub struct ExposureReservation {
+    held: Option<(Arc<InFlightExposure>, u64)>,
+}
+
+impl Drop for ExposureReservation {
+    fn drop(&mut self) {
+        if let Some((in_flight, id)) = self.held.take() {
+            in_flight
+                .runs
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .remove(&id);
+        }
+    }
+}
+
+/// What the open buy orders would spend if the rest of each filled.
+fn resting_notional(orders: Vec<ClobOrder>, cancelling: &[String]) -> f64 {
+    orders
+        .iter()
+        .filter(|o| o.side.eq_ignore_ascii_case("buy") && !cancelling.contains(&o.id))
+        .map(|o| o.price() * o.remaining_size())
+        .sum()
+}
+
+/// A cent of slack so a ladder sized exactly to the cap isn't refused over
+/// float rounding. `subject` names the order a per-order cap refused.
+fn check_cap(
+    logs: &mut BotLog,
+    cap: &str,
+    subject: Option<&str>,
+    limit: f64,
+    attempted: f64,
+) -> Result<()> {
+    if attempted > limit + 0.01 {
+        logs.event(BotLogEventKind::CapTriggered {
+            cap: cap.to_string(),
+            limit,
+            attempted,
+        });
+        let cap = match subject {
+            Some(subject) => format!("{} ({})", cap, subject),
+            None => cap.to_string(),
+        };
+        return Err(AppError::Validation(format!(
+            "Exposure cap exceeded: {} is ${:.2}, attempted ${:.2}",
+            cap, limit, attempted
+        )));
+    }
+    Ok(())
+}
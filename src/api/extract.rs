@@ -0,0 +1,163 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, FromRequestParts, Query, Request},
+    http::{request::Parts, Uri},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use url::form_urlencoded;
+
+use crate::types::{KnownFields, Validate};
+use crate::AppError;
+
+/// JSON body extractor for request types. Every failure is a 400
+/// `AppError::Validation`: unknown top-level keys (with a suggestion for
+/// likely typos), bodies that don't deserialize (naming the field and what it
+/// expected), and the request's own [`Validate`] checks.
+pub struct AppJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for AppJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + KnownFields + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(raw) = Json::<serde_json::Value>::from_request(req, state).await?;
+
+        if let Some(object) = raw.as_object() {
+            let keys: Vec<&str> = object.keys().map(String::as_str).collect();
+            check_known_fields(&keys, T::FIELDS)?;
+        }
+
+        let request: T = serde_path_to_error::deserialize(raw).map_err(invalid_body)?;
+        request.validate()?;
+        Ok(AppJson(request))
+    }
+}
+
+/// Query parameters read by their own extractors alongside [`AppQuery`],
+/// e.g. `?fresh=true` for [`crate::api::market_cache::CacheQuery`] and
+/// `?format=csv` for [`crate::api::csv_export::FormatQuery`].
+const SHARED_QUERY_PARAMS: &[&str] = &["fresh", "format"];
+
+/// [`AppJson`] for the query string of a GET variant, so a read-only
+/// endpoint takes the same request type either way and fails the same way:
+/// unknown parameters, values that don't parse and missing fields are a 400
+/// `AppError::Validation`, followed by the request's [`Validate`] checks.
+pub struct AppQuery<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequestParts<S> for AppQuery<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + KnownFields + Validate,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let query = parts.uri.query().unwrap_or_default();
+        let pairs: Vec<(String, String)> = form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect();
+        let keys: Vec<&str> = pairs
+            .iter()
+            .map(|(key, _)| key.as_str())
+            .filter(|key| !SHARED_QUERY_PARAMS.contains(key))
+            .collect();
+        check_known_fields(&keys, T::FIELDS)?;
+
+        // Shared parameters would trip the request type's unknown-field check
+        let own = form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(
+                pairs
+                    .iter()
+                    .filter(|(key, _)| T::FIELDS.contains(&key.as_str())),
+            )
+            .finish();
+        let uri: Uri = format!("/?{}", own)
+            .parse()
+            .map_err(|e| AppError::Validation(format!("Invalid query string: {}", e)))?;
+        let Query(request) = Query::<T>::try_from_uri(&uri)
+            .map_err(|rejection| AppError::Validation(rejection.body_text()))?;
+        request.validate()?;
+        Ok(AppQuery(request))
+    }
+}
+
+/// E.g. `Invalid request body at 'outcomes[0].weight': invalid type: string
+/// "half", expected f64`.
+fn invalid_body(err: serde_path_to_error::Error<serde_json::Error>) -> AppError {
+    let path = err.path().to_string();
+    if path == "." {
+        AppError::Validation(format!("Invalid request body: {}", err.inner()))
+    } else {
+        AppError::Validation(format!(
+            "Invalid request body at '{}': {}",
+            path,
+            err.inner()
+        ))
+    }
+}
+
+/// Rejects any key not in `known`, suggesting the closest known field for
+/// likely typos, e.g. `Unknown field 'bankrol_usd' (did you mean 'bankroll_usd'?)`.
+pub fn check_known_fields(provided: &[&str], known: &[&str]) -> crate::Result<()> {
+    let problems: Vec<String> = provided
+        .iter()
+        .filter(|key| !known.contains(key))
+        .map(|key| match suggest_field(key, known) {
+            Some(suggestion) => format!("'{}' (did you mean '{}'?)", key, suggestion),
+            None => format!("'{}'", key),
+        })
+        .collect();
+
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    Err(AppError::Validation(format!(
+        "Unknown field{} {}; valid fields: {}",
+        if problems.len() == 1 { "" } else { "s" },
+        problems.join(", "),
+        known.join(", ")
+    )))
+}
+
+/// Closest known field within an edit distance of 2 (1 for short keys), or
+/// an exact case-insensitive match.
+pub fn suggest_field<'a>(key: &str, known: &[&'a str]) -> Option<&'a str> {
+    let key_lower = key.to_ascii_lowercase();
+    let max_distance = if key.len() <= 4 { 1 } else { 2 };
+
+    known
+        .iter()
+        .map(|field| {
+            (
+                *field,
+                edit_distance(&key_lower, &field.to_ascii_lowercase()),
+            )
+        })
+        .filter(|(_, distance)| *distance <= max_distance)
+        .min_by_key(|(_, distance)| *distance)
+        .map(|(field, _)| field)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
@@ -0,0 +1,244 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::{BTreeMap, HashMap};
+use utoipa::openapi::schema::{ArrayItems, Schema, SchemaType, Type};
+use utoipa::openapi::RefOr;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::{AppError, Result};
+
+/// Top-level field that is always returned, when the response has one,
+/// regardless of the selection.
+const ALWAYS_INCLUDED: &str = "metadata";
+
+/// `?fields=positions,pair_status,market.slug` query parameter.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct FieldSelection {
+    /// Comma-separated fields to return, e.g. `positions,market.slug`
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct FieldTree {
+    children: BTreeMap<String, FieldTree>,
+}
+
+impl FieldTree {
+    fn insert(&mut self, path: &[&str]) {
+        let Some((head, rest)) = path.split_first() else {
+            return;
+        };
+        let child = self.children.entry(head.to_string()).or_default();
+        if rest.is_empty() {
+            // Selecting a field selects everything below it.
+            child.children.clear();
+            child.children.insert(String::new(), FieldTree::default());
+        } else if !child.selects_all() {
+            child.insert(rest);
+        }
+    }
+
+    fn selects_all(&self) -> bool {
+        self.children.contains_key("")
+    }
+}
+
+/// Prunes a serialized `T` down to the comma-separated dotted `fields`.
+///
+/// Paths descend through arrays element-wise, so `positions.shares` keeps only
+/// `shares` on every position. An empty selection returns the value unchanged.
+/// Paths are checked against `T`'s schema rather than the value, so a field
+/// this response happens to leave out is simply missing from the result.
+/// Unknown paths are rejected with a validation error listing the valid fields
+/// at the level where resolution failed.
+pub fn select_fields<T: ToSchema>(value: Value, fields: &str) -> Result<Value> {
+    let mut tree = FieldTree::default();
+    for path in fields.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let segments: Vec<&str> = path.split('.').collect();
+        if segments.iter().any(|s| s.is_empty()) {
+            return Err(AppError::Validation(format!(
+                "Invalid field path: {}",
+                path
+            )));
+        }
+        tree.insert(&segments);
+    }
+
+    if tree.children.is_empty() {
+        return Ok(value);
+    }
+
+    let schemas = Schemas::of::<T>();
+    schemas.validate(&tree, &T::schema(), "")?;
+
+    if !tree.children.contains_key(ALWAYS_INCLUDED) && value.get(ALWAYS_INCLUDED).is_some() {
+        tree.insert(&[ALWAYS_INCLUDED]);
+    }
+
+    Ok(prune(value, &tree))
+}
+
+/// Serializes `response`, pruned to `fields` when a selection is given.
+pub fn select_response<T: Serialize + ToSchema>(
+    response: &T,
+    fields: Option<&str>,
+) -> Result<Value> {
+    let value = serde_json::to_value(response)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to serialize response: {}", e)))?;
+    match fields {
+        Some(fields) => select_fields::<T>(value, fields),
+        None => Ok(value),
+    }
+}
+
+/// What a selection may name below a point in a response type.
+enum Shape<'a> {
+    Fields(BTreeMap<&'a str, &'a RefOr<Schema>>),
+    /// A string, number or enum, with nothing below it
+    Scalar,
+    /// A map or free-form value, whose keys aren't known up front
+    Any,
+}
+
+/// A response type's schema and the component schemas it refers to.
+struct Schemas {
+    components: HashMap<String, RefOr<Schema>>,
+}
+
+impl Schemas {
+    fn of<T: ToSchema>() -> Self {
+        let mut components = Vec::new();
+        T::schemas(&mut components);
+        Self {
+            components: components.into_iter().collect(),
+        }
+    }
+
+    fn validate(&self, tree: &FieldTree, schema: &RefOr<Schema>, prefix: &str) -> Result<()> {
+        if tree.selects_all() {
+            return Ok(());
+        }
+
+        let fields = match self.shape(schema) {
+            Shape::Any => return Ok(()),
+            Shape::Scalar => BTreeMap::new(),
+            Shape::Fields(fields) => fields,
+        };
+        for (key, subtree) in &tree.children {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            match fields.get(key.as_str()) {
+                Some(child) => self.validate(subtree, child, &path)?,
+                None if fields.is_empty() => {
+                    return Err(AppError::Validation(format!(
+                        "Unknown field '{}'; '{}' has no fields",
+                        path, prefix
+                    )));
+                }
+                None => {
+                    return Err(AppError::Validation(format!(
+                        "Unknown field '{}'; valid fields{} are: {}",
+                        path,
+                        if prefix.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" under '{}'", prefix)
+                        },
+                        fields.keys().copied().collect::<Vec<_>>().join(", ")
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Arrays are looked through, and the branches of `Option`s, flattened
+    /// fields and enums are merged.
+    fn shape<'a>(&'a self, schema: &'a RefOr<Schema>) -> Shape<'a> {
+        let schema = match schema {
+            RefOr::Ref(reference) => {
+                let name = reference
+                    .ref_location
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default();
+                match self.components.get(name) {
+                    Some(component) => return self.shape(component),
+                    None => return Shape::Any,
+                }
+            }
+            RefOr::T(schema) => schema,
+        };
+
+        let branches = match schema {
+            Schema::Object(object) if !object.properties.is_empty() => {
+                let fields = object
+                    .properties
+                    .iter()
+                    .map(|(name, field)| (name.as_str(), field))
+                    .collect();
+                return Shape::Fields(fields);
+            }
+            Schema::Object(object) => {
+                let free_form = object.additional_properties.is_some()
+                    || matches!(
+                        object.schema_type,
+                        SchemaType::AnyValue | SchemaType::Type(Type::Object)
+                    );
+                return if free_form { Shape::Any } else { Shape::Scalar };
+            }
+            Schema::Array(array) => {
+                return match &array.items {
+                    ArrayItems::RefOrSchema(items) => self.shape(items),
+                    _ => Shape::Scalar,
+                };
+            }
+            Schema::OneOf(one_of) => &one_of.items,
+            Schema::AllOf(all_of) => &all_of.items,
+            Schema::AnyOf(any_of) => &any_of.items,
+            _ => return Shape::Any,
+        };
+
+        let mut merged = BTreeMap::new();
+        for branch in branches {
+            match self.shape(branch) {
+                Shape::Any => return Shape::Any,
+                Shape::Scalar => {}
+                Shape::Fields(fields) => merged.extend(fields),
+            }
+        }
+        if merged.is_empty() {
+            Shape::Scalar
+        } else {
+            Shape::Fields(merged)
+        }
+    }
+}
+
+fn prune(value: Value, tree: &FieldTree) -> Value {
+    if tree.selects_all() {
+        return value;
+    }
+
+    match value {
+        Value::Object(mut object) => {
+            let mut pruned = Map::new();
+            for (key, subtree) in &tree.children {
+                // Fields this response leaves out stay out
+                if let Some(child) = object.remove(key) {
+                    pruned.insert(key.clone(), prune(child, subtree));
+                }
+            }
+            Value::Object(pruned)
+        }
+        Value::Array(items) => {
+            Value::Array(items.into_iter().map(|item| prune(item, tree)).collect())
+        }
+        // Nested selection on a null/scalar (e.g. an absent optional) keeps the value.
+        other => other,
+    }
+}
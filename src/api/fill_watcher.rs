@@ -0,0 +1,108 @@
+use chrono::Utc;
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::AppState;
+use crate::clients::clob_signing::WalletAuth;
+use crate::clients::webhook::EventKind;
+use crate::types::{OrderResult, OrderStatus};
+
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 5;
+/// Two 15-minute market cycles.
+pub const DEFAULT_WATCH_SECS: u64 = 1800;
+
+/// Body POSTed to a bot request's `webhook_url` when one of its orders
+/// fills or is cancelled.
+#[derive(Debug, Serialize)]
+struct OrderEvent<'a> {
+    event: EventKind,
+    event_id: String,
+    created_at: String,
+    market_slug: Option<&'a str>,
+    run_id: Option<&'a str>,
+    order: OrderResult,
+}
+
+/// Polls the placed orders in the background and notifies `webhook_url` as
+/// each fills or is cancelled. Gives up after `WEBHOOK_WATCH_SECS`; failures
+/// are only logged.
+pub(crate) fn spawn_fill_watcher(
+    state: &Arc<AppState>,
+    auth: &WalletAuth,
+    webhook_url: &str,
+    market_slug: Option<&str>,
+    run_id: Option<&str>,
+    orders: &[OrderResult],
+) {
+    let mut watching: Vec<String> = orders
+        .iter()
+        .filter(|o| !matches!(o.status, OrderStatus::Failed | OrderStatus::Simulated))
+        .filter_map(|o| o.order_id.clone())
+        .collect();
+    if watching.is_empty() {
+        return;
+    }
+
+    let poll_interval = state.config.webhook_poll_interval;
+    let watch_for = state.config.webhook_watch_duration;
+    let state = state.clone();
+    let auth = auth.clone();
+    let webhook_url = webhook_url.to_string();
+    let market_slug = market_slug.map(str::to_string);
+    let run_id = run_id.map(str::to_string);
+
+    tokio::spawn(async move {
+        let deadline = Instant::now() + watch_for;
+        let mut interval = tokio::time::interval(poll_interval);
+        while !watching.is_empty() && Instant::now() < deadline {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = state.shutdown.cancelled() => return,
+            }
+
+            let mut still_open = Vec::with_capacity(watching.len());
+            for order_id in watching {
+                let order = match state.polymarket_client.get_order(&auth, &order_id).await {
+                    Ok(Some(order)) => order,
+                    // Not indexed yet, or a transient failure: check again next tick
+                    Ok(None) => {
+                        still_open.push(order_id);
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::debug!("Fill watcher lookup of {} failed: {}", order_id, e);
+                        still_open.push(order_id);
+                        continue;
+                    }
+                };
+
+                let event = match order.status() {
+                    OrderStatus::Filled => EventKind::OrderFilled,
+                    OrderStatus::Cancelled => EventKind::OrderCancelled,
+                    _ => {
+                        still_open.push(order_id);
+                        continue;
+                    }
+                };
+                let order = match order.into_order_result() {
+                    Ok(order) => order,
+                    Err(e) => {
+                        tracing::warn!("Fill watcher dropped order {}: {}", order_id, e);
+                        continue;
+                    }
+                };
+                let payload = OrderEvent {
+                    event,
+                    event_id: format!("evt_{}", uuid::Uuid::new_v4().simple()),
+                    created_at: Utc::now().to_rfc3339(),
+                    market_slug: market_slug.as_deref(),
+                    run_id: run_id.as_deref(),
+                    order,
+                };
+                state.webhooks.deliver(&webhook_url, &payload).await;
+            }
+            watching = still_open;
+        }
+    });
+}
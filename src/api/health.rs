@@ -0,0 +1,240 @@
+use axum::{extract::State, http::StatusCode, Json};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::task::JoinSet;
+
+use crate::api::AppState;
+use crate::clients::{ai, AiProvider, AiRequestOptions, CircuitSnapshot, CircuitState};
+use crate::AppError;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 5;
+pub const DEFAULT_CACHE_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyStatus {
+    Ok,
+    /// Reachable, but rate limiting us or slower than half the timeout
+    Degraded,
+    /// Unreachable, timed out or rejecting our credentials
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DependencyCheck {
+    pub name: &'static str,
+    pub status: DependencyStatus,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepHealthResponse {
+    /// The worst status among the dependencies
+    pub status: DependencyStatus,
+    pub checked_at: DateTime<Utc>,
+    /// Served from a check made within `HEALTH_CHECK_CACHE_SECS`
+    pub cached: bool,
+    pub dependencies: Vec<DependencyCheck>,
+    /// Circuit breakers as they are now, never cached. One that isn't
+    /// closed makes the status at least degraded.
+    pub circuits: Vec<CircuitSnapshot>,
+}
+
+impl DeepHealthResponse {
+    fn with_circuits(mut self, circuits: Vec<CircuitSnapshot>) -> Self {
+        if circuits
+            .iter()
+            .any(|circuit| circuit.state != CircuitState::Closed)
+        {
+            self.status = self.status.max(DependencyStatus::Degraded);
+        }
+        self.circuits = circuits;
+        self
+    }
+}
+
+/// Upstream connectivity checks behind `GET /health/deep`. Each configured
+/// upstream gets one cheap request, run concurrently and bounded by
+/// `HEALTH_CHECK_TIMEOUT_SECS`; the result is reused for
+/// `HEALTH_CHECK_CACHE_SECS` so frequent probes don't reach the upstreams.
+#[derive(Debug)]
+pub struct DeepHealth {
+    timeout: Duration,
+    ttl: Duration,
+    /// Held while checking, so concurrent probes share one round of checks
+    last: Mutex<Option<(Instant, DeepHealthResponse)>>,
+}
+
+impl DeepHealth {
+    pub fn new(timeout: Duration, ttl: Duration) -> Self {
+        Self {
+            timeout,
+            ttl,
+            last: Mutex::new(None),
+        }
+    }
+
+    async fn report(&self, state: &Arc<AppState>) -> DeepHealthResponse {
+        let mut last = self.last.lock().await;
+        if let Some((at, report)) = last.as_ref() {
+            if at.elapsed() < self.ttl {
+                return DeepHealthResponse {
+                    cached: true,
+                    ..report.clone()
+                };
+            }
+        }
+
+        let report = self.check_all(state).await;
+        *last = Some((Instant::now(), report.clone()));
+        report
+    }
+
+    async fn check_all(&self, state: &Arc<AppState>) -> DeepHealthResponse {
+        let mut checks = JoinSet::new();
+        let mut spawn = |name: &'static str, check: CheckFuture| {
+            checks.spawn(run_check(name, self.timeout, check));
+        };
+
+        let polymarket = state.polymarket_client.clone();
+        spawn("gamma", Box::pin(async move { polymarket.ping().await }));
+        if let Some(dome) = state.dome_client.clone() {
+            spawn("dome", Box::pin(async move { dome.ping().await }));
+        }
+        if let Some(kalshi) = state.kalshi_client.clone() {
+            spawn("kalshi", Box::pin(async move { kalshi.ping().await }));
+        }
+        for (name, provider, configured) in [
+            ("openai", AiProvider::OpenAi, state.capabilities.openai),
+            ("grok", AiProvider::Grok, state.capabilities.grok),
+            (
+                "anthropic",
+                AiProvider::Claude,
+                state.capabilities.anthropic,
+            ),
+        ] {
+            if !configured {
+                continue;
+            }
+            let state = state.clone();
+            spawn(
+                name,
+                Box::pin(async move {
+                    state
+                        .ai_client(provider, &AiRequestOptions::default())?
+                        .ping()
+                        .await
+                }),
+            );
+        }
+        // Polyfactual has no cheap endpoint; a research run is too costly to
+        // use as a probe
+
+        let mut dependencies = Vec::new();
+        while let Some(joined) = checks.join_next().await {
+            match joined {
+                Ok(check) => dependencies.push(check),
+                Err(e) => tracing::error!("Health check task failed: {}", e),
+            }
+        }
+        dependencies.sort_by_key(|check| check.name);
+
+        DeepHealthResponse {
+            status: dependencies
+                .iter()
+                .map(|check| check.status)
+                .max()
+                .unwrap_or(DependencyStatus::Ok),
+            checked_at: Utc::now(),
+            cached: false,
+            dependencies,
+            circuits: Vec::new(),
+        }
+    }
+}
+
+/// Breakers of the configured upstreams that have one.
+fn circuits(state: &AppState) -> Vec<CircuitSnapshot> {
+    let mut circuits: Vec<CircuitSnapshot> = [
+        state.polymarket_client.gamma_circuit(),
+        state.dome_client.as_ref().and_then(|dome| dome.circuit()),
+        state
+            .polyfactual_client
+            .as_ref()
+            .and_then(|polyfactual| polyfactual.circuit()),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    for (provider, configured) in [
+        (AiProvider::OpenAi, state.capabilities.openai),
+        (AiProvider::Grok, state.capabilities.grok),
+        (AiProvider::Claude, state.capabilities.anthropic),
+    ] {
+        if configured {
+            circuits.push(ai::circuit_breaker(&provider).snapshot());
+        }
+    }
+    circuits.sort_by_key(|circuit| circuit.name);
+    circuits
+}
+
+type CheckFuture = std::pin::Pin<Box<dyn Future<Output = crate::Result<()>> + Send>>;
+
+async fn run_check(name: &'static str, timeout: Duration, check: CheckFuture) -> DependencyCheck {
+    let started = Instant::now();
+    let outcome = tokio::time::timeout(timeout, check).await;
+    let latency = started.elapsed();
+
+    let (status, error) = match outcome {
+        Ok(Ok(())) if latency > timeout / 2 => (DependencyStatus::Degraded, None),
+        Ok(Ok(())) => (DependencyStatus::Ok, None),
+        Ok(Err(e @ AppError::RateLimit { .. })) => {
+            (DependencyStatus::Degraded, Some(e.to_string()))
+        }
+        Ok(Err(e)) => (DependencyStatus::Down, Some(e.to_string())),
+        Err(_) => (
+            DependencyStatus::Down,
+            Some(format!("No response within {}s", timeout.as_secs())),
+        ),
+    };
+    if status != DependencyStatus::Ok {
+        tracing::warn!(
+            "Health check: {} is {:?}: {}",
+            name,
+            status,
+            error.as_deref().unwrap_or("slow response")
+        );
+    }
+
+    DependencyCheck {
+        name,
+        status,
+        latency_ms: latency.as_millis() as u64,
+        error,
+    }
+}
+
+/// Reports each configured upstream as ok, degraded or down. Answers 503
+/// when any is down, 200 otherwise.
+pub async fn deep_handler(
+    State(state): State<Arc<AppState>>,
+) -> (StatusCode, Json<DeepHealthResponse>) {
+    let report = state
+        .deep_health
+        .report(&state)
+        .await
+        .with_circuits(circuits(&state));
+    let code = if report.status == DependencyStatus::Down {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+    (code, Json(report))
+}
@@ -0,0 +1,112 @@
+use alloy_primitives::hex;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::AppError;
+
+/// A read-only response browsers and CDNs may reuse: sent with a weak
+/// `ETag` over the payload and `Cache-Control: private, max-age=<ttl>`.
+/// The tag leaves out `metadata`, which changes on every request (timestamp,
+/// latency, request id) without the data changing. [`not_modified`] answers
+/// a matching `If-None-Match` with 304.
+pub struct Cacheable<T> {
+    pub body: T,
+    /// Usually the TTL of the server-side cache the data came through
+    pub max_age: Duration,
+}
+
+impl<T> Cacheable<T> {
+    pub fn new(body: T, max_age: Duration) -> Self {
+        Self { body, max_age }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Cacheable<T> {
+    fn into_response(self) -> Response {
+        let etag = match serde_json::to_value(&self.body) {
+            Ok(value) => weak_etag(value),
+            Err(e) => {
+                return AppError::Internal(anyhow::anyhow!("Failed to serialize response: {}", e))
+                    .into_response()
+            }
+        };
+
+        let mut response = Json(self.body).into_response();
+        let headers = response.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            headers.insert(header::ETAG, value);
+        }
+        if let Ok(value) =
+            HeaderValue::from_str(&format!("private, max-age={}", self.max_age.as_secs()))
+        {
+            headers.insert(header::CACHE_CONTROL, value);
+        }
+        response
+    }
+}
+
+/// `W/"<hash>"` of the payload without its `metadata`.
+fn weak_etag(mut value: serde_json::Value) -> String {
+    if let Some(object) = value.as_object_mut() {
+        object.remove("metadata");
+    }
+    let digest = Sha256::digest(value.to_string().as_bytes());
+    format!("W/\"{}\"", hex::encode(&digest[..16]))
+}
+
+/// Whether the `If-None-Match` values list `etag` (or `*`). Weak
+/// comparison: `W/` prefixes are ignored on both sides.
+fn matches(if_none_match: &[HeaderValue], etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let etag = opaque(etag);
+    if_none_match
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+/// Turns a successful GET or HEAD whose [`Cacheable`] `ETag` the client
+/// already holds into a bodiless 304, keeping the `ETag` and
+/// `Cache-Control` headers. Responses without an `ETag` pass through.
+pub async fn not_modified(request: Request, next: Next) -> Response {
+    let if_none_match: Vec<HeaderValue> = match *request.method() {
+        Method::GET | Method::HEAD => request
+            .headers()
+            .get_all(header::IF_NONE_MATCH)
+            .iter()
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    let response = next.run(request).await;
+    if if_none_match.is_empty() || response.status() != StatusCode::OK {
+        return response;
+    }
+    let Some(etag) = response.headers().get(header::ETAG) else {
+        return response;
+    };
+    if !etag
+        .to_str()
+        .is_ok_and(|etag| matches(&if_none_match, etag))
+    {
+        return response;
+    }
+
+    let mut not_modified = StatusCode::NOT_MODIFIED.into_response();
+    for name in [header::ETAG, header::CACHE_CONTROL] {
+        if let Some(value) = response.headers().get(&name) {
+            not_modified.headers_mut().insert(name, value.clone());
+        }
+    }
+    not_modified
+}
@@ -0,0 +1,188 @@
+use axum::http::HeaderMap;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::api::limit_order_bot::wallet_auth;
+use crate::clients::clob_signing::ClobSigner;
+use crate::types::{LimitOrderBotRequest, LimitOrderBotResponse};
+use crate::{AppError, Result};
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// Two 15-minute market cycles.
+pub const DEFAULT_TTL_SECS: u64 = 1800;
+const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug)]
+enum Entry {
+    InFlight {
+        started_at: Instant,
+    },
+    Completed {
+        response: Box<LimitOrderBotResponse>,
+        completed_at: Instant,
+    },
+    /// Orders were sent but the run failed before producing a response, so
+    /// replaying it could double the exposure.
+    Interrupted {
+        failed_at: Instant,
+    },
+}
+
+impl Entry {
+    fn since(&self) -> Instant {
+        match self {
+            Entry::InFlight { started_at } => *started_at,
+            Entry::Completed { completed_at, .. } => *completed_at,
+            Entry::Interrupted { failed_at } => *failed_at,
+        }
+    }
+}
+
+/// Result of claiming a key.
+#[derive(Debug)]
+pub enum Claim<'a> {
+    /// First use of the key: run the request and complete the guard.
+    New(InFlightGuard<'a>),
+    /// The key already completed; return its response unchanged.
+    Replay(Box<LimitOrderBotResponse>),
+}
+
+/// Limit order bot responses by idempotency key, so a retried request gets
+/// the first run's result instead of placing its orders again.
+#[derive(Debug)]
+pub struct IdempotencyStore {
+    entries: Mutex<HashMap<String, Entry>>,
+    ttl: Duration,
+}
+
+impl IdempotencyStore {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    /// Claims `key` at `now`. Keys still in flight, or whose run placed
+    /// orders and then failed, are a conflict until they expire.
+    pub fn claim(&self, key: String, now: Instant) -> Result<Claim<'_>> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.retain(|_, entry| now.saturating_duration_since(entry.since()) < self.ttl);
+
+        match entries.get(&key) {
+            Some(Entry::InFlight { .. }) => Err(AppError::Conflict(
+                "A request with this idempotency key is still in progress; retry once it completes"
+                    .to_string(),
+            )),
+            Some(Entry::Interrupted { .. }) => Err(AppError::Conflict(
+                "A request with this idempotency key placed orders but failed before completing; \
+                 check GET /api/orders before retrying with a new key"
+                    .to_string(),
+            )),
+            Some(Entry::Completed { response, .. }) => Ok(Claim::Replay(response.clone())),
+            None => {
+                entries.insert(key.clone(), Entry::InFlight { started_at: now });
+                Ok(Claim::New(InFlightGuard {
+                    store: self,
+                    key,
+                    orders_sent: false,
+                    completed: false,
+                }))
+            }
+        }
+    }
+
+    fn finish(&self, key: &str, entry: Option<Entry>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        match entry {
+            Some(entry) => entries.insert(key.to_string(), entry),
+            None => entries.remove(key),
+        };
+    }
+}
+
+/// Holds a claimed key for the length of a run. Dropped without completing,
+/// the key is released for a retry, unless orders had already been sent.
+#[derive(Debug)]
+pub struct InFlightGuard<'a> {
+    store: &'a IdempotencyStore,
+    key: String,
+    orders_sent: bool,
+    completed: bool,
+}
+
+impl InFlightGuard<'_> {
+    /// From here on a failed run must not be retried under the same key.
+    pub fn mark_orders_sent(&mut self) {
+        self.orders_sent = true;
+    }
+
+    pub fn complete(mut self, response: &LimitOrderBotResponse) {
+        self.store.finish(
+            &self.key,
+            Some(Entry::Completed {
+                response: Box::new(response.clone()),
+                completed_at: Instant::now(),
+            }),
+        );
+        self.completed = true;
+    }
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        if self.completed {
+            return;
+        }
+        let entry = self.orders_sent.then(|| Entry::Interrupted {
+            failed_at: Instant::now(),
+        });
+        self.store.finish(&self.key, entry);
+    }
+}
+
+/// The request's idempotency key from the `Idempotency-Key` header or the
+/// `idempotency_key` field, scoped to the signing wallet and to dry runs so
+/// neither can replay the other's response.
+pub fn idempotency_key(
+    headers: &HeaderMap,
+    request: &LimitOrderBotRequest,
+    server_wallet: Option<&ClobSigner>,
+) -> Result<Option<String>> {
+    let header = headers
+        .get(IDEMPOTENCY_KEY_HEADER)
+        .map(|v| {
+            v.to_str().map_err(|_| {
+                AppError::Validation("Idempotency-Key header must be visible ASCII".to_string())
+            })
+        })
+        .transpose()?;
+
+    let key = match (header, request.idempotency_key.as_deref()) {
+        (Some(header), Some(field)) if header != field => {
+            return Err(AppError::Validation(
+                "Idempotency-Key header and idempotency_key field differ".to_string(),
+            ))
+        }
+        (Some(key), _) | (None, Some(key)) => key.trim(),
+        (None, None) => return Ok(None),
+    };
+    if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+        return Err(AppError::Validation(format!(
+            "Idempotency key must be 1-{} characters",
+            MAX_KEY_LENGTH
+        )));
+    }
+
+    Ok(Some(format!(
+        "{}:{}:{}",
+        wallet_auth(request, server_wallet)?.fingerprint(),
+        if request.dry_run.unwrap_or(false) {
+            "dry"
+        } else {
+            "live"
+        },
+        key
+    )))
+}
@@ -0,0 +1,267 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+use crate::api::extract::AppJson;
+use crate::api::market_cache::CacheQuery;
+use crate::api::AppState;
+use crate::api::{analyze_event_markets, polyfactual_research};
+use crate::error::ResponseVersion;
+use crate::request_id;
+use crate::types::{AnalyzeEventMarketsRequest, PolyfactualResearchRequest};
+use crate::{AppError, Result};
+
+pub const DEFAULT_WORKERS: usize = 4;
+pub const DEFAULT_MAX_QUEUED: usize = 100;
+pub const DEFAULT_RETENTION_SECS: u64 = 3600;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Research,
+    Analyze,
+}
+
+/// A submitted request and, once it finishes, its outcome.
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: String,
+    pub kind: JobKind,
+    pub status: JobStatus,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// The body the blocking endpoint would have returned
+    pub result: Option<Value>,
+    /// The error body the blocking endpoint would have returned
+    pub error: Option<Value>,
+    /// The submitting request's id, which the job's logs carry too
+    pub request_id: Option<String>,
+    #[serde(skip)]
+    finished: Option<Instant>,
+}
+
+enum JobRequest {
+    Research(PolyfactualResearchRequest),
+    Analyze(Box<AnalyzeEventMarketsRequest>),
+}
+
+/// Research and analysis requests run in the background, so clients can
+/// poll for a result instead of holding a connection open for minutes.
+///
+/// At most `workers` jobs run at once; the rest wait as `queued`, up to
+/// `max_queued`. Finished jobs are forgotten after `retention`.
+#[derive(Debug)]
+pub struct JobQueue {
+    jobs: Mutex<HashMap<String, Job>>,
+    workers: Arc<Semaphore>,
+    max_queued: usize,
+    retention: Duration,
+}
+
+impl JobQueue {
+    pub fn new(workers: usize, max_queued: usize, retention: Duration) -> Self {
+        Self {
+            jobs: Mutex::new(HashMap::new()),
+            workers: Arc::new(Semaphore::new(workers)),
+            max_queued,
+            retention,
+        }
+    }
+
+    /// A copy of the job, unless it is unknown or has expired.
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.lock();
+        self.expire(&mut jobs, Instant::now());
+        jobs.get(id).cloned()
+    }
+
+    /// Registers a queued job, or refuses it when the queue is full.
+    fn enqueue(&self, kind: JobKind) -> Result<Job> {
+        let mut jobs = self.lock();
+        self.expire(&mut jobs, Instant::now());
+        let queued = jobs
+            .values()
+            .filter(|job| job.status == JobStatus::Queued)
+            .count();
+        if queued >= self.max_queued {
+            tracing::warn!("Job queue is full ({} queued); refusing a new job", queued);
+            return Err(AppError::RateLimit {
+                retry_after: None,
+                upstream: None,
+            });
+        }
+
+        let job = Job {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            status: JobStatus::Queued,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+            request_id: request_id::current(),
+            finished: None,
+        };
+        jobs.insert(job.id.clone(), job.clone());
+        Ok(job)
+    }
+
+    fn start(&self, id: &str) {
+        if let Some(job) = self.lock().get_mut(id) {
+            job.status = JobStatus::Running;
+            job.started_at = Some(Utc::now());
+        }
+    }
+
+    fn finish(&self, id: &str, outcome: std::result::Result<Value, Value>) {
+        if let Some(job) = self.lock().get_mut(id) {
+            job.finished_at = Some(Utc::now());
+            job.finished = Some(Instant::now());
+            match outcome {
+                Ok(result) => {
+                    job.status = JobStatus::Succeeded;
+                    job.result = Some(result);
+                }
+                Err(error) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(error);
+                }
+            }
+        }
+    }
+
+    fn expire(&self, jobs: &mut HashMap<String, Job>, now: Instant) {
+        jobs.retain(|_, job| {
+            job.finished
+                .is_none_or(|finished| now.saturating_duration_since(finished) < self.retention)
+        });
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Queues a Polyfactual research query (the body of
+/// `POST /api/polyfactual-research`).
+pub async fn submit_research(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<PolyfactualResearchRequest>,
+) -> Result<impl IntoResponse> {
+    state.polyfactual()?;
+    submit(state, JobKind::Research, JobRequest::Research(request))
+}
+
+/// Queues a market analysis (the body of `POST /api/analyze-event-markets`).
+pub async fn submit_analyze(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<AnalyzeEventMarketsRequest>,
+) -> Result<impl IntoResponse> {
+    state.dome()?;
+    submit(state, JobKind::Analyze, JobRequest::Analyze(Box::new(request)))
+}
+
+/// A job's status, and its result or error once finished.
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<Json<Job>> {
+    state
+        .jobs
+        .get(&id)
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("Job {} not found or expired", id)))
+}
+
+fn submit(state: Arc<AppState>, kind: JobKind, request: JobRequest) -> Result<impl IntoResponse> {
+    let job = state.jobs.enqueue(kind)?;
+    let location = format!("/api/jobs/{}", job.id);
+
+    let id = job.id.clone();
+    // The job's error, if any, is laid out the way the submitter asked
+    let run = ResponseVersion::current().scope(run_job(state, id, request));
+    match request_id::current() {
+        Some(request_id) => tokio::spawn(request_id::scope(request_id, run)),
+        None => tokio::spawn(run),
+    };
+
+    Ok((
+        StatusCode::ACCEPTED,
+        [(header::LOCATION, location)],
+        Json(job),
+    ))
+}
+
+/// Waits for a worker, then runs the request. Jobs still queued when
+/// shutdown starts fail; running ones are waited for like requests.
+async fn run_job(state: Arc<AppState>, id: String, request: JobRequest) {
+    let jobs = &state.jobs;
+    let _permit = tokio::select! {
+        permit = jobs.workers.clone().acquire_owned() => permit.expect("the semaphore is never closed"),
+        _ = state.shutdown.cancelled() => {
+            let error = AppError::Internal(anyhow::anyhow!("The server shut down before the job started"));
+            jobs.finish(&id, Err(error.into_json().await));
+            return;
+        }
+    };
+    let _guard = state.in_flight.track(format!("job {}", id));
+    jobs.start(&id);
+
+    let outcome = match execute(&state, request).await {
+        Ok(result) => Ok(result),
+        Err(e) => {
+            tracing::warn!("Job {} failed: {}", id, e);
+            Err(e.into_json().await)
+        }
+    };
+    jobs.finish(&id, outcome);
+}
+
+async fn execute(state: &Arc<AppState>, request: JobRequest) -> Result<Value> {
+    let result = match request {
+        JobRequest::Research(request) => {
+            let fresh = request.force_refresh.unwrap_or(false);
+            let mut response = polyfactual_research::research(
+                state,
+                state.polyfactual()?,
+                request.query,
+                fresh,
+                request.timeout_secs.map(Duration::from_secs),
+            )
+            .await?;
+            response.retain_relevant(request.min_relevance);
+            serde_json::to_value(response)
+        }
+        JobRequest::Analyze(request) => {
+            let Json(response) = analyze_event_markets::handler(
+                State(state.clone()),
+                Query(CacheQuery::default()),
+                AppJson(*request),
+            )
+            .await?;
+            serde_json::to_value(response)
+        }
+    };
+    result.map_err(|e| AppError::Internal(e.into()))
+}
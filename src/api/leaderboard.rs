@@ -0,0 +1,202 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::wallet_snapshots::WalletSnapshot;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{
+    ExcludedWallet, LeaderboardEntry, LeaderboardResponse, PnlPoint, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+const DEFAULT_PERIOD: &str = "7d";
+const MAX_PERIOD_DAYS: i64 = 90;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LeaderboardQuery {
+    pub period: Option<String>,
+}
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<LeaderboardQuery>,
+) -> Result<Json<LeaderboardResponse>> {
+    let start = Instant::now();
+
+    let period = query.period.unwrap_or_else(|| DEFAULT_PERIOD.to_string());
+    let length = parse_period(&period)
+        .filter(|d| *d <= Duration::days(MAX_PERIOD_DAYS))
+        .ok_or_else(|| {
+            AppError::Validation(format!(
+                "Invalid period '{}'; use e.g. 24h, 7d or 30d (max {}d)",
+                period, MAX_PERIOD_DAYS
+            ))
+        })?;
+
+    let now = Utc::now();
+    let period_start = now - length;
+    let labels: Vec<String> = state
+        .tracked_wallets
+        .iter()
+        .map(|w| w.label.clone())
+        .collect();
+
+    let (entries, excluded) = build_leaderboard(
+        &state.wallet_snapshots.history(),
+        &labels,
+        period_start,
+        now,
+    );
+
+    Ok(Json(LeaderboardResponse {
+        period,
+        period_start: period_start.to_rfc3339(),
+        entries,
+        excluded,
+        metadata: ResponseMetadata {
+            timestamp: now.to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            // Without persistence, history only covers time since the last restart
+            degraded_features: if state.capabilities.persistence {
+                Vec::new()
+            } else {
+                vec!["persistence".to_string()]
+            },
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+/// Parses a leaderboard period such as `7d` or `24h`.
+pub fn parse_period(period: &str) -> Option<Duration> {
+    let period = period.trim();
+    let (amount, unit) = period.split_at(period.len().checked_sub(1)?);
+    let amount: i64 = amount.parse().ok().filter(|n| *n > 0)?;
+    match unit {
+        "d" => Some(Duration::days(amount)),
+        "h" => Some(Duration::hours(amount)),
+        _ => None,
+    }
+}
+
+/// Ranks `labels` by P&L change between `period_start` and `now`.
+///
+/// The baseline is the last snapshot at or before `period_start`; wallets
+/// whose history starts inside the period use their first snapshot instead.
+/// Wallets without snapshots, or with no P&L or volume change, are excluded
+/// with a reason.
+pub fn build_leaderboard(
+    history: &HashMap<String, Vec<WalletSnapshot>>,
+    labels: &[String],
+    period_start: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> (Vec<LeaderboardEntry>, Vec<ExcludedWallet>) {
+    let mut entries = Vec::new();
+    let mut excluded = Vec::new();
+
+    for label in labels {
+        let snapshots: &[WalletSnapshot] = history.get(label).map(Vec::as_slice).unwrap_or(&[]);
+        let snapshots: Vec<&WalletSnapshot> =
+            snapshots.iter().filter(|s| s.taken_at <= now).collect();
+
+        let prior = snapshots.iter().rev().find(|s| s.taken_at <= period_start);
+        let (baseline, joined_mid_period) = match prior {
+            Some(baseline) => (*baseline, false),
+            None => match snapshots.first() {
+                Some(first) => (*first, true),
+                None => {
+                    excluded.push(ExcludedWallet {
+                        label: label.clone(),
+                        reason: "No snapshots recorded in this period".to_string(),
+                    });
+                    continue;
+                }
+            },
+        };
+        let latest = *snapshots.last().unwrap_or(&baseline);
+
+        let realized_change = latest.realized_pnl - baseline.realized_pnl;
+        let unrealized_change = latest.unrealized_pnl - baseline.unrealized_pnl;
+        let volume_traded = (latest.volume - baseline.volume).max(0.0);
+
+        if realized_change == 0.0 && unrealized_change == 0.0 && volume_traded == 0.0 {
+            excluded.push(ExcludedWallet {
+                label: label.clone(),
+                reason: "No trading activity in this period".to_string(),
+            });
+            continue;
+        }
+
+        entries.push(LeaderboardEntry {
+            rank: 0,
+            label: label.clone(),
+            pnl_change: realized_change + unrealized_change,
+            realized_pnl_change: realized_change,
+            unrealized_pnl_change: unrealized_change,
+            volume_traded,
+            joined_mid_period,
+            sparkline: daily_points(
+                &snapshots,
+                baseline,
+                period_start.max(baseline.taken_at),
+                now,
+            ),
+        });
+    }
+
+    entries.sort_by(|a, b| {
+        b.pnl_change
+            .total_cmp(&a.pnl_change)
+            .then_with(|| a.label.cmp(&b.label))
+    });
+    for (i, entry) in entries.iter_mut().enumerate() {
+        entry.rank = i + 1;
+    }
+
+    (entries, excluded)
+}
+
+/// One point per calendar day (UTC) from `from` to `now`, each the cumulative
+/// P&L change at that day's last snapshot, carried forward over gaps.
+fn daily_points(
+    snapshots: &[&WalletSnapshot],
+    baseline: &WalletSnapshot,
+    from: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Vec<PnlPoint> {
+    let first_day = from.date_naive();
+    let last_day = now.date_naive();
+
+    first_day
+        .iter_days()
+        .take_while(|day| *day <= last_day)
+        .map(|day: NaiveDate| {
+            let pnl = snapshots
+                .iter()
+                .rev()
+                .find(|s| s.taken_at.date_naive() <= day && s.taken_at >= baseline.taken_at)
+                .map(|s| s.total_pnl() - baseline.total_pnl())
+                .unwrap_or(0.0);
+            PnlPoint {
+                date: day.format("%Y-%m-%d").to_string(),
+                pnl,
+            }
+        })
+        .collect()
+}
@@ -1,180 +1,1589 @@
-use axum::{extract::State, Json};
-use chrono::Utc;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 
+use crate::api::extract::AppJson;
+use crate::api::fill_watcher::spawn_fill_watcher;
+use crate::api::idempotency::{idempotency_key, Claim, InFlightGuard};
+use crate::api::market_cache::CacheQuery;
+use crate::api::openapi::ErrorResponse;
 use crate::api::AppState;
+use crate::clients::ai::prompts::build_run_summary_prompt;
+use crate::clients::clob_signing::{
+    round_order, ApiCredentials, ClobSigner, WalletAuth, MIN_ORDER_NOTIONAL_USD,
+};
+use crate::clients::polymarket::MARKET_TIMEZONE;
+use crate::clients::{AiProvider, AiRequestOptions, PolymarketClient};
+use crate::request_id;
 use crate::types::{
-    LimitOrderBotRequest, LimitOrderBotResponse, OrderMode,
-    ResponseMetadata,
+    BotLogEvent, BotLogEventKind, LimitOrderBotRequest, LimitOrderBotResponse, MarketData,
+    OrderAdjustment, OrderBook, OrderExpiration, OrderMode, OrderResult, OrderStatus, Outcome,
+    OutcomeTarget, PlacementVerification, Price, ResponseMetadata, Secret, SimplePricing,
+    StraddleCost, StraddleLeg, Validate, DEFAULT_PRICE_LEVELS,
 };
 use crate::Result;
 
+const DEFAULT_VERIFY_DELAY_MS: u64 = 1500;
+const MAX_VERIFY_DELAY_MS: u64 = 10_000;
+const PRICE_TICK: f64 = 0.01;
+pub const DEFAULT_IMPROVEMENT_TICKS: u32 = 1;
+pub const DEFAULT_MAX_SPREAD_CENTS: u32 = 10;
+/// Buying every side of a straddle for a dollar or more locks in a loss.
+pub const DEFAULT_MAX_COMBINED_PRICE: f64 = 0.99;
+pub const DEFAULT_LADDER_PRICE_BAND: f64 = 0.10;
+/// Polymarket rejects orders below 5 shares.
+const MIN_ORDER_SHARES: f64 = 5.0;
+/// Orders in flight at once when placing a run.
+const PLACEMENT_CONCURRENCY: usize = 4;
+/// `market_close` orders expiring sooner than this are refused as too late
+/// in the cycle.
+const MIN_EXPIRY_LEAD_SECS: i64 = 30;
+
+/// Places (or, with `dry_run`, simulates) limit orders on a market.
+#[utoipa::path(
+    post,
+    path = "/api/limit-order-bot",
+    tag = "trading",
+    params(
+        CacheQuery,
+        ("Idempotency-Key" = Option<String>, Header, description = "Replays the first run's response for a repeated key"),
+    ),
+    request_body = LimitOrderBotRequest,
+    responses(
+        (status = 200, body = LimitOrderBotResponse),
+        (status = 400, description = "Invalid request or missing credentials", body = ErrorResponse),
+        (status = 409, description = "A run with this idempotency key is still in progress", body = ErrorResponse),
+        (status = 502, description = "Upstream failure", body = ErrorResponse),
+        (status = 503, description = "Trading is disabled", body = ErrorResponse),
+    )
+)]
 pub async fn handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<LimitOrderBotRequest>,
+    headers: HeaderMap,
+    Query(cache): Query<CacheQuery>,
+    AppJson(request): AppJson<LimitOrderBotRequest>,
 ) -> Result<Json<LimitOrderBotResponse>> {
+    // A retried run returns the first run's response instead of placing again
+    let idempotency = match idempotency_key(&headers, &request, state.config.wallet.as_ref())? {
+        Some(key) => match state.idempotency.claim(key, Instant::now())? {
+            Claim::Replay(response) => return Ok(Json(*response)),
+            Claim::New(guard) => Some(guard),
+        },
+        None => None,
+    };
+
+    let market = BotMarket::Fetch {
+        fresh: cache.fresh(),
+    };
+    run_bot(&state, &request, idempotency, market)
+        .await
+        .map(Json)
+}
+
+/// Where a run gets its market.
+pub(crate) enum BotMarket {
+    /// Looked up from the request's `market_slug` or `asset`
+    Fetch { fresh: bool },
+    /// Already in hand, e.g. the market an analysis just ran on
+    Fetched {
+        market: Arc<MarketData>,
+        cache_hit: bool,
+    },
+}
+
+/// One full bot run: plan, place, verify and summarize. Shared by the
+/// handler, the auto-trade scheduler and analyze-and-trade.
+pub(crate) async fn run_bot(
+    state: &Arc<AppState>,
+    request: &LimitOrderBotRequest,
+    mut idempotency: Option<InFlightGuard<'_>>,
+    market: BotMarket,
+) -> Result<LimitOrderBotResponse> {
     let start = Instant::now();
-    let mut logs = Vec::new();
+    let mut logs = BotLog::default();
+    let dry_run = request.dry_run.unwrap_or(false);
 
-    // Validate request
-    if request.wallet_private_key.is_empty() {
-        return Err(crate::AppError::Validation(
-            "Wallet private key is required".to_string(),
-        ));
+    // Dry runs never reach the exchange, so they stay available in safe mode
+    if dry_run {
+        logs.push("Dry run: orders will be simulated, nothing is sent to the exchange".to_string());
+    } else {
+        state.runtime_config.ensure_trading_enabled()?;
     }
 
-    if request.bankroll_usd <= 0.0 {
-        return Err(crate::AppError::Validation(
-            "Bankroll must be greater than 0".to_string(),
+    validate_request(request, state.config.wallet.as_ref())?;
+    if let Some(webhook_url) = &request.webhook_url {
+        state.webhooks.check_url(webhook_url).await?;
+    }
+    let expires_at = resolve_expiry(request, state.config.order_expiry_margin)?;
+    if let Some(expires_at) = expires_at {
+        logs.push(format!("Orders expire at {}", expires_at.to_rfc3339()));
+    }
+
+    let auth = wallet_auth(request, state.config.wallet.as_ref())?;
+    if !dry_run {
+        auth.signer()?;
+    }
+    let wallet = auth.address().to_checksum(None);
+    logs.push(format!("Wallet: {}", wallet));
+
+    let (market, market_timestamp, cache_hit) = match market {
+        BotMarket::Fetch { fresh } => fetch_market(state, request, fresh, &mut logs).await?,
+        BotMarket::Fetched { market, cache_hit } => {
+            logs.push(format!(
+                "Target market: {}",
+                market.slug.as_deref().unwrap_or(&market.id)
+            ));
+            ensure_open(&market)?;
+            logs.event(market_resolved(&market));
+            (MarketData::clone(&market), Utc::now(), cache_hit)
+        }
+    };
+
+    // Outcomes to buy and their share of the bankroll
+    let targets = resolve_targets(&market, request.outcomes.as_deref())?;
+    for target in &targets {
+        logs.push(format!(
+            "{} token: {} ({:.0}% of bankroll)",
+            target.outcome.name,
+            target.outcome.id,
+            target.weight * 100.0
         ));
     }
 
-    // Calculate next 15-min market timestamp
-    let market_timestamp = state.polymarket_client.calculate_next_15min_market_timestamp();
-    let market_slug = request.market_slug.unwrap_or_else(|| {
-        format!("15min-up-down-{}", market_timestamp.format("%Y%m%d-%H%M"))
+    let (planned, straddle) = plan_orders(state, request, &market, &targets, &mut logs).await?;
+    let (planned, adjustments) = round_planned(state, planned, &mut logs).await?;
+    let reservation = state
+        .exposure_caps
+        .enforce(
+            state,
+            &auth,
+            &market,
+            &planned,
+            &[],
+            request.override_caps.unwrap_or(false),
+            &mut logs,
+        )
+        .await?;
+    // A dry run places nothing, so holds nothing against other runs
+    let _reservation = (!dry_run).then_some(reservation);
+
+    if let (Some(guard), false) = (idempotency.as_mut(), dry_run) {
+        guard.mark_orders_sent();
+    }
+    let mut orders = place_orders(state, &auth, planned, expires_at, dry_run, &mut logs).await?;
+
+    let mut degraded_features = Vec::new();
+    let verification = if request.verify_placement.unwrap_or(false) && dry_run {
+        logs.push("Skipping placement verification for dry run".to_string());
+        None
+    } else if request.verify_placement.unwrap_or(false) {
+        let delay = Duration::from_millis(
+            request
+                .verify_delay_ms
+                .unwrap_or(DEFAULT_VERIFY_DELAY_MS)
+                .min(MAX_VERIFY_DELAY_MS),
+        );
+        let (verification, complete) = verify_placements(state, &auth, &mut orders, delay).await;
+        if !complete {
+            logs.push("Some placements could not be verified; marked unconfirmed".to_string());
+            degraded_features.push("placement_verification".to_string());
+        }
+        logs.push(format!(
+            "Verified {} orders: {} open, {} filled, {} unconfirmed, {} skipped",
+            verification.checked,
+            verification.confirmed_open,
+            verification.filled,
+            verification.unconfirmed.len(),
+            verification.skipped
+        ));
+        Some(verification)
+    } else {
+        None
+    };
+
+    let window_close = request.market_slug.is_none().then(|| {
+        let interval = request.interval.unwrap_or_default();
+        PolymarketClient::market_window_at(interval, MARKET_TIMEZONE, market_timestamp).next
     });
+    let mut summary = summarize_run(
+        &request.mode,
+        market.slug.as_deref().unwrap_or(&market.id),
+        &orders,
+        window_close,
+    );
+
+    if request.ai_summary.unwrap_or(false) {
+        match summarize_run_with_ai(state, &summary, &orders).await {
+            Ok(ai_summary) => summary = ai_summary,
+            Err(e) => {
+                tracing::warn!("AI run summary failed, using deterministic summary: {}", e);
+                logs.push("AI summary unavailable; using deterministic summary".to_string());
+                degraded_features.push("ai_summary".to_string());
+            }
+        }
+    }
+
+    let orders_failed = orders
+        .iter()
+        .filter(|o| matches!(o.status, OrderStatus::Failed))
+        .count();
+    let orders_placed = orders.len() - orders_failed;
+    let order_ids = orders.iter().filter_map(|o| o.order_id.clone()).collect();
+    if orders_failed > 0 {
+        logs.push(format!(
+            "{} of {} orders failed",
+            orders_failed,
+            orders.len()
+        ));
+    }
+
+    let execution_time = start.elapsed().as_millis() as u64;
 
-    logs.push(format!("Target market: {}", market_slug));
+    logs.push(format!("Completed in {}ms", execution_time));
+    let (logs, mut events) = logs.into_parts();
+    if !request.structured_logs.unwrap_or(false) {
+        events.clear();
+    }
+
+    let mut response = LimitOrderBotResponse {
+        orders,
+        orders_placed,
+        orders_failed,
+        order_ids,
+        market,
+        logs,
+        events,
+        summary,
+        verification,
+        adjustments,
+        straddle,
+        run_id: None,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: execution_time,
+            model_used: None,
+            retries: 0,
+            degraded_features,
+            custom_prompt: false,
+            dry_run,
+            cache_hit: Some(cache_hit),
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    };
 
+    // The orders are already out, so a failed write degrades the response
+    // instead of failing it
+    if let Some(storage) = &state.storage {
+        match storage.record_run(&wallet, request.mode, &response).await {
+            Ok(run_id) => response.run_id = Some(run_id),
+            Err(e) => {
+                tracing::warn!("Failed to store bot run: {}", e);
+                response
+                    .metadata
+                    .degraded_features
+                    .push("persistence".to_string());
+            }
+        }
+    }
+    if let (Some(webhook_url), false) = (&request.webhook_url, dry_run) {
+        spawn_fill_watcher(
+            state,
+            &auth,
+            webhook_url,
+            response.market.slug.as_deref(),
+            response.run_id.as_deref(),
+            &response.orders,
+        );
+    }
+    if let Some(guard) = idempotency {
+        guard.complete(&response);
+    }
+
+    Ok(response)
+}
+
+/// Checks the fields every bot flow needs before touching the network.
+/// Scheduled runs build their request in code, so this repeats the
+/// [`Validate`] checks `AppJson` ran for HTTP requests.
+pub(crate) fn validate_request(
+    request: &LimitOrderBotRequest,
+    server_wallet: Option<&ClobSigner>,
+) -> Result<()> {
+    wallet_auth(request, server_wallet)?;
+    request.validate()
+}
+
+/// When the request's orders should expire: never for `gtc`, at
+/// `expires_at` for `gtd`, and `margin` before the next boundary of the
+/// request's up/down interval for `market_close`. A `market_close` expiry under 30 seconds away is
+/// refused as too late in the cycle.
+pub fn resolve_expiry(
+    request: &LimitOrderBotRequest,
+    margin: Duration,
+) -> Result<Option<DateTime<Utc>>> {
+    match request.expiration.unwrap_or_default() {
+        OrderExpiration::Gtc => Ok(None),
+        OrderExpiration::Gtd => Ok(request.expires_at),
+        OrderExpiration::MarketClose => {
+            let margin = chrono::Duration::from_std(margin)
+                .map_err(|_| anyhow::anyhow!("Order expiry margin is out of range"))?;
+            let interval = request.interval.unwrap_or_default();
+            let expires_at =
+                PolymarketClient::market_window(interval, MARKET_TIMEZONE).next - margin;
+            if expires_at - Utc::now() < chrono::Duration::seconds(MIN_EXPIRY_LEAD_SECS) {
+                return Err(crate::AppError::Validation(format!(
+                    "Too late in the cycle for market_close expiration: orders would expire at {}",
+                    expires_at.to_rfc3339()
+                )));
+            }
+            Ok(Some(expires_at))
+        }
+    }
+}
+
+/// The wallet a bot request acts for: its private key, its CLOB credentials
+/// (with `wallet_address`), or else the server's wallet. Sending both a key
+/// and credentials, or only part of the credentials, is refused.
+pub(crate) fn wallet_auth(
+    request: &LimitOrderBotRequest,
+    server_wallet: Option<&ClobSigner>,
+) -> Result<WalletAuth> {
+    fn provided(secret: &Option<Secret>) -> Option<&str> {
+        secret
+            .as_ref()
+            .map(|s| s.expose().trim())
+            .filter(|s| !s.is_empty())
+    }
+    let private_key = provided(&request.wallet_private_key);
+    let credentials = [
+        provided(&request.clob_api_key),
+        provided(&request.clob_secret),
+        provided(&request.clob_passphrase),
+    ];
+
+    let auth = match (private_key, credentials) {
+        (Some(_), credentials) if credentials.iter().any(Option::is_some) => {
+            return Err(crate::AppError::Validation(
+                "Send either wallet_private_key or the CLOB API credentials, not both".to_string(),
+            ))
+        }
+        (Some(key), _) => WalletAuth::Signer(ClobSigner::from_private_key(key)?),
+        (None, [Some(api_key), Some(secret), Some(passphrase)]) => {
+            let address = request
+                .wallet_address
+                .as_deref()
+                .ok_or_else(|| {
+                    crate::AppError::Validation(
+                        "wallet_address is required with the CLOB API credentials".to_string(),
+                    )
+                })?
+                .trim()
+                .parse()
+                .map_err(|_| crate::AppError::Validation("Invalid wallet_address".to_string()))?;
+            WalletAuth::Credentials {
+                address,
+                credentials: ApiCredentials {
+                    api_key: api_key.to_string(),
+                    secret: secret.to_string(),
+                    passphrase: passphrase.to_string(),
+                },
+            }
+        }
+        (None, [None, None, None]) => match server_wallet {
+            Some(signer) => WalletAuth::Signer(signer.clone()),
+            None => {
+                return Err(crate::AppError::Validation(
+                    "Wallet credentials are required: send wallet_private_key, or \
+                     clob_api_key, clob_secret and clob_passphrase with wallet_address"
+                        .to_string(),
+                ))
+            }
+        },
+        (None, _) => {
+            return Err(crate::AppError::Validation(
+                "clob_api_key, clob_secret and clob_passphrase must be sent together".to_string(),
+            ))
+        }
+    };
+
+    if let Some(address) = request.wallet_address.as_deref() {
+        let matches = address
+            .trim()
+            .parse::<alloy_primitives::Address>()
+            .is_ok_and(|address| address == auth.address());
+        if !matches {
+            return Err(crate::AppError::Validation(
+                "wallet_address doesn't match the wallet's credentials".to_string(),
+            ));
+        }
+    }
+
+    Ok(auth)
+}
+
+/// The requested market, or the next up/down window of the request's
+/// interval, along with that window's start and whether the market came from the cache.
+pub(crate) async fn fetch_market(
+    state: &AppState,
+    request: &LimitOrderBotRequest,
+    fresh: bool,
+    logs: &mut BotLog,
+) -> Result<(MarketData, DateTime<Utc>, bool)> {
+    let interval = request.interval.unwrap_or_default();
+    let market_timestamp = PolymarketClient::market_window(interval, MARKET_TIMEZONE).next;
     // Fetch market data
-    let market = state.polymarket_client.get_market_by_slug(&market_slug).await?;
+    let cached = match request.market_slug.as_deref() {
+        Some(market_slug) => {
+            logs.push(format!("Target market: {}", market_slug));
+            state
+                .market_cache
+                .polymarket(market_slug, fresh, || {
+                    state.polymarket_client.get_market_by_slug(market_slug)
+                })
+                .await?
+        }
+        None => {
+            let asset = PolymarketClient::updown_asset(request.asset.as_deref())?;
+            let market_slug =
+                PolymarketClient::build_updown_slug(asset, interval, market_timestamp);
+            logs.push(format!("Target market: {} (generated)", market_slug));
+            let cached = state
+                .market_cache
+                .polymarket(&market_slug, fresh, || {
+                    state
+                        .polymarket_client
+                        .resolve_updown_market(&market_slug, market_timestamp)
+                })
+                .await?;
+            let market = &cached.market;
+            if market.slug.as_deref() != Some(market_slug.as_str()) {
+                logs.push(format!(
+                    "Slug {} not indexed yet; discovered market {} via listing",
+                    market_slug,
+                    market.slug.as_deref().unwrap_or("unknown")
+                ));
+            }
+            cached
+        }
+    };
+    if cached.hit {
+        logs.push("Market data served from cache".to_string());
+    }
+    let market = MarketData::clone(&cached.market);
+
     logs.push(format!("Fetched market: {}", market.question));
+    ensure_open(&market)?;
+    logs.event(market_resolved(&market));
 
-    // Extract token IDs (Up/Down)
-    let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+    Ok((market, market_timestamp, cached.hit))
+}
 
-    if token_ids.len() < 2 {
-        return Err(crate::AppError::Validation(
-            "Market must have at least 2 outcomes".to_string(),
-        ));
+fn market_resolved(market: &MarketData) -> BotLogEventKind {
+    BotLogEventKind::MarketResolved {
+        slug: market.slug.clone().unwrap_or_else(|| market.id.clone()),
+        question: market.question.clone(),
     }
+}
 
-    let up_token_id = &token_ids[0];
-    let down_token_id = &token_ids[1];
+pub(crate) fn ensure_open(market: &MarketData) -> Result<()> {
+    if market.closed {
+        return Err(crate::AppError::Validation(format!(
+            "Market {} is closed{}; orders can't be placed on it",
+            market.slug.as_deref().unwrap_or(&market.id),
+            market
+                .resolved_outcome
+                .as_ref()
+                .map(|winner| format!(" and resolved to {}", winner))
+                .unwrap_or_default()
+        )));
+    }
+    Ok(())
+}
 
-    logs.push(format!("Up token: {}, Down token: {}", up_token_id, down_token_id));
+/// An order the bot intends to place.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedOrder {
+    pub token_id: String,
+    pub outcome: String,
+    pub side: &'static str, // "buy" or "sell"
+    pub price: Price,
+    pub size: f64,
+}
 
-    // Place orders based on mode
-    let mut orders = Vec::new();
+/// Rounds `planned` to each token's tick size and to 0.01 shares, dropping
+/// orders the exchange would refuse: under the market's minimum size or
+/// the minimum notional. Returns the orders left and every change made, or
+/// an error when nothing is left to place.
+pub(crate) async fn round_planned(
+    state: &AppState,
+    planned: Vec<PlannedOrder>,
+    logs: &mut BotLog,
+) -> Result<(Vec<PlannedOrder>, Vec<OrderAdjustment>)> {
+    let mut params = HashMap::new();
+    for order in &planned {
+        if !params.contains_key(&order.token_id) {
+            let found = state
+                .polymarket_client
+                .get_market_params(&order.token_id)
+                .await;
+            params.insert(order.token_id.clone(), found);
+        }
+    }
+
+    let mut kept = Vec::with_capacity(planned.len());
+    let mut adjustments = Vec::new();
+    for order in planned {
+        let market = params[&order.token_id];
+        let rounded = round_order(
+            order.price.value(),
+            order.size,
+            market.tick_size,
+            MIN_ORDER_NOTIONAL_USD,
+        )
+        .and_then(|(price, size)| {
+            if size < market.min_order_size {
+                Err(format!(
+                    "size {:.2} is under the market's {} share minimum",
+                    size, market.min_order_size
+                ))
+            } else {
+                Ok((price, size))
+            }
+        });
+
+        let adjustment =
+            |price: Option<Price>, size: Option<f64>, reason: String| OrderAdjustment {
+                token_id: order.token_id.clone(),
+                outcome: order.outcome.clone(),
+                planned_price: order.price,
+                planned_size: order.size,
+                dropped: price.is_none(),
+                price,
+                size,
+                reason,
+            };
+        match rounded {
+            Ok((price, size)) => {
+                let mut reasons = Vec::new();
+                if (price.value() - order.price.value()).abs() > 1e-12 {
+                    reasons.push(format!(
+                        "price {} rounded to {} (tick {})",
+                        order.price.value(),
+                        price.value(),
+                        market.tick_size
+                    ));
+                }
+                if (size - order.size).abs() > 1e-12 {
+                    reasons.push(format!("size {} floored to {:.2}", order.size, size));
+                }
+                if !reasons.is_empty() {
+                    let reason = reasons.join("; ");
+                    logs.push(format!("Adjusted {} order: {}", order.outcome, reason));
+                    adjustments.push(adjustment(Some(price), Some(size), reason));
+                }
+                kept.push(PlannedOrder {
+                    price,
+                    size,
+                    ..order
+                });
+            }
+            Err(reason) => {
+                logs.push(format!("Dropped {} order: {}", order.outcome, reason));
+                adjustments.push(adjustment(None, None, reason));
+            }
+        }
+    }
+
+    if kept.is_empty() {
+        return Err(crate::AppError::Validation(match adjustments.first() {
+            Some(adjustment) => format!(
+                "Every order falls under the exchange minimums after rounding ({})",
+                adjustment.reason
+            ),
+            // Nothing was planned, e.g. a bankroll below one minimum-size rung
+            None => format!(
+                "No orders to place: the amount is too small for a single {}-share order",
+                MIN_ORDER_SHARES
+            ),
+        }));
+    }
+    Ok((kept, adjustments))
+}
+
+/// Computes the orders for `request.mode` across `targets` without placing
+/// anything. Simple mode prices every outcome off the live book first, so a
+/// refusal never leaves a partial straddle, and returns the straddle's cost
+/// when it buys more than one outcome.
+pub(crate) async fn plan_orders(
+    state: &AppState,
+    request: &LimitOrderBotRequest,
+    market: &MarketData,
+    targets: &[Target<'_>],
+    logs: &mut BotLog,
+) -> Result<(Vec<PlannedOrder>, Option<StraddleCost>)> {
+    let mut planned = Vec::new();
+    let mut straddle = None;
+    let use_orderbook_price = request.use_orderbook_price.unwrap_or(false);
+    if use_orderbook_price {
+        logs.push("Reference price: order book midpoint".to_string());
+    }
 
     match request.mode {
         OrderMode::Simple => {
-            // Straddle: buy both Up and Down at current prices
+            // Straddle: buy every target outcome at the current book
             logs.push("Mode: Simple (straddle)".to_string());
 
-            let up_price = market.outcomes[0].price;
-            let down_price = market.outcomes[1].price;
-            let allocation_per_side = request.bankroll_usd / 2.0;
+            let pricing = request.pricing.unwrap_or_default();
+            let improvement_ticks = request
+                .improvement_ticks
+                .unwrap_or(state.config.simple_improvement_ticks);
+            let max_spread_cents = request
+                .max_spread_cents
+                .unwrap_or(state.config.simple_max_spread_cents);
+            let strict = request.strict_spread.unwrap_or(true);
 
-            let up_shares = (allocation_per_side / up_price).max(5.0);
-            let down_shares = (allocation_per_side / down_price).max(5.0);
+            let mut priced = Vec::with_capacity(targets.len());
+            for target in targets {
+                let name = &target.outcome.name;
+                let book = state
+                    .polymarket_client
+                    .get_order_book(&target.outcome.id)
+                    .await?;
+                let reference = if use_orderbook_price {
+                    book_midpoint(&book, name)?
+                } else {
+                    target.outcome.price
+                };
+                let decision = decide_simple_price(
+                    &book,
+                    reference,
+                    pricing,
+                    improvement_ticks,
+                    max_spread_cents,
+                    strict,
+                )
+                .map_err(|reason| {
+                    crate::AppError::Validation(format!(
+                        "Refusing to price {} order: {}",
+                        name, reason
+                    ))
+                })?;
+                if let Some(warning) = decision.warning {
+                    tracing::warn!("{} order pricing: {}", name, warning);
+                    logs.push(format!("Warning ({}): {}", name, warning));
+                }
+                logs.push(format!(
+                    "Pricing {:?}: {} ${:.4} (bid {:?} / ask {:?})",
+                    pricing, name, decision.price, book.best_bid, book.best_ask
+                ));
+                priced.push((decision.price, book));
+            }
+
+            if targets.len() > 1 {
+                let sides: Vec<StraddleSide> = targets
+                    .iter()
+                    .zip(&priced)
+                    .map(|(target, (price, book))| StraddleSide {
+                        outcome: &target.outcome.name,
+                        price: *price,
+                        book: Some(book),
+                    })
+                    .collect();
+                let max_combined_price = request
+                    .max_combined_price
+                    .unwrap_or(state.config.simple_max_combined_price);
+                let cost = check_straddle(&sides, max_combined_price, request.max_spread).map_err(
+                    |reason| crate::AppError::Validation(format!("Refusing straddle: {}", reason)),
+                )?;
+                logs.push(format!(
+                    "Straddle cost: ${:.4} per share of each side (max ${:.4}); spreads: {}",
+                    cost.combined_cost,
+                    cost.max_combined_price,
+                    describe_legs(&cost.legs, |leg| match leg.spread {
+                        Some(spread) => format!("{:.3}", spread),
+                        None => "n/a".to_string(),
+                    })
+                ));
+                straddle = Some(cost);
+            }
+
+            // A leg whose allocation can't buy the minimum is refused rather
+            // than topped up, so the run never spends more than the bankroll
+            for (target, (price, _)) in targets.iter().zip(priced) {
+                let allocation = request.bankroll_usd * target.weight;
+                let shares = allocation / price.value();
+                if shares < MIN_ORDER_SHARES {
+                    return Err(crate::AppError::Validation(format!(
+                        "Refusing {} order: ${:.2} buys {:.2} shares at ${:.4}, under the \
+                         {}-share minimum (${:.2} needed)",
+                        target.outcome.name,
+                        allocation,
+                        shares,
+                        price.value(),
+                        MIN_ORDER_SHARES,
+                        MIN_ORDER_SHARES * price.value()
+                    )));
+                }
+                planned.push(PlannedOrder {
+                    token_id: target.outcome.id.clone(),
+                    outcome: target.outcome.name.clone(),
+                    side: "buy",
+                    price,
+                    size: shares,
+                });
+            }
+            for order in &planned {
+                logs.event(order_planned(order, None));
+            }
+        }
+        OrderMode::Ladder => {
+            // Ladder: multiple price levels weighted per the profile
+            let profile = request.ladder_profile.unwrap_or_default();
+            logs.push(format!("Mode: Ladder ({:?} profile)", profile));
+
+            let price_levels = request.price_levels.unwrap_or(DEFAULT_PRICE_LEVELS);
+            let weights = profile.weights(price_levels, request.weights.as_deref());
+            let spacing = request.ladder_spacing.unwrap_or_default();
+            let band = state.config.ladder_price_band;
 
             logs.push(format!(
-                "Placing Up order: {} shares @ ${:.4}",
-                up_shares, up_price
-            ));
-            logs.push(format!(
-                "Placing Down order: {} shares @ ${:.4}",
-                down_shares, down_price
+                "Calculated {} price levels per outcome",
+                price_levels
             ));
 
-            let up_order = state
-                .polymarket_client
-                .place_order(
-                    &request.wallet_private_key,
-                    up_token_id,
-                    "buy",
-                    up_price,
-                    up_shares,
+            for target in targets {
+                let reference = if use_orderbook_price {
+                    let book = state
+                        .polymarket_client
+                        .get_order_book(&target.outcome.id)
+                        .await?;
+                    book_midpoint(&book, &target.outcome.name)?
+                } else {
+                    target.outcome.price
+                };
+                let (min_price, max_price) = ladder_bounds(
+                    reference,
+                    request.ladder_min_price,
+                    request.ladder_max_price,
+                    band,
                 )
-                .await?;
+                .map_err(crate::AppError::Validation)?;
+                logs.push(format!(
+                    "{} ladder: ${:.4}-${:.4} ({:?} spacing)",
+                    target.outcome.name, min_price, max_price, spacing
+                ));
 
-            let down_order = state
+                let allocation = request.bankroll_usd * target.weight;
+                let tick_size = state
+                    .polymarket_client
+                    .get_market_params(&target.outcome.id)
+                    .await
+                    .tick_size;
+                let ladder = PolymarketClient::calculate_ladder_orders(
+                    allocation, min_price, max_price, spacing, &weights, tick_size,
+                );
+                if ladder.len() < price_levels {
+                    logs.push(format!(
+                        "Warning ({}): bankroll covers {} of {} levels at the 5-share minimum",
+                        target.outcome.name,
+                        ladder.len(),
+                        price_levels
+                    ));
+                }
+
+                for (level, (price, shares)) in ladder.iter().enumerate() {
+                    logs.push(format!(
+                        "{} level {}: {:.2} shares @ ${:.4} = ${:.2} ({:.1}%)",
+                        target.outcome.name,
+                        level + 1,
+                        shares,
+                        price,
+                        shares * price,
+                        shares * price / allocation * 100.0
+                    ));
+                }
+
+                for (level, (price, shares)) in ladder.into_iter().enumerate() {
+                    let order = PlannedOrder {
+                        token_id: target.outcome.id.clone(),
+                        outcome: target.outcome.name.clone(),
+                        side: "buy",
+                        price: Price::from_decimal(price)?,
+                        size: shares,
+                    };
+                    logs.event(order_planned(&order, Some(level + 1)));
+                    planned.push(order);
+                }
+            }
+        }
+        OrderMode::Exit => {
+            // Exit: sell what the wallet holds in each target outcome
+            logs.push("Mode: Exit (sell held shares)".to_string());
+
+            let target_pct = request.exit_target_pct.unwrap_or_default();
+            let wallet = wallet_auth(request, state.config.wallet.as_ref())?
+                .address()
+                .to_checksum(None);
+            let token_ids: Vec<String> = targets.iter().map(|t| t.outcome.id.clone()).collect();
+            let positions = state
                 .polymarket_client
-                .place_order(
-                    &request.wallet_private_key,
-                    down_token_id,
-                    "buy",
-                    down_price,
-                    down_shares,
-                )
+                .get_market_position(&wallet, market.condition_id.as_deref(), &token_ids)
                 .await?;
+            if positions.iter().all(|p| p.shares <= 0.0) {
+                return Err(crate::AppError::Validation(format!(
+                    "Wallet has no position in market {}",
+                    market.slug.as_deref().unwrap_or(&market.id)
+                )));
+            }
+
+            for target in targets {
+                let name = &target.outcome.name;
+                let Some(position) = positions
+                    .iter()
+                    .find(|p| p.token_id == target.outcome.id && p.shares > 0.0)
+                else {
+                    continue;
+                };
 
-            orders.push(up_order);
-            orders.push(down_order);
+                // Never sell more than is held
+                let shares = (position.shares * 100.0 + 1e-9).floor() / 100.0;
+                if shares < MIN_ORDER_SHARES {
+                    logs.push(format!(
+                        "Warning ({}): {} shares held is below the {}-share minimum; unsellable",
+                        name, shares, MIN_ORDER_SHARES
+                    ));
+                    continue;
+                }
+
+                let price = exit_price(position.avg_price, target_pct);
+                if price < position.avg_price * (1.0 + target_pct / 100.0) {
+                    logs.push(format!(
+                        "Warning ({}): {:.1}% target is above the maximum price; selling at ${:.2}",
+                        name, target_pct, price
+                    ));
+                }
+                logs.push(format!(
+                    "Exiting {}: {} shares (avg ${:.4}) @ ${:.2}",
+                    name, shares, position.avg_price, price
+                ));
+                let order = PlannedOrder {
+                    token_id: target.outcome.id.clone(),
+                    outcome: name.clone(),
+                    side: "sell",
+                    price: Price::from_decimal(price)?,
+                    size: shares,
+                };
+                logs.event(order_planned(&order, None));
+                planned.push(order);
+            }
         }
-        OrderMode::Ladder => {
-            // Ladder: multiple price levels with exponential taper
-            logs.push("Mode: Ladder (exponential taper)".to_string());
-
-            let price_levels = request.price_levels.unwrap_or(5);
-            let min_price = 0.01;
-            let max_price = 0.99;
-
-            let up_ladder = state.polymarket_client.calculate_ladder_orders(
-                request.bankroll_usd / 2.0,
-                price_levels,
-                min_price,
-                max_price,
-            );
-
-            let down_ladder = state.polymarket_client.calculate_ladder_orders(
-                request.bankroll_usd / 2.0,
-                price_levels,
-                min_price,
-                max_price,
-            );
-
-            logs.push(format!("Calculated {} price levels per side", price_levels));
-
-            for (price, shares) in up_ladder {
-                logs.push(format!("Up ladder: {} shares @ ${:.4}", shares, price));
-                let order = state
-                    .polymarket_client
-                    .place_order(
-                        &request.wallet_private_key,
-                        up_token_id,
-                        "buy",
-                        price,
-                        shares,
-                    )
-                    .await?;
-                orders.push(order);
+    }
+
+    Ok((planned, straddle))
+}
+
+fn order_planned(order: &PlannedOrder, level: Option<usize>) -> BotLogEventKind {
+    BotLogEventKind::OrderPlanned {
+        outcome: order.outcome.clone(),
+        side: order.side.to_string(),
+        price: order.price.value(),
+        shares: order.size,
+        level,
+    }
+}
+
+/// An outcome to buy and its normalized share of the bankroll.
+#[derive(Debug)]
+pub struct Target<'a> {
+    pub outcome: &'a Outcome,
+    pub weight: f64,
+}
+
+/// Matches requested outcomes against the market by token id or
+/// case-insensitive name, normalizing their weights to sum to 1.
+///
+/// Without a request, buys Up/Down (resolved by name, falling back to
+/// canonical order) with half the bankroll each.
+pub fn resolve_targets<'a>(
+    market: &'a MarketData,
+    requested: Option<&[OutcomeTarget]>,
+) -> Result<Vec<Target<'a>>> {
+    let Some(requested) = requested else {
+        // Buying both sides only makes sense on a two-outcome market
+        let (up, down) = market
+            .binary_outcomes()
+            .filter(|_| market.outcomes.len() == 2)
+            .ok_or_else(|| {
+                let available: Vec<&str> =
+                    market.outcomes.iter().map(|o| o.name.as_str()).collect();
+                crate::AppError::Validation(format!(
+                    "Market has {} outcomes; pass outcomes to choose which to buy. \
+                     Available outcomes: {}",
+                    market.outcomes.len(),
+                    available.join(", ")
+                ))
+            })?;
+        return Ok(vec![
+            Target {
+                outcome: up,
+                weight: 0.5,
+            },
+            Target {
+                outcome: down,
+                weight: 0.5,
+            },
+        ]);
+    };
+
+    if requested.is_empty() {
+        return Err(crate::AppError::Validation(
+            "outcomes must not be empty".to_string(),
+        ));
+    }
+
+    let mut targets: Vec<Target> = Vec::with_capacity(requested.len());
+    for entry in requested {
+        let key = entry.outcome.trim();
+        let outcome = market
+            .outcomes
+            .iter()
+            .find(|o| o.id == key)
+            .or_else(|| market.outcome_by_name(key))
+            .ok_or_else(|| {
+                let available: Vec<&str> =
+                    market.outcomes.iter().map(|o| o.name.as_str()).collect();
+                crate::AppError::Validation(format!(
+                    "Outcome '{}' not found in market; available outcomes: {}",
+                    key,
+                    available.join(", ")
+                ))
+            })?;
+
+        let weight = entry.weight.unwrap_or(1.0);
+        if !(weight.is_finite() && weight > 0.0) {
+            return Err(crate::AppError::Validation(format!(
+                "Weight for outcome '{}' must be greater than 0",
+                key
+            )));
+        }
+        if targets.iter().any(|t| t.outcome.id == outcome.id) {
+            return Err(crate::AppError::Validation(format!(
+                "Outcome '{}' is listed more than once",
+                outcome.name
+            )));
+        }
+        targets.push(Target { outcome, weight });
+    }
+
+    let total: f64 = targets.iter().map(|t| t.weight).sum();
+    for target in targets.iter_mut() {
+        target.weight /= total;
+    }
+    Ok(targets)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct PricingDecision {
+    pub price: Price,
+    pub warning: Option<String>,
+}
+
+/// Prices a Simple mode order from a book snapshot.
+///
+/// A spread wider than `max_spread_cents`, or an empty or one-sided book,
+/// usually means the book is broken: strict mode refuses with the reason,
+/// otherwise the order is priced anyway (falling back to `reference` when the
+/// needed side is missing) and the reason is returned as a warning.
+pub fn decide_simple_price(
+    book: &OrderBook,
+    reference: Price,
+    pricing: SimplePricing,
+    improvement_ticks: u32,
+    max_spread_cents: u32,
+    strict: bool,
+) -> std::result::Result<PricingDecision, String> {
+    let problem = match (book.best_bid, book.best_ask) {
+        (Some(bid), Some(ask)) => {
+            let spread_cents = ((ask - bid) * 100.0).round();
+            (spread_cents > f64::from(max_spread_cents)).then(|| {
+                format!(
+                    "spread of {:.0}¢ (bid {:.3} / ask {:.3}) exceeds the {}¢ maximum",
+                    spread_cents, bid, ask, max_spread_cents
+                )
+            })
+        }
+        (None, None) => Some("order book is empty".to_string()),
+        (None, Some(_)) => Some("order book has no bids".to_string()),
+        (Some(_), None) => Some("order book has no asks".to_string()),
+    };
+
+    if let Some(reason) = &problem {
+        if strict {
+            return Err(reason.clone());
+        }
+    }
+
+    let raw = match pricing {
+        SimplePricing::Last => reference.value(),
+        SimplePricing::JoinBid => match (book.best_bid, book.best_ask) {
+            (Some(bid), Some(ask)) => {
+                let improved = bid + f64::from(improvement_ticks) * PRICE_TICK;
+                // Improve inside the spread but never cross it
+                improved.min(ask - PRICE_TICK).max(bid)
             }
+            (Some(bid), None) => bid + f64::from(improvement_ticks) * PRICE_TICK,
+            (None, _) => reference.value(),
+        },
+        SimplePricing::CrossSpread => book.best_ask.unwrap_or(reference.value()),
+    };
 
-            for (price, shares) in down_ladder {
-                logs.push(format!("Down ladder: {} shares @ ${:.4}", shares, price));
-                let order = state
-                    .polymarket_client
-                    .place_order(
-                        &request.wallet_private_key,
-                        down_token_id,
-                        "buy",
-                        price,
-                        shares,
-                    )
-                    .await?;
-                orders.push(order);
+    // Snap off float noise and keep strictly inside (0, 1)
+    let rounded = ((raw * 1000.0).round() / 1000.0).clamp(PRICE_TICK, 1.0 - PRICE_TICK);
+    let price = Price::from_decimal(rounded).map_err(|e| e.to_string())?;
+
+    Ok(PricingDecision {
+        price,
+        warning: problem.map(|reason| format!("{}; pricing anyway (strict_spread=false)", reason)),
+    })
+}
+
+/// One side of a simple-mode straddle: its planned limit price and, when
+/// there is one, its order book.
+#[derive(Debug, Clone, Copy)]
+pub struct StraddleSide<'a> {
+    pub outcome: &'a str,
+    pub price: Price,
+    pub book: Option<&'a OrderBook>,
+}
+
+/// Refuses a straddle that would lose money or trade into a broken book:
+/// a crossed book on any side, a side whose spread exceeds `max_spread`,
+/// or one share of every side costing more than `max_combined_price`. A
+/// side fills at its limit price, or at the best ask when that is lower;
+/// without a book (or asks) the limit price is all there is to go on.
+pub fn check_straddle(
+    sides: &[StraddleSide<'_>],
+    max_combined_price: f64,
+    max_spread: Option<f64>,
+) -> std::result::Result<StraddleCost, String> {
+    let mut legs = Vec::with_capacity(sides.len());
+    for side in sides {
+        let (best_bid, best_ask) = side
+            .book
+            .map_or((None, None), |book| (book.best_bid, book.best_ask));
+        let spread = match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) if bid > ask => {
+                return Err(format!(
+                    "{} order book is crossed (bid {:.3} / ask {:.3})",
+                    side.outcome, bid, ask
+                ))
+            }
+            (Some(bid), Some(ask)) => Some(round_cost(ask - bid)),
+            _ => None,
+        };
+        if let (Some(spread), Some(max)) = (spread, max_spread) {
+            if spread > max + 1e-9 {
+                return Err(format!(
+                    "{} spread of {:.3} exceeds the {:.3} max_spread",
+                    side.outcome, spread, max
+                ));
             }
         }
+        let fill_price = best_ask.map_or(side.price.value(), |ask| ask.min(side.price.value()));
+        legs.push(StraddleLeg {
+            outcome: side.outcome.to_string(),
+            fill_price,
+            spread,
+        });
     }
 
-    let execution_time = start.elapsed().as_millis() as u64;
+    let combined_cost = round_cost(legs.iter().map(|leg| leg.fill_price).sum());
+    if combined_cost > max_combined_price + 1e-9 {
+        return Err(format!(
+            "combined cost ${:.4} ({}) exceeds the ${:.4} max_combined_price",
+            combined_cost,
+            describe_legs(&legs, |leg| format!("{:.3}", leg.fill_price)),
+            max_combined_price
+        ));
+    }
+    Ok(StraddleCost {
+        combined_cost,
+        max_combined_price,
+        legs,
+    })
+}
 
-    logs.push(format!("Completed in {}ms", execution_time));
+/// Four places covers the 0.001 tick and snaps off float noise.
+fn round_cost(value: f64) -> f64 {
+    (value * 10_000.0).round() / 10_000.0
+}
 
-    Ok(Json(LimitOrderBotResponse {
-        orders,
-        market,
-        logs,
-        metadata: ResponseMetadata {
-            timestamp: Utc::now().to_rfc3339(),
-            execution_time_ms: execution_time,
-            model_used: None,
-            retries: 0,
+fn describe_legs(legs: &[StraddleLeg], value: impl Fn(&StraddleLeg) -> String) -> String {
+    legs.iter()
+        .map(|leg| format!("{} {}", leg.outcome, value(leg)))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The book midpoint as an order reference price. A one-sided or empty book
+/// has none, and guessing one would defeat the point of asking for it.
+fn book_midpoint(book: &OrderBook, outcome: &str) -> Result<Price> {
+    let midpoint = book.midpoint.ok_or_else(|| {
+        crate::AppError::Validation(format!(
+            "Order book for {} has no midpoint (bid {:?} / ask {:?}); retry without use_orderbook_price",
+            outcome, book.best_bid, book.best_ask
+        ))
+    })?;
+    Ok(Price::from_decimal(midpoint)?)
+}
+
+/// Sell price that realizes `target_pct` profit on shares bought at
+/// `avg_price`, rounded up to the tick and capped at the highest tradable
+/// price.
+pub fn exit_price(avg_price: f64, target_pct: f64) -> f64 {
+    let target = avg_price * (1.0 + target_pct / 100.0);
+    ((target * 100.0 - 1e-9).ceil() / 100.0).clamp(PRICE_TICK, 1.0 - PRICE_TICK)
+}
+
+/// A bot run's log: the free-text lines every response carries and the
+/// typed events returned with `structured_logs`. Events are mirrored to
+/// tracing as they are recorded.
+#[derive(Debug, Default)]
+pub struct BotLog {
+    lines: Vec<String>,
+    events: Vec<BotLogEvent>,
+}
+
+impl BotLog {
+    pub fn push(&mut self, line: impl Into<String>) {
+        self.lines.push(line.into());
+    }
+
+    pub fn event(&mut self, kind: BotLogEventKind) {
+        let event = BotLogEvent::now(kind);
+        tracing::info!(
+            "Bot run event: {}",
+            serde_json::to_string(&event.kind).unwrap_or_default()
+        );
+        self.events.push(event);
+    }
+
+    pub fn into_parts(self) -> (Vec<String>, Vec<BotLogEvent>) {
+        (self.lines, self.events)
+    }
+}
+
+fn order_log(dry_run: bool, line: String) -> String {
+    if dry_run {
+        format!("[SIMULATED] {}", line)
+    } else {
+        line
+    }
+}
+
+/// Ladder price range: explicit bounds where given, otherwise `reference ±
+/// band` clamped to the tradable range. Both ends must lie strictly inside
+/// (0, 1) with min below max.
+pub fn ladder_bounds(
+    reference: Price,
+    min_price: Option<f64>,
+    max_price: Option<f64>,
+    band: f64,
+) -> std::result::Result<(f64, f64), String> {
+    for (name, value) in [
+        ("ladder_min_price", min_price),
+        ("ladder_max_price", max_price),
+    ] {
+        if let Some(value) = value {
+            if !(value > 0.0 && value < 1.0) {
+                return Err(format!("{} must be between 0 and 1 (exclusive)", name));
+            }
+        }
+    }
+
+    let min_price = min_price.unwrap_or_else(|| (reference.value() - band).max(PRICE_TICK));
+    let max_price = max_price.unwrap_or_else(|| (reference.value() + band).min(1.0 - PRICE_TICK));
+    if min_price >= max_price {
+        return Err(format!(
+            "Ladder min price {:.4} must be below max price {:.4}",
+            min_price, max_price
+        ));
+    }
+
+    Ok((min_price, max_price))
+}
+
+/// Places `planned` with up to `PLACEMENT_CONCURRENCY` orders in flight,
+/// returning results in plan order and logging each order's latency. See
+/// [`collect_placements`] for how failures are reported.
+pub(crate) async fn place_orders(
+    state: &Arc<AppState>,
+    auth: &WalletAuth,
+    planned: Vec<PlannedOrder>,
+    expires_at: Option<DateTime<Utc>>,
+    dry_run: bool,
+    logs: &mut BotLog,
+) -> Result<Vec<OrderResult>> {
+    let signer = Arc::new(auth.fingerprint());
+    let auth = Arc::new(auth.clone());
+    let semaphore = Arc::new(Semaphore::new(PLACEMENT_CONCURRENCY));
+    let mut workers = JoinSet::new();
+
+    for (index, order) in planned.iter().enumerate() {
+        logs.push(order_log(
+            dry_run,
+            format!(
+                "Placing {} order: {} shares @ ${:.4}",
+                order.outcome, order.size, order.price
+            ),
+        ));
+        let state = state.clone();
+        let signer = signer.clone();
+        let auth = auth.clone();
+        let semaphore = semaphore.clone();
+        let order = order.clone();
+        workers.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let started = Instant::now();
+            let placed = place_checked(&state, &auth, &signer, &order, expires_at, dry_run).await;
+            (index, placed, started.elapsed())
+        });
+    }
+
+    let mut results: Vec<Option<Result<OrderResult>>> = planned.iter().map(|_| None).collect();
+    let mut timings = vec![Duration::ZERO; planned.len()];
+    while let Some(joined) = workers.join_next().await {
+        let (index, placed, elapsed) =
+            joined.map_err(|e| anyhow::anyhow!("Order placement task failed: {}", e))?;
+        results[index] = Some(placed);
+        timings[index] = elapsed;
+    }
+
+    let results: Vec<Result<OrderResult>> = results
+        .into_iter()
+        .map(|placed| placed.expect("every placement task reports back"))
+        .collect();
+    for ((order, placed), elapsed) in planned.iter().zip(&results).zip(timings) {
+        let outcome = match placed {
+            Ok(placed) => format!("{:?}", placed.status),
+            Err(e) => format!("failed ({})", e),
+        };
+        if !dry_run {
+            let status = match placed {
+                Ok(placed) => &placed.status,
+                Err(_) => &OrderStatus::Failed,
+            };
+            state.metrics.order_placed(status.as_str());
+        }
+        logs.event(match placed {
+            Ok(placed) => BotLogEventKind::OrderPlaced {
+                outcome: order.outcome.clone(),
+                order_id: placed.order_id.clone(),
+                status: placed.status.clone(),
+            },
+            Err(e) => BotLogEventKind::OrderFailed {
+                outcome: order.outcome.clone(),
+                reason: e.to_string(),
+            },
+        });
+        logs.push(order_log(
+            dry_run,
+            format!(
+                "{} order @ ${:.4}: {} in {}ms",
+                order.outcome,
+                order.price,
+                outcome,
+                elapsed.as_millis()
+            ),
+        ));
+    }
+
+    collect_placements(planned, results)
+}
+
+/// Places a single order, re-checking the trading switch first so an
+/// operator disabling trading stops the remaining orders of a run.
+///
+/// With `dry_run` the order is returned as `Simulated` without touching the
+/// exchange or consuming a salt.
+pub(crate) async fn place_checked(
+    state: &AppState,
+    auth: &WalletAuth,
+    signer: &str,
+    order: &PlannedOrder,
+    expires_at: Option<DateTime<Utc>>,
+    dry_run: bool,
+) -> Result<OrderResult> {
+    if dry_run {
+        return Ok(OrderResult {
+            token_id: order.token_id.clone(),
+            outcome: "Unknown".to_string(),
+            side: order.side.to_string(),
+            price: order.price,
+            size: order.size,
+            order_id: None,
+            status: OrderStatus::Simulated,
+            filled_size: None,
+            error: None,
+        });
+    }
+
+    state.runtime_config.ensure_trading_enabled()?;
+    state
+        .polymarket_client
+        .place_order(
+            auth,
+            &order.token_id,
+            order.side,
+            order.price,
+            order.size,
+            state.salt_allocator.next_salt(signer),
+            expires_at,
+        )
+        .await
+}
+
+/// Pairs placement results with their planned orders. A failed placement is
+/// recorded as a `Failed` order carrying the error, so orders that did go
+/// through are still reported; only when every placement failed is the
+/// first error returned instead.
+pub fn collect_placements(
+    planned: Vec<PlannedOrder>,
+    mut results: Vec<Result<OrderResult>>,
+) -> Result<Vec<OrderResult>> {
+    if !results.is_empty() && results.iter().all(|r| r.is_err()) {
+        return Err(results.swap_remove(0).expect_err("every placement failed"));
+    }
+
+    Ok(planned
+        .into_iter()
+        .zip(results)
+        .map(|(order, placed)| match placed {
+            Ok(placed) => OrderResult {
+                outcome: order.outcome,
+                ..placed
+            },
+            Err(e) => {
+                tracing::warn!(
+                    "Order for {} @ {} failed: {}",
+                    order.token_id,
+                    order.price,
+                    e
+                );
+                OrderResult {
+                    token_id: order.token_id,
+                    outcome: order.outcome,
+                    side: order.side.to_string(),
+                    price: order.price,
+                    size: order.size,
+                    order_id: None,
+                    status: OrderStatus::Failed,
+                    filled_size: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        })
+        .collect())
+}
+
+/// Cross-checks placed orders against the exchange after `delay`.
+///
+/// Orders missing from the open-order listing are looked up individually and
+/// checked against recent trades (so instant fills aren't flagged); anything
+/// still unaccounted for is marked `Unconfirmed`.
+async fn verify_placements(
+    state: &AppState,
+    auth: &WalletAuth,
+    orders: &mut [OrderResult],
+    delay: Duration,
+) -> (PlacementVerification, bool) {
+    tokio::time::sleep(delay).await;
+
+    let mut token_ids: Vec<String> = orders.iter().map(|o| o.token_id.clone()).collect();
+    token_ids.sort();
+    token_ids.dedup();
+
+    // The orders are already live, so lookup failures leave orders
+    // unconfirmed instead of failing the run
+    let mut complete = true;
+    let (open_orders, trades) = match tokio::try_join!(
+        state.polymarket_client.get_open_orders(auth, &token_ids),
+        state.polymarket_client.get_trades(auth, &token_ids),
+    ) {
+        Ok(found) => found,
+        Err(e) => {
+            tracing::warn!("Failed to list orders for placement verification: {}", e);
+            (Vec::new(), Vec::new())
+        }
+    };
+
+    let open_ids: HashSet<&str> = open_orders.iter().map(|o| o.id.as_str()).collect();
+    let traded_ids: HashSet<&str> = trades.iter().flat_map(|t| t.order_ids()).collect();
+
+    let mut verification = PlacementVerification {
+        checked: 0,
+        confirmed_open: 0,
+        filled: 0,
+        unconfirmed: Vec::new(),
+        skipped: 0,
+    };
+
+    for order in orders.iter_mut() {
+        let Some(order_id) = order.order_id.clone() else {
+            verification.skipped += 1;
+            continue;
+        };
+        verification.checked += 1;
+
+        if traded_ids.contains(order_id.as_str()) {
+            verification.filled += 1;
+            continue;
+        }
+        if open_ids.contains(order_id.as_str()) {
+            verification.confirmed_open += 1;
+            continue;
+        }
+
+        match state.polymarket_client.get_order(auth, &order_id).await {
+            Ok(Some(found)) if matches!(found.status(), OrderStatus::Filled) => {
+                verification.filled += 1;
+            }
+            Ok(Some(_)) => verification.confirmed_open += 1,
+            Ok(None) => {
+                tracing::warn!("Order {} accepted but not found on the exchange", order_id);
+                order.status = OrderStatus::Unconfirmed;
+                verification.unconfirmed.push(order_id);
+            }
+            Err(e) => {
+                tracing::warn!("Failed to verify order {}: {}", order_id, e);
+                complete = false;
+                order.status = OrderStatus::Unconfirmed;
+                verification.unconfirmed.push(order_id);
+            }
+        }
+    }
+
+    (verification, complete)
+}
+
+/// One-paragraph, deterministic summary of a bot run for ops channels, e.g.
+/// "Placed 8/10 ladder orders on btc-updown-15m-… totaling $412.00 (2 failed:
+/// insufficient balance); window closes 14:45 UTC."
+pub fn summarize_run(
+    mode: &OrderMode,
+    market_slug: &str,
+    orders: &[OrderResult],
+    window_close: Option<DateTime<Utc>>,
+) -> String {
+    let mode_label = match mode {
+        OrderMode::Simple => "straddle",
+        OrderMode::Ladder => "ladder",
+        OrderMode::Exit => "exit",
+    };
+
+    let failed = orders
+        .iter()
+        .filter(|o| matches!(o.status, OrderStatus::Failed))
+        .count();
+    let unconfirmed = orders
+        .iter()
+        .filter(|o| matches!(o.status, OrderStatus::Unconfirmed))
+        .count();
+    let placed: Vec<&OrderResult> = orders
+        .iter()
+        .filter(|o| !matches!(o.status, OrderStatus::Failed | OrderStatus::Unconfirmed))
+        .collect();
+    // Folded from +0.0: an empty `sum` is -0.0, printed as "$-0.00"
+    let notional = placed
+        .iter()
+        .fold(0.0, |total, o| total + o.price.value() * o.size);
+
+    let simulated = !orders.is_empty()
+        && orders
+            .iter()
+            .all(|o| matches!(o.status, OrderStatus::Simulated));
+
+    let mut summary = format!(
+        "{} {}/{} {} orders on {} totaling ${:.2}",
+        if simulated {
+            "Dry run: would place"
+        } else {
+            "Placed"
         },
-    }))
+        placed.len(),
+        orders.len(),
+        mode_label,
+        market_slug,
+        notional
+    );
+
+    // Each distinct reason once, in the order the orders failed
+    let mut reasons: Vec<&str> = Vec::new();
+    for error in orders
+        .iter()
+        .filter(|o| matches!(o.status, OrderStatus::Failed))
+        .filter_map(|o| o.error.as_deref())
+    {
+        if !reasons.contains(&error) {
+            reasons.push(error);
+        }
+    }
+    let failed_label = if reasons.is_empty() {
+        "failed".to_string()
+    } else {
+        format!("failed: {}", reasons.join("; "))
+    };
+
+    let issues: Vec<String> = [
+        (failed, failed_label.as_str()),
+        (unconfirmed, "unconfirmed"),
+    ]
+    .iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, label)| format!("{} {}", count, label))
+    .collect();
+    if !issues.is_empty() {
+        summary.push_str(&format!(" ({})", issues.join(", ")));
+    }
+
+    if let Some(close) = window_close {
+        summary.push_str(&format!("; window closes {} UTC", close.format("%H:%M")));
+    }
+
+    summary.push('.');
+    summary
 }
 
+async fn summarize_run_with_ai(
+    state: &AppState,
+    summary: &str,
+    orders: &[OrderResult],
+) -> Result<String> {
+    let order_lines = orders
+        .iter()
+        .map(|o| {
+            format!(
+                "- {} {:.2} shares of {} @ ${:.4}: {:?}",
+                o.side, o.size, o.outcome, o.price, o.status
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let prompt = build_run_summary_prompt(&format!("{}\n\nOrders:\n{}", summary, order_lines));
+
+    let ai_client = state.ai_client(AiProvider::Grok, &AiRequestOptions::default())?;
+    let text = ai_client.complete(prompt).await?;
+    Ok(text.trim().to_string())
+}
@@ -0,0 +1,303 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::extract::AppJson;
+use crate::api::fill_watcher::spawn_fill_watcher;
+use crate::api::limit_order_bot::{
+    fetch_market, place_orders, plan_orders, resolve_expiry, resolve_targets, round_planned,
+    validate_request, wallet_auth, BotLog, PlannedOrder,
+};
+use crate::api::market_cache::CacheQuery;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{
+    DiffApplied, DiffOrder, LimitOrderDiffRequest, LimitOrderDiffResponse, OrderMode,
+    ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+/// Half a tick: anything closer is the same price level.
+const DEFAULT_PRICE_TOLERANCE: f64 = 0.005;
+const DEFAULT_SIZE_TOLERANCE: f64 = 0.05;
+const TOLERANCE_EPSILON: f64 = 1e-9;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(cache): Query<CacheQuery>,
+    AppJson(request): AppJson<LimitOrderDiffRequest>,
+) -> Result<Json<LimitOrderDiffResponse>> {
+    let start = Instant::now();
+    let mut logs = BotLog::default();
+    let apply = request.apply.unwrap_or(false);
+    let bot = request.bot;
+
+    // Validate request
+    if apply {
+        state.runtime_config.ensure_trading_enabled()?;
+    }
+    validate_request(&bot, state.config.wallet.as_ref())?;
+    if let Some(webhook_url) = &bot.webhook_url {
+        state.webhooks.check_url(webhook_url).await?;
+    }
+    if let OrderMode::Exit = bot.mode {
+        return Err(AppError::Validation(
+            "Exit mode is not supported by the diff; it reconciles buy orders only".to_string(),
+        ));
+    }
+
+    let expires_at = resolve_expiry(&bot, state.config.order_expiry_margin)?;
+
+    let price_tolerance = request.price_tolerance.unwrap_or(DEFAULT_PRICE_TOLERANCE);
+    let size_tolerance = request.size_tolerance.unwrap_or(DEFAULT_SIZE_TOLERANCE);
+
+    let auth = wallet_auth(&bot, state.config.wallet.as_ref())?;
+    // Checked up front so credentials that can't sign never cancel anything
+    if apply {
+        auth.signer()?;
+    }
+    let wallet = auth.address().to_checksum(None);
+    logs.push(format!("Wallet: {}", wallet));
+
+    let (market, _, cache_hit) = fetch_market(&state, &bot, cache.fresh(), &mut logs).await?;
+    let outcome_names: HashMap<String, String> = market
+        .outcomes
+        .iter()
+        .map(|o| (o.id.clone(), o.name.clone()))
+        .collect();
+
+    let targets = resolve_targets(&market, bot.outcomes.as_deref())?;
+    let token_ids: Vec<String> = targets.iter().map(|t| t.outcome.id.clone()).collect();
+    let (planned, _) = plan_orders(&state, &bot, &market, &targets, &mut logs).await?;
+    let (planned, _) = round_planned(&state, planned, &mut logs).await?;
+
+    // The bot only places buys; resting sells are never ours to cancel
+    let live: Vec<LiveOrder> = state
+        .polymarket_client
+        .get_open_orders(&auth, &token_ids)
+        .await?
+        .into_iter()
+        .filter(|o| o.side.eq_ignore_ascii_case("buy"))
+        .map(|o| LiveOrder {
+            price: o.price(),
+            size: o.remaining_size(),
+            id: o.id,
+            token_id: o.asset_id,
+        })
+        .collect();
+    logs.push(format!(
+        "Planned {} orders against {} resting buy orders",
+        planned.len(),
+        live.len()
+    ));
+
+    let reconciliation = reconcile(planned, live, price_tolerance, size_tolerance);
+    let net_notional_change = reconciliation.net_notional_change();
+    logs.push(format!(
+        "Keep {}, cancel {}, add {} (net notional {:+.2})",
+        reconciliation.keep.len(),
+        reconciliation.cancel.len(),
+        reconciliation.add.len(),
+        net_notional_change
+    ));
+
+    let outcome_of = |token_id: &str| {
+        outcome_names
+            .get(token_id)
+            .cloned()
+            .unwrap_or_else(|| "Unknown".to_string())
+    };
+
+    let applied = if apply {
+        // Checked before cancelling so a refusal leaves the book untouched
+        let cancel_ids: Vec<String> = reconciliation.cancel.iter().map(|o| o.id.clone()).collect();
+        let _reservation = state
+            .exposure_caps
+            .enforce(
+                &state,
+                &auth,
+                &market,
+                &reconciliation.add,
+                &cancel_ids,
+                bot.override_caps.unwrap_or(false),
+                &mut logs,
+            )
+            .await?;
+
+        let cancelled = state
+            .polymarket_client
+            .cancel_orders(&auth, &cancel_ids)
+            .await?;
+        logs.push(format!(
+            "Cancelled {} orders ({} refused)",
+            cancelled.canceled.len(),
+            cancelled.not_canceled.len()
+        ));
+
+        let placed = place_orders(
+            &state,
+            &auth,
+            reconciliation.add.clone(),
+            expires_at,
+            false,
+            &mut logs,
+        )
+        .await?;
+        if let Some(webhook_url) = &bot.webhook_url {
+            spawn_fill_watcher(
+                &state,
+                &auth,
+                webhook_url,
+                market.slug.as_deref(),
+                None,
+                &placed,
+            );
+        }
+
+        Some(DiffApplied {
+            cancelled: cancelled.canceled,
+            cancel_failed: cancelled.not_canceled,
+            placed,
+        })
+    } else {
+        None
+    };
+
+    let keep = reconciliation
+        .keep
+        .iter()
+        .map(|(live, _)| live.to_diff(outcome_of(&live.token_id)))
+        .collect();
+    let cancel = reconciliation
+        .cancel
+        .iter()
+        .map(|live| live.to_diff(outcome_of(&live.token_id)))
+        .collect();
+    let add = reconciliation
+        .add
+        .iter()
+        .map(|planned| DiffOrder {
+            order_id: None,
+            token_id: planned.token_id.clone(),
+            outcome: planned.outcome.clone(),
+            price: planned.price.value(),
+            size: planned.size,
+        })
+        .collect();
+
+    Ok(Json(LimitOrderDiffResponse {
+        market,
+        keep,
+        cancel,
+        add,
+        net_notional_change,
+        applied,
+        logs: logs.into_parts().0,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: !apply,
+            cache_hit: Some(cache_hit),
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+/// A resting buy order on the exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveOrder {
+    pub id: String,
+    pub token_id: String,
+    pub price: f64,
+    /// Remaining (unmatched) shares
+    pub size: f64,
+}
+
+impl LiveOrder {
+    fn to_diff(&self, outcome: String) -> DiffOrder {
+        DiffOrder {
+            order_id: Some(self.id.clone()),
+            token_id: self.token_id.clone(),
+            outcome,
+            price: self.price,
+            size: self.size,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Reconciliation {
+    /// Resting orders paired with the planned order they satisfy
+    pub keep: Vec<(LiveOrder, PlannedOrder)>,
+    pub cancel: Vec<LiveOrder>,
+    pub add: Vec<PlannedOrder>,
+}
+
+impl Reconciliation {
+    /// Notional of orders to add minus notional of orders to cancel, in USD.
+    pub fn net_notional_change(&self) -> f64 {
+        let added: f64 = self.add.iter().map(|o| o.price.value() * o.size).sum();
+        let cancelled: f64 = self.cancel.iter().map(|o| o.price * o.size).sum();
+        ((added - cancelled) * 100.0).round() / 100.0
+    }
+}
+
+/// Pairs each planned order with at most one resting order on the same token
+/// whose price is within `price_tolerance` and whose size is within
+/// `size_tolerance` (a fraction of the planned size).
+///
+/// When several resting orders qualify — e.g. duplicates at one price — the
+/// closest in price, then size, wins and the rest are cancelled. Planned
+/// orders without a match are added.
+pub fn reconcile(
+    planned: Vec<PlannedOrder>,
+    live: Vec<LiveOrder>,
+    price_tolerance: f64,
+    size_tolerance: f64,
+) -> Reconciliation {
+    let mut unmatched: Vec<Option<LiveOrder>> = live.into_iter().map(Some).collect();
+    let mut reconciliation = Reconciliation::default();
+
+    for order in planned {
+        let price = order.price.value();
+        let price_gap = |live: &LiveOrder| (live.price - price).abs();
+        let size_gap = |live: &LiveOrder| (live.size - order.size).abs();
+
+        let best = unmatched
+            .iter()
+            .enumerate()
+            .filter_map(|(i, live)| live.as_ref().map(|live| (i, live)))
+            .filter(|(_, live)| {
+                live.token_id == order.token_id
+                    && price_gap(live) <= price_tolerance + TOLERANCE_EPSILON
+                    && size_gap(live) <= size_tolerance * order.size + TOLERANCE_EPSILON
+            })
+            .min_by(|(_, a), (_, b)| {
+                price_gap(a)
+                    .total_cmp(&price_gap(b))
+                    .then(size_gap(a).total_cmp(&size_gap(b)))
+            })
+            .map(|(i, _)| i);
+
+        match best.and_then(|i| unmatched[i].take()) {
+            Some(live) => reconciliation.keep.push((live, order)),
+            None => reconciliation.add.push(order),
+        }
+    }
+
+    reconciliation.cancel = unmatched.into_iter().flatten().collect();
+    reconciliation
+}
@@ -0,0 +1,226 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::IntoParams;
+
+use crate::clients::polymarket::{MarketSearch, MarketSearchPage};
+use crate::clients::{KalshiVenue, MarketDataSource};
+use crate::types::{MarketData, Platform};
+use crate::Result;
+
+pub const DEFAULT_POLYMARKET_TTL_SECS: u64 = 10;
+pub const DEFAULT_DOME_TTL_SECS: u64 = 60;
+pub const DEFAULT_SEARCH_TTL_SECS: u64 = 60;
+/// Past this many keys, expired entries are dropped on the next insert.
+const PRUNE_THRESHOLD: usize = 1000;
+
+/// `?fresh=true` bypasses the market cache for one request.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CacheQuery {
+    /// Bypass the market cache
+    pub fresh: Option<bool>,
+}
+
+impl CacheQuery {
+    pub fn fresh(&self) -> bool {
+        self.fresh.unwrap_or(false)
+    }
+}
+
+/// A market from the cache, and whether it was served without an upstream
+/// call.
+#[derive(Debug, Clone)]
+pub struct CachedMarket {
+    pub market: Arc<MarketData>,
+    pub hit: bool,
+}
+
+type Slot = Arc<tokio::sync::Mutex<Option<(Arc<MarketData>, Instant)>>>;
+
+/// Recently fetched markets, so pollers of the same market share one
+/// upstream call per TTL. Each key has its own lock, held across the fetch:
+/// concurrent misses for a key wait for the first caller's result instead of
+/// fetching it again. Failures are not cached.
+#[derive(Debug)]
+pub struct MarketCache {
+    slots: Mutex<HashMap<String, Slot>>,
+    polymarket_ttl: Duration,
+    dome_ttl: Duration,
+}
+
+impl MarketCache {
+    pub fn new(polymarket_ttl: Duration, dome_ttl: Duration) -> Self {
+        Self {
+            slots: Mutex::new(HashMap::new()),
+            polymarket_ttl,
+            dome_ttl,
+        }
+    }
+
+    /// How long Polymarket and Kalshi markets are reused; responses built
+    /// on live prices advertise it as their `max-age`.
+    pub fn polymarket_ttl(&self) -> Duration {
+        self.polymarket_ttl
+    }
+
+    /// A Polymarket market by slug, fetched with `fetch` on a miss.
+    pub async fn polymarket<F, Fut>(
+        &self,
+        slug: &str,
+        fresh: bool,
+        fetch: F,
+    ) -> Result<CachedMarket>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<MarketData>>,
+    {
+        self.get_or_fetch(
+            format!("polymarket:{}", slug),
+            self.polymarket_ttl,
+            fresh,
+            fetch,
+        )
+        .await
+    }
+
+    /// A Kalshi market by ticker from the direct client. Prices move, so it
+    /// shares the Polymarket TTL.
+    pub async fn kalshi(
+        &self,
+        kalshi: &dyn KalshiVenue,
+        ticker: &str,
+        fresh: bool,
+    ) -> Result<CachedMarket> {
+        self.get_or_fetch(
+            format!("kalshi:{}", ticker),
+            self.polymarket_ttl,
+            fresh,
+            || kalshi.get_market(ticker),
+        )
+        .await
+    }
+
+    /// A market from Dome by platform and identifier.
+    pub async fn dome(
+        &self,
+        dome: &dyn MarketDataSource,
+        platform: Platform,
+        identifier: &str,
+        fresh: bool,
+    ) -> Result<CachedMarket> {
+        let key = format!("dome:{:?}:{}", platform, identifier);
+        self.get_or_fetch(key, self.dome_ttl, fresh, || {
+            dome.get_market(platform, identifier)
+        })
+        .await
+    }
+
+    async fn get_or_fetch<F, Fut>(
+        &self,
+        key: String,
+        ttl: Duration,
+        fresh: bool,
+        fetch: F,
+    ) -> Result<CachedMarket>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<MarketData>>,
+    {
+        if ttl.is_zero() {
+            return Ok(CachedMarket {
+                market: Arc::new(fetch().await?),
+                hit: false,
+            });
+        }
+
+        let slot = self.slot(key);
+        let mut entry = slot.lock().await;
+        if let (Some((market, fetched_at)), false) = (entry.as_ref(), fresh) {
+            if fetched_at.elapsed() < ttl {
+                return Ok(CachedMarket {
+                    market: market.clone(),
+                    hit: true,
+                });
+            }
+        }
+
+        let market = Arc::new(fetch().await?);
+        *entry = Some((market.clone(), Instant::now()));
+        Ok(CachedMarket { market, hit: false })
+    }
+
+    fn slot(&self, key: String) -> Slot {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        if slots.len() >= PRUNE_THRESHOLD && !slots.contains_key(&key) {
+            let ttl = self.polymarket_ttl.max(self.dome_ttl);
+            // Busy slots are mid-fetch and about to be fresh
+            slots.retain(|_, slot| match slot.try_lock() {
+                Ok(entry) => entry
+                    .as_ref()
+                    .is_some_and(|(_, fetched_at)| fetched_at.elapsed() < ttl),
+                Err(_) => true,
+            });
+        }
+        slots.entry(key).or_default().clone()
+    }
+}
+
+/// Recent market search pages by their filters. Listings change slowly and
+/// are browsed a page at a time, so a page is reused for the TTL. Unlike
+/// [`MarketCache`] concurrent misses each fetch; failures are not cached.
+#[derive(Debug)]
+pub struct MarketSearchCache {
+    pages: Mutex<HashMap<String, (Arc<MarketSearchPage>, Instant)>>,
+    ttl: Duration,
+}
+
+impl MarketSearchCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            pages: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// The page for `search`, fetched with `fetch` on a miss or with
+    /// `fresh`, and whether it came from the cache.
+    pub async fn search<F, Fut>(
+        &self,
+        search: &MarketSearch,
+        fresh: bool,
+        fetch: F,
+    ) -> Result<(Arc<MarketSearchPage>, bool)>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<MarketSearchPage>>,
+    {
+        if self.ttl.is_zero() {
+            return Ok((Arc::new(fetch().await?), false));
+        }
+
+        let key = format!("{:?}", search);
+        if !fresh {
+            let pages = self.pages.lock().unwrap_or_else(|e| e.into_inner());
+            if let Some((page, fetched_at)) = pages.get(&key) {
+                if fetched_at.elapsed() < self.ttl {
+                    return Ok((page.clone(), true));
+                }
+            }
+        }
+
+        let page = Arc::new(fetch().await?);
+        let mut pages = self.pages.lock().unwrap_or_else(|e| e.into_inner());
+        if pages.len() >= PRUNE_THRESHOLD {
+            pages.retain(|_, (_, fetched_at)| fetched_at.elapsed() < self.ttl);
+        }
+        pages.insert(key, (page.clone(), Instant::now()));
+        Ok((page, false))
+    }
+}
@@ -0,0 +1,134 @@
+use axum::extract::{Query, State};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::fields::select_response;
+use crate::api::http_cache::Cacheable;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{
+    CandleInterval, CandleSummary, MarketData, MarketHistoryResponse, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+/// Most candles one request may span, e.g. a day of one-minute candles.
+pub const MAX_CANDLES: i64 = 1440;
+
+#[derive(Debug, Deserialize)]
+pub struct MarketHistoryQuery {
+    pub url: String,
+    /// `1m`, `1h` or `1d`; defaults to `1m`
+    pub interval: Option<String>,
+    /// How far back to go, e.g. `90m`, `2h` or `7d`; defaults to `2h`
+    pub lookback: Option<String>,
+    /// Bypass the market cache
+    pub fresh: Option<bool>,
+    /// Comma-separated fields to return, e.g. `summary,market.slug`
+    pub fields: Option<String>,
+}
+
+/// Dome price candles for a Polymarket market, with the momentum summary
+/// the analysis prompt uses.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MarketHistoryQuery>,
+) -> Result<Cacheable<Value>> {
+    let start = Instant::now();
+
+    // Validate request
+    let interval: CandleInterval = query
+        .interval
+        .as_deref()
+        .unwrap_or("1m")
+        .parse()
+        .map_err(AppError::Validation)?;
+    let lookback = parse_lookback(query.lookback.as_deref().unwrap_or("2h"))?;
+    let candles = lookback.num_minutes() / interval.minutes();
+    if candles < 1 {
+        return Err(AppError::Validation(format!(
+            "lookback must cover at least one {} candle",
+            interval
+        )));
+    }
+    if candles > MAX_CANDLES {
+        return Err(AppError::Validation(format!(
+            "lookback spans {} {} candles; at most {} are allowed",
+            candles, interval, MAX_CANDLES
+        )));
+    }
+
+    let dome = state.dome()?;
+    let market_ref = dome.market_ref_from_url(&query.url)?;
+    let cached = state
+        .market_cache
+        .dome(
+            dome,
+            market_ref.platform,
+            &market_ref.identifier,
+            query.fresh.unwrap_or(false),
+        )
+        .await?;
+    let market = MarketData::clone(&cached.market);
+    let condition_id = market.condition_id.clone().ok_or_else(|| {
+        AppError::Validation("Price history is only available for Polymarket markets".to_string())
+    })?;
+
+    let end = Utc::now();
+    let from = end - lookback;
+    let candles = dome
+        .get_candles(market.platform, &condition_id, interval, from, end)
+        .await?;
+    let summary = CandleSummary::from_candles(&candles);
+
+    let response = MarketHistoryResponse {
+        market,
+        interval,
+        start: from,
+        end,
+        candles,
+        summary,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: Some(cached.hit),
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    };
+    Ok(Cacheable::new(
+        select_response(&response, query.fields.as_deref())?,
+        state.market_cache.polymarket_ttl(),
+    ))
+}
+
+/// A positive count of minutes, hours or days, e.g. `90m`, `2h` or `7d`.
+fn parse_lookback(value: &str) -> Result<Duration> {
+    let invalid = || {
+        AppError::Validation(format!(
+            "Invalid lookback '{}'; expected a number followed by m, h or d, e.g. 2h",
+            value
+        ))
+    };
+    let value = value.trim();
+    let (unit_at, _) = value.char_indices().last().ok_or_else(invalid)?;
+    let (count, unit) = value.split_at(unit_at);
+    let count: i64 = count.parse().ok().filter(|n| *n > 0).ok_or_else(invalid)?;
+    let lookback = match unit {
+        "m" => Duration::try_minutes(count),
+        "h" => Duration::try_hours(count),
+        "d" => Duration::try_days(count),
+        _ => None,
+    };
+    lookback.ok_or_else(invalid)
+}
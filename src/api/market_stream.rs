@@ -0,0 +1,261 @@
+use axum::{
+    extract::{
+        ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade},
+        Path, State,
+    },
+    response::Response,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::time::MissedTickBehavior;
+
+use crate::api::{middleware, AppState};
+use crate::types::{MarketData, Outcome};
+use crate::Result;
+
+pub const DEFAULT_POLL_MS: u64 = 2000;
+/// Messages buffered per subscriber before a slow one starts skipping
+const CHANNEL_CAPACITY: usize = 64;
+
+type Feed = broadcast::Sender<MarketStreamMessage>;
+
+/// A message pushed on `/ws/market/:slug`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MarketStreamMessage {
+    /// An outcome's current price: every outcome on connect, then each change
+    Price {
+        token_id: String,
+        outcome: String,
+        price: f64,
+        ts: DateTime<Utc>,
+    },
+    /// The market stopped trading; the socket is closed after this
+    MarketClosed {
+        slug: String,
+        resolved_outcome: Option<String>,
+        ts: DateTime<Utc>,
+    },
+}
+
+impl MarketStreamMessage {
+    fn price(outcome: &Outcome) -> Self {
+        MarketStreamMessage::Price {
+            token_id: outcome.id.clone(),
+            outcome: outcome.name.clone(),
+            price: outcome.price.value(),
+            ts: Utc::now(),
+        }
+    }
+
+    fn closed(slug: &str, market: &MarketData) -> Self {
+        MarketStreamMessage::MarketClosed {
+            slug: slug.to_string(),
+            resolved_outcome: market.resolved_outcome.clone(),
+            ts: Utc::now(),
+        }
+    }
+}
+
+/// One Gamma poller per streamed market, shared by all of its sockets
+/// through a broadcast channel. A poller stops once its last socket goes
+/// away or the market closes.
+#[derive(Debug)]
+pub struct MarketStreams {
+    feeds: Mutex<HashMap<String, Feed>>,
+    poll_interval: Duration,
+}
+
+impl MarketStreams {
+    pub fn new(poll_interval: Duration) -> Self {
+        Self {
+            feeds: Mutex::new(HashMap::new()),
+            poll_interval,
+        }
+    }
+
+    /// Joins the market's feed, starting its poller from `market` when no
+    /// one else is streaming it.
+    fn subscribe(
+        &self,
+        state: &Arc<AppState>,
+        slug: &str,
+        market: &MarketData,
+    ) -> broadcast::Receiver<MarketStreamMessage> {
+        let mut feeds = self.lock();
+        if let Some(feed) = feeds.get(slug) {
+            return feed.subscribe();
+        }
+        let (feed, updates) = broadcast::channel(CHANNEL_CAPACITY);
+        feeds.insert(slug.to_string(), feed.clone());
+        let prices = market
+            .outcomes
+            .iter()
+            .map(|outcome| (outcome.id.clone(), outcome.price.value()))
+            .collect();
+        tokio::spawn(poll_market(state.clone(), slug.to_string(), feed, prices));
+        updates
+    }
+
+    /// Drops the feed when nobody is listening. Checked under the same lock
+    /// as [`subscribe`](Self::subscribe), so a socket can't join a feed
+    /// that is going away.
+    fn release_if_unused(&self, slug: &str, feed: &Feed) -> bool {
+        let mut feeds = self.lock();
+        if feed.receiver_count() > 0 {
+            return false;
+        }
+        feeds.remove(slug);
+        true
+    }
+
+    fn remove(&self, slug: &str) {
+        self.lock().remove(slug);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Feed>> {
+        self.feeds.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Trading is over once Gamma says so or the window has ended.
+fn is_closed(market: &MarketData) -> bool {
+    market.closed || market.end_date.is_some_and(|end| end <= Utc::now())
+}
+
+/// Streams a Polymarket market's outcome prices over a WebSocket: the
+/// current prices on connect, then each change, then `market_closed` when
+/// the market stops trading.
+pub async fn handler(
+    ws: WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<Response> {
+    // Resolved before upgrading, so an unknown market is a plain 404
+    let market = state.polymarket_client.get_market_by_slug(&slug).await?;
+    // Echoes the subprotocol a browser authenticated with, or it drops the socket
+    Ok(ws
+        .protocols([middleware::STREAM_AUTH_PROTOCOL])
+        .on_upgrade(move |socket| serve(socket, state, slug, market)))
+}
+
+async fn serve(mut socket: WebSocket, state: Arc<AppState>, slug: String, market: MarketData) {
+    if is_closed(&market) {
+        let _ = send(&mut socket, &MarketStreamMessage::closed(&slug, &market)).await;
+        close(&mut socket, close_code::NORMAL, "market closed").await;
+        return;
+    }
+
+    // Joined before the snapshot goes out, so no change falls in between
+    let mut updates = state.market_streams.subscribe(&state, &slug, &market);
+    for outcome in &market.outcomes {
+        if send(&mut socket, &MarketStreamMessage::price(outcome))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(message) => {
+                    if send(&mut socket, &message).await.is_err() {
+                        return;
+                    }
+                    if matches!(message, MarketStreamMessage::MarketClosed { .. }) {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::debug!(
+                        "Price stream for {} skipped {} updates for a slow client",
+                        slug,
+                        skipped
+                    );
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                None | Some(Err(_)) | Some(Ok(Message::Close(_))) => return,
+                // Pings are answered by axum; anything else is ignored
+                Some(Ok(_)) => {}
+            },
+            _ = state.shutdown.cancelled() => {
+                close(&mut socket, close_code::AWAY, "server shutting down").await;
+                return;
+            }
+        }
+    }
+    close(&mut socket, close_code::NORMAL, "market closed").await;
+}
+
+async fn send(
+    socket: &mut WebSocket,
+    message: &MarketStreamMessage,
+) -> std::result::Result<(), axum::Error> {
+    let text = serde_json::to_string(message).expect("stream messages serialize");
+    socket.send(Message::Text(text)).await
+}
+
+async fn close(socket: &mut WebSocket, code: u16, reason: &'static str) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+/// Polls Gamma for `slug` and broadcasts each outcome whose price moved
+/// since `prices`, until the market closes or nobody is listening.
+async fn poll_market(
+    state: Arc<AppState>,
+    slug: String,
+    feed: Feed,
+    mut prices: HashMap<String, f64>,
+) {
+    let streams = &state.market_streams;
+    let period = streams.poll_interval;
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {}
+            _ = state.shutdown.cancelled() => {
+                streams.remove(&slug);
+                return;
+            }
+        }
+        if streams.release_if_unused(&slug, &feed) {
+            tracing::debug!("Price stream for {} has no subscribers; stopping", slug);
+            return;
+        }
+
+        let market = match state.polymarket_client.get_market_by_slug(&slug).await {
+            Ok(market) => market,
+            Err(e) => {
+                tracing::warn!("Price stream for {} failed to poll Gamma: {}", slug, e);
+                continue;
+            }
+        };
+        for outcome in &market.outcomes {
+            let price = outcome.price.value();
+            if prices.insert(outcome.id.clone(), price) != Some(price) {
+                let _ = feed.send(MarketStreamMessage::price(outcome));
+            }
+        }
+        if is_closed(&market) {
+            // Removed first, so later sockets start a fresh feed that sees
+            // the close straight away
+            streams.remove(&slug);
+            let _ = feed.send(MarketStreamMessage::closed(&slug, &market));
+            return;
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use axum::extract::{Query, State};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::api::extract::AppQuery;
+use crate::api::fields::select_response;
+use crate::api::http_cache::Cacheable;
+use crate::api::market_cache::CacheQuery;
+use crate::api::pagination::{resolve_page, Paginated};
+use crate::api::AppState;
+use crate::clients::polymarket::MarketSearch;
+use crate::types::{MarketSearchRequest, MarketSummary};
+use crate::Result;
+
+const DEFAULT_LIMIT: usize = 25;
+/// Gamma's own page limit
+const MAX_LIMIT: usize = 100;
+/// Cursors are bound to the endpoint that issued them
+const CURSOR_SCOPE: &str = "/api/markets";
+
+/// Polymarket markets to pick from, e.g.
+/// `GET /api/markets?query=fed&min_volume=10000&limit=25`. Pages are cached
+/// for `MARKET_SEARCH_CACHE_TTL_SECS`, which is also the `max-age` clients
+/// may reuse them for; `?fresh=true` bypasses the cache and `?fields=`
+/// trims the page to the selected fields.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(cache): Query<CacheQuery>,
+    AppQuery(request): AppQuery<MarketSearchRequest>,
+) -> Result<Cacheable<Value>> {
+    let page = resolve_page(
+        request.cursor.as_deref(),
+        request.offset,
+        request.limit,
+        CURSOR_SCOPE,
+        DEFAULT_LIMIT,
+        MAX_LIMIT,
+    )?;
+    let search = MarketSearch {
+        query: request.query.map(|q| q.trim().to_string()),
+        active_only: request.active.unwrap_or(true),
+        tag: request.tag.map(|t| t.trim().to_lowercase()),
+        min_volume: request.min_volume,
+        min_liquidity: request.min_liquidity,
+        offset: page.offset,
+        limit: page.limit,
+    };
+
+    let (found, _) = state
+        .market_search_cache
+        .search(&search, cache.fresh(), || {
+            state.polymarket_client.search_markets(&search)
+        })
+        .await?;
+
+    let page: Paginated<MarketSummary> =
+        Paginated::new(found.markets.clone(), &page, found.has_more, None);
+    Ok(Cacheable::new(
+        select_response(&page, request.fields.as_deref())?,
+        state.market_search_cache.ttl(),
+    ))
+}
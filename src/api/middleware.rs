@@ -0,0 +1,349 @@
+use alloy_primitives::hex;
+use axum::{
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+use crate::api::admin::constant_time_eq;
+use crate::api::openapi;
+use crate::api::AppState;
+use crate::error::{ResponseVersion, RESPONSE_VERSION_HEADER};
+use crate::request_id::{self, REQUEST_ID_HEADER};
+use crate::{AppError, Result};
+
+const WINDOW: Duration = Duration::from_secs(60);
+pub const DEFAULT_AI_PER_MIN: usize = 10;
+pub const DEFAULT_STANDARD_PER_MIN: usize = 60;
+/// Routes that trigger paid AI calls; prefixes, so sub-routes match too.
+const AI_ROUTES: &[&str] = &[
+    "/api/analyze-event-markets",
+    "/api/analyze-and-trade",
+    "/api/construct-portfolio",
+    "/api/analysis-subscriptions",
+    "/api/jobs/analyze",
+];
+const EXEMPT_ROUTES: &[&str] = &["/health", "/ready", "/metrics"];
+
+/// Which limit a route counts against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    /// AI-backed routes (`RATE_LIMIT_AI_PER_MIN`)
+    Ai,
+    /// Everything else (`RATE_LIMIT_PER_MIN`)
+    Standard,
+}
+
+impl RouteGroup {
+    /// The group for `path`, or `None` when it is never limited.
+    pub fn for_path(path: &str) -> Option<Self> {
+        let matches = |route: &&str| {
+            path.strip_prefix(*route)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        };
+        if EXEMPT_ROUTES.iter().any(matches) {
+            None
+        } else if AI_ROUTES.iter().any(matches) {
+            Some(RouteGroup::Ai)
+        } else {
+            Some(RouteGroup::Standard)
+        }
+    }
+}
+
+/// Per-client sliding-window request limits: each IP may make a group's
+/// limit of requests in any 60 seconds.
+#[derive(Debug)]
+pub struct IpRateLimiter {
+    hits: Mutex<HashMap<(IpAddr, RouteGroup), VecDeque<Instant>>>,
+    ai_per_min: usize,
+    standard_per_min: usize,
+    /// Take the client from the last `X-Forwarded-For` entry, the one the
+    /// reverse proxy appended; earlier entries are whatever the client sent
+    trust_forwarded_for: bool,
+}
+
+impl IpRateLimiter {
+    /// Limits per minute for each group; 0 turns a group's limit off.
+    pub fn new(ai_per_min: usize, standard_per_min: usize, trust_forwarded_for: bool) -> Self {
+        Self {
+            hits: Mutex::new(HashMap::new()),
+            ai_per_min,
+            standard_per_min,
+            trust_forwarded_for,
+        }
+    }
+
+    fn limit(&self, group: RouteGroup) -> usize {
+        match group {
+            RouteGroup::Ai => self.ai_per_min,
+            RouteGroup::Standard => self.standard_per_min,
+        }
+    }
+
+    /// Counts a request from `ip` at `now`, refusing it with the wait until
+    /// the oldest request in the window expires once the limit is reached.
+    pub fn check(&self, ip: IpAddr, group: RouteGroup, now: Instant) -> Result<()> {
+        let limit = self.limit(group);
+        if limit == 0 {
+            return Ok(());
+        }
+
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        let window = hits.entry((ip, group)).or_default();
+        while window
+            .front()
+            .is_some_and(|at| now.saturating_duration_since(*at) >= WINDOW)
+        {
+            window.pop_front();
+        }
+        if window.len() >= limit {
+            let oldest = window.front().copied().unwrap_or(now);
+            return Err(AppError::RateLimit {
+                retry_after: Some(WINDOW.saturating_sub(now.saturating_duration_since(oldest))),
+                upstream: None,
+            });
+        }
+        window.push_back(now);
+        Ok(())
+    }
+
+    /// Forgets clients with no requests in the window.
+    pub fn prune(&self, now: Instant) {
+        let mut hits = self.hits.lock().unwrap_or_else(|e| e.into_inner());
+        hits.retain(|_, window| {
+            window
+                .back()
+                .is_some_and(|at| now.saturating_duration_since(*at) < WINDOW)
+        });
+    }
+
+    fn client_ip(&self, request: &Request) -> IpAddr {
+        if self.trust_forwarded_for {
+            let forwarded = request
+                .headers()
+                .get("x-forwarded-for")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.rsplit(',').next())
+                .and_then(|ip| ip.trim().parse().ok());
+            if let Some(ip) = forwarded {
+                return ip;
+            }
+        }
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip())
+            // Served without connect info: every client shares one budget
+            .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED))
+    }
+}
+
+/// Applies [`IpRateLimiter`] to every route but `/health`, `/ready` and
+/// `/metrics`, answering 429 with `Retry-After` once a client is over its
+/// group's limit.
+pub async fn rate_limit(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if let Some(group) = RouteGroup::for_path(request.uri().path()) {
+        let limiter = &state.ip_rate_limiter;
+        let ip = limiter.client_ip(&request);
+        if let Err(e) = limiter.check(ip, group, Instant::now()) {
+            tracing::warn!("Rate limited {} on {}", ip, request.uri().path());
+            return e.into_response();
+        }
+    }
+    next.run(request).await
+}
+
+/// Prunes idle clients from the limiter once a window, until shutdown.
+pub fn spawn_pruner(limiter: Arc<IpRateLimiter>, shutdown: CancellationToken) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(WINDOW);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            limiter.prune(Instant::now());
+        }
+    });
+}
+
+/// Bearer tokens accepted on `/api/*` and `/ws/*`, from `API_AUTH_TOKENS`
+/// (comma-separated). With none configured every request is let through.
+#[derive(Clone, Default)]
+pub struct ApiAuth {
+    tokens: Vec<String>,
+}
+
+impl std::fmt::Debug for ApiAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiAuth")
+            .field("tokens", &self.tokens.len())
+            .finish()
+    }
+}
+
+impl ApiAuth {
+    pub fn new(tokens: Vec<String>) -> Self {
+        Self { tokens }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.tokens.is_empty()
+    }
+
+    /// Checks an `Authorization` header value, returning the fingerprint of
+    /// the token it carries. Every configured token is compared so the time
+    /// taken doesn't reveal which one came close.
+    pub fn authenticate(&self, authorization: Option<&str>) -> Result<String> {
+        let provided = authorization
+            .and_then(|v| v.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| {
+                AppError::Unauthorized("Missing Authorization: Bearer <token> header".to_string())
+            })?;
+
+        let matched = self.tokens.iter().fold(false, |matched, token| {
+            constant_time_eq(provided.as_bytes(), token.as_bytes()) | matched
+        });
+        if !matched {
+            return Err(AppError::Unauthorized("Invalid API token".to_string()));
+        }
+        Ok(token_fingerprint(provided))
+    }
+}
+
+/// A short, non-reversible label for a token, safe to log.
+pub fn token_fingerprint(token: &str) -> String {
+    let digest = Sha256::digest(token.as_bytes());
+    hex::encode(&digest[..4])
+}
+
+/// Subprotocol a browser offers alongside its token on `/ws/*`, where it
+/// can't set headers: `new WebSocket(url, ["bearer", token])`.
+pub const STREAM_AUTH_PROTOCOL: &str = "bearer";
+
+/// Requires a configured bearer token on `/api/*`, `/ws/*` (and `/metrics`
+/// with `METRICS_REQUIRE_AUTH`) when [`ApiAuth`] is enabled, answering 401
+/// otherwise. The API docs and their spec stay public. The token's
+/// fingerprint is recorded on the request span.
+pub async fn require_api_token(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let auth = &state.api_auth;
+    let path = request.uri().path();
+    let docs = path == openapi::SPEC_PATH
+        || path
+            .strip_prefix(openapi::DOCS_PATH)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'));
+    let stream = path.starts_with("/ws/");
+    let protected = (path.starts_with("/api/") && !docs)
+        || stream
+        || (path == "/metrics" && state.config.metrics_require_auth);
+    if auth.is_enabled() && protected {
+        let headers = request.headers();
+        let authorization = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| stream.then(|| stream_token(headers)).flatten());
+        match auth.authenticate(authorization.as_deref()) {
+            Ok(fingerprint) => {
+                tracing::Span::current().record("token", fingerprint.as_str());
+            }
+            Err(e) => {
+                tracing::warn!("Rejected unauthenticated request: {}", e);
+                return e.into_response();
+            }
+        }
+    }
+
+    next.run(request).await
+}
+
+/// The token in a `Sec-WebSocket-Protocol: bearer, <token>` offer, as an
+/// `Authorization` value.
+fn stream_token(headers: &HeaderMap) -> Option<String> {
+    let offered = headers.get(header::SEC_WEBSOCKET_PROTOCOL)?.to_str().ok()?;
+    let mut protocols = offered.split(',').map(str::trim);
+    protocols
+        .position(|p| p == STREAM_AUTH_PROTOCOL)
+        .and_then(|_| protocols.next())
+        .map(|token| format!("Bearer {}", token))
+}
+
+/// Gives each request an id (the caller's `X-Request-Id`, else a new UUID)
+/// and runs it in a span carrying the id, method and path, with errors laid
+/// out as its `X-Response-Version` asks. The id is echoed
+/// in the response header, and the outcome is logged with its latency and
+/// counted in [`AppState::metrics`] under the matched route.
+pub async fn request_context(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let start = Instant::now();
+    let request_id = request_id::from_header(
+        request
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    );
+    let version = ResponseVersion::from_header(
+        request
+            .headers()
+            .get(RESPONSE_VERSION_HEADER)
+            .and_then(|v| v.to_str().ok()),
+    );
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched| matched.as_str().to_string());
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        token = tracing::field::Empty,
+    );
+
+    let mut response = request_id::scope(request_id.clone(), version.scope(next.run(request)))
+        .instrument(span.clone())
+        .await;
+
+    let elapsed = start.elapsed();
+    let status = response.status().as_u16();
+    state
+        .metrics
+        .observe_request(route.as_deref(), method.as_str(), status, elapsed);
+    span.in_scope(|| {
+        tracing::info!(
+            "{} {} -> {} in {}ms",
+            method,
+            path,
+            status,
+            elapsed.as_millis()
+        )
+    });
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
@@ -1,33 +1,278 @@
+pub mod admin;
+pub mod analysis_store;
+pub mod analysis_subscriptions;
+pub mod analyze_and_trade;
 pub mod analyze_event_markets;
+pub mod arb_scan;
+pub mod auto_trade;
+pub mod batch_analyze;
+pub mod capabilities;
+pub mod chart;
+pub mod construct_portfolio;
+pub mod cors;
+pub mod csv_export;
+pub mod diagnostics;
+pub mod event_analysis;
+pub mod event_mispricing;
+pub mod exposure_caps;
+pub mod extract;
+pub mod fields;
+pub mod fill_watcher;
+pub mod health;
+pub mod http_cache;
+pub mod idempotency;
+pub mod jobs;
+pub mod leaderboard;
 pub mod limit_order_bot;
+pub mod limit_order_diff;
+pub mod market_cache;
+pub mod market_history;
+pub mod market_stream;
+pub mod markets;
+pub mod middleware;
+pub mod openapi;
+pub mod orderbook;
+pub mod orders;
+pub mod pagination;
 pub mod polyfactual_research;
+pub mod portfolio;
+pub mod position_monitor;
+pub mod position_snapshots;
 pub mod position_tracker;
+pub mod ready;
+pub mod refresh_analysis;
+pub mod research_cache;
+pub mod runs;
+pub mod runtime_config;
+pub mod shutdown;
+pub mod status;
+pub mod unwind_leg;
+pub mod wallet_snapshots;
 
 use axum::{
-    routing::{get, post},
+    extract::State,
+    http::header,
+    response::IntoResponse,
+    routing::{delete, get, post},
     Router,
 };
 use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
 
-use crate::clients::{PolyfactualClient, PolymarketClient};
-use crate::api::analyze_event_markets::Clients;
+use crate::clients::{
+    create_ai_client, AiClient, AiProvider, AiRequestOptions, KalshiVenue, MarketDataSource,
+    ResearchSource, SaltAllocator, TradingVenue, WebhookSender,
+};
+use crate::api::capabilities::{Capabilities, Capability};
+use crate::api::analysis_store::AnalysisStore;
+use crate::api::analysis_subscriptions::SubscriptionStore;
+use crate::api::auto_trade::AutoTrader;
+use crate::api::exposure_caps::{ExposureCaps, InFlightExposure};
+use crate::api::health::DeepHealth;
+use crate::api::idempotency::IdempotencyStore;
+use crate::api::jobs::JobQueue;
+use crate::api::market_cache::{MarketCache, MarketSearchCache};
+use crate::api::market_stream::MarketStreams;
+use crate::api::middleware::{ApiAuth, IpRateLimiter};
+use crate::api::openapi::ApiDoc;
+use crate::api::position_monitor::MonitorStore;
+use crate::api::position_snapshots::PositionSnapshotStore;
+use crate::api::research_cache::ResearchCache;
+use crate::api::runtime_config::RuntimeConfig;
+use crate::api::shutdown::InFlight;
+use crate::api::wallet_snapshots::{TrackedWallet, WalletSnapshotStore};
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::storage::Storage;
 
 #[derive(Clone)]
 pub struct AppState {
-    pub dome_clients: Arc<Clients>,
-    pub polyfactual_client: Arc<PolyfactualClient>,
-    pub polymarket_client: Arc<PolymarketClient>,
+    /// Settings validated at startup; served without secrets on `/api/config`
+    pub config: Arc<Config>,
+    /// Market lookups (Dome); set when `DOME_API_KEY` is configured
+    pub dome_client: Option<Arc<dyn MarketDataSource>>,
+    /// Research (Polyfactual); set when `POLYFACTUAL_API_KEY` is configured
+    pub polyfactual_client: Option<Arc<dyn ResearchSource>>,
+    /// Direct Kalshi access, preferred over Dome for Kalshi markets; set
+    /// when `KALSHI_API_KEY`/`KALSHI_PRIVATE_KEY_PATH` or
+    /// `KALSHI_EMAIL`/`KALSHI_PASSWORD` are
+    pub kalshi_client: Option<Arc<dyn KalshiVenue>>,
+    /// Polymarket market data, wallets and orders
+    pub polymarket_client: Arc<dyn TradingVenue>,
+    pub salt_allocator: Arc<SaltAllocator>,
+    pub analysis_store: Arc<AnalysisStore>,
+    pub analysis_subscriptions: Arc<SubscriptionStore>,
+    pub runtime_config: Arc<RuntimeConfig>,
+    /// Recently fetched markets, shared by pollers of the same market
+    pub market_cache: Arc<MarketCache>,
+    /// Recent `GET /api/markets` pages
+    pub market_search_cache: Arc<MarketSearchCache>,
+    /// Shared Gamma pollers behind `/ws/market/:slug`
+    pub market_streams: Arc<MarketStreams>,
+    /// Polyfactual answers by normalized query
+    pub research_cache: Arc<ResearchCache>,
+    /// Per-client request limits applied by [`middleware::rate_limit`]
+    pub ip_rate_limiter: Arc<IpRateLimiter>,
+    /// Bearer tokens required on `/api/*` by [`middleware::require_api_token`]
+    pub api_auth: ApiAuth,
+    /// Completed limit order bot runs by idempotency key
+    pub idempotency: Arc<IdempotencyStore>,
+    /// Background research and analysis runs polled via `/api/jobs/:id`
+    pub jobs: Arc<JobQueue>,
+    pub exposure_caps: ExposureCaps,
+    /// Buy notional of running bot runs, reserved by [`ExposureCaps::enforce`]
+    pub exposure_in_flight: Arc<InFlightExposure>,
+    pub auto_trader: Arc<AutoTrader>,
+    /// Signed delivery for order webhooks (`WEBHOOK_SECRET`)
+    pub webhooks: Arc<WebhookSender>,
+    pub tracked_wallets: Arc<Vec<TrackedWallet>>,
+    pub wallet_snapshots: Arc<WalletSnapshotStore>,
+    /// Latest positions per wallet and market for `track_changes` polls
+    pub position_snapshots: Arc<PositionSnapshotStore>,
+    /// P&L thresholds checked by [`position_monitor::spawn_scheduler`]
+    pub position_monitors: Arc<MonitorStore>,
+    /// Set when `DATABASE_URL` is configured
+    pub storage: Option<Arc<Storage>>,
+    pub capabilities: Capabilities,
+    /// Cancelled on SIGINT/SIGTERM; background tasks stop when it fires
+    pub shutdown: CancellationToken,
+    /// Requests and runs that shutdown waits for
+    pub in_flight: Arc<InFlight>,
+    /// Served on `/metrics`
+    pub metrics: &'static Metrics,
+    /// Cached upstream checks for `/health/deep`
+    pub deep_health: Arc<DeepHealth>,
+}
+
+impl AppState {
+    pub fn dome(&self) -> crate::Result<&dyn MarketDataSource> {
+        self.dome_client
+            .as_deref()
+            .ok_or_else(|| Capability::Dome.missing())
+    }
+
+    pub fn polyfactual(&self) -> crate::Result<&dyn ResearchSource> {
+        self.polyfactual_client
+            .as_deref()
+            .ok_or_else(|| Capability::Polyfactual.missing())
+    }
+
+    pub fn kalshi(&self) -> crate::Result<&dyn KalshiVenue> {
+        self.kalshi_client
+            .as_deref()
+            .ok_or_else(|| Capability::Kalshi.missing())
+    }
+
+    /// A client for `provider`, or the missing-capability error when its
+    /// key isn't configured.
+    pub fn ai_client(
+        &self,
+        provider: AiProvider,
+        options: &AiRequestOptions,
+    ) -> crate::Result<Box<dyn AiClient>> {
+        self.capabilities.require(Capability::ai(&provider))?;
+        create_ai_client(&self.config, provider, options)
+    }
+
+    pub fn storage(&self) -> crate::Result<&Storage> {
+        self.storage
+            .as_deref()
+            .ok_or_else(|| Capability::Persistence.missing())
+    }
 }
 
 pub fn create_router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/api/analyze-event-markets", post(analyze_event_markets::handler))
+        .route("/api/analyze-and-trade", post(analyze_and_trade::handler))
+        .route(
+            "/api/analyze-event-markets/batch",
+            post(batch_analyze::handler),
+        )
+        .route(
+            "/api/analyze-event-markets/refresh",
+            post(refresh_analysis::handler),
+        )
+        .route(
+            "/api/analysis-subscriptions",
+            post(analysis_subscriptions::create),
+        )
+        .route(
+            "/api/analysis-subscriptions/:id/history",
+            get(analysis_subscriptions::history),
+        )
+        .route("/api/position-monitors", post(position_monitor::create))
+        .route(
+            "/api/position-monitors/:id",
+            delete(position_monitor::delete),
+        )
+        .route(
+            "/api/construct-portfolio",
+            post(construct_portfolio::handler),
+        )
+        .route("/api/event-mispricing", post(event_mispricing::handler))
+        .route("/api/arb-scan", post(arb_scan::handler))
         .route("/api/polyfactual-research", post(polyfactual_research::handler))
-        .route("/api/position-tracker", post(position_tracker::handler))
+        .route(
+            "/api/polyfactual-research/stream",
+            get(polyfactual_research::stream),
+        )
+        .route("/api/jobs/research", post(jobs::submit_research))
+        .route("/api/jobs/analyze", post(jobs::submit_analyze))
+        .route("/api/jobs/:id", get(jobs::get_job))
+        .route(
+            "/api/position-tracker",
+            post(position_tracker::handler).get(position_tracker::get_handler),
+        )
+        .route(
+            "/api/portfolio",
+            post(portfolio::handler).get(portfolio::get_handler),
+        )
         .route("/api/limit-order-bot", post(limit_order_bot::handler))
+        .route("/api/limit-order-bot/diff", post(limit_order_diff::handler))
+        .route("/api/auto-trade/start", post(auto_trade::start))
+        .route("/api/auto-trade/stop", post(auto_trade::stop))
+        .route("/api/auto-trade/status", get(auto_trade::status))
+        .route("/api/runs", get(runs::list_runs))
+        .route("/api/runs/:id", get(runs::get_run))
+        .route("/api/orderbook", get(orderbook::handler))
+        .route("/api/market-history", get(market_history::handler))
+        .route("/api/markets", get(markets::handler))
+        .route("/ws/market/:slug", get(market_stream::handler))
+        .route("/api/orders", get(orders::list_orders))
+        .route("/api/orders/cancel-all", post(orders::cancel_all))
+        .route("/api/unwind-leg", post(unwind_leg::handler))
+        .route(
+            "/api/orders/:order_id",
+            get(orders::get_order).delete(orders::cancel_order),
+        )
+        .route("/api/diagnostics", get(diagnostics::handler))
+        .route("/api/leaderboard", get(leaderboard::handler))
+        .route(
+            "/api/admin/runtime-config",
+            get(admin::get_runtime_config).post(admin::update_runtime_config),
+        )
+        .route("/api/admin/recordings/:id", get(admin::get_recording))
+        .route("/api/config", get(admin::get_config))
+        .route("/status/public", get(status::public_handler))
         .route("/health", get(health_check))
+        .route("/health/deep", get(health::deep_handler))
+        .route("/ready", get(ready::handler))
+        .route("/metrics", get(metrics_handler))
+        // 304s for `If-None-Match` on `http_cache::Cacheable` responses
+        .layer(axum::middleware::from_fn(http_cache::not_modified))
+        .merge(SwaggerUi::new(openapi::DOCS_PATH).url(openapi::SPEC_PATH, ApiDoc::openapi()))
 }
 
 async fn health_check() -> &'static str {
     "OK"
 }
+
+async fn metrics_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, Metrics::content_type())],
+        state.metrics.render(),
+    )
+}
@@ -0,0 +1,86 @@
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi, ToSchema};
+
+use crate::api::{analyze_event_markets, limit_order_bot, polyfactual_research, position_tracker};
+use crate::error::ErrorCode;
+use crate::types::{AnalyzeEventResponse, CrossPlatformPositionsResponse};
+
+/// Where the spec and Swagger UI are served; both skip `API_AUTH_TOKENS` auth so
+/// a browser can load them.
+pub const SPEC_PATH: &str = "/api/openapi.json";
+pub const DOCS_PATH: &str = "/api/docs";
+
+/// Body of every error response in the default layout; see `AppError` and
+/// `ResponseVersion`.
+#[derive(ToSchema)]
+pub struct ErrorResponse {
+    pub error: String,
+    pub code: ErrorCode,
+    /// The HTTP status code, repeated
+    pub status: u16,
+    /// The id in the response's `X-Request-Id` header
+    pub request_id: Option<String>,
+    /// What the model answered, when it wasn't a valid analysis (first 4 KB)
+    pub raw_content: Option<String>,
+}
+
+/// Body of every error response when the request sends
+/// `X-Response-Version: 2`.
+#[derive(ToSchema)]
+pub struct ErrorResponseV2 {
+    pub error: ErrorDetail,
+    /// The id in the response's `X-Request-Id` header
+    pub request_id: Option<String>,
+}
+
+#[derive(ToSchema)]
+pub struct ErrorDetail {
+    pub code: ErrorCode,
+    pub message: String,
+    /// The HTTP status code, repeated
+    pub status: u16,
+    /// Context for the codes that have any, e.g. `market` for
+    /// `MARKET_NOT_FOUND`; see `ErrorCode`
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<serde_json::Value>,
+}
+
+/// The OpenAPI document, derived from the handler annotations and the
+/// `ToSchema` types at compile time so it can't drift from the code.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "predict-os-be", description = "Prediction market analysis and trading API"),
+    paths(
+        analyze_event_markets::handler,
+        polyfactual_research::handler,
+        polyfactual_research::stream,
+        position_tracker::handler,
+        limit_order_bot::handler,
+    ),
+    components(schemas(
+        ErrorResponse,
+        ErrorResponseV2,
+        CrossPlatformPositionsResponse,
+        AnalyzeEventResponse
+    )),
+    modifiers(&BearerAuth),
+    security(("bearer" = [])),
+    tags(
+        (name = "analysis", description = "AI market analysis and research"),
+        (name = "trading", description = "Positions and order placement"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Declares the `API_AUTH_TOKENS` bearer scheme, so Swagger UI can send it.
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
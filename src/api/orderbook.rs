@@ -0,0 +1,56 @@
+use axum::extract::{Query, State};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::http_cache::Cacheable;
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{OrderBookResponse, ResponseMetadata};
+use crate::{AppError, Result};
+
+#[derive(Debug, Deserialize)]
+pub struct OrderBookQuery {
+    pub token_id: String,
+}
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<OrderBookQuery>,
+) -> Result<Cacheable<OrderBookResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    let token_id = query.token_id.trim();
+    if token_id.is_empty() || !token_id.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(AppError::Validation(
+            "token_id must be a numeric CLOB token id".to_string(),
+        ));
+    }
+
+    let order_book = state.polymarket_client.get_order_book(token_id).await?;
+
+    let response = OrderBookResponse {
+        order_book,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    };
+    Ok(Cacheable::new(
+        response,
+        state.market_cache.polymarket_ttl(),
+    ))
+}
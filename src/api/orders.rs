@@ -0,0 +1,292 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::clients::clob_signing::{ClobSigner, WalletAuth};
+use crate::clients::polymarket::CancelResult;
+use crate::request_id;
+use crate::types::{
+    CancelAllOrdersRequest, CancelOrdersResponse, CancelStatus, CancelledOrder, OrderListResponse,
+    OrderLookupResponse, OrderStatus, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+/// Orders or tokens accepted by one cancel-all request.
+const MAX_CANCEL_TARGETS: usize = 100;
+
+/// CLOB reads are signed by the wallet that owns the orders. The key travels
+/// in a header rather than the query string so it never lands in access logs;
+/// without it the server's `WALLET_PRIVATE_KEY` is used.
+pub(crate) const WALLET_KEY_HEADER: &str = "x-wallet-private-key";
+
+#[derive(Debug, Deserialize)]
+pub struct OrderListQuery {
+    pub market_slug: String,
+}
+
+pub async fn get_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(order_id): Path<String>,
+) -> Result<Json<OrderLookupResponse>> {
+    let start = Instant::now();
+    validate_order_id(&order_id)?;
+    let auth = wallet_auth(&headers, state.config.wallet.as_ref())?;
+
+    let order = state
+        .polymarket_client
+        .get_order(&auth, &order_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Order {} not found", order_id)))?;
+
+    Ok(Json(OrderLookupResponse {
+        order: order.into_order_result()?,
+        metadata: metadata(start),
+    }))
+}
+
+pub async fn list_orders(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(query): Query<OrderListQuery>,
+) -> Result<Json<OrderListResponse>> {
+    let start = Instant::now();
+    let auth = wallet_auth(&headers, state.config.wallet.as_ref())?;
+
+    let market = state
+        .polymarket_client
+        .get_market_by_slug(&query.market_slug)
+        .await?;
+    let outcome_names: HashMap<&str, &str> = market
+        .outcomes
+        .iter()
+        .map(|o| (o.id.as_str(), o.name.as_str()))
+        .collect();
+    let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+
+    let orders = state
+        .polymarket_client
+        .get_open_orders(&auth, &token_ids)
+        .await?
+        .into_iter()
+        .map(|order| {
+            let mut result = order.into_order_result()?;
+            if let Some(name) = outcome_names.get(result.token_id.as_str()) {
+                result.outcome = name.to_string();
+            }
+            Ok(result)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Json(OrderListResponse {
+        market_slug: query.market_slug,
+        orders,
+        metadata: metadata(start),
+    }))
+}
+
+/// Cancels one order. Like placement it is refused while trading is
+/// disabled, so safe mode leaves the book untouched.
+pub async fn cancel_order(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(order_id): Path<String>,
+) -> Result<Json<CancelOrdersResponse>> {
+    let start = Instant::now();
+    state.runtime_config.ensure_trading_enabled()?;
+    validate_order_id(&order_id)?;
+    let auth = wallet_auth(&headers, state.config.wallet.as_ref())?;
+
+    let result = state
+        .polymarket_client
+        .cancel_order(&auth, &order_id)
+        .await?;
+    let results = classify_cancels(&state, &auth, result).await?;
+    if results.iter().all(|r| r.status == CancelStatus::NotFound) {
+        return Err(AppError::NotFound(format!("Order {} not found", order_id)));
+    }
+
+    Ok(Json(cancel_response(results, start)))
+}
+
+/// Cancels by market, tokens or order ids; refused while trading is
+/// disabled, like [`cancel_order`].
+pub async fn cancel_all(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<CancelAllOrdersRequest>,
+) -> Result<Json<CancelOrdersResponse>> {
+    let start = Instant::now();
+    state.runtime_config.ensure_trading_enabled()?;
+    let auth = wallet_auth(&headers, state.config.wallet.as_ref())?;
+
+    let result = match (request.market_slug, request.token_ids, request.order_ids) {
+        (Some(market_slug), None, None) => {
+            let market = state
+                .polymarket_client
+                .get_market_by_slug(&market_slug)
+                .await?;
+            let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+            state
+                .polymarket_client
+                .cancel_all(&auth, &token_ids)
+                .await?
+        }
+        (None, Some(token_ids), None) => {
+            validate_targets("token_ids", &token_ids)?;
+            if let Some(bad) = token_ids
+                .iter()
+                .find(|id| id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()))
+            {
+                return Err(AppError::Validation(format!(
+                    "Invalid token id '{}': expected a numeric CLOB token id",
+                    bad
+                )));
+            }
+            state
+                .polymarket_client
+                .cancel_all(&auth, &token_ids)
+                .await?
+        }
+        (None, None, Some(order_ids)) => {
+            validate_targets("order_ids", &order_ids)?;
+            for order_id in &order_ids {
+                validate_order_id(order_id)?;
+            }
+            state
+                .polymarket_client
+                .cancel_orders(&auth, &order_ids)
+                .await?
+        }
+        _ => {
+            return Err(AppError::Validation(
+                "Provide exactly one of market_slug, token_ids or order_ids".to_string(),
+            ))
+        }
+    };
+    let results = classify_cancels(&state, &auth, result).await?;
+
+    Ok(Json(cancel_response(results, start)))
+}
+
+/// Turns the exchange's cancelled / not-cancelled split into a per-order
+/// status. The exchange's refusal reasons aren't stable, so each refused
+/// order is looked up to tell filled, already cancelled and unknown apart.
+async fn classify_cancels(
+    state: &AppState,
+    auth: &WalletAuth,
+    result: CancelResult,
+) -> Result<Vec<CancelledOrder>> {
+    let mut results: Vec<CancelledOrder> = result
+        .canceled
+        .into_iter()
+        .map(|order_id| CancelledOrder {
+            order_id,
+            status: CancelStatus::Cancelled,
+            reason: None,
+        })
+        .collect();
+
+    let mut refused: Vec<(String, String)> = result.not_canceled.into_iter().collect();
+    refused.sort();
+    for (order_id, reason) in refused {
+        let status = match state.polymarket_client.get_order(auth, &order_id).await? {
+            None => CancelStatus::NotFound,
+            Some(order) => match order.status() {
+                OrderStatus::Filled => CancelStatus::AlreadyFilled,
+                OrderStatus::Cancelled => CancelStatus::AlreadyCancelled,
+                _ => CancelStatus::Failed,
+            },
+        };
+        results.push(CancelledOrder {
+            order_id,
+            status,
+            reason: Some(reason),
+        });
+    }
+
+    Ok(results)
+}
+
+fn cancel_response(results: Vec<CancelledOrder>, start: Instant) -> CancelOrdersResponse {
+    CancelOrdersResponse {
+        cancelled: results
+            .iter()
+            .filter(|r| r.status == CancelStatus::Cancelled)
+            .count(),
+        results,
+        metadata: metadata(start),
+    }
+}
+
+fn validate_targets(field: &str, targets: &[String]) -> Result<()> {
+    if targets.is_empty() || targets.len() > MAX_CANCEL_TARGETS {
+        return Err(AppError::Validation(format!(
+            "{} must list 1-{} entries",
+            field, MAX_CANCEL_TARGETS
+        )));
+    }
+    Ok(())
+}
+
+/// CLOB order ids are 0x-prefixed hex hashes; anything else can't exist and
+/// must not be spliced into an upstream path.
+fn validate_order_id(order_id: &str) -> Result<()> {
+    let valid = order_id
+        .strip_prefix("0x")
+        .is_some_and(|hex| !hex.is_empty() && hex.bytes().all(|b| b.is_ascii_hexdigit()));
+    if !valid {
+        return Err(AppError::Validation(format!(
+            "Invalid order id '{}': expected a 0x-prefixed hex hash",
+            order_id
+        )));
+    }
+    Ok(())
+}
+
+pub(crate) fn wallet_auth(
+    headers: &HeaderMap,
+    server_wallet: Option<&ClobSigner>,
+) -> Result<WalletAuth> {
+    let header_key = headers
+        .get(WALLET_KEY_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::trim)
+        .filter(|key| !key.is_empty());
+    let signer = match header_key {
+        Some(key) => ClobSigner::from_private_key(key)?,
+        None => server_wallet.cloned().ok_or_else(|| {
+            AppError::Unauthorized(
+                "X-Wallet-Private-Key header is required for order routes".to_string(),
+            )
+        })?,
+    };
+    Ok(WalletAuth::Signer(signer))
+}
+
+fn metadata(start: Instant) -> ResponseMetadata {
+    ResponseMetadata {
+        timestamp: Utc::now().to_rfc3339(),
+        execution_time_ms: start.elapsed().as_millis() as u64,
+        model_used: None,
+        retries: 0,
+        degraded_features: Vec::new(),
+        custom_prompt: false,
+        dry_run: false,
+        cache_hit: None,
+        request_id: request_id::current(),
+        ai_usage: None,
+        ai_provider: None,
+        query_compression: None,
+        timeout_budget: None,
+    }
+}
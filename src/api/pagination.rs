@@ -0,0 +1,165 @@
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Query},
+    http::request::Parts,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use utoipa::ToSchema;
+
+use crate::api::AppState;
+use crate::AppError;
+
+pub const DEFAULT_PAGE_LIMIT: usize = 50;
+pub const DEFAULT_MAX_PAGE_LIMIT: usize = 200;
+
+/// Shared response envelope for list endpoints.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    /// Pass back as `?cursor=` for the next page; absent on the last page.
+    pub next_cursor: Option<String>,
+    /// Total matching items, when the source knows it.
+    pub total: Option<u64>,
+    pub limit: usize,
+}
+
+impl<T> Paginated<T> {
+    /// Pages an in-memory list that is already filtered and sorted.
+    pub fn from_vec(items: Vec<T>, page: &PageParams) -> Self {
+        let total = items.len();
+        let has_more = page.offset + page.limit < total;
+        let items = items
+            .into_iter()
+            .skip(page.offset)
+            .take(page.limit)
+            .collect();
+        Self::new(items, page, has_more, Some(total as u64))
+    }
+
+    /// Wraps one page fetched from a source that pages itself.
+    pub fn new(items: Vec<T>, page: &PageParams, has_more: bool, total: Option<u64>) -> Self {
+        Self {
+            items,
+            next_cursor: has_more.then(|| page.next_cursor()),
+            total,
+            limit: page.limit,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RawPageParams {
+    cursor: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+}
+
+/// Validated paging position, from either `?cursor=` or `?offset=&limit=`.
+///
+/// Limits above the maximum are clamped; defaults come from
+/// `PAGE_DEFAULT_LIMIT` and `PAGE_MAX_LIMIT`.
+#[derive(Debug, Clone)]
+pub struct PageParams {
+    pub offset: usize,
+    pub limit: usize,
+    scope: String,
+}
+
+impl PageParams {
+    pub fn next_cursor(&self) -> String {
+        encode_cursor(&self.scope, self.offset + self.limit)
+    }
+}
+
+#[async_trait]
+impl FromRequestParts<Arc<AppState>> for PageParams {
+    type Rejection = AppError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &Arc<AppState>,
+    ) -> Result<Self, Self::Rejection> {
+        let Query(raw) = Query::<RawPageParams>::from_request_parts(parts, state)
+            .await
+            .map_err(|e| AppError::Validation(format!("Invalid paging parameters: {}", e)))?;
+
+        // Cursors are bound to the endpoint that issued them
+        let scope = parts.uri.path().to_string();
+        resolve_page(
+            raw.cursor.as_deref(),
+            raw.offset,
+            raw.limit,
+            &scope,
+            state.config.page_default_limit,
+            state.config.page_max_limit,
+        )
+    }
+}
+
+pub fn resolve_page(
+    cursor: Option<&str>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    scope: &str,
+    default_limit: usize,
+    max_limit: usize,
+) -> crate::Result<PageParams> {
+    let limit = match limit {
+        Some(0) => return Err(AppError::Validation("limit must be at least 1".to_string())),
+        Some(limit) => limit.min(max_limit),
+        None => default_limit.min(max_limit),
+    };
+
+    let offset = match (cursor, offset) {
+        (Some(_), Some(_)) => {
+            return Err(AppError::Validation(
+                "Use either cursor or offset, not both".to_string(),
+            ))
+        }
+        (Some(cursor), None) => decode_cursor(scope, cursor)?,
+        (None, offset) => offset.unwrap_or(0),
+    };
+
+    Ok(PageParams {
+        offset,
+        limit,
+        scope: scope.to_string(),
+    })
+}
+
+/// Opaque cursor payload; the checksum rejects edited or cross-endpoint cursors.
+#[derive(Debug, Serialize, Deserialize)]
+struct Cursor {
+    o: usize,
+    c: u64,
+}
+
+pub fn encode_cursor(scope: &str, offset: usize) -> String {
+    let cursor = Cursor {
+        o: offset,
+        c: cursor_checksum(scope, offset),
+    };
+    let json = serde_json::to_vec(&cursor).unwrap_or_default();
+    URL_SAFE_NO_PAD.encode(json)
+}
+
+pub fn decode_cursor(scope: &str, cursor: &str) -> crate::Result<usize> {
+    let invalid = || AppError::Validation("Invalid or expired cursor".to_string());
+
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let cursor: Cursor = serde_json::from_slice(&bytes).map_err(|_| invalid())?;
+    if cursor.c != cursor_checksum(scope, cursor.o) {
+        return Err(invalid());
+    }
+    Ok(cursor.o)
+}
+
+fn cursor_checksum(scope: &str, offset: usize) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    ("cursor-v1", scope, offset).hash(&mut hasher);
+    hasher.finish()
+}
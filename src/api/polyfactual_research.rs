@@ -1,22 +1,225 @@
-use axum::{extract::State, Json};
+use async_trait::async_trait;
+use axum::{
+    extract::{Query, State},
+    response::sse::{Event, Sse},
+    Json,
+};
+use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+use tokio_stream::wrappers::ReceiverStream;
 
+use crate::api::extract::AppJson;
+use crate::api::openapi::ErrorResponse;
 use crate::api::AppState;
-use crate::types::PolyfactualResearchRequest;
+use crate::clients::polyfactual::{fit_query, query_too_long};
+use crate::clients::{AiClient, AiProvider, AiRequestOptions, ResearchSource};
+use crate::error::ResponseVersion;
+use crate::request_id;
+use crate::types::{PolyfactualResearchRequest, PolyfactualResearchResponse, Validate};
 use crate::Result;
 
+/// How often a `heartbeat` event is sent while the research runs, so
+/// proxies don't close an idle connection.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// AI providers tried, in order, for summarizing queries that are too long.
+const SUMMARY_PROVIDERS: [AiProvider; 3] =
+    [AiProvider::Grok, AiProvider::OpenAi, AiProvider::Claude];
+
+type EventStream = ReceiverStream<std::result::Result<Event, axum::Error>>;
+
+/// Runs a Polyfactual research query.
+#[utoipa::path(
+    post,
+    path = "/api/polyfactual-research",
+    tag = "analysis",
+    request_body = PolyfactualResearchRequest,
+    responses(
+        (status = 200, body = PolyfactualResearchResponse),
+        (status = 400, description = "Empty query or Polyfactual not configured", body = ErrorResponse),
+        (status = 502, description = "Upstream failure", body = ErrorResponse),
+    )
+)]
 pub async fn handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<PolyfactualResearchRequest>,
-) -> Result<Json<crate::types::PolyfactualResearchResponse>> {
-    // Validate request
-    if request.query.is_empty() {
-        return Err(crate::AppError::Validation("Query is required".to_string()));
-    }
+    AppJson(request): AppJson<PolyfactualResearchRequest>,
+) -> Result<Json<PolyfactualResearchResponse>> {
+    let client = state.polyfactual()?;
 
-    // Call Polyfactual API
-    let response = state.polyfactual_client.research(request.query).await?;
+    // Call Polyfactual API, unless the query was answered recently
+    let fresh = request.force_refresh.unwrap_or(false);
+    let timeout = request.timeout_secs.map(Duration::from_secs);
+    let mut response = research(&state, client, request.query, fresh, timeout).await?;
+    response.retain_relevant(request.min_relevance);
 
     Ok(Json(response))
 }
 
+/// Research through the cache, with a query over
+/// `POLYFACTUAL_MAX_QUERY_LENGTH` summarized first (see [`fit_query`]).
+/// Answers stay cached by the query as asked, so a repeat isn't summarized
+/// again.
+pub(crate) async fn research(
+    state: &AppState,
+    polyfactual: &dyn ResearchSource,
+    query: String,
+    fresh: bool,
+    timeout: Option<Duration>,
+) -> Result<PolyfactualResearchResponse> {
+    let source = Summarizing { state, polyfactual };
+    state
+        .research_cache
+        .research(&source, query, fresh, timeout)
+        .await
+}
+
+/// The first configured AI provider, to summarize long queries with.
+fn summarizer(state: &AppState) -> Option<Box<dyn AiClient>> {
+    let options = AiRequestOptions {
+        temperature: Some(0.0),
+        // A summary is a few hundred tokens; this leaves room for the model
+        // to overshoot so an overlong answer is reported, not cut off
+        max_tokens: Some(state.config.polyfactual_max_query_length.max(256) as u32),
+        ..Default::default()
+    };
+    SUMMARY_PROVIDERS
+        .into_iter()
+        .find_map(|provider| state.ai_client(provider, &options).ok())
+}
+
+/// Polyfactual behind [`fit_query`].
+struct Summarizing<'a> {
+    state: &'a AppState,
+    polyfactual: &'a dyn ResearchSource,
+}
+
+#[async_trait]
+impl ResearchSource for Summarizing<'_> {
+    async fn research(
+        &self,
+        query: String,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse> {
+        let max_length = self.state.config.polyfactual_max_query_length;
+        let summarizer = if query.chars().count() > max_length {
+            summarizer(self.state)
+        } else {
+            None
+        };
+        let (query, compression) = fit_query(query, max_length, summarizer.as_deref()).await?;
+        let mut response = self.polyfactual.research(query, timeout).await?;
+        response.metadata.query_compression = compression;
+        Ok(response)
+    }
+}
+
+/// Runs a Polyfactual research query as server-sent events: `accepted`
+/// straight away, a `heartbeat` every 15 seconds while Polyfactual works,
+/// then `result` with the same body as the POST, or `error` with the error
+/// body it would have returned. Polyfactual reports no progress of its own,
+/// so there are no interim status events.
+#[utoipa::path(
+    get,
+    path = "/api/polyfactual-research/stream",
+    tag = "analysis",
+    params(("query" = String, Query, description = "The research question")),
+    responses(
+        (status = 200, description = "`accepted`, `heartbeat`, then `result` or `error` events", content_type = "text/event-stream"),
+        (status = 400, description = "Empty query or Polyfactual not configured", body = ErrorResponse),
+    )
+)]
+pub async fn stream(
+    State(state): State<Arc<AppState>>,
+    Query(request): Query<PolyfactualResearchRequest>,
+) -> Result<Sse<EventStream>> {
+    request.validate()?;
+    // A query that can't be summarized fails before the stream starts
+    let length = request.query.chars().count();
+    let max_length = state.config.polyfactual_max_query_length;
+    if length > max_length && summarizer(&state).is_none() {
+        return Err(query_too_long(length, max_length));
+    }
+    state.polyfactual()?;
+
+    let (tx, rx) = mpsc::channel(4);
+    // The handler returns as soon as the stream starts, so the run is
+    // tracked here for shutdown to wait on
+    let guard = state
+        .in_flight
+        .track("GET /api/polyfactual-research/stream".to_string());
+    let fresh = request.force_refresh.unwrap_or(false);
+    let timeout = request.timeout_secs.map(Duration::from_secs);
+    let run = ResponseVersion::current().scope(run_research(
+        state,
+        request.query,
+        fresh,
+        timeout,
+        request.min_relevance,
+        tx,
+    ));
+    let id = request_id::current();
+    tokio::spawn(async move {
+        let _guard = guard;
+        match id {
+            Some(id) => request_id::scope(id, run).await,
+            None => run.await,
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx)))
+}
+
+/// Sends the events for one research run. Returns early, dropping the
+/// upstream call, once the client disconnects.
+async fn run_research(
+    state: Arc<AppState>,
+    query: String,
+    fresh: bool,
+    timeout: Option<Duration>,
+    min_relevance: Option<f64>,
+    tx: mpsc::Sender<std::result::Result<Event, axum::Error>>,
+) {
+    let start = Instant::now();
+    let accepted = json!({ "query": query, "request_id": request_id::current() });
+    if tx.send(event("accepted", &accepted)).await.is_err() {
+        return;
+    }
+
+    let research = async {
+        let client = state.polyfactual()?;
+        research(&state, client, query, fresh, timeout).await
+    };
+    tokio::pin!(research);
+    let mut heartbeat = tokio::time::interval_at(start + HEARTBEAT_INTERVAL, HEARTBEAT_INTERVAL);
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut research => break outcome,
+            _ = heartbeat.tick() => {
+                let beat = json!({ "elapsed_secs": start.elapsed().as_secs() });
+                if tx.send(event("heartbeat", &beat)).await.is_err() {
+                    return;
+                }
+            }
+            _ = tx.closed() => {
+                tracing::info!("Research stream client went away; abandoning the query");
+                return;
+            }
+        }
+    };
+
+    let last = match outcome {
+        Ok(mut response) => {
+            response.retain_relevant(min_relevance);
+            event("result", &response)
+        }
+        Err(e) => event("error", &e.into_json().await),
+    };
+    let _ = tx.send(last).await;
+}
+
+fn event(name: &str, data: &impl serde::Serialize) -> std::result::Result<Event, axum::Error> {
+    Event::default().event(name).json_data(data)
+}
@@ -0,0 +1,235 @@
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::api::csv_export::{csv_response, FormatQuery, LedgerRow};
+use crate::api::extract::{AppJson, AppQuery};
+use crate::api::fields::{select_response, FieldSelection};
+use crate::api::http_cache::Cacheable;
+use crate::api::AppState;
+use crate::clients::polymarket::WalletPosition;
+use crate::request_id;
+use crate::types::{
+    checksum_address, MarketData, MarketPositions, PortfolioRequest, PortfolioResponse,
+    PortfolioTotals, Position, Price, ResponseMetadata,
+};
+use crate::Result;
+
+const MARKET_LOOKUP_CONCURRENCY: usize = 8;
+/// Dust left after selling rounds to zero shares.
+const MIN_POSITION_SHARES: f64 = 1e-6;
+
+/// A wallet's positions grouped by market; with `?format=csv` (or
+/// `Accept: text/csv`) one CSV row per position instead. `fields` (body or
+/// `?fields=`) trims the JSON response to the selected fields.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    Query(selection): Query<FieldSelection>,
+    Query(format): Query<FormatQuery>,
+    headers: HeaderMap,
+    AppJson(mut request): AppJson<PortfolioRequest>,
+) -> Result<Response> {
+    // Body selection wins over the query string
+    let fields = request.fields.take().or(selection.fields);
+    let response = portfolio(state, request).await?;
+    Ok(match format.csv(&headers) {
+        true => csv_response(&ledger_rows(&response), "portfolio"),
+        false => Json(select_response(&response, fields.as_deref())?).into_response(),
+    })
+}
+
+/// [`handler`] as `GET /api/portfolio?wallet_address=0x...`, cacheable for
+/// the market cache TTL.
+pub async fn get_handler(
+    State(state): State<Arc<AppState>>,
+    Query(format): Query<FormatQuery>,
+    headers: HeaderMap,
+    AppQuery(mut request): AppQuery<PortfolioRequest>,
+) -> Result<Response> {
+    let max_age = state.market_cache.polymarket_ttl();
+    let fields = request.fields.take();
+    let response = portfolio(state, request).await?;
+    Ok(match format.csv(&headers) {
+        true => csv_response(&ledger_rows(&response), "portfolio"),
+        false => {
+            Cacheable::new(select_response(&response, fields.as_deref())?, max_age).into_response()
+        }
+    })
+}
+
+/// Every position as of the response's timestamp, market by market.
+pub fn ledger_rows(response: &PortfolioResponse) -> Vec<LedgerRow> {
+    response
+        .markets
+        .iter()
+        .flat_map(|market| {
+            market.positions.iter().map(|position| LedgerRow {
+                market: market.title.clone(),
+                outcome: position.outcome.clone(),
+                side: None,
+                shares: position.shares,
+                avg_price: position.avg_price.value(),
+                current_price: Some(position.current_price.value()),
+                realized_pnl: position.realized_pnl,
+                unrealized_pnl: Some(position.unrealized_pnl),
+                timestamp: response.metadata.timestamp.clone(),
+            })
+        })
+        .collect()
+}
+
+async fn portfolio(state: Arc<AppState>, request: PortfolioRequest) -> Result<PortfolioResponse> {
+    let start = Instant::now();
+    let wallet_address = checksum_address("wallet_address", &request.wallet_address)?;
+
+    let holdings = state
+        .polymarket_client
+        .get_wallet_positions(&wallet_address)
+        .await?;
+    let groups = group_by_market(holdings);
+
+    let slugs: Vec<String> = groups.iter().map(|g| g.slug.clone()).collect();
+    let mut markets = fetch_markets(state.clone(), slugs).await;
+
+    let mut degraded_features = Vec::new();
+    let mut market_positions = Vec::with_capacity(groups.len());
+    for group in groups {
+        let market = markets.remove(&group.slug);
+        if market.is_none() && degraded_features.is_empty() {
+            degraded_features.push("market_metadata".to_string());
+        }
+        market_positions.push(group.into_market_positions(market)?);
+    }
+
+    let totals = PortfolioTotals {
+        markets: market_positions.len(),
+        positions: market_positions.iter().map(|m| m.positions.len()).sum(),
+        cost_basis: round_cents(market_positions.iter().map(|m| m.cost_basis).sum()),
+        current_value: round_cents(market_positions.iter().map(|m| m.current_value).sum()),
+        unrealized_pnl: round_cents(market_positions.iter().map(|m| m.unrealized_pnl).sum()),
+    };
+
+    Ok(PortfolioResponse {
+        wallet_address,
+        markets: market_positions,
+        totals,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features,
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    })
+}
+
+/// A wallet's holdings in one market, as reported by the data API.
+#[derive(Debug)]
+pub struct MarketGroup {
+    pub slug: String,
+    pub title: String,
+    pub holdings: Vec<WalletPosition>,
+}
+
+impl MarketGroup {
+    fn into_market_positions(self, market: Option<MarketData>) -> Result<MarketPositions> {
+        let positions = self
+            .holdings
+            .iter()
+            .map(|h| {
+                Ok(Position {
+                    token_id: h.asset.clone(),
+                    outcome: h.outcome.clone(),
+                    shares: h.size,
+                    avg_price: Price::from_decimal(h.avg_price)?,
+                    current_price: Price::from_decimal(h.cur_price)?,
+                    unrealized_pnl: (h.cur_price - h.avg_price) * h.size,
+                    realized_pnl: None,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let cost_basis: f64 = self.holdings.iter().map(|h| h.size * h.avg_price).sum();
+        let current_value: f64 = self.holdings.iter().map(|h| h.size * h.cur_price).sum();
+
+        Ok(MarketPositions {
+            market_slug: self.slug,
+            title: self.title,
+            market,
+            positions,
+            cost_basis: round_cents(cost_basis),
+            current_value: round_cents(current_value),
+            unrealized_pnl: round_cents(current_value - cost_basis),
+        })
+    }
+}
+
+/// Groups holdings by market slug in first-seen order, dropping positions
+/// with no shares left.
+pub fn group_by_market(holdings: Vec<WalletPosition>) -> Vec<MarketGroup> {
+    let mut groups: Vec<MarketGroup> = Vec::new();
+    for holding in holdings {
+        if holding.size < MIN_POSITION_SHARES {
+            continue;
+        }
+        match groups.iter_mut().find(|g| g.slug == holding.slug) {
+            Some(group) => group.holdings.push(holding),
+            None => groups.push(MarketGroup {
+                slug: holding.slug.clone(),
+                title: holding.title.clone(),
+                holdings: vec![holding],
+            }),
+        }
+    }
+    groups
+}
+
+/// Looks up market metadata by slug, bounded like the batch endpoint.
+/// Failed lookups are logged and left out.
+async fn fetch_markets(state: Arc<AppState>, slugs: Vec<String>) -> HashMap<String, MarketData> {
+    let semaphore = Arc::new(Semaphore::new(MARKET_LOOKUP_CONCURRENCY));
+    let mut workers = JoinSet::new();
+
+    for slug in slugs {
+        let state = state.clone();
+        let semaphore = semaphore.clone();
+        workers.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            let market = state.polymarket_client.get_market_by_slug(&slug).await;
+            (slug, market)
+        });
+    }
+
+    let mut markets = HashMap::new();
+    while let Some(joined) = workers.join_next().await {
+        match joined {
+            Ok((slug, Ok(market))) => {
+                markets.insert(slug, market);
+            }
+            Ok((slug, Err(e))) => tracing::warn!("Market lookup failed for {}: {}", slug, e),
+            Err(e) => tracing::error!("Market lookup task failed: {}", e),
+        }
+    }
+    markets
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
@@ -0,0 +1,249 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::api::extract::AppJson;
+use crate::api::position_tracker::tracked_positions;
+use crate::api::AppState;
+use crate::clients::webhook::EventKind;
+use crate::request_id;
+use crate::types::{
+    checksum_address, CreatePositionMonitorRequest, MarketData, MonitorThreshold, PairStatus,
+    Position, PositionMonitor, PositionMonitorResponse, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+const MAX_MONITORS: usize = 100;
+pub const DEFAULT_TICK_SECS: u64 = 30;
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<CreatePositionMonitorRequest>,
+) -> Result<Json<PositionMonitorResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    let wallet_address = checksum_address("wallet_address", &request.wallet_address)?;
+    state.webhooks.check_url(&request.webhook_url).await?;
+    // Unknown markets are refused now rather than on every tick
+    state
+        .polymarket_client
+        .get_market_by_slug(&request.market_slug)
+        .await?;
+
+    let monitor = Monitor {
+        id: format!("mon_{}", uuid::Uuid::new_v4().simple()),
+        wallet_address,
+        market_slug: request.market_slug,
+        webhook_url: request.webhook_url,
+        pnl_above: request.pnl_above,
+        pnl_below: request.pnl_below,
+        created_at: Utc::now(),
+    };
+    let view = monitor.view();
+    state
+        .position_monitors
+        .insert(monitor)
+        .map_err(AppError::Validation)?;
+
+    Ok(Json(PositionMonitorResponse {
+        monitor: view,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+pub async fn delete(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    state
+        .position_monitors
+        .remove(&id)
+        .map(|_| StatusCode::NO_CONTENT)
+        .ok_or_else(|| AppError::NotFound(format!("Monitor {} not found", id)))
+}
+
+#[derive(Debug, Clone)]
+pub struct Monitor {
+    pub id: String,
+    pub wallet_address: String,
+    pub market_slug: String,
+    pub webhook_url: String,
+    pub pnl_above: Option<f64>,
+    pub pnl_below: Option<f64>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Monitor {
+    pub fn view(&self) -> PositionMonitor {
+        PositionMonitor {
+            id: self.id.clone(),
+            wallet_address: self.wallet_address.clone(),
+            market_slug: self.market_slug.clone(),
+            webhook_url: self.webhook_url.clone(),
+            pnl_above: self.pnl_above,
+            pnl_below: self.pnl_below,
+            created_at: self.created_at.to_rfc3339(),
+        }
+    }
+
+    /// The threshold `pnl` has reached, if any. Bounds are inclusive.
+    pub fn crossed(&self, pnl: f64) -> Option<MonitorThreshold> {
+        match (self.pnl_above, self.pnl_below) {
+            (Some(above), _) if pnl >= above => Some(MonitorThreshold::PnlAbove(above)),
+            (_, Some(below)) if pnl <= below => Some(MonitorThreshold::PnlBelow(below)),
+            _ => None,
+        }
+    }
+}
+
+/// In-memory monitors keyed by id.
+#[derive(Debug, Default)]
+pub struct MonitorStore {
+    monitors: Mutex<HashMap<String, Monitor>>,
+}
+
+impl MonitorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, monitor: Monitor) -> std::result::Result<(), String> {
+        let mut monitors = self.monitors.lock().unwrap_or_else(|e| e.into_inner());
+        if monitors.len() >= MAX_MONITORS {
+            return Err(format!("Monitor limit of {} reached", MAX_MONITORS));
+        }
+        monitors.insert(monitor.id.clone(), monitor);
+        Ok(())
+    }
+
+    pub fn remove(&self, id: &str) -> Option<Monitor> {
+        let mut monitors = self.monitors.lock().unwrap_or_else(|e| e.into_inner());
+        monitors.remove(id)
+    }
+
+    pub fn all(&self) -> Vec<Monitor> {
+        let monitors = self.monitors.lock().unwrap_or_else(|e| e.into_inner());
+        monitors.values().cloned().collect()
+    }
+}
+
+/// Body POSTed to a monitor's webhook when its threshold is crossed.
+#[derive(Debug, Serialize)]
+struct ThresholdEvent<'a> {
+    event: EventKind,
+    event_id: String,
+    created_at: String,
+    monitor_id: &'a str,
+    market_slug: &'a str,
+    wallet_address: &'a str,
+    threshold: MonitorThreshold,
+    /// Unrealized plus, once resolved, realized P&L across the market
+    pnl: f64,
+    pair_status: PairStatus,
+    positions: &'a [Position],
+}
+
+/// Checks every monitor each `POSITION_MONITOR_TICK_SECS` until shutdown.
+pub fn spawn_scheduler(state: Arc<AppState>) {
+    let tick = state.config.position_monitor_tick;
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = state.shutdown.cancelled() => return,
+            }
+            check_monitors(&state).await;
+        }
+    });
+}
+
+/// Prices each monitored position and notifies the webhook of any monitor
+/// whose threshold is crossed. A monitor fires once and is then removed;
+/// lookups that fail are retried on the next tick.
+pub async fn check_monitors(state: &AppState) {
+    for monitor in state.position_monitors.all() {
+        if let Err(e) = check_monitor(state, &monitor).await {
+            tracing::warn!("Position monitor {} check failed: {}", monitor.id, e);
+        }
+    }
+}
+
+async fn check_monitor(state: &AppState, monitor: &Monitor) -> Result<()> {
+    let cached = state
+        .market_cache
+        .polymarket(&monitor.market_slug, false, || {
+            state
+                .polymarket_client
+                .get_market_by_slug(&monitor.market_slug)
+        })
+        .await?;
+    let market = MarketData::clone(&cached.market);
+    let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+    let positions = state
+        .polymarket_client
+        .get_market_position(
+            &monitor.wallet_address,
+            market.condition_id.as_deref(),
+            &token_ids,
+        )
+        .await?;
+    let tracked = tracked_positions(market, &positions, None)?;
+
+    let pnl: f64 = tracked
+        .positions
+        .iter()
+        .map(|p| p.unrealized_pnl + p.realized_pnl.unwrap_or(0.0))
+        .sum();
+    let Some(threshold) = monitor.crossed(pnl) else {
+        return Ok(());
+    };
+    // Removed first so a slow delivery can't fire it twice
+    if state.position_monitors.remove(&monitor.id).is_none() {
+        return Ok(());
+    }
+
+    tracing::info!(
+        "Position monitor {} crossed {:?} at P&L {:.2}",
+        monitor.id,
+        threshold,
+        pnl
+    );
+    let event = ThresholdEvent {
+        event: EventKind::ThresholdTriggered,
+        event_id: format!("evt_{}", uuid::Uuid::new_v4().simple()),
+        created_at: Utc::now().to_rfc3339(),
+        monitor_id: &monitor.id,
+        market_slug: &monitor.market_slug,
+        wallet_address: &monitor.wallet_address,
+        threshold,
+        pnl: (pnl * 100.0).round() / 100.0,
+        pair_status: tracked.pair_status,
+        positions: &tracked.positions,
+    };
+    state.webhooks.deliver(&monitor.webhook_url, &event).await;
+    Ok(())
+}
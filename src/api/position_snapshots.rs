@@ -0,0 +1,117 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::types::{PositionChange, PositionChanges, TrackedPositions};
+
+/// How long a snapshot is kept when its market has no end date (or has
+/// already ended).
+const DEFAULT_SNAPSHOT_TTL_SECS: i64 = 3_600;
+
+/// Positions as of one `track_changes` poll.
+#[derive(Debug)]
+struct Snapshot {
+    taken_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+    /// `(token_id, outcome, shares, pnl)` per position
+    positions: Vec<(String, String, f64, f64)>,
+}
+
+/// The latest tracked positions per owner (wallet or Kalshi portfolio) and
+/// market, so pollers get deltas between calls. A snapshot is dropped once
+/// its market's window has passed.
+#[derive(Debug, Default)]
+pub struct PositionSnapshotStore {
+    snapshots: Mutex<HashMap<(String, String), Snapshot>>,
+}
+
+impl PositionSnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `tracked` as the latest snapshot of `owner` in `market` and
+    /// returns how it changed since the snapshot it replaces. Positions
+    /// missing from either side count as zero shares and P&L.
+    pub fn record(
+        &self,
+        owner: &str,
+        market: &str,
+        tracked: &TrackedPositions,
+        now: DateTime<Utc>,
+    ) -> PositionChanges {
+        let current: Vec<(String, String, f64, f64)> = tracked
+            .positions
+            .iter()
+            .map(|p| {
+                (
+                    p.token_id.clone(),
+                    p.outcome.clone(),
+                    p.shares,
+                    p.unrealized_pnl + p.realized_pnl.unwrap_or(0.0),
+                )
+            })
+            .collect();
+        let expires_at = tracked
+            .market
+            .end_date
+            .filter(|end| *end > now)
+            .unwrap_or(now + chrono::Duration::seconds(DEFAULT_SNAPSHOT_TTL_SECS));
+
+        let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        snapshots.retain(|_, snapshot| snapshot.expires_at > now);
+        let previous = snapshots.insert(
+            (owner.to_lowercase(), market.to_string()),
+            Snapshot {
+                taken_at: now,
+                expires_at,
+                positions: current.clone(),
+            },
+        );
+        let Some(previous) = previous else {
+            return PositionChanges {
+                since: None,
+                elapsed_ms: None,
+                positions: Vec::new(),
+                new_fills_detected: false,
+            };
+        };
+
+        let find = |positions: &[(String, String, f64, f64)], token_id: &str| {
+            positions
+                .iter()
+                .find(|(id, ..)| id == token_id)
+                .map_or((0.0, 0.0), |(_, _, shares, pnl)| (*shares, *pnl))
+        };
+        let mut positions: Vec<PositionChange> = current
+            .iter()
+            .map(|(token_id, outcome, shares, pnl)| {
+                let (before_shares, before_pnl) = find(&previous.positions, token_id);
+                PositionChange {
+                    token_id: token_id.clone(),
+                    outcome: outcome.clone(),
+                    shares_delta: shares - before_shares,
+                    pnl_delta: pnl - before_pnl,
+                }
+            })
+            .collect();
+        // Positions closed out since the last poll
+        for (token_id, outcome, shares, pnl) in &previous.positions {
+            if !current.iter().any(|(id, ..)| id == token_id) {
+                positions.push(PositionChange {
+                    token_id: token_id.clone(),
+                    outcome: outcome.clone(),
+                    shares_delta: -shares,
+                    pnl_delta: -pnl,
+                });
+            }
+        }
+
+        PositionChanges {
+            since: Some(previous.taken_at.to_rfc3339()),
+            elapsed_ms: Some((now - previous.taken_at).num_milliseconds().max(0) as u64),
+            new_fills_detected: positions.iter().any(|p| p.shares_delta > 1e-9),
+            positions,
+        }
+    }
+}
@@ -1,51 +1,448 @@
-use axum::{extract::State, Json};
-use chrono::Utc;
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::task::JoinSet;
 
+use crate::api::csv_export::{csv_response, FormatQuery, LedgerRow};
+use crate::api::extract::{AppJson, AppQuery};
+use crate::api::fields::{select_response, FieldSelection};
+use crate::api::market_cache::CacheQuery;
+use crate::api::openapi::ErrorResponse;
 use crate::api::AppState;
+use crate::clients::polymarket::{PositionData, WalletTrade, MARKET_TIMEZONE};
+use crate::clients::PolymarketClient;
+use crate::request_id;
 use crate::types::{
-    PairStatus, Position, PositionTrackerRequest, PositionTrackerResponse, ResponseMetadata,
+    checksum_address, CrossPlatformPositionsResponse, MarketData, PairStatus, Position,
+    PositionTrackerRequest, PositionTrackerResponse, Price, ResponseMetadata, ShareImbalance,
+    TrackedPositions, TradeFill, WalletPositions,
 };
-use crate::Result;
+use crate::{AppError, Result};
+
+/// Names the Kalshi account in position snapshots when `portfolio_id` is
+/// omitted.
+const DEFAULT_KALSHI_PORTFOLIO: &str = "kalshi";
 
+/// A wallet's positions in a market and whether the pair locks a profit.
+/// With `platform: "both"` the response is a
+/// [`CrossPlatformPositionsResponse`] instead. `?fields=` trims the response
+/// to the selected fields; `?format=csv` (or `Accept: text/csv`) exports
+/// the positions, and with `include_history` the trades, as CSV.
+#[utoipa::path(
+    post,
+    path = "/api/position-tracker",
+    tag = "trading",
+    params(FieldSelection, CacheQuery, FormatQuery),
+    request_body = PositionTrackerRequest,
+    responses(
+        (status = 200, description = "Positions on one platform; `platform: both` answers with a CrossPlatformPositionsResponse", body = PositionTrackerResponse),
+        (status = 400, description = "Invalid request or field selection", body = ErrorResponse),
+        (status = 404, description = "Market not found", body = ErrorResponse),
+        (status = 502, description = "Upstream failure", body = ErrorResponse),
+    )
+)]
 pub async fn handler(
     State(state): State<Arc<AppState>>,
-    Json(request): Json<PositionTrackerRequest>,
-) -> Result<Json<PositionTrackerResponse>> {
+    Query(selection): Query<FieldSelection>,
+    Query(cache): Query<CacheQuery>,
+    Query(format): Query<FormatQuery>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<PositionTrackerRequest>,
+) -> Result<Response> {
+    track(&state, selection, cache, format.csv(&headers), request).await
+}
+
+/// [`handler`] with the request as query parameters, e.g.
+/// `GET /api/position-tracker?wallet_address=0x...&market_slug=...`, for
+/// polling and caching. `fields` selects the response fields.
+pub async fn get_handler(
+    State(state): State<Arc<AppState>>,
+    Query(cache): Query<CacheQuery>,
+    Query(format): Query<FormatQuery>,
+    headers: HeaderMap,
+    AppQuery(request): AppQuery<PositionTrackerRequest>,
+) -> Result<Response> {
+    let csv = format.csv(&headers);
+    track(&state, FieldSelection::default(), cache, csv, request).await
+}
+
+async fn track(
+    state: &AppState,
+    selection: FieldSelection,
+    cache: CacheQuery,
+    csv: bool,
+    mut request: PositionTrackerRequest,
+) -> Result<Response> {
     let start = Instant::now();
+    // Upstreams get, and the response echoes, checksummed addresses
+    if let Some(wallet) = &mut request.wallet_address {
+        *wallet = checksum_address("wallet_address", wallet)?;
+    }
+    for (i, wallet) in request.wallet_addresses.iter_mut().flatten().enumerate() {
+        *wallet = checksum_address(&format!("wallet_addresses[{}]", i), wallet)?;
+    }
+    let fields = request.fields.clone();
+    let fresh = cache.fresh();
+    let mut degraded_features = Vec::new();
 
-    // Validate request
-    if request.wallet_address.is_empty() {
-        return Err(crate::AppError::Validation(
-            "Wallet address is required".to_string(),
-        ));
+    let polymarket = async {
+        match request.platform.polymarket() {
+            true => track_polymarket(state, &request, fresh, &mut degraded_features)
+                .await
+                .map(Some),
+            false => Ok(None),
+        }
+    };
+    let kalshi = async {
+        match request.platform.kalshi() {
+            true => track_kalshi(state, &request, fresh).await.map(Some),
+            false => Ok(None),
+        }
+    };
+    let (mut polymarket, mut kalshi) = tokio::try_join!(polymarket, kalshi)?;
+
+    let track_changes = request.track_changes.unwrap_or(false);
+    if let Some((tracked, _)) = &mut polymarket {
+        let market_slug = tracked.market.slug.as_deref();
+        // Several wallets are stored one by one and tracked as a group
+        let owner = match &tracked.wallets {
+            Some(wallets) => {
+                for wallet in wallets.iter().filter(|w| w.error.is_none()) {
+                    record(
+                        state,
+                        &wallet.wallet_address,
+                        market_slug,
+                        &wallet.positions,
+                        &mut degraded_features,
+                    )
+                    .await;
+                }
+                let mut addresses: Vec<String> =
+                    wallets.iter().map(|w| w.wallet_address.clone()).collect();
+                addresses.sort();
+                addresses.join(",")
+            }
+            None => {
+                let wallet_address = request.wallet_address.clone().unwrap_or_default();
+                record(
+                    state,
+                    &wallet_address,
+                    market_slug,
+                    &tracked.positions,
+                    &mut degraded_features,
+                )
+                .await;
+                wallet_address
+            }
+        };
+        if track_changes {
+            diff_snapshot(state, &owner, tracked);
+        }
     }
+    if let Some((tracked, _)) = &mut kalshi {
+        let portfolio_id = request
+            .portfolio_id
+            .as_deref()
+            .unwrap_or(DEFAULT_KALSHI_PORTFOLIO);
+        let ticker = tracked.market.ticker.as_deref();
+        record(
+            state,
+            portfolio_id,
+            ticker,
+            &tracked.positions,
+            &mut degraded_features,
+        )
+        .await;
+        if track_changes {
+            diff_snapshot(state, portfolio_id, tracked);
+        }
+    }
+
+    let cache_hit = [&polymarket, &kalshi]
+        .into_iter()
+        .flatten()
+        .all(|(_, hit)| *hit);
+    let metadata = ResponseMetadata {
+        timestamp: Utc::now().to_rfc3339(),
+        execution_time_ms: start.elapsed().as_millis() as u64,
+        model_used: None,
+        retries: 0,
+        degraded_features,
+        custom_prompt: false,
+        dry_run: false,
+        cache_hit: Some(cache_hit),
+        request_id: request_id::current(),
+        ai_usage: None,
+        ai_provider: None,
+        query_compression: None,
+        timeout_budget: None,
+    };
 
-    // Determine current 15-min market
-    let market_timestamp = state.polymarket_client.calculate_15min_market_timestamp();
-    let market_slug = request.market_slug.unwrap_or_else(|| {
-        format!("15min-up-down-{}", market_timestamp.format("%Y%m%d-%H%M"))
+    // A spreadsheet has no room for the pair analysis or field selection
+    if csv {
+        let rows: Vec<LedgerRow> = [&polymarket, &kalshi]
+            .into_iter()
+            .flatten()
+            .flat_map(|(tracked, _)| ledger_rows(tracked, &metadata.timestamp))
+            .collect();
+        return Ok(csv_response(&rows, "positions"));
+    }
+
+    // Body selection wins over the query string
+    let fields = fields.or(selection.fields);
+    let value = match (polymarket, kalshi) {
+        (Some((polymarket, _)), Some((kalshi, _))) => select_response(
+            &CrossPlatformPositionsResponse {
+                net_pnl: polymarket.pnl() + kalshi.pnl(),
+                polymarket,
+                kalshi,
+                metadata,
+            },
+            fields.as_deref(),
+        )?,
+        (Some((tracked, _)), None) | (None, Some((tracked, _))) => select_response(
+            &PositionTrackerResponse { tracked, metadata },
+            fields.as_deref(),
+        )?,
+        (None, None) => unreachable!("every platform tracks at least one venue"),
+    };
+    Ok(Json(value).into_response())
+}
+
+/// The positions as of `as_of`, then the trades oldest first.
+pub fn ledger_rows(tracked: &TrackedPositions, as_of: &str) -> Vec<LedgerRow> {
+    let market = &tracked.market.question;
+    let positions = tracked.positions.iter().map(|position| LedgerRow {
+        market: market.clone(),
+        outcome: position.outcome.clone(),
+        side: None,
+        shares: position.shares,
+        avg_price: position.avg_price.value(),
+        current_price: Some(position.current_price.value()),
+        realized_pnl: position.realized_pnl,
+        unrealized_pnl: Some(position.unrealized_pnl),
+        timestamp: as_of.to_string(),
     });
+    let trades = tracked.trades.iter().flatten().map(|trade| LedgerRow {
+        market: market.clone(),
+        outcome: trade.outcome.clone(),
+        side: Some(trade.side.clone()),
+        shares: trade.size,
+        avg_price: trade.price.value(),
+        current_price: None,
+        realized_pnl: None,
+        unrealized_pnl: None,
+        timestamp: trade.timestamp.clone(),
+    });
+    positions.chain(trades).collect()
+}
+
+/// The wallet's positions in the requested (or current up/down window's)
+/// Polymarket market, and whether the market came from the cache.
+async fn track_polymarket(
+    state: &AppState,
+    request: &PositionTrackerRequest,
+    fresh: bool,
+    degraded_features: &mut Vec<String>,
+) -> Result<(TrackedPositions, bool)> {
+    let wallet_address = request.wallet_address.as_deref().unwrap_or_default();
 
+    // Determine the current up/down window
+    let interval = request.interval.unwrap_or_default();
+    let market_timestamp = PolymarketClient::market_window(interval, MARKET_TIMEZONE).current;
     // Fetch market data
-    let market = state.polymarket_client.get_market_by_slug(&market_slug).await?;
+    let cached = match &request.market_slug {
+        Some(market_slug) => {
+            state
+                .market_cache
+                .polymarket(market_slug, fresh, || {
+                    state.polymarket_client.get_market_by_slug(market_slug)
+                })
+                .await?
+        }
+        None => {
+            let asset = PolymarketClient::updown_asset(request.asset.as_deref())?;
+            let market_slug =
+                PolymarketClient::build_updown_slug(asset, interval, market_timestamp);
+            tracing::info!("Generated market slug: {}", market_slug);
+            state
+                .market_cache
+                .polymarket(&market_slug, fresh, || {
+                    state
+                        .polymarket_client
+                        .resolve_updown_market(&market_slug, market_timestamp)
+                })
+                .await?
+        }
+    };
+    let market = MarketData::clone(&cached.market);
 
     // Extract token IDs (Up/Down)
     let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
 
     if token_ids.len() < 2 {
-        return Err(crate::AppError::Validation(
+        return Err(AppError::Validation(
             "Market must have at least 2 outcomes".to_string(),
         ));
     }
 
-    // Fetch positions
-    let position_data = state
-        .polymarket_client
-        .get_market_position(&request.wallet_address, &token_ids)
-        .await?;
+    if let Some(wallets) = &request.wallet_addresses {
+        let tracked = track_wallets(state, market, wallets, &token_ids, degraded_features).await?;
+        return Ok((tracked, cached.hit));
+    }
+
+    // Fetch positions, along with the wallet's fills when history is wanted
+    let (position_data, trade_data) = if request.include_history.unwrap_or(false) {
+        let (positions, trades) = tokio::try_join!(
+            state.polymarket_client.get_market_position(
+                wallet_address,
+                market.condition_id.as_deref(),
+                &token_ids
+            ),
+            state.polymarket_client.get_trade_history(
+                wallet_address,
+                market.condition_id.as_deref(),
+                &token_ids
+            ),
+        )?;
+        (positions, Some(trades))
+    } else {
+        let positions = state
+            .polymarket_client
+            .get_market_position(wallet_address, market.condition_id.as_deref(), &token_ids)
+            .await?;
+        (positions, None)
+    };
+
+    let mut tracked = tracked_positions(market, &position_data, trade_data)?;
+    tracked.wallet_address = Some(wallet_address.to_string());
+    Ok((tracked, cached.hit))
+}
+
+/// The Kalshi account's positions in the requested market, and whether the
+/// market came from the cache.
+async fn track_kalshi(
+    state: &AppState,
+    request: &PositionTrackerRequest,
+    fresh: bool,
+) -> Result<(TrackedPositions, bool)> {
+    let kalshi = state.kalshi()?;
+    let ticker = request
+        .kalshi_ticker
+        .as_deref()
+        .unwrap_or_default()
+        .trim()
+        .to_uppercase();
+
+    let (cached, position_data) = tokio::try_join!(
+        state.market_cache.kalshi(kalshi, &ticker, fresh),
+        kalshi.get_positions(Some(&ticker)),
+    )?;
+    let market = MarketData::clone(&cached.market);
+
+    Ok((tracked_positions(market, &position_data, None)?, cached.hit))
+}
+
+/// Every wallet's positions in `market`, fetched concurrently, with the
+/// aggregate as the top-level positions: shares summed per token and the
+/// average price weighted by shares. A wallet whose lookup fails is
+/// reported with its error and left out; only when every lookup fails is
+/// the first error returned.
+async fn track_wallets(
+    state: &AppState,
+    market: MarketData,
+    wallets: &[String],
+    token_ids: &[String],
+    degraded_features: &mut Vec<String>,
+) -> Result<TrackedPositions> {
+    let mut lookups = JoinSet::new();
+    for (index, wallet) in wallets.iter().enumerate() {
+        let venue = state.polymarket_client.clone();
+        let wallet = wallet.clone();
+        let condition_id = market.condition_id.clone();
+        let token_ids = token_ids.to_vec();
+        lookups.spawn(async move {
+            let positions = venue
+                .get_market_position(&wallet, condition_id.as_deref(), &token_ids)
+                .await;
+            (index, positions)
+        });
+    }
+    let mut fetched: Vec<Option<Result<Vec<PositionData>>>> =
+        wallets.iter().map(|_| None).collect();
+    while let Some(joined) = lookups.join_next().await {
+        let (index, positions) =
+            joined.map_err(|e| anyhow::anyhow!("Position lookup task failed: {}", e))?;
+        fetched[index] = Some(positions);
+    }
 
+    let mut breakdown = Vec::with_capacity(wallets.len());
+    let mut combined: Vec<PositionData> = Vec::new();
+    let mut first_error = None;
+    for (wallet, positions) in wallets.iter().zip(fetched) {
+        let wallet_address = wallet.clone();
+        match positions.expect("every lookup task reports back") {
+            Ok(positions) => {
+                for p in &positions {
+                    match combined.iter_mut().find(|c| c.token_id == p.token_id) {
+                        Some(c) => {
+                            let shares = c.shares + p.shares;
+                            if shares > EPSILON {
+                                c.avg_price =
+                                    (c.avg_price * c.shares + p.avg_price * p.shares) / shares;
+                            }
+                            c.shares = shares;
+                        }
+                        None => combined.push(p.clone()),
+                    }
+                }
+                let tracked = tracked_positions(market.clone(), &positions, None)?;
+                breakdown.push(WalletPositions {
+                    wallet_address,
+                    pnl: tracked.pnl(),
+                    positions: tracked.positions,
+                    pair_status: Some(tracked.pair_status),
+                    error: None,
+                });
+            }
+            Err(e) => {
+                tracing::warn!("Positions for wallet {} unavailable: {}", wallet_address, e);
+                if !degraded_features.iter().any(|f| f == "wallet_positions") {
+                    degraded_features.push("wallet_positions".to_string());
+                }
+                breakdown.push(WalletPositions {
+                    wallet_address,
+                    positions: Vec::new(),
+                    pair_status: None,
+                    pnl: 0.0,
+                    error: Some(e.to_string()),
+                });
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if let Some(e) = first_error.filter(|_| breakdown.iter().all(|w| w.error.is_some())) {
+        return Err(e);
+    }
+
+    let mut tracked = tracked_positions(market, &combined, None)?;
+    tracked.wallets = Some(breakdown);
+    Ok(tracked)
+}
+
+/// Priced positions, pair status and, given fills, their history.
+pub(crate) fn tracked_positions(
+    market: MarketData,
+    position_data: &[PositionData],
+    trade_data: Option<Vec<WalletTrade>>,
+) -> Result<TrackedPositions> {
     // Calculate positions and pair status
     let positions: Vec<Position> = position_data
         .iter()
@@ -56,65 +453,313 @@ pub async fn handler(
                 .find(|o| o.id == p.token_id)
                 .map(|o| o.name.clone())
                 .unwrap_or_else(|| "Unknown".to_string());
+            // Once resolved, winning shares pay $1 and the rest nothing
+            let payout_price =
+                market
+                    .resolved_outcome
+                    .as_ref()
+                    .map(|winner| if *winner == outcome { 1.0 } else { 0.0 });
 
-            Position {
+            Ok(Position {
                 token_id: p.token_id.clone(),
                 outcome,
                 shares: p.shares,
-                avg_price: p.avg_price,
-                current_price: p.current_price,
-                unrealized_pnl: (p.current_price - p.avg_price) * p.shares,
-            }
+                avg_price: Price::from_decimal(p.avg_price)?,
+                current_price: Price::from_decimal(p.current_price)?,
+                unrealized_pnl: match payout_price {
+                    Some(_) => 0.0,
+                    None => (p.current_price - p.avg_price) * p.shares,
+                },
+                realized_pnl: payout_price.map(|payout| (payout - p.avg_price) * p.shares),
+            })
         })
-        .collect();
+        .collect::<Result<_>>()?;
 
     // Calculate pair status
-    let (pair_status, profit_lock, break_even) = calculate_pair_status(&positions);
+    let pair = calculate_pair_status(&positions);
+    let realized_payout = market.resolved_outcome.as_ref().map(|winner| {
+        positions
+            .iter()
+            .filter(|p| p.outcome == *winner)
+            .map(|p| p.shares)
+            .sum()
+    });
 
-    let execution_time = start.elapsed().as_millis() as u64;
+    let trades = trade_data
+        .map(|trades| {
+            trades
+                .into_iter()
+                .map(|t| {
+                    Ok(TradeFill {
+                        token_id: t.asset,
+                        outcome: t.outcome,
+                        side: t.side.to_lowercase(),
+                        price: Price::from_decimal(t.price)?,
+                        size: t.size,
+                        timestamp: DateTime::from_timestamp(t.timestamp, 0)
+                            .map(|at| at.to_rfc3339())
+                            .unwrap_or_default(),
+                        fee: t.fee,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        })
+        .transpose()?;
+    let history = trades
+        .as_deref()
+        .map(|trades| summarize_trades(trades, market.resolved_outcome.as_deref()));
 
-    Ok(Json(PositionTrackerResponse {
+    Ok(TrackedPositions {
+        wallet_address: None,
         market,
         positions,
-        pair_status,
-        profit_lock,
-        break_even,
-        metadata: ResponseMetadata {
-            timestamp: Utc::now().to_rfc3339(),
-            execution_time_ms: execution_time,
-            model_used: None,
-            retries: 0,
-        },
-    }))
+        pair_status: pair.status,
+        profit_lock: pair.profit_lock,
+        break_even: pair.break_even,
+        imbalance: pair.imbalance,
+        realized_payout,
+        realized_pnl: history.as_ref().map(|h| h.realized_pnl),
+        total_invested: history.as_ref().map(|h| h.total_invested),
+        trades,
+        changes: None,
+        wallets: None,
+    })
 }
 
-fn calculate_pair_status(positions: &[Position]) -> (PairStatus, Option<f64>, Option<f64>) {
-    if positions.len() < 2 {
-        return (PairStatus::NoPosition, None, None);
+/// Stores a position snapshot under `owner` (a wallet address or Kalshi
+/// portfolio) when persistence is configured.
+async fn record(
+    state: &AppState,
+    owner: &str,
+    market: Option<&str>,
+    positions: &[Position],
+    degraded_features: &mut Vec<String>,
+) {
+    let Some(storage) = &state.storage else {
+        return;
+    };
+    if let Err(e) = storage.record_positions(owner, market, positions).await {
+        tracing::warn!("Failed to store position snapshot: {}", e);
+        if !degraded_features.iter().any(|f| f == "persistence") {
+            degraded_features.push("persistence".to_string());
+        }
     }
+}
 
-    let up_position = positions.iter().find(|p| p.outcome.contains("Up"));
-    let down_position = positions.iter().find(|p| p.outcome.contains("Down"));
+/// Sets `tracked.changes` to the movement since `owner`'s previous
+/// `track_changes` poll of the market, and keeps this poll for the next.
+fn diff_snapshot(state: &AppState, owner: &str, tracked: &mut TrackedPositions) {
+    let market = tracked
+        .market
+        .slug
+        .clone()
+        .or_else(|| tracked.market.ticker.clone())
+        .unwrap_or_else(|| tracked.market.id.clone());
+    let changes = state
+        .position_snapshots
+        .record(owner, &market, tracked, Utc::now());
+    tracked.changes = Some(changes);
+}
 
-    match (up_position, down_position) {
-        (Some(up), Some(down)) => {
-            let up_pnl = up.unrealized_pnl;
-            let down_pnl = down.unrealized_pnl;
-            let total_pnl = up_pnl + down_pnl;
+/// Tolerance for comparing dollar amounts and share counts.
+const EPSILON: f64 = 1e-9;
 
-            if total_pnl > 0.0 {
-                // Profit locked
-                (PairStatus::ProfitLocked, Some(total_pnl), None)
-            } else if total_pnl == 0.0 {
-                // Break even
-                (PairStatus::BreakEven, None, Some(0.0))
-            } else {
-                // At risk
-                let break_even_price = (up.avg_price + down.avg_price) / 2.0;
-                (PairStatus::AtRisk, None, Some(break_even_price))
-            }
+#[derive(Debug, PartialEq)]
+pub struct PairSummary {
+    pub status: PairStatus,
+    pub profit_lock: Option<f64>,
+    pub break_even: Option<f64>,
+    pub imbalance: Option<ShareImbalance>,
+}
+
+/// The side of a binary pair an outcome is on: Up/Down in the 15-min
+/// markets, Yes/No on Kalshi.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Leg {
+    Up,
+    Down,
+    Yes,
+    No,
+}
+
+impl Leg {
+    fn of(outcome: &str) -> Option<Leg> {
+        if outcome.contains("Up") {
+            Some(Leg::Up)
+        } else if outcome.contains("Down") {
+            Some(Leg::Down)
+        } else if outcome.eq_ignore_ascii_case("yes") {
+            Some(Leg::Yes)
+        } else if outcome.eq_ignore_ascii_case("no") {
+            Some(Leg::No)
+        } else {
+            None
+        }
+    }
+
+    /// Up and Yes are the first side of their pair.
+    fn is_first(self) -> bool {
+        matches!(self, Leg::Up | Leg::Yes)
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Leg::Up => "Up",
+            Leg::Down => "Down",
+            Leg::Yes => "Yes",
+            Leg::No => "No",
         }
-        _ => (PairStatus::NoPosition, None, None),
     }
 }
 
+/// Holding both sides pays $1 per matched Up/Down (or Yes/No) pair at
+/// resolution, whatever the prices do, so a straddle is locked only when
+/// `min(up, down)` shares outweigh the total cost of every share held.
+///
+/// When not locked, `break_even` is the price below which buying the
+/// lagging side's missing shares would lock a profit, if any price can.
+pub fn calculate_pair_status(positions: &[Position]) -> PairSummary {
+    let legs: Vec<(Leg, f64)> = positions
+        .iter()
+        .filter_map(|p| Leg::of(&p.outcome).map(|leg| (leg, p.shares)))
+        .collect();
+    let shares_of = |first: bool| -> f64 {
+        legs.iter()
+            .filter(|(leg, _)| leg.is_first() == first)
+            .map(|(_, shares)| shares)
+            .sum()
+    };
+    let (up_shares, down_shares) = (shares_of(true), shares_of(false));
+
+    if up_shares + down_shares <= EPSILON {
+        return PairSummary {
+            status: PairStatus::NoPosition,
+            profit_lock: None,
+            break_even: None,
+            imbalance: None,
+        };
+    }
+
+    let cost: f64 = positions
+        .iter()
+        .map(|p| p.avg_price.value() * p.shares)
+        .sum();
+    let matched = up_shares.min(down_shares);
+    let missing = (up_shares - down_shares).abs();
+    let imbalance = (missing > EPSILON).then(|| {
+        let heavy_first = up_shares > down_shares;
+        let heavy = legs
+            .iter()
+            .map(|(leg, _)| *leg)
+            .find(|leg| leg.is_first() == heavy_first);
+        ShareImbalance {
+            outcome: heavy
+                .map_or(if heavy_first { "Up" } else { "Down" }, Leg::name)
+                .to_string(),
+            shares: missing,
+        }
+    });
+
+    let locked = matched - cost;
+    if locked > EPSILON {
+        return PairSummary {
+            status: PairStatus::ProfitLocked,
+            profit_lock: Some(locked),
+            break_even: None,
+            imbalance,
+        };
+    }
+
+    // Filling the gap matches every share held: payout becomes the larger
+    // side, so the fill must cost less than that minus what's already spent
+    let break_even = (missing > EPSILON)
+        .then(|| (up_shares.max(down_shares) - cost) / missing)
+        .filter(|price| *price > 0.0)
+        .map(|price| price.min(1.0));
+
+    PairSummary {
+        status: if locked.abs() <= EPSILON {
+            PairStatus::BreakEven
+        } else {
+            PairStatus::AtRisk
+        },
+        profit_lock: None,
+        break_even,
+        imbalance,
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct TradeSummary {
+    pub realized_pnl: f64,
+    pub total_invested: f64,
+}
+
+/// Shares bought in one fill that haven't been sold yet.
+struct Lot {
+    shares: f64,
+    /// Fill price plus the fill's fee spread across its shares
+    unit_cost: f64,
+}
+
+/// Realized P&L and total invested from fills, oldest first.
+///
+/// Sells close the oldest open lots of their token first (FIFO). Once the
+/// market has resolved, lots still open settle at $1 for `resolved_outcome`
+/// and $0 otherwise. Shares sold beyond those bought (e.g. from a split)
+/// have no known cost and are left out.
+pub fn summarize_trades(trades: &[TradeFill], resolved_outcome: Option<&str>) -> TradeSummary {
+    let mut open: HashMap<&str, (&str, VecDeque<Lot>)> = HashMap::new();
+    let mut realized_pnl = 0.0;
+    let mut total_invested = 0.0;
+
+    for trade in trades {
+        if trade.size <= EPSILON {
+            continue;
+        }
+        let fee = trade.fee.unwrap_or(0.0);
+        let (_, lots) = open
+            .entry(trade.token_id.as_str())
+            .or_insert_with(|| (trade.outcome.as_str(), VecDeque::new()));
+
+        if trade.side == "buy" {
+            let cost = trade.price.value() * trade.size + fee;
+            total_invested += cost;
+            lots.push_back(Lot {
+                shares: trade.size,
+                unit_cost: cost / trade.size,
+            });
+            continue;
+        }
+
+        let unit_proceeds = trade.price.value() - fee / trade.size;
+        let mut remaining = trade.size;
+        while remaining > EPSILON {
+            let Some(lot) = lots.front_mut() else {
+                break;
+            };
+            let closed = remaining.min(lot.shares);
+            realized_pnl += closed * (unit_proceeds - lot.unit_cost);
+            lot.shares -= closed;
+            remaining -= closed;
+            if lot.shares <= EPSILON {
+                lots.pop_front();
+            }
+        }
+    }
+
+    if let Some(winner) = resolved_outcome {
+        for (outcome, lots) in open.values() {
+            let payout = if *outcome == winner { 1.0 } else { 0.0 };
+            realized_pnl += lots
+                .iter()
+                .map(|lot| lot.shares * (payout - lot.unit_cost))
+                .sum::<f64>();
+        }
+    }
+
+    TradeSummary {
+        realized_pnl,
+        total_invested,
+    }
+}
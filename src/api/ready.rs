@@ -0,0 +1,24 @@
+use axum::{extract::State, Json};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api::capabilities::{Capabilities, EndpointStatus};
+use crate::api::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct ReadyResponse {
+    pub status: &'static str,
+    pub capabilities: Capabilities,
+    /// Routes that need optional integrations, and whether they can be used
+    pub endpoints: Vec<EndpointStatus>,
+}
+
+/// Readiness for load balancers and operators: the server is up, and these
+/// are the features it was started with.
+pub async fn handler(State(state): State<Arc<AppState>>) -> Json<ReadyResponse> {
+    Json(ReadyResponse {
+        status: "ready",
+        capabilities: state.capabilities,
+        endpoints: state.capabilities.endpoints(),
+    })
+}
@@ -0,0 +1,203 @@
+use axum::{extract::State, Json};
+use chrono::Utc;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::analysis_store::{new_analysis_id, MarketSnapshot, StoredAnalysis};
+use crate::api::analyze_event_markets::{fetch_market, resolve_provider, run_analysis};
+use crate::api::extract::AppJson;
+use crate::api::AppState;
+use crate::clients::ai::prompts::PromptEvidence;
+use crate::request_id;
+use crate::types::{
+    AiAnalysis, AnalysisChange, MarketData, MarketMovement, RefreshAnalysisRequest,
+    RefreshAnalysisResponse, ResponseMetadata,
+};
+use crate::{AppError, Result};
+
+pub const DEFAULT_PRICE_THRESHOLD: f64 = 0.05;
+pub const DEFAULT_VOLUME_THRESHOLD: f64 = 0.5;
+
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    AppJson(request): AppJson<RefreshAnalysisRequest>,
+) -> Result<Json<RefreshAnalysisResponse>> {
+    let start = Instant::now();
+
+    // Validate request
+    let price_threshold = request
+        .price_threshold
+        .unwrap_or(state.config.analysis_refresh_price_threshold);
+    let volume_threshold = request
+        .volume_threshold
+        .unwrap_or(state.config.analysis_refresh_volume_threshold);
+    if price_threshold < 0.0 || volume_threshold < 0.0 {
+        return Err(AppError::Validation(
+            "Thresholds must not be negative".to_string(),
+        ));
+    }
+
+    let previous = state
+        .analysis_store
+        .get(&request.analysis_id)
+        .ok_or_else(|| AppError::NotFound(format!("Analysis {} not found", request.analysis_id)))?;
+
+    // Fetched the way the analysis was, bypassing the cache to see the
+    // market as it is now
+    let cached = fetch_market(&state, &previous.market, true).await?;
+    let market_data = MarketData::clone(&cached.market);
+
+    let movement = compute_movement(
+        &previous.snapshot,
+        &market_data,
+        price_threshold,
+        volume_threshold,
+    );
+
+    if !exceeds_threshold(&movement) {
+        return Ok(Json(RefreshAnalysisResponse {
+            analysis_id: previous.id.clone(),
+            previous_analysis_id: previous.id,
+            stale: false,
+            movement,
+            analysis: previous.analysis,
+            changes: None,
+            market_data,
+            metadata: ResponseMetadata {
+                timestamp: Utc::now().to_rfc3339(),
+                execution_time_ms: start.elapsed().as_millis() as u64,
+                model_used: None,
+                retries: 0,
+                degraded_features: Vec::new(),
+                custom_prompt: false,
+                dry_run: false,
+                cache_hit: None,
+                request_id: request_id::current(),
+                ai_usage: None,
+                ai_provider: None,
+                query_compression: None,
+                timeout_budget: None,
+            },
+        }));
+    }
+
+    let provider = resolve_provider(previous.model.as_deref());
+    let run = run_analysis(
+        &state,
+        &market_data,
+        previous.question.as_ref(),
+        previous.custom_prompt.as_deref(),
+        &PromptEvidence::default(),
+        provider,
+        &previous.ai_options,
+        true,
+    )
+    .await?;
+    let changes = diff_analyses(&previous.analysis, &run.analysis);
+    let timeout_budget = run.timeout_budget(&previous.ai_options);
+
+    let analysis_id = new_analysis_id();
+    state.analysis_store.insert(StoredAnalysis {
+        id: analysis_id.clone(),
+        url: previous.url.clone(),
+        market: previous.market.clone(),
+        question: previous.question.clone(),
+        model: previous.model.clone(),
+        custom_prompt: previous.custom_prompt.clone(),
+        ai_options: previous.ai_options.clone(),
+        snapshot: MarketSnapshot::capture(&market_data),
+        analysis: run.analysis.clone(),
+        created_at: Utc::now(),
+    });
+
+    Ok(Json(RefreshAnalysisResponse {
+        analysis_id,
+        previous_analysis_id: previous.id,
+        stale: true,
+        movement,
+        analysis: run.analysis,
+        changes: Some(changes),
+        market_data,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: Some(run.model_used),
+            retries: run.retries,
+            degraded_features: Vec::new(),
+            custom_prompt: previous.custom_prompt.is_some(),
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            query_compression: None,
+            timeout_budget: Some(timeout_budget),
+            ai_usage: Some(run.usage),
+            ai_provider: None,
+        },
+    }))
+}
+
+/// How far a market has moved since `previous` was captured: the largest
+/// absolute price change of any outcome present in both, and the relative
+/// volume growth (None when there is no prior volume to compare against).
+pub fn compute_movement(
+    previous: &MarketSnapshot,
+    current: &MarketData,
+    price_threshold: f64,
+    volume_threshold: f64,
+) -> MarketMovement {
+    let max_price_delta = current
+        .outcomes
+        .iter()
+        .filter_map(|o| {
+            previous
+                .prices
+                .iter()
+                .find(|(id, _)| *id == o.id)
+                .map(|(_, price)| (o.price.value() - price).abs())
+        })
+        .fold(0.0, f64::max);
+
+    let volume_growth = match (previous.volume, current.volume) {
+        (Some(before), Some(after)) if before > 0.0 => Some((after - before) / before),
+        _ => None,
+    };
+
+    MarketMovement {
+        max_price_delta,
+        volume_growth,
+        price_threshold,
+        volume_threshold,
+    }
+}
+
+pub fn exceeds_threshold(movement: &MarketMovement) -> bool {
+    movement.max_price_delta >= movement.price_threshold
+        || movement
+            .volume_growth
+            .is_some_and(|growth| growth >= movement.volume_threshold)
+}
+
+fn diff_analyses(previous: &AiAnalysis, current: &AiAnalysis) -> AnalysisChange {
+    let before: HashSet<&String> = previous.key_factors.iter().collect();
+    let after: HashSet<&String> = current.key_factors.iter().collect();
+
+    AnalysisChange {
+        previous_recommendation: previous.recommendation.clone(),
+        recommendation_changed: previous.recommendation != current.recommendation,
+        previous_confidence: previous.confidence,
+        confidence_delta: current.confidence - previous.confidence,
+        added_key_factors: current
+            .key_factors
+            .iter()
+            .filter(|f| !before.contains(f))
+            .cloned()
+            .collect(),
+        removed_key_factors: previous
+            .key_factors
+            .iter()
+            .filter(|f| !after.contains(f))
+            .cloned()
+            .collect(),
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::clients::ResearchSource;
+use crate::types::PolyfactualResearchResponse;
+use crate::Result;
+
+pub const DEFAULT_TTL_SECS: u64 = 3600;
+pub const DEFAULT_MAX_ENTRIES: usize = 500;
+
+type Slot = Arc<tokio::sync::Mutex<Option<(PolyfactualResearchResponse, Instant)>>>;
+
+/// Polyfactual answers by normalized query, since the same questions are
+/// asked over and over and each run is slow and paid for.
+///
+/// Like [`MarketCache`](crate::api::market_cache::MarketCache), each query
+/// has its own lock held across the upstream call, so concurrent identical
+/// queries share one run. Past `max_entries` the least recently used query
+/// is evicted. Failures are not cached.
+#[derive(Debug)]
+pub struct ResearchCache {
+    slots: Mutex<HashMap<String, (Slot, Instant)>>,
+    ttl: Duration,
+    max_entries: usize,
+}
+
+impl ResearchCache {
+    pub fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            slots: Mutex::new(HashMap::new()),
+            ttl,
+            max_entries: max_entries.max(1),
+        }
+    }
+
+    /// Runs `query` on `source`, or returns the cached answer for it with
+    /// `metadata.cache_hit` set and the original timestamp. `fresh` skips the
+    /// cached answer and replaces it. `timeout` limits each attempt of a
+    /// run and doesn't affect which answers are reused.
+    pub async fn research(
+        &self,
+        source: &dyn ResearchSource,
+        query: String,
+        fresh: bool,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse> {
+        if self.ttl.is_zero() {
+            return source.research(query, timeout).await;
+        }
+
+        let slot = self.slot(normalize(&query));
+        let mut entry = slot.lock().await;
+        if let (Some((response, fetched_at)), false) = (entry.as_ref(), fresh) {
+            if fetched_at.elapsed() < self.ttl {
+                let mut response = response.clone();
+                response.metadata.cache_hit = Some(true);
+                return Ok(response);
+            }
+        }
+
+        let mut response = source.research(query, timeout).await?;
+        response.metadata.cache_hit = Some(false);
+        *entry = Some((response.clone(), Instant::now()));
+        Ok(response)
+    }
+
+    fn slot(&self, key: String) -> Slot {
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        if let Some((slot, last_used)) = slots.get_mut(&key) {
+            *last_used = now;
+            return slot.clone();
+        }
+
+        if slots.len() >= self.max_entries {
+            // Busy slots are mid-run and about to be fresh
+            let ttl = self.ttl;
+            slots.retain(|_, (slot, _)| match slot.try_lock() {
+                Ok(entry) => entry
+                    .as_ref()
+                    .is_some_and(|(_, fetched_at)| fetched_at.elapsed() < ttl),
+                Err(_) => true,
+            });
+        }
+        while slots.len() >= self.max_entries {
+            let least_recent = slots
+                .iter()
+                .filter(|(_, (slot, _))| slot.try_lock().is_ok())
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            match least_recent {
+                Some(key) => slots.remove(&key),
+                // Every entry is mid-run; go over the limit briefly
+                None => break,
+            };
+        }
+
+        let slot = Slot::default();
+        slots.insert(key, (slot.clone(), now));
+        slot
+    }
+}
+
+/// Trimmed, lowercased and with whitespace runs collapsed, so trivially
+/// different spellings of a question share an entry.
+pub fn normalize(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
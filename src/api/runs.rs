@@ -0,0 +1,64 @@
+use axum::{
+    extract::{Path, State},
+    http::HeaderMap,
+    Json,
+};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::admin::require_admin;
+use crate::api::pagination::{PageParams, Paginated};
+use crate::api::AppState;
+use crate::request_id;
+use crate::types::{ResponseMetadata, RunResponse, StoredRunSummary};
+use crate::{AppError, Result};
+
+/// Stored bot runs, newest first. Runs span every wallet that used the
+/// bot, so both routes need the admin token.
+pub async fn list_runs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    page: PageParams,
+) -> Result<Json<Paginated<StoredRunSummary>>> {
+    require_admin(&state, &headers)?;
+    let storage = state.storage()?;
+    let (runs, total) = storage.list_runs(page.offset, page.limit).await?;
+    let has_more = ((page.offset + runs.len()) as u64) < total;
+
+    Ok(Json(Paginated::new(runs, &page, has_more, Some(total))))
+}
+
+/// A stored run with its orders and logs.
+pub async fn get_run(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> Result<Json<RunResponse>> {
+    let start = Instant::now();
+    require_admin(&state, &headers)?;
+    let storage = state.storage()?;
+    let run = storage
+        .get_run(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Run {} not found", id)))?;
+
+    Ok(Json(RunResponse {
+        run,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run: false,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
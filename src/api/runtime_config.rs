@@ -0,0 +1,109 @@
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+
+use crate::types::{KnownFields, Validate};
+use crate::{AppError, Result};
+
+/// Operational settings that can be changed without a redeploy via
+/// `/api/admin/runtime-config`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSettings {
+    /// Shown to end users on `/status/public`.
+    pub incident_message: Option<String>,
+    /// Safe-mode switch: when false every order-placing path refuses to run.
+    pub trading_enabled: bool,
+    /// When and by whom `trading_enabled` was last changed at runtime.
+    pub trading_changed_at: Option<String>,
+    pub trading_changed_by: Option<String>,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            incident_message: None,
+            trading_enabled: true,
+            trading_changed_at: None,
+            trading_changed_by: None,
+        }
+    }
+}
+
+/// Partial update; absent fields are left unchanged. An empty
+/// `incident_message` clears it.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuntimeSettingsUpdate {
+    pub incident_message: Option<String>,
+    pub trading_enabled: Option<bool>,
+}
+
+impl KnownFields for RuntimeSettingsUpdate {
+    const FIELDS: &'static [&'static str] = &["incident_message", "trading_enabled"];
+}
+
+impl Validate for RuntimeSettingsUpdate {
+    fn validate(&self) -> crate::Result<()> {
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RuntimeConfig {
+    settings: RwLock<RuntimeSettings>,
+}
+
+impl RuntimeConfig {
+    /// Starts from defaults, with trading off when `trading_enabled` is
+    /// false (`TRADING_ENABLED=false`).
+    pub fn new(trading_enabled: bool) -> Self {
+        let mut settings = RuntimeSettings {
+            trading_enabled,
+            ..RuntimeSettings::default()
+        };
+        if !trading_enabled {
+            settings.trading_changed_at = Some(Utc::now().to_rfc3339());
+            settings.trading_changed_by = Some("TRADING_ENABLED env".to_string());
+        }
+
+        Self {
+            settings: RwLock::new(settings),
+        }
+    }
+
+    pub fn snapshot(&self) -> RuntimeSettings {
+        self.settings
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    pub fn apply(&self, update: RuntimeSettingsUpdate, actor: &str) -> RuntimeSettings {
+        let mut settings = self.settings.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(message) = update.incident_message {
+            let message = message.trim().to_string();
+            settings.incident_message = (!message.is_empty()).then_some(message);
+        }
+        if let Some(enabled) = update.trading_enabled {
+            if enabled != settings.trading_enabled {
+                settings.trading_enabled = enabled;
+                settings.trading_changed_at = Some(Utc::now().to_rfc3339());
+                settings.trading_changed_by = Some(actor.to_string());
+            }
+        }
+        settings.clone()
+    }
+
+    /// Checked before every order placement, so flipping the flag stops work
+    /// already in flight rather than only new requests.
+    pub fn ensure_trading_enabled(&self) -> Result<()> {
+        let settings = self.settings.read().unwrap_or_else(|e| e.into_inner());
+        if settings.trading_enabled {
+            return Ok(());
+        }
+        Err(AppError::TradingDisabled {
+            since: settings.trading_changed_at.clone(),
+            actor: settings.trading_changed_by.clone(),
+        })
+    }
+}
@@ -0,0 +1,204 @@
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::api::AppState;
+
+/// How often the drain reports what it is still waiting on.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Work that shutdown waits for: requests being handled and scheduled runs
+/// (such as an auto-trade window) that place orders.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    next_id: AtomicU64,
+    entries: Mutex<HashMap<u64, (String, Instant)>>,
+    idle: Notify,
+}
+
+/// Removes its entry from [`InFlight`] when dropped.
+#[derive(Debug)]
+pub struct InFlightGuard {
+    in_flight: Arc<InFlight>,
+    id: u64,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        let mut entries = self
+            .in_flight
+            .entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        entries.remove(&self.id);
+        if entries.is_empty() {
+            self.in_flight.idle.notify_waiters();
+        }
+    }
+}
+
+impl InFlight {
+    /// Registers work described by `label` until the guard is dropped.
+    pub fn track(self: &Arc<Self>, label: String) -> InFlightGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.entries
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(id, (label, Instant::now()));
+        InFlightGuard {
+            in_flight: self.clone(),
+            id,
+        }
+    }
+
+    /// What is running, oldest first, with how long it has been running.
+    pub fn snapshot(&self) -> Vec<(String, Duration)> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        let mut running: Vec<(String, Duration)> = entries
+            .values()
+            .map(|(label, started)| (label.clone(), started.elapsed()))
+            .collect();
+        running.sort_by_key(|(_, running_for)| std::cmp::Reverse(*running_for));
+        running
+    }
+
+    /// Resolves once nothing is tracked.
+    pub async fn wait_idle(&self) {
+        loop {
+            // Created before the check so a drop in between still wakes us
+            let idle = self.idle.notified();
+            if self
+                .entries
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .is_empty()
+            {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    fn describe(&self) -> String {
+        let running = self.snapshot();
+        if running.is_empty() {
+            return "open connections".to_string();
+        }
+        running
+            .iter()
+            .map(|(label, running_for)| format!("{} ({}s)", label, running_for.as_secs()))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Tracks each request in [`AppState::in_flight`] while its handler runs.
+pub async fn track_in_flight(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let _guard = state
+        .in_flight
+        .track(format!("{} {}", request.method(), request.uri().path()));
+    next.run(request).await
+}
+
+/// Cancels `shutdown` on SIGINT or SIGTERM.
+pub fn spawn_signal_handler(shutdown: CancellationToken) {
+    tokio::spawn(async move {
+        let ctrl_c = async {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                tracing::warn!("Failed to listen for Ctrl-C: {}", e);
+                std::future::pending::<()>().await;
+            }
+        };
+        #[cfg(unix)]
+        let terminate = async {
+            use tokio::signal::unix::{signal, SignalKind};
+            match signal(SignalKind::terminate()) {
+                Ok(mut sigterm) => {
+                    sigterm.recv().await;
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to listen for SIGTERM: {}", e);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = ctrl_c => tracing::info!("Received SIGINT"),
+            _ = terminate => tracing::info!("Received SIGTERM"),
+        }
+        shutdown.cancel();
+    });
+}
+
+/// Runs `server` until `shutdown` is cancelled, then waits up to
+/// `drain_timeout` for open connections to finish and tracked work to
+/// complete, logging what is still running along the way. Work left at the
+/// deadline is abandoned.
+pub async fn serve_until_drained<F>(
+    server: F,
+    shutdown: &CancellationToken,
+    in_flight: &InFlight,
+    drain_timeout: Duration,
+) -> std::io::Result<()>
+where
+    F: Future<Output = std::io::Result<()>>,
+{
+    tokio::pin!(server);
+    tokio::select! {
+        // Checked first so a server that stops because of the signal is
+        // still drained
+        biased;
+        _ = shutdown.cancelled() => {}
+        result = &mut server => return result,
+    }
+
+    tracing::info!(
+        "Shutting down: no longer accepting connections; draining for up to {}s",
+        drain_timeout.as_secs()
+    );
+    let drained = async {
+        server.await?;
+        in_flight.wait_idle().await;
+        Ok::<_, std::io::Error>(())
+    };
+    tokio::pin!(drained);
+    let deadline = tokio::time::sleep(drain_timeout);
+    tokio::pin!(deadline);
+    let mut progress = tokio::time::interval(PROGRESS_INTERVAL);
+    progress.tick().await;
+
+    loop {
+        tokio::select! {
+            result = &mut drained => {
+                tracing::info!("Drained; exiting");
+                return result;
+            }
+            _ = &mut deadline => {
+                tracing::warn!(
+                    "Drain timeout reached; abandoning: {}",
+                    in_flight.describe()
+                );
+                return Ok(());
+            }
+            _ = progress.tick() => {
+                tracing::info!("Draining; waiting on: {}", in_flight.describe());
+            }
+        }
+    }
+}
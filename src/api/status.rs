@@ -0,0 +1,100 @@
+use axum::{
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use std::sync::Arc;
+
+use crate::api::AppState;
+
+const PUBLIC_STATUS_CACHE_CONTROL: &str = "public, max-age=15";
+
+/// Internal view of what is configured and healthy. Never serialized — it is
+/// only ever mapped through [`derive_public_status`].
+#[derive(Debug, Clone)]
+pub struct InternalStatus {
+    pub dome_configured: bool,
+    pub polyfactual_configured: bool,
+    pub grok_configured: bool,
+    pub openai_configured: bool,
+    pub trading_enabled: bool,
+    pub incident_message: Option<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverallStatus {
+    Operational,
+    Degraded,
+    Down,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct FeatureAvailability {
+    pub analysis: bool,
+    pub research: bool,
+    pub trading: bool,
+    pub tracking: bool,
+}
+
+/// Deliberately minimal payload for embedding in the public frontend.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct PublicStatus {
+    pub status: OverallStatus,
+    pub features: FeatureAvailability,
+    pub incident: Option<String>,
+}
+
+/// Maps internal state onto public categories. Every public field is derived
+/// explicitly here, so new internal checks can't leak into the payload.
+pub fn derive_public_status(internal: &InternalStatus) -> PublicStatus {
+    let features = FeatureAvailability {
+        analysis: internal.dome_configured
+            && (internal.grok_configured || internal.openai_configured),
+        research: internal.polyfactual_configured,
+        // Trading and tracking only need Polymarket's public endpoints;
+        // trading is also off while the safe-mode switch is engaged
+        trading: internal.trading_enabled,
+        tracking: true,
+    };
+
+    let available = [
+        features.analysis,
+        features.research,
+        features.trading,
+        features.tracking,
+    ];
+    let status = if available.iter().all(|a| *a) {
+        OverallStatus::Operational
+    } else if available.iter().any(|a| *a) {
+        OverallStatus::Degraded
+    } else {
+        OverallStatus::Down
+    };
+
+    PublicStatus {
+        status,
+        features,
+        incident: internal.incident_message.clone(),
+    }
+}
+
+pub async fn public_handler(State(state): State<Arc<AppState>>) -> Response {
+    let settings = state.runtime_config.snapshot();
+    let internal = InternalStatus {
+        dome_configured: state.capabilities.dome,
+        polyfactual_configured: state.capabilities.polyfactual,
+        grok_configured: state.capabilities.grok,
+        openai_configured: state.capabilities.openai,
+        trading_enabled: settings.trading_enabled,
+        incident_message: settings.incident_message,
+    };
+
+    (
+        [(header::CACHE_CONTROL, PUBLIC_STATUS_CACHE_CONTROL)],
+        Json(derive_public_status(&internal)),
+    )
+        .into_response()
+}
@@ -0,0 +1,263 @@
+use axum::{extract::State, http::HeaderMap, Json};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::api::extract::AppJson;
+use crate::api::limit_order_bot::{ensure_open, place_checked, PlannedOrder};
+use crate::api::orders::wallet_auth;
+use crate::api::AppState;
+use crate::clients::clob_signing::{round_order, MIN_ORDER_NOTIONAL_USD};
+use crate::clients::polymarket::PositionData;
+use crate::request_id;
+use crate::types::{
+    HeldLeg, MarketData, OrderResult, ResponseMetadata, UnwindLegRequest, UnwindLegResponse,
+    UnwindProjection,
+};
+use crate::{AppError, Result};
+
+/// Sells `fraction` of one leg of the wallet's position in a market, e.g.
+/// taking profit on the side of a straddle that moved while holding the
+/// other. Signed like the order routes, with the `X-Wallet-Private-Key`
+/// header or the server's wallet; dry runs and the trading switch apply as
+/// they do for the bot.
+pub async fn handler(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    AppJson(request): AppJson<UnwindLegRequest>,
+) -> Result<Json<UnwindLegResponse>> {
+    let start = Instant::now();
+    let dry_run = request.dry_run.unwrap_or(false);
+    if !dry_run {
+        state.runtime_config.ensure_trading_enabled()?;
+    }
+    let auth = wallet_auth(&headers, state.config.wallet.as_ref())?;
+
+    let market = state
+        .polymarket_client
+        .get_market_by_slug(&request.market_slug)
+        .await?;
+    ensure_open(&market)?;
+
+    let wallet = auth.address().to_checksum(None);
+    let token_ids: Vec<String> = market.outcomes.iter().map(|o| o.id.clone()).collect();
+    let positions = state
+        .polymarket_client
+        .get_market_position(&wallet, market.condition_id.as_deref(), &token_ids)
+        .await?;
+    let held = held_legs(&market, &positions);
+    let leg = pick_leg(&held, &request.leg)?.clone();
+
+    let params = state
+        .polymarket_client
+        .get_market_params(&leg.token_id)
+        .await;
+    let book = state
+        .polymarket_client
+        .get_order_book(&leg.token_id)
+        .await?;
+    let best_bid = book
+        .best_bid
+        .ok_or_else(|| AppError::Validation(format!("No bids on {} to sell into", leg.outcome)))?;
+    let offset = request.price_offset.unwrap_or(0.0);
+    let limit = best_bid - offset;
+    if limit < params.tick_size {
+        return Err(AppError::Validation(format!(
+            "price_offset {} leaves no price to sell at under the {} best bid of {}",
+            offset, leg.outcome, best_bid
+        )));
+    }
+
+    let wanted = leg.shares * request.fraction;
+    let (price, size) = round_order(limit, wanted, params.tick_size, MIN_ORDER_NOTIONAL_USD)
+        .and_then(|(price, size)| {
+            if size < params.min_order_size {
+                Err(format!(
+                    "size {:.2} is under the market's {} share minimum",
+                    size, params.min_order_size
+                ))
+            } else {
+                Ok((price, size))
+            }
+        })
+        .map_err(|reason| {
+            AppError::Validation(format!(
+                "Can't sell {} of {:.2} {} shares: {}",
+                request.fraction, leg.shares, leg.outcome, reason
+            ))
+        })?;
+
+    let order = PlannedOrder {
+        token_id: leg.token_id.clone(),
+        outcome: leg.outcome.clone(),
+        side: "sell",
+        price,
+        size,
+    };
+    tracing::info!(
+        "Unwinding {} of {} {} shares in {} @ {}",
+        size,
+        leg.shares,
+        leg.outcome,
+        request.market_slug,
+        price
+    );
+    let placed = place_checked(&state, &auth, &auth.fingerprint(), &order, None, dry_run).await;
+    if !dry_run {
+        state.metrics.order_placed(match &placed {
+            Ok(placed) => placed.status.as_str(),
+            Err(_) => "failed",
+        });
+    }
+    let placed = OrderResult {
+        outcome: leg.outcome.clone(),
+        ..placed?
+    };
+
+    let projection = project(&market, &held, &leg.token_id, price.value(), size);
+    Ok(Json(UnwindLegResponse {
+        market_slug: request.market_slug,
+        order: placed,
+        projection,
+        metadata: ResponseMetadata {
+            timestamp: Utc::now().to_rfc3339(),
+            execution_time_ms: start.elapsed().as_millis() as u64,
+            model_used: None,
+            retries: 0,
+            degraded_features: Vec::new(),
+            custom_prompt: false,
+            dry_run,
+            cache_hit: None,
+            request_id: request_id::current(),
+            ai_usage: None,
+            ai_provider: None,
+            query_compression: None,
+            timeout_budget: None,
+        },
+    }))
+}
+
+/// The wallet's position in each of the market's outcomes, in market order,
+/// with outcomes it doesn't hold at 0 shares.
+fn held_legs(market: &MarketData, positions: &[PositionData]) -> Vec<HeldLeg> {
+    market
+        .outcomes
+        .iter()
+        .map(|outcome| {
+            let position = positions.iter().find(|p| p.token_id == outcome.id);
+            HeldLeg {
+                token_id: outcome.id.clone(),
+                outcome: outcome.name.clone(),
+                shares: position.map_or(0.0, |p| p.shares.max(0.0)),
+                avg_price: position.map_or(0.0, |p| p.avg_price),
+                current_price: position.map_or(outcome.price.value(), |p| p.current_price),
+            }
+        })
+        .collect()
+}
+
+/// The leg `requested` names: "winning" and "losing" compare each held
+/// leg's current price with its average entry price, ties going to the
+/// first outcome. Naming a leg the wallet doesn't hold is refused with what
+/// it does hold.
+fn pick_leg<'a>(held: &'a [HeldLeg], requested: &str) -> Result<&'a HeldLeg> {
+    let holdings = || {
+        held.iter()
+            .map(|leg| format!("{} {:.2}", leg.outcome, leg.shares))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let owned = held.iter().filter(|leg| leg.shares > 0.0);
+    let gain = |leg: &&HeldLeg| leg.current_price - leg.avg_price;
+    let requested = requested.trim();
+
+    let leg = match requested.to_ascii_lowercase().as_str() {
+        "winning" => owned.fold(None, |best: Option<&HeldLeg>, leg| match best {
+            Some(best) if gain(&best) >= gain(&leg) => Some(best),
+            _ => Some(leg),
+        }),
+        "losing" => owned.fold(None, |worst: Option<&HeldLeg>, leg| match worst {
+            Some(worst) if gain(&worst) <= gain(&leg) => Some(worst),
+            _ => Some(leg),
+        }),
+        _ => {
+            let leg = held
+                .iter()
+                .find(|leg| {
+                    leg.token_id == requested || leg.outcome.eq_ignore_ascii_case(requested)
+                })
+                .ok_or_else(|| {
+                    AppError::Validation(format!(
+                        "Unknown leg '{}': expected winning, losing or one of {}",
+                        requested,
+                        held.iter()
+                            .map(|leg| leg.outcome.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))
+                })?;
+            Some(leg).filter(|leg| leg.shares > 0.0)
+        }
+    };
+
+    leg.ok_or_else(|| {
+        AppError::Validation(format!(
+            "No shares to sell for leg '{}'; held: {}",
+            requested,
+            holdings()
+        ))
+    })
+}
+
+/// Holdings once `size` shares of `sold` fill at `price`, and the profit or
+/// loss at settlement under the outcome that pays the least.
+fn project(
+    market: &MarketData,
+    held: &[HeldLeg],
+    sold: &str,
+    price: f64,
+    size: f64,
+) -> UnwindProjection {
+    let cost: f64 = held.iter().map(|leg| leg.shares * leg.avg_price).sum();
+    let sale_proceeds = price * size;
+    let positions: Vec<HeldLeg> = held
+        .iter()
+        .map(|leg| {
+            let mut leg = leg.clone();
+            if leg.token_id == sold {
+                leg.shares = ((leg.shares - size) * 100.0).round() / 100.0;
+            }
+            leg
+        })
+        .filter(|leg| leg.shares > 0.0)
+        .collect();
+    let remaining_value = positions
+        .iter()
+        .map(|leg| leg.shares * leg.current_price)
+        .sum();
+
+    let (worst_case_outcome, worst_case_pnl) = market
+        .outcomes
+        .iter()
+        .map(|outcome| {
+            let payout: f64 = positions
+                .iter()
+                .filter(|leg| leg.token_id == outcome.id)
+                .map(|leg| leg.shares)
+                .sum();
+            (outcome.name.clone(), payout + sale_proceeds - cost)
+        })
+        .fold(None, |worst: Option<(String, f64)>, case| match worst {
+            Some(worst) if worst.1 <= case.1 => Some(worst),
+            _ => Some(case),
+        })
+        .unwrap_or_default();
+
+    UnwindProjection {
+        positions,
+        sale_proceeds,
+        remaining_value,
+        worst_case_outcome,
+        worst_case_pnl,
+    }
+}
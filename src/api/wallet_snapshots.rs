@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::clients::TradingVenue;
+
+/// Snapshots kept per wallet (hourly snapshots cover ~90 days).
+const MAX_SNAPSHOTS_PER_WALLET: usize = 2_200;
+pub const DEFAULT_SNAPSHOT_INTERVAL_SECS: u64 = 3_600;
+
+/// A strategy wallet tracked for the leaderboard. Only the label is ever
+/// exposed through the API.
+#[derive(Debug, Clone)]
+pub struct TrackedWallet {
+    pub label: String,
+    pub address: String,
+}
+
+/// Parses `TRACKED_WALLETS`, e.g. `momentum=0xabc…,ladder=0xdef…`.
+pub fn parse_tracked_wallets(raw: &str) -> Vec<TrackedWallet> {
+    raw.split(',')
+        .filter_map(|entry| {
+            let (label, address) = entry.split_once('=')?;
+            let (label, address) = (label.trim(), address.trim());
+            (!label.is_empty() && !address.is_empty()).then(|| TrackedWallet {
+                label: label.to_string(),
+                address: address.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Cumulative wallet totals at `taken_at`.
+#[derive(Debug, Clone, Copy)]
+pub struct WalletSnapshot {
+    pub taken_at: DateTime<Utc>,
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    pub volume: f64,
+}
+
+impl WalletSnapshot {
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl
+    }
+}
+
+/// In-memory snapshot history keyed by wallet label, oldest first.
+#[derive(Debug, Default)]
+pub struct WalletSnapshotStore {
+    snapshots: Mutex<HashMap<String, Vec<WalletSnapshot>>>,
+}
+
+impl WalletSnapshotStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, label: &str, snapshot: WalletSnapshot) {
+        let mut snapshots = self.snapshots.lock().unwrap_or_else(|e| e.into_inner());
+        let history = snapshots.entry(label.to_string()).or_default();
+        let position = history.partition_point(|s| s.taken_at <= snapshot.taken_at);
+        history.insert(position, snapshot);
+        if history.len() > MAX_SNAPSHOTS_PER_WALLET {
+            let excess = history.len() - MAX_SNAPSHOTS_PER_WALLET;
+            history.drain(..excess);
+        }
+    }
+
+    pub fn history(&self) -> HashMap<String, Vec<WalletSnapshot>> {
+        self.snapshots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// Periodically snapshots every tracked wallet's P&L until `shutdown`. A
+/// no-op without tracked wallets.
+pub fn spawn_snapshotter(
+    client: Arc<dyn TradingVenue>,
+    store: Arc<WalletSnapshotStore>,
+    wallets: Vec<TrackedWallet>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    if wallets.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            for wallet in &wallets {
+                match client.get_wallet_pnl(&wallet.address).await {
+                    Ok(pnl) => store.record(
+                        &wallet.label,
+                        WalletSnapshot {
+                            taken_at: Utc::now(),
+                            realized_pnl: pnl.realized_pnl,
+                            unrealized_pnl: pnl.unrealized_pnl,
+                            volume: pnl.volume,
+                        },
+                    ),
+                    Err(e) => tracing::warn!("Snapshot failed for wallet {}: {}", wallet.label, e),
+                }
+            }
+        }
+    });
+}
@@ -0,0 +1,315 @@
+use crate::clients::ai::prompts::{PromptMessage, PromptRole};
+use crate::clients::ai::{
+    parse_ai_analysis, record_attempt, record_retry_delay, record_timeout, record_usage, AiAttempt,
+    AiClient, AiProvider, AiRequestOptions, AiResult, TokenUsage, DEFAULT_TEMPERATURE,
+};
+use crate::clients::{handle_upstream_response, transport_error, TimedSend, UpstreamSettings};
+use crate::clients::circuit_breaker::CircuitBreaker;
+use crate::clients::rate_limit::RateLimiter;
+use crate::clients::recorder::{ai_parse_failure, parse_json};
+use crate::clients::retry::{retry_observed, RetryPolicy};
+use crate::config::Config;
+use crate::metrics::{Metrics, UpstreamApi};
+use crate::types::AiAnalysis;
+use crate::{AppError, Result};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com/v1";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+const DEFAULT_MODEL: &str = "claude-sonnet-4-5";
+/// The messages API requires `max_tokens`, so unlike the others it always
+/// has a value.
+const MAX_TOKENS: u32 = 4096;
+pub const DEFAULT_RPM: f64 = 60.0;
+
+/// Clients are built per request, so they share one process-wide limiter.
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+static BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
+
+/// The circuit breaker every Claude client shares.
+pub fn circuit_breaker() -> &'static CircuitBreaker {
+    BREAKER.get_or_init(|| CircuitBreaker::configured(UpstreamApi::Anthropic))
+}
+
+/// The messages API has no JSON response mode, so the format is asked for
+/// in the system prompt instead.
+const JSON_SYSTEM_PROMPT: &str = "Respond with a single JSON object and nothing else: no prose before or after it and no markdown code fences.";
+
+#[derive(Debug, Serialize)]
+struct ClaudeRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<Message>,
+    temperature: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClaudeResponse {
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct ContentBlock {
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(default)]
+    text: String,
+}
+
+pub struct ClaudeClient {
+    client: Client,
+    base_url: String,
+    /// Paces calls (`ANTHROPIC_RPM`)
+    limiter: &'static RateLimiter,
+    api_key: String,
+    model: String,
+    temperature: f64,
+    max_tokens: u32,
+    /// Per-request limit (`timeout_secs`)
+    timeout: Duration,
+    /// Tokens billed across this client's calls
+    billed: Mutex<TokenUsage>,
+    /// Set once a call runs out of `timeout`
+    timed_out: AtomicBool,
+    retry: RetryPolicy,
+    /// Every call's status and retry delay, oldest first
+    attempts: Mutex<Vec<AiAttempt>>,
+}
+
+impl ClaudeClient {
+    /// A client for `api_key`, failing when it isn't set. `model` is used
+    /// when the request doesn't name one; `base_url` defaults to the
+    /// production API.
+    pub fn new(
+        api_key: Option<String>,
+        model: Option<String>,
+        options: &AiRequestOptions,
+        retry: RetryPolicy,
+        client: Client,
+        base_url: Option<String>,
+    ) -> Result<Self> {
+        let api_key = api_key.ok_or_else(|| AppError::missing_api_key("ANTHROPIC_API_KEY"))?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.unwrap_or_else(|| ANTHROPIC_API_BASE.to_string()),
+            limiter: LIMITER.get_or_init(|| {
+                let settings = UpstreamSettings::current();
+                RateLimiter::new("Anthropic API", settings.anthropic_rpm / 60.0, settings.rate_limit_max_wait)
+            }),
+            api_key,
+            model: options.resolve_model(model, DEFAULT_MODEL),
+            temperature: options.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            max_tokens: options.max_tokens.unwrap_or(MAX_TOKENS),
+            timeout: options.timeout(),
+            billed: Mutex::default(),
+            timed_out: AtomicBool::new(false),
+            retry,
+            attempts: Mutex::default(),
+        })
+    }
+
+    /// Key and default model from the environment, as read by [`Config`].
+    pub fn from_env(options: &AiRequestOptions) -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        let retry = config.ai_retry_policy(&AiProvider::Claude);
+        Self::new(
+            config.anthropic_api_key,
+            config.anthropic_model,
+            options,
+            retry,
+            config.http.clone(),
+            None,
+        )
+    }
+
+    fn completions_url(&self) -> String {
+        format!("{}/messages", self.base_url)
+    }
+
+    async fn call_with_retry(&self, messages: Vec<PromptMessage>) -> Result<AiAnalysis> {
+        let mut retries: u32 = 0;
+        let retried = retry_observed(
+            || self.call_api(&messages),
+            self.retry,
+            |_, delay| {
+                retries += 1;
+                record_retry_delay(&self.attempts, delay);
+            },
+        )
+        .await;
+        Metrics::global().ai_retried(self.provider_name(), retries);
+        Ok(retried?.value)
+    }
+
+    async fn call_api(&self, messages: &[PromptMessage]) -> Result<AiAnalysis> {
+        let content = self.fetch_content(messages, true).await?;
+
+        // Parse JSON from content
+        match parse_ai_analysis(&content) {
+            Ok(analysis) => Ok(analysis),
+            Err(e) => {
+                tracing::debug!(
+                    provider = self.provider_name(),
+                    raw_content = %content,
+                    "Unparseable AI analysis: {}",
+                    e
+                );
+                Err(ai_parse_failure(e, &self.completions_url(), &content).await)
+            }
+        }
+    }
+
+    async fn fetch_content(
+        &self,
+        messages: &[PromptMessage],
+        json_response: bool,
+    ) -> Result<String> {
+        // The messages API takes the system prompt apart from the turns
+        let (system, turns): (Vec<_>, Vec<_>) = messages
+            .iter()
+            .partition(|m| m.role == PromptRole::System);
+        let mut system: Vec<&str> = system.iter().map(|m| m.content.as_str()).collect();
+        if json_response {
+            system.push(JSON_SYSTEM_PROMPT);
+        }
+        let request = ClaudeRequest {
+            model: self.model.clone(),
+            max_tokens: self.max_tokens,
+            system: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages: turns
+                .iter()
+                .map(|m| Message {
+                    role: m.role.as_str().to_string(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            temperature: self.temperature,
+        };
+
+        let response = self
+            .client
+            .post(self.completions_url())
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Anthropic)
+            .await
+            .map_err(|e| {
+                record_timeout(&self.timed_out, &e);
+                record_attempt(&self.attempts, None);
+                transport_error("Claude API", e, self.timeout)
+            })?;
+        record_attempt(&self.attempts, Some(response.status().as_u16()));
+
+        let response = handle_upstream_response(response, "Claude API").await?;
+
+        let claude_response: ClaudeResponse = parse_json(response, "Claude response").await?;
+        if let Some(usage) = &claude_response.usage {
+            record_usage(
+                &self.billed,
+                TokenUsage {
+                    prompt_tokens: usage.input_tokens,
+                    completion_tokens: usage.output_tokens,
+                },
+            );
+        }
+
+        let content: String = claude_response
+            .content
+            .iter()
+            .filter(|block| block.type_ == "text")
+            .map(|block| block.text.as_str())
+            .collect();
+        if content.is_empty() {
+            return Err(AppError::ExternalApi(
+                "No content in Claude response".to_string(),
+            ));
+        }
+
+        Ok(content)
+    }
+}
+
+#[async_trait::async_trait]
+impl AiClient for ClaudeClient {
+    async fn analyze_markets(&self, messages: Vec<PromptMessage>) -> Result<AiResult> {
+        self.limiter.acquire().await?;
+        let analysis = circuit_breaker()
+            .call(self.call_with_retry(messages))
+            .await?;
+        Ok(AiResult {
+            analysis,
+            usage: self.usage(),
+            model: self.model.clone(),
+        })
+    }
+
+    async fn complete(&self, prompt: String) -> Result<String> {
+        self.limiter.acquire().await?;
+        circuit_breaker()
+            .call(self.fetch_content(&[PromptMessage::user(prompt)], false))
+            .await
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "claude"
+    }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn usage(&self) -> TokenUsage {
+        *self.billed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    fn attempts(&self) -> Vec<AiAttempt> {
+        self.attempts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let response = self
+            .client
+            .get(format!("{}/models", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Anthropic)
+            .await
+            .map_err(|e| transport_error("Claude API", e, self.timeout))?;
+        handle_upstream_response(response, "Claude API").await?;
+        Ok(())
+    }
+}
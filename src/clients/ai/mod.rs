@@ -1,30 +1,415 @@
+pub mod claude;
 pub mod grok;
 pub mod openai;
+pub mod pricing;
 pub mod prompts;
 
+pub use claude::ClaudeClient;
 pub use grok::GrokClient;
 pub use openai::OpenAiClient;
 
+use crate::clients::ai::prompts::PromptMessage;
+use crate::clients::circuit_breaker::CircuitBreaker;
+use crate::config::Config;
 use crate::types::AiAnalysis;
 use crate::Result;
 use async_trait::async_trait;
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
 pub enum AiProvider {
     Grok,
     OpenAi,
+    Claude,
+}
+
+impl AiProvider {
+    /// The name its client reports, e.g. `openai`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AiProvider::Grok => "grok",
+            AiProvider::OpenAi => "openai",
+            AiProvider::Claude => "claude",
+        }
+    }
+}
+
+/// Tokens a provider reported billing for one or more calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl TokenUsage {
+    pub fn add(&mut self, other: TokenUsage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+/// One call to a provider: the HTTP status it answered with (`None` when
+/// no response arrived) and, when the call was retried, how long we waited
+/// before the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AiAttempt {
+    pub status: Option<u16>,
+    pub retry_delay: Option<Duration>,
+}
+
+/// An analysis with the model that wrote it and the tokens it took,
+/// retried attempts included.
+#[derive(Debug, Clone)]
+pub struct AiResult {
+    pub analysis: AiAnalysis,
+    pub usage: TokenUsage,
+    pub model: String,
 }
 
 #[async_trait]
 pub trait AiClient: Send + Sync {
-    async fn analyze_markets(&self, prompt: String) -> Result<AiAnalysis>;
+    /// Analyzes a chat prompt (see [`prompts`]) into the JSON analysis schema.
+    async fn analyze_markets(&self, messages: Vec<PromptMessage>) -> Result<AiResult>;
+    /// Free-form text completion, without the JSON analysis schema.
+    async fn complete(&self, prompt: String) -> Result<String>;
     fn provider_name(&self) -> &'static str;
+    /// The concrete model sent to the provider, e.g. `gpt-4o`.
+    fn model_name(&self) -> &str;
+    /// Tokens billed to this client so far, including calls that failed
+    /// after the provider answered (e.g. an unparseable reply).
+    fn usage(&self) -> TokenUsage;
+    /// Whether a call ran out of the request's time limit, whether or not
+    /// a retry then succeeded.
+    fn timed_out(&self) -> bool;
+    /// Every call made so far, oldest first.
+    fn attempts(&self) -> Vec<AiAttempt>;
+    /// Lists the provider's models: a cheap call that checks the key.
+    async fn ping(&self) -> Result<()>;
+
+    /// `text` rewritten in at most `max_chars` characters, as the model
+    /// answered; the caller checks the length.
+    async fn summarize(&self, text: &str, max_chars: usize) -> Result<String> {
+        let summary = self
+            .complete(prompts::build_query_summary_prompt(text, max_chars))
+            .await?;
+        Ok(summary.trim().trim_matches('"').trim().to_string())
+    }
+
+    /// Calls that failed and were retried.
+    fn retries(&self) -> u32 {
+        self.attempts()
+            .iter()
+            .filter(|attempt| attempt.retry_delay.is_some())
+            .count() as u32
+    }
+}
+
+/// Adds what a provider reported for one call to a client's running total.
+pub(crate) fn record_usage(total: &std::sync::Mutex<TokenUsage>, usage: TokenUsage) {
+    total.lock().unwrap_or_else(|e| e.into_inner()).add(usage);
+}
+
+/// Notes on a client's flag that a call ran out of its time limit.
+pub(crate) fn record_timeout(timed_out: &std::sync::atomic::AtomicBool, error: &reqwest::Error) {
+    if error.is_timeout() {
+        timed_out.store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+/// Notes a call on a client's attempt history.
+pub(crate) fn record_attempt(attempts: &std::sync::Mutex<Vec<AiAttempt>>, status: Option<u16>) {
+    attempts
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(AiAttempt {
+            status,
+            retry_delay: None,
+        });
+}
+
+/// Notes on a client's latest call that it is being retried after `delay`.
+pub(crate) fn record_retry_delay(attempts: &std::sync::Mutex<Vec<AiAttempt>>, delay: Duration) {
+    if let Some(last) = attempts
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .last_mut()
+    {
+        last.retry_delay = Some(delay);
+    }
+}
+
+/// Retries after a provider's first attempt when `<PROVIDER>_MAX_RETRIES`
+/// isn't set.
+pub const DEFAULT_AI_MAX_RETRIES: u32 = 2;
+/// First retry backoff when `AI_RETRY_BASE_DELAY_MS` isn't set.
+pub const DEFAULT_AI_RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+
+/// Sampling temperature used when a request doesn't set one.
+pub const DEFAULT_TEMPERATURE: f64 = 0.7;
+
+/// Time limit on one provider call when a request doesn't set
+/// `timeout_secs`.
+pub const DEFAULT_AI_TIMEOUT: Duration = Duration::from_secs(120);
+/// Longest `timeout_secs` a request may ask for.
+pub const MAX_AI_TIMEOUT_SECS: u64 = 600;
+
+/// Per-request overrides of the provider's model, sampling settings and
+/// time limit. Unset fields fall back to the client's defaults.
+#[derive(Debug, Clone, Default)]
+pub struct AiRequestOptions {
+    pub model_name: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    /// Seconds each provider call may take; retries get their own
+    pub timeout_secs: Option<u64>,
+}
+
+impl AiRequestOptions {
+    pub fn validate(&self, provider: &AiProvider) -> std::result::Result<(), String> {
+        if let Some(model_name) = &self.model_name {
+            if model_name.trim().is_empty() {
+                return Err("model_name must not be empty".to_string());
+            }
+        }
+        if let Some(temperature) = self.temperature {
+            if !(0.0..=2.0).contains(&temperature) {
+                return Err("temperature must be between 0 and 2".to_string());
+            }
+            // Anthropic's messages API only accepts 0 to 1
+            if matches!(provider, AiProvider::Claude) && temperature > 1.0 {
+                return Err("temperature must be between 0 and 1 for claude".to_string());
+            }
+        }
+        if self.max_tokens == Some(0) {
+            return Err("max_tokens must be greater than 0".to_string());
+        }
+        if self
+            .timeout_secs
+            .is_some_and(|secs| !(1..=MAX_AI_TIMEOUT_SECS).contains(&secs))
+        {
+            return Err(format!(
+                "timeout_secs must be between 1 and {}",
+                MAX_AI_TIMEOUT_SECS
+            ));
+        }
+        Ok(())
+    }
+
+    /// The limit on each provider call: `timeout_secs`, else the default.
+    pub fn timeout(&self) -> Duration {
+        self.timeout_secs.map_or(DEFAULT_AI_TIMEOUT, Duration::from_secs)
+    }
+
+    /// The requested model, else `configured`, else `default`.
+    pub(crate) fn resolve_model(&self, configured: Option<String>, default: &str) -> String {
+        self.model_name
+            .clone()
+            .or(configured)
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| default.to_string())
+    }
+}
+
+/// Characters of the raw reply quoted in a parse error.
+const RAW_EXCERPT_CHARS: usize = 500;
+
+/// Parses a model reply into an analysis, tolerating what models wrap JSON
+/// in: markdown code fences, a sentence before or after the object, a
+/// `confidence` given as a percentage (`"75%"` or `75`) and a
+/// `recommendation` in the wrong case (`"buy_yes"`, `"Buy Yes"`).
+///
+/// The error quotes the start of the raw reply.
+pub fn parse_ai_analysis(content: &str) -> std::result::Result<AiAnalysis, String> {
+    let failure = |reason: String| {
+        let excerpt: String = content.trim().chars().take(RAW_EXCERPT_CHARS).collect();
+        format!("{}; model returned: {:?}", reason, excerpt)
+    };
+
+    let json = first_json_object(strip_code_fence(content))
+        .ok_or_else(|| failure("no JSON object found".to_string()))?;
+    let mut value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| failure(e.to_string()))?;
+
+    normalize_fields(&mut value);
+    serde_json::from_value(value).map_err(|e| failure(e.to_string()))
+}
+
+/// Parses a reply to a combined prompt, `{"analyses": [...]}` with one
+/// analysis per market, into input order. Entries are matched by their
+/// 1-based `market` number when every entry has one, by position otherwise.
+pub fn parse_combined_analyses(
+    content: &str,
+    expected: usize,
+) -> std::result::Result<Vec<AiAnalysis>, String> {
+    let failure = |reason: String| {
+        let excerpt: String = content.trim().chars().take(RAW_EXCERPT_CHARS).collect();
+        format!("{}; model returned: {:?}", reason, excerpt)
+    };
+
+    let json = first_json_object(strip_code_fence(content))
+        .ok_or_else(|| failure("no JSON object found".to_string()))?;
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| failure(e.to_string()))?;
+    let mut entries = match value {
+        serde_json::Value::Object(mut object) => match object.remove("analyses") {
+            Some(serde_json::Value::Array(entries)) => entries,
+            _ => return Err(failure("missing \"analyses\" array".to_string())),
+        },
+        _ => return Err(failure("expected a JSON object".to_string())),
+    };
+    if entries.len() != expected {
+        return Err(failure(format!(
+            "expected {} analyses, got {}",
+            expected,
+            entries.len()
+        )));
+    }
+
+    let numbers: Option<Vec<u64>> = entries
+        .iter()
+        .map(|entry| entry.get("market").and_then(|n| n.as_u64()))
+        .collect();
+    if let Some(numbers) = numbers {
+        let mut sorted = numbers.clone();
+        sorted.sort_unstable();
+        if sorted != (1..=expected as u64).collect::<Vec<_>>() {
+            return Err(failure(format!(
+                "market numbers {:?} don't cover 1 to {}",
+                numbers, expected
+            )));
+        }
+        let mut numbered: Vec<_> = numbers.into_iter().zip(entries).collect();
+        numbered.sort_by_key(|(number, _)| *number);
+        entries = numbered.into_iter().map(|(_, entry)| entry).collect();
+    }
+
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            normalize_fields(&mut entry);
+            serde_json::from_value(entry).map_err(|e| failure(e.to_string()))
+        })
+        .collect()
+}
+
+fn normalize_fields(value: &mut serde_json::Value) {
+    if let Some(confidence) = value.get_mut("confidence") {
+        if let Some(normalized) = normalize_confidence(confidence) {
+            *confidence = normalized.into();
+        }
+    }
+    if let Some(serde_json::Value::String(recommendation)) = value.get_mut("recommendation") {
+        *recommendation = recommendation
+            .trim()
+            .to_ascii_uppercase()
+            .replace([' ', '-'], "_");
+    }
+}
+
+/// The body of the first ``` fence, or the text unchanged without one.
+fn strip_code_fence(text: &str) -> &str {
+    let Some(open) = text.find("```") else {
+        return text;
+    };
+    // Skip the info string, e.g. ```json
+    let after = &text[open + 3..];
+    let body = after
+        .find('\n')
+        .map_or(after, |newline| &after[newline + 1..]);
+    body.find("```").map_or(body, |close| &body[..close])
+}
+
+/// The first balanced `{...}` block, ignoring braces inside strings.
+fn first_json_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (offset, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..=start + offset]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// A confidence as a 0-1 fraction, read from a number or a numeric string;
+/// values above 1 (up to 100) are taken as percentages.
+fn normalize_confidence(value: &serde_json::Value) -> Option<f64> {
+    let raw = match value {
+        serde_json::Value::Number(n) => n.as_f64()?,
+        serde_json::Value::String(s) => s.trim().trim_end_matches('%').trim().parse().ok()?,
+        _ => return None,
+    };
+    let is_percent = matches!(value, serde_json::Value::String(s) if s.trim().ends_with('%'));
+    if is_percent || (raw > 1.0 && raw <= 100.0) {
+        Some(raw / 100.0)
+    } else {
+        Some(raw)
+    }
+}
+
+/// The circuit breaker shared by every `provider` client.
+pub fn circuit_breaker(provider: &AiProvider) -> &'static CircuitBreaker {
+    match provider {
+        AiProvider::Grok => grok::circuit_breaker(),
+        AiProvider::OpenAi => openai::circuit_breaker(),
+        AiProvider::Claude => claude::circuit_breaker(),
+    }
 }
 
-pub fn create_ai_client(provider: AiProvider) -> Result<Box<dyn AiClient>> {
+/// A client for `provider` with the key and default model from `config`.
+/// Fails when the provider's key isn't configured.
+pub fn create_ai_client(
+    config: &Config,
+    provider: AiProvider,
+    options: &AiRequestOptions,
+) -> Result<Box<dyn AiClient>> {
+    let retry = config.ai_retry_policy(&provider);
     match provider {
-        AiProvider::Grok => Ok(Box::new(GrokClient::new()?)),
-        AiProvider::OpenAi => Ok(Box::new(OpenAiClient::new()?)),
+        AiProvider::Grok => Ok(Box::new(GrokClient::new(
+            config.grok_api_key.clone(),
+            config.grok_model.clone(),
+            options,
+            retry,
+            config.http.clone(),
+            config.grok_base_url.clone(),
+        )?)),
+        AiProvider::OpenAi => Ok(Box::new(OpenAiClient::new(
+            config.openai_api_key.clone(),
+            config.openai_model.clone(),
+            options,
+            retry,
+            config.http.clone(),
+            config.openai_base_url.clone(),
+        )?)),
+        AiProvider::Claude => Ok(Box::new(ClaudeClient::new(
+            config.anthropic_api_key.clone(),
+            config.anthropic_model.clone(),
+            options,
+            retry,
+            config.http.clone(),
+            config.anthropic_base_url.clone(),
+        )?)),
     }
 }
 
@@ -1,21 +1,45 @@
-use crate::clients::ai::AiClient;
+use crate::clients::ai::prompts::PromptMessage;
+use crate::clients::ai::{
+    parse_ai_analysis, record_attempt, record_retry_delay, record_timeout, record_usage, AiAttempt,
+    AiClient, AiProvider, AiRequestOptions, AiResult, TokenUsage, DEFAULT_TEMPERATURE,
+};
+use crate::clients::{handle_upstream_response, transport_error, TimedSend, UpstreamSettings};
+use crate::clients::circuit_breaker::CircuitBreaker;
+use crate::clients::rate_limit::RateLimiter;
+use crate::clients::recorder::{ai_parse_failure, parse_json};
+use crate::clients::retry::{retry_observed, RetryPolicy};
+use crate::config::Config;
+use crate::metrics::{Metrics, UpstreamApi};
 use crate::types::AiAnalysis;
 use crate::{AppError, Result};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
-use tracing::warn;
 
-const OPENAI_API_URL: &str = "https://api.openai.com/v1/chat/completions";
-const MAX_RETRIES: u32 = 3;
-const TIMEOUT_SECS: u64 = 120;
+const OPENAI_API_BASE: &str = "https://api.openai.com/v1";
+const DEFAULT_MODEL: &str = "gpt-4";
+pub const DEFAULT_RPM: f64 = 60.0;
+
+/// Clients are built per request, so they share one process-wide limiter.
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+static BREAKER: OnceLock<CircuitBreaker> = OnceLock::new();
+
+/// The circuit breaker every OpenAI client shares.
+pub fn circuit_breaker() -> &'static CircuitBreaker {
+    BREAKER.get_or_init(|| CircuitBreaker::configured(UpstreamApi::OpenAi))
+}
 
 #[derive(Debug, Serialize)]
 struct OpenAiRequest {
     model: String,
     messages: Vec<Message>,
-    response_format: ResponseFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
     temperature: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
 }
 
 #[derive(Debug, Serialize)]
@@ -33,6 +57,16 @@ struct ResponseFormat {
 #[derive(Debug, Deserialize)]
 struct OpenAiResponse {
     choices: Vec<Choice>,
+    #[serde(default)]
+    usage: Option<ResponseUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseUsage {
+    #[serde(default)]
+    prompt_tokens: u64,
+    #[serde(default)]
+    completion_tokens: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -47,111 +81,223 @@ struct ChoiceMessage {
 
 pub struct OpenAiClient {
     client: Client,
+    base_url: String,
+    /// Paces calls (`OPENAI_RPM`)
+    limiter: &'static RateLimiter,
     api_key: String,
+    model: String,
+    temperature: f64,
+    max_tokens: Option<u32>,
+    /// Per-request limit (`timeout_secs`)
+    timeout: Duration,
+    /// Tokens billed across this client's calls
+    billed: Mutex<TokenUsage>,
+    /// Set once a call runs out of `timeout`
+    timed_out: AtomicBool,
+    retry: RetryPolicy,
+    /// Every call's status and retry delay, oldest first
+    attempts: Mutex<Vec<AiAttempt>>,
 }
 
 impl OpenAiClient {
-    pub fn new() -> Result<Self> {
-        let api_key = std::env::var("OPENAI_API_KEY")
-            .map_err(|_| AppError::Validation("OPENAI_API_KEY not set".to_string()))?;
+    /// A client for `api_key`, failing when it isn't set. `model` is used
+    /// when the request doesn't name one; `base_url` defaults to the
+    /// production API.
+    pub fn new(
+        api_key: Option<String>,
+        model: Option<String>,
+        options: &AiRequestOptions,
+        retry: RetryPolicy,
+        client: Client,
+        base_url: Option<String>,
+    ) -> Result<Self> {
+        let api_key = api_key.ok_or_else(|| AppError::missing_api_key("OPENAI_API_KEY"))?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(TIMEOUT_SECS))
-            .build()
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            base_url: base_url.unwrap_or_else(|| OPENAI_API_BASE.to_string()),
+            limiter: LIMITER.get_or_init(|| {
+                let settings = UpstreamSettings::current();
+                RateLimiter::new("OpenAI API", settings.openai_rpm / 60.0, settings.rate_limit_max_wait)
+            }),
+            api_key,
+            model: options.resolve_model(model, DEFAULT_MODEL),
+            temperature: options.temperature.unwrap_or(DEFAULT_TEMPERATURE),
+            max_tokens: options.max_tokens,
+            timeout: options.timeout(),
+            billed: Mutex::default(),
+            timed_out: AtomicBool::new(false),
+            retry,
+            attempts: Mutex::default(),
+        })
+    }
+
+    /// Key and default model from the environment, as read by [`Config`].
+    pub fn from_env(options: &AiRequestOptions) -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        let retry = config.ai_retry_policy(&AiProvider::OpenAi);
+        Self::new(
+            config.openai_api_key,
+            config.openai_model,
+            options,
+            retry,
+            config.http.clone(),
+            None,
+        )
+    }
 
-        Ok(Self { client, api_key })
+    fn completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url)
     }
 
-    async fn call_with_retry(&self, prompt: String) -> Result<AiAnalysis> {
-        let mut last_error = None;
-
-        for attempt in 0..MAX_RETRIES {
-            match self.call_api(&prompt).await {
-                Ok(analysis) => {
-                    if attempt > 0 {
-                        tracing::info!("OpenAI API call succeeded on attempt {}", attempt + 1);
-                    }
-                    return Ok(analysis);
-                }
-                Err(e) => {
-                    last_error = Some(e);
-                    if attempt < MAX_RETRIES - 1 {
-                        let delay = Duration::from_millis(2_u64.pow(attempt) * 100);
-                        warn!("OpenAI API call failed, retrying in {:?}...", delay);
-                        tokio::time::sleep(delay).await;
-                    }
-                }
+    async fn call_with_retry(&self, messages: Vec<PromptMessage>) -> Result<AiAnalysis> {
+        let mut retries: u32 = 0;
+        let retried = retry_observed(
+            || self.call_api(&messages),
+            self.retry,
+            |_, delay| {
+                retries += 1;
+                record_retry_delay(&self.attempts, delay);
+            },
+        )
+        .await;
+        Metrics::global().ai_retried(self.provider_name(), retries);
+        Ok(retried?.value)
+    }
+
+    async fn call_api(&self, messages: &[PromptMessage]) -> Result<AiAnalysis> {
+        let content = self.fetch_content(messages, true).await?;
+
+        // Parse JSON from content
+        match parse_ai_analysis(&content) {
+            Ok(analysis) => Ok(analysis),
+            Err(e) => {
+                tracing::debug!(
+                    provider = self.provider_name(),
+                    raw_content = %content,
+                    "Unparseable AI analysis: {}",
+                    e
+                );
+                Err(ai_parse_failure(e, &self.completions_url(), &content).await)
             }
         }
-
-        Err(last_error.unwrap_or_else(|| {
-            AppError::ExternalApi("OpenAI API call failed after retries".to_string())
-        }))
     }
 
-    async fn call_api(&self, prompt: &str) -> Result<AiAnalysis> {
+    async fn fetch_content(
+        &self,
+        messages: &[PromptMessage],
+        json_response: bool,
+    ) -> Result<String> {
         let request = OpenAiRequest {
-            model: "gpt-4".to_string(),
-            messages: vec![Message {
-                role: "user".to_string(),
-                content: prompt.to_string(),
-            }],
-            response_format: ResponseFormat {
+            model: self.model.clone(),
+            messages: messages
+                .iter()
+                .map(|m| Message {
+                    role: m.role.as_str().to_string(),
+                    content: m.content.clone(),
+                })
+                .collect(),
+            response_format: json_response.then(|| ResponseFormat {
                 type_: "json_object".to_string(),
-            },
-            temperature: 0.7,
+            }),
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
         };
 
         let response = self
             .client
-            .post(OPENAI_API_URL)
+            .post(self.completions_url())
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
             .json(&request)
-            .send()
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::OpenAi)
             .await
-            .map_err(|e| AppError::ExternalApi(format!("OpenAI API request failed: {}", e)))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::ExternalApi(format!(
-                "OpenAI API returned {}: {}",
-                status, error_text
-            )));
-        }
+            .map_err(|e| {
+                record_timeout(&self.timed_out, &e);
+                record_attempt(&self.attempts, None);
+                transport_error("OpenAI API", e, self.timeout)
+            })?;
+        record_attempt(&self.attempts, Some(response.status().as_u16()));
 
-        let openai_response: OpenAiResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse OpenAI response: {}", e)))?;
+        let response = handle_upstream_response(response, "OpenAI API").await?;
+
+        let openai_response: OpenAiResponse = parse_json(response, "OpenAI response").await?;
+        if let Some(usage) = &openai_response.usage {
+            record_usage(
+                &self.billed,
+                TokenUsage {
+                    prompt_tokens: usage.prompt_tokens,
+                    completion_tokens: usage.completion_tokens,
+                },
+            );
+        }
 
         let content = openai_response
             .choices
             .first()
-            .and_then(|c| Some(c.message.content.clone()))
+            .map(|c| c.message.content.clone())
             .ok_or_else(|| AppError::ExternalApi("No content in OpenAI response".to_string()))?;
 
-        // Parse JSON from content
-        let analysis: AiAnalysis = serde_json::from_str(&content)
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse AI analysis JSON: {}", e)))?;
-
-        Ok(analysis)
+        Ok(content)
     }
 }
 
 #[async_trait::async_trait]
 impl AiClient for OpenAiClient {
-    async fn analyze_markets(&self, prompt: String) -> Result<AiAnalysis> {
-        self.call_with_retry(prompt).await
+    async fn analyze_markets(&self, messages: Vec<PromptMessage>) -> Result<AiResult> {
+        self.limiter.acquire().await?;
+        let analysis = circuit_breaker()
+            .call(self.call_with_retry(messages))
+            .await?;
+        Ok(AiResult {
+            analysis,
+            usage: self.usage(),
+            model: self.model.clone(),
+        })
+    }
+
+    async fn complete(&self, prompt: String) -> Result<String> {
+        self.limiter.acquire().await?;
+        circuit_breaker()
+            .call(self.fetch_content(&[PromptMessage::user(prompt)], false))
+            .await
     }
 
     fn provider_name(&self) -> &'static str {
         "openai"
     }
+
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn usage(&self) -> TokenUsage {
+        *self.billed.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn timed_out(&self) -> bool {
+        self.timed_out.load(Ordering::Relaxed)
+    }
+
+    fn attempts(&self) -> Vec<AiAttempt> {
+        self.attempts
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    async fn ping(&self) -> Result<()> {
+        let response = self
+            .client
+            .get(format!("{}/models", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::OpenAi)
+            .await
+            .map_err(|e| transport_error("OpenAI API", e, self.timeout))?;
+        handle_upstream_response(response, "OpenAI API").await?;
+        Ok(())
+    }
 }
 
@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+use crate::clients::ai::TokenUsage;
+
+/// List prices in USD per million prompt and completion tokens.
+const LIST_PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-5", 1.25, 10.0),
+    ("gpt-5-mini", 0.25, 2.0),
+    ("gpt-4.1", 2.0, 8.0),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4-turbo", 10.0, 30.0),
+    ("gpt-4", 30.0, 60.0),
+    ("gpt-3.5-turbo", 0.5, 1.5),
+    ("o1", 15.0, 60.0),
+    ("o1-mini", 1.1, 4.4),
+    ("o3-mini", 1.1, 4.4),
+    ("grok-beta", 5.0, 15.0),
+    ("grok-2", 2.0, 10.0),
+    ("grok-3", 3.0, 15.0),
+    ("grok-3-mini", 0.3, 0.5),
+    ("grok-4", 3.0, 15.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-haiku-4", 1.0, 5.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-sonnet-4", 3.0, 15.0),
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-opus-4", 15.0, 75.0),
+];
+
+/// A model's price in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub prompt_per_mtok: f64,
+    pub completion_per_mtok: f64,
+}
+
+/// Per-model prices for estimating what AI calls cost: the list prices,
+/// with `AI_MODEL_PRICES` overriding or adding models.
+///
+/// A model is priced by the longest entry that prefixes its name, so dated
+/// snapshots such as `gpt-4o-2024-08-06` price like `gpt-4o`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ModelPrices {
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl Default for ModelPrices {
+    fn default() -> Self {
+        Self {
+            prices: LIST_PRICES
+                .iter()
+                .map(|&(model, prompt, completion)| {
+                    (
+                        model.to_string(),
+                        ModelPrice {
+                            prompt_per_mtok: prompt,
+                            completion_per_mtok: completion,
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ModelPrices {
+    pub fn price(&self, model: &str) -> Option<ModelPrice> {
+        let model = model.trim().to_lowercase();
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    /// Estimated USD for `usage` on `model`; `None` for a model with no price.
+    pub fn cost(&self, model: &str, usage: TokenUsage) -> Option<f64> {
+        let price = self.price(model)?;
+        Some(
+            (usage.prompt_tokens as f64 * price.prompt_per_mtok
+                + usage.completion_tokens as f64 * price.completion_per_mtok)
+                / 1_000_000.0,
+        )
+    }
+
+    fn set(&mut self, model: String, price: ModelPrice) {
+        match self.prices.iter_mut().find(|(m, _)| *m == model) {
+            Some((_, existing)) => *existing = price,
+            None => self.prices.push((model, price)),
+        }
+    }
+}
+
+/// Comma-separated `model=prompt/completion` entries in USD per million
+/// tokens, applied over the list prices, e.g. `gpt-4o=2.5/10,my-model=1/2`.
+impl FromStr for ModelPrices {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut prices = ModelPrices::default();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let invalid = || format!("expected model=prompt/completion, got '{}'", entry);
+            let (model, rates) = entry.split_once('=').ok_or_else(invalid)?;
+            let (prompt, completion) = rates.split_once('/').ok_or_else(invalid)?;
+            let rate = |rate: &str| {
+                rate.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|r| r.is_finite() && *r >= 0.0)
+                    .ok_or_else(invalid)
+            };
+            let model = model.trim().to_lowercase();
+            if model.is_empty() {
+                return Err(invalid());
+            }
+            prices.set(
+                model,
+                ModelPrice {
+                    prompt_per_mtok: rate(prompt)?,
+                    completion_per_mtok: rate(completion)?,
+                },
+            );
+        }
+        Ok(prices)
+    }
+}
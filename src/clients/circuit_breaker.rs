@@ -0,0 +1,220 @@
+use serde::Serialize;
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::clients::UpstreamSettings;
+use crate::metrics::{Metrics, UpstreamApi};
+use crate::{AppError, Result};
+
+const DEFAULT_THRESHOLD: u32 = 5;
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// When a breaker opens and how long it stays open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// Consecutive failures that open the breaker; 0 never opens it
+    pub threshold: u32,
+    /// The failures must all fall within this span of the first one
+    pub window: Duration,
+    /// How long calls fail fast before a probe is let through
+    pub cooldown: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            threshold: DEFAULT_THRESHOLD,
+            window: DEFAULT_WINDOW,
+            cooldown: DEFAULT_COOLDOWN,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    /// Calls go through
+    Closed,
+    /// Calls fail fast until the cooldown is over
+    Open,
+    /// One probe call is in flight; its outcome closes or reopens the
+    /// breaker
+    HalfOpen,
+}
+
+impl CircuitState {
+    /// The value of the `upstream_circuit_state` gauge.
+    pub fn gauge_value(&self) -> i64 {
+        match self {
+            CircuitState::Closed => 0,
+            CircuitState::HalfOpen => 1,
+            CircuitState::Open => 2,
+        }
+    }
+}
+
+/// A breaker's state as reported by `GET /health/deep`.
+#[derive(Debug, Clone, Serialize)]
+pub struct CircuitSnapshot {
+    pub name: &'static str,
+    pub state: CircuitState,
+    /// Consecutive failures counted towards opening
+    pub failures: u32,
+    /// While open, seconds until a probe is let through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retry_after_secs: Option<u64>,
+}
+
+/// Stops calling an upstream that keeps failing. After `threshold`
+/// consecutive outages (`ExternalApi` or `Timeout` errors) within `window`
+/// the breaker opens and calls fail immediately with
+/// [`AppError::ExternalApi`]. Once `cooldown` has passed one probe call is
+/// let through: success closes the breaker, another outage reopens it.
+/// Other errors (404s, rejections, rate limits, malformed payloads) show
+/// the upstream is up, so they count as successes.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    api: UpstreamApi,
+    config: BreakerConfig,
+    state: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    failures: u32,
+    first_failure: Option<Instant>,
+    /// When the breaker last opened, or when the half-open probe started
+    since: Instant,
+}
+
+impl CircuitBreaker {
+    pub fn new(api: UpstreamApi, config: BreakerConfig) -> Self {
+        Metrics::global().circuit_state(api, CircuitState::Closed);
+        Self {
+            api,
+            config,
+            state: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                failures: 0,
+                first_failure: None,
+                since: Instant::now(),
+            }),
+        }
+    }
+
+    /// A breaker with the installed [`UpstreamSettings`].
+    pub fn configured(api: UpstreamApi) -> Self {
+        Self::new(api, UpstreamSettings::current().circuit_breaker)
+    }
+
+    /// Runs `call` unless the breaker is open, and counts its outcome.
+    /// Wrap the whole retried call, so one failing request counts once and
+    /// a fast failure isn't retried.
+    pub async fn call<T>(&self, call: impl Future<Output = Result<T>>) -> Result<T> {
+        if self.config.threshold == 0 {
+            return call.await;
+        }
+        self.check()?;
+        let result = call.await;
+        self.record(&result);
+        result
+    }
+
+    /// Fails while the breaker is open, or half-open with a probe still in
+    /// flight. Lets the first call after the cooldown through as the probe.
+    fn check(&self) -> Result<()> {
+        let mut inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if inner.state == CircuitState::Closed {
+            return Ok(());
+        }
+
+        // A probe that never reported back (its caller gave up) doesn't
+        // hold the breaker half-open for longer than a cooldown
+        let elapsed = inner.since.elapsed();
+        if elapsed < self.config.cooldown {
+            let retry_after = (self.config.cooldown - elapsed).as_secs_f64().ceil() as u64;
+            return Err(AppError::ExternalApi(format!(
+                "{} circuit open, retry after {}s",
+                self.api.name(),
+                retry_after.max(1)
+            )));
+        }
+
+        tracing::info!("{} circuit half-open, sending a probe", self.api.name());
+        inner.since = Instant::now();
+        self.transition(&mut inner, CircuitState::HalfOpen);
+        Ok(())
+    }
+
+    fn record<T>(&self, result: &Result<T>) {
+        let mut inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let outage = matches!(
+            result,
+            Err(AppError::ExternalApi(_)) | Err(AppError::Timeout(_))
+        );
+        if !outage {
+            inner.failures = 0;
+            inner.first_failure = None;
+            if inner.state != CircuitState::Closed {
+                tracing::info!("{} circuit closed", self.api.name());
+                self.transition(&mut inner, CircuitState::Closed);
+            }
+            return;
+        }
+
+        let now = Instant::now();
+        match inner.first_failure {
+            Some(first) if now.duration_since(first) <= self.config.window => {
+                inner.failures += 1;
+            }
+            _ => {
+                inner.failures = 1;
+                inner.first_failure = Some(now);
+            }
+        }
+
+        let reopen = inner.state == CircuitState::HalfOpen;
+        if reopen
+            || (inner.state == CircuitState::Closed && inner.failures >= self.config.threshold)
+        {
+            tracing::warn!(
+                "{} circuit open after {} consecutive failures; failing fast for {:?}",
+                self.api.name(),
+                inner.failures,
+                self.config.cooldown
+            );
+            inner.since = now;
+            self.transition(&mut inner, CircuitState::Open);
+        }
+    }
+
+    fn transition(&self, inner: &mut Inner, state: CircuitState) {
+        inner.state = state;
+        Metrics::global().circuit_state(self.api, state);
+    }
+
+    pub fn state(&self) -> CircuitState {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).state
+    }
+
+    pub fn snapshot(&self) -> CircuitSnapshot {
+        let inner = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let retry_after_secs = (inner.state == CircuitState::Open).then(|| {
+            self.config
+                .cooldown
+                .saturating_sub(inner.since.elapsed())
+                .as_secs_f64()
+                .ceil() as u64
+        });
+        CircuitSnapshot {
+            name: self.api.label(),
+            state: inner.state,
+            failures: inner.failures,
+            retry_after_secs,
+        }
+    }
+}
@@ -0,0 +1,432 @@
+//! Order signing and request authentication for the Polymarket CLOB.
+//!
+//! Orders are EIP-712 signed by the wallet key (L1). Authenticated REST calls
+//! carry HMAC headers made with API credentials (L2), which are either set in
+//! the environment, supplied by the caller or derived from the wallet key.
+
+use alloy_primitives::{address, Address, U256};
+use alloy_signer::SignerSync;
+use alloy_signer_local::PrivateKeySigner;
+use alloy_sol_types::{eip712_domain, sol, Eip712Domain, SolStruct};
+use base64::{engine::general_purpose::URL_SAFE, Engine};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use crate::clients::salt::signer_fingerprint;
+use crate::types::Price;
+use crate::{AppError, Result};
+
+pub const POLYGON_CHAIN_ID: u64 = 137;
+pub const CTF_EXCHANGE: Address = address!("4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E");
+pub const NEG_RISK_CTF_EXCHANGE: Address = address!("C5d563A36AE78145C45a50134d48A1215220f80a");
+
+const CLOB_AUTH_MESSAGE: &str = "This message attests that I control the given wallet";
+/// USDC and conditional tokens both use 6 decimals on-chain.
+const TOKEN_DECIMALS: f64 = 1_000_000.0;
+/// Smallest order notional, in USD, the exchange accepts.
+pub const MIN_ORDER_NOTIONAL_USD: f64 = 1.0;
+
+sol! {
+    struct Order {
+        uint256 salt;
+        address maker;
+        address signer;
+        address taker;
+        uint256 tokenId;
+        uint256 makerAmount;
+        uint256 takerAmount;
+        uint256 expiration;
+        uint256 nonce;
+        uint256 feeRateBps;
+        uint8 side;
+        uint8 signatureType;
+    }
+
+    struct ClobAuth {
+        address address;
+        string timestamp;
+        uint256 nonce;
+        string message;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+impl OrderSide {
+    pub fn parse(side: &str) -> Result<Self> {
+        match side.to_ascii_lowercase().as_str() {
+            "buy" => Ok(OrderSide::Buy),
+            "sell" => Ok(OrderSide::Sell),
+            other => Err(AppError::Validation(format!(
+                "Invalid order side '{}': expected buy or sell",
+                other
+            ))),
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            OrderSide::Buy => 0,
+            OrderSide::Sell => 1,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            OrderSide::Buy => "BUY",
+            OrderSide::Sell => "SELL",
+        }
+    }
+}
+
+/// Per-token settings the exchange validates orders against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketParams {
+    pub tick_size: f64,
+    pub neg_risk: bool,
+    pub fee_rate_bps: u32,
+    /// Smallest order size in shares
+    pub min_order_size: f64,
+}
+
+impl Default for MarketParams {
+    fn default() -> Self {
+        Self {
+            tick_size: 0.01,
+            neg_risk: false,
+            fee_rate_bps: 0,
+            min_order_size: 5.0,
+        }
+    }
+}
+
+/// Wallet key used to sign orders and L1 auth messages.
+#[derive(Clone)]
+pub struct ClobSigner {
+    signer: PrivateKeySigner,
+}
+
+impl ClobSigner {
+    pub fn from_private_key(private_key: &str) -> Result<Self> {
+        let key = private_key.trim();
+        let key = key.strip_prefix("0x").unwrap_or(key);
+        let signer = key
+            .parse::<PrivateKeySigner>()
+            .map_err(|_| AppError::Validation("Invalid wallet private key".to_string()))?;
+        Ok(Self { signer })
+    }
+
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// EIP-712 signature over a `ClobAuth` message, used to create or derive
+    /// API credentials.
+    pub fn sign_clob_auth(&self, timestamp: u64, nonce: u64) -> Result<String> {
+        let message = ClobAuth {
+            address: self.address(),
+            timestamp: timestamp.to_string(),
+            nonce: U256::from(nonce),
+            message: CLOB_AUTH_MESSAGE.to_string(),
+        };
+        let domain = eip712_domain! {
+            name: "ClobAuthDomain",
+            version: "1",
+            chain_id: POLYGON_CHAIN_ID,
+        };
+        self.sign_struct(&message, &domain)
+    }
+
+    fn sign_struct<T: SolStruct>(&self, value: &T, domain: &Eip712Domain) -> Result<String> {
+        let hash = value.eip712_signing_hash(domain);
+        let signature = self
+            .signer
+            .sign_hash_sync(&hash)
+            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to sign: {}", e)))?;
+        Ok(format!("0x{}", hex_encode(&signature.as_bytes())))
+    }
+}
+
+/// Order in the shape `POST /order` expects.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SignedOrder {
+    /// At most [`MAX_SALT`](crate::clients::salt::MAX_SALT) so it survives
+    /// JSON parsers that read numbers as doubles
+    pub salt: u64,
+    pub maker: String,
+    pub signer: String,
+    pub taker: String,
+    pub token_id: String,
+    pub maker_amount: String,
+    pub taker_amount: String,
+    pub expiration: String,
+    pub nonce: String,
+    pub fee_rate_bps: String,
+    pub side: &'static str,
+    pub signature_type: u8,
+    pub signature: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PostOrderRequest<'a> {
+    pub order: &'a SignedOrder,
+    pub owner: &'a str,
+    pub order_type: &'static str,
+}
+
+/// On-chain amounts (6 decimals) for an order of `size` shares at `price`.
+///
+/// Size is rounded down to 0.01 shares and price to the market tick. A buy
+/// gives USDC (maker) for shares (taker); a sell is the reverse.
+pub fn order_amounts(
+    side: OrderSide,
+    price: Price,
+    size: f64,
+    tick_size: f64,
+) -> Result<(u64, u64)> {
+    let ticks = (price.value() / tick_size).round();
+    let price = ticks * tick_size;
+    if price <= 0.0 || price >= 1.0 {
+        return Err(AppError::Validation(format!(
+            "Order price {} must be strictly between 0 and 1 at tick size {}",
+            price, tick_size
+        )));
+    }
+
+    let size_cents = (size * 100.0 + 1e-9).floor();
+    if size_cents <= 0.0 {
+        return Err(AppError::Validation(format!(
+            "Order size {} rounds to zero",
+            size
+        )));
+    }
+
+    let shares = (size_cents * 10_000.0) as u64;
+    let notional = (size_cents / 100.0 * price * TOKEN_DECIMALS).round() as u64;
+
+    Ok(match side {
+        OrderSide::Buy => (notional, shares),
+        OrderSide::Sell => (shares, notional),
+    })
+}
+
+/// `price` rounded to the nearest multiple of `tick`, kept at least a tick
+/// away from 0 and 1.
+pub fn snap_to_tick(price: f64, tick: f64) -> f64 {
+    let max_ticks = (1.0 / tick).round() - 1.0;
+    let ticks = (price / tick).round().clamp(1.0, max_ticks.max(1.0));
+    // Snapped to the tick's decimals so the float prints as the tick multiple
+    let scale = 10_f64.powi((-tick.log10()).ceil().max(0.0) as i32);
+    (ticks * tick * scale).round() / scale
+}
+
+/// `price` snapped to the tick (see [`snap_to_tick`]) and `size` floored to
+/// 0.01 shares, ready to sign.
+///
+/// Errs with the reason when the order can't be placed at all: its size
+/// rounds to zero or its rounded notional is under `min_notional`.
+pub fn round_order(
+    price: f64,
+    size: f64,
+    tick: f64,
+    min_notional: f64,
+) -> std::result::Result<(Price, f64), String> {
+    let price = Price::from_decimal(snap_to_tick(price, tick)).map_err(|e| e.to_string())?;
+
+    let size = (size * 100.0 + 1e-9).floor() / 100.0;
+    if size <= 0.0 {
+        return Err("size rounds to zero".to_string());
+    }
+    let notional = price.value() * size;
+    if notional < min_notional {
+        return Err(format!(
+            "notional ${:.2} is under the ${:.2} minimum",
+            notional, min_notional
+        ));
+    }
+    Ok((price, size))
+}
+
+/// Builds and signs an order from an EOA wallet (maker = signer) that
+/// expires at `expiration` (unix seconds), or never when it is 0.
+#[allow(clippy::too_many_arguments)]
+pub fn build_signed_order(
+    signer: &ClobSigner,
+    token_id: &str,
+    side: OrderSide,
+    price: Price,
+    size: f64,
+    salt: u64,
+    expiration: u64,
+    params: &MarketParams,
+) -> Result<SignedOrder> {
+    let token = token_id
+        .parse::<U256>()
+        .map_err(|_| AppError::Validation(format!("Invalid token id: {}", token_id)))?;
+    let (maker_amount, taker_amount) = order_amounts(side, price, size, params.tick_size)?;
+
+    let order = Order {
+        salt: U256::from(salt),
+        maker: signer.address(),
+        signer: signer.address(),
+        taker: Address::ZERO,
+        tokenId: token,
+        makerAmount: U256::from(maker_amount),
+        takerAmount: U256::from(taker_amount),
+        expiration: U256::from(expiration),
+        nonce: U256::ZERO,
+        feeRateBps: U256::from(params.fee_rate_bps),
+        side: side.as_u8(),
+        signatureType: 0,
+    };
+
+    let domain = eip712_domain! {
+        name: "Polymarket CTF Exchange",
+        version: "1",
+        chain_id: POLYGON_CHAIN_ID,
+        verifying_contract: if params.neg_risk { NEG_RISK_CTF_EXCHANGE } else { CTF_EXCHANGE },
+    };
+    let signature = signer.sign_struct(&order, &domain)?;
+
+    Ok(SignedOrder {
+        salt,
+        maker: signer.address().to_checksum(None),
+        signer: signer.address().to_checksum(None),
+        taker: Address::ZERO.to_checksum(None),
+        token_id: token_id.to_string(),
+        maker_amount: maker_amount.to_string(),
+        taker_amount: taker_amount.to_string(),
+        expiration: expiration.to_string(),
+        nonce: "0".to_string(),
+        fee_rate_bps: params.fee_rate_bps.to_string(),
+        side: side.as_str(),
+        signature_type: 0,
+        signature,
+    })
+}
+
+/// L2 API credentials. `Debug` leaves out the secret and passphrase.
+#[derive(Clone, Deserialize)]
+pub struct ApiCredentials {
+    #[serde(rename = "apiKey")]
+    pub api_key: String,
+    pub secret: String,
+    pub passphrase: String,
+}
+
+impl std::fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ApiCredentials")
+            .field("api_key", &self.api_key)
+            .field("secret", &"***")
+            .field("passphrase", &"***")
+            .finish()
+    }
+}
+
+impl ApiCredentials {
+    /// Headers for an authenticated CLOB request. `path` excludes the query
+    /// string, which the exchange does not sign.
+    pub fn l2_headers(
+        &self,
+        address: Address,
+        timestamp: u64,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<Vec<(&'static str, String)>> {
+        Ok(vec![
+            ("POLY_ADDRESS", address.to_checksum(None)),
+            (
+                "POLY_SIGNATURE",
+                l2_signature(&self.secret, timestamp, method, path, body)?,
+            ),
+            ("POLY_TIMESTAMP", timestamp.to_string()),
+            ("POLY_API_KEY", self.api_key.clone()),
+            ("POLY_PASSPHRASE", self.passphrase.clone()),
+        ])
+    }
+}
+
+/// How a caller authenticates to the CLOB for one request.
+#[derive(Clone)]
+pub enum WalletAuth {
+    /// The wallet key. It signs orders, and its L2 credentials are configured
+    /// or derived.
+    Signer(ClobSigner),
+    /// Pre-derived L2 credentials for `address`. They can read and cancel the
+    /// wallet's orders but not sign new ones.
+    Credentials {
+        address: Address,
+        credentials: ApiCredentials,
+    },
+}
+
+impl WalletAuth {
+    pub fn address(&self) -> Address {
+        match self {
+            WalletAuth::Signer(signer) => signer.address(),
+            WalletAuth::Credentials { address, .. } => *address,
+        }
+    }
+
+    /// The key that signs new orders.
+    pub fn signer(&self) -> Result<&ClobSigner> {
+        match self {
+            WalletAuth::Signer(signer) => Ok(signer),
+            WalletAuth::Credentials { .. } => Err(AppError::Validation(
+                "Placing orders needs a signing key; CLOB API credentials can only read and \
+                 cancel orders"
+                    .to_string(),
+            )),
+        }
+    }
+
+    pub fn can_sign(&self) -> bool {
+        matches!(self, WalletAuth::Signer(_))
+    }
+
+    /// Stable identifier for the wallet, e.g. to key per-signer salts.
+    pub fn fingerprint(&self) -> String {
+        signer_fingerprint(&self.address().to_checksum(None))
+    }
+}
+
+impl std::fmt::Debug for WalletAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self {
+            WalletAuth::Signer(_) => "Signer",
+            WalletAuth::Credentials { .. } => "Credentials",
+        };
+        f.debug_tuple(kind).field(&self.address()).finish()
+    }
+}
+
+/// URL-safe base64 HMAC-SHA256 of `timestamp + method + path + body`, keyed
+/// with the base64-decoded API secret.
+pub fn l2_signature(
+    secret: &str,
+    timestamp: u64,
+    method: &str,
+    path: &str,
+    body: &str,
+) -> Result<String> {
+    let key = URL_SAFE.decode(secret.trim()).map_err(|_| {
+        AppError::Validation("POLYMARKET_API_SECRET is not valid base64".to_string())
+    })?;
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Invalid HMAC key: {}", e)))?;
+    mac.update(format!("{}{}{}{}", timestamp, method, path, body).as_bytes());
+    Ok(URL_SAFE.encode(mac.finalize().into_bytes()))
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -1,11 +1,24 @@
-use crate::types::{MarketData, Outcome, Platform};
+use crate::clients::{handle_upstream_response, transport_error, TimedSend, UpstreamSettings};
+use crate::clients::circuit_breaker::{BreakerConfig, CircuitBreaker, CircuitSnapshot};
+use crate::clients::rate_limit::RateLimiter;
+use crate::clients::recorder::parse_json;
+use crate::config::Config;
+use crate::metrics::UpstreamApi;
+use crate::types::{
+    canonicalize_outcomes, Candle, CandleInterval, MarketData, MarketRef, Outcome, Platform, Price,
+};
 use crate::{AppError, Result};
+use chrono::{DateTime, Utc};
 use reqwest::Client;
 use serde::Deserialize;
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
 use url::Url;
 
 const DOME_API_BASE: &str = "https://api.domeapi.io/v1";
+pub const DEFAULT_DOME_RPS: f64 = 5.0;
 
 #[derive(Debug, Deserialize)]
 struct DomeMarketsResponse {
@@ -37,6 +50,14 @@ struct DomeMarket {
     image: Option<String>,
     #[allow(dead_code)]
     tags: Option<Vec<String>>,
+    /// Unix seconds
+    #[serde(default)]
+    end_time: Option<i64>,
+    /// "open" or "closed"
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    winning_side: Option<DomeWinner>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,81 +66,243 @@ struct DomeSide {
     label: String,
 }
 
+/// The winning side of a resolved market, given either as a side object or
+/// just its label.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum DomeWinner {
+    Side(DomeSide),
+    Label(String),
+}
+
+impl DomeWinner {
+    fn label(&self) -> &str {
+        match self {
+            DomeWinner::Side(side) => &side.label,
+            DomeWinner::Label(label) => label,
+        }
+    }
+}
+
+/// `candlesticks` holds one `[candles, token]` pair per outcome token.
+#[derive(Debug, Deserialize)]
+struct DomeCandlesticksResponse {
+    candlesticks: Vec<(Vec<DomeCandle>, DomeCandleToken)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DomeCandle {
+    /// Unix seconds
+    end_period_ts: i64,
+    price: DomeCandlePrice,
+    #[serde(default)]
+    volume: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct DomeCandlePrice {
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+struct DomeCandleToken {
+    token_id: String,
+}
+
+#[derive(Clone)]
 pub struct DomeClient {
     client: Client,
+    base_url: String,
     api_key: String,
+    /// Limit set on each request, quoted in timeout errors
+    timeout: Duration,
+    /// Concurrent lookups in [`DomeClient::get_markets`]
+    batch_concurrency: usize,
+    /// Paces requests (`DOME_RPS`), shared by every clone
+    limiter: Arc<RateLimiter>,
+    /// Fails requests fast while Dome is down, shared by every clone
+    breaker: Arc<CircuitBreaker>,
 }
 
 impl DomeClient {
-    pub fn new() -> Result<Self> {
-        let api_key = std::env::var("DOME_API_KEY")
-            .map_err(|_| AppError::Validation("DOME_API_KEY not set".to_string()))?;
-
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .map_err(|e| {
-                AppError::Internal(anyhow::anyhow!("Failed to create HTTP client: {}", e))
-            })?;
-
-        Ok(Self { client, api_key })
+    /// A client for `api_key`, failing when it isn't set. `base_url`
+    /// defaults to the production API.
+    pub fn new(
+        api_key: Option<String>,
+        batch_concurrency: usize,
+        timeout: Duration,
+        client: Client,
+        base_url: Option<String>,
+    ) -> Result<Self> {
+        let api_key = api_key.ok_or_else(|| AppError::missing_api_key("DOME_API_KEY"))?;
+        let settings = UpstreamSettings::current();
+
+        Ok(Self {
+            client,
+            base_url: base_url.unwrap_or_else(|| DOME_API_BASE.to_string()),
+            api_key,
+            timeout,
+            batch_concurrency: batch_concurrency.max(1),
+            limiter: Arc::new(RateLimiter::new(
+                "Dome API",
+                settings.dome_rps,
+                settings.rate_limit_max_wait,
+            )),
+            breaker: Arc::new(CircuitBreaker::configured(UpstreamApi::Dome)),
+        })
+    }
+
+    /// Replaces the installed circuit breaker settings.
+    pub fn with_circuit_breaker(mut self, config: BreakerConfig) -> Self {
+        self.breaker = Arc::new(CircuitBreaker::new(UpstreamApi::Dome, config));
+        self
+    }
+
+    pub fn circuit(&self) -> CircuitSnapshot {
+        self.breaker.snapshot()
+    }
+
+    /// Settings from the environment, as read by [`Config`].
+    pub fn from_env() -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        Self::new(
+            config.dome_api_key,
+            config.dome_batch_concurrency,
+            config.upstream_timeout,
+            config.http.clone(),
+            None,
+        )
+    }
+
+    /// Looks up several markets at once, at most `DOME_BATCH_CONCURRENCY` at
+    /// a time. Results are in input order and a failed lookup only fails its
+    /// own entry.
+    pub async fn get_markets(&self, markets: &[MarketRef]) -> Vec<Result<MarketData>> {
+        let semaphore = Arc::new(Semaphore::new(self.batch_concurrency));
+        let mut workers = JoinSet::new();
+
+        for (index, market) in markets.iter().cloned().enumerate() {
+            let client = self.clone();
+            let semaphore = semaphore.clone();
+            workers.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let result = client.get_market(market.platform, &market.identifier).await;
+                (index, result)
+            });
+        }
+
+        let mut results: Vec<Option<Result<MarketData>>> = markets.iter().map(|_| None).collect();
+        while let Some(joined) = workers.join_next().await {
+            match joined {
+                Ok((index, result)) => results[index] = Some(result),
+                Err(e) => tracing::error!("Dome lookup task failed: {}", e),
+            }
+        }
+        results
+            .into_iter()
+            .map(|result| {
+                result.unwrap_or_else(|| {
+                    Err(AppError::Internal(anyhow::anyhow!(
+                        "Dome lookup task failed"
+                    )))
+                })
+            })
+            .collect()
     }
 
     pub async fn get_market_by_url(&self, url: &str) -> Result<MarketData> {
-        // Extract identifier from URL
-        let identifier = self.extract_identifier(url)?;
-        let platform = self.detect_platform(url)?;
-        let endpoint = match platform {
-            Platform::Polymarket => format!("{}/polymarket/markets?event_slug={}", DOME_API_BASE, identifier),
-            Platform::Kalshi => format!("{}/markets/kalshi/{}", DOME_API_BASE, identifier),
-        };
-        tracing::info!("endpoint -----------> {:?}", endpoint);
-        let response = self
-            .client
-            .get(&endpoint)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .send()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Dome API request failed: {}", e)))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::ExternalApi(format!(
-                "Dome API returned {}: {}",
-                status, error_text
+        let market = parse_market_url(url).map_err(AppError::Validation)?;
+        self.get_market(market.platform, &market.identifier).await
+    }
+
+    /// Looks up a market by Polymarket slug or Kalshi ticker. A Polymarket
+    /// slug is tried as a market slug, then as an event slug (whose first
+    /// market is used). An identifier Dome has no market for is
+    /// `MarketNotFound`.
+    pub async fn get_market(&self, platform: Platform, identifier: &str) -> Result<MarketData> {
+        let identifier = identifier.trim();
+        if identifier.is_empty()
+            || !identifier
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(AppError::Validation(format!(
+                "Invalid market identifier: {:?}",
+                identifier
             )));
         }
-        let dome_response: DomeMarketsResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse Dome response: {}", e)))?;
+        let unknown = || AppError::MarketNotFound {
+            market: identifier.to_string(),
+            message: format!(
+                "Unknown market identifier: {} on {:?}",
+                identifier, platform
+            ),
+        };
+
+        let mut markets = match platform {
+            Platform::Polymarket => {
+                let by_market = self
+                    .fetch_markets(&format!(
+                        "{}/polymarket/markets?market_slug={}",
+                        self.base_url, identifier
+                    ))
+                    .await?;
+                if by_market.is_empty() {
+                    self.fetch_markets(&format!(
+                        "{}/polymarket/markets?event_slug={}",
+                        self.base_url, identifier
+                    ))
+                    .await?
+                } else {
+                    by_market
+                }
+            }
+            Platform::Kalshi => {
+                self.fetch_markets(&format!("{}/markets/kalshi/{}", self.base_url, identifier))
+                    .await?
+            }
+        };
 
         // Get the first market from the response
-        let market = dome_response
-            .markets
-            .first()
-            .ok_or_else(|| AppError::ExternalApi("No markets found in Dome API response".to_string()))?;
+        if markets.is_empty() {
+            return Err(unknown());
+        }
+        let market = markets.swap_remove(0);
 
         // Convert sides to outcomes
-        // Note: Dome API doesn't provide prices directly, so we set them to 0.0
+        // Note: Dome API doesn't provide prices directly, so we set them to zero
         // You may need to fetch prices from a separate endpoint or calculate them
-        let mut outcomes = Vec::new();
-        outcomes.push(Outcome {
-            id: market.side_a.id.clone(),
-            name: market.side_a.label.clone(),
-            price: 0.0, // Price not available in this response
-            volume: None,
-        });
-        outcomes.push(Outcome {
-            id: market.side_b.id.clone(),
-            name: market.side_b.label.clone(),
-            price: 0.0, // Price not available in this response
-            volume: None,
+        let mut outcomes = vec![
+            Outcome {
+                id: market.side_a.id.clone(),
+                name: market.side_a.label.clone(),
+                price: Price::ZERO, // Price not available in this response
+                volume: None,
+            },
+            Outcome {
+                id: market.side_b.id.clone(),
+                name: market.side_b.label.clone(),
+                price: Price::ZERO, // Price not available in this response
+                volume: None,
+            },
+        ];
+        let outcome_ordering = canonicalize_outcomes(&mut outcomes);
+        // Report the winner under our outcome name, whatever case Dome used
+        let resolved_outcome = market.winning_side.as_ref().and_then(|winner| {
+            outcomes
+                .iter()
+                .find(|o| o.name.eq_ignore_ascii_case(winner.label()))
+                .map(|o| o.name.clone())
         });
+        let closed = resolved_outcome.is_some()
+            || market
+                .status
+                .as_deref()
+                .is_some_and(|status| status.eq_ignore_ascii_case("closed"));
 
         Ok(MarketData {
             id: market.condition_id.clone(),
@@ -127,54 +310,188 @@ impl DomeClient {
             slug: Some(market.market_slug.clone()),
             ticker: None,
             platform,
+            condition_id: (platform == Platform::Polymarket).then(|| market.condition_id.clone()),
             outcomes,
             volume: market.volume_total,
             liquidity: None, // Liquidity not available in this response
+            outcome_ordering,
+            end_date: market
+                .end_time
+                .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+            closed,
+            resolved_outcome,
         })
     }
 
-    fn extract_identifier(&self, url: &str) -> Result<String> {
-        let parsed =
-            Url::parse(url).map_err(|e| AppError::Validation(format!("Invalid URL: {}", e)))?;
-
-        // Extract slug from Polymarket URL: https://polymarket.com/event/...
-        if parsed.host_str().unwrap_or("").contains("polymarket") {
-            let path = parsed.path();
-            if let Some(slug) = path.strip_prefix("/event/") {
-                println!("slug ---------> {:?}", slug.to_string());
-                return Ok(slug.to_string());
-            }
+    /// Price candles for a Polymarket market's first outcome between `start`
+    /// and `end`, oldest first. `identifier` is the market's condition id;
+    /// Dome has no candles for Kalshi markets.
+    pub async fn get_candles(
+        &self,
+        platform: Platform,
+        identifier: &str,
+        interval: CandleInterval,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>> {
+        if platform != Platform::Polymarket {
+            return Err(AppError::Validation(
+                "Price history is only available for Polymarket markets".to_string(),
+            ));
         }
-
-        // Extract ticker from Kalshi URL: https://kalshi.com/trade/...
-        if parsed.host_str().unwrap_or("").contains("kalshi") {
-            let path = parsed.path();
-            if let Some(ticker) = path.strip_prefix("/trade/") {
-                return Ok(ticker.to_string());
-            }
+        let identifier = identifier.trim();
+        if identifier.is_empty() || !identifier.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(AppError::Validation(format!(
+                "Invalid condition id: {:?}",
+                identifier
+            )));
         }
 
-        Err(AppError::Validation(format!(
-            "Could not extract identifier from URL: {}",
-            url
-        )))
+        let endpoint = format!("{}/polymarket/candlesticks/{}", self.base_url, identifier);
+        tracing::debug!("Dome request: {}", endpoint);
+        self.limiter.acquire().await?;
+        let dome_response: DomeCandlesticksResponse = self
+            .breaker
+            .call(async {
+                let response = self
+                    .client
+                    .get(&endpoint)
+                    .query(&[
+                        ("start_time", start.timestamp().to_string()),
+                        ("end_time", end.timestamp().to_string()),
+                        ("interval", interval.minutes().to_string()),
+                    ])
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .timeout(self.timeout)
+                    .send_timed(UpstreamApi::Dome)
+                    .await
+                    .map_err(|e| transport_error("Dome API", e, self.timeout))?;
+                if response.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Err(AppError::NotFound(format!(
+                        "No price history for market {}",
+                        identifier
+                    )));
+                }
+                let response = handle_upstream_response(response, "Dome API").await?;
+                parse_json(response, "Dome candlesticks").await
+            })
+            .await?;
+
+        // The first series is the market's first outcome, as in `get_market`
+        let Some((series, _)) = dome_response.candlesticks.into_iter().next() else {
+            return Ok(Vec::new());
+        };
+        let mut candles: Vec<Candle> = series
+            .into_iter()
+            .filter_map(|candle| {
+                Some(Candle {
+                    timestamp: DateTime::from_timestamp(candle.end_period_ts, 0)?,
+                    open: candle.price.open,
+                    high: candle.price.high,
+                    low: candle.price.low,
+                    close: candle.price.close,
+                    volume: candle.volume.unwrap_or(0.0),
+                })
+            })
+            .collect();
+        candles.sort_by_key(|candle| candle.timestamp);
+        Ok(candles)
     }
 
-    fn detect_platform(&self, url: &str) -> Result<Platform> {
-        let parsed =
-            Url::parse(url).map_err(|e| AppError::Validation(format!("Invalid URL: {}", e)))?;
-
-        let host = parsed.host_str().unwrap_or("").to_lowercase();
-
-        if host.contains("polymarket") {
-            Ok(Platform::Polymarket)
-        } else if host.contains("kalshi") {
-            Ok(Platform::Kalshi)
-        } else {
-            Err(AppError::Validation(format!(
-                "Unsupported platform in URL: {}",
-                url
-            )))
-        }
+    /// Lists a single market, which fails when Dome rejects the key.
+    pub async fn ping(&self) -> Result<()> {
+        self.limiter.acquire().await?;
+        let response = self
+            .client
+            .get(format!("{}/polymarket/markets", self.base_url))
+            .query(&[("limit", "1")])
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Dome)
+            .await
+            .map_err(|e| transport_error("Dome API", e, self.timeout))?;
+        handle_upstream_response(response, "Dome API").await?;
+        Ok(())
+    }
+
+    /// Markets listed at a Dome endpoint; a 404 is an empty list.
+    async fn fetch_markets(&self, endpoint: &str) -> Result<Vec<DomeMarket>> {
+        tracing::debug!("Dome request: {}", endpoint);
+        self.limiter.acquire().await?;
+        self.breaker
+            .call(async {
+                let response = self
+                    .client
+                    .get(endpoint)
+                    .header("Authorization", format!("Bearer {}", self.api_key))
+                    .timeout(self.timeout)
+                    .send_timed(UpstreamApi::Dome)
+                    .await
+                    .map_err(|e| transport_error("Dome API", e, self.timeout))?;
+
+                let status = response.status();
+                if status == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(Vec::new());
+                }
+                let response = handle_upstream_response(response, "Dome API").await?;
+                let dome_response: DomeMarketsResponse =
+                    parse_json(response, "Dome response").await?;
+                Ok(dome_response.markets)
+            })
+            .await
     }
 }
+
+const SUPPORTED_URL_PATTERNS: &str = "https://polymarket.com/event/<event-slug>[/<market-slug>], \
+https://polymarket.com/market/<market-slug>, https://kalshi.com/markets/<series>/.../<ticker> \
+or https://kalshi.com/trade/<ticker>";
+
+/// Extracts the platform and market identifier from a market link as it
+/// is usually shared: with or without a scheme or `www.`, any host case,
+/// trailing slashes, query strings and fragments. With nested paths the
+/// deepest segment is the most specific, so it is the one used; Kalshi
+/// tickers are uppercased.
+pub fn parse_market_url(url: &str) -> std::result::Result<MarketRef, String> {
+    let trimmed = url.trim();
+    let parsed = Url::parse(trimmed)
+        .or_else(|e| {
+            // Links pasted without a scheme, e.g. polymarket.com/event/...
+            if trimmed.contains("://") {
+                Err(e)
+            } else {
+                Url::parse(&format!("https://{}", trimmed))
+            }
+        })
+        .map_err(|e| format!("Invalid URL: {}", e))?;
+
+    let host = parsed.host_str().unwrap_or("").to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    let unsupported = || {
+        format!(
+            "Invalid URL: unsupported market link {}; expected {}",
+            url, SUPPORTED_URL_PATTERNS
+        )
+    };
+    let (platform, sections) = match host {
+        "polymarket.com" => (Platform::Polymarket, ["event", "market"]),
+        "kalshi.com" => (Platform::Kalshi, ["markets", "trade"]),
+        _ => return Err(unsupported()),
+    };
+    let identifier = match segments.as_slice() {
+        [section, .., last] if sections.contains(section) => *last,
+        _ => return Err(unsupported()),
+    };
+
+    Ok(MarketRef {
+        platform,
+        identifier: match platform {
+            Platform::Polymarket => identifier.to_string(),
+            Platform::Kalshi => identifier.to_uppercase(),
+        },
+    })
+}
@@ -0,0 +1,452 @@
+use crate::clients::polymarket::PositionData;
+use crate::clients::recorder::parse_json;
+use crate::clients::{handle_upstream_response, transport_error, TimedSend};
+use crate::config::Config;
+use crate::metrics::UpstreamApi;
+use crate::types::{canonicalize_outcomes, MarketData, Outcome, Platform, Price};
+use crate::{AppError, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use rsa::pkcs1::DecodeRsaPrivateKey;
+use rsa::pkcs8::DecodePrivateKey;
+use rsa::pss::BlindedSigningKey;
+use rsa::signature::{RandomizedSigner, SignatureEncoding};
+use rsa::RsaPrivateKey;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+const KALSHI_API_BASE: &str = "https://api.elections.kalshi.com/trade-api/v2";
+/// Position pages fetched before giving up on the rest
+const MAX_POSITION_PAGES: usize = 20;
+const POSITIONS_PAGE_SIZE: &str = "200";
+/// Markets looked up at once when pricing positions
+const MARKET_LOOKUP_CONCURRENCY: usize = 8;
+
+/// How the client signs in: an API key id with the RSA key that signs each
+/// request, or an email and password exchanged for a session token on first
+/// use.
+#[derive(Clone)]
+pub enum KalshiCredentials {
+    ApiKey {
+        key_id: String,
+        private_key: KalshiSigningKey,
+    },
+    Login {
+        email: String,
+        password: String,
+    },
+}
+
+impl KalshiCredentials {
+    /// `KALSHI_API_KEY` with `KALSHI_PRIVATE_KEY_PATH`, else `KALSHI_EMAIL`
+    /// with `KALSHI_PASSWORD`.
+    pub fn from_config(config: &Config) -> Option<Self> {
+        if let (Some(key_id), Some(private_key)) =
+            (&config.kalshi_api_key, &config.kalshi_private_key)
+        {
+            return Some(KalshiCredentials::ApiKey {
+                key_id: key_id.clone(),
+                private_key: private_key.clone(),
+            });
+        }
+        match (&config.kalshi_email, &config.kalshi_password) {
+            (Some(email), Some(password)) => Some(KalshiCredentials::Login {
+                email: email.clone(),
+                password: password.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// The RSA private key downloaded with a Kalshi API key. Requests are
+/// signed with RSA-PSS over SHA-256 of the timestamp, method and path.
+#[derive(Clone)]
+pub struct KalshiSigningKey(Arc<BlindedSigningKey<Sha256>>);
+
+impl KalshiSigningKey {
+    /// A PEM key, either PKCS#1 as Kalshi hands it out or PKCS#8.
+    pub fn from_pem(pem: &str) -> std::result::Result<Self, String> {
+        let key = RsaPrivateKey::from_pkcs1_pem(pem)
+            .or_else(|_| RsaPrivateKey::from_pkcs8_pem(pem))
+            .map_err(|e| format!("not an RSA private key: {}", e))?;
+        Ok(Self(Arc::new(BlindedSigningKey::new(key))))
+    }
+
+    /// The base64 `KALSHI-ACCESS-SIGNATURE` for a request sent at
+    /// `timestamp_ms` to `path`, which leaves out the query string.
+    pub fn sign(&self, timestamp_ms: i64, method: &Method, path: &str) -> String {
+        let message = format!("{}{}{}", timestamp_ms, method.as_str(), path);
+        let signature = self
+            .0
+            .sign_with_rng(&mut rand::thread_rng(), message.as_bytes());
+        STANDARD.encode(signature.to_bytes())
+    }
+}
+
+/// Reads the PEM file at a path.
+impl FromStr for KalshiSigningKey {
+    type Err = String;
+
+    fn from_str(path: &str) -> std::result::Result<Self, Self::Err> {
+        let pem =
+            std::fs::read_to_string(path).map_err(|e| format!("can't read the file: {}", e))?;
+        Self::from_pem(&pem)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct KalshiLoginRequest<'a> {
+    email: &'a str,
+    password: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiLoginResponse {
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiMarketResponse {
+    market: KalshiMarket,
+}
+
+/// Prices are integer cents, 0-100.
+#[derive(Debug, Deserialize)]
+struct KalshiMarket {
+    ticker: String,
+    title: String,
+    /// "initialized", "active", "closed", "settled" or "finalized"
+    #[serde(default)]
+    status: String,
+    /// "yes" or "no" once settled, empty before
+    #[serde(default)]
+    result: String,
+    #[serde(default)]
+    yes_bid: u8,
+    #[serde(default)]
+    yes_ask: u8,
+    #[serde(default)]
+    last_price: u8,
+    #[serde(default)]
+    volume: Option<f64>,
+    /// Cents
+    #[serde(default)]
+    liquidity: Option<f64>,
+    #[serde(default)]
+    close_time: Option<DateTime<Utc>>,
+}
+
+impl KalshiMarket {
+    /// The last trade, or the midpoint of the book before any trade.
+    fn yes_cents(&self) -> u8 {
+        if self.last_price > 0 || self.yes_bid == 0 || self.yes_ask == 0 {
+            self.last_price
+        } else {
+            ((u16::from(self.yes_bid) + u16::from(self.yes_ask)) / 2) as u8
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiPositionsResponse {
+    #[serde(default)]
+    market_positions: Vec<KalshiPosition>,
+    #[serde(default)]
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KalshiPosition {
+    ticker: String,
+    /// Contracts held: positive for Yes, negative for No
+    position: i64,
+    /// Cost of the contracts held, in cents
+    #[serde(default)]
+    market_exposure: i64,
+}
+
+/// Kalshi's trade API, for markets and the signed-in account's positions
+/// without going through Dome.
+#[derive(Clone)]
+pub struct KalshiClient {
+    client: Client,
+    base_url: String,
+    credentials: KalshiCredentials,
+    /// Limit set on each request, quoted in timeout errors
+    timeout: Duration,
+    /// Session token from `/login`; unused with an API key
+    session: Arc<tokio::sync::Mutex<Option<String>>>,
+}
+
+impl KalshiClient {
+    /// A client for `credentials`, failing when there are none. `base_url`
+    /// defaults to the production API.
+    pub fn new(
+        credentials: Option<KalshiCredentials>,
+        timeout: Duration,
+        client: Client,
+        base_url: Option<String>,
+    ) -> Result<Self> {
+        let credentials = credentials.ok_or_else(|| AppError::MissingApiKey {
+            variable: "KALSHI_API_KEY",
+            message: "KALSHI_API_KEY and KALSHI_PRIVATE_KEY_PATH (or KALSHI_EMAIL and \
+                      KALSHI_PASSWORD) not set"
+                .to_string(),
+        })?;
+
+        Ok(Self {
+            client,
+            base_url: base_url.unwrap_or_else(|| KALSHI_API_BASE.to_string()),
+            credentials,
+            timeout,
+            session: Arc::new(tokio::sync::Mutex::new(None)),
+        })
+    }
+
+    /// Settings from the environment, as read by [`Config`].
+    pub fn from_env() -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        Self::new(
+            KalshiCredentials::from_config(&config),
+            config.upstream_timeout,
+            config.http.clone(),
+            None,
+        )
+    }
+
+    /// A market by ticker, with prices converted from cents to
+    /// probabilities. An unknown ticker is `MarketNotFound`.
+    pub async fn get_market(&self, ticker: &str) -> Result<MarketData> {
+        let ticker = ticker.trim().to_uppercase();
+        if ticker.is_empty()
+            || !ticker
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err(AppError::Validation(format!(
+                "Invalid market identifier: {:?}",
+                ticker
+            )));
+        }
+
+        let url = format!("{}/markets/{}", self.base_url, ticker);
+        let response = self.send(|| self.client.get(&url)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(AppError::MarketNotFound {
+                market: ticker.to_string(),
+                message: format!("Unknown market identifier: {} on Kalshi", ticker),
+            });
+        }
+        let response = handle_upstream_response(response, "Kalshi API").await?;
+        let body: KalshiMarketResponse = parse_json(response, "Kalshi market").await?;
+        market_data(body.market)
+    }
+
+    /// The account's open positions, optionally on one ticker only, in the
+    /// shape the position tracker uses: one row per held side, with the
+    /// token id `<ticker>:yes` or `<ticker>:no`. Markets are looked up a few
+    /// at a time, and a position whose market can't be fetched is logged
+    /// and left out.
+    pub async fn get_positions(&self, ticker: Option<&str>) -> Result<Vec<PositionData>> {
+        let url = format!("{}/portfolio/positions", self.base_url);
+        let mut held = Vec::new();
+        let mut cursor: Option<String> = None;
+        for _ in 0..MAX_POSITION_PAGES {
+            let mut query = vec![("limit", POSITIONS_PAGE_SIZE.to_string())];
+            if let Some(ticker) = ticker {
+                query.push(("ticker", ticker.trim().to_uppercase()));
+            }
+            if let Some(cursor) = &cursor {
+                query.push(("cursor", cursor.clone()));
+            }
+            let response = self.send(|| self.client.get(&url).query(&query)).await?;
+            let response = handle_upstream_response(response, "Kalshi API").await?;
+            let page: KalshiPositionsResponse = parse_json(response, "Kalshi positions").await?;
+
+            held.extend(
+                page.market_positions
+                    .into_iter()
+                    .filter(|p| p.position != 0),
+            );
+            cursor = page.cursor.filter(|c| !c.is_empty());
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        let semaphore = Arc::new(Semaphore::new(MARKET_LOOKUP_CONCURRENCY));
+        let mut workers = JoinSet::new();
+        for (index, position) in held.into_iter().enumerate() {
+            let client = self.clone();
+            let semaphore = semaphore.clone();
+            workers.spawn(async move {
+                let _permit = semaphore.acquire_owned().await;
+                let market = client.get_market(&position.ticker).await;
+                (index, position, market)
+            });
+        }
+
+        let mut priced = Vec::new();
+        while let Some(joined) = workers.join_next().await {
+            match joined {
+                Ok((index, position, Ok(market))) => {
+                    if let Some(row) = position_data(&position, &market) {
+                        priced.push((index, row));
+                    }
+                }
+                Ok((_, position, Err(e))) => {
+                    tracing::warn!("Kalshi market lookup failed for {}: {}", position.ticker, e)
+                }
+                Err(e) => tracing::error!("Kalshi market lookup task failed: {}", e),
+            }
+        }
+        priced.sort_by_key(|(index, _)| *index);
+        Ok(priced.into_iter().map(|(_, row)| row).collect())
+    }
+
+    /// The exchange status, which checks Kalshi is reachable and accepts our
+    /// credentials.
+    pub async fn ping(&self) -> Result<()> {
+        let url = format!("{}/exchange/status", self.base_url);
+        let response = self.send(|| self.client.get(&url)).await?;
+        handle_upstream_response(response, "Kalshi API").await?;
+        Ok(())
+    }
+
+    /// Sends the request `build` makes with our credentials. An expired
+    /// session is renewed and the request sent once more.
+    async fn send(&self, build: impl Fn() -> RequestBuilder) -> Result<Response> {
+        let response = self
+            .authorize(build(), false)
+            .await?
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Kalshi)
+            .await
+            .map_err(|e| transport_error("Kalshi API", e, self.timeout))?;
+        if response.status() != StatusCode::UNAUTHORIZED
+            || matches!(self.credentials, KalshiCredentials::ApiKey { .. })
+        {
+            return Ok(response);
+        }
+
+        tracing::debug!("Kalshi session expired; signing in again");
+        self.authorize(build(), true)
+            .await?
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Kalshi)
+            .await
+            .map_err(|e| transport_error("Kalshi API", e, self.timeout))
+    }
+
+    /// Adds the signed `KALSHI-ACCESS-*` headers with an API key, or the
+    /// session token otherwise.
+    async fn authorize(&self, request: RequestBuilder, renew: bool) -> Result<RequestBuilder> {
+        let (key_id, private_key) = match &self.credentials {
+            KalshiCredentials::ApiKey {
+                key_id,
+                private_key,
+            } => (key_id, private_key),
+            KalshiCredentials::Login { email, password } => {
+                let token = self.session_token(email, password, renew).await?;
+                return Ok(request.header("Authorization", format!("Bearer {}", token)));
+            }
+        };
+
+        let (client, request) = request.build_split();
+        let request = request.map_err(|e| transport_error("Kalshi API", e, self.timeout))?;
+        let timestamp = Utc::now().timestamp_millis();
+        let signature = private_key.sign(timestamp, request.method(), request.url().path());
+        Ok(RequestBuilder::from_parts(client, request)
+            .header("KALSHI-ACCESS-KEY", key_id)
+            .header("KALSHI-ACCESS-SIGNATURE", signature)
+            .header("KALSHI-ACCESS-TIMESTAMP", timestamp.to_string()))
+    }
+
+    /// The session token, signing in when there is none yet or `renew` is
+    /// set.
+    async fn session_token(&self, email: &str, password: &str, renew: bool) -> Result<String> {
+        let mut session = self.session.lock().await;
+        if let (Some(token), false) = (session.as_ref(), renew) {
+            return Ok(token.clone());
+        }
+
+        let response = self
+            .client
+            .post(format!("{}/login", self.base_url))
+            .json(&KalshiLoginRequest { email, password })
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Kalshi)
+            .await
+            .map_err(|e| transport_error("Kalshi API", e, self.timeout))?;
+        let response = handle_upstream_response(response, "Kalshi API").await?;
+        let login: KalshiLoginResponse = parse_json(response, "Kalshi login").await?;
+        *session = Some(login.token.clone());
+        Ok(login.token)
+    }
+}
+
+/// A held position priced from its market; `None` when the market has no
+/// outcome for the held side.
+fn position_data(position: &KalshiPosition, market: &MarketData) -> Option<PositionData> {
+    let side = if position.position > 0 { "yes" } else { "no" };
+    let token_id = format!("{}:{}", market.id, side);
+    let outcome = market.outcomes.iter().find(|o| o.id == token_id)?;
+    let shares = position.position.unsigned_abs() as f64;
+    Some(PositionData {
+        outcome: outcome.name.clone(),
+        shares,
+        avg_price: position.market_exposure as f64 / 100.0 / shares,
+        current_price: outcome.price.value(),
+        token_id,
+    })
+}
+
+fn market_data(market: KalshiMarket) -> Result<MarketData> {
+    let yes = Price::from_cents(market.yes_cents())
+        .map_err(|e| AppError::ExternalApi(format!("Kalshi market {}: {}", market.ticker, e)))?;
+    let no = Price::from_decimal(1.0 - yes.value()).unwrap_or(Price::ZERO);
+    let mut outcomes = vec![
+        Outcome {
+            id: format!("{}:yes", market.ticker),
+            name: "Yes".to_string(),
+            price: yes,
+            volume: None,
+        },
+        Outcome {
+            id: format!("{}:no", market.ticker),
+            name: "No".to_string(),
+            price: no,
+            volume: None,
+        },
+    ];
+    let outcome_ordering = canonicalize_outcomes(&mut outcomes);
+    let resolved_outcome = outcomes
+        .iter()
+        .find(|o| o.name.eq_ignore_ascii_case(&market.result))
+        .map(|o| o.name.clone());
+    let closed = resolved_outcome.is_some() || !matches!(market.status.as_str(), "active" | "");
+
+    Ok(MarketData {
+        id: market.ticker.clone(),
+        question: market.title,
+        slug: None,
+        ticker: Some(market.ticker),
+        platform: Platform::Kalshi,
+        condition_id: None,
+        outcomes,
+        volume: market.volume,
+        liquidity: market.liquidity.map(|cents| cents / 100.0),
+        outcome_ordering,
+        end_date: market.close_time,
+        closed,
+        resolved_outcome,
+    })
+}
@@ -1,10 +1,277 @@
 pub mod ai;
+pub mod circuit_breaker;
+pub mod clob_signing;
 pub mod dome;
+pub mod kalshi;
 pub mod polyfactual;
 pub mod polymarket;
+pub mod rate_limit;
+pub mod recorder;
+pub mod replay;
+pub mod retry;
+pub mod salt;
+pub mod sources;
+pub mod webhook;
 
-pub use ai::{AiClient, AiProvider, create_ai_client};
+pub use ai::{AiClient, AiProvider, AiRequestOptions, create_ai_client};
+pub use circuit_breaker::{BreakerConfig, CircuitBreaker, CircuitSnapshot, CircuitState};
 pub use dome::DomeClient;
+pub use kalshi::KalshiClient;
 pub use polyfactual::PolyfactualClient;
 pub use polymarket::PolymarketClient;
+pub use rate_limit::RateLimiter;
+pub use replay::ReplayMode;
+pub use retry::{retry_with_backoff, Retried, RetryPolicy};
+pub use salt::SaltAllocator;
+pub use sources::{KalshiVenue, MarketDataSource, ResearchSource, TradingVenue};
+pub use webhook::WebhookSender;
 
+use crate::metrics::{Metrics, UpstreamApi};
+use crate::{AppError, Result};
+use reqwest::{header, RequestBuilder, Response, StatusCode};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+/// Outbound HTTP settings shared by every upstream client, read once at
+/// startup (see [`crate::config::Config`]).
+#[derive(Debug, Clone, Default)]
+pub struct HttpClientConfig {
+    /// `HTTPS_PROXY` and `HTTP_PROXY`, each bypassed for `NO_PROXY` hosts.
+    /// Without any, requests go direct.
+    pub proxies: Vec<reqwest::Proxy>,
+    /// `EXTRA_CA_CERT_PATH`, trusted alongside the system roots
+    pub extra_root_certs: Vec<reqwest::Certificate>,
+    /// `DANGEROUS_ACCEPT_INVALID_CERTS`; for development against
+    /// self-signed mirrors only
+    pub accept_invalid_certs: bool,
+}
+
+impl HttpClientConfig {
+    /// Sends requests for `scheme` (`http` or `https`) URLs through the
+    /// proxy at `url`, except to hosts in the `NO_PROXY`-style `no_proxy`
+    /// list.
+    pub fn add_proxy(
+        &mut self,
+        scheme: &str,
+        url: &str,
+        no_proxy: Option<&str>,
+    ) -> std::result::Result<(), String> {
+        const EXPECTED: &str = "expected a URL like http://proxy.internal:3128";
+        let parsed = url::Url::parse(url).map_err(|e| format!("{} ({})", EXPECTED, e))?;
+        if !matches!(parsed.scheme(), "http" | "https") || parsed.host_str().is_none() {
+            return Err(EXPECTED.to_string());
+        }
+        let proxy = match scheme {
+            "https" => reqwest::Proxy::https(parsed.as_str()),
+            _ => reqwest::Proxy::http(parsed.as_str()),
+        }
+        .map_err(|e| format!("{} ({})", EXPECTED, e))?;
+        self.proxies
+            .push(proxy.no_proxy(no_proxy.and_then(reqwest::NoProxy::from_string)));
+        Ok(())
+    }
+
+    /// Trusts every certificate in the PEM file at `path`.
+    pub fn add_root_certs(&mut self, path: &str) -> std::result::Result<(), String> {
+        let pem = std::fs::read(path).map_err(|e| format!("can't read {}: {}", path, e))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("{} isn't a PEM certificate file: {}", path, e))?;
+        if certs.is_empty() {
+            return Err(format!("{} holds no PEM certificates", path));
+        }
+        self.extra_root_certs.extend(certs);
+        Ok(())
+    }
+}
+
+/// Process-wide settings of the upstream clients, which are partly built
+/// per request. Read from the environment with the rest of
+/// [`crate::config::Config`] and installed at startup; the defaults apply
+/// until then.
+#[derive(Debug, Clone)]
+pub struct UpstreamSettings {
+    /// Calls per second to Dome and Gamma; 0 doesn't limit
+    pub dome_rps: f64,
+    pub gamma_rps: f64,
+    /// Calls per minute to each AI provider; 0 doesn't limit
+    pub grok_rpm: f64,
+    pub openai_rpm: f64,
+    pub anthropic_rpm: f64,
+    /// Longest a call waits for a rate limiter permit before failing
+    pub rate_limit_max_wait: Duration,
+    pub circuit_breaker: BreakerConfig,
+    /// Save upstream responses that fail to parse (see [`recorder`])
+    pub record_failures: bool,
+    pub recordings_dir: PathBuf,
+    pub max_recordings: usize,
+    pub replay: ReplayMode,
+}
+
+impl Default for UpstreamSettings {
+    fn default() -> Self {
+        Self {
+            dome_rps: dome::DEFAULT_DOME_RPS,
+            gamma_rps: polymarket::DEFAULT_GAMMA_RPS,
+            grok_rpm: ai::grok::DEFAULT_RPM,
+            openai_rpm: ai::openai::DEFAULT_RPM,
+            anthropic_rpm: ai::claude::DEFAULT_RPM,
+            rate_limit_max_wait: rate_limit::DEFAULT_MAX_WAIT,
+            circuit_breaker: BreakerConfig::default(),
+            record_failures: false,
+            recordings_dir: PathBuf::from(recorder::DEFAULT_RECORDINGS_DIR),
+            max_recordings: recorder::DEFAULT_MAX_RECORDINGS,
+            replay: ReplayMode::Off,
+        }
+    }
+}
+
+static UPSTREAM_SETTINGS: RwLock<Option<Arc<UpstreamSettings>>> = RwLock::new(None);
+
+impl UpstreamSettings {
+    /// Makes these the settings every upstream client uses from now on.
+    /// Limiters and breakers shared across the process keep the settings
+    /// they were first built with.
+    pub fn install(self) {
+        *UPSTREAM_SETTINGS.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(self));
+    }
+
+    pub fn current() -> Arc<Self> {
+        UPSTREAM_SETTINGS
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+            .unwrap_or_default()
+    }
+}
+
+/// `User-Agent` sent on every upstream request
+pub const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+/// The client every upstream client shares, so they draw on one connection
+/// pool. It has the proxy and certificate settings in `http` but no
+/// timeout: each request sets its own. Environment proxies are only used
+/// through `http`, so every client routes the same way.
+pub fn build_http_client(http: &HttpClientConfig) -> Result<reqwest::Client> {
+    http_client_builder(http)
+        .build()
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to create HTTP client: {}", e)))
+}
+
+/// A builder with the proxy and certificate settings in `http`, for clients
+/// that need more on top, like the webhook sender.
+pub fn http_client_builder(http: &HttpClientConfig) -> reqwest::ClientBuilder {
+    let mut builder = reqwest::Client::builder().user_agent(USER_AGENT);
+    builder = match http.proxies.is_empty() {
+        true => builder.no_proxy(),
+        false => http
+            .proxies
+            .iter()
+            .fold(builder, |builder, proxy| builder.proxy(proxy.clone())),
+    };
+    for cert in &http.extra_root_certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+    if http.accept_invalid_certs {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    builder
+}
+
+/// Sends a request while recording the call, its latency and any failure
+/// against `api` in [`Metrics`]. Under `UPSTREAM_RECORD_DIR` the response
+/// is also saved as a fixture; under `UPSTREAM_REPLAY_DIR` it is served
+/// from one without touching the network (see [`replay`]).
+pub trait TimedSend {
+    fn send_timed(self, api: UpstreamApi)
+        -> impl Future<Output = reqwest::Result<Response>> + Send;
+}
+
+impl TimedSend for RequestBuilder {
+    async fn send_timed(self, api: UpstreamApi) -> reqwest::Result<Response> {
+        let (client, request) = self.build_split();
+        let request = request?;
+        let record_to = match UpstreamSettings::current().replay.clone() {
+            ReplayMode::Replay(dir) => return Ok(replay::replay(&request, api, &dir).await),
+            ReplayMode::Record(dir) => request.try_clone().map(|copy| (copy, dir)),
+            ReplayMode::Off => None,
+        };
+
+        let started = Instant::now();
+        let result = client.execute(request).await;
+        let status = result.as_ref().ok().map(|r| r.status().as_u16());
+        Metrics::global().observe_upstream(api, status, started.elapsed());
+        match record_to {
+            Some((request, dir)) => replay::record(&request, result?, api, &dir).await,
+            None => result,
+        }
+    }
+}
+
+/// Maps a request that got no response onto `Timeout` when its time
+/// `limit` expired and `ExternalApi` otherwise.
+pub fn transport_error(api_name: &str, error: reqwest::Error, limit: Duration) -> AppError {
+    if error.is_timeout() {
+        AppError::Timeout(format!(
+            "{} request timed out after {}s: {}",
+            api_name,
+            limit.as_secs(),
+            error
+        ))
+    } else {
+        AppError::ExternalApi(format!("{} request failed: {}", api_name, error))
+    }
+}
+
+/// Passes a successful upstream response through and maps a failed one onto
+/// the matching error: 404 is `NotFound`, 429 is `RateLimit` (with any
+/// `Retry-After`), 408/504 are `Timeout`, other 4xx are `UpstreamRejected`
+/// and everything else is `ExternalApi`. The status and body are kept in
+/// the message.
+pub async fn handle_upstream_response(response: Response, api_name: &str) -> Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let retry_after = response
+            .headers()
+            .get(header::RETRY_AFTER)
+            .and_then(|v| v.to_str().ok())
+            .and_then(parse_retry_after);
+        tracing::warn!(
+            "{} rate limited us (retry after {:?})",
+            api_name,
+            retry_after
+        );
+        return Err(AppError::RateLimit {
+            retry_after,
+            upstream: Some(api_name.to_string()),
+        });
+    }
+
+    let error_text = response
+        .text()
+        .await
+        .unwrap_or_else(|_| "Unknown error".to_string());
+    let message = format!("{} returned {}: {}", api_name, status, error_text);
+    Err(match status {
+        StatusCode::NOT_FOUND => AppError::NotFound(message),
+        StatusCode::REQUEST_TIMEOUT | StatusCode::GATEWAY_TIMEOUT => AppError::Timeout(message),
+        s if s.is_client_error() => AppError::UpstreamRejected(message),
+        _ => AppError::ExternalApi(message),
+    })
+}
+
+/// `Retry-After` as delay seconds or an HTTP date.
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = at.signed_duration_since(chrono::Utc::now());
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
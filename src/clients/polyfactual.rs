@@ -1,14 +1,37 @@
-use crate::types::{Citation, PolyfactualResearchResponse, ResponseMetadata};
+use crate::clients::ai::AiClient;
+use crate::clients::{handle_upstream_response, transport_error, TimedSend};
+use crate::clients::circuit_breaker::{BreakerConfig, CircuitBreaker, CircuitSnapshot};
+use crate::clients::recorder::parse_json;
+use crate::clients::retry::{retry_with_backoff, Retried};
+use crate::config::Config;
+use crate::metrics::UpstreamApi;
+use crate::request_id;
+use crate::types::{
+    Citation, PolyfactualResearchResponse, QueryCompression, ResponseMetadata, TimeoutBudget,
+};
 use crate::{AppError, Result};
 use chrono::Utc;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::{Duration, Instant};
 use tracing::info;
 
-const POLYFACTUAL_API_URL: &str = "https://api.polyfactual.com/v1/research";
-const MAX_QUERY_LENGTH: usize = 1000;
-const TIMEOUT_SECS: u64 = 300; // 5 minutes
+const POLYFACTUAL_API_BASE: &str = "https://api.polyfactual.com/v1";
+/// Longest query when `POLYFACTUAL_MAX_QUERY_LENGTH` isn't set.
+pub const DEFAULT_MAX_QUERY_LENGTH: usize = 1000;
+/// Citations kept per answer when `POLYFACTUAL_MAX_CITATIONS` isn't set.
+pub const DEFAULT_MAX_CITATIONS: usize = 20;
+/// Query parameters that only track where a click came from; dropped when
+/// comparing citation URLs. `utm_*` are dropped too.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "msclkid", "igshid", "mc_cid", "mc_eid", "ref", "ref_src", "_ga",
+];
+/// Longest `timeout_secs` a research request may ask for.
+pub const MAX_RESEARCH_TIMEOUT_SECS: u64 = 900;
+/// Retries after the first attempt; a run that timed out is retried too
+const MAX_RETRIES: u32 = 2;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
 
 #[derive(Debug, Serialize)]
 struct PolyfactualRequest {
@@ -31,29 +54,107 @@ struct PolyfactualCitation {
 pub struct PolyfactualClient {
     client: Client,
     api_key: String,
+    base_url: String,
+    /// `POLYFACTUAL_TIMEOUT_SECS`, for runs that don't set their own
+    timeout: Duration,
+    /// `POLYFACTUAL_MAX_QUERY_LENGTH`; longer queries are refused
+    max_query_length: usize,
+    /// `POLYFACTUAL_MAX_CITATIONS`
+    max_citations: usize,
+    /// Fails runs fast while Polyfactual is down
+    breaker: CircuitBreaker,
 }
 
 impl PolyfactualClient {
-    pub fn new() -> Result<Self> {
-        let api_key = std::env::var("POLYFACTUAL_API_KEY")
-            .map_err(|_| AppError::Validation("POLYFACTUAL_API_KEY not set".to_string()))?;
+    /// A client for `api_key`, failing when it isn't set. `timeout` bounds
+    /// one research run; `base_url` defaults to the production API.
+    pub fn new(
+        api_key: Option<String>,
+        timeout: Duration,
+        max_query_length: usize,
+        max_citations: usize,
+        client: Client,
+        base_url: Option<String>,
+    ) -> Result<Self> {
+        let api_key = api_key.ok_or_else(|| AppError::missing_api_key("POLYFACTUAL_API_KEY"))?;
 
-        let client = Client::builder()
-            .timeout(Duration::from_secs(TIMEOUT_SECS))
-            .build()
-            .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to create HTTP client: {}", e)))?;
+        Ok(Self {
+            client,
+            api_key,
+            base_url: base_url.unwrap_or_else(|| POLYFACTUAL_API_BASE.to_string()),
+            timeout,
+            max_query_length,
+            max_citations,
+            breaker: CircuitBreaker::configured(UpstreamApi::Polyfactual),
+        })
+    }
+
+    /// Replaces the installed circuit breaker settings.
+    pub fn with_circuit_breaker(mut self, config: BreakerConfig) -> Self {
+        self.breaker = CircuitBreaker::new(UpstreamApi::Polyfactual, config);
+        self
+    }
 
-        Ok(Self { client, api_key })
+    pub fn circuit(&self) -> CircuitSnapshot {
+        self.breaker.snapshot()
     }
 
-    pub async fn research(&self, query: String) -> Result<PolyfactualResearchResponse> {
+    /// Settings from the environment, as read by [`Config`].
+    pub fn from_env() -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        Self::new(
+            config.polyfactual_api_key,
+            config.polyfactual_timeout,
+            config.polyfactual_max_query_length,
+            config.polyfactual_max_citations,
+            config.http.clone(),
+            None,
+        )
+    }
+
+    async fn send(
+        &self,
+        request: &PolyfactualRequest,
+        timeout: Duration,
+        timed_out: &AtomicBool,
+    ) -> Result<PolyfactualResponse> {
+        let response = self
+            .client
+            .post(format!("{}/research", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.api_key))
+            .header("Content-Type", "application/json")
+            .json(request)
+            .timeout(timeout)
+            .send_timed(UpstreamApi::Polyfactual)
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    timed_out.store(true, Ordering::Relaxed);
+                }
+                transport_error("Polyfactual API", e, timeout)
+            })?;
+
+        let response = handle_upstream_response(response, "Polyfactual API").await?;
+
+        parse_json(response, "Polyfactual response").await
+    }
+
+    /// Runs `query`, each attempt limited to `timeout` when given and to
+    /// the client's own timeout otherwise.
+    pub async fn research(
+        &self,
+        query: String,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse> {
         let start = Instant::now();
+        let timeout = timeout.unwrap_or(self.timeout);
+        let timed_out = AtomicBool::new(false);
 
-        // Validate query length
-        if query.len() > MAX_QUERY_LENGTH {
+        // Validate query length; see `fit_query` for summarizing long ones
+        if query.chars().count() > self.max_query_length {
             return Err(AppError::Validation(format!(
                 "Query exceeds maximum length of {} characters",
-                MAX_QUERY_LENGTH
+                self.max_query_length
             )));
         }
 
@@ -63,38 +164,22 @@ impl PolyfactualClient {
             query: query.clone(),
         };
 
-        let response = self
-            .client
-            .post(POLYFACTUAL_API_URL)
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .header("Content-Type", "application/json")
-            .json(&request)
-            .send()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Polyfactual API request failed: {}", e)))?;
-
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::ExternalApi(format!(
-                "Polyfactual API returned {}: {}",
-                status, error_text
-            )));
-        }
-
-        let polyfactual_response: PolyfactualResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse Polyfactual response: {}", e)))?;
+        let Retried {
+            value: polyfactual_response,
+            retries,
+        } = self
+            .breaker
+            .call(retry_with_backoff(
+                || self.send(&request, timeout, &timed_out),
+                MAX_RETRIES,
+                RETRY_BASE_DELAY,
+            ))
+            .await?;
 
         let execution_time = start.elapsed().as_millis() as u64;
 
-        Ok(PolyfactualResearchResponse {
-            answer: polyfactual_response.answer,
-            citations: polyfactual_response
+        let (citations, warnings) = clean_citations(
+            polyfactual_response
                 .citations
                 .into_iter()
                 .map(|c| Citation {
@@ -103,13 +188,156 @@ impl PolyfactualClient {
                     relevance: c.relevance.unwrap_or(0.0),
                 })
                 .collect(),
+            self.max_citations,
+        );
+
+        Ok(PolyfactualResearchResponse {
+            answer: polyfactual_response.answer,
+            citations,
+            warnings,
             metadata: ResponseMetadata {
                 timestamp: Utc::now().to_rfc3339(),
                 execution_time_ms: execution_time,
                 model_used: None,
-                retries: 0,
+                retries,
+                degraded_features: Vec::new(),
+                custom_prompt: false,
+                dry_run: false,
+                cache_hit: None,
+                request_id: request_id::current(),
+                ai_usage: None,
+                ai_provider: None,
+                query_compression: None,
+                timeout_budget: Some(TimeoutBudget {
+                    limit_secs: timeout.as_secs(),
+                    timed_out: timed_out.load(Ordering::Relaxed),
+                }),
             },
         })
     }
 }
 
+/// Tidies the citations Polyfactual returned: URLs are normalized (no
+/// fragment, tracking parameters or trailing slash), citations whose URL
+/// isn't http(s) are dropped with a warning, repeats of a URL are merged
+/// into the most relevant one, and the rest are sorted most relevant first
+/// and cut to `max`. Citations without a URL are kept.
+pub fn clean_citations(citations: Vec<Citation>, max: usize) -> (Vec<Citation>, Vec<String>) {
+    let mut warnings = Vec::new();
+    let mut cleaned: Vec<Citation> = Vec::with_capacity(citations.len());
+    for mut citation in citations {
+        if let Some(raw) = citation.url.take() {
+            match normalize_url(&raw) {
+                Some(url) => citation.url = Some(url),
+                None => {
+                    warnings.push(format!(
+                        "Dropped citation '{}': '{}' is not an http(s) URL",
+                        citation.source, raw
+                    ));
+                    continue;
+                }
+            }
+        }
+        let duplicate = citation
+            .url
+            .as_ref()
+            .and_then(|url| cleaned.iter_mut().find(|c| c.url.as_ref() == Some(url)));
+        match duplicate {
+            Some(kept) if citation.relevance > kept.relevance => *kept = citation,
+            Some(_) => {}
+            None => cleaned.push(citation),
+        }
+    }
+
+    cleaned.sort_by(|a, b| b.relevance.total_cmp(&a.relevance));
+    if cleaned.len() > max {
+        warnings.push(format!(
+            "Kept the {} most relevant of {} citations",
+            max,
+            cleaned.len()
+        ));
+        cleaned.truncate(max);
+    }
+    (cleaned, warnings)
+}
+
+/// `raw` without its fragment, tracking parameters or trailing slash, or
+/// `None` when it isn't an absolute http(s) URL.
+fn normalize_url(raw: &str) -> Option<String> {
+    let mut url = url::Url::parse(raw.trim()).ok()?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return None;
+    }
+    url.set_fragment(None);
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| {
+            let name = name.to_ascii_lowercase();
+            !name.starts_with("utm_") && !TRACKING_PARAMS.contains(&name.as_str())
+        })
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if kept.is_empty() {
+        url.set_query(None);
+    } else {
+        url.query_pairs_mut().clear().extend_pairs(kept);
+    }
+    let path = url.path().trim_end_matches('/').to_string();
+    url.set_path(&path);
+
+    let mut normalized = url.to_string();
+    // The root path always serializes as "/"
+    if url.path() == "/" && url.query().is_none() {
+        normalized.pop();
+    }
+    Some(normalized)
+}
+
+/// The error for a query of `length` characters over `max_length` when no AI
+/// provider can summarize it.
+pub fn query_too_long(length: usize, max_length: usize) -> AppError {
+    AppError::Validation(format!(
+        "Query is {} characters, over the {}-character limit, and no AI provider is configured to summarize it",
+        length, max_length
+    ))
+}
+
+/// `query` when it is at most `max_length` characters, else a summary of it
+/// by `summarizer`, with the lengths before and after. Fails when a long
+/// query has no summarizer or its summary is still too long.
+pub async fn fit_query(
+    query: String,
+    max_length: usize,
+    summarizer: Option<&dyn AiClient>,
+) -> Result<(String, Option<QueryCompression>)> {
+    let original_length = query.chars().count();
+    if original_length <= max_length {
+        return Ok((query, None));
+    }
+    let Some(summarizer) = summarizer else {
+        return Err(query_too_long(original_length, max_length));
+    };
+
+    let summary = summarizer.summarize(&query, max_length).await?;
+    let compressed_length = summary.chars().count();
+    if summary.is_empty() || compressed_length > max_length {
+        return Err(AppError::Validation(format!(
+            "Query is {} characters, over the {}-character limit, and its summary ({} characters) still doesn't fit",
+            original_length, max_length, compressed_length
+        )));
+    }
+    info!(
+        "Summarized a {}-character research query to {} characters with {}",
+        original_length,
+        compressed_length,
+        summarizer.model_name()
+    );
+    Ok((
+        summary,
+        Some(QueryCompression {
+            original_length,
+            compressed_length,
+            model: summarizer.model_name().to_string(),
+        }),
+    ))
+}
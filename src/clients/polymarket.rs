@@ -1,186 +1,1506 @@
-use crate::types::{MarketData, OrderResult, OrderStatus, Outcome, Platform};
+use crate::clients::clob_signing::{
+    build_signed_order, snap_to_tick, ApiCredentials, ClobSigner, MarketParams, OrderSide,
+    PostOrderRequest, WalletAuth,
+};
+use crate::clients::{handle_upstream_response, transport_error, TimedSend, UpstreamSettings};
+use crate::clients::circuit_breaker::{BreakerConfig, CircuitBreaker, CircuitSnapshot};
+use crate::clients::rate_limit::RateLimiter;
+use crate::clients::recorder::{parse_failure, parse_json};
+use crate::clients::retry::retry_with_backoff;
+use crate::config::Config;
+use crate::metrics::UpstreamApi;
+use crate::types::{
+    canonicalize_outcomes, BookLevel, LadderSpacing, MarketData, MarketInterval, MarketSummary,
+    OrderBook, OrderResult, OrderStatus, Outcome, Platform, Price,
+};
 use crate::{AppError, Result};
-use chrono::{DateTime, Timelike, Utc};
-use reqwest::Client;
+use chrono::{DateTime, NaiveDate, NaiveTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use reqwest::{Client, RequestBuilder};
+use alloy_primitives::Address;
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
 use std::time::Duration;
 
 const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
 const DATA_API_BASE: &str = "https://data-api.polymarket.com";
+const CLOB_API_BASE: &str = "https://clob.polymarket.com";
+/// Cursor the CLOB returns on the last page of a paginated listing.
+/// Retries after the first attempt for market and position reads
+const MAX_FETCH_RETRIES: u32 = 2;
+const FETCH_RETRY_BASE_DELAY: Duration = Duration::from_millis(200);
+const CLOB_END_CURSOR: &str = "LTE=";
+const CLOB_MAX_PAGES: usize = 10;
+/// The data API's largest page
+const DATA_API_PAGE_SIZE: usize = 500;
+/// Most fills [`PolymarketClient::get_market_trades`] returns
+pub const MARKET_TRADES_LIMIT: usize = DATA_API_PAGE_SIZE;
+pub const DEFAULT_GAMMA_RPS: f64 = 10.0;
+const UPDOWN_TAG_SLUG: &str = "up-or-down";
+const UPDOWN_LISTING_PAGE_SIZE: usize = 100;
+/// Listing pages searched for a window missing by slug before giving up
+const UPDOWN_LISTING_MAX_PAGES: usize = 5;
+/// Assets with recurring 15-minute up/down markets.
+pub const UPDOWN_ASSETS: &[&str] = &["btc", "eth", "sol", "xrp"];
+const DEFAULT_UPDOWN_ASSET: &str = "btc";
+/// Polymarket names and bounds its recurring markets in US Eastern time.
+pub const MARKET_TIMEZONE: Tz = chrono_tz::America::New_York;
+/// Local hour daily up/down windows open and close at
+const DAILY_WINDOW_HOUR: u32 = 12;
+/// Events a text search reads from Gamma; filters and paging apply within them
+const SEARCH_MAX_EVENTS: usize = 50;
 
+/// A Gamma market. Outcomes, their prices and their CLOB token ids come as
+/// three parallel JSON-encoded string arrays (`"[\"Yes\", \"No\"]"`), and
+/// volume/liquidity as decimal strings.
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct GammaMarketResponse {
     id: String,
     question: String,
     slug: String,
-    outcomes: Vec<GammaOutcome>,
+    #[serde(default, deserialize_with = "string_array")]
+    outcomes: Vec<String>,
+    #[serde(default, deserialize_with = "string_array")]
+    outcome_prices: Vec<String>,
+    #[serde(default, deserialize_with = "string_array")]
+    clob_token_ids: Vec<String>,
+    #[serde(default, deserialize_with = "number_or_string")]
     volume: Option<f64>,
+    #[serde(default, deserialize_with = "number_or_string")]
     liquidity: Option<f64>,
+    #[serde(default)]
+    condition_id: Option<String>,
+    #[serde(default)]
+    event_start_time: Option<DateTime<Utc>>,
+    #[serde(default)]
+    end_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    closed: bool,
+    #[serde(default)]
+    active: Option<bool>,
+    #[serde(default, deserialize_with = "number_or_string")]
+    best_bid: Option<f64>,
+    #[serde(default, deserialize_with = "number_or_string")]
+    best_ask: Option<f64>,
+}
+
+impl GammaMarketResponse {
+    fn matches(&self, search: &MarketSearch) -> bool {
+        let at_least = |value: Option<f64>, min: Option<f64>| match min {
+            Some(min) => value.is_some_and(|v| v >= min),
+            None => true,
+        };
+        (!search.active_only || (!self.closed && self.active != Some(false)))
+            && at_least(self.volume, search.min_volume)
+            && at_least(self.liquidity, search.min_liquidity)
+    }
+
+    fn into_summary(self) -> Result<MarketSummary> {
+        let (best_bid, best_ask) = (self.best_bid, self.best_ask);
+        Ok(MarketSummary {
+            best_bid,
+            best_ask,
+            ..self.into_market_data()?.into()
+        })
+    }
+
+    fn into_market_data(self) -> Result<MarketData> {
+        if self.outcome_prices.len() != self.outcomes.len()
+            || self.clob_token_ids.len() != self.outcomes.len()
+        {
+            return Err(AppError::ExternalApi(format!(
+                "Gamma market {} has {} outcomes, {} prices and {} token ids",
+                self.slug,
+                self.outcomes.len(),
+                self.outcome_prices.len(),
+                self.clob_token_ids.len()
+            )));
+        }
+
+        let mut outcomes = self
+            .outcomes
+            .into_iter()
+            .zip(self.outcome_prices)
+            .zip(self.clob_token_ids)
+            .map(|((name, price), id)| {
+                let price = price
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(|p| Price::from_decimal(p).ok())
+                    .ok_or_else(|| {
+                        AppError::ExternalApi(format!("Gamma returned an invalid price: {}", price))
+                    })?;
+                Ok(Outcome {
+                    id,
+                    name,
+                    price,
+                    volume: None, // Gamma only reports market-level volume
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let outcome_ordering = canonicalize_outcomes(&mut outcomes);
+        // A resolved market settles its prices at exactly 1 and 0; a closed
+        // one still awaiting resolution keeps its last trading prices
+        let mut winners = outcomes.iter().filter(|o| o.price == Price::ONE);
+        let resolved_outcome = match (self.closed, winners.next(), winners.next()) {
+            (true, Some(winner), None) => Some(winner.name.clone()),
+            _ => None,
+        };
+
+        Ok(MarketData {
+            id: self.id,
+            question: self.question,
+            slug: Some(self.slug),
+            ticker: None,
+            platform: Platform::Polymarket,
+            condition_id: self.condition_id,
+            outcomes,
+            volume: self.volume,
+            liquidity: self.liquidity,
+            outcome_ordering,
+            end_date: self.end_date,
+            closed: self.closed,
+            resolved_outcome,
+        })
+    }
 }
 
 #[derive(Debug, Deserialize)]
-struct GammaOutcome {
-    id: String,
-    name: String,
-    price: f64,
-    volume: Option<f64>,
+struct GammaEventResponse {
+    slug: String,
+    title: String,
+    /// Set on events whose markets are mutually exclusive outcomes
+    #[serde(rename = "negRisk", default)]
+    neg_risk: Option<bool>,
+    #[serde(default)]
+    markets: Vec<GammaMarketResponse>,
+}
+
+/// An event and all of its sibling markets.
+#[derive(Debug, Clone)]
+pub struct PolymarketEvent {
+    pub slug: String,
+    pub title: String,
+    pub neg_risk: Option<bool>,
+    pub markets: Vec<MarketData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GammaSearchResponse {
+    #[serde(default)]
+    events: Vec<GammaSearchEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GammaSearchEvent {
+    #[serde(default)]
+    tags: Vec<GammaTag>,
+    #[serde(default)]
+    markets: Vec<GammaMarketResponse>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GammaTag {
+    #[serde(default)]
+    slug: String,
+}
+
+/// The recurring market window in progress and the start of the one after
+/// it, which is also when the current one closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MarketWindow {
+    pub current: DateTime<Utc>,
+    pub next: DateTime<Utc>,
+}
+
+/// The first instant of `date` in `tz` at or after `hour` o'clock: that
+/// hour, or the end of the gap when a DST change skips it.
+fn local_hour(tz: Tz, date: NaiveDate, hour: u32) -> DateTime<Utc> {
+    (hour..24)
+        .find_map(|hour| {
+            let time = NaiveTime::from_hms_opt(hour, 0, 0)?;
+            tz.from_local_datetime(&date.and_time(time)).earliest()
+        })
+        .map(|start| start.with_timezone(&Utc))
+        .unwrap_or_else(|| date.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Filters for [`PolymarketClient::search_markets`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MarketSearch {
+    pub query: Option<String>,
+    /// Only markets still trading
+    pub active_only: bool,
+    /// Gamma tag slug, e.g. `politics`
+    pub tag: Option<String>,
+    pub min_volume: Option<f64>,
+    pub min_liquidity: Option<f64>,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// One page of [`PolymarketClient::search_markets`] results.
+#[derive(Debug, Clone)]
+pub struct MarketSearchPage {
+    pub markets: Vec<MarketSummary>,
+    pub has_more: bool,
+}
+
+/// Summaries of `listing`, skipping markets Gamma sent malformed (often
+/// placeholders without outcomes yet) rather than failing the page.
+fn summaries(listing: Vec<GammaMarketResponse>) -> Vec<MarketSummary> {
+    listing
+        .into_iter()
+        .filter_map(|market| {
+            let slug = market.slug.clone();
+            market
+                .into_summary()
+                .map_err(|e| tracing::debug!("Skipping Gamma market {}: {}", slug, e))
+                .ok()
+        })
+        .collect()
+}
+
+/// The market matching `slug` in a `/markets?slug=` listing.
+fn market_from_listing(slug: &str, listing: Vec<GammaMarketResponse>) -> Result<MarketData> {
+    listing
+        .into_iter()
+        .find(|m| m.slug == slug)
+        .ok_or_else(|| AppError::MarketNotFound {
+            market: slug.to_string(),
+            message: format!("Gamma market not found: {}", slug),
+        })?
+        .into_market_data()
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StringArray {
+    Encoded(String),
+    Plain(Vec<String>),
+}
+
+/// A string array that Gamma may send JSON-encoded inside a string.
+fn string_array<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<StringArray>::deserialize(deserializer)? {
+        None => Ok(Vec::new()),
+        Some(StringArray::Plain(items)) => Ok(items),
+        Some(StringArray::Encoded(raw)) if raw.trim().is_empty() => Ok(Vec::new()),
+        Some(StringArray::Encoded(raw)) => {
+            serde_json::from_str(&raw).map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NumberOrString {
+    Number(f64),
+    Text(String),
+}
+
+/// A number that Gamma may send as a decimal string; empty means absent.
+fn number_or_string<'de, D>(deserializer: D) -> std::result::Result<Option<f64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    match Option::<NumberOrString>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(NumberOrString::Number(value)) => Ok(Some(value)),
+        Some(NumberOrString::Text(raw)) if raw.trim().is_empty() => Ok(None),
+        Some(NumberOrString::Text(raw)) => raw
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ClobPage<T> {
+    data: Vec<T>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClobOrder {
+    pub id: String,
+    pub asset_id: String,
+    pub status: String,
+    #[serde(default)]
+    pub side: String,
+    #[serde(default)]
+    pub price: String,
+    #[serde(default)]
+    pub original_size: String,
+    #[serde(default)]
+    pub size_matched: String,
+    #[serde(default)]
+    pub outcome: String,
+    /// Unix seconds the order expires at; "0" when it never does
+    #[serde(default)]
+    pub expiration: String,
+}
+
+impl ClobOrder {
+    pub fn price(&self) -> f64 {
+        self.price.parse().unwrap_or(0.0)
+    }
+
+    pub fn matched_size(&self) -> f64 {
+        self.size_matched.parse().unwrap_or(0.0)
+    }
+
+    /// Our view of the order: LIVE is pending (or partially filled once
+    /// anything matched), MATCHED filled, and any CANCELED variant
+    /// (including cancellation on market resolution) cancelled.
+    pub fn status(&self) -> OrderStatus {
+        let status = self.status.to_ascii_uppercase();
+        if status.contains("MATCHED") {
+            OrderStatus::Filled
+        } else if status.contains("CANCEL") {
+            OrderStatus::Cancelled
+        } else if self.matched_size() > 0.0 {
+            OrderStatus::PartiallyFilled
+        } else {
+            OrderStatus::Pending
+        }
+    }
+
+    pub fn into_order_result(self) -> Result<OrderResult> {
+        Ok(OrderResult {
+            status: self.status(),
+            filled_size: Some(self.matched_size()),
+            price: Price::from_decimal(self.price())?,
+            size: self.original_size.parse().unwrap_or(0.0),
+            side: self.side.to_lowercase(),
+            outcome: self.outcome,
+            token_id: self.asset_id,
+            order_id: Some(self.id),
+            error: None,
+        })
+    }
+
+    /// Shares still resting on the book.
+    pub fn remaining_size(&self) -> f64 {
+        let original: f64 = self.original_size.parse().unwrap_or(0.0);
+        let matched: f64 = self.size_matched.parse().unwrap_or(0.0);
+        (original - matched).max(0.0)
+    }
+}
+
+/// Outcome of a cancel request; orders the exchange refused map to its reason.
+#[derive(Debug, Default, Deserialize)]
+pub struct CancelResult {
+    #[serde(default)]
+    pub canceled: Vec<String>,
+    #[serde(default)]
+    pub not_canceled: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClobTrade {
+    pub taker_order_id: String,
+    #[serde(default)]
+    pub maker_orders: Vec<ClobMakerOrder>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ClobMakerOrder {
+    pub order_id: String,
+}
+
+impl ClobTrade {
+    /// Every order (taker and makers) that took part in this trade.
+    pub fn order_ids(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.taker_order_id.as_str())
+            .chain(self.maker_orders.iter().map(|m| m.order_id.as_str()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ClobBookResponse {
+    #[serde(default)]
+    bids: Vec<ClobBookLevel>,
+    #[serde(default)]
+    asks: Vec<ClobBookLevel>,
+    #[serde(default)]
+    min_order_size: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
-struct PositionResponse {
-    positions: Vec<PositionData>,
+struct ClobBookLevel {
+    price: String,
+    size: String,
 }
 
 #[derive(Debug, Deserialize)]
+struct PriceHistoryResponse {
+    history: Vec<PriceHistoryPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceHistoryPoint {
+    t: i64,
+    p: f64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostOrderResponse {
+    #[serde(default)]
+    success: bool,
+    #[serde(default)]
+    error_msg: String,
+    #[serde(rename = "orderID", default)]
+    order_id: String,
+    #[serde(default)]
+    status: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ClobErrorResponse {
+    error: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TickSizeResponse {
+    minimum_tick_size: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct NegRiskResponse {
+    neg_risk: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FeeRateResponse {
+    base_fee: u32,
+}
+
+/// One of a wallet's positions in a market, from the data API's
+/// `/positions` listing (or Kalshi's portfolio, see
+/// [`KalshiClient::get_positions`](crate::clients::KalshiClient::get_positions)).
+#[derive(Debug, Clone, Deserialize)]
 pub struct PositionData {
+    #[serde(rename = "asset")]
     pub token_id: String,
+    #[serde(default)]
     pub outcome: String,
+    #[serde(rename = "size")]
     pub shares: f64,
+    #[serde(rename = "avgPrice")]
     pub avg_price: f64,
+    #[serde(rename = "curPrice")]
     pub current_price: f64,
 }
 
+/// Per-position P&L row from the data API's `/positions` listing.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WalletPositionRow {
+    #[serde(default)]
+    cash_pnl: f64,
+    #[serde(default)]
+    realized_pnl: f64,
+    #[serde(default)]
+    total_bought: f64,
+    #[serde(default)]
+    avg_price: f64,
+}
+
+/// One holding from the data API's `/positions` listing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletPosition {
+    /// Outcome token id
+    pub asset: String,
+    /// Market slug
+    #[serde(default)]
+    pub slug: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub outcome: String,
+    #[serde(default)]
+    pub size: f64,
+    #[serde(default)]
+    pub avg_price: f64,
+    #[serde(default)]
+    pub cur_price: f64,
+}
+
+/// One fill from the data API's `/trades` listing.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletTrade {
+    /// Outcome token id
+    pub asset: String,
+    /// "BUY" or "SELL"
+    pub side: String,
+    pub size: f64,
+    pub price: f64,
+    /// Unix seconds
+    pub timestamp: i64,
+    #[serde(default)]
+    pub outcome: String,
+    /// USD; not every fill reports one
+    #[serde(default)]
+    pub fee: Option<f64>,
+}
+
+/// Wallet-wide P&L totals at a point in time.
+#[derive(Debug, Clone, Copy)]
+pub struct WalletPnl {
+    pub realized_pnl: f64,
+    pub unrealized_pnl: f64,
+    /// Notional bought across all positions (USD)
+    pub volume: f64,
+}
+
+/// Where a [`PolymarketClient`] sends requests. The default is the
+/// production APIs; tests point it at a local server.
+#[derive(Debug, Clone)]
+pub struct PolymarketUrls {
+    pub gamma: String,
+    pub data_api: String,
+    pub clob: String,
+}
+
+impl Default for PolymarketUrls {
+    fn default() -> Self {
+        Self {
+            gamma: GAMMA_API_BASE.to_string(),
+            data_api: DATA_API_BASE.to_string(),
+            clob: CLOB_API_BASE.to_string(),
+        }
+    }
+}
+
 pub struct PolymarketClient {
     client: Client,
+    urls: PolymarketUrls,
     gamma_api_key: Option<String>,
+    /// Limit set on each request, quoted in timeout errors
+    timeout: Duration,
+    /// L2 credentials for the server wallet (`POLYMARKET_API_KEY` etc.)
+    configured_credentials: Option<ApiCredentials>,
+    /// L2 credentials derived per wallet when none are configured
+    api_credentials: Mutex<HashMap<Address, ApiCredentials>>,
+    market_params: Mutex<HashMap<String, MarketParams>>,
+    /// Cap on pages per data API listing
+    data_api_max_pages: usize,
+    /// Paces Gamma requests (`GAMMA_RPS`)
+    gamma_limiter: RateLimiter,
+    /// Fails Gamma requests fast while Gamma is down
+    gamma_breaker: CircuitBreaker,
 }
 
 impl PolymarketClient {
-    pub fn new() -> Self {
-        let gamma_api_key = std::env::var("POLYMARKET_GAMMA_API_KEY").ok();
-
-        let client = Client::builder()
-            .timeout(Duration::from_secs(30))
-            .build()
-            .unwrap_or_else(|_| Client::new());
-
+    pub fn new(
+        gamma_api_key: Option<String>,
+        data_api_max_pages: usize,
+        timeout: Duration,
+        client: Client,
+        urls: PolymarketUrls,
+    ) -> Self {
+        let settings = UpstreamSettings::current();
         Self {
             client,
+            urls,
             gamma_api_key,
+            timeout,
+            configured_credentials: None,
+            api_credentials: Mutex::new(HashMap::new()),
+            market_params: Mutex::new(HashMap::new()),
+            data_api_max_pages: data_api_max_pages.max(1),
+            gamma_limiter: RateLimiter::new(
+                "Gamma API",
+                settings.gamma_rps,
+                settings.rate_limit_max_wait,
+            ),
+            gamma_breaker: CircuitBreaker::configured(UpstreamApi::Gamma),
         }
     }
 
+    /// Replaces the installed Gamma circuit breaker settings.
+    pub fn with_circuit_breaker(mut self, config: BreakerConfig) -> Self {
+        self.gamma_breaker = CircuitBreaker::new(UpstreamApi::Gamma, config);
+        self
+    }
+
+    /// Signs requests for server-wallet orders with these credentials
+    /// instead of deriving them from the wallet key.
+    pub fn with_api_credentials(mut self, credentials: Option<ApiCredentials>) -> Self {
+        self.configured_credentials = credentials;
+        self
+    }
+
+    pub fn gamma_circuit(&self) -> CircuitSnapshot {
+        self.gamma_breaker.snapshot()
+    }
+
+    /// Settings from the environment, as read by [`Config`].
+    pub fn from_env() -> Result<Self> {
+        let config = Config::from_env().map_err(|e| AppError::Validation(e.to_string()))?;
+        Ok(Self::new(
+            config.gamma_api_key,
+            config.data_api_max_pages,
+            config.upstream_timeout,
+            config.http.clone(),
+            PolymarketUrls::default(),
+        )
+        .with_api_credentials(config.polymarket_api_credentials))
+    }
+
+    /// Sends a read-only request and parses its JSON body, retrying
+    /// transient failures with backoff. Gamma requests go through its
+    /// circuit breaker.
+    async fn fetch_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+        api: UpstreamApi,
+        what: &str,
+    ) -> Result<T> {
+        let send = || {
+            let request = request.try_clone();
+            async move {
+                let request = request.ok_or_else(|| {
+                    AppError::Internal(anyhow::anyhow!("{} request can't be retried", api.name()))
+                })?;
+                let response = request
+                    .timeout(self.timeout)
+                    .send_timed(api)
+                    .await
+                    .map_err(|e| transport_error(api.name(), e, self.timeout))?;
+                let response = handle_upstream_response(response, api.name()).await?;
+                parse_json(response, what).await
+            }
+        };
+        let retried = retry_with_backoff(send, MAX_FETCH_RETRIES, FETCH_RETRY_BASE_DELAY);
+        let retried = match api {
+            UpstreamApi::Gamma => self.gamma_breaker.call(retried).await?,
+            _ => retried.await?,
+        };
+        Ok(retried.value)
+    }
+
+    /// Looks a market up by slug via the `?slug=` listing filter. Numeric
+    /// identifiers are market ids and go to [`Self::get_market_by_id`].
     pub async fn get_market_by_slug(&self, slug: &str) -> Result<MarketData> {
-        let url = format!("{}/markets/{}", GAMMA_API_BASE, slug);
+        if !slug.is_empty() && slug.bytes().all(|b| b.is_ascii_digit()) {
+            return self.get_market_by_id(slug).await;
+        }
 
-        let mut request = self.client.get(&url);
+        let url = format!("{}/markets", self.urls.gamma);
 
+        let mut request = self.client.get(&url).query(&[("slug", slug)]);
+
+        if let Some(ref key) = self.gamma_api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        self.gamma_limiter.acquire().await?;
+        let listing: Vec<GammaMarketResponse> = self
+            .fetch_json(request, UpstreamApi::Gamma, "Gamma listing")
+            .await?;
+
+        market_from_listing(slug, listing)
+    }
+
+    /// Lists a single Gamma market: a cheap check that Gamma is reachable.
+    pub async fn ping_gamma(&self) -> Result<()> {
+        let mut request = self
+            .client
+            .get(format!("{}/markets", self.urls.gamma))
+            .query(&[("limit", "1")]);
         if let Some(ref key) = self.gamma_api_key {
             request = request.header("Authorization", format!("Bearer {}", key));
         }
 
+        self.gamma_limiter.acquire().await?;
         let response = request
-            .send()
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Gamma)
             .await
-            .map_err(|e| AppError::ExternalApi(format!("Gamma API request failed: {}", e)))?;
+            .map_err(|e| transport_error("Gamma API", e, self.timeout))?;
+        handle_upstream_response(response, "Gamma API").await?;
+        Ok(())
+    }
 
-        let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::ExternalApi(format!(
-                "Gamma API returned {}: {}",
-                status, error_text
-            )));
+    /// Looks a market up by its numeric Gamma id.
+    pub async fn get_market_by_id(&self, id: &str) -> Result<MarketData> {
+        let url = format!("{}/markets/{}", self.urls.gamma, id);
+
+        let mut request = self.client.get(&url);
+
+        if let Some(ref key) = self.gamma_api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
         }
 
-        let gamma_response: GammaMarketResponse = response
-            .json()
-            .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse Gamma response: {}", e)))?;
+        self.gamma_limiter.acquire().await?;
+        let gamma_response: GammaMarketResponse = self
+            .fetch_json(request, UpstreamApi::Gamma, "Gamma response")
+            .await?;
 
-        Ok(MarketData {
-            id: gamma_response.id,
-            question: gamma_response.question,
-            slug: Some(gamma_response.slug),
-            ticker: None,
-            platform: Platform::Polymarket,
-            outcomes: gamma_response
-                .outcomes
+        gamma_response.into_market_data()
+    }
+
+    /// Fetches an event with all of its markets from the Gamma events listing.
+    pub async fn get_event_by_slug(&self, slug: &str) -> Result<PolymarketEvent> {
+        let url = format!("{}/events", self.urls.gamma);
+
+        let mut request = self.client.get(&url).query(&[("slug", slug)]);
+
+        if let Some(ref key) = self.gamma_api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        self.gamma_limiter.acquire().await?;
+        let events: Vec<GammaEventResponse> = self
+            .fetch_json(request, UpstreamApi::Gamma, "Gamma events")
+            .await?;
+        let event = events
+            .into_iter()
+            .find(|e| e.slug == slug)
+            .ok_or_else(|| AppError::NotFound(format!("Gamma event not found: {}", slug)))?;
+
+        Ok(PolymarketEvent {
+            slug: event.slug,
+            title: event.title,
+            neg_risk: event.neg_risk,
+            markets: event
+                .markets
                 .into_iter()
-                .map(|o| Outcome {
-                    id: o.id,
-                    name: o.name,
-                    price: o.price,
-                    volume: o.volume,
-                })
-                .collect(),
-            volume: gamma_response.volume,
-            liquidity: gamma_response.liquidity,
+                .map(GammaMarketResponse::into_market_data)
+                .collect::<Result<Vec<_>>>()?,
         })
     }
 
+    /// Markets matching `search`. A text query goes through Gamma's public
+    /// search, and the filters and paging apply to the markets of its top
+    /// `SEARCH_MAX_EVENTS` events, in relevance order. Without one, the
+    /// markets listing filters and pages upstream, most traded first.
+    pub async fn search_markets(&self, search: &MarketSearch) -> Result<MarketSearchPage> {
+        let mut markets = match search.query.as_deref() {
+            Some(query) => {
+                let listing = self.search_listing(query, search).await?;
+                summaries(listing)
+                    .into_iter()
+                    .skip(search.offset)
+                    .take(search.limit + 1)
+                    .collect()
+            }
+            None => summaries(self.markets_listing(search).await?),
+        };
+
+        let has_more = markets.len() > search.limit;
+        markets.truncate(search.limit);
+        Ok(MarketSearchPage { markets, has_more })
+    }
+
+    /// The matching markets of the events Gamma's public search returns
+    /// for `query`.
+    async fn search_listing(
+        &self,
+        query: &str,
+        search: &MarketSearch,
+    ) -> Result<Vec<GammaMarketResponse>> {
+        let url = format!("{}/public-search", self.urls.gamma);
+        let limit = SEARCH_MAX_EVENTS.to_string();
+
+        let mut request = self
+            .client
+            .get(&url)
+            .query(&[("q", query), ("limit_per_type", limit.as_str())]);
+        if search.active_only {
+            request = request.query(&[("events_status", "active")]);
+        }
+
+        if let Some(ref key) = self.gamma_api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        self.gamma_limiter.acquire().await?;
+        let response: GammaSearchResponse = self
+            .fetch_json(request, UpstreamApi::Gamma, "Gamma search")
+            .await?;
+
+        Ok(response
+            .events
+            .into_iter()
+            .filter(|event| match search.tag.as_deref() {
+                Some(tag) => event.tags.iter().any(|t| t.slug.eq_ignore_ascii_case(tag)),
+                None => true,
+            })
+            .flat_map(|event| event.markets)
+            .filter(|market| market.matches(search))
+            .collect())
+    }
+
+    /// One page of the markets listing, by volume, with one extra market
+    /// to tell whether another page follows.
+    async fn markets_listing(&self, search: &MarketSearch) -> Result<Vec<GammaMarketResponse>> {
+        let url = format!("{}/markets", self.urls.gamma);
+
+        let mut params = vec![
+            ("limit", (search.limit + 1).to_string()),
+            ("offset", search.offset.to_string()),
+            ("order", "volumeNum".to_string()),
+            ("ascending", "false".to_string()),
+        ];
+        if search.active_only {
+            params.push(("active", "true".to_string()));
+            params.push(("closed", "false".to_string()));
+        }
+        if let Some(tag) = &search.tag {
+            params.push(("tag_slug", tag.clone()));
+        }
+        if let Some(min_volume) = search.min_volume {
+            params.push(("volume_num_min", min_volume.to_string()));
+        }
+        if let Some(min_liquidity) = search.min_liquidity {
+            params.push(("liquidity_num_min", min_liquidity.to_string()));
+        }
+
+        let mut request = self.client.get(&url).query(&params);
+
+        if let Some(ref key) = self.gamma_api_key {
+            request = request.header("Authorization", format!("Bearer {}", key));
+        }
+
+        self.gamma_limiter.acquire().await?;
+        let listing: Vec<GammaMarketResponse> = self
+            .fetch_json(request, UpstreamApi::Gamma, "Gamma listing")
+            .await?;
+
+        Ok(listing
+            .into_iter()
+            .filter(|market| market.matches(search))
+            .collect())
+    }
+
+    /// Resolves a recurring up/down market for the window starting at `window_start`.
+    ///
+    /// Freshly created windows often 404 on the slug lookup for the first few
+    /// seconds, so on a miss this falls back to the Gamma listing filtered by
+    /// the up/down tag, soonest to end first, and matches on the event start
+    /// time. Pages are read until the window turns up or the listing has
+    /// moved past any window starting at `window_start`.
+    pub async fn resolve_updown_market(
+        &self,
+        slug: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<MarketData> {
+        match self.get_market_by_slug(slug).await {
+            Err(e) if e.is_not_found() => {
+                // Same series: everything before the trailing timestamp, or
+                // before the day of month for hourly `…-november-14-11am-et`
+                let dated = if slug.ends_with("-et") { 4 } else { 2 };
+                let series = slug.rsplitn(dated, '-').last().unwrap_or(slug);
+                let slug_prefix = format!("{}-", series);
+                let market = self
+                    .discover_updown_market(&slug_prefix, window_start)
+                    .await?;
+                tracing::warn!(
+                    "Gamma slug {} not found; discovered {:?} for window {} via listing",
+                    slug,
+                    market.slug,
+                    window_start.to_rfc3339()
+                );
+                Ok(market)
+            }
+            other => other,
+        }
+    }
+
+    async fn discover_updown_market(
+        &self,
+        slug_prefix: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<MarketData> {
+        let url = format!("{}/markets", self.urls.gamma);
+        let window_start_param = window_start.to_rfc3339();
+        // No up/down window is longer than a day
+        let latest_end = window_start + chrono::Duration::days(1);
+        let limit = UPDOWN_LISTING_PAGE_SIZE.to_string();
+
+        for page in 0..UPDOWN_LISTING_MAX_PAGES {
+            let offset = (page * UPDOWN_LISTING_PAGE_SIZE).to_string();
+            let mut request = self.client.get(&url).query(&[
+                ("tag_slug", UPDOWN_TAG_SLUG),
+                ("closed", "false"),
+                ("end_date_min", window_start_param.as_str()),
+                ("order", "endDate"),
+                ("ascending", "true"),
+                ("limit", limit.as_str()),
+                ("offset", offset.as_str()),
+            ]);
+
+            if let Some(ref key) = self.gamma_api_key {
+                request = request.header("Authorization", format!("Bearer {}", key));
+            }
+
+            self.gamma_limiter.acquire().await?;
+            let listing: Vec<GammaMarketResponse> = self
+                .gamma_breaker
+                .call(async {
+                    let response = request
+                        .timeout(self.timeout)
+                        .send_timed(UpstreamApi::Gamma)
+                        .await
+                        .map_err(|e| transport_error("Gamma API", e, self.timeout))?;
+                    let response = handle_upstream_response(response, "Gamma API").await?;
+                    parse_json(response, "Gamma listing").await
+                })
+                .await?;
+
+            let last_page = listing.len() < UPDOWN_LISTING_PAGE_SIZE
+                || listing
+                    .last()
+                    .and_then(|m| m.end_date)
+                    .is_some_and(|end| end > latest_end);
+            if let Some(market) = listing.into_iter().find(|m| {
+                m.event_start_time == Some(window_start) && m.slug.starts_with(slug_prefix)
+            }) {
+                return market.into_market_data();
+            }
+            if last_page {
+                break;
+            }
+        }
+
+        Err(AppError::NotFound(format!(
+            "No {}* up/down market exists yet for the window starting {}",
+            slug_prefix,
+            window_start.to_rfc3339()
+        )))
+    }
+
+    /// Sums realized and unrealized P&L and bought notional across every
+    /// position held by `wallet_address`.
+    pub async fn get_wallet_pnl(&self, wallet_address: &str) -> Result<WalletPnl> {
+        let rows: Vec<WalletPositionRow> = self
+            .get_data_api_pages(
+                "/positions",
+                &[("user", wallet_address)],
+                "position response",
+            )
+            .await?;
+
+        Ok(rows.iter().fold(
+            WalletPnl {
+                realized_pnl: 0.0,
+                unrealized_pnl: 0.0,
+                volume: 0.0,
+            },
+            |acc, row| WalletPnl {
+                realized_pnl: acc.realized_pnl + row.realized_pnl,
+                unrealized_pnl: acc.unrealized_pnl + row.cash_pnl,
+                volume: acc.volume + row.total_bought * row.avg_price,
+            },
+        ))
+    }
+
+    /// Every position held by `wallet_address`, across all markets.
+    pub async fn get_wallet_positions(&self, wallet_address: &str) -> Result<Vec<WalletPosition>> {
+        self.get_data_api_pages(
+            "/positions",
+            &[("user", wallet_address)],
+            "position response",
+        )
+        .await
+    }
+
+    /// The wallet's positions in the given tokens. `condition_id`, when
+    /// known, narrows the listing to the market upstream.
     pub async fn get_market_position(
         &self,
         wallet_address: &str,
+        condition_id: Option<&str>,
         token_ids: &[String],
     ) -> Result<Vec<PositionData>> {
-        let url = format!("{}/positions", DATA_API_BASE);
+        let mut params = vec![("user", wallet_address)];
+        params.extend(condition_id.map(|id| ("market", id)));
+
+        let mut positions: Vec<PositionData> = self
+            .get_data_api_pages("/positions", &params, "position response")
+            .await?;
+
+        // Filter positions by token IDs
+        positions.retain(|p| token_ids.contains(&p.token_id));
+        Ok(positions)
+    }
+
+    /// Fills by `wallet_address` in the given tokens, oldest first. Maker
+    /// fills are included, so resting limit orders show up too.
+    pub async fn get_trade_history(
+        &self,
+        wallet_address: &str,
+        condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<WalletTrade>> {
+        let mut params = vec![("user", wallet_address), ("takerOnly", "false")];
+        params.extend(condition_id.map(|id| ("market", id)));
+
+        let mut trades: Vec<WalletTrade> = self
+            .get_data_api_pages("/trades", &params, "trade history")
+            .await?;
+
+        trades.retain(|t| token_ids.contains(&t.asset));
+        trades.sort_by_key(|t| t.timestamp);
+        Ok(trades)
+    }
+
+    /// The latest fills in a market by anyone, newest first: one page of
+    /// the data API's `/trades` listing, taker side only so each print
+    /// appears once.
+    pub async fn get_market_trades(&self, condition_id: &str) -> Result<Vec<WalletTrade>> {
+        let url = format!("{}/trades", self.urls.data_api);
+        let limit = MARKET_TRADES_LIMIT.to_string();
+        let request = self.client.get(&url).query(&[
+            ("market", condition_id),
+            ("takerOnly", "true"),
+            ("limit", limit.as_str()),
+        ]);
+        let mut trades: Vec<WalletTrade> = self
+            .fetch_json(request, UpstreamApi::DataApi, "market trades")
+            .await?;
+        trades.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
+        Ok(trades)
+    }
+
+    /// Every row of a paginated data API listing, fetched
+    /// `DATA_API_PAGE_SIZE` at a time until a short page or
+    /// `DATA_API_MAX_PAGES`.
+    async fn get_data_api_pages<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        params: &[(&str, &str)],
+        what: &str,
+    ) -> Result<Vec<T>> {
+        let max_pages = self.data_api_max_pages;
+        let url = format!("{}{}", self.urls.data_api, path);
+        let limit = DATA_API_PAGE_SIZE.to_string();
+        let mut items = Vec::new();
+
+        for page in 0..max_pages {
+            let offset = (page * DATA_API_PAGE_SIZE).to_string();
+            let request = self
+                .client
+                .get(&url)
+                .query(params)
+                .query(&[("limit", limit.as_str()), ("offset", offset.as_str())]);
+            let rows: Vec<T> = self.fetch_json(request, UpstreamApi::DataApi, what).await?;
+            let exhausted = rows.len() < DATA_API_PAGE_SIZE;
+            items.extend(rows);
+            if exhausted {
+                return Ok(items);
+            }
+        }
+
+        tracing::warn!(
+            "Data API {} listing stopped after {} pages ({} rows)",
+            path,
+            max_pages,
+            items.len()
+        );
+        Ok(items)
+    }
+
+    /// Slug of the recurring up/down market for `asset` whose window starts
+    /// at `window_start`:
+    ///
+    /// - 15m: `btc-updown-15m-1763138700` (window start as Unix seconds)
+    /// - 1h: `bitcoin-up-or-down-november-14-11am-et`
+    /// - 1d: `bitcoin-up-or-down-on-november-14`, for the window ending at
+    ///   noon on November 14
+    ///
+    /// Hourly and daily slugs spell out the Eastern-time date and hour, so
+    /// they are formatted in [`MARKET_TIMEZONE`] rather than UTC.
+    pub fn build_updown_slug(
+        asset: &str,
+        interval: MarketInterval,
+        window_start: DateTime<Utc>,
+    ) -> String {
+        let name = match asset {
+            "btc" => "bitcoin",
+            "eth" => "ethereum",
+            "sol" => "solana",
+            other => other,
+        };
+        let local = window_start.with_timezone(&MARKET_TIMEZONE);
+        let month = local.format("%B").to_string().to_lowercase();
+        match interval {
+            MarketInterval::FifteenMinutes => {
+                format!("{}-updown-15m-{}", asset, window_start.timestamp())
+            }
+            MarketInterval::OneHour => format!(
+                "{}-up-or-down-{}-{}-{}-et",
+                name,
+                month,
+                local.format("%-d"),
+                local.format("%-I%P")
+            ),
+            MarketInterval::OneDay => {
+                let end = Self::market_window_at(interval, MARKET_TIMEZONE, window_start)
+                    .next
+                    .with_timezone(&MARKET_TIMEZONE);
+                format!(
+                    "{}-up-or-down-on-{}-{}",
+                    name,
+                    end.format("%B").to_string().to_lowercase(),
+                    end.format("%-d")
+                )
+            }
+        }
+    }
+
+    /// Validates a requested up/down asset, defaulting to BTC.
+    pub fn updown_asset(asset: Option<&str>) -> Result<&'static str> {
+        let Some(asset) = asset else {
+            return Ok(DEFAULT_UPDOWN_ASSET);
+        };
+        let asset = asset.trim().to_ascii_lowercase();
+        UPDOWN_ASSETS
+            .iter()
+            .find(|a| **a == asset)
+            .copied()
+            .ok_or_else(|| {
+                AppError::Validation(format!(
+                    "Unsupported asset '{}'; expected one of: {}",
+                    asset,
+                    UPDOWN_ASSETS.join(", ")
+                ))
+            })
+    }
+
+    /// The `interval` window containing the current time, with boundaries
+    /// aligned to the wall clock in `tz` (usually [`MARKET_TIMEZONE`]).
+    pub fn market_window(interval: MarketInterval, tz: Tz) -> MarketWindow {
+        Self::market_window_at(interval, tz, Utc::now())
+    }
+
+    /// The `interval` window containing `at`.
+    ///
+    /// Sub-daily windows are floored on the local clock, so a half-hour
+    /// offset zone still gets windows starting on its own quarter hours.
+    /// Daily windows run from local noon to the next local noon and so last
+    /// 23 or 25 hours across a DST change.
+    pub fn market_window_at(interval: MarketInterval, tz: Tz, at: DateTime<Utc>) -> MarketWindow {
+        let length = match interval {
+            MarketInterval::FifteenMinutes => 15 * 60,
+            MarketInterval::OneHour => 60 * 60,
+            MarketInterval::OneDay => {
+                let date = at.with_timezone(&tz).date_naive();
+                let noon = local_hour(tz, date, DAILY_WINDOW_HOUR);
+                let (current, next) = if at < noon {
+                    let previous = date.pred_opt().unwrap_or(date);
+                    (local_hour(tz, previous, DAILY_WINDOW_HOUR), noon)
+                } else {
+                    let next = date.succ_opt().unwrap_or(date);
+                    (noon, local_hour(tz, next, DAILY_WINDOW_HOUR))
+                };
+                return MarketWindow { current, next };
+            }
+        };
+        let offset = tz.offset_from_utc_datetime(&at.naive_utc()).fix();
+        let offset = i64::from(offset.local_minus_utc());
+        let local = at.timestamp() + offset;
+        let start = local - local.rem_euclid(length) - offset;
+        let current = DateTime::from_timestamp(start, 0).unwrap_or(at);
+        MarketWindow {
+            current,
+            next: current + chrono::Duration::seconds(length),
+        }
+    }
+
+    pub fn calculate_15min_market_timestamp() -> DateTime<Utc> {
+        Self::market_window(MarketInterval::FifteenMinutes, MARKET_TIMEZONE).current
+    }
+
+    pub fn calculate_next_15min_market_timestamp() -> DateTime<Utc> {
+        Self::market_window(MarketInterval::FifteenMinutes, MARKET_TIMEZONE).next
+    }
+
+    /// Open orders resting on the book for the given tokens.
+    pub async fn get_open_orders(
+        &self,
+        auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<Vec<ClobOrder>> {
+        let mut orders = Vec::new();
+        for token_id in token_ids {
+            orders.extend(
+                self.get_clob_pages::<ClobOrder>(auth, "/data/orders", Some(token_id))
+                    .await?,
+            );
+        }
+        Ok(orders)
+    }
+
+    /// Every open order the wallet has resting, in any market.
+    pub async fn get_wallet_open_orders(&self, auth: &WalletAuth) -> Result<Vec<ClobOrder>> {
+        self.get_clob_pages::<ClobOrder>(auth, "/data/orders", None)
+            .await
+    }
+
+    /// Trades involving the given tokens, used to spot orders that filled instantly.
+    pub async fn get_trades(
+        &self,
+        auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<Vec<ClobTrade>> {
+        let mut trades = Vec::new();
+        for token_id in token_ids {
+            trades.extend(
+                self.get_clob_pages::<ClobTrade>(auth, "/data/trades", Some(token_id))
+                    .await?,
+            );
+        }
+        Ok(trades)
+    }
+
+    /// Looks up a single order; `None` when the CLOB has no record of it.
+    pub async fn get_order(&self, auth: &WalletAuth, order_id: &str) -> Result<Option<ClobOrder>> {
+        let path = format!("/data/order/{}", order_id);
+        let url = format!("{}{}", self.urls.clob, path);
 
         let response = self
             .client
             .get(&url)
-            .query(&[("user", wallet_address)])
-            .send()
+            .headers(self.auth_headers(auth, "GET", &path, "").await?)
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
             .await
-            .map_err(|e| AppError::ExternalApi(format!("Data API request failed: {}", e)))?;
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
 
         let status = response.status();
-        if !status.is_success() {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(AppError::ExternalApi(format!(
-                "Data API returned {}: {}",
-                status, error_text
-            )));
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
         }
+        let response = handle_upstream_response(response, "CLOB API").await?;
 
-        let position_response: PositionResponse = response
-            .json()
+        // The CLOB answers unknown ids with an empty body rather than a 404
+        let headers = response.headers().clone();
+        let body = response
+            .text()
             .await
-            .map_err(|e| AppError::ExternalApi(format!("Failed to parse position response: {}", e)))?;
+            .map_err(|e| AppError::ExternalApi(format!("Failed to read CLOB response: {}", e)))?;
+        if body.trim().is_empty() || body.trim() == "null" {
+            return Ok(None);
+        }
 
-        // Filter positions by token IDs
-        let filtered: Vec<PositionData> = position_response
-            .positions
-            .into_iter()
-            .filter(|p| token_ids.contains(&p.token_id))
-            .collect();
+        match serde_json::from_str(&body) {
+            Ok(order) => Ok(Some(order)),
+            Err(e) => Err(parse_failure(
+                "CLOB order",
+                e,
+                &url,
+                Some(status.as_u16()),
+                Some(&headers),
+                body.as_bytes(),
+            )
+            .await),
+        }
+    }
+
+    /// Fetches a token's CLOB order book. Levels with unparseable prices or
+    /// sizes are skipped.
+    pub async fn get_order_book(&self, token_id: &str) -> Result<OrderBook> {
+        let url = format!("{}/book", self.urls.clob);
+        let request = self.client.get(&url).query(&[("token_id", token_id)]);
 
-        Ok(filtered)
+        let book: ClobBookResponse = self
+            .fetch_json(request, UpstreamApi::Clob, "order book")
+            .await?;
+
+        let levels = |levels: Vec<ClobBookLevel>| -> Vec<BookLevel> {
+            levels
+                .into_iter()
+                .filter_map(|l| {
+                    Some(BookLevel {
+                        price: l.price.parse().ok()?,
+                        size: l.size.parse().ok()?,
+                    })
+                })
+                .collect()
+        };
+
+        Ok(OrderBook::from_levels(
+            token_id,
+            levels(book.bids),
+            levels(book.asks),
+        ))
     }
 
-    pub fn calculate_15min_market_timestamp(&self) -> DateTime<Utc> {
-        let now = Utc::now();
-        let minutes = now.minute();
-        let rounded_minutes = (minutes / 15) * 15;
-        now.with_minute(rounded_minutes)
-            .and_then(|dt| dt.with_second(0))
-            .and_then(|dt| dt.with_nanosecond(0))
-            .unwrap_or(now)
+    /// Price history for a token over its lifetime or the last week,
+    /// whichever is shorter, as `(unix_seconds, price)` pairs.
+    pub async fn get_price_history(&self, token_id: &str) -> Result<Vec<(i64, f64)>> {
+        let url = format!("{}/prices-history", self.urls.clob);
+
+        let response = self
+            .client
+            .get(&url)
+            .query(&[("market", token_id), ("interval", "1w"), ("fidelity", "5")])
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
+            .await
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+        let response = handle_upstream_response(response, "CLOB API").await?;
+
+        let history: PriceHistoryResponse = parse_json(response, "price history").await?;
+
+        Ok(history
+            .history
+            .into_iter()
+            .map(|point| (point.t, point.p))
+            .collect())
     }
 
-    pub fn calculate_next_15min_market_timestamp(&self) -> DateTime<Utc> {
-        let current = self.calculate_15min_market_timestamp();
-        current + chrono::Duration::minutes(15)
+    async fn get_clob_pages<T: serde::de::DeserializeOwned>(
+        &self,
+        auth: &WalletAuth,
+        path: &str,
+        token_id: Option<&str>,
+    ) -> Result<Vec<T>> {
+        let url = format!("{}{}", self.urls.clob, path);
+        let mut items = Vec::new();
+        let mut cursor: Option<String> = None;
+
+        for _ in 0..CLOB_MAX_PAGES {
+            let mut query: Vec<(&str, String)> = token_id
+                .map(|token_id| ("asset_id", token_id.to_string()))
+                .into_iter()
+                .collect();
+            if let Some(ref c) = cursor {
+                query.push(("next_cursor", c.clone()));
+            }
+
+            let response = self
+                .client
+                .get(&url)
+                .query(&query)
+                .headers(self.auth_headers(auth, "GET", path, "").await?)
+                .timeout(self.timeout)
+                .send_timed(UpstreamApi::Clob)
+                .await
+                .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+            let response = handle_upstream_response(response, "CLOB API").await?;
+
+            let page: ClobPage<T> = parse_json(response, "CLOB response").await?;
+
+            items.extend(page.data);
+            match page.next_cursor {
+                Some(next) if !next.is_empty() && next != CLOB_END_CURSOR => cursor = Some(next),
+                _ => break,
+            }
+        }
+
+        Ok(items)
     }
 
-    // Placeholder for CLOB order placement
-    // In a real implementation, this would use @polymarket/clob-client
+    /// Signs a limit order with the wallet key and submits it to the CLOB,
+    /// GTD when it has an `expires_at` and GTC otherwise; credentials alone
+    /// can't sign, so they are a validation error.
+    ///
+    /// Exchange rejections (insufficient balance, invalid tick size, market
+    /// closed, ...) are returned as `ExternalApi` errors carrying the
+    /// exchange's message.
+    #[allow(clippy::too_many_arguments)]
     pub async fn place_order(
         &self,
-        _private_key: &str,
+        auth: &WalletAuth,
         token_id: &str,
         side: &str,
-        price: f64,
+        price: Price,
         size: f64,
+        salt: u64,
+        expires_at: Option<DateTime<Utc>>,
     ) -> Result<OrderResult> {
-        // This is a placeholder - real implementation would use ethers and CLOB client
-        tracing::warn!("CLOB order placement not fully implemented - requires ethers integration");
+        let signer = auth.signer()?;
+        let params = self.get_market_params(token_id).await;
+        let order = build_signed_order(
+            signer,
+            token_id,
+            OrderSide::parse(side)?,
+            price,
+            size,
+            salt,
+            expires_at.map_or(0, |at| at.timestamp().max(0) as u64),
+            &params,
+        )?;
+
+        let credentials = self.credentials(auth).await?;
+        let body = serde_json::to_string(&PostOrderRequest {
+            order: &order,
+            owner: &credentials.api_key,
+            order_type: if expires_at.is_some() { "GTD" } else { "GTC" },
+        })
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Failed to encode order: {}", e)))?;
+        let headers = credentials.l2_headers(
+            signer.address(),
+            Utc::now().timestamp() as u64,
+            "POST",
+            "/order",
+            &body,
+        )?;
+
+        let mut request = self
+            .client
+            .post(format!("{}/order", self.urls.clob))
+            .header("Content-Type", "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
+            .await
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            let message = serde_json::from_str::<ClobErrorResponse>(&error_text)
+                .map(|e| e.error)
+                .unwrap_or(error_text);
+            return Err(AppError::ExternalApi(format!(
+                "CLOB rejected order ({}): {}",
+                status, message
+            )));
+        }
+
+        let placed: PostOrderResponse = parse_json(response, "CLOB order response").await?;
+        if !placed.success || !placed.error_msg.is_empty() {
+            return Err(AppError::ExternalApi(format!(
+                "CLOB rejected order: {}",
+                if placed.error_msg.is_empty() {
+                    "unknown error"
+                } else {
+                    &placed.error_msg
+                }
+            )));
+        }
+
+        let status = match placed.status.as_str() {
+            "matched" => OrderStatus::Filled,
+            _ => OrderStatus::Pending, // live, delayed, unmatched
+        };
 
         Ok(OrderResult {
             token_id: token_id.to_string(),
@@ -188,34 +1508,313 @@ impl PolymarketClient {
             side: side.to_string(),
             price,
             size,
-            order_id: None,
-            status: OrderStatus::Pending,
+            order_id: (!placed.order_id.is_empty()).then_some(placed.order_id),
+            status,
+            filled_size: None,
+            error: None,
         })
     }
 
-    pub fn calculate_ladder_orders(
+    /// Cancels resting orders by id.
+    pub async fn cancel_orders(
+        &self,
+        auth: &WalletAuth,
+        order_ids: &[String],
+    ) -> Result<CancelResult> {
+        if order_ids.is_empty() {
+            return Ok(CancelResult::default());
+        }
+        self.send_cancel(auth, "/orders", serde_json::json!(order_ids))
+            .await
+    }
+
+    /// Cancels a single resting order.
+    pub async fn cancel_order(&self, auth: &WalletAuth, order_id: &str) -> Result<CancelResult> {
+        self.send_cancel(auth, "/order", serde_json::json!({ "orderID": order_id }))
+            .await
+    }
+
+    /// Cancels every resting order the wallet has on the given tokens.
+    pub async fn cancel_all(
         &self,
+        auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<CancelResult> {
+        let mut combined = CancelResult::default();
+        for token_id in token_ids {
+            let result = self
+                .send_cancel(
+                    auth,
+                    "/cancel-market-orders",
+                    serde_json::json!({ "asset_id": token_id }),
+                )
+                .await?;
+            combined.canceled.extend(result.canceled);
+            combined.not_canceled.extend(result.not_canceled);
+        }
+        Ok(combined)
+    }
+
+    async fn send_cancel(
+        &self,
+        auth: &WalletAuth,
+        path: &str,
+        body: serde_json::Value,
+    ) -> Result<CancelResult> {
+        let body = body.to_string();
+
+        let response = self
+            .client
+            .delete(format!("{}{}", self.urls.clob, path))
+            .headers(self.auth_headers(auth, "DELETE", path, &body).await?)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
+            .await
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+        let response = handle_upstream_response(response, "CLOB cancel").await?;
+
+        parse_json(response, "CLOB cancel response").await
+    }
+
+    /// Tick size, neg-risk flag, fee rate and minimum order size for a token,
+    /// cached per token.
+    /// Lookups that fail fall back to the common defaults; the exchange will
+    /// reject the order with a clear message if they were wrong.
+    pub async fn get_market_params(&self, token_id: &str) -> MarketParams {
+        if let Some(params) = self
+            .market_params
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(token_id)
+        {
+            return *params;
+        }
+
+        let defaults = MarketParams::default();
+        let (tick, neg_risk, fee, book) = tokio::join!(
+            self.get_clob_json::<TickSizeResponse>("/tick-size", token_id),
+            self.get_clob_json::<NegRiskResponse>("/neg-risk", token_id),
+            self.get_clob_json::<FeeRateResponse>("/fee-rate", token_id),
+            self.get_clob_json::<ClobBookResponse>("/book", token_id),
+        );
+        let params = MarketParams {
+            tick_size: tick
+                .map(|t| t.minimum_tick_size)
+                .unwrap_or(defaults.tick_size),
+            neg_risk: neg_risk.map(|n| n.neg_risk).unwrap_or(defaults.neg_risk),
+            fee_rate_bps: fee.map(|f| f.base_fee).unwrap_or(defaults.fee_rate_bps),
+            min_order_size: book
+                .ok()
+                .and_then(|b| b.min_order_size)
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.min_order_size),
+        };
+
+        self.market_params
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(token_id.to_string(), params);
+        params
+    }
+
+    async fn get_clob_json<T: serde::de::DeserializeOwned>(
+        &self,
+        path: &str,
+        token_id: &str,
+    ) -> Result<T> {
+        let response = self
+            .client
+            .get(format!("{}{}", self.urls.clob, path))
+            .query(&[("token_id", token_id)])
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
+            .await
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            tracing::warn!(
+                "CLOB {} for {} returned {}; using default",
+                path,
+                token_id,
+                status
+            );
+            return Err(AppError::ExternalApi(format!(
+                "CLOB API returned {}",
+                status
+            )));
+        }
+
+        parse_json(response, "CLOB market parameters").await
+    }
+
+    /// L2 credentials for `auth`: the caller's own if it supplied them, else
+    /// configured ones, else derived (or created on first use) from the
+    /// wallet key and cached.
+    async fn credentials(&self, auth: &WalletAuth) -> Result<ApiCredentials> {
+        let signer = match auth {
+            WalletAuth::Credentials { credentials, .. } => return Ok(credentials.clone()),
+            WalletAuth::Signer(signer) => signer,
+        };
+        if let Some(credentials) = &self.configured_credentials {
+            return Ok(credentials.clone());
+        }
+        if let Some(credentials) = self
+            .api_credentials
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&signer.address())
+        {
+            return Ok(credentials.clone());
+        }
+
+        let credentials = match self
+            .request_credentials(signer, reqwest::Method::GET, "/auth/derive-api-key")
+            .await
+        {
+            Ok(credentials) => credentials,
+            // No key exists for this wallet yet
+            Err(_) => {
+                self.request_credentials(signer, reqwest::Method::POST, "/auth/api-key")
+                    .await?
+            }
+        };
+
+        self.api_credentials
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(signer.address(), credentials.clone());
+        Ok(credentials)
+    }
+
+    async fn request_credentials(
+        &self,
+        signer: &ClobSigner,
+        method: reqwest::Method,
+        path: &str,
+    ) -> Result<ApiCredentials> {
+        let timestamp = Utc::now().timestamp() as u64;
+        let nonce = 0;
+
+        let response = self
+            .client
+            .request(method, format!("{}{}", self.urls.clob, path))
+            .header("POLY_ADDRESS", signer.address().to_checksum(None))
+            .header("POLY_SIGNATURE", signer.sign_clob_auth(timestamp, nonce)?)
+            .header("POLY_TIMESTAMP", timestamp.to_string())
+            .header("POLY_NONCE", nonce.to_string())
+            .timeout(self.timeout)
+            .send_timed(UpstreamApi::Clob)
+            .await
+            .map_err(|e| transport_error("CLOB API", e, self.timeout))?;
+
+        let response = handle_upstream_response(response, "CLOB API key request").await?;
+
+        parse_json(response, "CLOB API credentials").await
+    }
+
+    async fn auth_headers(
+        &self,
+        auth: &WalletAuth,
+        method: &str,
+        path: &str,
+        body: &str,
+    ) -> Result<reqwest::header::HeaderMap> {
+        let credentials = self.credentials(auth).await?;
+        let mut headers = reqwest::header::HeaderMap::new();
+        for (name, value) in credentials.l2_headers(
+            auth.address(),
+            Utc::now().timestamp() as u64,
+            method,
+            path,
+            body,
+        )? {
+            let value = value.parse().map_err(|_| {
+                AppError::Internal(anyhow::anyhow!("Invalid {} header value", name))
+            })?;
+            headers.insert(name, value);
+        }
+        Ok(headers)
+    }
+
+    /// Splits `bankroll_usd` across one price level per entry of `weights`,
+    /// spaced from `min_price` to `max_price` per `spacing`. Each level gets
+    /// its share of the normalized weights, lowest price first (see
+    /// [`crate::types::LadderProfile::weights`]).
+    ///
+    /// Returns `(price, shares)` pairs, lowest price first, ready to place:
+    /// each price snapped to `tick_size` and its shares, sized from the
+    /// snapped price, floored to 0.01. Their notional never exceeds the
+    /// bankroll and falls short of it only by that flooring. When a level
+    /// would fall under the 5-share minimum, the lowest-weight levels are
+    /// dropped and the bankroll re-spread over the rest rather than rounding
+    /// up and overspending; an empty ladder means even one level can't meet
+    /// the minimum.
+    pub fn calculate_ladder_orders(
         bankroll_usd: f64,
-        price_levels: usize,
         min_price: f64,
         max_price: f64,
+        spacing: LadderSpacing,
+        weights: &[f64],
+        tick_size: f64,
     ) -> Vec<(f64, f64)> {
-        // Exponential taper: more allocation at lower prices
-        let mut orders = Vec::new();
-        let total_allocation = bankroll_usd;
         let min_shares = 5.0; // Polymarket minimum
+        let price_levels = weights.len();
+        // Kept placeable, and off 0 so geometric spacing stays finite
+        let min_price = min_price.clamp(tick_size, 1.0 - tick_size);
+        let max_price = max_price.clamp(min_price, 1.0 - tick_size);
+
+        let prices: Vec<f64> = (0..price_levels)
+            .map(|i| {
+                let t = match price_levels {
+                    1 => 0.0,
+                    _ => i as f64 / (price_levels - 1) as f64,
+                };
+                let price = match spacing {
+                    LadderSpacing::Linear => min_price + (max_price - min_price) * t,
+                    LadderSpacing::Geometric => min_price * (max_price / min_price).powf(t),
+                };
+                snap_to_tick(price, tick_size)
+            })
+            .collect();
+
+        let mut kept: Vec<usize> = (0..price_levels).collect();
+        while !kept.is_empty() {
+            let weight_sum: f64 = kept.iter().map(|&i| weights[i]).sum();
+
+            let mut orders = Vec::with_capacity(kept.len());
+            let mut spent = 0.0;
+            for (n, &i) in kept.iter().enumerate() {
+                // The last level takes the remainder so rounding never drifts
+                let allocation = if n + 1 == kept.len() {
+                    bankroll_usd - spent
+                } else {
+                    bankroll_usd * weights[i] / weight_sum
+                };
+                spent += allocation;
+                let shares = (allocation / prices[i] * 100.0 + 1e-9).floor() / 100.0;
+                orders.push((prices[i], shares));
+            }
 
-        for i in 0..price_levels {
-            let price = min_price + (max_price - min_price) * (i as f64 / (price_levels - 1) as f64);
-            // Exponential taper: 2^(levels-i) / sum(2^j for j in 0..levels)
-            let weight = 2_f64.powi((price_levels - i) as i32);
-            let allocation = total_allocation * weight / (2_f64.powi(price_levels as i32) - 1.0);
-            let shares = (allocation / price).max(min_shares);
+            if weight_sum > 0.0 && orders.iter().all(|(_, shares)| *shares >= min_shares) {
+                return orders;
+            }
 
-            orders.push((price, shares));
+            // Drop the lightest level, the highest-priced one on a tie
+            let lightest = kept
+                .iter()
+                .enumerate()
+                .rev()
+                .min_by(|(_, &a), (_, &b)| weights[a].total_cmp(&weights[b]))
+                .map(|(n, _)| n)
+                .expect("kept is not empty");
+            kept.remove(lightest);
         }
 
-        orders
+        Vec::new()
     }
 }
 
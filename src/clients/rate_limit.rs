@@ -0,0 +1,85 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::{AppError, Result};
+
+/// How long a call waits for a permit before failing, unless
+/// `RATE_LIMIT_MAX_WAIT_MS` says otherwise.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(5);
+
+/// A token bucket holding up to one second's worth of permits, so bursts
+/// go out at once and sustained load is spread evenly at the configured
+/// rate. Callers that would wait longer than the bound are refused with
+/// [`AppError::RateLimit`] instead of queueing.
+#[derive(Debug)]
+pub struct RateLimiter {
+    name: &'static str,
+    /// Permits per second; `None` never limits
+    rate: Option<f64>,
+    capacity: f64,
+    max_wait: Duration,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Negative while permits are reserved by callers still waiting
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `per_second` calls a second; 0 disables it.
+    pub fn new(name: &'static str, per_second: f64, max_wait: Duration) -> Self {
+        let rate = (per_second.is_finite() && per_second > 0.0).then_some(per_second);
+        let capacity = rate.map_or(1.0, |rate| rate.max(1.0));
+        Self {
+            name,
+            rate,
+            capacity,
+            max_wait,
+            bucket: Mutex::new(Bucket {
+                tokens: capacity,
+                updated_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits for a permit to make one outbound request.
+    pub async fn acquire(&self) -> Result<()> {
+        let Some(rate) = self.rate else {
+            return Ok(());
+        };
+
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(self.capacity);
+            bucket.updated_at = now;
+
+            let wait = Duration::from_secs_f64((1.0 - bucket.tokens).max(0.0) / rate);
+            if wait > self.max_wait {
+                tracing::warn!(
+                    "{} rate limiter saturated; refusing a call that would wait {:?}",
+                    self.name,
+                    wait
+                );
+                return Err(AppError::RateLimit {
+                    retry_after: Some(wait),
+                    upstream: Some(self.name.to_string()),
+                });
+            }
+            // Reserve the permit now so later callers queue behind this one
+            bucket.tokens -= 1.0;
+            wait
+        };
+
+        if !wait.is_zero() {
+            tracing::debug!("{} rate limiter: waiting {:?}", self.name, wait);
+            tokio::time::sleep(wait).await;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,312 @@
+use chrono::Utc;
+use reqwest::header::HeaderMap;
+use reqwest::{Response, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use crate::clients::UpstreamSettings;
+use crate::{AppError, Result};
+
+pub const DEFAULT_RECORDINGS_DIR: &str = "upstream-recordings";
+pub const DEFAULT_MAX_RECORDINGS: usize = 200;
+const MAX_TOTAL_BYTES: u64 = 50 * 1024 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const REDACTED: &str = "[REDACTED]";
+/// JSON body fields holding credentials, compared ignoring case, `_` and
+/// `-` (`api_key`, `apiKey`): e.g. the token a Kalshi login answers with.
+const SENSITIVE_FIELDS: &[&str] = &[
+    "token",
+    "accesstoken",
+    "refreshtoken",
+    "apikey",
+    "secret",
+    "passphrase",
+    "password",
+    "privatekey",
+];
+
+/// Raw upstream response that we failed to deserialize, kept so it can be
+/// turned into a test fixture.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpstreamRecording {
+    pub id: String,
+    pub recorded_at: String,
+    /// Request URL with secret-looking query values redacted
+    pub url: String,
+    pub status: Option<u16>,
+    /// Response headers with credential-bearing values redacted
+    pub headers: BTreeMap<String, String>,
+    pub error: String,
+    /// Response body, with credential fields redacted when it is JSON
+    pub body: String,
+    pub body_truncated: bool,
+}
+
+/// Reads a JSON response body, recording the raw response when it fails to
+/// deserialize and `RECORD_UPSTREAM_FAILURES=true`.
+///
+/// `what` names the payload for the error, e.g. "Gamma response".
+pub async fn parse_json<T: DeserializeOwned>(response: Response, what: &str) -> Result<T> {
+    let url = response.url().clone();
+    let status = response.status().as_u16();
+    let headers = response.headers().clone();
+    let body = response
+        .bytes()
+        .await
+        .map_err(|e| AppError::ExternalApi(format!("Failed to read {}: {}", what, e)))?;
+
+    match serde_json::from_slice(&body) {
+        Ok(value) => Ok(value),
+        Err(e) => {
+            Err(parse_failure(what, e, url.as_str(), Some(status), Some(&headers), &body).await)
+        }
+    }
+}
+
+/// Builds the parse error for `what`, recording the raw payload first when
+/// recording is on so the recording id can be quoted in the message.
+pub async fn parse_failure(
+    what: &str,
+    error: impl Display,
+    url: &str,
+    status: Option<u16>,
+    headers: Option<&HeaderMap>,
+    body: &[u8],
+) -> AppError {
+    AppError::MalformedResponse(failure_message(what, error, url, status, headers, body).await)
+}
+
+/// [`parse_failure`] for a model's answer, which is kept on the error.
+pub async fn ai_parse_failure(error: impl Display, url: &str, content: &str) -> AppError {
+    let message =
+        failure_message("AI analysis JSON", error, url, None, None, content.as_bytes()).await;
+    AppError::ai_parse(message, content)
+}
+
+async fn failure_message(
+    what: &str,
+    error: impl Display,
+    url: &str,
+    status: Option<u16>,
+    headers: Option<&HeaderMap>,
+    body: &[u8],
+) -> String {
+    let error = error.to_string();
+    match record(url, status, headers, body, &error).await {
+        Some(id) => format!("Failed to parse {}: {} (recording {})", what, error, id),
+        None => format!("Failed to parse {}: {}", what, error),
+    }
+}
+
+pub fn recording_enabled() -> bool {
+    UpstreamSettings::current().record_failures
+}
+
+fn recordings_dir() -> PathBuf {
+    UpstreamSettings::current().recordings_dir.clone()
+}
+
+fn max_recordings() -> usize {
+    UpstreamSettings::current().max_recordings
+}
+
+/// Writes a recording and returns its id, or `None` when recording is off or
+/// the write fails (recording must never mask the original error).
+async fn record(
+    url: &str,
+    status: Option<u16>,
+    headers: Option<&HeaderMap>,
+    body: &[u8],
+    error: &str,
+) -> Option<String> {
+    if !recording_enabled() {
+        return None;
+    }
+
+    let id = uuid::Uuid::new_v4().to_string();
+    let body = redact_body(body);
+    let truncated = body.len() > MAX_BODY_BYTES;
+    let recording = UpstreamRecording {
+        id: id.clone(),
+        recorded_at: Utc::now().to_rfc3339(),
+        url: redact_url(url),
+        status,
+        headers: headers.map(redact_headers).unwrap_or_default(),
+        error: error.to_string(),
+        body: String::from_utf8_lossy(&body[..body.len().min(MAX_BODY_BYTES)]).into_owned(),
+        body_truncated: truncated,
+    };
+
+    let dir = recordings_dir();
+    let write = async {
+        tokio::fs::create_dir_all(&dir).await?;
+        let json = serde_json::to_vec_pretty(&recording)?;
+        tokio::fs::write(dir.join(format!("{}.json", id)), json).await?;
+        rotate(&dir).await
+    };
+
+    match write.await {
+        Ok(()) => {
+            tracing::warn!(
+                "Recorded unparseable upstream response {} from {}",
+                id,
+                recording.url
+            );
+            Some(id)
+        }
+        Err(e) => {
+            tracing::error!("Failed to record upstream response: {}", e);
+            None
+        }
+    }
+}
+
+/// Deletes the oldest recordings past the count or total size cap.
+async fn rotate(dir: &PathBuf) -> std::io::Result<()> {
+    let mut files = Vec::new();
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let metadata = entry.metadata().await?;
+        files.push((metadata.modified()?, metadata.len(), path));
+    }
+    files.sort_by_key(|(modified, _, _)| *modified);
+
+    let max = max_recordings();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    let mut count = files.len();
+    for (_, len, path) in files {
+        if count <= max && total <= MAX_TOTAL_BYTES {
+            break;
+        }
+        tokio::fs::remove_file(&path).await?;
+        count -= 1;
+        total -= len;
+    }
+    Ok(())
+}
+
+/// Loads a recording by id. Ids are validated as UUIDs so they can't escape
+/// the recordings directory.
+pub async fn load_recording(id: &str) -> Result<Option<UpstreamRecording>> {
+    let id = uuid::Uuid::parse_str(id)
+        .map_err(|_| AppError::Validation(format!("Invalid recording id: {}", id)))?;
+    let path = recordings_dir().join(format!("{}.json", id));
+
+    let bytes = match tokio::fs::read(&path).await {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => {
+            return Err(AppError::Internal(anyhow::anyhow!(
+                "Failed to read recording {}: {}",
+                id,
+                e
+            )))
+        }
+    };
+
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| AppError::Internal(anyhow::anyhow!("Corrupt recording {}: {}", id, e)))
+}
+
+fn is_sensitive(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    // Ids named after tokens or keys (`token_id`, `clobTokenIds`) are not
+    // credentials, and fixtures are keyed on them
+    if name.ends_with("id") || name.ends_with("ids") {
+        return false;
+    }
+    matches!(
+        name.as_str(),
+        "authorization" | "proxy-authorization" | "cookie" | "set-cookie"
+    ) || ["key", "token", "secret", "signature", "auth", "passphrase"]
+        .iter()
+        .any(|marker| name.contains(marker))
+}
+
+pub fn redact_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if is_sensitive(name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.as_str().to_string(), value)
+        })
+        .collect()
+}
+
+pub fn redact_url(url: &str) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    if parsed.query().is_none() {
+        return parsed.to_string();
+    }
+
+    let pairs: Vec<(String, String)> = parsed
+        .query_pairs()
+        .map(|(k, v)| {
+            let value = if is_sensitive(&k) {
+                REDACTED.to_string()
+            } else {
+                v.into_owned()
+            };
+            (k.into_owned(), value)
+        })
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+/// Replaces the value of every [`SENSITIVE_FIELDS`] field in `value`, at any
+/// depth. Returns whether anything was redacted.
+pub fn redact_json(value: &mut Value) -> bool {
+    match value {
+        Value::Object(fields) => {
+            let mut redacted = false;
+            for (name, field) in fields.iter_mut() {
+                let normalized: String = name
+                    .chars()
+                    .filter(|c| !matches!(c, '_' | '-'))
+                    .collect::<String>()
+                    .to_ascii_lowercase();
+                if SENSITIVE_FIELDS.contains(&normalized.as_str()) && !field.is_null() {
+                    *field = Value::String(REDACTED.to_string());
+                    redacted = true;
+                } else {
+                    redacted |= redact_json(field);
+                }
+            }
+            redacted
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .fold(false, |redacted, item| redact_json(item) | redacted),
+        _ => false,
+    }
+}
+
+/// `body` with [`redact_json`] applied when it is JSON holding credentials;
+/// anything else comes back as it is.
+pub fn redact_body(body: &[u8]) -> Cow<'_, [u8]> {
+    let Ok(mut json) = serde_json::from_slice::<Value>(body) else {
+        return Cow::Borrowed(body);
+    };
+    if redact_json(&mut json) {
+        Cow::Owned(json.to_string().into_bytes())
+    } else {
+        Cow::Borrowed(body)
+    }
+}
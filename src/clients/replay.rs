@@ -0,0 +1,179 @@
+use alloy_primitives::hex;
+use axum::http::{self, HeaderValue, StatusCode};
+use chrono::Utc;
+use reqwest::header::{HeaderMap, CONTENT_TYPE};
+use reqwest::{Request, Response, ResponseBuilderExt, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+use crate::clients::recorder::{redact_body, redact_json, redact_url};
+use crate::metrics::UpstreamApi;
+
+/// Whether upstream calls are recorded as fixtures, served from them, or
+/// sent normally. Replay wins when both directories are set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayMode {
+    Off,
+    /// `UPSTREAM_RECORD_DIR`: calls go out and every response is written
+    /// to the directory
+    Record(PathBuf),
+    /// `UPSTREAM_REPLAY_DIR`: calls are answered from the directory and
+    /// never reach the network
+    Replay(PathBuf),
+}
+
+/// One recorded upstream exchange, stored as
+/// `<dir>/<api>/<method>-<key>.json` where the key hashes the method and
+/// the URL and request body with credentials redacted. Request headers
+/// (where most credentials are) aren't kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamFixture {
+    pub method: String,
+    /// Request URL with secret-looking query values redacted
+    pub url: String,
+    pub recorded_at: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// The response body when it is JSON, kept as JSON so fixtures stay
+    /// readable and editable, with credential fields (e.g. a login's
+    /// `token`) redacted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json: Option<Value>,
+    /// Any other response body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+}
+
+/// Where the fixture answering `request` to `api` lives under `dir`.
+pub fn fixture_path(dir: &Path, api: UpstreamApi, request: &Request) -> PathBuf {
+    let method = request.method().as_str();
+    let mut hasher = Sha256::new();
+    hasher.update(method.as_bytes());
+    hasher.update(b" ");
+    hasher.update(redact_url(request.url().as_str()).as_bytes());
+    hasher.update(b"\n");
+    if let Some(body) = request.body().and_then(|body| body.as_bytes()) {
+        hasher.update(redact_body(body));
+    }
+    let key = hex::encode(&hasher.finalize()[..8]);
+    dir.join(api.label())
+        .join(format!("{}-{}.json", method.to_ascii_lowercase(), key))
+}
+
+/// Answers `request` from its fixture. A missing or unreadable fixture is
+/// served as a 421 Misdirected Request naming the file to record: a status
+/// no client treats as "not found" or retries, so the call fails with that
+/// message instead of reaching the network.
+pub(crate) async fn replay(request: &Request, api: UpstreamApi, dir: &Path) -> Response {
+    let path = fixture_path(dir, api, request);
+    let url = request.url().clone();
+    let fixture = match tokio::fs::read(&path).await {
+        Ok(bytes) => serde_json::from_slice::<UpstreamFixture>(&bytes)
+            .map_err(|e| format!("corrupt replay fixture {}: {}", path.display(), e)),
+        Err(e) => Err(format!(
+            "no replay fixture for {} {} (expected {}: {})",
+            request.method(),
+            redact_url(url.as_str()),
+            path.display(),
+            e
+        )),
+    };
+
+    match fixture {
+        Ok(fixture) => {
+            let body = match (fixture.json, fixture.text) {
+                (Some(json), _) => json.to_string(),
+                (None, text) => text.unwrap_or_default(),
+            };
+            let status = StatusCode::from_u16(fixture.status).unwrap_or(StatusCode::OK);
+            let mut headers = HeaderMap::new();
+            if let Some(value) = fixture
+                .content_type
+                .and_then(|value| HeaderValue::from_str(&value).ok())
+            {
+                headers.insert(CONTENT_TYPE, value);
+            }
+            response(url, status, headers, body.into_bytes())
+        }
+        Err(message) => {
+            tracing::error!("{}", message);
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+            response(
+                url,
+                StatusCode::MISDIRECTED_REQUEST,
+                headers,
+                message.into_bytes(),
+            )
+        }
+    }
+}
+
+/// Writes `upstream` to the fixture for `request` and hands back an
+/// identical response. A failed write is logged; recording never fails the
+/// call.
+pub(crate) async fn record(
+    request: &Request,
+    upstream: Response,
+    api: UpstreamApi,
+    dir: &Path,
+) -> reqwest::Result<Response> {
+    let path = fixture_path(dir, api, request);
+    let status = upstream.status();
+    let url = upstream.url().clone();
+    let headers = upstream.headers().clone();
+    let body = upstream.bytes().await?.to_vec();
+
+    let content_type = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let json = serde_json::from_slice::<Value>(&body).ok().map(|mut json| {
+        redact_json(&mut json);
+        json
+    });
+    let fixture = UpstreamFixture {
+        method: request.method().to_string(),
+        url: redact_url(request.url().as_str()),
+        recorded_at: Utc::now().to_rfc3339(),
+        status: status.as_u16(),
+        content_type,
+        text: json
+            .is_none()
+            .then(|| String::from_utf8_lossy(&body).into_owned()),
+        json,
+    };
+
+    let write = async {
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let json = serde_json::to_vec_pretty(&fixture)?;
+        tokio::fs::write(&path, json).await
+    };
+    match write.await {
+        Ok(()) => tracing::debug!(
+            "Recorded {} {} to {}",
+            fixture.method,
+            fixture.url,
+            path.display()
+        ),
+        Err(e) => tracing::error!("Failed to record {}: {}", path.display(), e),
+    }
+
+    Ok(response(url, status, headers, body))
+}
+
+fn response(url: Url, status: StatusCode, headers: HeaderMap, body: Vec<u8>) -> Response {
+    let mut builder = http::Response::builder().status(status).url(url);
+    if let Some(target) = builder.headers_mut() {
+        *target = headers;
+    }
+    builder
+        .body(body)
+        .map(Response::from)
+        .expect("status and headers are already validated")
+}
@@ -0,0 +1,100 @@
+use crate::{AppError, Result};
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// Longest `Retry-After` worth waiting out; a longer one fails the call.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// How many times a failed call is repeated, and the backoff it starts
+/// from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+/// A successful result and how many retries it took.
+#[derive(Debug)]
+pub struct Retried<T> {
+    pub value: T,
+    pub retries: u32,
+}
+
+/// Runs `op`, retrying up to `max_retries` more times on transient failures
+/// (connect errors, timeouts, 5xx and 429; see [`AppError::is_retryable`]).
+/// Waits a random delay of up to `base_delay * 2^attempt` between attempts,
+/// and never less than the upstream's `Retry-After` when it sent one. Other
+/// errors, such as 4xx rejections and malformed payloads, are returned
+/// straight away.
+pub async fn retry_with_backoff<F, Fut, T>(
+    op: F,
+    max_retries: u32,
+    base_delay: Duration,
+) -> Result<Retried<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let policy = RetryPolicy {
+        max_retries,
+        base_delay,
+    };
+    retry_observed(op, policy, |_, _| {}).await
+}
+
+/// [`retry_with_backoff`] under `policy`, calling `on_retry` with each
+/// retried error and the delay before the next attempt.
+pub async fn retry_observed<F, Fut, T, R>(
+    mut op: F,
+    policy: RetryPolicy,
+    mut on_retry: R,
+) -> Result<Retried<T>>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+    R: FnMut(&AppError, Duration),
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => {
+                if attempt > 0 {
+                    tracing::info!("Upstream call succeeded on attempt {}", attempt + 1);
+                }
+                return Ok(Retried {
+                    value,
+                    retries: attempt,
+                });
+            }
+            Err(e) => {
+                let delay = match retry_delay(&e, attempt, policy.base_delay) {
+                    Some(delay) if attempt < policy.max_retries => delay,
+                    _ => return Err(e),
+                };
+                tracing::warn!("Upstream call failed ({}), retrying in {:?}...", e, delay);
+                on_retry(&e, delay);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Delay before retrying after `error`, or `None` when it isn't worth
+/// retrying.
+fn retry_delay(error: &AppError, attempt: u32, base_delay: Duration) -> Option<Duration> {
+    if !error.is_retryable() {
+        return None;
+    }
+    // Full jitter, so clients that failed together don't retry together
+    let backoff = base_delay.saturating_mul(2_u32.saturating_pow(attempt));
+    let jittered =
+        Duration::from_millis(rand::thread_rng().gen_range(0..=backoff.as_millis() as u64));
+    match error.retry_after() {
+        Some(wait) if wait > MAX_RETRY_AFTER => None,
+        Some(wait) => Some(wait.max(jittered)),
+        None => Some(jittered),
+    }
+}
@@ -0,0 +1,106 @@
+use alloy_primitives::hex;
+use rand::Rng;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Low bits of each seed filled with randomness so two signers (or a restart
+/// within the same millisecond) never start from the same value.
+const RANDOM_SEED_BITS: u32 = 11;
+/// Largest salt handed out: the exchange reads the order JSON's `salt` as a
+/// number, and integers above 2^53 lose precision as doubles. Millisecond
+/// seeds shifted by [`RANDOM_SEED_BITS`] stay below it until 2109.
+pub const MAX_SALT: u64 = (1 << 53) - 1;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SaltAllocatorStats {
+    pub signers: usize,
+    pub total_allocated: u64,
+    pub clock_regressions: u64,
+    /// Smallest number of salts left before any signer's counter passes
+    /// [`MAX_SALT`].
+    pub min_headroom: u64,
+}
+
+#[derive(Debug, Default)]
+struct AllocatorState {
+    counters: HashMap<String, Arc<AtomicU64>>,
+    last_seed_millis: u64,
+}
+
+/// Hands out per-signer order salts that are unique and strictly increasing,
+/// even when a ladder places many orders from the same signer concurrently.
+///
+/// Each signer's counter is seeded from the current time in milliseconds with
+/// random low bits, so salts also stay unique across restarts.
+#[derive(Debug, Default)]
+pub struct SaltAllocator {
+    state: Mutex<AllocatorState>,
+    total_allocated: AtomicU64,
+    clock_regressions: AtomicU64,
+}
+
+impl SaltAllocator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next_salt(&self, signer: &str) -> u64 {
+        let counter = {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            match state.counters.get(signer) {
+                Some(counter) => counter.clone(),
+                None => {
+                    let seed = self.seed(&mut state);
+                    let counter = Arc::new(AtomicU64::new(seed));
+                    state.counters.insert(signer.to_string(), counter.clone());
+                    counter
+                }
+            }
+        };
+
+        self.total_allocated.fetch_add(1, Ordering::Relaxed);
+        counter.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub fn stats(&self) -> SaltAllocatorStats {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        SaltAllocatorStats {
+            signers: state.counters.len(),
+            total_allocated: self.total_allocated.load(Ordering::Relaxed),
+            clock_regressions: self.clock_regressions.load(Ordering::Relaxed),
+            min_headroom: state
+                .counters
+                .values()
+                .map(|c| MAX_SALT.saturating_sub(c.load(Ordering::Relaxed)))
+                .min()
+                .unwrap_or(MAX_SALT),
+        }
+    }
+
+    fn seed(&self, state: &mut AllocatorState) -> u64 {
+        let now_millis = chrono::Utc::now().timestamp_millis().max(0) as u64;
+        if now_millis < state.last_seed_millis {
+            self.clock_regressions.fetch_add(1, Ordering::Relaxed);
+            tracing::warn!(
+                "System clock moved backwards by {}ms while seeding order salts",
+                state.last_seed_millis - now_millis
+            );
+        }
+        state.last_seed_millis = state.last_seed_millis.max(now_millis);
+
+        let random_bits = rand::thread_rng().gen_range(0..1u64 << RANDOM_SEED_BITS);
+        (now_millis << RANDOM_SEED_BITS) | random_bits
+    }
+}
+
+/// Stable identifier for a signer, from its wallet address, that keys its
+/// salt sequence; any checksum casing or surrounding whitespace gives the
+/// same value. SHA-256 rather than `DefaultHasher`, whose output may change
+/// between Rust releases.
+pub fn signer_fingerprint(address: &str) -> String {
+    let digest = Sha256::digest(address.trim().to_lowercase().as_bytes());
+    hex::encode(&digest[..8])
+}
@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+use crate::clients::circuit_breaker::CircuitSnapshot;
+use crate::clients::clob_signing::{MarketParams, WalletAuth};
+use crate::clients::dome::parse_market_url;
+use crate::clients::polymarket::{
+    CancelResult, ClobOrder, ClobTrade, MarketSearch, MarketSearchPage, PolymarketEvent,
+    PositionData, WalletPnl, WalletPosition, WalletTrade,
+};
+use crate::clients::{DomeClient, KalshiClient, PolyfactualClient, PolymarketClient};
+use crate::types::{
+    Candle, CandleInterval, MarketData, MarketRef, OrderBook, OrderResult, Platform,
+    PolyfactualResearchResponse, Price,
+};
+use crate::{AppError, Result};
+
+/// Market lookups across platforms (Dome in production).
+#[async_trait]
+pub trait MarketDataSource: Send + Sync {
+    /// A market by Polymarket slug or Kalshi ticker.
+    async fn get_market(&self, platform: Platform, identifier: &str) -> Result<MarketData>;
+    /// Several markets at once, in input order; a failed lookup only fails
+    /// its own entry.
+    async fn get_markets(&self, markets: &[MarketRef]) -> Vec<Result<MarketData>>;
+    /// Price candles between `start` and `end`, oldest first. Polymarket
+    /// markets are named by condition id.
+    async fn get_candles(
+        &self,
+        platform: Platform,
+        identifier: &str,
+        interval: CandleInterval,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>>;
+    /// A cheap request that checks the source is reachable and accepts our
+    /// credentials.
+    async fn ping(&self) -> Result<()>;
+
+    /// The source's circuit breaker, when it has one.
+    fn circuit(&self) -> Option<CircuitSnapshot> {
+        None
+    }
+
+    /// Platform and identifier named by a Polymarket or Kalshi market URL.
+    fn market_ref_from_url(&self, url: &str) -> Result<MarketRef> {
+        parse_market_url(url).map_err(AppError::Validation)
+    }
+}
+
+/// Kalshi markets and the signed-in account's positions, straight from
+/// Kalshi rather than through Dome.
+#[async_trait]
+pub trait KalshiVenue: Send + Sync {
+    /// A market by ticker, priced as probabilities.
+    async fn get_market(&self, ticker: &str) -> Result<MarketData>;
+    /// Open positions, on `ticker` only when given.
+    async fn get_positions(&self, ticker: Option<&str>) -> Result<Vec<PositionData>>;
+    async fn ping(&self) -> Result<()>;
+}
+
+/// Research answers with citations (Polyfactual in production).
+#[async_trait]
+pub trait ResearchSource: Send + Sync {
+    /// Runs `query`, each attempt limited to `timeout` when given.
+    async fn research(
+        &self,
+        query: String,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse>;
+
+    /// The source's circuit breaker, when it has one.
+    fn circuit(&self) -> Option<CircuitSnapshot> {
+        None
+    }
+}
+
+/// Polymarket market data, wallet data and order management (Gamma, the
+/// data API and the CLOB in production).
+#[async_trait]
+pub trait TradingVenue: Send + Sync {
+    async fn get_market_by_slug(&self, slug: &str) -> Result<MarketData>;
+    async fn get_event_by_slug(&self, slug: &str) -> Result<PolymarketEvent>;
+    /// One page of markets matching `search`.
+    async fn search_markets(&self, search: &MarketSearch) -> Result<MarketSearchPage>;
+    /// The up/down market `slug` for the window starting at `window_start`,
+    /// found by start time when a fresh window's slug isn't indexed yet.
+    async fn resolve_updown_market(
+        &self,
+        slug: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<MarketData>;
+    async fn get_market_params(&self, token_id: &str) -> MarketParams;
+    async fn get_order_book(&self, token_id: &str) -> Result<OrderBook>;
+    async fn get_price_history(&self, token_id: &str) -> Result<Vec<(i64, f64)>>;
+
+    async fn get_wallet_pnl(&self, wallet_address: &str) -> Result<WalletPnl>;
+    async fn get_wallet_positions(&self, wallet_address: &str) -> Result<Vec<WalletPosition>>;
+    async fn get_market_position(
+        &self,
+        wallet_address: &str,
+        condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<PositionData>>;
+    async fn get_trade_history(
+        &self,
+        wallet_address: &str,
+        condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<WalletTrade>>;
+    /// The latest fills in a market by anyone, newest first.
+    async fn get_market_trades(&self, condition_id: &str) -> Result<Vec<WalletTrade>>;
+
+    async fn get_open_orders(
+        &self,
+        auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<Vec<ClobOrder>>;
+    /// Every open order of the wallet, across markets.
+    async fn get_wallet_open_orders(&self, auth: &WalletAuth) -> Result<Vec<ClobOrder>>;
+    async fn get_trades(&self, auth: &WalletAuth, token_ids: &[String]) -> Result<Vec<ClobTrade>>;
+    /// An order by id, or `None` when the exchange doesn't know it.
+    async fn get_order(&self, auth: &WalletAuth, order_id: &str) -> Result<Option<ClobOrder>>;
+    /// A limit order, resting until `expires_at` when given and until
+    /// cancelled otherwise.
+    #[allow(clippy::too_many_arguments)]
+    async fn place_order(
+        &self,
+        auth: &WalletAuth,
+        token_id: &str,
+        side: &str,
+        price: Price,
+        size: f64,
+        salt: u64,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<OrderResult>;
+    async fn cancel_orders(&self, auth: &WalletAuth, order_ids: &[String]) -> Result<CancelResult>;
+    async fn cancel_order(&self, auth: &WalletAuth, order_id: &str) -> Result<CancelResult>;
+    /// Every open order on `token_ids`.
+    async fn cancel_all(&self, auth: &WalletAuth, token_ids: &[String]) -> Result<CancelResult>;
+
+    /// A cheap request that checks Gamma is reachable.
+    async fn ping(&self) -> Result<()>;
+
+    /// Gamma's circuit breaker, when the venue has one.
+    fn gamma_circuit(&self) -> Option<CircuitSnapshot> {
+        None
+    }
+}
+
+#[async_trait]
+impl MarketDataSource for DomeClient {
+    async fn get_market(&self, platform: Platform, identifier: &str) -> Result<MarketData> {
+        DomeClient::get_market(self, platform, identifier).await
+    }
+
+    async fn get_markets(&self, markets: &[MarketRef]) -> Vec<Result<MarketData>> {
+        DomeClient::get_markets(self, markets).await
+    }
+
+    async fn get_candles(
+        &self,
+        platform: Platform,
+        identifier: &str,
+        interval: CandleInterval,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>> {
+        DomeClient::get_candles(self, platform, identifier, interval, start, end).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        DomeClient::ping(self).await
+    }
+
+    fn circuit(&self) -> Option<CircuitSnapshot> {
+        Some(DomeClient::circuit(self))
+    }
+}
+
+#[async_trait]
+impl KalshiVenue for KalshiClient {
+    async fn get_market(&self, ticker: &str) -> Result<MarketData> {
+        KalshiClient::get_market(self, ticker).await
+    }
+
+    async fn get_positions(&self, ticker: Option<&str>) -> Result<Vec<PositionData>> {
+        KalshiClient::get_positions(self, ticker).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        KalshiClient::ping(self).await
+    }
+}
+
+#[async_trait]
+impl ResearchSource for PolyfactualClient {
+    async fn research(
+        &self,
+        query: String,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse> {
+        PolyfactualClient::research(self, query, timeout).await
+    }
+
+    fn circuit(&self) -> Option<CircuitSnapshot> {
+        Some(PolyfactualClient::circuit(self))
+    }
+}
+
+#[async_trait]
+impl TradingVenue for PolymarketClient {
+    async fn get_market_by_slug(&self, slug: &str) -> Result<MarketData> {
+        PolymarketClient::get_market_by_slug(self, slug).await
+    }
+
+    async fn get_event_by_slug(&self, slug: &str) -> Result<PolymarketEvent> {
+        PolymarketClient::get_event_by_slug(self, slug).await
+    }
+
+    async fn search_markets(&self, search: &MarketSearch) -> Result<MarketSearchPage> {
+        PolymarketClient::search_markets(self, search).await
+    }
+
+    async fn resolve_updown_market(
+        &self,
+        slug: &str,
+        window_start: DateTime<Utc>,
+    ) -> Result<MarketData> {
+        PolymarketClient::resolve_updown_market(self, slug, window_start).await
+    }
+
+    async fn get_market_params(&self, token_id: &str) -> MarketParams {
+        PolymarketClient::get_market_params(self, token_id).await
+    }
+
+    async fn get_order_book(&self, token_id: &str) -> Result<OrderBook> {
+        PolymarketClient::get_order_book(self, token_id).await
+    }
+
+    async fn get_price_history(&self, token_id: &str) -> Result<Vec<(i64, f64)>> {
+        PolymarketClient::get_price_history(self, token_id).await
+    }
+
+    async fn get_wallet_pnl(&self, wallet_address: &str) -> Result<WalletPnl> {
+        PolymarketClient::get_wallet_pnl(self, wallet_address).await
+    }
+
+    async fn get_wallet_positions(&self, wallet_address: &str) -> Result<Vec<WalletPosition>> {
+        PolymarketClient::get_wallet_positions(self, wallet_address).await
+    }
+
+    async fn get_market_position(
+        &self,
+        wallet_address: &str,
+        condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<PositionData>> {
+        PolymarketClient::get_market_position(self, wallet_address, condition_id, token_ids).await
+    }
+
+    async fn get_trade_history(
+        &self,
+        wallet_address: &str,
+        condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<WalletTrade>> {
+        PolymarketClient::get_trade_history(self, wallet_address, condition_id, token_ids).await
+    }
+
+    async fn get_market_trades(&self, condition_id: &str) -> Result<Vec<WalletTrade>> {
+        PolymarketClient::get_market_trades(self, condition_id).await
+    }
+
+    async fn get_open_orders(
+        &self,
+        auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<Vec<ClobOrder>> {
+        PolymarketClient::get_open_orders(self, auth, token_ids).await
+    }
+
+    async fn get_wallet_open_orders(&self, auth: &WalletAuth) -> Result<Vec<ClobOrder>> {
+        PolymarketClient::get_wallet_open_orders(self, auth).await
+    }
+
+    async fn get_trades(&self, auth: &WalletAuth, token_ids: &[String]) -> Result<Vec<ClobTrade>> {
+        PolymarketClient::get_trades(self, auth, token_ids).await
+    }
+
+    async fn get_order(&self, auth: &WalletAuth, order_id: &str) -> Result<Option<ClobOrder>> {
+        PolymarketClient::get_order(self, auth, order_id).await
+    }
+
+    async fn place_order(
+        &self,
+        auth: &WalletAuth,
+        token_id: &str,
+        side: &str,
+        price: Price,
+        size: f64,
+        salt: u64,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<OrderResult> {
+        PolymarketClient::place_order(self, auth, token_id, side, price, size, salt, expires_at)
+            .await
+    }
+
+    async fn cancel_orders(&self, auth: &WalletAuth, order_ids: &[String]) -> Result<CancelResult> {
+        PolymarketClient::cancel_orders(self, auth, order_ids).await
+    }
+
+    async fn cancel_order(&self, auth: &WalletAuth, order_id: &str) -> Result<CancelResult> {
+        PolymarketClient::cancel_order(self, auth, order_id).await
+    }
+
+    async fn cancel_all(&self, auth: &WalletAuth, token_ids: &[String]) -> Result<CancelResult> {
+        PolymarketClient::cancel_all(self, auth, token_ids).await
+    }
+
+    async fn ping(&self) -> Result<()> {
+        PolymarketClient::ping_gamma(self).await
+    }
+
+    fn gamma_circuit(&self) -> Option<CircuitSnapshot> {
+        Some(PolymarketClient::gamma_circuit(self))
+    }
+}
@@ -0,0 +1,246 @@
+use alloy_primitives::hex;
+use hmac::{Hmac, Mac};
+use reqwest::{redirect, Client};
+use serde::Serialize;
+use sha2::Sha256;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::clients::{
+    handle_upstream_response, http_client_builder, retry_with_backoff, HttpClientConfig,
+};
+use crate::{AppError, Result};
+
+/// Carries `t=<unix seconds>,v1=<hex HMAC-SHA256>`.
+pub const SIGNATURE_HEADER: &str = "X-PredictOS-Signature";
+const TIMEOUT_SECS: u64 = 10;
+/// Retries after the first attempt
+const MAX_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// What a delivery reports, as its `event` field.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A watched order filled completely
+    OrderFilled,
+    /// A watched order was cancelled before filling
+    OrderCancelled,
+    /// A position monitor's P&L threshold was crossed
+    ThresholdTriggered,
+    /// A subscription's re-analysis flipped or moved in confidence
+    AnalysisChanged,
+}
+
+/// HMAC-SHA256 over `"<timestamp>.<body>"`, keyed with the secret as-is, as
+/// the value of [`SIGNATURE_HEADER`]. Binding the timestamp lets receivers
+/// reject replays.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    format!(
+        "t={},v1={}",
+        timestamp,
+        hex::encode(mac(secret, timestamp, body).finalize().into_bytes())
+    )
+}
+
+/// Checks a [`SIGNATURE_HEADER`] value against `body`, rejecting signatures
+/// more than `tolerance` away from `now` (unix seconds).
+pub fn verify_signature(
+    secret: &str,
+    header: &str,
+    body: &[u8],
+    now: i64,
+    tolerance: Duration,
+) -> bool {
+    let mut timestamp = None;
+    let mut signature = None;
+    for part in header.split(',') {
+        match part.trim().split_once('=') {
+            Some(("t", value)) => timestamp = value.parse::<i64>().ok(),
+            Some(("v1", value)) => signature = hex::decode(value).ok(),
+            _ => {}
+        }
+    }
+    let (Some(timestamp), Some(signature)) = (timestamp, signature) else {
+        return false;
+    };
+    if now.abs_diff(timestamp) > tolerance.as_secs() {
+        return false;
+    }
+    mac(secret, timestamp, body)
+        .verify_slice(&signature)
+        .is_ok()
+}
+
+fn mac(secret: &str, timestamp: i64, body: &[u8]) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+/// Delivers signed webhook events. Signing needs `WEBHOOK_SECRET`; without
+/// it, requests asking for webhooks are refused.
+#[derive(Debug, Clone)]
+pub struct WebhookSender {
+    /// Proxy and certificate settings for each delivery's client
+    http: HttpClientConfig,
+    secret: Option<String>,
+    /// Hosts exempt from the private address check
+    allowed_hosts: Vec<String>,
+}
+
+impl WebhookSender {
+    pub fn new(http: HttpClientConfig, secret: Option<String>, allowed_hosts: Vec<String>) -> Self {
+        Self {
+            http,
+            secret,
+            allowed_hosts,
+        }
+    }
+
+    /// Validates a caller-supplied webhook URL before anything is sent to it.
+    /// Hosts that are, or resolve to, loopback, private or link-local
+    /// addresses are refused unless listed in `WEBHOOK_ALLOWED_HOSTS`, so
+    /// callers can't aim the server at its own network.
+    pub async fn check_url(&self, url: &str) -> Result<()> {
+        let parsed = Url::parse(url)
+            .map_err(|e| AppError::Validation(format!("Invalid webhook_url: {}", e)))?;
+        if !matches!(parsed.scheme(), "http" | "https") {
+            return Err(AppError::Validation(
+                "webhook_url must be an http(s) URL".to_string(),
+            ));
+        }
+        if self.secret.is_none() {
+            return Err(AppError::Validation(
+                "webhook_url needs WEBHOOK_SECRET to be set on the server".to_string(),
+            ));
+        }
+        self.check_host(&parsed).await.map(|_| ())
+    }
+
+    /// The vetted addresses of `url`'s host; none for allowed hosts, which
+    /// are resolved as usual.
+    async fn check_host(&self, url: &Url) -> Result<Vec<SocketAddr>> {
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+        if self.allowed_hosts.contains(&host) {
+            return Ok(Vec::new());
+        }
+        let port = url.port_or_known_default().unwrap_or(443);
+        let addresses: Vec<SocketAddr> = match url.host() {
+            Some(Host::Ipv4(ip)) => vec![SocketAddr::new(ip.into(), port)],
+            Some(Host::Ipv6(ip)) => vec![SocketAddr::new(ip.into(), port)],
+            Some(Host::Domain(domain)) => tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|e| {
+                    AppError::Validation(format!(
+                        "webhook_url host {} could not be resolved: {}",
+                        host, e
+                    ))
+                })?
+                .collect(),
+            None => Vec::new(),
+        };
+        if addresses.is_empty() {
+            return Err(AppError::Validation(format!(
+                "webhook_url host '{}' has no address",
+                host
+            )));
+        }
+        if let Some(address) = addresses.iter().find(|a| !is_public(a.ip())) {
+            return Err(AppError::Validation(format!(
+                "webhook_url host {} resolves to non-public address {}; list it in WEBHOOK_ALLOWED_HOSTS to allow it",
+                host,
+                address.ip()
+            )));
+        }
+        Ok(addresses)
+    }
+
+    /// A client for one delivery to `url`. It follows no redirects, so a
+    /// public host can't bounce the request onto a private one, and it
+    /// connects to the `addresses` just vetted instead of resolving the
+    /// name again, which could by then answer differently.
+    fn client(&self, url: &Url, addresses: &[SocketAddr]) -> Result<Client> {
+        let mut builder = http_client_builder(&self.http).redirect(redirect::Policy::none());
+        if let (Some(Host::Domain(domain)), false) = (url.host(), addresses.is_empty()) {
+            builder = builder.resolve_to_addrs(domain, addresses);
+        }
+        builder.build().map_err(|e| {
+            AppError::Internal(anyhow::anyhow!("Failed to create webhook client: {}", e))
+        })
+    }
+
+    /// POSTs `event` as JSON, retrying transient failures. Only logs when
+    /// delivery finally fails.
+    pub async fn deliver<E: Serialize>(&self, url: &str, event: &E) {
+        if let Err(e) = self.try_deliver(url, event).await {
+            tracing::warn!("Webhook delivery to {} failed: {}", url, e);
+        }
+    }
+
+    async fn try_deliver<E: Serialize>(&self, url: &str, event: &E) -> Result<()> {
+        let secret = self
+            .secret
+            .as_deref()
+            .ok_or_else(|| AppError::missing_api_key("WEBHOOK_SECRET"))?;
+        // Checked again in case the name has since been pointed elsewhere
+        let parsed = Url::parse(url).map_err(|e| AppError::Validation(e.to_string()))?;
+        let addresses = self.check_host(&parsed).await?;
+        let client = self.client(&parsed, &addresses)?;
+        let body = serde_json::to_vec(event).map_err(anyhow::Error::from)?;
+
+        retry_with_backoff(
+            || async {
+                // Signed per attempt so retries carry a fresh timestamp
+                let signature = sign(secret, chrono::Utc::now().timestamp(), &body);
+                let response = client
+                    .post(url)
+                    .header(reqwest::header::CONTENT_TYPE, "application/json")
+                    .header(SIGNATURE_HEADER, signature)
+                    .body(body.clone())
+                    .timeout(Duration::from_secs(TIMEOUT_SECS))
+                    .send()
+                    .await
+                    .map_err(|e| AppError::ExternalApi(format!("Webhook request failed: {}", e)))?;
+                handle_upstream_response(response, "Webhook").await?;
+                Ok(())
+            },
+            MAX_RETRIES,
+            RETRY_BASE_DELAY,
+        )
+        .await?;
+        Ok(())
+    }
+}
+
+/// Whether `ip` is reachable on the public internet: not loopback, private,
+/// link-local, shared (CGNAT), unspecified or broadcast, including IPv4
+/// addresses mapped into IPv6.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(mapped) => is_public(mapped.into()),
+            None => {
+                let first = ip.segments()[0];
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    // Unique local fc00::/7 and link-local fe80::/10
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
@@ -0,0 +1,812 @@
+use serde::Serialize;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::api::auto_trade::{self, AutoTradeConfig};
+use crate::api::cors::CorsOrigins;
+use crate::api::exposure_caps::ExposureCaps;
+use crate::api::wallet_snapshots::{self, TrackedWallet};
+use crate::api::{
+    analysis_subscriptions, analyze_event_markets, fill_watcher, health, idempotency, jobs,
+    limit_order_bot, market_cache, market_stream, middleware, pagination, position_monitor,
+    refresh_analysis, research_cache,
+};
+use crate::clients::ai::pricing::ModelPrices;
+use crate::clients::ai::prompts::FewShot;
+use crate::clients::ai::{self, AiProvider, DEFAULT_AI_MAX_RETRIES, DEFAULT_AI_RETRY_BASE_DELAY};
+use crate::clients::circuit_breaker::BreakerConfig;
+use crate::clients::clob_signing::{ApiCredentials, ClobSigner};
+use crate::clients::kalshi::KalshiSigningKey;
+use crate::clients::polyfactual::{DEFAULT_MAX_CITATIONS, DEFAULT_MAX_QUERY_LENGTH};
+use crate::clients::{
+    build_http_client, dome, polymarket, rate_limit, recorder, HttpClientConfig, ReplayMode,
+    RetryPolicy, UpstreamSettings,
+};
+use crate::types::OrderMode;
+
+const DEFAULT_HOST: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const DEFAULT_PORT: u16 = 8000;
+const DEFAULT_UPSTREAM_TIMEOUT_SECS: u64 = 30;
+const DEFAULT_POLYFACTUAL_TIMEOUT_SECS: u64 = 300;
+const DEFAULT_SHUTDOWN_DRAIN_SECS: u64 = 30;
+const DEFAULT_DOME_BATCH_CONCURRENCY: usize = 5;
+/// Pages fetched per data API listing before giving up on the rest
+const DEFAULT_DATA_API_MAX_PAGES: usize = 20;
+/// Markets analyzed per `analyze_all_markets` request, to bound AI spend
+const DEFAULT_EVENT_ANALYSIS_MAX_MARKETS: usize = 15;
+const DEFAULT_ORDER_EXPIRY_MARGIN_SECS: u64 = 30;
+
+/// Server settings read once at startup. Every variable is checked before
+/// the server starts, and all the invalid ones are reported together.
+/// Components are built from these fields; nothing else reads the
+/// environment while serving.
+#[derive(Clone)]
+pub struct Config {
+    pub host: IpAddr,
+    pub port: u16,
+    pub grok_api_key: Option<String>,
+    pub openai_api_key: Option<String>,
+    pub anthropic_api_key: Option<String>,
+    pub dome_api_key: Option<String>,
+    pub polyfactual_api_key: Option<String>,
+    /// Direct Kalshi access: an API key id with its RSA private key, or an
+    /// email and password
+    pub kalshi_api_key: Option<String>,
+    pub kalshi_private_key: Option<KalshiSigningKey>,
+    pub kalshi_email: Option<String>,
+    pub kalshi_password: Option<String>,
+    pub gamma_api_key: Option<String>,
+    /// Enables the admin API, which then requires it in `X-Admin-Token`
+    pub admin_api_token: Option<String>,
+    pub database_url: Option<String>,
+    /// Bearer tokens accepted on `/api/*`; none leaves the API open
+    pub api_auth_tokens: Vec<String>,
+    /// The server's wallet, used by order routes when a request brings no
+    /// key of its own
+    pub wallet: Option<ClobSigner>,
+    /// CLOB credentials for the server wallet, instead of deriving them
+    pub polymarket_api_credentials: Option<ApiCredentials>,
+    pub polygon_rpc_url: Option<String>,
+    /// Default models; a request's `model_name` still wins
+    pub grok_model: Option<String>,
+    pub openai_model: Option<String>,
+    pub anthropic_model: Option<String>,
+    /// API roots, e.g. of a gateway; each provider's public API by default
+    pub grok_base_url: Option<String>,
+    pub openai_base_url: Option<String>,
+    pub anthropic_base_url: Option<String>,
+    /// Prices behind `estimated_cost_usd` in response metadata
+    pub ai_model_prices: ModelPrices,
+    /// Example exchange sent ahead of each analysis prompt
+    pub ai_few_shot: FewShot,
+    /// Retries after a failed call, per AI provider
+    pub grok_max_retries: u32,
+    pub openai_max_retries: u32,
+    pub anthropic_max_retries: u32,
+    /// First backoff between AI retries, doubled on each further retry
+    pub ai_retry_base_delay: Duration,
+    /// Dome, Gamma, CLOB and data API requests
+    pub upstream_timeout: Duration,
+    /// One Polyfactual research run
+    pub polyfactual_timeout: Duration,
+    /// Longest query sent to Polyfactual; longer ones are summarized first
+    pub polyfactual_max_query_length: usize,
+    /// Citations kept per research answer
+    pub polyfactual_max_citations: usize,
+    /// Egress proxy and certificate settings for every upstream client
+    pub http_client: HttpClientConfig,
+    /// The client built from `http_client`, cloned into every upstream
+    /// client so they share one connection pool
+    pub http: reqwest::Client,
+    /// How long shutdown waits for in-flight requests and runs
+    pub shutdown_drain_timeout: Duration,
+    /// Concurrent lookups in a Dome batch
+    pub dome_batch_concurrency: usize,
+    /// Cap on pages per data API listing
+    pub data_api_max_pages: usize,
+    /// Cap on markets analyzed per event (`analyze_all_markets`)
+    pub event_analysis_max_markets: usize,
+    /// How long before the 15-minute boundary `market_close` orders expire
+    pub order_expiry_margin: Duration,
+    /// Trading state at startup; adjustable later via the admin API
+    pub trading_enabled: bool,
+    /// Operator limits on what a bot run may buy
+    pub exposure_caps: ExposureCaps,
+    /// Simple-mode pricing defaults when a request leaves them out
+    pub simple_improvement_ticks: u32,
+    pub simple_max_spread_cents: u32,
+    /// Most a Simple-mode straddle may cost per share of every side
+    pub simple_max_combined_price: f64,
+    /// Ladder span either side of the reference price when a request sets
+    /// no bounds
+    pub ladder_price_band: f64,
+    /// What the scheduler trades each window; `None` unless
+    /// `AUTO_TRADE_ENABLED` is set
+    pub auto_trade: Option<AutoTradeConfig>,
+    /// Require an API token on `/metrics` too
+    pub metrics_require_auth: bool,
+    /// Browser origins allowed by CORS
+    pub cors_allowed_origins: CorsOrigins,
+    /// Per-IP requests a minute for AI and other routes; 0 doesn't limit
+    pub rate_limit_ai_per_min: usize,
+    pub rate_limit_per_min: usize,
+    /// Take the client IP from the last `X-Forwarded-For` entry (behind a reverse proxy)
+    pub rate_limit_trust_proxy: bool,
+    /// How long Polymarket/Kalshi and Dome markets are reused; 0 disables
+    pub market_cache_ttl: Duration,
+    pub dome_market_cache_ttl: Duration,
+    pub market_search_cache_ttl: Duration,
+    pub market_stream_poll_interval: Duration,
+    /// How long research answers are reused (0 disables), and for how many
+    /// queries
+    pub research_cache_ttl: Duration,
+    pub research_cache_max_entries: usize,
+    /// Limit on the research run behind an event analysis
+    pub analysis_research_timeout: Duration,
+    /// Default changes that make `refresh_analysis` re-run an analysis
+    pub analysis_refresh_price_threshold: f64,
+    pub analysis_refresh_volume_threshold: f64,
+    /// How often analysis subscriptions and position monitors are checked
+    pub analysis_subscription_tick: Duration,
+    pub position_monitor_tick: Duration,
+    pub page_default_limit: usize,
+    pub page_max_limit: usize,
+    pub idempotency_ttl: Duration,
+    /// Background job concurrency, queue bound and how long finished jobs
+    /// are kept
+    pub job_workers: usize,
+    pub job_queue_max: usize,
+    pub job_retention: Duration,
+    /// Per-check timeout of `/health/deep`, and how long its report is
+    /// reused
+    pub health_check_timeout: Duration,
+    pub health_check_cache_ttl: Duration,
+    /// Signs webhook deliveries; required to register a webhook
+    pub webhook_secret: Option<String>,
+    /// Webhook hosts exempt from the public-address check
+    pub webhook_allowed_hosts: Vec<String>,
+    /// How often placed orders are polled for fills, and for how long
+    pub webhook_poll_interval: Duration,
+    pub webhook_watch_duration: Duration,
+    /// Strategy wallets on the leaderboard, and how often they are
+    /// snapshotted
+    pub tracked_wallets: Vec<TrackedWallet>,
+    pub wallet_snapshot_interval: Duration,
+    /// Rate limits, circuit breakers and recordings of the upstream
+    /// clients, installed process-wide at startup
+    pub upstream: UpstreamSettings,
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.summary(), f)
+    }
+}
+
+/// Every problem found in the environment, one per line.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The non-secret view of [`Config`] served by `GET /api/config`. Keys and
+/// tokens only say whether they are set.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfigSummary {
+    pub bind_address: String,
+    pub upstream_timeout_secs: u64,
+    pub polyfactual_timeout_secs: u64,
+    pub shutdown_drain_timeout_secs: u64,
+    pub dome_batch_concurrency: usize,
+    pub data_api_max_pages: usize,
+    pub cors_allowed_origins: String,
+    pub models: ModelSummary,
+    pub api_keys: ApiKeySummary,
+    pub features: FeatureSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelSummary {
+    pub grok: Option<String>,
+    pub openai: Option<String>,
+    pub anthropic: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeySummary {
+    pub grok: bool,
+    pub openai: bool,
+    pub anthropic: bool,
+    pub dome: bool,
+    pub polyfactual: bool,
+    pub kalshi: bool,
+    pub polymarket_gamma: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FeatureSummary {
+    pub trading_enabled: bool,
+    pub record_upstream_failures: bool,
+    pub auto_trade_enabled: bool,
+    pub metrics_require_auth: bool,
+    pub admin_api: bool,
+    pub api_auth: bool,
+    pub server_wallet: bool,
+    pub persistence: bool,
+    pub outbound_proxy: bool,
+    pub accept_invalid_certs: bool,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        let mut env = EnvReader::default();
+        let http_client = env.http_client();
+        let http = build_http_client(&http_client).unwrap_or_else(|e| {
+            env.problems
+                .push(format!("Outbound HTTP client can't be built: {}", e));
+            reqwest::Client::new()
+        });
+        let config = Self {
+            host: env.parse("HOST", DEFAULT_HOST),
+            port: env.parse("PORT", DEFAULT_PORT),
+            grok_api_key: env.string("GROK_API_KEY"),
+            openai_api_key: env.string("OPENAI_API_KEY"),
+            anthropic_api_key: env.string("ANTHROPIC_API_KEY"),
+            dome_api_key: env.string("DOME_API_KEY"),
+            polyfactual_api_key: env.string("POLYFACTUAL_API_KEY"),
+            kalshi_api_key: env.string("KALSHI_API_KEY"),
+            kalshi_private_key: env.kalshi_key("KALSHI_PRIVATE_KEY_PATH"),
+            kalshi_email: env.string("KALSHI_EMAIL"),
+            kalshi_password: env.string("KALSHI_PASSWORD"),
+            gamma_api_key: env.string("POLYMARKET_GAMMA_API_KEY"),
+            admin_api_token: env.string("ADMIN_API_TOKEN"),
+            database_url: env.string("DATABASE_URL"),
+            api_auth_tokens: env.list("API_AUTH_TOKENS"),
+            wallet: env.wallet("WALLET_PRIVATE_KEY"),
+            polymarket_api_credentials: env.api_credentials(),
+            polygon_rpc_url: env.string("POLYGON_RPC_URL"),
+            grok_model: env.string("GROK_MODEL"),
+            openai_model: env.string("OPENAI_MODEL"),
+            anthropic_model: env.string("ANTHROPIC_MODEL"),
+            grok_base_url: env.string("GROK_BASE_URL"),
+            openai_base_url: env.string("OPENAI_BASE_URL"),
+            anthropic_base_url: env.string("ANTHROPIC_BASE_URL"),
+            ai_model_prices: env.parse("AI_MODEL_PRICES", ModelPrices::default()),
+            ai_few_shot: env.parse("AI_FEW_SHOT_FILE", FewShot::default()),
+            grok_max_retries: env.parse("GROK_MAX_RETRIES", DEFAULT_AI_MAX_RETRIES),
+            openai_max_retries: env.parse("OPENAI_MAX_RETRIES", DEFAULT_AI_MAX_RETRIES),
+            anthropic_max_retries: env.parse("ANTHROPIC_MAX_RETRIES", DEFAULT_AI_MAX_RETRIES),
+            ai_retry_base_delay: Duration::from_millis(env.positive(
+                "AI_RETRY_BASE_DELAY_MS",
+                DEFAULT_AI_RETRY_BASE_DELAY.as_millis() as u64,
+            )),
+            upstream_timeout: Duration::from_secs(
+                env.positive("UPSTREAM_TIMEOUT_SECS", DEFAULT_UPSTREAM_TIMEOUT_SECS),
+            ),
+            polyfactual_timeout: Duration::from_secs(
+                env.positive("POLYFACTUAL_TIMEOUT_SECS", DEFAULT_POLYFACTUAL_TIMEOUT_SECS),
+            ),
+            polyfactual_max_query_length: env
+                .positive("POLYFACTUAL_MAX_QUERY_LENGTH", DEFAULT_MAX_QUERY_LENGTH),
+            polyfactual_max_citations: env
+                .positive("POLYFACTUAL_MAX_CITATIONS", DEFAULT_MAX_CITATIONS),
+            http_client,
+            http,
+            shutdown_drain_timeout: Duration::from_secs(
+                env.positive("SHUTDOWN_DRAIN_TIMEOUT_SECS", DEFAULT_SHUTDOWN_DRAIN_SECS),
+            ),
+            dome_batch_concurrency: env
+                .positive("DOME_BATCH_CONCURRENCY", DEFAULT_DOME_BATCH_CONCURRENCY),
+            data_api_max_pages: env.positive("DATA_API_MAX_PAGES", DEFAULT_DATA_API_MAX_PAGES),
+            event_analysis_max_markets: env.positive(
+                "EVENT_ANALYSIS_MAX_MARKETS",
+                DEFAULT_EVENT_ANALYSIS_MAX_MARKETS,
+            ),
+            order_expiry_margin: Duration::from_secs(
+                env.parse("ORDER_EXPIRY_MARGIN_SECS", DEFAULT_ORDER_EXPIRY_MARGIN_SECS),
+            ),
+            trading_enabled: env.flag("TRADING_ENABLED", true),
+            exposure_caps: ExposureCaps {
+                max_order_notional: env.usd("MAX_ORDER_NOTIONAL_USD"),
+                max_market_exposure: env.usd("MAX_MARKET_EXPOSURE_USD"),
+                max_total_exposure: env.usd("MAX_TOTAL_EXPOSURE_USD"),
+                allow_override: env.flag("ALLOW_CAP_OVERRIDE", false),
+            },
+            simple_improvement_ticks: env.parse(
+                "SIMPLE_IMPROVEMENT_TICKS",
+                limit_order_bot::DEFAULT_IMPROVEMENT_TICKS,
+            ),
+            simple_max_spread_cents: env.parse(
+                "SIMPLE_MAX_SPREAD_CENTS",
+                limit_order_bot::DEFAULT_MAX_SPREAD_CENTS,
+            ),
+            simple_max_combined_price: env.price(
+                "SIMPLE_MAX_COMBINED_PRICE",
+                limit_order_bot::DEFAULT_MAX_COMBINED_PRICE,
+            ),
+            ladder_price_band: env.price(
+                "LADDER_PRICE_BAND",
+                limit_order_bot::DEFAULT_LADDER_PRICE_BAND,
+            ),
+            auto_trade: env.auto_trade(),
+            metrics_require_auth: env.flag("METRICS_REQUIRE_AUTH", false),
+            cors_allowed_origins: env.parse("CORS_ALLOWED_ORIGINS", CorsOrigins::Unset),
+            rate_limit_ai_per_min: env
+                .parse("RATE_LIMIT_AI_PER_MIN", middleware::DEFAULT_AI_PER_MIN),
+            rate_limit_per_min: env
+                .parse("RATE_LIMIT_PER_MIN", middleware::DEFAULT_STANDARD_PER_MIN),
+            rate_limit_trust_proxy: env.flag("RATE_LIMIT_TRUST_PROXY", false),
+            market_cache_ttl: Duration::from_secs(env.parse(
+                "MARKET_CACHE_TTL_SECS",
+                market_cache::DEFAULT_POLYMARKET_TTL_SECS,
+            )),
+            dome_market_cache_ttl: Duration::from_secs(env.parse(
+                "DOME_MARKET_CACHE_TTL_SECS",
+                market_cache::DEFAULT_DOME_TTL_SECS,
+            )),
+            market_search_cache_ttl: Duration::from_secs(env.parse(
+                "MARKET_SEARCH_CACHE_TTL_SECS",
+                market_cache::DEFAULT_SEARCH_TTL_SECS,
+            )),
+            market_stream_poll_interval: Duration::from_millis(
+                env.positive("MARKET_STREAM_POLL_MS", market_stream::DEFAULT_POLL_MS),
+            ),
+            research_cache_ttl: Duration::from_secs(
+                env.parse("RESEARCH_CACHE_TTL_SECS", research_cache::DEFAULT_TTL_SECS),
+            ),
+            research_cache_max_entries: env.positive(
+                "RESEARCH_CACHE_MAX_ENTRIES",
+                research_cache::DEFAULT_MAX_ENTRIES,
+            ),
+            analysis_research_timeout: Duration::from_secs(env.positive(
+                "ANALYSIS_RESEARCH_TIMEOUT_SECS",
+                analyze_event_markets::DEFAULT_RESEARCH_TIMEOUT_SECS,
+            )),
+            analysis_refresh_price_threshold: env.non_negative(
+                "ANALYSIS_REFRESH_PRICE_THRESHOLD",
+                refresh_analysis::DEFAULT_PRICE_THRESHOLD,
+            ),
+            analysis_refresh_volume_threshold: env.non_negative(
+                "ANALYSIS_REFRESH_VOLUME_THRESHOLD",
+                refresh_analysis::DEFAULT_VOLUME_THRESHOLD,
+            ),
+            analysis_subscription_tick: Duration::from_secs(env.positive(
+                "ANALYSIS_SUBSCRIPTION_TICK_SECS",
+                analysis_subscriptions::DEFAULT_TICK_SECS,
+            )),
+            position_monitor_tick: Duration::from_secs(env.positive(
+                "POSITION_MONITOR_TICK_SECS",
+                position_monitor::DEFAULT_TICK_SECS,
+            )),
+            page_default_limit: env.positive("PAGE_DEFAULT_LIMIT", pagination::DEFAULT_PAGE_LIMIT),
+            page_max_limit: env.positive("PAGE_MAX_LIMIT", pagination::DEFAULT_MAX_PAGE_LIMIT),
+            idempotency_ttl: Duration::from_secs(
+                env.positive("IDEMPOTENCY_TTL_SECS", idempotency::DEFAULT_TTL_SECS),
+            ),
+            job_workers: env.positive("JOB_WORKERS", jobs::DEFAULT_WORKERS),
+            job_queue_max: env.positive("JOB_QUEUE_MAX", jobs::DEFAULT_MAX_QUEUED),
+            job_retention: Duration::from_secs(
+                env.positive("JOB_RETENTION_SECS", jobs::DEFAULT_RETENTION_SECS),
+            ),
+            health_check_timeout: Duration::from_secs(
+                env.positive("HEALTH_CHECK_TIMEOUT_SECS", health::DEFAULT_TIMEOUT_SECS),
+            ),
+            health_check_cache_ttl: Duration::from_secs(
+                env.positive("HEALTH_CHECK_CACHE_SECS", health::DEFAULT_CACHE_SECS),
+            ),
+            webhook_secret: env.string("WEBHOOK_SECRET"),
+            webhook_allowed_hosts: env
+                .list("WEBHOOK_ALLOWED_HOSTS")
+                .into_iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect(),
+            webhook_poll_interval: Duration::from_secs(env.positive(
+                "WEBHOOK_POLL_INTERVAL_SECS",
+                fill_watcher::DEFAULT_POLL_INTERVAL_SECS,
+            )),
+            webhook_watch_duration: Duration::from_secs(
+                env.positive("WEBHOOK_WATCH_SECS", fill_watcher::DEFAULT_WATCH_SECS),
+            ),
+            tracked_wallets: env
+                .string("TRACKED_WALLETS")
+                .map(|raw| wallet_snapshots::parse_tracked_wallets(&raw))
+                .unwrap_or_default(),
+            wallet_snapshot_interval: Duration::from_secs(env.positive(
+                "WALLET_SNAPSHOT_INTERVAL_SECS",
+                wallet_snapshots::DEFAULT_SNAPSHOT_INTERVAL_SECS,
+            )),
+            upstream: env.upstream(),
+        };
+
+        if config.kalshi_api_key.is_some() != env.string("KALSHI_PRIVATE_KEY_PATH").is_some() {
+            env.problems.push(
+                "KALSHI_API_KEY and KALSHI_PRIVATE_KEY_PATH must be set together".to_string(),
+            );
+        }
+        if config.kalshi_email.is_some() != config.kalshi_password.is_some() {
+            env.problems
+                .push("KALSHI_EMAIL and KALSHI_PASSWORD must be set together".to_string());
+        }
+
+        if env.problems.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError {
+                problems: env.problems,
+            })
+        }
+    }
+
+    /// How calls to `provider` are retried.
+    pub fn ai_retry_policy(&self, provider: &AiProvider) -> RetryPolicy {
+        let max_retries = match provider {
+            AiProvider::Grok => self.grok_max_retries,
+            AiProvider::OpenAi => self.openai_max_retries,
+            AiProvider::Claude => self.anthropic_max_retries,
+        };
+        RetryPolicy {
+            max_retries,
+            base_delay: self.ai_retry_base_delay,
+        }
+    }
+
+    pub fn bind_address(&self) -> SocketAddr {
+        SocketAddr::new(self.host, self.port)
+    }
+
+    pub fn summary(&self) -> ConfigSummary {
+        ConfigSummary {
+            bind_address: self.bind_address().to_string(),
+            upstream_timeout_secs: self.upstream_timeout.as_secs(),
+            polyfactual_timeout_secs: self.polyfactual_timeout.as_secs(),
+            shutdown_drain_timeout_secs: self.shutdown_drain_timeout.as_secs(),
+            dome_batch_concurrency: self.dome_batch_concurrency,
+            data_api_max_pages: self.data_api_max_pages,
+            cors_allowed_origins: self.cors_allowed_origins.to_string(),
+            models: ModelSummary {
+                grok: self.grok_model.clone(),
+                openai: self.openai_model.clone(),
+                anthropic: self.anthropic_model.clone(),
+            },
+            api_keys: ApiKeySummary {
+                grok: self.grok_api_key.is_some(),
+                openai: self.openai_api_key.is_some(),
+                anthropic: self.anthropic_api_key.is_some(),
+                dome: self.dome_api_key.is_some(),
+                polyfactual: self.polyfactual_api_key.is_some(),
+                kalshi: self.kalshi_api_key.is_some() || self.kalshi_email.is_some(),
+                polymarket_gamma: self.gamma_api_key.is_some(),
+            },
+            features: FeatureSummary {
+                trading_enabled: self.trading_enabled,
+                record_upstream_failures: self.upstream.record_failures,
+                auto_trade_enabled: self.auto_trade.is_some(),
+                metrics_require_auth: self.metrics_require_auth,
+                admin_api: self.admin_api_token.is_some(),
+                api_auth: !self.api_auth_tokens.is_empty(),
+                server_wallet: self.wallet.is_some(),
+                persistence: self.database_url.is_some(),
+                outbound_proxy: !self.http_client.proxies.is_empty(),
+                accept_invalid_certs: self.http_client.accept_invalid_certs,
+            },
+        }
+    }
+}
+
+/// Reads variables, collecting a problem for each invalid one instead of
+/// stopping at the first.
+#[derive(Default)]
+struct EnvReader {
+    problems: Vec<String>,
+}
+
+impl EnvReader {
+    /// Set and not blank.
+    fn string(&self, name: &str) -> Option<String> {
+        std::env::var(name)
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str, default: T) -> T
+    where
+        T::Err: fmt::Display,
+    {
+        match self.string(name) {
+            None => default,
+            Some(value) => value.parse().unwrap_or_else(|e| {
+                self.problems
+                    .push(format!("{} has invalid value '{}': {}", name, value, e));
+                default
+            }),
+        }
+    }
+
+    fn positive<T: FromStr + PartialOrd + Default + Copy>(&mut self, name: &str, default: T) -> T {
+        match self.string(name) {
+            None => default,
+            Some(value) => match value.parse::<T>() {
+                Ok(n) if n > T::default() => n,
+                _ => {
+                    self.problems.push(format!(
+                        "{} must be a positive whole number, got '{}'",
+                        name, value
+                    ));
+                    default
+                }
+            },
+        }
+    }
+
+    fn non_negative(&mut self, name: &str, default: f64) -> f64 {
+        match self.string(name) {
+            None => default,
+            Some(value) => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() && n >= 0.0 => n,
+                _ => {
+                    self.problems.push(format!(
+                        "{} must be a number of at least 0, got '{}'",
+                        name, value
+                    ));
+                    default
+                }
+            },
+        }
+    }
+
+    /// A share price, above 0 and at most 1.
+    fn price(&mut self, name: &str, default: f64) -> f64 {
+        match self.string(name) {
+            None => default,
+            Some(value) => match value.parse::<f64>() {
+                Ok(p) if p > 0.0 && p <= 1.0 => p,
+                _ => {
+                    self.problems.push(format!(
+                        "{} must be a price above 0 and at most 1, got '{}'",
+                        name, value
+                    ));
+                    default
+                }
+            },
+        }
+    }
+
+    /// A dollar limit; unset means no limit.
+    fn usd(&mut self, name: &str) -> Option<f64> {
+        let value = self.string(name)?;
+        match value.parse::<f64>() {
+            Ok(usd) if usd.is_finite() && usd > 0.0 => Some(usd),
+            _ => {
+                self.problems.push(format!(
+                    "{} must be a positive dollar amount, got '{}'",
+                    name, value
+                ));
+                None
+            }
+        }
+    }
+
+    /// Comma-separated, with blank entries dropped.
+    fn list(&self, name: &str) -> Vec<String> {
+        self.string(name)
+            .map(|raw| {
+                raw.split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn wallet(&mut self, name: &str) -> Option<ClobSigner> {
+        let key = self.string(name)?;
+        ClobSigner::from_private_key(&key)
+            .map_err(|_| {
+                self.problems
+                    .push(format!("{} is not a valid private key", name))
+            })
+            .ok()
+    }
+
+    /// A PEM file holding the RSA key for a Kalshi API key.
+    fn kalshi_key(&mut self, name: &str) -> Option<KalshiSigningKey> {
+        let path = self.string(name)?;
+        path.parse()
+            .map_err(|e| {
+                self.problems
+                    .push(format!("{} has invalid value '{}': {}", name, path, e))
+            })
+            .ok()
+    }
+
+    /// `POLYMARKET_API_KEY`, `_SECRET` and `_PASSPHRASE`, which only work
+    /// together.
+    fn api_credentials(&mut self) -> Option<ApiCredentials> {
+        let parts = [
+            self.string("POLYMARKET_API_KEY"),
+            self.string("POLYMARKET_API_SECRET"),
+            self.string("POLYMARKET_API_PASSPHRASE"),
+        ];
+        match parts {
+            [Some(api_key), Some(secret), Some(passphrase)] => Some(ApiCredentials {
+                api_key,
+                secret,
+                passphrase,
+            }),
+            [None, None, None] => None,
+            _ => {
+                self.problems.push(
+                    "POLYMARKET_API_KEY, POLYMARKET_API_SECRET and POLYMARKET_API_PASSPHRASE \
+                     must be set together"
+                        .to_string(),
+                );
+                None
+            }
+        }
+    }
+
+    /// The `AUTO_TRADE_*` settings, checked only when `AUTO_TRADE_ENABLED`
+    /// is set.
+    fn auto_trade(&mut self) -> Option<AutoTradeConfig> {
+        if !self.flag("AUTO_TRADE_ENABLED", false) {
+            return None;
+        }
+
+        let wallet_private_key = self.string("AUTO_TRADE_WALLET_PRIVATE_KEY");
+        match &wallet_private_key {
+            None => self.problems.push(
+                "AUTO_TRADE_WALLET_PRIVATE_KEY must be set when AUTO_TRADE_ENABLED is".to_string(),
+            ),
+            Some(key) if ClobSigner::from_private_key(key).is_err() => self
+                .problems
+                .push("AUTO_TRADE_WALLET_PRIVATE_KEY is not a valid private key".to_string()),
+            Some(_) => {}
+        }
+        let asset = self.string("AUTO_TRADE_ASSET");
+        let asset = polymarket::PolymarketClient::updown_asset(asset.as_deref())
+            .map_err(|e| self.problems.push(format!("AUTO_TRADE_ASSET: {}", e)))
+            .ok();
+        let mode = match self
+            .string("AUTO_TRADE_MODE")
+            .map(|mode| mode.to_ascii_lowercase())
+            .as_deref()
+        {
+            None | Some("simple") => Some(OrderMode::Simple),
+            Some("ladder") => Some(OrderMode::Ladder),
+            Some(other) => {
+                self.problems.push(format!(
+                    "AUTO_TRADE_MODE must be simple or ladder, got '{}'",
+                    other
+                ));
+                None
+            }
+        };
+        let bankroll_usd = self.usd("AUTO_TRADE_BANKROLL_USD");
+        if bankroll_usd.is_none() && self.string("AUTO_TRADE_BANKROLL_USD").is_none() {
+            self.problems
+                .push("AUTO_TRADE_BANKROLL_USD must be set when AUTO_TRADE_ENABLED is".to_string());
+        }
+        let dry_run = self.flag("AUTO_TRADE_DRY_RUN", false);
+        let start_delay_secs = self.parse(
+            "AUTO_TRADE_START_DELAY_SECS",
+            auto_trade::DEFAULT_START_DELAY_SECS,
+        );
+        let grace_secs = self.positive("AUTO_TRADE_GRACE_SECS", auto_trade::DEFAULT_GRACE_SECS);
+
+        Some(AutoTradeConfig {
+            asset: asset?,
+            mode: mode?,
+            bankroll_usd: bankroll_usd?,
+            wallet_private_key: wallet_private_key?,
+            dry_run,
+            start_delay: Duration::from_secs(start_delay_secs.min(auto_trade::MAX_GRACE_SECS)),
+            grace_period: Duration::from_secs(grace_secs.min(auto_trade::MAX_GRACE_SECS)),
+        })
+    }
+
+    /// Upstream rate limits, circuit breakers, failure recordings and
+    /// `UPSTREAM_RECORD_DIR`/`UPSTREAM_REPLAY_DIR`; replay wins when both
+    /// directories are set.
+    fn upstream(&mut self) -> UpstreamSettings {
+        let defaults = BreakerConfig::default();
+        let replay = match (
+            self.string("UPSTREAM_REPLAY_DIR"),
+            self.string("UPSTREAM_RECORD_DIR"),
+        ) {
+            (Some(replay), _) => ReplayMode::Replay(PathBuf::from(replay)),
+            (None, Some(record)) => ReplayMode::Record(PathBuf::from(record)),
+            (None, None) => ReplayMode::Off,
+        };
+        UpstreamSettings {
+            dome_rps: self.non_negative("DOME_RPS", dome::DEFAULT_DOME_RPS),
+            gamma_rps: self.non_negative("GAMMA_RPS", polymarket::DEFAULT_GAMMA_RPS),
+            grok_rpm: self.non_negative("GROK_RPM", ai::grok::DEFAULT_RPM),
+            openai_rpm: self.non_negative("OPENAI_RPM", ai::openai::DEFAULT_RPM),
+            anthropic_rpm: self.non_negative("ANTHROPIC_RPM", ai::claude::DEFAULT_RPM),
+            rate_limit_max_wait: Duration::from_millis(self.parse(
+                "RATE_LIMIT_MAX_WAIT_MS",
+                rate_limit::DEFAULT_MAX_WAIT.as_millis() as u64,
+            )),
+            circuit_breaker: BreakerConfig {
+                threshold: self.parse("CIRCUIT_BREAKER_THRESHOLD", defaults.threshold),
+                window: Duration::from_secs(
+                    self.positive("CIRCUIT_BREAKER_WINDOW_SECS", defaults.window.as_secs()),
+                ),
+                cooldown: Duration::from_secs(
+                    self.positive("CIRCUIT_BREAKER_COOLDOWN_SECS", defaults.cooldown.as_secs()),
+                ),
+            },
+            record_failures: self.flag("RECORD_UPSTREAM_FAILURES", false),
+            recordings_dir: self.string("UPSTREAM_RECORDINGS_DIR").map_or_else(
+                || PathBuf::from(recorder::DEFAULT_RECORDINGS_DIR),
+                PathBuf::from,
+            ),
+            max_recordings: self
+                .positive("UPSTREAM_RECORDINGS_MAX", recorder::DEFAULT_MAX_RECORDINGS),
+            replay,
+        }
+    }
+
+    /// `HTTPS_PROXY` and `HTTP_PROXY` (or their lowercase forms) with
+    /// `NO_PROXY`, `EXTRA_CA_CERT_PATH` and `DANGEROUS_ACCEPT_INVALID_CERTS`.
+    fn http_client(&mut self) -> HttpClientConfig {
+        let mut http = HttpClientConfig::default();
+        let either = |name: &str| {
+            self.string(name)
+                .or_else(|| self.string(&name.to_ascii_lowercase()))
+        };
+        let no_proxy = either("NO_PROXY");
+        let proxies = [
+            ("HTTPS_PROXY", "https", either("HTTPS_PROXY")),
+            ("HTTP_PROXY", "http", either("HTTP_PROXY")),
+        ];
+        // The URL is left out of the message, since it may hold credentials
+        for (name, scheme, url) in proxies {
+            if let Some(url) = url {
+                if let Err(e) = http.add_proxy(scheme, &url, no_proxy.as_deref()) {
+                    self.problems.push(format!("{} is invalid: {}", name, e));
+                }
+            }
+        }
+        if let Some(path) = self.string("EXTRA_CA_CERT_PATH") {
+            if let Err(e) = http.add_root_certs(&path) {
+                self.problems
+                    .push(format!("EXTRA_CA_CERT_PATH is unusable: {}", e));
+            }
+        }
+        http.accept_invalid_certs = self.flag("DANGEROUS_ACCEPT_INVALID_CERTS", false);
+        http
+    }
+
+    fn flag(&mut self, name: &str, default: bool) -> bool {
+        match self.string(name) {
+            None => default,
+            Some(value) => match value.to_ascii_lowercase().as_str() {
+                "true" | "1" | "yes" => true,
+                "false" | "0" | "no" => false,
+                _ => {
+                    self.problems
+                        .push(format!("{} must be true or false, got '{}'", name, value));
+                    default
+                }
+            },
+        }
+    }
+}
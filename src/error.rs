@@ -1,10 +1,144 @@
 use axum::{
-    http::StatusCode,
+    extract::rejection::JsonRejection,
+    http::{header, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use serde::{Deserialize, Serialize};
 use serde_json::json;
 use thiserror::Error;
+use utoipa::ToSchema;
+
+/// Longest model answer quoted back in an `AiParse` error.
+pub const MAX_AI_RAW_CONTENT_BYTES: usize = 4096;
+
+/// Request header choosing the error body layout; echoed on error
+/// responses. See [`ResponseVersion`].
+pub const RESPONSE_VERSION_HEADER: &str = "x-response-version";
+
+tokio::task_local! {
+    static RESPONSE_VERSION: ResponseVersion;
+}
+
+/// Layouts of an error body.
+///
+/// `V1` is the default while clients move off it: `error` is the message,
+/// with `code`, `status` and any details beside it. `V2`, asked for with
+/// `X-Response-Version: 2`, nests them:
+/// `{"error": {"code", "message", "status", "details"}}`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResponseVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl ResponseVersion {
+    /// `2` is `V2`; anything else, or no header, is `V1`.
+    pub fn from_header(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some("2") => ResponseVersion::V2,
+            _ => ResponseVersion::V1,
+        }
+    }
+
+    /// The layout the current request asked for; `V1` outside a request.
+    pub fn current() -> Self {
+        RESPONSE_VERSION.try_with(|v| *v).unwrap_or_default()
+    }
+
+    /// Runs `future` with errors laid out as `self`.
+    pub async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        RESPONSE_VERSION.scope(self, future).await
+    }
+
+    fn header_value(self) -> HeaderValue {
+        HeaderValue::from_static(match self {
+            ResponseVersion::V1 => "1",
+            ResponseVersion::V2 => "2",
+        })
+    }
+}
+
+/// The stable `code` of every error body. Clients should branch on these,
+/// not on messages, whose wording may change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// A failure on our side
+    InternalError,
+    /// The request is malformed or fails a check
+    ValidationFailed,
+    /// The integration a request needs has no API key (or other setting)
+    /// on this server. Details: `variable`
+    MissingApiKey,
+    /// An upstream failed in a way that may pass
+    UpstreamError,
+    /// An upstream answered with a payload we couldn't parse
+    UpstreamMalformedResponse,
+    /// A model answered with something that isn't a valid analysis.
+    /// Details: `raw_content`
+    AiParseFailed,
+    /// An upstream refused the request itself
+    UpstreamRejected,
+    /// This server is limiting the caller. Details: `retry_after_secs`
+    RateLimited,
+    /// An upstream, or our budget for it, is limiting us. Details:
+    /// `upstream`, `retry_after_secs`
+    UpstreamRateLimited,
+    /// An upstream didn't answer in time
+    UpstreamTimeout,
+    NotFound,
+    /// No market matches the identifier. Details: `market`
+    MarketNotFound,
+    /// The request clashes with one still being processed
+    Conflict,
+    Unauthorized,
+    /// The service is in read-only mode. Details: `disabled_at`,
+    /// `disabled_by`
+    TradingDisabled,
+}
+
+impl ErrorCode {
+    pub const ALL: [ErrorCode; 15] = [
+        ErrorCode::InternalError,
+        ErrorCode::ValidationFailed,
+        ErrorCode::MissingApiKey,
+        ErrorCode::UpstreamError,
+        ErrorCode::UpstreamMalformedResponse,
+        ErrorCode::AiParseFailed,
+        ErrorCode::UpstreamRejected,
+        ErrorCode::RateLimited,
+        ErrorCode::UpstreamRateLimited,
+        ErrorCode::UpstreamTimeout,
+        ErrorCode::NotFound,
+        ErrorCode::MarketNotFound,
+        ErrorCode::Conflict,
+        ErrorCode::Unauthorized,
+        ErrorCode::TradingDisabled,
+    ];
+
+    /// The code as it appears in error bodies.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InternalError => "INTERNAL_ERROR",
+            ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+            ErrorCode::MissingApiKey => "MISSING_API_KEY",
+            ErrorCode::UpstreamError => "UPSTREAM_ERROR",
+            ErrorCode::UpstreamMalformedResponse => "UPSTREAM_MALFORMED_RESPONSE",
+            ErrorCode::AiParseFailed => "AI_PARSE_FAILED",
+            ErrorCode::UpstreamRejected => "UPSTREAM_REJECTED",
+            ErrorCode::RateLimited => "RATE_LIMITED",
+            ErrorCode::UpstreamRateLimited => "UPSTREAM_RATE_LIMITED",
+            ErrorCode::UpstreamTimeout => "UPSTREAM_TIMEOUT",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::MarketNotFound => "MARKET_NOT_FOUND",
+            ErrorCode::Conflict => "CONFLICT",
+            ErrorCode::Unauthorized => "UNAUTHORIZED",
+            ErrorCode::TradingDisabled => "TRADING_DISABLED",
+        }
+    }
+}
 
 #[derive(Error, Debug)]
 pub enum AppError {
@@ -14,42 +148,298 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     Validation(String),
 
+    /// A request needs an integration whose `variable` isn't set on this
+    /// server. Served as a 400, like other requests that can't be served.
+    #[error("Validation error: {message}")]
+    MissingApiKey {
+        variable: &'static str,
+        message: String,
+    },
+
     #[error("External API error: {0}")]
     ExternalApi(String),
 
+    /// An upstream answered with a payload we couldn't parse. Asking again
+    /// would get the same answer, so it isn't retried.
+    #[error("External API error: {0}")]
+    MalformedResponse(String),
+
+    /// A model answered with something that isn't a valid analysis.
+    /// `raw_content` is what it said, cut to [`MAX_AI_RAW_CONTENT_BYTES`], so
+    /// the caller can see it or salvage it. Not retried, like
+    /// `MalformedResponse`.
+    #[error("External API error: {message}")]
+    AiParse { message: String, raw_content: String },
+
+    /// An upstream 4xx other than 404/408/429: the request itself was
+    /// refused, so retrying it can't help.
+    #[error("External API error: {0}")]
+    UpstreamRejected(String),
+
     #[error("Rate limit exceeded")]
-    RateLimit,
+    RateLimit {
+        /// How long the upstream asked us to wait, from `Retry-After`
+        retry_after: Option<std::time::Duration>,
+        /// The upstream limiting us; `None` when this server is limiting
+        /// the caller
+        upstream: Option<String>,
+    },
 
     #[error("Timeout: {0}")]
     Timeout(String),
 
     #[error("Not found: {0}")]
     NotFound(String),
+
+    /// No market matches `market` on the platform asked.
+    #[error("Not found: {message}")]
+    MarketNotFound { market: String, message: String },
+
+    /// The request clashes with one still being processed.
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+
+    #[error("Trading is disabled")]
+    TradingDisabled {
+        since: Option<String>,
+        actor: Option<String>,
+    },
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(err: sqlx::Error) -> Self {
+        AppError::Internal(anyhow::anyhow!("Database error: {}", err))
+    }
+}
+
+/// A body that isn't JSON, or isn't sent as `application/json`.
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        AppError::Validation(rejection.body_text())
+    }
+}
+
+impl From<crate::types::InvalidPrice> for AppError {
+    fn from(err: crate::types::InvalidPrice) -> Self {
+        AppError::Validation(err.to_string())
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_message) = match self {
+        let code = self.code();
+        let status = self.status();
+        let details = self.details();
+        let retry_after = self.retry_after();
+        let message = match self {
             AppError::Internal(err) => {
                 tracing::error!("Internal error: {}", err);
-                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                err.to_string()
             }
-            AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::ExternalApi(msg) => {
                 tracing::warn!("External API error: {}", msg);
-                (StatusCode::BAD_GATEWAY, msg)
+                msg
+            }
+            AppError::MalformedResponse(msg) => {
+                tracing::warn!("External API returned a malformed response: {}", msg);
+                msg
+            }
+            AppError::AiParse { message, .. } => {
+                tracing::warn!("AI returned an unparseable analysis: {}", message);
+                message
+            }
+            AppError::UpstreamRejected(msg) => {
+                tracing::warn!("External API rejected request: {}", msg);
+                msg
+            }
+            AppError::RateLimit { .. } => "Rate limit exceeded".to_string(),
+            AppError::TradingDisabled { .. } => {
+                "Trading is disabled; the service is in read-only mode".to_string()
             }
-            AppError::RateLimit => (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()),
-            AppError::Timeout(msg) => (StatusCode::GATEWAY_TIMEOUT, msg),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            AppError::Validation(msg)
+            | AppError::Timeout(msg)
+            | AppError::NotFound(msg)
+            | AppError::Conflict(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::MissingApiKey { message: msg, .. }
+            | AppError::MarketNotFound { message: msg, .. } => msg,
         };
 
-        let body = Json(json!({
-            "error": error_message,
-            "status": status.as_u16(),
-        }));
+        let version = ResponseVersion::current();
+        let body = match version {
+            ResponseVersion::V1 => {
+                let mut body = json!({
+                    "error": message,
+                    "code": code,
+                    "status": status.as_u16(),
+                });
+                if let (Some(fields), Some(serde_json::Value::Object(details))) =
+                    (body.as_object_mut(), details)
+                {
+                    fields.extend(details);
+                }
+                body
+            }
+            ResponseVersion::V2 => {
+                let mut error = json!({
+                    "code": code,
+                    "message": message,
+                    "status": status.as_u16(),
+                });
+                if let Some(details) = details {
+                    error["details"] = details;
+                }
+                json!({ "error": error })
+            }
+        };
+
+        let mut response = (status, error_body(body)).into_response();
+        let headers = response.headers_mut();
+        headers.insert(RESPONSE_VERSION_HEADER, version.header_value());
+        if let Some(retry_after) = retry_after {
+            // Round up so clients never retry early
+            let secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
+    }
+}
+
+/// Adds the current request's id, so a reported error can be matched to
+/// its logs.
+fn error_body(mut body: serde_json::Value) -> Json<serde_json::Value> {
+    if let (Some(fields), Some(request_id)) = (body.as_object_mut(), crate::request_id::current()) {
+        fields.insert("request_id".to_string(), request_id.into());
+    }
+    Json(body)
+}
+
+impl AppError {
+    /// An [`AppError::AiParse`] quoting `raw_content`, cut on a character
+    /// boundary to at most [`MAX_AI_RAW_CONTENT_BYTES`].
+    pub fn ai_parse(message: String, raw_content: &str) -> Self {
+        let mut end = raw_content.len().min(MAX_AI_RAW_CONTENT_BYTES);
+        while !raw_content.is_char_boundary(end) {
+            end -= 1;
+        }
+        AppError::AiParse {
+            message,
+            raw_content: raw_content[..end].to_string(),
+        }
+    }
+
+    /// An [`AppError::MissingApiKey`] for the unset `variable`.
+    pub fn missing_api_key(variable: &'static str) -> Self {
+        AppError::MissingApiKey {
+            variable,
+            message: format!("{} not set", variable),
+        }
+    }
+
+    /// The stable code this error is served with.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            AppError::Internal(_) => ErrorCode::InternalError,
+            AppError::Validation(_) => ErrorCode::ValidationFailed,
+            AppError::MissingApiKey { .. } => ErrorCode::MissingApiKey,
+            AppError::ExternalApi(_) => ErrorCode::UpstreamError,
+            AppError::MalformedResponse(_) => ErrorCode::UpstreamMalformedResponse,
+            AppError::AiParse { .. } => ErrorCode::AiParseFailed,
+            AppError::UpstreamRejected(_) => ErrorCode::UpstreamRejected,
+            AppError::RateLimit { upstream: None, .. } => ErrorCode::RateLimited,
+            AppError::RateLimit {
+                upstream: Some(_), ..
+            } => ErrorCode::UpstreamRateLimited,
+            AppError::Timeout(_) => ErrorCode::UpstreamTimeout,
+            AppError::NotFound(_) => ErrorCode::NotFound,
+            AppError::MarketNotFound { .. } => ErrorCode::MarketNotFound,
+            AppError::Conflict(_) => ErrorCode::Conflict,
+            AppError::Unauthorized(_) => ErrorCode::Unauthorized,
+            AppError::TradingDisabled { .. } => ErrorCode::TradingDisabled,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Validation(_) | AppError::MissingApiKey { .. } => StatusCode::BAD_REQUEST,
+            AppError::ExternalApi(_)
+            | AppError::MalformedResponse(_)
+            | AppError::AiParse { .. }
+            | AppError::UpstreamRejected(_) => StatusCode::BAD_GATEWAY,
+            AppError::RateLimit { .. } => StatusCode::TOO_MANY_REQUESTS,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::NotFound(_) | AppError::MarketNotFound { .. } => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::TradingDisabled { .. } => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+
+    /// Structured context for the error body's `details`, for the codes
+    /// that have any.
+    pub fn details(&self) -> Option<serde_json::Value> {
+        match self {
+            AppError::MissingApiKey { variable, .. } => Some(json!({ "variable": variable })),
+            AppError::AiParse { raw_content, .. } => Some(json!({ "raw_content": raw_content })),
+            AppError::RateLimit {
+                retry_after,
+                upstream,
+            } => {
+                let mut details = json!({
+                    "retry_after_secs": retry_after.map(|d| d.as_secs_f64().ceil() as u64),
+                });
+                if let Some(upstream) = upstream {
+                    details["upstream"] = upstream.as_str().into();
+                }
+                Some(details)
+            }
+            AppError::MarketNotFound { market, .. } => Some(json!({ "market": market })),
+            AppError::TradingDisabled { since, actor } => Some(json!({
+                "disabled_at": since,
+                "disabled_by": actor,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Whether the lookup failed because nothing matched, market or other.
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self,
+            AppError::NotFound(_) | AppError::MarketNotFound { .. }
+        )
+    }
+
+    /// Whether an upstream call that failed with this error is worth
+    /// repeating: transient upstream failures, timeouts and rate limits.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            AppError::ExternalApi(_) | AppError::Timeout(_) | AppError::RateLimit { .. }
+        )
+    }
+
+    /// The JSON body this error is served with, for failures reported
+    /// outside a response (stream events, job results).
+    pub async fn into_json(self) -> serde_json::Value {
+        let body = self.into_response().into_body();
+        axum::body::to_bytes(body, usize::MAX)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .unwrap_or_default()
+    }
 
-        (status, body).into_response()
+    /// The wait an upstream asked for before retrying, if any.
+    pub fn retry_after(&self) -> Option<std::time::Duration> {
+        match self {
+            AppError::RateLimit { retry_after, .. } => *retry_after,
+            _ => None,
+        }
     }
 }
 
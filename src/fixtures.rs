@@ -0,0 +1,277 @@
+//! Maintainer tool that refreshes the upstream response fixtures under
+//! `tests/fixtures/` from live endpoints:
+//!
+//! ```text
+//! cargo run -- refresh-fixtures --allow-network
+//! ```
+
+use chrono::Utc;
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const FIXTURES_DIR: &str = "tests/fixtures";
+pub const REFRESH_COMMAND: &str = "cargo run -- refresh-fixtures --allow-network";
+
+const GAMMA_API_BASE: &str = "https://gamma-api.polymarket.com";
+const DATA_API_BASE: &str = "https://data-api.polymarket.com";
+
+/// A resolved 2020 market that no longer changes shape-relevant values.
+const STABLE_MARKET_SLUG: &str = "will-joe-biden-win-the-us-2020-presidential-election";
+
+/// Keys whose id-like values are public market identifiers and are kept.
+const ID_WHITELIST: &[&str] = &[
+    "id",
+    "slug",
+    "conditionId",
+    "condition_id",
+    "questionID",
+    "clobTokenIds",
+    "token_id",
+    "asset",
+    "asset_id",
+    "market",
+    "eventSlug",
+    "negRiskMarketID",
+];
+
+/// Profile fields that identify a wallet owner.
+const PROFILE_KEYS: &[&str] = &[
+    "name",
+    "pseudonym",
+    "bio",
+    "profileImage",
+    "profileImageOptimized",
+    "displayUsernamePublic",
+];
+
+/// Payloads where `name` is a person rather than an outcome or market.
+const WALLET_FIXTURES: &[&str] = &["data_positions.json"];
+
+struct FixtureSource {
+    file: &'static str,
+    api: &'static str,
+    url: String,
+    /// A listing filtered down to one entry; only that entry is stored
+    single: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Manifest {
+    fetched_at: String,
+    refresh_command: &'static str,
+    fixtures: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    file: String,
+    api: String,
+    /// Endpoint path; query strings are omitted since they may carry a wallet
+    endpoint: String,
+    /// Upstream version header, when the API sends one
+    api_version: Option<String>,
+}
+
+/// Entry point for `refresh-fixtures`. Refuses to touch the network unless
+/// `--allow-network` is passed.
+pub async fn run_cli(args: &[String]) -> anyhow::Result<()> {
+    if !args.iter().any(|a| a == "--allow-network") {
+        anyhow::bail!(
+            "refresh-fixtures calls live Polymarket APIs; re-run as `{}`",
+            REFRESH_COMMAND
+        );
+    }
+
+    let manifest = refresh_fixtures(Path::new(FIXTURES_DIR)).await?;
+    println!(
+        "Refreshed {} fixtures in {} ({})",
+        manifest.fixtures.len(),
+        FIXTURES_DIR,
+        manifest.fetched_at
+    );
+    Ok(())
+}
+
+fn sources() -> Vec<FixtureSource> {
+    let mut sources = vec![
+        FixtureSource {
+            file: "gamma_market.json",
+            api: "gamma",
+            url: format!("{}/markets?slug={}", GAMMA_API_BASE, STABLE_MARKET_SLUG),
+            single: true,
+        },
+        FixtureSource {
+            file: "gamma_events.json",
+            api: "gamma",
+            url: format!("{}/events?closed=false&limit=5", GAMMA_API_BASE),
+            single: false,
+        },
+    ];
+
+    // Kept out of the repo so a real wallet is never committed in config either
+    match std::env::var("FIXTURE_WALLET_ADDRESS") {
+        Ok(wallet) if !wallet.trim().is_empty() => sources.push(FixtureSource {
+            file: "data_positions.json",
+            api: "data",
+            url: format!(
+                "{}/positions?user={}&limit=25",
+                DATA_API_BASE,
+                wallet.trim()
+            ),
+            single: false,
+        }),
+        _ => tracing::warn!("FIXTURE_WALLET_ADDRESS not set; skipping positions fixture"),
+    }
+
+    sources
+}
+
+async fn refresh_fixtures(dir: &Path) -> anyhow::Result<Manifest> {
+    let client = reqwest::Client::new();
+    tokio::fs::create_dir_all(dir).await?;
+
+    let mut entries = Vec::new();
+    for source in sources() {
+        let response = client.get(&source.url).send().await?.error_for_status()?;
+        let api_version = ["x-api-version", "api-version"]
+            .iter()
+            .find_map(|h| response.headers().get(*h))
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let endpoint = response.url().path().to_string();
+
+        let mut value: Value = response.json().await?;
+        if source.single {
+            value = match value {
+                Value::Array(mut items) if items.len() == 1 => items.remove(0),
+                _ => anyhow::bail!("Expected exactly one entry from {}", source.url),
+            };
+        }
+        redact_fixture(&mut value, WALLET_FIXTURES.contains(&source.file));
+
+        let path: PathBuf = dir.join(source.file);
+        tokio::fs::write(&path, serde_json::to_vec_pretty(&value)?).await?;
+        tracing::info!("Wrote {}", path.display());
+
+        entries.push(ManifestEntry {
+            file: source.file.to_string(),
+            api: source.api.to_string(),
+            endpoint,
+            api_version,
+        });
+    }
+
+    let manifest = Manifest {
+        fetched_at: Utc::now().to_rfc3339(),
+        refresh_command: REFRESH_COMMAND,
+        fixtures: entries,
+    };
+    tokio::fs::write(
+        dir.join("manifest.json"),
+        serde_json::to_vec_pretty(&manifest)?,
+    )
+    .await?;
+
+    Ok(manifest)
+}
+
+/// Replaces identifying values in place:
+///
+/// - every Ethereum address (anywhere, including inside strings)
+/// - id- and hash-like fields not in [`ID_WHITELIST`]
+/// - owner profile fields (`name` only when `wallet_payload` is set)
+///
+/// Replacements are consistent within one call, so a redacted id still
+/// matches wherever else it appears in the same fixture.
+pub fn redact_fixture(value: &mut Value, wallet_payload: bool) {
+    let mut redactor = Redactor {
+        wallet_payload,
+        replacements: HashMap::new(),
+    };
+    redactor.walk(value, None);
+}
+
+struct Redactor {
+    wallet_payload: bool,
+    replacements: HashMap<String, String>,
+}
+
+impl Redactor {
+    fn walk(&mut self, value: &mut Value, key: Option<&str>) {
+        match value {
+            Value::Object(map) => {
+                for (k, v) in map.iter_mut() {
+                    self.walk(v, Some(k.as_str()));
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    self.walk(item, key);
+                }
+            }
+            Value::String(s) => *s = self.redact_string(s, key),
+            Value::Number(_) if key.is_some_and(is_redacted_id_key) => {
+                *value = Value::String(self.placeholder("id", &value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    fn redact_string(&mut self, s: &str, key: Option<&str>) -> String {
+        if let Some(key) = key {
+            let is_profile = PROFILE_KEYS.contains(&key) && (key != "name" || self.wallet_payload);
+            if is_profile && !s.is_empty() {
+                return self.placeholder(key, s);
+            }
+            if is_redacted_id_key(key) && !s.is_empty() && !address_regex().is_match(s) {
+                return self.placeholder("id", s);
+            }
+        }
+
+        address_regex()
+            .replace_all(s, |caps: &regex::Captures| self.fake_address(&caps[0]))
+            .into_owned()
+    }
+
+    fn placeholder(&mut self, kind: &str, original: &str) -> String {
+        let next = self.replacements.len() + 1;
+        self.replacements
+            .entry(format!("{}:{}", kind, original))
+            .or_insert_with(|| format!("redacted-{}-{}", kind, next))
+            .clone()
+    }
+
+    fn fake_address(&mut self, original: &str) -> String {
+        let next = self.replacements.len() + 1;
+        self.replacements
+            .entry(format!("address:{}", original.to_ascii_lowercase()))
+            .or_insert_with(|| format!("0x{:040x}", next))
+            .clone()
+    }
+}
+
+/// Whether an id-like `key`'s value is replaced: ids, hashes and wallet
+/// fields, but not the public market identifiers in [`ID_WHITELIST`].
+pub fn is_redacted_id_key(key: &str) -> bool {
+    if ID_WHITELIST.contains(&key) {
+        return false;
+    }
+    let lower = key.to_ascii_lowercase();
+    lower.ends_with("id")
+        || lower.ends_with("_id")
+        || lower.ends_with("hash")
+        || lower == "user"
+        || lower == "owner"
+        || lower == "maker"
+        || lower == "taker"
+        || lower.ends_with("wallet")
+}
+
+fn address_regex() -> &'static Regex {
+    static ADDRESS: OnceLock<Regex> = OnceLock::new();
+    ADDRESS.get_or_init(|| Regex::new(r"0x[0-9a-fA-F]{40}\b").expect("valid address regex"))
+}
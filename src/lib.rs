@@ -1,6 +1,13 @@
 pub mod api;
 pub mod clients;
+pub mod config;
 pub mod error;
+pub mod fixtures;
+pub mod metrics;
+#[cfg(any(test, feature = "test-util"))]
+pub mod mock;
+pub mod request_id;
+pub mod storage;
 pub mod types;
 
 pub use error::{AppError, Result};
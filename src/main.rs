@@ -1,9 +1,35 @@
 use predict_os_be::api;
-use predict_os_be::clients::{PolyfactualClient, PolymarketClient};
-use predict_os_be::api::analyze_event_markets::Clients;
+use predict_os_be::api::analysis_store::AnalysisStore;
+use predict_os_be::api::analysis_subscriptions::{self, SubscriptionStore};
+use predict_os_be::api::auto_trade::{self, AutoTrader};
+use predict_os_be::api::capabilities::Capabilities;
+use predict_os_be::api::cors::CorsOrigins;
+use predict_os_be::api::exposure_caps::InFlightExposure;
+use predict_os_be::api::health::DeepHealth;
+use predict_os_be::api::idempotency::IdempotencyStore;
+use predict_os_be::api::jobs::JobQueue;
+use predict_os_be::api::market_cache::{MarketCache, MarketSearchCache};
+use predict_os_be::api::market_stream::MarketStreams;
+use predict_os_be::api::middleware::{self, ApiAuth, IpRateLimiter};
+use predict_os_be::api::research_cache::ResearchCache;
+use predict_os_be::api::runtime_config::RuntimeConfig;
+use predict_os_be::api::shutdown::{self, InFlight};
+use predict_os_be::api::position_monitor::{self, MonitorStore};
+use predict_os_be::api::position_snapshots::PositionSnapshotStore;
+use predict_os_be::api::wallet_snapshots::{self, WalletSnapshotStore};
+use predict_os_be::clients::kalshi::KalshiCredentials;
+use predict_os_be::clients::polymarket::PolymarketUrls;
+use predict_os_be::clients::{
+    DomeClient, KalshiClient, KalshiVenue, MarketDataSource, PolyfactualClient, PolymarketClient,
+    ResearchSource, SaltAllocator, TradingVenue, WebhookSender,
+};
+use predict_os_be::config::Config;
+use predict_os_be::metrics::Metrics;
+use predict_os_be::storage::Storage;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tower_http::cors::CorsLayer;
-use tracing_subscriber;
+use tokio_util::sync::CancellationToken;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -18,30 +44,256 @@ async fn main() -> anyhow::Result<()> {
     // Load environment variables
     dotenvy::dotenv().ok();
 
+    // Maintainer commands run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("refresh-fixtures") {
+        return predict_os_be::fixtures::run_cli(&args[1..]).await;
+    }
+
+    // Every setting is checked up front so one run reports all mistakes
+    let config = Arc::new(Config::from_env()?);
+    tracing::info!("Configuration: {:?}", config);
+    config.upstream.clone().install();
+    if config.http_client.accept_invalid_certs {
+        tracing::warn!("DANGEROUS_ACCEPT_INVALID_CERTS is set; upstream certificates go unchecked");
+    }
+
     // Initialize clients
-    let dome_clients = Arc::new(Clients::new().map_err(|e| anyhow::anyhow!("{}", e))?);
-    let polyfactual_client = Arc::new(
-        PolyfactualClient::new().map_err(|e| anyhow::anyhow!("{}", e))?
+    // Dome, Polyfactual and Kalshi are optional; endpoints that need them report
+    // the missing capability instead of the server refusing to start
+    let dome_client = match DomeClient::new(
+        config.dome_api_key.clone(),
+        config.dome_batch_concurrency,
+        config.upstream_timeout,
+        config.http.clone(),
+        None,
+    ) {
+        Ok(client) => Some(Arc::new(client) as Arc<dyn MarketDataSource>),
+        Err(e) => {
+            tracing::warn!("Dome integration disabled: {}", e);
+            None
+        }
+    };
+    let polyfactual_client = match PolyfactualClient::new(
+        config.polyfactual_api_key.clone(),
+        config.polyfactual_timeout,
+        config.polyfactual_max_query_length,
+        config.polyfactual_max_citations,
+        config.http.clone(),
+        None,
+    ) {
+        Ok(client) => Some(Arc::new(client) as Arc<dyn ResearchSource>),
+        Err(e) => {
+            tracing::warn!("Polyfactual integration disabled: {}", e);
+            None
+        }
+    };
+    let kalshi_client = match KalshiClient::new(
+        KalshiCredentials::from_config(&config),
+        config.upstream_timeout,
+        config.http.clone(),
+        None,
+    ) {
+        Ok(client) => Some(Arc::new(client) as Arc<dyn KalshiVenue>),
+        Err(e) => {
+            tracing::info!("Direct Kalshi access disabled: {}", e);
+            None
+        }
+    };
+    // Runs migrations; a database that can't be opened disables persistence
+    let storage = match &config.database_url {
+        Some(url) => match Storage::connect(url).await {
+            Ok(storage) => Some(Arc::new(storage)),
+            Err(e) => {
+                tracing::warn!("Persistence disabled: {}", e);
+                None
+            }
+        },
+        None => None,
+    };
+    // Subscriptions made before a restart pick up their schedule again
+    let analysis_subscriptions = match &storage {
+        Some(storage) => match storage.load_subscriptions().await {
+            Ok(subscriptions) => {
+                tracing::info!("Loaded {} analysis subscriptions", subscriptions.len());
+                SubscriptionStore::with_subscriptions(subscriptions)
+            }
+            Err(e) => {
+                tracing::warn!("Failed to load analysis subscriptions: {}", e);
+                SubscriptionStore::new()
+            }
+        },
+        None => SubscriptionStore::new(),
+    };
+    let capabilities = Capabilities::detect(
+        &config,
+        dome_client.is_some(),
+        polyfactual_client.is_some(),
+        kalshi_client.is_some(),
+        storage.is_some(),
+    );
+    tracing::info!("Capabilities: {:?}", capabilities);
+    for endpoint in capabilities.endpoints() {
+        if endpoint.enabled {
+            tracing::info!("Enabled: {}", endpoint.path);
+        } else {
+            tracing::warn!(
+                "Disabled: {} (needs {})",
+                endpoint.path,
+                endpoint.missing.join("; ")
+            );
+        }
+    }
+    let polymarket_client: Arc<dyn TradingVenue> = Arc::new(
+        PolymarketClient::new(
+            config.gamma_api_key.clone(),
+            config.data_api_max_pages,
+            config.upstream_timeout,
+            config.http.clone(),
+            PolymarketUrls::default(),
+        )
+        .with_api_credentials(config.polymarket_api_credentials.clone()),
+    );
+
+    // Cancelled on SIGINT/SIGTERM; stops background tasks and starts the drain
+    let shutdown = CancellationToken::new();
+    shutdown::spawn_signal_handler(shutdown.clone());
+
+    // Start P&L snapshots for leaderboard wallets
+    let wallet_snapshots = Arc::new(WalletSnapshotStore::new());
+    wallet_snapshots::spawn_snapshotter(
+        polymarket_client.clone(),
+        wallet_snapshots.clone(),
+        config.tracked_wallets.clone(),
+        config.wallet_snapshot_interval,
+        shutdown.clone(),
     );
-    let polymarket_client = Arc::new(PolymarketClient::new());
 
     // Create app state
+    let api_auth = ApiAuth::new(config.api_auth_tokens.clone());
+    if !api_auth.is_enabled() {
+        tracing::warn!("API_AUTH_TOKENS is not set; /api/* routes are open to anyone");
+        if config.wallet.is_some() {
+            tracing::warn!(
+                "WALLET_PRIVATE_KEY is set without API_AUTH_TOKENS; anyone can trade with the server wallet"
+            );
+        }
+    }
+
     let app_state = Arc::new(api::AppState {
-        dome_clients,
+        config: config.clone(),
+        dome_client,
         polyfactual_client,
+        kalshi_client,
         polymarket_client,
+        salt_allocator: Arc::new(SaltAllocator::new()),
+        analysis_store: Arc::new(AnalysisStore::new()),
+        analysis_subscriptions: Arc::new(analysis_subscriptions),
+        runtime_config: Arc::new(RuntimeConfig::new(config.trading_enabled)),
+        market_cache: Arc::new(MarketCache::new(
+            config.market_cache_ttl,
+            config.dome_market_cache_ttl,
+        )),
+        market_search_cache: Arc::new(MarketSearchCache::new(config.market_search_cache_ttl)),
+        market_streams: Arc::new(MarketStreams::new(config.market_stream_poll_interval)),
+        research_cache: Arc::new(ResearchCache::new(
+            config.research_cache_ttl,
+            config.research_cache_max_entries,
+        )),
+        ip_rate_limiter: Arc::new(IpRateLimiter::new(
+            config.rate_limit_ai_per_min,
+            config.rate_limit_per_min,
+            config.rate_limit_trust_proxy,
+        )),
+        api_auth,
+        idempotency: Arc::new(IdempotencyStore::new(config.idempotency_ttl)),
+        jobs: Arc::new(JobQueue::new(
+            config.job_workers,
+            config.job_queue_max,
+            config.job_retention,
+        )),
+        exposure_caps: config.exposure_caps.clone(),
+        exposure_in_flight: Arc::new(InFlightExposure::default()),
+        auto_trader: Arc::new(AutoTrader::new(config.auto_trade.clone())),
+        webhooks: Arc::new(WebhookSender::new(
+            config.http_client.clone(),
+            config.webhook_secret.clone(),
+            config.webhook_allowed_hosts.clone(),
+        )),
+        tracked_wallets: Arc::new(config.tracked_wallets.clone()),
+        wallet_snapshots,
+        position_snapshots: Arc::new(PositionSnapshotStore::new()),
+        position_monitors: Arc::new(MonitorStore::new()),
+        storage,
+        capabilities,
+        shutdown: shutdown.clone(),
+        in_flight: Arc::new(InFlight::default()),
+        metrics: Metrics::global(),
+        deep_health: Arc::new(DeepHealth::new(
+            config.health_check_timeout,
+            config.health_check_cache_ttl,
+        )),
     });
 
+    // Start scheduled re-analysis for subscriptions
+    analysis_subscriptions::spawn_scheduler(app_state.clone());
+
+    // Check position monitors' P&L thresholds
+    position_monitor::spawn_scheduler(app_state.clone());
+
+    // Trade each new 15-minute window when AUTO_TRADE_ENABLED=true
+    auto_trade::spawn_scheduler(app_state.clone());
+
+    // Forget idle clients of the per-IP limiter
+    middleware::spawn_pruner(app_state.ip_rate_limiter.clone(), shutdown.clone());
+
+    if config.cors_allowed_origins == CorsOrigins::Unset {
+        tracing::warn!(
+            "CORS_ALLOWED_ORIGINS is not set: any website can call this API from a visitor's \
+             browser. Set it to the frontend's origin(s) before exposing the server"
+        );
+    }
+
     // Create router with state
     let app = api::create_router()
-        .layer(CorsLayer::permissive())
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            shutdown::track_in_flight,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::require_api_token,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::rate_limit,
+        ))
+        .layer(axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::request_context,
+        ))
+        .layer(config.cors_allowed_origins.layer())
         .with_state(app_state.clone());
 
     // Start server
-    let listener = tokio::net::TcpListener::bind("127.0.0.1:8000").await?;
-    tracing::info!("Server listening on http://127.0.0.1:8000");
+    let bind_address = config.bind_address();
+    let listener = tokio::net::TcpListener::bind(bind_address).await?;
+    tracing::info!("Server listening on http://{}", bind_address);
 
-    axum::serve(listener, app).await?;
+    // Connect info gives the rate limiter each client's address. On a
+    // signal, stop accepting and drain in-flight work before exiting
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(shutdown.clone().cancelled_owned());
+    shutdown::serve_until_drained(
+        server.into_future(),
+        &shutdown,
+        &app_state.in_flight,
+        config.shutdown_drain_timeout,
+    )
+    .await?;
 
     Ok(())
 }
@@ -0,0 +1,235 @@
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use crate::clients::circuit_breaker::CircuitState;
+
+/// Seconds; wide enough for AI calls and Polyfactual runs as well as quick
+/// lookups.
+const LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0,
+];
+
+/// Route label for requests that matched no route, so unknown paths don't
+/// each get a series.
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Clients are built in many places (AI clients per request), so the
+/// counters live in one process-wide registry.
+static METRICS: OnceLock<Metrics> = OnceLock::new();
+
+/// An upstream API, as labelled on the `upstream_*` metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamApi {
+    Gamma,
+    Clob,
+    DataApi,
+    Dome,
+    Polyfactual,
+    Kalshi,
+    OpenAi,
+    Grok,
+    Anthropic,
+}
+
+impl UpstreamApi {
+    pub fn label(&self) -> &'static str {
+        match self {
+            UpstreamApi::Gamma => "gamma",
+            UpstreamApi::Clob => "clob",
+            UpstreamApi::DataApi => "data_api",
+            UpstreamApi::Dome => "dome",
+            UpstreamApi::Polyfactual => "polyfactual",
+            UpstreamApi::Kalshi => "kalshi",
+            UpstreamApi::OpenAi => "openai",
+            UpstreamApi::Grok => "grok",
+            UpstreamApi::Anthropic => "anthropic",
+        }
+    }
+
+    /// How the API is named in error messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            UpstreamApi::Gamma => "Gamma API",
+            UpstreamApi::Clob => "CLOB API",
+            UpstreamApi::DataApi => "Data API",
+            UpstreamApi::Dome => "Dome API",
+            UpstreamApi::Polyfactual => "Polyfactual API",
+            UpstreamApi::Kalshi => "Kalshi API",
+            UpstreamApi::OpenAi => "OpenAI API",
+            UpstreamApi::Grok => "Grok API",
+            UpstreamApi::Anthropic => "Anthropic API",
+        }
+    }
+}
+
+/// Counters and histograms served on `GET /metrics` in the Prometheus text
+/// format.
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+    upstream_requests: IntCounterVec,
+    upstream_errors: IntCounterVec,
+    upstream_request_duration: HistogramVec,
+    orders_placed: IntCounterVec,
+    ai_retries: IntCounterVec,
+    upstream_circuit_state: IntGaugeVec,
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics").finish_non_exhaustive()
+    }
+}
+
+impl Metrics {
+    pub fn global() -> &'static Metrics {
+        METRICS.get_or_init(Metrics::new)
+    }
+
+    fn new() -> Self {
+        let registry = Registry::new();
+        let counter = |name: &str, help: &str, labels: &[&str]| {
+            let counter = IntCounterVec::new(Opts::new(name, help), labels)
+                .expect("metric definition is valid");
+            registry
+                .register(Box::new(counter.clone()))
+                .expect("metric names are unique");
+            counter
+        };
+        let histogram = |name: &str, help: &str, labels: &[&str]| {
+            let opts = HistogramOpts::new(name, help).buckets(LATENCY_BUCKETS.to_vec());
+            let histogram = HistogramVec::new(opts, labels).expect("metric definition is valid");
+            registry
+                .register(Box::new(histogram.clone()))
+                .expect("metric names are unique");
+            histogram
+        };
+        let gauge = |name: &str, help: &str, labels: &[&str]| {
+            let gauge = IntGaugeVec::new(Opts::new(name, help), labels)
+                .expect("metric definition is valid");
+            registry
+                .register(Box::new(gauge.clone()))
+                .expect("metric names are unique");
+            gauge
+        };
+
+        Self {
+            http_requests: counter(
+                "http_requests_total",
+                "Requests handled, by route, method and status",
+                &["route", "method", "status"],
+            ),
+            http_request_duration: histogram(
+                "http_request_duration_seconds",
+                "Time to produce a response, by route and method",
+                &["route", "method"],
+            ),
+            upstream_requests: counter(
+                "upstream_requests_total",
+                "Calls made to upstream APIs",
+                &["api"],
+            ),
+            upstream_errors: counter(
+                "upstream_errors_total",
+                "Upstream calls that failed, by HTTP status or `transport`",
+                &["api", "reason"],
+            ),
+            upstream_request_duration: histogram(
+                "upstream_request_duration_seconds",
+                "Time until an upstream API answered",
+                &["api"],
+            ),
+            orders_placed: counter(
+                "orders_placed_total",
+                "Orders submitted by the limit order bot, by resulting status",
+                &["status"],
+            ),
+            ai_retries: counter(
+                "ai_retries_total",
+                "AI calls retried after a transient failure",
+                &["provider"],
+            ),
+            upstream_circuit_state: gauge(
+                "upstream_circuit_state",
+                "Circuit breaker per upstream: 0 closed, 1 half-open, 2 open",
+                &["api"],
+            ),
+            registry,
+        }
+    }
+
+    /// Records a handled request. `route` is the matched route template,
+    /// or `None` when nothing matched.
+    pub fn observe_request(
+        &self,
+        route: Option<&str>,
+        method: &str,
+        status: u16,
+        elapsed: Duration,
+    ) {
+        let route = route.unwrap_or(UNMATCHED_ROUTE);
+        self.http_requests
+            .with_label_values(&[route, method, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[route, method])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Records an upstream call: its HTTP status, or `None` when no
+    /// response arrived.
+    pub fn observe_upstream(&self, api: UpstreamApi, status: Option<u16>, elapsed: Duration) {
+        let api = api.label();
+        self.upstream_requests.with_label_values(&[api]).inc();
+        self.upstream_request_duration
+            .with_label_values(&[api])
+            .observe(elapsed.as_secs_f64());
+        match status {
+            Some(status) if status < 400 => {}
+            Some(status) => self
+                .upstream_errors
+                .with_label_values(&[api, &status.to_string()])
+                .inc(),
+            None => self
+                .upstream_errors
+                .with_label_values(&[api, "transport"])
+                .inc(),
+        }
+    }
+
+    pub fn order_placed(&self, status: &str) {
+        self.orders_placed.with_label_values(&[status]).inc();
+    }
+
+    pub fn ai_retried(&self, provider: &str, retries: u32) {
+        if retries > 0 {
+            self.ai_retries
+                .with_label_values(&[provider])
+                .inc_by(u64::from(retries));
+        }
+    }
+
+    pub fn circuit_state(&self, api: UpstreamApi, state: CircuitState) {
+        self.upstream_circuit_state
+            .with_label_values(&[api.label()])
+            .set(state.gauge_value());
+    }
+
+    /// Everything registered, in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        TextEncoder::new()
+            .encode_to_string(&self.registry.gather())
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to encode metrics: {}", e);
+                String::new()
+            })
+    }
+
+    pub fn content_type() -> &'static str {
+        prometheus::TEXT_FORMAT
+    }
+}
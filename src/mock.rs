@@ -0,0 +1,975 @@
+//! In-memory stand-ins for the upstream clients, so handlers can be
+//! exercised through [`create_router`](crate::api::create_router) without the network.
+//!
+//! Each mock answers from data seeded with its `insert_*` methods, records
+//! the methods it was called with, and can be told to fail a method with
+//! [`fail`](MockVenue::fail). Build an [`AppState`] over them with
+//! [`app_state`].
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::api::analysis_store::AnalysisStore;
+use crate::api::analysis_subscriptions::SubscriptionStore;
+use crate::api::auto_trade::AutoTrader;
+use crate::api::capabilities::Capabilities;
+use crate::api::cors::CorsOrigins;
+use crate::api::exposure_caps::{ExposureCaps, InFlightExposure};
+use crate::api::health::DeepHealth;
+use crate::api::idempotency::IdempotencyStore;
+use crate::api::jobs::JobQueue;
+use crate::api::market_cache::{MarketCache, MarketSearchCache};
+use crate::api::market_stream::MarketStreams;
+use crate::api::middleware::{ApiAuth, IpRateLimiter};
+use crate::api::position_monitor::MonitorStore;
+use crate::api::position_snapshots::PositionSnapshotStore;
+use crate::api::research_cache::ResearchCache;
+use crate::api::runtime_config::RuntimeConfig;
+use crate::api::shutdown::InFlight;
+use crate::api::wallet_snapshots::WalletSnapshotStore;
+use crate::api::AppState;
+use crate::clients::ai::pricing::ModelPrices;
+use crate::clients::ai::prompts::FewShot;
+use crate::clients::clob_signing::{MarketParams, WalletAuth};
+use crate::clients::polymarket::{
+    CancelResult, ClobOrder, ClobTrade, MarketSearch, MarketSearchPage, PolymarketEvent,
+    PositionData, WalletPnl, WalletPosition, WalletTrade,
+};
+use crate::clients::{
+    HttpClientConfig, KalshiVenue, MarketDataSource, ResearchSource, SaltAllocator, TradingVenue,
+    UpstreamSettings, WebhookSender,
+};
+use crate::config::Config;
+use crate::metrics::Metrics;
+use crate::request_id;
+use crate::types::{
+    Candle, CandleInterval, Citation, MarketData, MarketRef, OrderBook, OrderResult, Outcome,
+    Platform, PolyfactualResearchResponse, Price, ResponseMetadata, TimeoutBudget,
+};
+use crate::{AppError, Result};
+
+type ErrorFactory = Box<dyn Fn() -> AppError + Send + Sync>;
+type PlaceHook = Box<dyn Fn(&ClobOrder) + Send + Sync>;
+
+/// Calls made to a mock, and the failures it has been told to inject.
+#[derive(Default)]
+struct Faults {
+    failing: Mutex<HashMap<&'static str, ErrorFactory>>,
+    /// Failures of a single call, by method and call number from 1
+    failing_calls: Mutex<HashMap<(&'static str, usize), ErrorFactory>>,
+    calls: Mutex<Vec<&'static str>>,
+}
+
+impl Faults {
+    fn set(&self, method: &'static str, error: ErrorFactory) {
+        self.failing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(method, error);
+    }
+
+    fn set_call(&self, method: &'static str, call: usize, error: ErrorFactory) {
+        self.failing_calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert((method, call), error);
+    }
+
+    fn clear(&self, method: &str) {
+        self.failing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(method);
+    }
+
+    /// Records a call to `method`, failing it when a fault is set.
+    fn enter(&self, method: &'static str) -> Result<()> {
+        let call = {
+            let mut calls = self.calls.lock().unwrap_or_else(|e| e.into_inner());
+            calls.push(method);
+            calls.iter().filter(|m| **m == method).count()
+        };
+        if let Some(error) = self
+            .failing_calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&(method, call))
+        {
+            return Err(error());
+        }
+        match self
+            .failing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(method)
+        {
+            Some(error) => Err(error()),
+            None => Ok(()),
+        }
+    }
+
+    fn calls(&self) -> Vec<&'static str> {
+        self.calls.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// A two-outcome Polymarket market with the given outcome names, token ids
+/// and prices.
+pub fn binary_market(slug: &str, outcomes: [(&str, &str, f64); 2]) -> MarketData {
+    MarketData {
+        id: format!("id-{}", slug),
+        question: format!("Mock market {}", slug),
+        slug: Some(slug.to_string()),
+        ticker: None,
+        platform: Platform::Polymarket,
+        condition_id: Some(format!("0x{:064x}", slug.len())),
+        outcomes: outcomes
+            .iter()
+            .map(|(name, token_id, price)| Outcome {
+                id: token_id.to_string(),
+                name: name.to_string(),
+                price: Price::from_decimal(*price).expect("mock price is a probability"),
+                volume: None,
+            })
+            .collect(),
+        volume: Some(1000.0),
+        liquidity: Some(500.0),
+        outcome_ordering: "yes_no".to_string(),
+        end_date: None,
+        closed: false,
+        resolved_outcome: None,
+    }
+}
+
+/// Market lookups from seeded markets; unknown identifiers are
+/// `MarketNotFound`.
+#[derive(Default)]
+pub struct MockMarketData {
+    faults: Faults,
+    markets: Mutex<HashMap<String, MarketData>>,
+    candles: Mutex<HashMap<String, Vec<Candle>>>,
+}
+
+impl MockMarketData {
+    pub fn insert_market(&self, platform: Platform, identifier: &str, market: MarketData) {
+        lock(&self.markets).insert(market_key(platform, identifier), market);
+    }
+
+    /// Candles for a market's condition id (or ticker), served whatever the
+    /// requested range; oldest first.
+    pub fn insert_candles(&self, platform: Platform, identifier: &str, candles: Vec<Candle>) {
+        lock(&self.candles).insert(market_key(platform, identifier), candles);
+    }
+
+    /// Makes every call to `method` fail with the error `error` builds.
+    pub fn fail(&self, method: &'static str, error: impl Fn() -> AppError + Send + Sync + 'static) {
+        self.faults.set(method, Box::new(error));
+    }
+
+    pub fn recover(&self, method: &str) {
+        self.faults.clear(method);
+    }
+
+    /// Methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.faults.calls()
+    }
+}
+
+fn market_key(platform: Platform, identifier: &str) -> String {
+    format!("{:?}:{}", platform, identifier)
+}
+
+#[async_trait]
+impl MarketDataSource for MockMarketData {
+    async fn get_market(&self, platform: Platform, identifier: &str) -> Result<MarketData> {
+        self.faults.enter("get_market")?;
+        lock(&self.markets)
+            .get(&market_key(platform, identifier))
+            .cloned()
+            .ok_or_else(|| AppError::MarketNotFound {
+                market: identifier.to_string(),
+                message: format!(
+                    "Unknown market identifier: {} on {:?}",
+                    identifier, platform
+                ),
+            })
+    }
+
+    async fn get_markets(&self, markets: &[MarketRef]) -> Vec<Result<MarketData>> {
+        let mut results = Vec::with_capacity(markets.len());
+        for market in markets {
+            results.push(self.get_market(market.platform, &market.identifier).await);
+        }
+        results
+    }
+
+    async fn get_candles(
+        &self,
+        platform: Platform,
+        identifier: &str,
+        _interval: CandleInterval,
+        _start: DateTime<Utc>,
+        _end: DateTime<Utc>,
+    ) -> Result<Vec<Candle>> {
+        self.faults.enter("get_candles")?;
+        Ok(lock(&self.candles)
+            .get(&market_key(platform, identifier))
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.faults.enter("ping")
+    }
+}
+
+/// Direct Kalshi access from seeded markets (by ticker) and positions.
+#[derive(Default)]
+pub struct MockKalshi {
+    faults: Faults,
+    markets: Mutex<HashMap<String, MarketData>>,
+    positions: Mutex<Vec<PositionData>>,
+}
+
+impl MockKalshi {
+    pub fn insert_market(&self, market: MarketData) {
+        let ticker = market.ticker.clone().unwrap_or_else(|| market.id.clone());
+        lock(&self.markets).insert(ticker, market);
+    }
+
+    /// The account's positions; a ticker filter matches their `<ticker>:`
+    /// token id prefix.
+    pub fn insert_positions(&self, positions: Vec<PositionData>) {
+        lock(&self.positions).extend(positions);
+    }
+
+    /// Makes every call to `method` fail with the error `error` builds.
+    pub fn fail(&self, method: &'static str, error: impl Fn() -> AppError + Send + Sync + 'static) {
+        self.faults.set(method, Box::new(error));
+    }
+
+    /// Methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.faults.calls()
+    }
+}
+
+#[async_trait]
+impl KalshiVenue for MockKalshi {
+    async fn get_market(&self, ticker: &str) -> Result<MarketData> {
+        self.faults.enter("get_market")?;
+        lock(&self.markets)
+            .get(ticker)
+            .cloned()
+            .ok_or_else(|| AppError::MarketNotFound {
+                market: ticker.to_string(),
+                message: format!("Unknown market identifier: {} on Kalshi", ticker),
+            })
+    }
+
+    async fn get_positions(&self, ticker: Option<&str>) -> Result<Vec<PositionData>> {
+        self.faults.enter("get_positions")?;
+        Ok(lock(&self.positions)
+            .iter()
+            .filter(|p| ticker.is_none_or(|t| p.token_id.starts_with(&format!("{}:", t))))
+            .cloned()
+            .collect())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.faults.enter("ping")
+    }
+}
+
+/// Research that answers every query with a fixed answer.
+pub struct MockResearch {
+    faults: Faults,
+    answer: Mutex<(String, Vec<Citation>)>,
+    delay: Mutex<Duration>,
+}
+
+impl Default for MockResearch {
+    fn default() -> Self {
+        Self {
+            faults: Faults::default(),
+            answer: Mutex::new(("Mock research answer".to_string(), Vec::new())),
+            delay: Mutex::new(Duration::ZERO),
+        }
+    }
+}
+
+impl MockResearch {
+    pub fn set_answer(&self, answer: &str, citations: Vec<Citation>) {
+        *lock(&self.answer) = (answer.to_string(), citations);
+    }
+
+    /// Makes each research call take `delay` before answering.
+    pub fn set_delay(&self, delay: Duration) {
+        *lock(&self.delay) = delay;
+    }
+
+    /// Makes every call to `method` fail with the error `error` builds.
+    pub fn fail(&self, method: &'static str, error: impl Fn() -> AppError + Send + Sync + 'static) {
+        self.faults.set(method, Box::new(error));
+    }
+
+    pub fn recover(&self, method: &str) {
+        self.faults.clear(method);
+    }
+
+    /// Methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.faults.calls()
+    }
+}
+
+#[async_trait]
+impl ResearchSource for MockResearch {
+    /// Fails like the real client when the answer's delay exceeds
+    /// `timeout`.
+    async fn research(
+        &self,
+        _query: String,
+        timeout: Option<Duration>,
+    ) -> Result<PolyfactualResearchResponse> {
+        self.faults.enter("research")?;
+        let delay = *lock(&self.delay);
+        if let Some(limit) = timeout.filter(|limit| delay > *limit) {
+            tokio::time::sleep(limit).await;
+            return Err(AppError::Timeout(format!(
+                "Polyfactual API request timed out after {}s",
+                limit.as_secs()
+            )));
+        }
+        tokio::time::sleep(delay).await;
+        let (answer, citations) = lock(&self.answer).clone();
+        Ok(PolyfactualResearchResponse {
+            answer,
+            citations,
+            warnings: Vec::new(),
+            metadata: ResponseMetadata {
+                timestamp: Utc::now().to_rfc3339(),
+                execution_time_ms: 0,
+                model_used: None,
+                retries: 0,
+                degraded_features: Vec::new(),
+                custom_prompt: false,
+                dry_run: false,
+                cache_hit: None,
+                request_id: request_id::current(),
+                ai_usage: None,
+                ai_provider: None,
+                query_compression: None,
+                timeout_budget: timeout.map(|limit| TimeoutBudget {
+                    limit_secs: limit.as_secs(),
+                    timed_out: false,
+                }),
+            },
+        })
+    }
+}
+
+/// A Polymarket stand-in. Placed orders rest as `LIVE` until cancelled, so
+/// they can be listed, looked up and cancelled afterwards. Wallets match in
+/// any letter case, as on the data API.
+#[derive(Default)]
+pub struct MockVenue {
+    faults: Faults,
+    markets: Mutex<HashMap<String, MarketData>>,
+    events: Mutex<HashMap<String, PolymarketEvent>>,
+    books: Mutex<HashMap<String, OrderBook>>,
+    price_history: Mutex<HashMap<String, Vec<(i64, f64)>>>,
+    wallet_positions: Mutex<HashMap<String, Vec<WalletPosition>>>,
+    market_positions: Mutex<HashMap<String, Vec<PositionData>>>,
+    /// Wallets whose position lookups fail
+    failing_wallets: Mutex<HashSet<String>>,
+    trade_history: Mutex<HashMap<String, Vec<WalletTrade>>>,
+    market_trades: Mutex<HashMap<String, Vec<WalletTrade>>>,
+    orders: Mutex<HashMap<String, ClobOrder>>,
+    next_order: AtomicU64,
+    /// Placements, numbered from 1, accepted but never booked
+    dropped: Mutex<HashSet<u64>>,
+    on_place: Mutex<Option<PlaceHook>>,
+}
+
+impl MockVenue {
+    /// Served by slug (including as an up/down window's market).
+    pub fn insert_market(&self, market: MarketData) {
+        let slug = market.slug.clone().unwrap_or_else(|| market.id.clone());
+        lock(&self.markets).insert(slug, market);
+    }
+
+    pub fn insert_event(&self, event: PolymarketEvent) {
+        lock(&self.events).insert(event.slug.clone(), event);
+    }
+
+    pub fn insert_order_book(&self, book: OrderBook) {
+        lock(&self.books).insert(book.token_id.clone(), book);
+    }
+
+    pub fn insert_price_history(&self, token_id: &str, history: Vec<(i64, f64)>) {
+        lock(&self.price_history).insert(token_id.to_string(), history);
+    }
+
+    pub fn insert_wallet_positions(&self, wallet: &str, positions: Vec<WalletPosition>) {
+        lock(&self.wallet_positions).insert(wallet.to_lowercase(), positions);
+    }
+
+    pub fn insert_market_positions(&self, wallet: &str, positions: Vec<PositionData>) {
+        lock(&self.market_positions).insert(wallet.to_lowercase(), positions);
+    }
+
+    /// Fails `get_market_position` for `wallet` only.
+    pub fn fail_wallet(&self, wallet: &str) {
+        lock(&self.failing_wallets).insert(wallet.to_lowercase());
+    }
+
+    pub fn insert_trade_history(&self, wallet: &str, trades: Vec<WalletTrade>) {
+        lock(&self.trade_history).insert(wallet.to_lowercase(), trades);
+    }
+
+    /// Fills in the market with `condition_id`, served newest first.
+    pub fn insert_market_trades(&self, condition_id: &str, trades: Vec<WalletTrade>) {
+        lock(&self.market_trades).insert(condition_id.to_string(), trades);
+    }
+
+    pub fn insert_order(&self, order: ClobOrder) {
+        lock(&self.orders).insert(order.id.clone(), order);
+    }
+
+    /// Every order placed or seeded, keyed by id.
+    pub fn orders(&self) -> HashMap<String, ClobOrder> {
+        lock(&self.orders).clone()
+    }
+
+    /// Makes every call to `method` fail with the error `error` builds.
+    pub fn fail(&self, method: &'static str, error: impl Fn() -> AppError + Send + Sync + 'static) {
+        self.faults.set(method, Box::new(error));
+    }
+
+    /// Makes only the `call`th call to `method` (counting from 1) fail.
+    pub fn fail_call(
+        &self,
+        method: &'static str,
+        call: usize,
+        error: impl Fn() -> AppError + Send + Sync + 'static,
+    ) {
+        self.faults.set_call(method, call, Box::new(error));
+    }
+
+    /// Accepts the `placement`th order placed (counting from 1) without
+    /// booking it, like a submission the exchange silently drops: it is
+    /// neither listed as open nor found by id.
+    pub fn drop_placement(&self, placement: u64) {
+        lock(&self.dropped).insert(placement);
+    }
+
+    /// Runs `hook` with each order the venue accepts, before the placement
+    /// returns, e.g. to change server state in the middle of a run.
+    pub fn on_place(&self, hook: impl Fn(&ClobOrder) + Send + Sync + 'static) {
+        *lock(&self.on_place) = Some(Box::new(hook));
+    }
+
+    pub fn recover(&self, method: &str) {
+        self.faults.clear(method);
+    }
+
+    /// Methods called so far, in order.
+    pub fn calls(&self) -> Vec<&'static str> {
+        self.faults.calls()
+    }
+
+    fn cancel(&self, order_ids: impl IntoIterator<Item = String>) -> CancelResult {
+        let mut orders = lock(&self.orders);
+        let mut result = CancelResult::default();
+        for order_id in order_ids {
+            match orders.get_mut(&order_id) {
+                Some(order) if order.status == "LIVE" => {
+                    order.status = "CANCELED".to_string();
+                    result.canceled.push(order_id);
+                }
+                Some(_) => {
+                    result
+                        .not_canceled
+                        .insert(order_id, "order can't be canceled".to_string());
+                }
+                None => {
+                    result
+                        .not_canceled
+                        .insert(order_id, "order not found".to_string());
+                }
+            }
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl TradingVenue for MockVenue {
+    async fn get_market_by_slug(&self, slug: &str) -> Result<MarketData> {
+        self.faults.enter("get_market_by_slug")?;
+        lock(&self.markets)
+            .get(slug)
+            .cloned()
+            .ok_or_else(|| AppError::MarketNotFound {
+                market: slug.to_string(),
+                message: format!("Gamma market not found: {}", slug),
+            })
+    }
+
+    async fn get_event_by_slug(&self, slug: &str) -> Result<PolymarketEvent> {
+        self.faults.enter("get_event_by_slug")?;
+        lock(&self.events)
+            .get(slug)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("Gamma event not found: {}", slug)))
+    }
+
+    /// Seeded markets by volume, the query matched against question and
+    /// slug. Tags aren't modelled, so `tag` matches every market.
+    async fn search_markets(&self, search: &MarketSearch) -> Result<MarketSearchPage> {
+        self.faults.enter("search_markets")?;
+        let query = search.query.as_deref().map(str::to_lowercase);
+        let at_least =
+            |value: Option<f64>, min: Option<f64>| min.is_none_or(|min| value >= Some(min));
+        let mut found: Vec<MarketData> = lock(&self.markets)
+            .values()
+            .filter(|m| {
+                query.as_deref().is_none_or(|q| {
+                    m.question.to_lowercase().contains(q)
+                        || m.slug.as_deref().is_some_and(|s| s.contains(q))
+                })
+            })
+            .filter(|m| !(search.active_only && m.closed))
+            .filter(|m| at_least(m.volume, search.min_volume))
+            .filter(|m| at_least(m.liquidity, search.min_liquidity))
+            .cloned()
+            .collect();
+        found.sort_by(|a, b| b.volume.unwrap_or(0.0).total_cmp(&a.volume.unwrap_or(0.0)));
+
+        let has_more = found.len() > search.offset + search.limit;
+        let markets = found
+            .into_iter()
+            .skip(search.offset)
+            .take(search.limit)
+            .map(Into::into)
+            .collect();
+        Ok(MarketSearchPage { markets, has_more })
+    }
+
+    async fn resolve_updown_market(
+        &self,
+        slug: &str,
+        _window_start: DateTime<Utc>,
+    ) -> Result<MarketData> {
+        self.faults.enter("resolve_updown_market")?;
+        lock(&self.markets)
+            .get(slug)
+            .cloned()
+            .ok_or_else(|| AppError::MarketNotFound {
+                market: slug.to_string(),
+                message: format!("Gamma market not found: {}", slug),
+            })
+    }
+
+    async fn get_market_params(&self, _token_id: &str) -> MarketParams {
+        let _ = self.faults.enter("get_market_params");
+        MarketParams::default()
+    }
+
+    async fn get_order_book(&self, token_id: &str) -> Result<OrderBook> {
+        self.faults.enter("get_order_book")?;
+        lock(&self.books)
+            .get(token_id)
+            .cloned()
+            .ok_or_else(|| AppError::NotFound(format!("No order book for token {}", token_id)))
+    }
+
+    async fn get_price_history(&self, token_id: &str) -> Result<Vec<(i64, f64)>> {
+        self.faults.enter("get_price_history")?;
+        Ok(lock(&self.price_history)
+            .get(token_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_wallet_pnl(&self, wallet_address: &str) -> Result<WalletPnl> {
+        self.faults.enter("get_wallet_pnl")?;
+        let positions = lock(&self.wallet_positions)
+            .get(&wallet_address.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        Ok(WalletPnl {
+            realized_pnl: 0.0,
+            unrealized_pnl: positions
+                .iter()
+                .map(|p| p.size * (p.cur_price - p.avg_price))
+                .sum(),
+            volume: positions.iter().map(|p| p.size * p.avg_price).sum(),
+        })
+    }
+
+    async fn get_wallet_positions(&self, wallet_address: &str) -> Result<Vec<WalletPosition>> {
+        self.faults.enter("get_wallet_positions")?;
+        Ok(lock(&self.wallet_positions)
+            .get(&wallet_address.to_lowercase())
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    async fn get_market_position(
+        &self,
+        wallet_address: &str,
+        _condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<PositionData>> {
+        self.faults.enter("get_market_position")?;
+        if lock(&self.failing_wallets).contains(&wallet_address.to_lowercase()) {
+            return Err(AppError::ExternalApi("Data API error 503".to_string()));
+        }
+        Ok(lock(&self.market_positions)
+            .get(&wallet_address.to_lowercase())
+            .map(|positions| {
+                positions
+                    .iter()
+                    .filter(|p| token_ids.is_empty() || token_ids.contains(&p.token_id))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn get_trade_history(
+        &self,
+        wallet_address: &str,
+        _condition_id: Option<&str>,
+        token_ids: &[String],
+    ) -> Result<Vec<WalletTrade>> {
+        self.faults.enter("get_trade_history")?;
+        Ok(lock(&self.trade_history)
+            .get(&wallet_address.to_lowercase())
+            .map(|trades| {
+                trades
+                    .iter()
+                    .filter(|t| token_ids.is_empty() || token_ids.contains(&t.asset))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn get_market_trades(&self, condition_id: &str) -> Result<Vec<WalletTrade>> {
+        self.faults.enter("get_market_trades")?;
+        let mut trades = lock(&self.market_trades)
+            .get(condition_id)
+            .cloned()
+            .unwrap_or_default();
+        trades.sort_by_key(|t| std::cmp::Reverse(t.timestamp));
+        Ok(trades)
+    }
+
+    async fn get_open_orders(
+        &self,
+        _auth: &WalletAuth,
+        token_ids: &[String],
+    ) -> Result<Vec<ClobOrder>> {
+        self.faults.enter("get_open_orders")?;
+        let mut open: Vec<ClobOrder> = lock(&self.orders)
+            .values()
+            .filter(|o| o.status == "LIVE" && token_ids.contains(&o.asset_id))
+            .cloned()
+            .collect();
+        open.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(open)
+    }
+
+    async fn get_wallet_open_orders(&self, _auth: &WalletAuth) -> Result<Vec<ClobOrder>> {
+        self.faults.enter("get_wallet_open_orders")?;
+        let mut open: Vec<ClobOrder> = lock(&self.orders)
+            .values()
+            .filter(|o| o.status == "LIVE")
+            .cloned()
+            .collect();
+        open.sort_by(|a, b| a.id.cmp(&b.id));
+        Ok(open)
+    }
+
+    async fn get_trades(
+        &self,
+        _auth: &WalletAuth,
+        _token_ids: &[String],
+    ) -> Result<Vec<ClobTrade>> {
+        self.faults.enter("get_trades")?;
+        Ok(Vec::new())
+    }
+
+    async fn get_order(&self, _auth: &WalletAuth, order_id: &str) -> Result<Option<ClobOrder>> {
+        self.faults.enter("get_order")?;
+        Ok(lock(&self.orders).get(order_id).cloned())
+    }
+
+    async fn place_order(
+        &self,
+        _auth: &WalletAuth,
+        token_id: &str,
+        side: &str,
+        price: Price,
+        size: f64,
+        _salt: u64,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<OrderResult> {
+        self.faults.enter("place_order")?;
+        let placement = self.next_order.fetch_add(1, Ordering::Relaxed) + 1;
+        let order_id = format!("0x{:064x}", placement);
+        let order = ClobOrder {
+            id: order_id.clone(),
+            asset_id: token_id.to_string(),
+            status: "LIVE".to_string(),
+            side: side.to_ascii_uppercase(),
+            price: price.value().to_string(),
+            original_size: size.to_string(),
+            size_matched: "0".to_string(),
+            outcome: String::new(),
+            expiration: expires_at.map_or(0, |at| at.timestamp()).to_string(),
+        };
+        if !lock(&self.dropped).contains(&placement) {
+            self.insert_order(order.clone());
+            if let Some(hook) = lock(&self.on_place).as_ref() {
+                hook(&order);
+            }
+        }
+        Ok(OrderResult {
+            token_id: token_id.to_string(),
+            outcome: String::new(),
+            side: side.to_string(),
+            price,
+            size,
+            order_id: Some(order_id),
+            status: crate::types::OrderStatus::Pending,
+            filled_size: None,
+            error: None,
+        })
+    }
+
+    async fn cancel_orders(
+        &self,
+        _auth: &WalletAuth,
+        order_ids: &[String],
+    ) -> Result<CancelResult> {
+        self.faults.enter("cancel_orders")?;
+        Ok(self.cancel(order_ids.iter().cloned()))
+    }
+
+    async fn cancel_order(&self, _auth: &WalletAuth, order_id: &str) -> Result<CancelResult> {
+        self.faults.enter("cancel_order")?;
+        Ok(self.cancel([order_id.to_string()]))
+    }
+
+    async fn cancel_all(&self, _auth: &WalletAuth, token_ids: &[String]) -> Result<CancelResult> {
+        self.faults.enter("cancel_all")?;
+        let live: Vec<String> = lock(&self.orders)
+            .values()
+            .filter(|o| o.status == "LIVE" && token_ids.contains(&o.asset_id))
+            .map(|o| o.id.clone())
+            .collect();
+        Ok(self.cancel(live))
+    }
+
+    async fn ping(&self) -> Result<()> {
+        self.faults.enter("ping")
+    }
+}
+
+/// Settings with every optional integration, limit and token unset.
+pub fn config() -> Config {
+    Config {
+        host: std::net::Ipv4Addr::LOCALHOST.into(),
+        port: 0,
+        grok_api_key: None,
+        openai_api_key: None,
+        anthropic_api_key: None,
+        dome_api_key: None,
+        polyfactual_api_key: None,
+        kalshi_api_key: None,
+        kalshi_private_key: None,
+        kalshi_email: None,
+        kalshi_password: None,
+        gamma_api_key: None,
+        admin_api_token: None,
+        database_url: None,
+        api_auth_tokens: Vec::new(),
+        wallet: None,
+        polymarket_api_credentials: None,
+        polygon_rpc_url: None,
+        grok_model: None,
+        openai_model: None,
+        anthropic_model: None,
+        grok_base_url: None,
+        openai_base_url: None,
+        anthropic_base_url: None,
+        ai_model_prices: ModelPrices::default(),
+        ai_few_shot: FewShot::default(),
+        grok_max_retries: 2,
+        openai_max_retries: 2,
+        anthropic_max_retries: 2,
+        ai_retry_base_delay: Duration::from_millis(10),
+        upstream_timeout: Duration::from_secs(5),
+        polyfactual_timeout: Duration::from_secs(5),
+        polyfactual_max_query_length: 1000,
+        polyfactual_max_citations: 20,
+        http_client: HttpClientConfig::default(),
+        http: reqwest::Client::new(),
+        shutdown_drain_timeout: Duration::from_secs(1),
+        dome_batch_concurrency: 5,
+        data_api_max_pages: 1,
+        event_analysis_max_markets: 15,
+        order_expiry_margin: Duration::from_secs(30),
+        trading_enabled: true,
+        exposure_caps: ExposureCaps::default(),
+        simple_improvement_ticks: 1,
+        simple_max_spread_cents: 10,
+        simple_max_combined_price: 0.99,
+        ladder_price_band: 0.10,
+        auto_trade: None,
+        metrics_require_auth: false,
+        cors_allowed_origins: CorsOrigins::Unset,
+        rate_limit_ai_per_min: 0,
+        rate_limit_per_min: 0,
+        rate_limit_trust_proxy: false,
+        market_cache_ttl: Duration::ZERO,
+        dome_market_cache_ttl: Duration::ZERO,
+        market_search_cache_ttl: Duration::ZERO,
+        market_stream_poll_interval: Duration::from_millis(20),
+        research_cache_ttl: Duration::from_secs(60),
+        research_cache_max_entries: 10,
+        analysis_research_timeout: Duration::from_secs(5),
+        analysis_refresh_price_threshold: 0.05,
+        analysis_refresh_volume_threshold: 0.5,
+        analysis_subscription_tick: Duration::from_secs(60),
+        position_monitor_tick: Duration::from_secs(30),
+        page_default_limit: 50,
+        page_max_limit: 200,
+        idempotency_ttl: Duration::from_secs(60),
+        job_workers: 2,
+        job_queue_max: 10,
+        job_retention: Duration::from_secs(60),
+        health_check_timeout: Duration::from_secs(1),
+        health_check_cache_ttl: Duration::ZERO,
+        webhook_secret: None,
+        webhook_allowed_hosts: Vec::new(),
+        webhook_poll_interval: Duration::from_secs(5),
+        webhook_watch_duration: Duration::from_secs(60),
+        tracked_wallets: Vec::new(),
+        wallet_snapshot_interval: Duration::from_secs(3_600),
+        upstream: UpstreamSettings::default(),
+    }
+}
+
+/// Upstreams for [`app_state`]. Dome, Polyfactual and Kalshi are only
+/// configured when their mocks are given.
+#[derive(Clone, Default)]
+pub struct MockUpstreams {
+    pub venue: Arc<MockVenue>,
+    pub market_data: Option<Arc<MockMarketData>>,
+    pub research: Option<Arc<MockResearch>>,
+    pub kalshi: Option<Arc<MockKalshi>>,
+}
+
+impl MockUpstreams {
+    /// Every upstream mocked, Dome and Polyfactual included. Direct Kalshi
+    /// access is left out, so Kalshi markets go through Dome.
+    pub fn all() -> Self {
+        Self {
+            venue: Arc::default(),
+            market_data: Some(Arc::default()),
+            research: Some(Arc::default()),
+            kalshi: None,
+        }
+    }
+}
+
+/// State over `upstreams` and `config`, with no storage or background
+/// tasks. Caching, rate limits and API tokens are off unless `config` sets
+/// them.
+pub fn app_state(upstreams: &MockUpstreams, config: Config) -> Arc<AppState> {
+    let capabilities = Capabilities::detect(
+        &config,
+        upstreams.market_data.is_some(),
+        upstreams.research.is_some(),
+        upstreams.kalshi.is_some(),
+        false,
+    );
+    Arc::new(AppState {
+        config: Arc::new(config.clone()),
+        dome_client: upstreams
+            .market_data
+            .clone()
+            .map(|mock| mock as Arc<dyn MarketDataSource>),
+        polyfactual_client: upstreams
+            .research
+            .clone()
+            .map(|mock| mock as Arc<dyn ResearchSource>),
+        kalshi_client: upstreams
+            .kalshi
+            .clone()
+            .map(|mock| mock as Arc<dyn KalshiVenue>),
+        polymarket_client: upstreams.venue.clone(),
+        salt_allocator: Arc::new(SaltAllocator::new()),
+        analysis_store: Arc::new(AnalysisStore::new()),
+        analysis_subscriptions: Arc::new(SubscriptionStore::new()),
+        runtime_config: Arc::new(RuntimeConfig::new(config.trading_enabled)),
+        market_cache: Arc::new(MarketCache::new(
+            config.market_cache_ttl,
+            config.dome_market_cache_ttl,
+        )),
+        market_search_cache: Arc::new(MarketSearchCache::new(config.market_search_cache_ttl)),
+        market_streams: Arc::new(MarketStreams::new(config.market_stream_poll_interval)),
+        research_cache: Arc::new(ResearchCache::new(
+            config.research_cache_ttl,
+            config.research_cache_max_entries,
+        )),
+        ip_rate_limiter: Arc::new(IpRateLimiter::new(
+            config.rate_limit_ai_per_min,
+            config.rate_limit_per_min,
+            config.rate_limit_trust_proxy,
+        )),
+        api_auth: ApiAuth::new(config.api_auth_tokens.clone()),
+        idempotency: Arc::new(IdempotencyStore::new(config.idempotency_ttl)),
+        jobs: Arc::new(JobQueue::new(
+            config.job_workers,
+            config.job_queue_max,
+            config.job_retention,
+        )),
+        exposure_caps: config.exposure_caps.clone(),
+        exposure_in_flight: Arc::new(InFlightExposure::default()),
+        auto_trader: Arc::new(AutoTrader::new(config.auto_trade.clone())),
+        webhooks: Arc::new(WebhookSender::new(
+            config.http_client.clone(),
+            config.webhook_secret.clone(),
+            config.webhook_allowed_hosts.clone(),
+        )),
+        tracked_wallets: Arc::new(config.tracked_wallets.clone()),
+        wallet_snapshots: Arc::new(WalletSnapshotStore::new()),
+        position_snapshots: Arc::new(PositionSnapshotStore::new()),
+        position_monitors: Arc::new(MonitorStore::new()),
+        storage: None,
+        capabilities,
+        shutdown: CancellationToken::new(),
+        in_flight: Arc::new(InFlight::default()),
+        metrics: Metrics::global(),
+        deep_health: Arc::new(DeepHealth::new(
+            config.health_check_timeout,
+            config.health_check_cache_ttl,
+        )),
+    })
+}
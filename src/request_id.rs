@@ -0,0 +1,32 @@
+//! The id of the request being handled, for logs, response metadata and
+//! error bodies.
+
+/// Echoed on every response; an incoming value is kept when it is usable.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longest incoming id that is honoured.
+const MAX_LEN: usize = 128;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The caller's id when it is short printable ASCII, else a new UUID.
+pub fn from_header(value: Option<&str>) -> String {
+    value
+        .map(str::trim)
+        .filter(|id| !id.is_empty() && id.len() <= MAX_LEN)
+        .filter(|id| id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string())
+}
+
+/// Runs `future` with `id` as the current request id.
+pub async fn scope<F: std::future::Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+/// The current request's id; `None` outside a request, e.g. in background
+/// tasks.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(String::clone).ok()
+}
@@ -0,0 +1,348 @@
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions, SqliteRow};
+use sqlx::Row;
+use std::str::FromStr;
+
+use crate::api::analysis_subscriptions::Subscription;
+use crate::types::{
+    LimitOrderBotResponse, OrderMode, Position, StoredOrder, StoredRun, StoredRunSummary,
+    SubscriptionRunPoint,
+};
+use crate::Result;
+
+const MAX_CONNECTIONS: u32 = 5;
+
+/// Optional SQLite store for bot runs, their orders, position snapshots and
+/// analysis subscriptions, enabled by `DATABASE_URL` (e.g. `sqlite://predict-os.db`).
+#[derive(Debug, Clone)]
+pub struct Storage {
+    pool: SqlitePool,
+}
+
+impl Storage {
+    /// Opens (creating if needed) the database and applies pending
+    /// migrations.
+    pub async fn connect(url: &str) -> Result<Self> {
+        let options = SqliteConnectOptions::from_str(url)?
+            .create_if_missing(true)
+            .foreign_keys(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect_with(options)
+            .await?;
+        sqlx::migrate!()
+            .run(&pool)
+            .await
+            .map_err(|e| anyhow::anyhow!("Database migration failed: {}", e))?;
+
+        Ok(Self { pool })
+    }
+
+    /// Stores a finished run and its orders, returning the run id.
+    pub async fn record_run(
+        &self,
+        wallet_address: &str,
+        mode: OrderMode,
+        response: &LimitOrderBotResponse,
+    ) -> Result<String> {
+        let id = format!("run_{}", uuid::Uuid::new_v4().simple());
+        let created_at = Utc::now().to_rfc3339();
+        let logs = serde_json::to_string(&response.logs).map_err(anyhow::Error::from)?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO bot_runs (id, created_at, wallet_address, market_slug, mode, dry_run, \
+             orders_placed, orders_failed, summary, logs) \
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&id)
+        .bind(&created_at)
+        .bind(wallet_address)
+        .bind(&response.market.slug)
+        .bind(enum_name(&mode))
+        .bind(response.metadata.dry_run)
+        .bind(response.orders_placed as i64)
+        .bind(response.orders_failed as i64)
+        .bind(&response.summary)
+        .bind(logs)
+        .execute(&mut *tx)
+        .await?;
+
+        for order in &response.orders {
+            sqlx::query(
+                "INSERT INTO orders (run_id, token_id, outcome, side, price, size, order_id, \
+                 status, error, market_slug, created_at) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&id)
+            .bind(&order.token_id)
+            .bind(&order.outcome)
+            .bind(&order.side)
+            .bind(order.price.value())
+            .bind(order.size)
+            .bind(&order.order_id)
+            .bind(enum_name(&order.status))
+            .bind(&order.error)
+            .bind(&response.market.slug)
+            .bind(&created_at)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(id)
+    }
+
+    pub async fn record_positions(
+        &self,
+        wallet_address: &str,
+        market_slug: Option<&str>,
+        positions: &[Position],
+    ) -> Result<()> {
+        let taken_at = Utc::now().to_rfc3339();
+
+        let mut tx = self.pool.begin().await?;
+        for position in positions {
+            sqlx::query(
+                "INSERT INTO position_snapshots (taken_at, wallet_address, market_slug, token_id, \
+                 outcome, shares, avg_price, current_price) \
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(&taken_at)
+            .bind(wallet_address)
+            .bind(market_slug)
+            .bind(&position.token_id)
+            .bind(&position.outcome)
+            .bind(position.shares)
+            .bind(position.avg_price.value())
+            .bind(position.current_price.value())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Runs newest first, along with the total stored.
+    pub async fn list_runs(
+        &self,
+        offset: usize,
+        limit: usize,
+    ) -> Result<(Vec<StoredRunSummary>, u64)> {
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM bot_runs")
+            .fetch_one(&self.pool)
+            .await?;
+        let runs = sqlx::query(
+            "SELECT id, created_at, wallet_address, market_slug, mode, dry_run, orders_placed, \
+             orders_failed, summary FROM bot_runs ORDER BY created_at DESC, id LIMIT ? OFFSET ?",
+        )
+        .bind(limit as i64)
+        .bind(offset as i64)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(run_summary)
+        .collect::<std::result::Result<_, _>>()?;
+
+        Ok((runs, total as u64))
+    }
+
+    pub async fn get_run(&self, id: &str) -> Result<Option<StoredRun>> {
+        let Some(row) = sqlx::query(
+            "SELECT id, created_at, wallet_address, market_slug, mode, dry_run, orders_placed, \
+             orders_failed, summary, logs FROM bot_runs WHERE id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let logs: String = row.try_get("logs")?;
+        let logs = serde_json::from_str(&logs).map_err(anyhow::Error::from)?;
+        let orders = sqlx::query(
+            "SELECT token_id, outcome, side, price, size, order_id, status, error, market_slug, \
+             created_at FROM orders WHERE run_id = ? ORDER BY id",
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(StoredOrder {
+                token_id: row.try_get("token_id")?,
+                outcome: row.try_get("outcome")?,
+                side: row.try_get("side")?,
+                price: row.try_get("price")?,
+                size: row.try_get("size")?,
+                order_id: row.try_get("order_id")?,
+                status: row.try_get("status")?,
+                error: row.try_get("error")?,
+                market_slug: row.try_get("market_slug")?,
+                created_at: row.try_get("created_at")?,
+            })
+        })
+        .collect::<std::result::Result<_, sqlx::Error>>()?;
+
+        Ok(Some(StoredRun {
+            run: run_summary(&row)?,
+            orders,
+            logs,
+        }))
+    }
+    pub async fn save_subscription(&self, subscription: &Subscription) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO analysis_subscriptions (id, market_url, cadence, model, webhook_url, \
+             min_confidence_change, created_at, next_run_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&subscription.id)
+        .bind(&subscription.market_url)
+        .bind(enum_name(&subscription.cadence))
+        .bind(&subscription.model)
+        .bind(&subscription.webhook_url)
+        .bind(subscription.min_confidence_change)
+        .bind(subscription.created_at.to_rfc3339())
+        .bind(subscription.next_run_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn reschedule_subscription(
+        &self,
+        id: &str,
+        next_run_at: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query("UPDATE analysis_subscriptions SET next_run_at = ? WHERE id = ?")
+            .bind(next_run_at.to_rfc3339())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores a run, dropping the oldest beyond `max_runs`.
+    pub async fn record_subscription_run(
+        &self,
+        id: &str,
+        point: &SubscriptionRunPoint,
+        max_runs: usize,
+    ) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "INSERT INTO subscription_runs (subscription_id, analyzed_at, analysis_id, \
+             recommendation, confidence, change) VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(id)
+        .bind(&point.analyzed_at)
+        .bind(&point.analysis_id)
+        .bind(enum_name(&point.recommendation))
+        .bind(point.confidence)
+        .bind(point.change.as_ref().map(enum_name))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "DELETE FROM subscription_runs WHERE subscription_id = ? AND id NOT IN \
+             (SELECT id FROM subscription_runs WHERE subscription_id = ? ORDER BY id DESC LIMIT ?)",
+        )
+        .bind(id)
+        .bind(id)
+        .bind(max_runs as i64)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Every subscription with its runs, oldest run first.
+    pub async fn load_subscriptions(&self) -> Result<Vec<Subscription>> {
+        let mut subscriptions = sqlx::query(
+            "SELECT id, market_url, cadence, model, webhook_url, min_confidence_change, \
+             created_at, next_run_at FROM analysis_subscriptions ORDER BY created_at",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| {
+            Ok(Subscription {
+                id: row.try_get("id")?,
+                market_url: row.try_get("market_url")?,
+                cadence: from_name(row.try_get("cadence")?)?,
+                model: row.try_get("model")?,
+                webhook_url: row.try_get("webhook_url")?,
+                min_confidence_change: row.try_get("min_confidence_change")?,
+                created_at: timestamp(row.try_get("created_at")?)?,
+                next_run_at: timestamp(row.try_get("next_run_at")?)?,
+                history: Vec::new(),
+            })
+        })
+        .collect::<std::result::Result<Vec<_>, sqlx::Error>>()?;
+
+        for subscription in &mut subscriptions {
+            subscription.history = sqlx::query(
+                "SELECT analyzed_at, analysis_id, recommendation, confidence, change \
+                 FROM subscription_runs WHERE subscription_id = ? ORDER BY id",
+            )
+            .bind(&subscription.id)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| {
+                Ok(SubscriptionRunPoint {
+                    analyzed_at: row.try_get("analyzed_at")?,
+                    analysis_id: row.try_get("analysis_id")?,
+                    recommendation: from_name(row.try_get("recommendation")?)?,
+                    confidence: row.try_get("confidence")?,
+                    change: row
+                        .try_get::<Option<String>, _>("change")?
+                        .map(from_name)
+                        .transpose()?,
+                })
+            })
+            .collect::<std::result::Result<_, sqlx::Error>>()?;
+        }
+
+        Ok(subscriptions)
+    }
+}
+
+fn run_summary(row: &SqliteRow) -> std::result::Result<StoredRunSummary, sqlx::Error> {
+    Ok(StoredRunSummary {
+        id: row.try_get("id")?,
+        created_at: row.try_get("created_at")?,
+        wallet_address: row.try_get("wallet_address")?,
+        market_slug: row.try_get("market_slug")?,
+        mode: row.try_get("mode")?,
+        dry_run: row.try_get("dry_run")?,
+        orders_placed: row.try_get::<i64, _>("orders_placed")? as usize,
+        orders_failed: row.try_get::<i64, _>("orders_failed")? as usize,
+        summary: row.try_get("summary")?,
+    })
+}
+
+/// The serialized name of a unit enum variant, e.g. `"partially_filled"`.
+fn enum_name<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+/// Parses a name written by [`enum_name`] back into its enum.
+fn from_name<T: DeserializeOwned>(name: String) -> std::result::Result<T, sqlx::Error> {
+    serde_json::from_value(serde_json::Value::String(name))
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
+
+fn timestamp(value: String) -> std::result::Result<DateTime<Utc>, sqlx::Error> {
+    DateTime::parse_from_rfc3339(&value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| sqlx::Error::Decode(Box::new(e)))
+}
@@ -1,129 +1,2384 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use utoipa::ToSchema;
 
 // AI Response Types
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct AiAnalysis {
     pub recommendation: Recommendation,
     pub confidence: f64,
     pub reasoning: String,
     pub key_factors: Vec<String>,
+    /// Name of the outcome to buy, as the model spelled it; older replies
+    /// and NO_TRADE leave it out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_outcome: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum Recommendation {
+    // The analysis prompt asks for the underscored spelling
+    #[serde(alias = "BUY_YES")]
     BuyYes,
+    #[serde(alias = "BUY_NO")]
     BuyNo,
+    #[serde(alias = "NO_TRADE")]
     NoTrade,
 }
 
 // Market Types
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct MarketData {
     pub id: String,
     pub question: String,
     pub slug: Option<String>,
     pub ticker: Option<String>,
     pub platform: Platform,
+    /// Polymarket condition id, which the data API filters by
+    #[serde(default)]
+    pub condition_id: Option<String>,
     pub outcomes: Vec<Outcome>,
     pub volume: Option<f64>,
     pub liquidity: Option<f64>,
+    /// Convention applied to `outcomes`: "yes_no" / "up_down" when the
+    /// affirmative side could be identified and placed first, "alphabetical"
+    /// otherwise.
+    pub outcome_ordering: String,
+    #[serde(default)]
+    pub end_date: Option<DateTime<Utc>>,
+    /// No longer trading, whether or not it has resolved yet
+    #[serde(default)]
+    pub closed: bool,
+    /// Name of the winning outcome once the market has resolved
+    #[serde(default)]
+    pub resolved_outcome: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Affirmative/negative outcome names, in the order they are canonicalized.
+const BINARY_OUTCOME_PAIRS: &[(&str, &str, &str)] =
+    &[("yes", "no", "yes_no"), ("up", "down", "up_down")];
+
+impl MarketData {
+    /// Case-insensitive lookup of an outcome by name.
+    pub fn outcome_by_name(&self, name: &str) -> Option<&Outcome> {
+        self.outcomes
+            .iter()
+            .find(|o| o.name.eq_ignore_ascii_case(name.trim()))
+    }
+
+    /// The affirmative (Yes/Up) and negative (No/Down) outcomes of a two-sided
+    /// market, resolved by name and falling back to canonical order.
+    pub fn binary_outcomes(&self) -> Option<(&Outcome, &Outcome)> {
+        for (affirmative, negative, _) in BINARY_OUTCOME_PAIRS {
+            if let (Some(a), Some(n)) = (
+                self.outcome_by_name(affirmative),
+                self.outcome_by_name(negative),
+            ) {
+                return Some((a, n));
+            }
+        }
+        match self.outcomes.as_slice() {
+            [first, second, ..] => Some((first, second)),
+            _ => None,
+        }
+    }
+}
+
+/// Sorts outcomes into a platform-independent order so downstream code never
+/// depends on upstream ordering: Yes/Up first for recognizable binary markets,
+/// alphabetical by name otherwise. Returns the ordering label applied.
+pub fn canonicalize_outcomes(outcomes: &mut [Outcome]) -> String {
+    if outcomes.len() == 2 {
+        let names: Vec<String> = outcomes
+            .iter()
+            .map(|o| o.name.trim().to_lowercase())
+            .collect();
+        for (affirmative, negative, label) in BINARY_OUTCOME_PAIRS {
+            if names.iter().any(|n| n == affirmative) && names.iter().any(|n| n == negative) {
+                if names[0] != *affirmative {
+                    outcomes.swap(0, 1);
+                }
+                return label.to_string();
+            }
+        }
+    }
+
+    outcomes.sort_by_key(|o| o.name.to_lowercase());
+    "alphabetical".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Platform {
     Polymarket,
     Kalshi,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Outcome {
-    pub id: String,
-    pub name: String,
-    pub price: f64,
-    pub volume: Option<f64>,
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct Outcome {
+    pub id: String,
+    pub name: String,
+    pub price: Price,
+    pub volume: Option<f64>,
+}
+
+/// Tolerance for floating-point noise at the edges of the probability range.
+const PRICE_EPSILON: f64 = 1e-9;
+
+/// An outcome price normalized to a probability in `[0, 1]`.
+///
+/// Kalshi quotes integer cents and Polymarket quotes decimals; construct with
+/// the matching constructor so the two can never be mixed up. Serializes as a
+/// decimal everywhere in our API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, PartialOrd, Serialize, Deserialize, ToSchema)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Price(f64);
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid price {0}: must be a probability between 0 and 1 (or 0-100 cents)")]
+pub struct InvalidPrice(pub String);
+
+impl Price {
+    pub const ZERO: Price = Price(0.0);
+    pub const ONE: Price = Price(1.0);
+
+    /// Kalshi-style integer cents, 0-100.
+    pub fn from_cents(cents: u8) -> Result<Self, InvalidPrice> {
+        if cents > 100 {
+            return Err(InvalidPrice(format!("{} cents", cents)));
+        }
+        Ok(Self(f64::from(cents) / 100.0))
+    }
+
+    /// Polymarket-style decimal probability, 0-1. Values within floating-point
+    /// noise of the bounds are snapped onto them.
+    pub fn from_decimal(value: f64) -> Result<Self, InvalidPrice> {
+        if !value.is_finite() || !(-PRICE_EPSILON..=1.0 + PRICE_EPSILON).contains(&value) {
+            return Err(InvalidPrice(value.to_string()));
+        }
+        Ok(Self(value.clamp(0.0, 1.0)))
+    }
+
+    pub fn value(self) -> f64 {
+        self.0
+    }
+
+    /// Nearest whole cent, rounding half up (0.995 -> 100).
+    pub fn to_cents(self) -> u8 {
+        ((self.0 * 100.0) + PRICE_EPSILON).round() as u8
+    }
+}
+
+impl TryFrom<f64> for Price {
+    type Error = InvalidPrice;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        Self::from_decimal(value)
+    }
+}
+
+impl From<Price> for f64 {
+    fn from(price: Price) -> Self {
+        price.0
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+/// One price level of a CLOB order book.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: f64,
+}
+
+/// A token's CLOB order book with each side sorted best price first. Either
+/// side may be empty, in which case the derived fields are `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OrderBook {
+    pub token_id: String,
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub spread: Option<f64>,
+    pub midpoint: Option<f64>,
+}
+
+impl OrderBook {
+    /// Builds a book from levels in any order; the CLOB doesn't promise one.
+    /// Non-finite levels are dropped.
+    pub fn from_levels(token_id: &str, mut bids: Vec<BookLevel>, mut asks: Vec<BookLevel>) -> Self {
+        bids.retain(|l| l.price.is_finite() && l.size.is_finite());
+        asks.retain(|l| l.price.is_finite() && l.size.is_finite());
+        bids.sort_by(|a, b| b.price.total_cmp(&a.price));
+        asks.sort_by(|a, b| a.price.total_cmp(&b.price));
+
+        let best_bid = bids.first().map(|l| l.price);
+        let best_ask = asks.first().map(|l| l.price);
+        // Four places covers the 0.001 tick and snaps off float noise
+        let round = |value: f64| (value * 10_000.0).round() / 10_000.0;
+        let (spread, midpoint) = match (best_bid, best_ask) {
+            (Some(bid), Some(ask)) => (Some(round(ask - bid)), Some(round((bid + ask) / 2.0))),
+            _ => (None, None),
+        };
+
+        Self {
+            token_id: token_id.to_string(),
+            bids,
+            asks,
+            best_bid,
+            best_ask,
+            spread,
+            midpoint,
+        }
+    }
+}
+
+// Request Types
+
+/// Top-level JSON keys a request type accepts, so a misspelled field is
+/// rejected with a suggestion instead of silently falling back to a default.
+pub trait KnownFields {
+    const FIELDS: &'static [&'static str];
+}
+
+/// Checks on a request's own fields that its type can't express, e.g. a
+/// required string sent empty. Run by
+/// [`AppJson`](crate::api::extract::AppJson) before the handler sees the
+/// request; checks that need configuration or upstreams stay in the handler.
+pub trait Validate {
+    fn validate(&self) -> crate::Result<()>;
+}
+
+/// Rejects a required string that is empty or only whitespace.
+fn require(field: &str, value: &str) -> crate::Result<()> {
+    if value.trim().is_empty() {
+        return Err(crate::AppError::Validation(format!(
+            "{} is required",
+            field
+        )));
+    }
+    Ok(())
+}
+
+/// Rejects a `question` longer than prompts quote.
+fn check_question(question: Option<&str>) -> crate::Result<()> {
+    let max = crate::clients::ai::prompts::MAX_QUESTION_CHARS;
+    match question.map(|q| q.trim().chars().count()) {
+        Some(length) if length > max => Err(crate::AppError::Validation(format!(
+            "question is {} characters; the maximum is {}",
+            length, max
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// `raw` as an EIP-55 checksummed wallet address. Any letter case is
+/// accepted; otherwise the error names `field` and what's wrong with it,
+/// e.g. `expected 42-character 0x-prefixed hex address, got 5 characters`.
+pub fn checksum_address(field: &str, raw: &str) -> crate::Result<String> {
+    let invalid = |problem: String| {
+        crate::AppError::Validation(format!("{} is not a valid address: {}", field, problem))
+    };
+    let raw = raw.trim();
+    if raw.len() != 42 {
+        return Err(invalid(format!(
+            "expected 42-character 0x-prefixed hex address, got {} characters",
+            raw.chars().count()
+        )));
+    }
+    let Some(hex) = raw.strip_prefix("0x").or_else(|| raw.strip_prefix("0X")) else {
+        return Err(invalid(format!(
+            "expected 42-character 0x-prefixed hex address, got '{}'",
+            raw
+        )));
+    };
+    if let Some(c) = hex.chars().find(|c| !c.is_ascii_hexdigit()) {
+        return Err(invalid(format!("'{}' is not a hex digit", c)));
+    }
+    hex.parse::<alloy_primitives::Address>()
+        .map(|address| address.to_checksum(None))
+        .map_err(|e| invalid(e.to_string()))
+}
+
+macro_rules! known_fields {
+    ($ty:ty { $($field:ident),* $(,)? }) => {
+        impl KnownFields for $ty {
+            const FIELDS: &'static [&'static str] = &[$(stringify!($field)),*];
+        }
+    };
+}
+
+/// A market named directly rather than by URL: a Polymarket event slug or a
+/// Kalshi ticker.
+#[derive(Debug, Clone, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct MarketRef {
+    pub platform: Platform,
+    pub identifier: String,
+}
+
+impl MarketRef {
+    /// The market's page on its platform.
+    pub fn page_url(&self) -> String {
+        match self.platform {
+            Platform::Polymarket => format!("https://polymarket.com/event/{}", self.identifier),
+            Platform::Kalshi => format!("https://kalshi.com/trade/{}", self.identifier),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct AnalyzeEventMarketsRequest {
+    pub url: Option<String>,
+    pub market: Option<MarketRef>, // Alternative to `url`; exactly one is required
+    pub question: Option<String>,
+    pub model: Option<String>, // "grok", "openai" or "claude"
+    pub include_chart: Option<bool>,
+    pub custom_prompt: Option<String>, // Replaces the built-in template; schema is still appended
+    pub model_name: Option<String>,    // Provider model override, e.g. "gpt-4o"
+    pub temperature: Option<f64>,      // 0 to 2 (0 to 1 for claude)
+    pub max_tokens: Option<u32>,
+    pub compare: Option<bool>, // Run Grok and OpenAI together and return a consensus
+    pub include_research: Option<bool>, // Add Polyfactual findings to the prompt
+    pub research_query: Option<String>, // Defaults to the market question
+    pub include_history: Option<bool>, // Add a summary of recent Dome candles to the prompt
+    pub include_orderbook: Option<bool>, // Add each outcome's book depth and recent trade count
+    pub min_confidence: Option<f64>, // Below this the recommendation becomes NO_TRADE; default 0
+    pub max_position_usd: Option<f64>, // Bankroll for `suggested_size_usd`, which never exceeds it
+    pub analyze_all_markets: Option<bool>, // Analyze every market of the url's Polymarket event and rank them
+    pub timeout_secs: Option<u64>, // Limit on each AI provider call, up to 600; default 120
+    pub allow_fallback: Option<bool>, // Retry a failed Grok analysis with OpenAI; default true
+}
+
+known_fields!(AnalyzeEventMarketsRequest {
+    url,
+    market,
+    question,
+    model,
+    include_chart,
+    custom_prompt,
+    model_name,
+    temperature,
+    max_tokens,
+    compare,
+    include_research,
+    research_query,
+    include_history,
+    include_orderbook,
+    min_confidence,
+    max_position_usd,
+    analyze_all_markets,
+    timeout_secs,
+    allow_fallback,
+});
+
+impl Validate for AnalyzeEventMarketsRequest {
+    fn validate(&self) -> crate::Result<()> {
+        check_question(self.question.as_deref())?;
+        match (&self.url, &self.market) {
+            (Some(url), None) => require("url", url)?,
+            (None, Some(market)) => require("market.identifier", &market.identifier)?,
+            _ => {
+                return Err(crate::AppError::Validation(
+                    "Provide exactly one of url or market".to_string(),
+                ))
+            }
+        }
+        if self.include_research == Some(true) && self.custom_prompt.is_some() {
+            return Err(crate::AppError::Validation(
+                "include_research can't be combined with custom_prompt".to_string(),
+            ));
+        }
+        if self.include_history == Some(true) && self.custom_prompt.is_some() {
+            return Err(crate::AppError::Validation(
+                "include_history can't be combined with custom_prompt".to_string(),
+            ));
+        }
+        if self.include_orderbook == Some(true) && self.custom_prompt.is_some() {
+            return Err(crate::AppError::Validation(
+                "include_orderbook can't be combined with custom_prompt".to_string(),
+            ));
+        }
+        if self.analyze_all_markets == Some(true) {
+            if self.url.is_none() {
+                return Err(crate::AppError::Validation(
+                    "analyze_all_markets needs a Polymarket event url".to_string(),
+                ));
+            }
+            let per_market_extras = [
+                ("compare", self.compare),
+                ("include_chart", self.include_chart),
+                ("include_research", self.include_research),
+                ("include_history", self.include_history),
+                ("include_orderbook", self.include_orderbook),
+            ];
+            if let Some((field, _)) = per_market_extras.iter().find(|(_, on)| *on == Some(true)) {
+                return Err(crate::AppError::Validation(format!(
+                    "{} can't be combined with analyze_all_markets",
+                    field
+                )));
+            }
+        }
+        if self.compare == Some(true) && self.model_name.is_some() {
+            return Err(crate::AppError::Validation(
+                "model_name can't be combined with compare".to_string(),
+            ));
+        }
+        if self
+            .min_confidence
+            .is_some_and(|c| !(0.0..=1.0).contains(&c))
+        {
+            return Err(crate::AppError::Validation(
+                "min_confidence must be between 0 and 1".to_string(),
+            ));
+        }
+        if self
+            .max_position_usd
+            .is_some_and(|usd| !(usd.is_finite() && usd > 0.0))
+        {
+            return Err(crate::AppError::Validation(
+                "max_position_usd must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchAnalyzeRequest {
+    pub urls: Vec<String>,
+    pub question: Option<String>,
+    pub model: Option<String>, // "grok", "openai" or "claude"
+    pub combined: Option<bool>, // One prompt across all markets instead of one per market
+}
+
+known_fields!(BatchAnalyzeRequest {
+    urls,
+    question,
+    model,
+    combined,
+});
+
+impl Validate for BatchAnalyzeRequest {
+    fn validate(&self) -> crate::Result<()> {
+        if self.urls.is_empty() {
+            return Err(crate::AppError::Validation(
+                "At least one URL is required".to_string(),
+            ));
+        }
+        if self.urls.iter().any(|u| u.trim().is_empty()) {
+            return Err(crate::AppError::Validation(
+                "URLs must not be empty".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RefreshAnalysisRequest {
+    pub analysis_id: String,
+    pub price_threshold: Option<f64>, // Max absolute outcome price move, e.g. 0.05
+    pub volume_threshold: Option<f64>, // Volume growth ratio, e.g. 0.5 for +50%
+}
+
+known_fields!(RefreshAnalysisRequest {
+    analysis_id,
+    price_threshold,
+    volume_threshold,
+});
+
+impl Validate for RefreshAnalysisRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("analysis_id", &self.analysis_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConstructPortfolioRequest {
+    pub analyses: Vec<PortfolioMarketRef>,
+    pub bankroll_usd: f64,
+    pub max_per_market_pct: Option<f64>, // Percent of bankroll, e.g. 25.0
+    pub min_confidence: Option<f64>,     // 0.0-1.0
+    pub kelly_fraction: Option<f64>,     // Multiplier on full Kelly, e.g. 0.25
+    pub model: Option<String>,           // For markets analyzed on the fly
+    pub execute: Option<bool>,           // Place each position with the limit order bot
+    pub wallet_private_key: Option<Secret>, // With execute; the server wallet otherwise
+    pub dry_run: Option<bool>,           // With execute; simulate the orders
+}
+
+known_fields!(ConstructPortfolioRequest {
+    analyses,
+    bankroll_usd,
+    max_per_market_pct,
+    min_confidence,
+    kelly_fraction,
+    model,
+    execute,
+    wallet_private_key,
+    dry_run,
+});
+
+impl Validate for ConstructPortfolioRequest {
+    fn validate(&self) -> crate::Result<()> {
+        if self.analyses.is_empty() {
+            return Err(crate::AppError::Validation(
+                "At least one analysis or market URL is required".to_string(),
+            ));
+        }
+        for entry in &self.analyses {
+            match (&entry.analysis_id, &entry.market_url) {
+                (Some(analysis_id), None) => require("analysis_id", analysis_id)?,
+                (None, Some(market_url)) => require("market_url", market_url)?,
+                _ => {
+                    return Err(crate::AppError::Validation(
+                        "Each entry needs exactly one of analysis_id or market_url".to_string(),
+                    ))
+                }
+            }
+        }
+        if self.bankroll_usd <= 0.0 {
+            return Err(crate::AppError::Validation(
+                "Bankroll must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A market to consider: a stored analysis, or a URL to analyze now.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortfolioMarketRef {
+    pub analysis_id: Option<String>,
+    pub market_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EventMispricingRequest {
+    pub url: String,
+    pub budget_usd: Option<f64>, // Size the trade set to this spend; minimum size otherwise
+}
+
+known_fields!(EventMispricingRequest { url, budget_usd });
+
+impl Validate for EventMispricingRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("url", &self.url)?;
+        if self.budget_usd.is_some_and(|b| b <= 0.0) {
+            return Err(crate::AppError::Validation(
+                "budget_usd must be greater than 0".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArbScanRequest {
+    pub first_url: Option<String>,
+    pub second_url: Option<String>,
+    pub outcome_map: Option<HashMap<String, String>>, // First market's outcome name -> second's, e.g. {"Up": "Yes"}
+    pub pairs: Option<Vec<ArbPair>>, // In place of first_url and second_url
+    pub polymarket_fee_bps: Option<u32>, // Fee rate assumed on Polymarket legs; default 0
+    pub kalshi_fee_bps: Option<u32>, // Fee rate assumed on Kalshi legs; default 700
+    pub min_edge_bps: Option<f64>, // Edge after fees needed to flag a pair; default 0
+}
+
+/// Two markets on the same question, usually one per platform.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ArbPair {
+    pub first_url: String,
+    pub second_url: String,
+    pub outcome_map: Option<HashMap<String, String>>,
+}
+
+known_fields!(ArbScanRequest {
+    first_url,
+    second_url,
+    outcome_map,
+    pairs,
+    polymarket_fee_bps,
+    kalshi_fee_bps,
+    min_edge_bps,
+});
+
+impl ArbScanRequest {
+    /// The pairs to scan: `pairs`, or the one pair given at the top level.
+    pub fn into_pairs(self) -> Vec<ArbPair> {
+        match (self.pairs, self.first_url, self.second_url) {
+            (Some(pairs), _, _) => pairs,
+            (None, Some(first_url), Some(second_url)) => vec![ArbPair {
+                first_url,
+                second_url,
+                outcome_map: self.outcome_map,
+            }],
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Validate for ArbScanRequest {
+    fn validate(&self) -> crate::Result<()> {
+        let single = self.first_url.is_some() || self.second_url.is_some();
+        match &self.pairs {
+            Some(_) if single || self.outcome_map.is_some() => {
+                return Err(crate::AppError::Validation(
+                    "Provide either pairs or first_url and second_url, not both".to_string(),
+                ));
+            }
+            Some(pairs) if pairs.is_empty() => {
+                return Err(crate::AppError::Validation(
+                    "pairs must not be empty".to_string(),
+                ));
+            }
+            Some(pairs) => {
+                for pair in pairs {
+                    require("first_url", &pair.first_url)?;
+                    require("second_url", &pair.second_url)?;
+                }
+            }
+            None => {
+                require("first_url", self.first_url.as_deref().unwrap_or(""))?;
+                require("second_url", self.second_url.as_deref().unwrap_or(""))?;
+            }
+        }
+
+        for (field, bps) in [
+            ("polymarket_fee_bps", self.polymarket_fee_bps),
+            ("kalshi_fee_bps", self.kalshi_fee_bps),
+        ] {
+            if bps.is_some_and(|bps| bps > 10_000) {
+                return Err(crate::AppError::Validation(format!(
+                    "{} must be at most 10000",
+                    field
+                )));
+            }
+        }
+        if self.min_edge_bps.is_some_and(|bps| !bps.is_finite()) {
+            return Err(crate::AppError::Validation(
+                "min_edge_bps must be a number".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreateAnalysisSubscriptionRequest {
+    pub market_url: String,
+    pub cadence: SubscriptionCadence,
+    pub model: Option<String>, // "grok", "openai" or "claude"
+    pub webhook_url: Option<String>,
+    pub min_confidence_change: Option<f64>, // Confidence move that counts as material, e.g. 0.15
+}
+
+known_fields!(CreateAnalysisSubscriptionRequest {
+    market_url,
+    cadence,
+    model,
+    webhook_url,
+    min_confidence_change,
+});
+
+impl Validate for CreateAnalysisSubscriptionRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("market_url", &self.market_url)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubscriptionCadence {
+    Daily,
+    Weekly,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CreatePositionMonitorRequest {
+    pub wallet_address: String,
+    pub market_slug: String,
+    pub webhook_url: String,
+    pub pnl_above: Option<f64>, // Take profit, in USD, e.g. 5
+    pub pnl_below: Option<f64>, // Stop loss, in USD, e.g. -5
+}
+
+known_fields!(CreatePositionMonitorRequest {
+    wallet_address,
+    market_slug,
+    webhook_url,
+    pnl_above,
+    pnl_below,
+});
+
+impl Validate for CreatePositionMonitorRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("wallet_address", &self.wallet_address)?;
+        require("market_slug", &self.market_slug)?;
+        require("webhook_url", &self.webhook_url)?;
+        if [self.pnl_above, self.pnl_below]
+            .iter()
+            .flatten()
+            .any(|pnl| !pnl.is_finite())
+        {
+            return Err(crate::AppError::Validation(
+                "pnl_above and pnl_below must be finite".to_string(),
+            ));
+        }
+        match (self.pnl_above, self.pnl_below) {
+            (None, None) => Err(crate::AppError::Validation(
+                "Set pnl_above, pnl_below or both".to_string(),
+            )),
+            (Some(above), Some(below)) if below >= above => Err(crate::AppError::Validation(
+                "pnl_below must be less than pnl_above".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PolyfactualResearchRequest {
+    pub query: String,
+    /// Skip a cached answer for the same query and run it again
+    pub force_refresh: Option<bool>,
+    /// Seconds each research attempt may take, up to 900; defaults to
+    /// `POLYFACTUAL_TIMEOUT_SECS`
+    pub timeout_secs: Option<u64>,
+    /// Leave out citations less relevant than this (0 to 1). Citations
+    /// Polyfactual didn't score count as 0.
+    pub min_relevance: Option<f64>,
+}
+
+known_fields!(PolyfactualResearchRequest {
+    query,
+    force_refresh,
+    timeout_secs,
+    min_relevance,
+});
+
+impl Validate for PolyfactualResearchRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("query", &self.query)?;
+        let max = crate::clients::polyfactual::MAX_RESEARCH_TIMEOUT_SECS;
+        if self
+            .timeout_secs
+            .is_some_and(|secs| !(1..=max).contains(&secs))
+        {
+            return Err(crate::AppError::Validation(format!(
+                "timeout_secs must be between 1 and {}",
+                max
+            )));
+        }
+        if self
+            .min_relevance
+            .is_some_and(|min| !(0.0..=1.0).contains(&min))
+        {
+            return Err(crate::AppError::Validation(
+                "min_relevance must be between 0 and 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where the position tracker looks: a Polymarket wallet, the Kalshi
+/// account, or both sides of a mirrored trade.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TrackerPlatform {
+    #[default]
+    Polymarket,
+    Kalshi,
+    Both,
+}
+
+impl TrackerPlatform {
+    pub fn polymarket(self) -> bool {
+        self != TrackerPlatform::Kalshi
+    }
+
+    pub fn kalshi(self) -> bool {
+        self != TrackerPlatform::Polymarket
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PositionTrackerRequest {
+    #[serde(default)]
+    pub platform: TrackerPlatform, // "polymarket" (default), "kalshi" or "both"
+    pub wallet_address: Option<String>, // Polymarket wallet; required unless platform is "kalshi"
+    pub wallet_addresses: Option<Vec<String>>, // Several wallets, aggregated; instead of wallet_address
+    pub market_slug: Option<String>,
+    pub asset: Option<String>, // "btc" (default), "eth", "sol" or "xrp"; used without market_slug
+    pub interval: Option<MarketInterval>, // Up/down window: "15m" (default), "1h" or "1d"
+    pub kalshi_ticker: Option<String>, // Kalshi market; required for "kalshi" and "both"
+    pub portfolio_id: Option<String>, // Names the Kalshi account in snapshots; defaults to "kalshi"
+    pub fields: Option<String>, // e.g. "positions,pair_status,market.slug"
+    pub include_history: Option<bool>, // Adds trades, realized P&L and total invested
+    pub track_changes: Option<bool>,   // Adds share and P&L changes since the previous such poll
+}
+
+known_fields!(PositionTrackerRequest {
+    platform,
+    wallet_address,
+    wallet_addresses,
+    market_slug,
+    asset,
+    interval,
+    kalshi_ticker,
+    portfolio_id,
+    fields,
+    include_history,
+    track_changes,
+});
+
+impl Validate for PositionTrackerRequest {
+    fn validate(&self) -> crate::Result<()> {
+        let only = |field: &str, set: bool, platform: &str| {
+            if set {
+                return Err(crate::AppError::Validation(format!(
+                    "{} only applies when platform includes {}",
+                    field, platform
+                )));
+            }
+            Ok(())
+        };
+
+        if self.platform.polymarket() {
+            match (&self.wallet_address, &self.wallet_addresses) {
+                (Some(_), Some(_)) => {
+                    return Err(crate::AppError::Validation(
+                        "Send either wallet_address or wallet_addresses, not both".to_string(),
+                    ))
+                }
+                (_, Some(wallets)) => {
+                    if wallets.is_empty() {
+                        return Err(crate::AppError::Validation(
+                            "wallet_addresses must not be empty".to_string(),
+                        ));
+                    }
+                    for (i, wallet) in wallets.iter().enumerate() {
+                        checksum_address(&format!("wallet_addresses[{}]", i), wallet)?;
+                    }
+                    if self.include_history == Some(true) {
+                        return Err(crate::AppError::Validation(
+                            "include_history needs a single wallet_address".to_string(),
+                        ));
+                    }
+                }
+                (wallet, None) => {
+                    let wallet = wallet.as_deref().unwrap_or_default();
+                    require("wallet_address", wallet)?;
+                    checksum_address("wallet_address", wallet)?;
+                }
+            }
+            if let Some(market_slug) = &self.market_slug {
+                require("market_slug", market_slug)?;
+            }
+        } else {
+            only("wallet_address", self.wallet_address.is_some(), "polymarket")?;
+            only(
+                "wallet_addresses",
+                self.wallet_addresses.is_some(),
+                "polymarket",
+            )?;
+            only("market_slug", self.market_slug.is_some(), "polymarket")?;
+            only("asset", self.asset.is_some(), "polymarket")?;
+            only("interval", self.interval.is_some(), "polymarket")?;
+        }
+
+        if self.platform.kalshi() {
+            require("kalshi_ticker", self.kalshi_ticker.as_deref().unwrap_or(""))?;
+            if let Some(portfolio_id) = &self.portfolio_id {
+                require("portfolio_id", portfolio_id)?;
+            }
+        } else {
+            only("kalshi_ticker", self.kalshi_ticker.is_some(), "kalshi")?;
+            only("portfolio_id", self.portfolio_id.is_some(), "kalshi")?;
+        }
+
+        if self.platform != TrackerPlatform::Polymarket && self.include_history == Some(true) {
+            return Err(crate::AppError::Validation(
+                "include_history is only supported for platform polymarket".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PortfolioRequest {
+    pub wallet_address: String,
+    pub fields: Option<String>, // e.g. "totals,markets.market_slug,markets.unrealized_pnl"
+}
+
+known_fields!(PortfolioRequest {
+    wallet_address,
+    fields,
+});
+
+impl Validate for PortfolioRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("wallet_address", &self.wallet_address)?;
+        checksum_address("wallet_address", &self.wallet_address).map(|_| ())
+    }
+}
+
+/// `GET /api/markets`: Polymarket markets to pick from, most traded first
+/// unless `query` orders them by relevance.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketSearchRequest {
+    pub query: Option<String>, // Free text matched against market and event titles
+    pub active: Option<bool>,  // Defaults to true: only markets still trading
+    pub tag: Option<String>,   // Category tag slug, e.g. "politics" or "crypto"
+    pub min_volume: Option<f64>, // USD
+    pub min_liquidity: Option<f64>, // USD
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    pub cursor: Option<String>, // From a previous page's next_cursor; replaces offset
+    pub fields: Option<String>, // e.g. "items.slug,items.outcomes,next_cursor"
+}
+
+known_fields!(MarketSearchRequest {
+    query,
+    active,
+    tag,
+    min_volume,
+    min_liquidity,
+    limit,
+    offset,
+    cursor,
+    fields,
+});
+
+impl Validate for MarketSearchRequest {
+    fn validate(&self) -> crate::Result<()> {
+        if let Some(query) = &self.query {
+            require("query", query)?;
+        }
+        if let Some(tag) = &self.tag {
+            require("tag", tag)?;
+        }
+        for (field, value) in [
+            ("min_volume", self.min_volume),
+            ("min_liquidity", self.min_liquidity),
+        ] {
+            if value.is_some_and(|v| !v.is_finite() || v < 0.0) {
+                return Err(crate::AppError::Validation(format!(
+                    "{} must not be negative",
+                    field
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A market found by `GET /api/markets`. `slug` and the outcome token ids
+/// go straight into the bot's `market_slug` and `outcomes`, and `url` into
+/// the analysis endpoints.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct MarketSummary {
+    pub id: String,
+    pub slug: String,
+    pub question: String,
+    pub url: String,
+    pub condition_id: Option<String>,
+    /// Names, prices and CLOB token ids
+    pub outcomes: Vec<Outcome>,
+    /// Best bid and ask on the first outcome
+    pub best_bid: Option<f64>,
+    pub best_ask: Option<f64>,
+    pub volume: Option<f64>,
+    pub liquidity: Option<f64>,
+    pub end_date: Option<DateTime<Utc>>,
+    pub closed: bool,
+}
+
+impl From<MarketData> for MarketSummary {
+    fn from(market: MarketData) -> Self {
+        let slug = market.slug.unwrap_or_else(|| market.id.clone());
+        Self {
+            url: format!("https://polymarket.com/market/{}", slug),
+            id: market.id,
+            slug,
+            question: market.question,
+            condition_id: market.condition_id,
+            outcomes: market.outcomes,
+            best_bid: None,
+            best_ask: None,
+            volume: market.volume,
+            liquidity: market.liquidity,
+            end_date: market.end_date,
+            closed: market.closed,
+        }
+    }
+}
+
+/// A credential from a request body. It renders as `***` in `Debug` and
+/// when serialized, so it can't leak through logs or error messages.
+#[derive(Clone, Deserialize, ToSchema)]
+#[serde(transparent)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"***\"")
+    }
+}
+
+impl Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str("***")
+    }
+}
+
+/// Orders are signed by `wallet_private_key` or, when neither it nor the
+/// CLOB credentials are sent, by the server's `WALLET_PRIVATE_KEY`. CLOB
+/// credentials alone can read and cancel orders but not place them.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct LimitOrderBotRequest {
+    pub wallet_private_key: Option<Secret>,
+    pub clob_api_key: Option<Secret>, // CLOB L2 credentials, all three together
+    pub clob_secret: Option<Secret>,
+    pub clob_passphrase: Option<Secret>,
+    pub wallet_address: Option<String>, // The wallet the CLOB credentials belong to
+    pub market_slug: Option<String>,
+    pub asset: Option<String>, // "btc" (default), "eth", "sol" or "xrp"; used without market_slug
+    pub interval: Option<MarketInterval>, // Up/down window: "15m" (default), "1h" or "1d"
+    pub mode: OrderMode,
+    #[serde(default)] // Unused in exit mode
+    pub bankroll_usd: f64,
+    pub price_levels: Option<usize>, // For ladder mode; 1 to MAX_PRICE_LEVELS
+    pub verify_placement: Option<bool>,
+    pub verify_delay_ms: Option<u64>,
+    pub ai_summary: Option<bool>,
+    pub pricing: Option<SimplePricing>, // Simple mode only; defaults to join_bid
+    pub improvement_ticks: Option<u32>,
+    pub max_spread_cents: Option<u32>,
+    pub strict_spread: Option<bool>, // Refuse (default) or warn when the spread is too wide
+    pub max_combined_price: Option<f64>, // Simple straddles: refuse above this summed cost per share
+    pub max_spread: Option<f64>, // Simple straddles: refuse a side wider than this, e.g. 0.05
+    pub dry_run: Option<bool>,   // Run the full flow but return Simulated orders
+    pub outcomes: Option<Vec<OutcomeTarget>>, // Defaults to Up/Down, half the bankroll each
+    pub ladder_min_price: Option<f64>, // Ladder mode; defaults to current price minus LADDER_PRICE_BAND
+    pub ladder_max_price: Option<f64>, // Ladder mode; defaults to current price plus LADDER_PRICE_BAND
+    pub ladder_spacing: Option<LadderSpacing>,
+    pub ladder_profile: Option<LadderProfile>, // Ladder mode; defaults to exponential
+    pub weights: Option<Vec<f64>>,             // With the custom profile, one per price level
+    pub exit_target_pct: Option<f64>, // Exit mode: profit over average entry price, e.g. 20.0
+    pub use_orderbook_price: Option<bool>, // Use the CLOB book midpoint instead of the Gamma price
+    pub idempotency_key: Option<String>, // Alternative to the Idempotency-Key header
+    pub override_caps: Option<bool>,  // Skip the exposure caps; needs ALLOW_CAP_OVERRIDE
+    pub webhook_url: Option<String>,  // Notified as placed orders fill or are cancelled
+    pub expiration: Option<OrderExpiration>, // Defaults to gtc
+    pub expires_at: Option<DateTime<Utc>>,   // With gtd only
+    pub structured_logs: Option<bool>, // Also return the run's log as typed `events`
+}
+
+known_fields!(LimitOrderBotRequest {
+    wallet_private_key,
+    clob_api_key,
+    clob_secret,
+    clob_passphrase,
+    wallet_address,
+    market_slug,
+    asset,
+    interval,
+    mode,
+    bankroll_usd,
+    price_levels,
+    verify_placement,
+    verify_delay_ms,
+    ai_summary,
+    pricing,
+    improvement_ticks,
+    max_spread_cents,
+    strict_spread,
+    max_combined_price,
+    max_spread,
+    dry_run,
+    outcomes,
+    ladder_min_price,
+    ladder_max_price,
+    ladder_spacing,
+    ladder_profile,
+    weights,
+    exit_target_pct,
+    use_orderbook_price,
+    idempotency_key,
+    override_caps,
+    webhook_url,
+    expiration,
+    expires_at,
+    structured_logs,
+});
+
+impl Validate for LimitOrderBotRequest {
+    fn validate(&self) -> crate::Result<()> {
+        if let Some(market_slug) = &self.market_slug {
+            require("market_slug", market_slug)?;
+        }
+        if let OrderMode::Exit = self.mode {
+            match self.exit_target_pct {
+                Some(pct) if pct >= 0.0 => {}
+                Some(_) => {
+                    return Err(crate::AppError::Validation(
+                        "exit_target_pct must not be negative".to_string(),
+                    ))
+                }
+                None => {
+                    return Err(crate::AppError::Validation(
+                        "exit_target_pct is required in exit mode".to_string(),
+                    ))
+                }
+            }
+        } else if self.bankroll_usd <= 0.0 {
+            return Err(crate::AppError::Validation(
+                "Bankroll must be greater than 0".to_string(),
+            ));
+        }
+        if self
+            .max_combined_price
+            .is_some_and(|max| !(max.is_finite() && max > 0.0))
+        {
+            return Err(crate::AppError::Validation(
+                "max_combined_price must be greater than 0".to_string(),
+            ));
+        }
+        if self
+            .max_spread
+            .is_some_and(|max| !(max.is_finite() && max > 0.0 && max < 1.0))
+        {
+            return Err(crate::AppError::Validation(
+                "max_spread must be between 0 and 1".to_string(),
+            ));
+        }
+        if self
+            .price_levels
+            .is_some_and(|levels| !(1..=MAX_PRICE_LEVELS).contains(&levels))
+        {
+            return Err(crate::AppError::Validation(format!(
+                "price_levels must be between 1 and {}",
+                MAX_PRICE_LEVELS
+            )));
+        }
+        match (self.ladder_profile.unwrap_or_default(), &self.weights) {
+            (LadderProfile::Custom, None) => {
+                return Err(crate::AppError::Validation(
+                    "weights is required with the custom ladder_profile".to_string(),
+                ))
+            }
+            (LadderProfile::Custom, Some(weights)) => {
+                let price_levels = self.price_levels.unwrap_or(DEFAULT_PRICE_LEVELS);
+                if weights.len() != price_levels {
+                    return Err(crate::AppError::Validation(format!(
+                        "weights has {} entries but price_levels is {}",
+                        weights.len(),
+                        price_levels
+                    )));
+                }
+                if weights.iter().any(|w| !w.is_finite() || *w < 0.0)
+                    || weights.iter().sum::<f64>() <= 0.0
+                {
+                    return Err(crate::AppError::Validation(
+                        "weights must not be negative and at least one must be positive"
+                            .to_string(),
+                    ));
+                }
+            }
+            (_, Some(_)) => {
+                return Err(crate::AppError::Validation(
+                    "weights is only used with the custom ladder_profile".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        match (self.expiration.unwrap_or_default(), self.expires_at) {
+            (OrderExpiration::Gtd, None) => {
+                return Err(crate::AppError::Validation(
+                    "expires_at is required with gtd expiration".to_string(),
+                ))
+            }
+            (OrderExpiration::Gtd, Some(expires_at)) if expires_at <= Utc::now() => {
+                return Err(crate::AppError::Validation(
+                    "expires_at must be in the future".to_string(),
+                ))
+            }
+            (OrderExpiration::Gtc | OrderExpiration::MarketClose, Some(_)) => {
+                return Err(crate::AppError::Validation(
+                    "expires_at is only used with gtd expiration".to_string(),
+                ))
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// A bot request to reconcile against the wallet's resting orders.
+#[derive(Debug, Deserialize)]
+pub struct LimitOrderDiffRequest {
+    #[serde(flatten)]
+    pub bot: LimitOrderBotRequest,
+    pub apply: Option<bool>,
+    pub price_tolerance: Option<f64>, // Absolute, e.g. 0.005
+    pub size_tolerance: Option<f64>,  // Fraction of the planned size, e.g. 0.05
+}
+
+known_fields!(LimitOrderDiffRequest {
+    wallet_private_key,
+    clob_api_key,
+    clob_secret,
+    clob_passphrase,
+    wallet_address,
+    market_slug,
+    asset,
+    interval,
+    mode,
+    bankroll_usd,
+    price_levels,
+    verify_placement,
+    verify_delay_ms,
+    ai_summary,
+    pricing,
+    improvement_ticks,
+    max_spread_cents,
+    strict_spread,
+    max_combined_price,
+    max_spread,
+    dry_run,
+    outcomes,
+    ladder_min_price,
+    ladder_max_price,
+    ladder_spacing,
+    ladder_profile,
+    weights,
+    exit_target_pct,
+    use_orderbook_price,
+    idempotency_key,
+    override_caps,
+    webhook_url,
+    expiration,
+    expires_at,
+    apply,
+    price_tolerance,
+    size_tolerance,
+});
+
+impl Validate for LimitOrderDiffRequest {
+    fn validate(&self) -> crate::Result<()> {
+        self.bot.validate()?;
+        if self.apply == Some(true) && self.bot.dry_run == Some(true) {
+            return Err(crate::AppError::Validation(
+                "apply and dry_run cannot both be set".to_string(),
+            ));
+        }
+        if self.price_tolerance.is_some_and(|t| t < 0.0)
+            || self.size_tolerance.is_some_and(|t| t < 0.0)
+        {
+            return Err(crate::AppError::Validation(
+                "Tolerances must not be negative".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An analysis whose recommendation, when confident enough, is traded
+/// straight away with the limit order bot's settings. The market and the
+/// outcome to buy come from the analysis.
+#[derive(Debug, Deserialize)]
+pub struct AnalyzeAndTradeRequest {
+    pub url: String, // A Polymarket market URL
+    pub question: Option<String>,
+    pub model: Option<String>, // "grok", "openai" or "claude"
+    pub min_confidence: f64,   // Trade only at or above this confidence
+    #[serde(flatten)]
+    pub bot: LimitOrderBotRequest,
+}
+
+// The bot's market_slug, asset, outcomes and exit settings are left out:
+// the analysis picks the market and outcome, and it only ever buys
+known_fields!(AnalyzeAndTradeRequest {
+    url,
+    question,
+    model,
+    min_confidence,
+    wallet_private_key,
+    clob_api_key,
+    clob_secret,
+    clob_passphrase,
+    wallet_address,
+    mode,
+    bankroll_usd,
+    price_levels,
+    verify_placement,
+    verify_delay_ms,
+    ai_summary,
+    pricing,
+    improvement_ticks,
+    max_spread_cents,
+    strict_spread,
+    dry_run,
+    ladder_min_price,
+    ladder_max_price,
+    ladder_spacing,
+    ladder_profile,
+    weights,
+    use_orderbook_price,
+    override_caps,
+    webhook_url,
+});
+
+impl Validate for AnalyzeAndTradeRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("url", &self.url)?;
+        check_question(self.question.as_deref())?;
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return Err(crate::AppError::Validation(
+                "min_confidence must be between 0 and 1".to_string(),
+            ));
+        }
+        if self.bot.mode == OrderMode::Exit {
+            return Err(crate::AppError::Validation(
+                "mode must be simple or ladder; analyze-and-trade only buys".to_string(),
+            ));
+        }
+        self.bot.validate()
+    }
+}
+
+/// Orders to cancel: everything resting on a market or on specific tokens,
+/// or specific orders (e.g. the bot's `order_ids`).
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CancelAllOrdersRequest {
+    pub market_slug: Option<String>,
+    pub token_ids: Option<Vec<String>>,
+    pub order_ids: Option<Vec<String>>,
+}
+
+known_fields!(CancelAllOrdersRequest {
+    market_slug,
+    token_ids,
+    order_ids,
+});
+
+impl Validate for CancelAllOrdersRequest {
+    fn validate(&self) -> crate::Result<()> {
+        if let Some(market_slug) = &self.market_slug {
+            require("market_slug", market_slug)?;
+        }
+        Ok(())
+    }
+}
+
+/// Sells part of one leg of a held straddle with a limit order under the
+/// best bid.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UnwindLegRequest {
+    pub market_slug: String,
+    /// "winning" (furthest above its average entry price), "losing"
+    /// (furthest below it), or an outcome by name or token id
+    pub leg: String,
+    pub fraction: f64, // Share of the leg's position to sell, over 0 and at most 1
+    pub price_offset: Option<f64>, // Limit price this far under the best bid; defaults to 0
+    pub dry_run: Option<bool>,
+}
+
+known_fields!(UnwindLegRequest {
+    market_slug,
+    leg,
+    fraction,
+    price_offset,
+    dry_run,
+});
+
+impl Validate for UnwindLegRequest {
+    fn validate(&self) -> crate::Result<()> {
+        require("market_slug", &self.market_slug)?;
+        require("leg", &self.leg)?;
+        if !(self.fraction.is_finite() && self.fraction > 0.0 && self.fraction <= 1.0) {
+            return Err(crate::AppError::Validation(
+                "fraction must be over 0 and at most 1".to_string(),
+            ));
+        }
+        if self
+            .price_offset
+            .is_some_and(|offset| !(offset.is_finite() && (0.0..1.0).contains(&offset)))
+        {
+            return Err(crate::AppError::Validation(
+                "price_offset must be at least 0 and under 1".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An outcome to buy, matched by token id or case-insensitive name.
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct OutcomeTarget {
+    pub outcome: String,
+    pub weight: Option<f64>, // Relative share of the bankroll; defaults to 1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrderMode {
+    Simple,
+    Ladder,
+    /// Sell the wallet's held shares at a target profit
+    Exit,
+}
+
+/// How Simple mode prices its orders against the live book.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SimplePricing {
+    /// Last/reference price from market data
+    Last,
+    /// Best bid plus the configured improvement, never crossing the ask
+    #[default]
+    JoinBid,
+    /// Take the best ask
+    CrossSpread,
+}
+
+/// How long the bot's orders rest on the book when nothing fills or
+/// cancels them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderExpiration {
+    /// Good till cancelled
+    #[default]
+    Gtc,
+    /// Good till `expires_at`
+    Gtd,
+    /// Good till shortly before the next 15-minute boundary, so unfilled
+    /// orders don't carry into the next market cycle
+    MarketClose,
+}
+
+/// How ladder price levels are spread between the min and max price.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LadderSpacing {
+    /// Evenly spaced
+    #[default]
+    Linear,
+    /// Constant ratio between levels, so levels bunch up near the low end
+    Geometric,
+}
+
+/// Price levels per outcome in ladder mode when the request doesn't say.
+pub const DEFAULT_PRICE_LEVELS: usize = 5;
+/// Most price levels per outcome a ladder may ask for.
+pub const MAX_PRICE_LEVELS: usize = 20;
+
+/// How a ladder's bankroll is weighted across its price levels, lowest
+/// price first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LadderProfile {
+    /// Each level weighs twice the next one up
+    #[default]
+    Exponential,
+    /// Equal notional per level
+    Flat,
+    /// Weights falling by the same step toward higher prices
+    Linear,
+    /// The request's `weights`, one per level
+    Custom,
+}
+
+impl LadderProfile {
+    /// Relative weights of `levels` levels, lowest price first. `custom` is
+    /// used as given for the custom profile.
+    pub fn weights(self, levels: usize, custom: Option<&[f64]>) -> Vec<f64> {
+        match self {
+            LadderProfile::Exponential => (0..levels)
+                .map(|i| 2_f64.powi((levels - i) as i32))
+                .collect(),
+            LadderProfile::Flat => vec![1.0; levels],
+            LadderProfile::Linear => (0..levels).map(|i| (levels - i) as f64).collect(),
+            LadderProfile::Custom => custom.unwrap_or_default().to_vec(),
+        }
+    }
+}
+
+// Response Types
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalyzeEventMarketsResponse {
+    pub recommendation: Recommendation,
+    pub analysis: AiAnalysis,
+    pub market_data: MarketData,
+    pub question_focus: Option<String>,
+    pub analysis_id: String,
+    /// Primary outcome price history as `[unix_seconds, price]`, at most 100 points
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chart: Option<Vec<(i64, f64)>>,
+    /// Sources behind the research given to the model (`include_research`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub research_citations: Option<Vec<Citation>>,
+    /// Per-provider analyses behind a `compare` request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comparison: Option<AnalysisComparison>,
+    /// Changes made to the model's answer, e.g. a recommendation downgraded
+    /// below `min_confidence` or a confidence clamped into [0, 1]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+    /// Reasons not to trust the analysis as is, e.g. an extreme confidence
+    /// whose reasoning cites no market data, or a provider fallback
+    /// (`grok: 429 rate limited after 3 attempts; analyzed with openai ...`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    /// Kelly stake for the recommended outcome out of `max_position_usd`;
+    /// 0 for NO_TRADE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_size_usd: Option<f64>,
+    /// Outcome id of the recommended outcome; unset for NO_TRADE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_token_id: Option<String>,
+    /// How `target_token_id` was found; anything but `exact` is a guess
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_match: Option<TargetMatch>,
+    pub metadata: ResponseMetadata,
+}
+
+/// What `/api/analyze-event-markets` answers: one market, or with
+/// `analyze_all_markets` the whole event.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum AnalyzeEventMarketsOutput {
+    Market(Box<AnalyzeEventMarketsResponse>),
+    Event(Box<AnalyzeEventResponse>),
+}
+
+/// Every market of a Polymarket event analyzed (`analyze_all_markets`).
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalyzeEventResponse {
+    pub event_slug: String,
+    pub title: String,
+    /// Best `score` first, then NO_TRADE, then markets whose analysis failed
+    pub markets: Vec<EventMarketAnalysis>,
+    /// The top-ranked market, when its edge is positive
+    pub best_opportunity: Option<EventMarketAnalysis>,
+    /// Markets left out: closed, or beyond `EVENT_ANALYSIS_MAX_MARKETS`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<SkippedEventMarket>,
+    pub metadata: ResponseMetadata,
+}
+
+/// One market of an event analysis; a failed analysis has only `error`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct EventMarketAnalysis {
+    pub market_data: MarketData,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<AiAnalysis>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_token_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_match: Option<TargetMatch>,
+    /// Confidence minus the recommended outcome's price; unset for NO_TRADE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edge: Option<f64>,
+    /// `edge` times confidence, the ranking key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub suggested_size_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct SkippedEventMarket {
+    pub market_id: String,
+    pub question: String,
+    pub reason: String,
+}
+
+/// How the model's `target_outcome` was matched to one of the market's
+/// outcomes.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetMatch {
+    /// The name as listed
+    Exact,
+    /// Ignoring case, or one name a prefix of the other
+    Fuzzy,
+    /// No name given; BUY_YES or BUY_NO picked a side of a two-outcome market
+    Inferred,
+}
+
+/// The outcome an analysis recommends buying.
+#[derive(Debug, Clone)]
+pub struct TargetOutcome {
+    pub outcome: Outcome,
+    pub matched: TargetMatch,
+}
+
+/// Both providers' analyses; a provider that failed is left out.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AnalysisComparison {
+    pub grok: Option<ProviderAnalysis>,
+    pub openai: Option<ProviderAnalysis>,
+    pub consensus: Consensus,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProviderAnalysis {
+    pub model: String,
+    pub analysis: AiAnalysis,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Consensus {
+    pub agreement: ConsensusAgreement,
+    pub recommendation: Recommendation,
+    /// Mean of both confidences; only set when the providers agree
+    pub confidence: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ConsensusAgreement {
+    Agree,
+    Disagree,
+    /// One provider failed, so its peer's analysis stands alone
+    SingleProvider,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchAnalyzeResponse {
+    pub results: Vec<BatchAnalyzeItem>,
+    pub summary: BatchAnalyzeSummary,
+    pub metadata: ResponseMetadata,
+}
+
+/// One market's outcome in a batch; exactly one of `analysis` or `error` is set.
+#[derive(Debug, Serialize)]
+pub struct BatchAnalyzeItem {
+    pub index: usize,
+    pub url: String,
+    pub analysis: Option<AiAnalysis>,
+    pub market_data: Option<MarketData>,
+    pub model_used: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchAnalyzeSummary {
+    pub total: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub execution_time_ms: u64,
+}
+
+/// NDJSON line emitted by the streaming batch endpoint.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BatchStreamEvent {
+    Result(Box<BatchAnalyzeItem>),
+    Summary(BatchAnalyzeSummary),
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardResponse {
+    pub period: String,
+    pub period_start: String,
+    pub entries: Vec<LeaderboardEntry>,
+    pub excluded: Vec<ExcludedWallet>,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub label: String,
+    /// Realized plus unrealized P&L change over the period
+    pub pnl_change: f64,
+    pub realized_pnl_change: f64,
+    pub unrealized_pnl_change: f64,
+    pub volume_traded: f64,
+    /// Baseline is the wallet's first snapshot rather than the period start
+    pub joined_mid_period: bool,
+    pub sparkline: Vec<PnlPoint>,
+}
+
+/// Cumulative P&L change as of the end of `date` (YYYY-MM-DD).
+#[derive(Debug, Serialize)]
+pub struct PnlPoint {
+    pub date: String,
+    pub pnl: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExcludedWallet {
+    pub label: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefreshAnalysisResponse {
+    /// Id of the returned analysis; a new id when the analysis was re-run.
+    pub analysis_id: String,
+    pub previous_analysis_id: String,
+    /// Whether the stored analysis was stale and the AI was re-run.
+    pub stale: bool,
+    pub movement: MarketMovement,
+    pub analysis: AiAnalysis,
+    pub changes: Option<AnalysisChange>,
+    pub market_data: MarketData,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MarketMovement {
+    pub max_price_delta: f64,
+    pub volume_growth: Option<f64>,
+    pub price_threshold: f64,
+    pub volume_threshold: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalysisChange {
+    pub previous_recommendation: Recommendation,
+    pub recommendation_changed: bool,
+    pub previous_confidence: f64,
+    pub confidence_delta: f64,
+    pub added_key_factors: Vec<String>,
+    pub removed_key_factors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ConstructPortfolioResponse {
+    pub positions: Vec<PortfolioPosition>,
+    pub skipped: Vec<SkippedMarket>,
+    pub bankroll_usd: f64,
+    pub total_allocated_usd: f64,
+    pub unallocated_usd: f64,
+    /// One bot run per funded position; only with `execute`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub executions: Vec<PortfolioExecution>,
+    pub metadata: ResponseMetadata,
+}
+
+/// The limit order bot run placing one position. A failed run is reported
+/// here and doesn't stop the others.
+#[derive(Debug, Serialize)]
+pub struct PortfolioExecution {
+    pub analysis_id: String,
+    pub stake_usd: f64,
+    pub orders: Vec<OrderResult>,
+    pub orders_placed: usize,
+    pub order_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PortfolioPosition {
+    pub analysis_id: String,
+    pub market_url: String,
+    pub question: String,
+    pub recommendation: Recommendation,
+    pub confidence: f64,
+    /// Outcome to buy and its CLOB token
+    pub outcome: String,
+    pub token_id: String,
+    pub price: Price,
+    /// Full-Kelly fraction of bankroll before scaling and caps
+    pub kelly: f64,
+    pub target_stake_usd: f64,
+    pub limiting_constraint: PortfolioConstraint,
+}
+
+/// What bound a position's stake.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PortfolioConstraint {
+    /// The fractional Kelly stake itself
+    Kelly,
+    /// `max_per_market_pct`
+    MarketCap,
+    /// Scaled down so the total fits the bankroll
+    Bankroll,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SkippedMarket {
+    pub analysis_id: Option<String>,
+    pub market_url: Option<String>,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EventMispricingResponse {
+    pub event_slug: String,
+    pub title: String,
+    pub structure: EventStructure,
+    /// Why the event was not treated as mutually exclusive buckets
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub structure_reason: Option<String>,
+    /// Sum of YES prices across buckets; only for mutually exclusive events
+    pub price_sum: Option<f64>,
+    pub deviation: Option<f64>,
+    pub buckets: Vec<BucketContribution>,
+    pub trade: Option<MispricingTrade>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_trade_reason: Option<String>,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArbScanResponse {
+    /// Both ways of buying opposite sides of every aligned pair, best edge
+    /// first
+    pub opportunities: Vec<ArbOpportunity>,
+    /// Opportunities with `flagged` set
+    pub flagged: usize,
+    /// Pairs that couldn't be priced, e.g. a failed lookup or outcomes that
+    /// don't line up
+    pub warnings: Vec<ArbPairWarning>,
+    pub metadata: ResponseMetadata,
+}
+
+/// One outcome bought on one market and the opposite outcome on the other:
+/// together they pay $1 whichever way the question resolves.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArbOpportunity {
+    /// Index of the pair in the request
+    pub pair_index: usize,
+    /// Bought on the first market
+    pub first: ArbLeg,
+    /// Bought on the second market
+    pub second: ArbLeg,
+    /// Both prices plus both fees, per share of each leg
+    pub cost_per_set: f64,
+    /// The $1 payout minus `cost_per_set`
+    pub edge: f64,
+    pub edge_bps: f64,
+    /// The smaller of the two markets' stated liquidity (USD)
+    pub max_size_usd: Option<f64>,
+    /// Sets that `max_size_usd` buys at `cost_per_set`
+    pub max_sets: Option<f64>,
+    /// A positive edge of at least `min_edge_bps`
+    pub flagged: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArbLeg {
+    pub market_id: String,
+    pub platform: Platform,
+    pub question: String,
+    pub outcome: String,
+    pub price: Price,
+    /// Assumed fee per share
+    pub fee: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArbPairWarning {
+    pub pair_index: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventStructure {
+    /// Exactly one bucket resolves YES, so YES prices should sum to 1
+    MutuallyExclusive,
+    /// Markets resolve independently; their prices need not sum to anything
+    Independent,
+    StructureUnknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BucketContribution {
+    pub market_id: String,
+    pub question: String,
+    pub yes_price: f64,
+    pub no_price: f64,
+    /// This bucket's share of `price_sum`
+    pub share_of_sum: f64,
 }
 
-// Request Types
-#[derive(Debug, Deserialize)]
-pub struct AnalyzeEventMarketsRequest {
-    pub url: String,
-    pub question: Option<String>,
-    pub model: Option<String>, // "grok" or "openai"
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MispricingDirection {
+    /// Buckets sum above 1: buy NO on every bucket
+    BuyNo,
+    /// Buckets sum below 1: buy YES on every bucket
+    BuyYes,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PolyfactualResearchRequest {
-    pub query: String,
+#[derive(Debug, Clone, Serialize)]
+pub struct MispricingTrade {
+    pub direction: MispricingDirection,
+    pub legs: Vec<TradeLeg>,
+    /// Shares bought on every leg
+    pub shares_per_leg: f64,
+    pub cost_usd: f64,
+    pub fees_usd: f64,
+    /// Guaranteed payout at resolution
+    pub payout_usd: f64,
+    pub net_profit_usd: f64,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct PositionTrackerRequest {
-    pub wallet_address: String,
-    pub market_slug: Option<String>,
+#[derive(Debug, Clone, Serialize)]
+pub struct TradeLeg {
+    pub market_id: String,
+    pub outcome: String,
+    pub token_id: String,
+    pub price: f64,
+    pub fee_rate_bps: u32,
 }
 
-#[derive(Debug, Deserialize)]
-pub struct LimitOrderBotRequest {
-    pub wallet_private_key: String,
-    pub market_slug: Option<String>,
-    pub mode: OrderMode,
-    pub bankroll_usd: f64,
-    pub price_levels: Option<usize>, // For ladder mode
+#[derive(Debug, Serialize)]
+pub struct AnalysisSubscriptionResponse {
+    pub subscription: AnalysisSubscription,
+    pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum OrderMode {
-    Simple,
-    Ladder,
+#[derive(Debug, Clone, Serialize)]
+pub struct AnalysisSubscription {
+    pub id: String,
+    pub market_url: String,
+    pub cadence: SubscriptionCadence,
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    pub min_confidence_change: f64,
+    pub created_at: String,
+    pub next_run_at: String,
 }
 
-// Response Types
 #[derive(Debug, Serialize)]
-pub struct AnalyzeEventMarketsResponse {
-    pub recommendation: Recommendation,
-    pub analysis: AiAnalysis,
-    pub market_data: MarketData,
+pub struct SubscriptionHistoryResponse {
+    pub subscription_id: String,
+    pub market_url: String,
+    /// Oldest first
+    pub points: Vec<SubscriptionRunPoint>,
     pub metadata: ResponseMetadata,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct SubscriptionRunPoint {
+    pub analyzed_at: String,
+    pub analysis_id: String,
+    pub recommendation: Recommendation,
+    pub confidence: f64,
+    /// Material change against the previous run, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<AnalysisDrift>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalysisDrift {
+    RecommendationChanged,
+    ConfidenceShift,
+}
+
 #[derive(Debug, Serialize)]
+pub struct PositionMonitorResponse {
+    pub monitor: PositionMonitor,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionMonitor {
+    pub id: String,
+    /// Checksummed
+    pub wallet_address: String,
+    pub market_slug: String,
+    pub webhook_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pnl_above: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pnl_below: Option<f64>,
+    pub created_at: String,
+}
+
+/// The bound a position monitor's P&L crossed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum MonitorThreshold {
+    PnlAbove(f64),
+    PnlBelow(f64),
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct PolyfactualResearchResponse {
     pub answer: String,
+    /// Deduplicated, most relevant first
     pub citations: Vec<Citation>,
+    /// Citations dropped or cut while tidying the list. Omitted when empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
     pub metadata: ResponseMetadata,
 }
 
-#[derive(Debug, Serialize)]
+impl PolyfactualResearchResponse {
+    /// Leaves out citations less relevant than `min_relevance`, when set.
+    pub fn retain_relevant(&mut self, min_relevance: Option<f64>) {
+        if let Some(min) = min_relevance {
+            self.citations.retain(|c| c.relevance >= min);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct Citation {
     pub source: String,
     pub url: Option<String>,
     pub relevance: f64,
 }
 
-#[derive(Debug, Serialize)]
+/// Length of a recurring up/down market's window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum MarketInterval {
+    #[default]
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+/// Candle widths Dome serves price history in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub enum CandleInterval {
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "1h")]
+    OneHour,
+    #[serde(rename = "1d")]
+    OneDay,
+}
+
+impl CandleInterval {
+    pub fn minutes(self) -> i64 {
+        match self {
+            CandleInterval::OneMinute => 1,
+            CandleInterval::OneHour => 60,
+            CandleInterval::OneDay => 1440,
+        }
+    }
+}
+
+impl std::str::FromStr for CandleInterval {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim() {
+            "1m" => Ok(CandleInterval::OneMinute),
+            "1h" => Ok(CandleInterval::OneHour),
+            "1d" => Ok(CandleInterval::OneDay),
+            other => Err(format!(
+                "Unsupported interval '{}'; expected 1m, 1h or 1d",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for CandleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CandleInterval::OneMinute => "1m",
+            CandleInterval::OneHour => "1h",
+            CandleInterval::OneDay => "1d",
+        })
+    }
+}
+
+/// One price candle of a market's first outcome; prices are probabilities.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct Candle {
+    /// End of the candle's period
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+}
+
+/// Momentum figures from a run of candles, as shown to the analysis model.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct CandleSummary {
+    pub last_price: f64,
+    /// Last close minus the close an hour earlier; `None` with under an
+    /// hour of history
+    pub change_1h: Option<f64>,
+    /// Standard deviation of candle-to-candle close changes
+    pub realized_volatility: f64,
+    pub candles: usize,
+}
+
+impl CandleSummary {
+    /// `None` for an empty series. Candles must be oldest first.
+    pub fn from_candles(candles: &[Candle]) -> Option<Self> {
+        let last = candles.last()?;
+        let hour_ago = last.timestamp - chrono::Duration::hours(1);
+        let change_1h = candles
+            .iter()
+            .rev()
+            .find(|c| c.timestamp <= hour_ago)
+            .map(|c| last.close - c.close);
+
+        let changes: Vec<f64> = candles
+            .windows(2)
+            .map(|w| w[1].close - w[0].close)
+            .collect();
+        let realized_volatility = if changes.is_empty() {
+            0.0
+        } else {
+            let mean = changes.iter().sum::<f64>() / changes.len() as f64;
+            let variance =
+                changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / changes.len() as f64;
+            variance.sqrt()
+        };
+
+        Some(Self {
+            last_price: last.close,
+            change_1h,
+            realized_volatility,
+            candles: candles.len(),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketHistoryResponse {
+    pub market: MarketData,
+    pub interval: CandleInterval,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Oldest first
+    pub candles: Vec<Candle>,
+    /// `None` when Dome had no candles for the range
+    pub summary: Option<CandleSummary>,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct PositionTrackerResponse {
+    #[serde(flatten)]
+    pub tracked: TrackedPositions,
+    pub metadata: ResponseMetadata,
+}
+
+/// The position tracker's answer for `platform: "both"`: each side of the
+/// mirrored trade, and what the two make together.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CrossPlatformPositionsResponse {
+    pub polymarket: TrackedPositions,
+    pub kalshi: TrackedPositions,
+    /// Unrealized plus realized P&L across every position on both platforms
+    pub net_pnl: f64,
+    pub metadata: ResponseMetadata,
+}
+
+/// Positions held in one market and how the pair stands.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TrackedPositions {
+    /// The Polymarket wallet, checksummed (single `wallet_address` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallet_address: Option<String>,
     pub market: MarketData,
     pub positions: Vec<Position>,
     pub pair_status: PairStatus,
+    /// Guaranteed profit at resolution, when locked
     pub profit_lock: Option<f64>,
+    /// Highest price for the lagging side's missing shares that still locks
+    /// a profit
     pub break_even: Option<f64>,
-    pub metadata: ResponseMetadata,
+    /// Shares on one side with no counterpart on the other
+    pub imbalance: Option<ShareImbalance>,
+    /// What the winning shares paid out, once the market has resolved
+    pub realized_payout: Option<f64>,
+    /// FIFO proceeds minus cost basis on closed lots (`include_history` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realized_pnl: Option<f64>,
+    /// Cost of every buy, fees included (`include_history` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_invested: Option<f64>,
+    /// Oldest first (`include_history` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trades: Option<Vec<TradeFill>>,
+    /// Since the previous poll of this wallet and market (`track_changes` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<PositionChanges>,
+    /// Each wallet's own positions (`wallet_addresses` only); the fields
+    /// above then cover all of them together
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallets: Option<Vec<WalletPositions>>,
+}
+
+/// One wallet's positions in a multi-wallet position tracker response.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WalletPositions {
+    pub wallet_address: String,
+    pub positions: Vec<Position>,
+    /// `None` when the wallet's positions couldn't be fetched
+    pub pair_status: Option<PairStatus>,
+    /// Unrealized plus realized P&L
+    pub pnl: f64,
+    /// Why the wallet's positions couldn't be fetched; it is left out of
+    /// the aggregate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// How positions moved since the previous `track_changes` poll.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PositionChanges {
+    /// When the previous snapshot was taken; `None` on the first poll
+    pub since: Option<String>,
+    pub elapsed_ms: Option<u64>,
+    pub positions: Vec<PositionChange>,
+    /// Whether any share count grew, which usually means orders filled
+    pub new_fills_detected: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PositionChange {
+    pub token_id: String,
+    pub outcome: String,
+    pub shares_delta: f64,
+    /// Change in unrealized plus realized P&L
+    pub pnl_delta: f64,
+}
+
+impl TrackedPositions {
+    /// Unrealized plus realized P&L over every position.
+    pub fn pnl(&self) -> f64 {
+        self.positions
+            .iter()
+            .map(|p| p.unrealized_pnl + p.realized_pnl.unwrap_or(0.0))
+            .sum()
+    }
 }
 
 #[derive(Debug, Serialize)]
+pub struct OrderBookResponse {
+    pub order_book: OrderBook,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PortfolioResponse {
+    /// Checksummed
+    pub wallet_address: String,
+    pub markets: Vec<MarketPositions>,
+    pub totals: PortfolioTotals,
+    pub metadata: ResponseMetadata,
+}
+
+/// A wallet's positions in one market.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MarketPositions {
+    pub market_slug: String,
+    pub title: String,
+    /// Omitted when the market lookup failed
+    pub market: Option<MarketData>,
+    pub positions: Vec<Position>,
+    pub cost_basis: f64,
+    pub current_value: f64,
+    pub unrealized_pnl: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PortfolioTotals {
+    pub markets: usize,
+    pub positions: usize,
+    pub cost_basis: f64,
+    pub current_value: f64,
+    pub unrealized_pnl: f64,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
 pub struct Position {
     pub token_id: String,
     pub outcome: String,
     pub shares: f64,
-    pub avg_price: f64,
-    pub current_price: f64,
+    pub avg_price: Price,
+    pub current_price: Price,
     pub unrealized_pnl: f64,
+    /// Payout minus cost once the market has resolved; `unrealized_pnl` is
+    /// then zero
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realized_pnl: Option<f64>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct TradeFill {
+    pub token_id: String,
+    pub outcome: String,
+    pub side: String, // "buy" or "sell"
+    pub price: Price,
+    pub size: f64,
+    pub timestamp: String,
+    pub fee: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct ShareImbalance {
+    /// The side holding the extra shares
+    pub outcome: String,
+    pub shares: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum PairStatus {
     ProfitLocked,
@@ -132,39 +2387,514 @@ pub enum PairStatus {
     NoPosition,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct LimitOrderBotResponse {
     pub orders: Vec<OrderResult>,
+    /// Orders accepted (or simulated); a run can partially succeed
+    pub orders_placed: usize,
+    pub orders_failed: usize,
+    /// Exchange ids of the placed orders, accepted as-is by
+    /// `DELETE /api/orders/:order_id` and `POST /api/orders/cancel-all`
+    pub order_ids: Vec<String>,
+    pub market: MarketData,
+    pub logs: Vec<String>,
+    /// The run's milestones as typed events; only sent with
+    /// `structured_logs`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub events: Vec<BotLogEvent>,
+    pub summary: String,
+    pub verification: Option<PlacementVerification>,
+    /// Planned orders rounded to the market's tick size or dropped under
+    /// the exchange minimums before placing
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub adjustments: Vec<OrderAdjustment>,
+    /// What a simple-mode straddle costs if every side fills
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub straddle: Option<StraddleCost>,
+    /// Id under `GET /api/runs/:id`; only set when persistence is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    pub metadata: ResponseMetadata,
+}
+
+/// A milestone of a bot run, alongside the free-text `logs`. The `event`
+/// tag and field names are stable; new kinds may be added.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BotLogEvent {
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: BotLogEventKind,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum BotLogEventKind {
+    /// The market the run trades, once fetched and found open
+    MarketResolved { slug: String, question: String },
+    /// An order computed from the request, before rounding to the tick size
+    OrderPlanned {
+        outcome: String,
+        side: String,
+        price: f64,
+        shares: f64,
+        /// 1-based ladder level; absent outside ladder mode
+        #[serde(skip_serializing_if = "Option::is_none")]
+        level: Option<usize>,
+    },
+    /// Accepted by the exchange, or simulated on a dry run
+    OrderPlaced {
+        outcome: String,
+        order_id: Option<String>,
+        status: OrderStatus,
+    },
+    OrderFailed { outcome: String, reason: String },
+    /// An exposure cap refused the run; `limit` and `attempted` in USD.
+    /// The run then fails, so this one is only seen in the server's tracing
+    CapTriggered {
+        cap: String,
+        limit: f64,
+        attempted: f64,
+    },
+}
+
+impl BotLogEvent {
+    pub fn now(kind: BotLogEventKind) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalyzeAndTradeResponse {
+    pub analysis: AiAnalysis,
+    pub market_data: MarketData,
+    pub analysis_id: String,
+    /// Whether the bot ran; its orders are simulated under `dry_run`
+    pub traded: bool,
+    /// Why nothing was traded
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<String>,
+    /// Changes made to the model's answer; see `/api/analyze-event-markets`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<String>,
+    /// Reasons the analysis wasn't trusted; a flagged analysis is not traded
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    pub orders: Vec<OrderResult>,
+    pub order_ids: Vec<String>,
+    /// The bot run's log, when it ran
+    pub logs: Vec<String>,
+    /// Id under `GET /api/runs/:id`; only set when persistence is configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    pub metadata: ResponseMetadata,
+}
+
+/// Result of cross-checking placed orders against the exchange.
+#[derive(Debug, Serialize)]
+pub struct LimitOrderDiffResponse {
     pub market: MarketData,
+    /// Resting orders that already match the plan
+    pub keep: Vec<DiffOrder>,
+    /// Resting buy orders no longer in the plan
+    pub cancel: Vec<DiffOrder>,
+    /// Planned orders with no resting match
+    pub add: Vec<DiffOrder>,
+    /// Notional added minus notional cancelled (USD)
+    pub net_notional_change: f64,
+    /// Present when `apply` was set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied: Option<DiffApplied>,
     pub logs: Vec<String>,
     pub metadata: ResponseMetadata,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffOrder {
+    /// Exchange order id; absent for orders still to be added
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<String>,
+    pub token_id: String,
+    pub outcome: String,
+    pub price: f64,
+    pub size: f64,
+}
+
 #[derive(Debug, Serialize)]
+pub struct DiffApplied {
+    pub cancelled: Vec<String>,
+    /// Order id to the exchange's reason
+    pub cancel_failed: HashMap<String, String>,
+    pub placed: Vec<OrderResult>,
+}
+
+/// A planned order changed or dropped to fit the market's tick size and
+/// minimum order size and notional.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct OrderAdjustment {
+    pub token_id: String,
+    pub outcome: String,
+    pub planned_price: Price,
+    pub planned_size: f64,
+    /// What was placed instead; `None` when the order was dropped
+    pub price: Option<Price>,
+    pub size: Option<f64>,
+    pub dropped: bool,
+    pub reason: String,
+}
+
+/// The cost of buying one share of every side of a simple-mode straddle,
+/// checked against `max_combined_price` before anything is placed.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct StraddleCost {
+    pub combined_cost: f64,
+    pub max_combined_price: f64,
+    pub legs: Vec<StraddleLeg>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct StraddleLeg {
+    pub outcome: String,
+    /// The order's limit price, or the best ask when that is lower
+    pub fill_price: f64,
+    /// Best ask minus best bid; `None` without a two-sided book
+    pub spread: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct PlacementVerification {
+    pub checked: usize,
+    pub confirmed_open: usize,
+    pub filled: usize,
+    /// Orders the exchange has no record of, even after an individual lookup.
+    pub unconfirmed: Vec<String>,
+    /// Orders that came back without an order_id and so can't be verified.
+    pub skipped: usize,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct OrderResult {
     pub token_id: String,
     pub outcome: String,
     pub side: String, // "buy" or "sell"
-    pub price: f64,
+    pub price: Price,
     pub size: f64,
     pub order_id: Option<String>,
     pub status: OrderStatus,
+    /// Shares matched so far; only set on orders looked up on the exchange
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filled_size: Option<f64>,
+    /// Why placement failed; only set on `Failed` orders
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum OrderStatus {
     Pending,
+    /// Resting with part of its size matched; see `filled_size`
+    #[serde(rename = "partially_filled")]
+    PartiallyFilled,
     Filled,
     Cancelled,
     Failed,
+    Unconfirmed,
+    /// Dry run: computed but never sent to the exchange
+    Simulated,
+}
+
+impl OrderStatus {
+    /// The serialized name, e.g. `partially_filled`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Pending => "pending",
+            OrderStatus::PartiallyFilled => "partially_filled",
+            OrderStatus::Filled => "filled",
+            OrderStatus::Cancelled => "cancelled",
+            OrderStatus::Failed => "failed",
+            OrderStatus::Unconfirmed => "unconfirmed",
+            OrderStatus::Simulated => "simulated",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderLookupResponse {
+    pub order: OrderResult,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderListResponse {
+    pub market_slug: String,
+    pub orders: Vec<OrderResult>,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelOrdersResponse {
+    pub results: Vec<CancelledOrder>,
+    /// Orders this request actually cancelled
+    pub cancelled: usize,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CancelledOrder {
+    pub order_id: String,
+    pub status: CancelStatus,
+    /// The exchange's reason when the order wasn't cancelled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CancelStatus {
+    Cancelled,
+    AlreadyFilled,
+    AlreadyCancelled,
+    NotFound,
+    /// Still resting; see `reason`
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnwindLegResponse {
+    pub market_slug: String,
+    /// The sell order, `simulated` under `dry_run`
+    pub order: OrderResult,
+    /// What the wallet holds once the order fills
+    pub projection: UnwindProjection,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UnwindProjection {
+    /// Every outcome still held, the sold leg included when shares remain
+    pub positions: Vec<HeldLeg>,
+    /// `size * price` of the sell order
+    pub sale_proceeds: f64,
+    /// The remaining shares at current prices
+    pub remaining_value: f64,
+    /// The outcome whose win leaves the wallet worst off: a held leg losing
+    pub worst_case_outcome: String,
+    /// Settlement payout plus sale proceeds, less the cost of every leg
+    /// before the sale
+    pub worst_case_pnl: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HeldLeg {
+    pub token_id: String,
+    pub outcome: String,
+    pub shares: f64,
+    pub avg_price: f64,
+    pub current_price: f64,
+}
+
+/// A bot run as stored by the persistence layer.
+#[derive(Debug, Serialize)]
+pub struct StoredRunSummary {
+    pub id: String,
+    pub created_at: String,
+    pub wallet_address: String,
+    pub market_slug: Option<String>,
+    pub mode: String,
+    pub dry_run: bool,
+    pub orders_placed: usize,
+    pub orders_failed: usize,
+    pub summary: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoredRun {
+    #[serde(flatten)]
+    pub run: StoredRunSummary,
+    pub orders: Vec<StoredOrder>,
+    pub logs: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct StoredOrder {
+    pub token_id: String,
+    pub outcome: String,
+    pub side: String,
+    pub price: f64,
+    pub size: f64,
+    pub order_id: Option<String>,
+    /// Status when the run finished
+    pub status: String,
+    pub error: Option<String>,
+    pub market_slug: Option<String>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RunResponse {
+    pub run: StoredRun,
+    pub metadata: ResponseMetadata,
 }
 
 #[derive(Debug, Serialize)]
+pub struct AutoTradeStatusResponse {
+    /// False unless the server started with `AUTO_TRADE_ENABLED=true`
+    pub configured: bool,
+    /// Paused by `POST /api/auto-trade/stop` when false
+    pub active: bool,
+    /// A run is in progress
+    pub running: bool,
+    pub next_run_at: Option<String>,
+    pub settings: Option<AutoTradeSettings>,
+    /// Full response of the most recent run that got as far as placing
+    pub last_result: Option<LimitOrderBotResponse>,
+    /// Newest first
+    pub history: Vec<AutoTradeRun>,
+    pub metadata: ResponseMetadata,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoTradeSettings {
+    pub asset: String,
+    pub mode: OrderMode,
+    pub bankroll_usd: f64,
+    pub dry_run: bool,
+    pub start_delay_secs: u64,
+    pub grace_period_secs: u64,
+}
+
+/// One scheduled run of the auto-trader.
+#[derive(Debug, Clone, Serialize)]
+pub struct AutoTradeRun {
+    pub window_start: String,
+    pub started_at: String,
+    pub finished_at: String,
+    pub market_slug: Option<String>,
+    /// Market lookups made before the market was found or the grace period ran out
+    pub attempts: u32,
+    pub status: AutoTradeRunStatus,
+    pub orders_placed: usize,
+    pub orders_failed: usize,
+    pub summary: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutoTradeRunStatus {
+    Completed,
+    /// The market never appeared within the grace period
+    MarketNotFound,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub struct ResponseMetadata {
     pub timestamp: String,
     pub execution_time_ms: u64,
     pub model_used: Option<String>,
     pub retries: u32,
+    /// Optional enhancements skipped because an integration was unavailable
+    /// or failed, e.g. `["chart"]`. Omitted when empty.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub degraded_features: Vec<String>,
+    /// Set when the analysis used a caller-supplied prompt
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub custom_prompt: bool,
+    /// Set when no orders were sent to the exchange (`dry_run` requests)
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// Whether the market came from the market cache; only set by endpoints
+    /// that consult it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache_hit: Option<bool>,
+    /// The id in this response's `X-Request-Id` header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    /// Tokens billed by AI providers for this response, fallbacks and
+    /// retried attempts included; only set by endpoints that run an analysis
+    #[serde(flatten)]
+    pub ai_usage: Option<AiUsage>,
+    /// The AI provider asked for and the one that answered; they differ
+    /// after a fallback. Only set by endpoints that run one analysis
+    #[serde(flatten)]
+    pub ai_provider: Option<AiProviderUsed>,
+    /// Set when a research query over the length limit was summarized
+    /// before it was sent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query_compression: Option<QueryCompression>,
+    /// The time limit upstream calls ran under; only set by endpoints that
+    /// take `timeout_secs`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_budget: Option<TimeoutBudget>,
+}
+
+/// A research query that was too long for Polyfactual, and the summary
+/// sent in its place.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+pub struct QueryCompression {
+    pub original_length: usize,
+    pub compressed_length: usize,
+    /// The model that wrote the summary
+    pub model: String,
+}
+
+/// A per-request time limit and whether the request ran up against it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct TimeoutBudget {
+    /// Seconds each upstream call was allowed: `timeout_secs`, else the
+    /// server default
+    pub limit_secs: u64,
+    /// Set when a call ran out of time and a retry or fallback answered
+    /// instead
+    pub timed_out: bool,
+}
+
+/// Which AI provider a request named (or defaulted to) and which one wrote
+/// the analysis.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AiProviderUsed {
+    /// e.g. `grok`
+    pub requested_provider: String,
+    /// e.g. `openai` after Grok failed
+    pub provider: String,
+}
+
+/// Tokens an AI provider billed and what they cost at `AI_MODEL_PRICES`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, ToSchema)]
+pub struct AiUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Unset when a model involved has no configured price
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_usd: Option<f64>,
+}
+
+/// Nothing billed, at no cost.
+impl Default for AiUsage {
+    fn default() -> Self {
+        Self {
+            prompt_tokens: 0,
+            completion_tokens: 0,
+            estimated_cost_usd: Some(0.0),
+        }
+    }
+}
+
+impl AiUsage {
+    /// Both usages together; the cost is only known if both costs are.
+    pub fn combine(self, other: AiUsage) -> AiUsage {
+        AiUsage {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            estimated_cost_usd: self
+                .estimated_cost_usd
+                .zip(other.estimated_cost_usd)
+                .map(|(a, b)| a + b),
+        }
+    }
 }
 
@@ -0,0 +1,5028 @@
+//! Handlers driven through `create_router()` against the in-memory
+//! upstreams in `predict_os_be::mock`.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Method, Request, StatusCode};
+use futures_util::StreamExt;
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tower::ServiceExt;
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use predict_os_be::api::analysis_store::MarketSnapshot;
+use predict_os_be::api::analysis_subscriptions::{
+    detect_change, run_due, Subscription, SubscriptionStore,
+};
+use predict_os_be::api::analyze_event_markets::{apply_risk_gate, resolve_target, suggested_size};
+use predict_os_be::api::chart::downsample_lttb;
+use predict_os_be::api::construct_portfolio::{allocate, kelly_fraction, Candidate};
+use predict_os_be::api::csv_export::{CsvSerializable, LedgerRow};
+use predict_os_be::api::event_mispricing::{
+    detect_structure, direction, price_sum, size_trade, Bucket,
+};
+use predict_os_be::api::exposure_caps::ExposureCaps;
+use predict_os_be::api::fields::select_fields;
+use predict_os_be::api::idempotency::{Claim, IdempotencyStore};
+use predict_os_be::api::jobs::JobQueue;
+use predict_os_be::api::leaderboard::build_leaderboard;
+use predict_os_be::api::limit_order_bot::{
+    check_straddle, decide_simple_price, resolve_targets, summarize_run, PlannedOrder, StraddleSide,
+};
+use predict_os_be::api::limit_order_diff::{reconcile, LiveOrder};
+use predict_os_be::api::market_cache::{MarketCache, MarketSearchCache};
+use predict_os_be::api::middleware::{IpRateLimiter, RouteGroup};
+use predict_os_be::api::pagination::Paginated;
+use predict_os_be::api::position_monitor::check_monitors;
+use predict_os_be::api::position_tracker::calculate_pair_status;
+use predict_os_be::api::refresh_analysis::{compute_movement, exceeds_threshold};
+use predict_os_be::api::runtime_config::RuntimeSettingsUpdate;
+use predict_os_be::api::status::{
+    derive_public_status, FeatureAvailability, InternalStatus, OverallStatus, PublicStatus,
+};
+use predict_os_be::api::wallet_snapshots::WalletSnapshot;
+use predict_os_be::api::{create_router, middleware, AppState};
+use predict_os_be::clients::clob_signing::ClobSigner;
+use predict_os_be::clients::polymarket::{
+    ClobOrder, PolymarketEvent, PositionData, WalletPosition,
+};
+use predict_os_be::clients::{HttpClientConfig, PolymarketClient, WebhookSender};
+use predict_os_be::config::Config;
+use predict_os_be::error::{ErrorCode, RESPONSE_VERSION_HEADER};
+use predict_os_be::mock::{self, MockUpstreams};
+use predict_os_be::storage::Storage;
+use predict_os_be::types::{
+    AiAnalysis, AnalysisDrift, BookLevel, BotLogEvent, BotLogEventKind, Candle, Citation,
+    EventStructure, LadderProfile, LadderSpacing, MarketData, MarketSummary, MispricingDirection,
+    OrderBook, OrderMode, OrderResult, OrderStatus, Outcome, OutcomeTarget, PairStatus, Platform,
+    PortfolioConstraint, Position, PositionTrackerResponse, Price, Recommendation, ShareImbalance,
+    SimplePricing, SubscriptionCadence, SubscriptionRunPoint, TargetMatch,
+};
+use predict_os_be::AppError;
+
+const TOKEN_YES: &str = "1111";
+const TOKEN_NO: &str = "2222";
+const WALLET: &str = "0x00000000000000000000000000000000000000aa";
+/// Any valid secp256k1 key; the mock venue never checks signatures.
+const WALLET_KEY: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+
+fn state(upstreams: &MockUpstreams) -> Arc<AppState> {
+    mock::app_state(upstreams, mock::config())
+}
+
+async fn send(state: Arc<AppState>, request: Request<Body>) -> (StatusCode, Value) {
+    let response = create_router()
+        .with_state(state)
+        .oneshot(request)
+        .await
+        .expect("router is infallible");
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body is readable");
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+fn post(uri: &str, body: Value) -> Request<Body> {
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+fn signed(method: Method, uri: &str, body: Option<Value>) -> Request<Body> {
+    let builder = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-wallet-private-key", WALLET_KEY);
+    match body {
+        Some(body) => builder
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string()))
+            .unwrap(),
+        None => builder.body(Body::empty()).unwrap(),
+    }
+}
+
+fn market(slug: &str) -> MarketData {
+    mock::binary_market(slug, [("Yes", TOKEN_YES, 0.6), ("No", TOKEN_NO, 0.4)])
+}
+
+fn live_order(order_id: &str, token_id: &str) -> ClobOrder {
+    ClobOrder {
+        id: order_id.to_string(),
+        asset_id: token_id.to_string(),
+        status: "LIVE".to_string(),
+        side: "BUY".to_string(),
+        price: "0.4".to_string(),
+        original_size: "10".to_string(),
+        size_matched: "0".to_string(),
+        outcome: String::new(),
+        expiration: "0".to_string(),
+    }
+}
+
+fn error_message(body: &Value) -> &str {
+    body["error"].as_str().unwrap_or_default()
+}
+
+#[tokio::test]
+async fn health_ready_and_metrics_answer_without_upstreams() {
+    let upstreams = MockUpstreams::default();
+
+    let (status, body) = send(state(&upstreams), get("/health")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, "OK");
+
+    let (status, body) = send(state(&upstreams), get("/ready")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["status"], "ready");
+    assert_eq!(body["capabilities"]["dome"], false);
+
+    let (status, _) = send(state(&upstreams), get("/metrics")).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[test]
+fn ip_rate_limits_refuse_past_the_limit_until_the_window_passes() {
+    let limiter = IpRateLimiter::new(1, 2, false);
+    let client: IpAddr = "203.0.113.7".parse().unwrap();
+    let other: IpAddr = "203.0.113.8".parse().unwrap();
+    let start = Instant::now();
+
+    assert!(limiter.check(client, RouteGroup::Standard, start).is_ok());
+    let later = start + Duration::from_secs(20);
+    assert!(limiter.check(client, RouteGroup::Standard, later).is_ok());
+    match limiter.check(client, RouteGroup::Standard, later) {
+        Err(AppError::RateLimit { retry_after, .. }) => {
+            // Waits for the first request to leave the window
+            assert_eq!(retry_after, Some(Duration::from_secs(40)));
+        }
+        other => panic!("expected a rate limit, got {other:?}"),
+    }
+    // Groups and clients each have their own budget
+    assert!(limiter.check(client, RouteGroup::Ai, later).is_ok());
+    assert!(limiter.check(client, RouteGroup::Ai, later).is_err());
+    assert!(limiter.check(other, RouteGroup::Standard, later).is_ok());
+
+    // The first request expires after 60s, freeing one slot
+    let reset = start + Duration::from_secs(60);
+    assert!(limiter.check(client, RouteGroup::Standard, reset).is_ok());
+    assert!(limiter.check(client, RouteGroup::Standard, reset).is_err());
+
+    // A limit of 0 turns the group off
+    let unlimited = IpRateLimiter::new(0, 0, false);
+    for _ in 0..100 {
+        assert!(unlimited.check(client, RouteGroup::Ai, start).is_ok());
+    }
+}
+
+#[test]
+fn probes_are_never_rate_limited() {
+    for path in ["/health", "/ready", "/metrics"] {
+        assert_eq!(RouteGroup::for_path(path), None, "{path}");
+    }
+    assert_eq!(RouteGroup::for_path("/health/deep"), None);
+    assert_eq!(
+        RouteGroup::for_path("/api/analyze-event-markets"),
+        Some(RouteGroup::Ai)
+    );
+    assert_eq!(
+        RouteGroup::for_path("/api/analyze-and-trade"),
+        Some(RouteGroup::Ai)
+    );
+    assert_eq!(
+        RouteGroup::for_path("/api/jobs/analyze"),
+        Some(RouteGroup::Ai)
+    );
+    assert_eq!(
+        RouteGroup::for_path("/api/jobs/research"),
+        Some(RouteGroup::Standard)
+    );
+    // Prefixes match whole segments only
+    assert_eq!(RouteGroup::for_path("/healthz"), Some(RouteGroup::Standard));
+}
+
+#[tokio::test]
+async fn rate_limits_key_on_the_forwarded_client_only_when_the_proxy_is_trusted() {
+    let upstreams = MockUpstreams::default();
+    let limited = |trust_proxy: bool| {
+        mock::app_state(
+            &upstreams,
+            Config {
+                rate_limit_per_min: 1,
+                rate_limit_trust_proxy: trust_proxy,
+                ..mock::config()
+            },
+        )
+    };
+    let call = |state: &Arc<AppState>, uri: &str, forwarded_for: &str| {
+        let request = Request::get(uri)
+            .header("x-forwarded-for", forwarded_for)
+            .body(Body::empty())
+            .unwrap();
+        create_router()
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                middleware::rate_limit,
+            ))
+            .with_state(state.clone())
+            .oneshot(request)
+    };
+
+    // Trusted: the entry the proxy appended is the client, and each gets
+    // its own budget
+    let state = limited(true);
+    for forwarded_for in ["198.51.100.1", "203.0.113.9, 198.51.100.2"] {
+        let response = call(&state, "/status/public", forwarded_for).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{forwarded_for}");
+    }
+    // Entries the client made up in front don't buy a fresh budget
+    let refused = call(&state, "/status/public", "203.0.113.10, 198.51.100.1")
+        .await
+        .unwrap();
+    assert_eq!(refused.status(), StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = refused.headers()[header::RETRY_AFTER]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!((1..=60).contains(&retry_after), "{retry_after}");
+    // Probes stay open to a client over its limit
+    for probe in ["/health", "/ready", "/metrics"] {
+        let response = call(&state, probe, "198.51.100.1").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK, "{probe}");
+    }
+
+    // Untrusted: the header is ignored, so both share the socket's budget
+    let state = limited(false);
+    let response = call(&state, "/status/public", "198.51.100.1")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = call(&state, "/status/public", "198.51.100.2")
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn api_tokens_guard_the_api_but_not_the_docs_or_probes() {
+    let upstreams = MockUpstreams::default();
+    let guarded = |metrics_require_auth: bool| {
+        mock::app_state(
+            &upstreams,
+            Config {
+                api_auth_tokens: vec!["first-token".to_string(), "second-token".to_string()],
+                metrics_require_auth,
+                ..mock::config()
+            },
+        )
+    };
+    let call = |state: &Arc<AppState>, uri: &str, token: Option<&str>| {
+        let mut request = Request::get(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        let request = request.body(Body::empty()).unwrap();
+        let state = state.clone();
+        async move {
+            let response = create_router()
+                .layer(axum::middleware::from_fn_with_state(
+                    state.clone(),
+                    middleware::require_api_token,
+                ))
+                .with_state(state)
+                .oneshot(request)
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+            (status, body)
+        }
+    };
+    let tracker = "/api/position-tracker?wallet_address=0x00000000000000000000000000000000000000aa&market_slug=no-such-market";
+
+    let state = guarded(false);
+    let (status, body) = call(&state, tracker, None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert_eq!(body["code"], "UNAUTHORIZED");
+    assert!(
+        error_message(&body).contains("Missing Authorization: Bearer <token> header"),
+        "{body}"
+    );
+    let (status, body) = call(&state, tracker, Some("first-tokenX")).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    assert!(error_message(&body).contains("Invalid API token"), "{body}");
+    // Any configured token gets through to the handler
+    for token in ["first-token", "second-token"] {
+        let (status, body) = call(&state, tracker, Some(token)).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "{token}: {body}");
+    }
+    // The price stream is behind the token too
+    let (status, _) = call(&state, "/ws/market/no-such-market", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Docs, probes and the public status page stay open
+    for open in [
+        "/api/openapi.json",
+        "/api/docs/",
+        "/health",
+        "/ready",
+        "/metrics",
+        "/status/public",
+    ] {
+        let (status, _) = call(&state, open, None).await;
+        assert_ne!(status, StatusCode::UNAUTHORIZED, "{open}");
+    }
+
+    // METRICS_REQUIRE_AUTH puts /metrics behind the token
+    let state = guarded(true);
+    let (status, _) = call(&state, "/metrics", None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = call(&state, "/metrics", Some("second-token")).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = call(&state, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // With no tokens configured the API is open
+    let open = mock::app_state(&upstreams, mock::config());
+    let (status, _) = call(&open, tracker, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn public_status_maps_internal_state_onto_features() {
+    let internal = InternalStatus {
+        dome_configured: true,
+        polyfactual_configured: true,
+        grok_configured: false,
+        openai_configured: true,
+        trading_enabled: true,
+        incident_message: None,
+    };
+    assert_eq!(
+        derive_public_status(&internal),
+        PublicStatus {
+            status: OverallStatus::Operational,
+            features: FeatureAvailability {
+                analysis: true,
+                research: true,
+                trading: true,
+                tracking: true,
+            },
+            incident: None,
+        }
+    );
+
+    // Analysis needs Dome and either AI provider
+    let cases = [
+        (false, true, true, false),
+        (true, false, false, false),
+        (true, true, false, true),
+        (true, false, true, true),
+    ];
+    for (dome, grok, openai, analysis) in cases {
+        let status = derive_public_status(&InternalStatus {
+            dome_configured: dome,
+            grok_configured: grok,
+            openai_configured: openai,
+            ..internal.clone()
+        });
+        assert_eq!(status.features.analysis, analysis, "{dome} {grok} {openai}");
+        let expected = if analysis {
+            OverallStatus::Operational
+        } else {
+            OverallStatus::Degraded
+        };
+        assert_eq!(status.status, expected);
+    }
+
+    // Safe mode and a missing research provider each degrade the service
+    let status = derive_public_status(&InternalStatus {
+        polyfactual_configured: false,
+        trading_enabled: false,
+        incident_message: Some("Trading paused for maintenance".to_string()),
+        ..internal.clone()
+    });
+    assert_eq!(status.status, OverallStatus::Degraded);
+    assert!(!status.features.research && !status.features.trading);
+    assert_eq!(
+        status.incident.as_deref(),
+        Some("Trading paused for maintenance")
+    );
+}
+
+#[tokio::test]
+async fn public_status_is_cacheable_and_exposes_nothing_internal() {
+    let upstreams = MockUpstreams::default();
+    let state = state(&upstreams);
+    let fetch = || {
+        create_router()
+            .with_state(state.clone())
+            .oneshot(get("/status/public"))
+    };
+
+    let response = fetch().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "public, max-age=15"
+    );
+    let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    // Exactly these fields, so nothing internal rides along
+    assert_eq!(
+        body,
+        json!({
+            "status": "degraded",
+            "features": {"analysis": false, "research": false, "trading": true, "tracking": true},
+            "incident": null,
+        })
+    );
+
+    // The incident message is set through the runtime config
+    state.runtime_config.apply(
+        RuntimeSettingsUpdate {
+            incident_message: Some("  Gamma is slow  ".to_string()),
+            trading_enabled: Some(false),
+        },
+        "on-call",
+    );
+    let (_, body) = send(state.clone(), get("/status/public")).await;
+    assert_eq!(body["incident"], "Gamma is slow");
+    assert_eq!(body["features"]["trading"], false);
+}
+
+#[tokio::test]
+async fn deep_health_reports_a_failing_upstream_as_down() {
+    let upstreams = MockUpstreams::all();
+    upstreams.venue.fail("ping", || {
+        AppError::ExternalApi("Gamma API error 500".to_string())
+    });
+
+    let (status, body) = send(state(&upstreams), get("/health/deep")).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(body["status"], "down");
+    let gamma = body["dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|d| d["name"] == "gamma")
+        .unwrap();
+    assert_eq!(gamma["status"], "down");
+}
+
+#[tokio::test]
+async fn orderbook_returns_the_book_for_a_token() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_order_book(OrderBook::from_levels(
+        TOKEN_YES,
+        vec![BookLevel {
+            price: 0.58,
+            size: 100.0,
+        }],
+        vec![BookLevel {
+            price: 0.62,
+            size: 50.0,
+        }],
+    ));
+
+    let uri = format!("/api/orderbook?token_id={}", TOKEN_YES);
+    let (status, body) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["order_book"]["best_bid"], 0.58);
+    assert_eq!(body["order_book"]["best_ask"], 0.62);
+    assert_eq!(upstreams.venue.calls(), ["get_order_book"]);
+}
+
+#[tokio::test]
+async fn read_only_gets_are_revalidated_with_etags() {
+    let upstreams = MockUpstreams::default();
+    let book = |bid: f64| {
+        let level = |price: f64| BookLevel { price, size: 100.0 };
+        OrderBook::from_levels(TOKEN_YES, vec![level(bid)], vec![level(0.62)])
+    };
+    upstreams.venue.insert_order_book(book(0.58));
+    let state = Arc::new(AppState {
+        market_cache: Arc::new(MarketCache::new(
+            std::time::Duration::from_secs(10),
+            std::time::Duration::from_secs(60),
+        )),
+        ..(*state(&upstreams)).clone()
+    });
+    let uri = format!("/api/orderbook?token_id={}", TOKEN_YES);
+    let request = |etag: Option<&str>| {
+        let mut request = Request::get(&uri);
+        if let Some(etag) = etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        request.body(Body::empty()).unwrap()
+    };
+    let respond =
+        |request: Request<Body>| create_router().with_state(state.clone()).oneshot(request);
+
+    let response = respond(request(None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "private, max-age=10"
+    );
+    let etag = response.headers()[header::ETAG]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(etag.starts_with("W/\""), "{etag}");
+
+    // Same book, new timestamp: still not modified
+    let response = respond(request(Some(&etag))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    assert_eq!(
+        response.headers()[header::CACHE_CONTROL],
+        "private, max-age=10"
+    );
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    assert!(body.is_empty());
+
+    // A list of tags, or the strong form of the same tag, matches too
+    let listed = format!("W/\"stale\", {}", etag.trim_start_matches("W/"));
+    let response = respond(request(Some(&listed))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+
+    // A changed book is sent in full with a new tag
+    upstreams.venue.insert_order_book(book(0.59));
+    let response = respond(request(Some(&etag))).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_ne!(response.headers()[header::ETAG], etag.as_str());
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["order_book"]["best_bid"], 0.59);
+
+    // Errors and POSTs carry no validators
+    let (status, _) = send(state.clone(), get("/api/orderbook?token_id=abc")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let response = respond(
+        Request::post("/api/portfolio")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(json!({ "wallet_address": WALLET }).to_string()))
+            .unwrap(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::ETAG).is_none());
+    let response = respond(get(&format!("/api/portfolio?wallet_address={WALLET}")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::ETAG).is_some());
+}
+
+#[tokio::test]
+async fn orderbook_rejects_a_non_numeric_token_without_calling_upstream() {
+    let upstreams = MockUpstreams::default();
+
+    let (status, body) = send(state(&upstreams), get("/api/orderbook?token_id=abc")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("token_id"));
+    assert!(upstreams.venue.calls().is_empty());
+}
+
+#[tokio::test]
+async fn orderbook_maps_upstream_failures() {
+    let upstreams = MockUpstreams::default();
+    let uri = format!("/api/orderbook?token_id={}", TOKEN_YES);
+
+    let (status, _) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    upstreams.venue.fail("get_order_book", || {
+        AppError::Timeout("CLOB API did not respond".to_string())
+    });
+    let (status, _) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+
+    upstreams.venue.fail("get_order_book", || {
+        AppError::ExternalApi("CLOB API error 500".to_string())
+    });
+    let (status, _) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn polyfactual_research_needs_the_integration() {
+    let upstreams = MockUpstreams::default();
+
+    let request = post("/api/polyfactual-research", json!({ "query": "Who wins?" }));
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("POLYFACTUAL_API_KEY"));
+}
+
+#[tokio::test]
+async fn polyfactual_research_returns_the_answer() {
+    let upstreams = MockUpstreams::all();
+    let research = upstreams.research.clone().unwrap();
+    research.set_answer("Probably yes", Vec::new());
+
+    let request = post("/api/polyfactual-research", json!({ "query": "Who wins?" }));
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["answer"], "Probably yes");
+    assert_eq!(research.calls(), ["research"]);
+}
+
+#[tokio::test]
+async fn polyfactual_research_rejects_an_empty_query() {
+    let upstreams = MockUpstreams::all();
+
+    let request = post("/api/polyfactual-research", json!({ "query": "" }));
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(upstreams.research.unwrap().calls().is_empty());
+}
+
+#[tokio::test]
+async fn polyfactual_research_runs_within_the_requested_timeout() {
+    let upstreams = MockUpstreams::all();
+    let research = upstreams.research.clone().unwrap();
+
+    for timeout_secs in [0, 901] {
+        let body = json!({ "query": "Who wins?", "timeout_secs": timeout_secs });
+        let request = post("/api/polyfactual-research", body);
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error_message(&body).contains("timeout_secs"), "{body}");
+    }
+
+    let body = json!({ "query": "Who wins?", "timeout_secs": 1 });
+    let request = post("/api/polyfactual-research", body);
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body["metadata"]["timeout_budget"],
+        json!({ "limit_secs": 1, "timed_out": false })
+    );
+
+    research.set_delay(std::time::Duration::from_secs(2));
+    let body = json!({ "query": "Who wins?", "timeout_secs": 1, "force_refresh": true });
+    let request = post("/api/polyfactual-research", body);
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::GATEWAY_TIMEOUT);
+    assert!(error_message(&body).contains("after 1s"), "{body}");
+}
+
+#[tokio::test]
+async fn polyfactual_research_passes_on_upstream_rate_limits() {
+    let upstreams = MockUpstreams::all();
+    upstreams
+        .research
+        .as_ref()
+        .unwrap()
+        .fail("research", || AppError::RateLimit {
+            retry_after: Some(std::time::Duration::from_secs(30)),
+            upstream: Some("Polyfactual API".to_string()),
+        });
+
+    let request = post("/api/polyfactual-research", json!({ "query": "Who wins?" }));
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn every_error_carries_a_stable_code() {
+    for code in ErrorCode::ALL {
+        let serialized = serde_json::to_value(code).unwrap();
+        assert_eq!(serialized, code.as_str());
+        assert_eq!(
+            serde_json::from_value::<ErrorCode>(serialized).unwrap(),
+            code
+        );
+    }
+    assert_eq!(ErrorCode::MarketNotFound.as_str(), "MARKET_NOT_FOUND");
+
+    let cases = [
+        (
+            AppError::Internal(anyhow::anyhow!("boom")),
+            "INTERNAL_ERROR",
+            500,
+        ),
+        (AppError::Validation("bad".into()), "VALIDATION_FAILED", 400),
+        (
+            AppError::missing_api_key("DOME_API_KEY"),
+            "MISSING_API_KEY",
+            400,
+        ),
+        (AppError::ExternalApi("down".into()), "UPSTREAM_ERROR", 502),
+        (
+            AppError::MalformedResponse("junk".into()),
+            "UPSTREAM_MALFORMED_RESPONSE",
+            502,
+        ),
+        (
+            AppError::ai_parse("no JSON".into(), "maybe?"),
+            "AI_PARSE_FAILED",
+            502,
+        ),
+        (
+            AppError::UpstreamRejected("403".into()),
+            "UPSTREAM_REJECTED",
+            502,
+        ),
+        (
+            AppError::RateLimit {
+                retry_after: None,
+                upstream: None,
+            },
+            "RATE_LIMITED",
+            429,
+        ),
+        (
+            AppError::RateLimit {
+                retry_after: Some(std::time::Duration::from_millis(1500)),
+                upstream: Some("Gamma API".into()),
+            },
+            "UPSTREAM_RATE_LIMITED",
+            429,
+        ),
+        (AppError::Timeout("slow".into()), "UPSTREAM_TIMEOUT", 504),
+        (
+            AppError::NotFound("Run 1 not found".into()),
+            "NOT_FOUND",
+            404,
+        ),
+        (
+            AppError::MarketNotFound {
+                market: "no-such-market".into(),
+                message: "Gamma market not found: no-such-market".into(),
+            },
+            "MARKET_NOT_FOUND",
+            404,
+        ),
+        (AppError::Conflict("busy".into()), "CONFLICT", 409),
+        (
+            AppError::Unauthorized("no token".into()),
+            "UNAUTHORIZED",
+            401,
+        ),
+        (
+            AppError::TradingDisabled {
+                since: None,
+                actor: None,
+            },
+            "TRADING_DISABLED",
+            503,
+        ),
+    ];
+    for (error, code, status) in cases {
+        assert_eq!(error.code().as_str(), code);
+        let body = error.into_json().await;
+        // Outside a request errors keep the v1 layout
+        assert_eq!(body["code"], code, "{body}");
+        assert_eq!(body["status"], status, "{body}");
+        assert!(body["error"].is_string(), "{body}");
+    }
+
+    let details = AppError::RateLimit {
+        retry_after: Some(std::time::Duration::from_millis(1500)),
+        upstream: Some("Gamma API".into()),
+    }
+    .details()
+    .unwrap();
+    assert_eq!(
+        details,
+        json!({ "retry_after_secs": 2, "upstream": "Gamma API" })
+    );
+    assert_eq!(
+        AppError::missing_api_key("DOME_API_KEY").details().unwrap(),
+        json!({ "variable": "DOME_API_KEY" })
+    );
+}
+
+#[tokio::test]
+async fn error_layout_follows_the_response_version_header() {
+    let upstreams = MockUpstreams::default();
+    let state = state(&upstreams);
+    let send = |version: Option<&str>| {
+        let mut request = Request::get(
+            "/api/position-tracker?wallet_address=0x00000000000000000000000000000000000000aa&market_slug=no-such-market",
+        );
+        if let Some(version) = version {
+            request = request.header(RESPONSE_VERSION_HEADER, version);
+        }
+        create_router()
+            .layer(axum::middleware::from_fn_with_state(
+                state.clone(),
+                middleware::request_context,
+            ))
+            .with_state(state.clone())
+            .oneshot(request.body(Body::empty()).unwrap())
+    };
+    let read = |response: axum::response::Response| async move {
+        let version = response.headers()[RESPONSE_VERSION_HEADER].clone();
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (version, serde_json::from_slice::<Value>(&bytes).unwrap())
+    };
+
+    // Without the header: the message string, with the code beside it
+    let response = send(None).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let (version, body) = read(response).await;
+    assert_eq!(version, "1");
+    assert_eq!(
+        error_message(&body),
+        "Gamma market not found: no-such-market"
+    );
+    assert_eq!(body["code"], "MARKET_NOT_FOUND");
+    assert_eq!(body["market"], "no-such-market");
+
+    let response = send(Some("2")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let (version, body) = read(response).await;
+    assert_eq!(version, "2");
+    assert_eq!(
+        body["error"],
+        json!({
+            "code": "MARKET_NOT_FOUND",
+            "message": "Gamma market not found: no-such-market",
+            "status": 404,
+            "details": { "market": "no-such-market" },
+        })
+    );
+    assert!(body["request_id"].is_string(), "{body}");
+}
+
+#[tokio::test]
+async fn research_citations_can_be_filtered_by_relevance() {
+    let upstreams = MockUpstreams::all();
+    let citation = |source: &str, relevance: f64| Citation {
+        source: source.to_string(),
+        url: None,
+        relevance,
+    };
+    upstreams.research.as_ref().unwrap().set_answer(
+        "Probably yes",
+        vec![citation("Reuters", 0.9), citation("Blog", 0.2)],
+    );
+    let state = state(&upstreams);
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut?", "min_relevance": 0.5 }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["citations"].as_array().unwrap().len(), 1);
+    assert_eq!(body["citations"][0]["source"], "Reuters");
+
+    // The filter applies to the cached answer, not the cache entry
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut?" }),
+    );
+    let (_, body) = send(state.clone(), request).await;
+    assert_eq!(body["metadata"]["cache_hit"], true);
+    assert_eq!(body["citations"].as_array().unwrap().len(), 2);
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut?", "min_relevance": 1.5 }),
+    );
+    let (status, body) = send(state, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_message(&body),
+        "min_relevance must be between 0 and 1"
+    );
+}
+
+#[tokio::test]
+async fn long_research_queries_need_an_ai_provider_to_summarize_them() {
+    let upstreams = MockUpstreams::all();
+    let state = mock::app_state(
+        &upstreams,
+        Config {
+            polyfactual_max_query_length: 40,
+            ..mock::config()
+        },
+    );
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut?" }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["metadata"].get("query_compression").is_none());
+
+    // No AI keys are configured here, so nothing can shorten it
+    let long = "Will the Fed cut rates at its March meeting, per the statement?";
+    let request = post("/api/polyfactual-research", json!({ "query": long }));
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        error_message(&body),
+        "Query is 63 characters, over the 40-character limit, and no AI provider is configured to summarize it"
+    );
+
+    let uri = format!(
+        "/api/polyfactual-research/stream?query={}",
+        long.replace(' ', "%20")
+            .replace(',', "%2C")
+            .replace('?', "%3F")
+    );
+    let (status, body) = send(state, get(&uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("no AI provider"), "{body}");
+}
+
+#[tokio::test]
+async fn repeated_research_queries_are_served_from_the_cache() {
+    let upstreams = MockUpstreams::all();
+    let research = upstreams.research.clone().unwrap();
+    let state = state(&upstreams);
+
+    let (_, first) = send(
+        state.clone(),
+        post(
+            "/api/polyfactual-research",
+            json!({ "query": "Will the Fed cut?" }),
+        ),
+    )
+    .await;
+    assert_eq!(first["metadata"]["cache_hit"], false);
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "  will the  FED cut? " }),
+    );
+    let (status, second) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(second["metadata"]["cache_hit"], true);
+    assert_eq!(
+        second["metadata"]["timestamp"],
+        first["metadata"]["timestamp"]
+    );
+    assert_eq!(research.calls(), ["research"]);
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut?", "force_refresh": true }),
+    );
+    let (_, third) = send(state, request).await;
+    assert_eq!(third["metadata"]["cache_hit"], false);
+    assert_eq!(research.calls(), ["research", "research"]);
+}
+
+#[tokio::test]
+async fn concurrent_identical_research_queries_share_one_call() {
+    let upstreams = MockUpstreams::all();
+    let research = upstreams.research.clone().unwrap();
+    research.set_delay(std::time::Duration::from_millis(50));
+    let state = state(&upstreams);
+
+    let ask = || {
+        send(
+            state.clone(),
+            post("/api/polyfactual-research", json!({ "query": "Who wins?" })),
+        )
+    };
+    let ((_, a), (_, b), (_, c)) = tokio::join!(ask(), ask(), ask());
+    let mut hits: Vec<bool> = [a, b, c]
+        .iter()
+        .map(|body| body["metadata"]["cache_hit"].as_bool().unwrap())
+        .collect();
+    hits.sort();
+    assert_eq!(hits, [false, true, true]);
+    assert_eq!(research.calls(), ["research"]);
+}
+
+/// The `(event, data)` pairs of a server-sent event stream.
+async fn events(state: Arc<AppState>, uri: &str) -> Vec<(String, Value)> {
+    let response = create_router()
+        .with_state(state)
+        .oneshot(get(uri))
+        .await
+        .expect("router is infallible");
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body is readable");
+    String::from_utf8(bytes.to_vec())
+        .unwrap()
+        .split("\n\n")
+        .filter(|block| !block.trim().is_empty())
+        .map(|block| {
+            let field = |name: &str| {
+                block
+                    .lines()
+                    .find_map(|line| line.strip_prefix(name))
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let data = serde_json::from_str(&field("data: ")).unwrap_or(Value::Null);
+            (field("event: "), data)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn polyfactual_research_stream_ends_with_the_result() {
+    let upstreams = MockUpstreams::all();
+    upstreams
+        .research
+        .as_ref()
+        .unwrap()
+        .set_answer("Probably yes", Vec::new());
+
+    let events = events(
+        state(&upstreams),
+        "/api/polyfactual-research/stream?query=Who%20wins%3F",
+    )
+    .await;
+    let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["accepted", "result"]);
+    assert_eq!(events[0].1["query"], "Who wins?");
+    assert_eq!(events[1].1["answer"], "Probably yes");
+}
+
+#[tokio::test(start_paused = true)]
+async fn polyfactual_research_stream_sends_heartbeats_while_waiting() {
+    let upstreams = MockUpstreams::all();
+    upstreams
+        .research
+        .as_ref()
+        .unwrap()
+        .set_delay(std::time::Duration::from_secs(40));
+
+    let events = events(
+        state(&upstreams),
+        "/api/polyfactual-research/stream?query=q",
+    )
+    .await;
+    let names: Vec<&str> = events.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["accepted", "heartbeat", "heartbeat", "result"]);
+    assert_eq!(events[1].1["elapsed_secs"], 15);
+    assert_eq!(events[2].1["elapsed_secs"], 30);
+}
+
+#[tokio::test]
+async fn polyfactual_research_stream_reports_upstream_errors_as_an_event() {
+    let upstreams = MockUpstreams::all();
+    upstreams.research.as_ref().unwrap().fail("research", || {
+        AppError::ExternalApi("Polyfactual API error 500".to_string())
+    });
+
+    let events = events(
+        state(&upstreams),
+        "/api/polyfactual-research/stream?query=q",
+    )
+    .await;
+    let (name, data) = events.last().unwrap();
+    assert_eq!(name, "error");
+    assert_eq!(data["status"], 502);
+    assert!(data["error"]
+        .as_str()
+        .unwrap()
+        .contains("Polyfactual API error 500"));
+}
+
+#[tokio::test]
+async fn polyfactual_research_stream_validates_before_streaming() {
+    let upstreams = MockUpstreams::all();
+    let (status, body) = send(
+        state(&upstreams),
+        get("/api/polyfactual-research/stream?query=%20"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("query is required"));
+
+    let (status, body) = send(
+        state(&MockUpstreams::default()),
+        get("/api/polyfactual-research/stream?query=q"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("POLYFACTUAL_API_KEY"));
+    assert!(upstreams.research.unwrap().calls().is_empty());
+}
+
+/// Polls a job until it reaches `status`.
+async fn wait_for_job(state: &Arc<AppState>, id: &str, status: &str) -> Value {
+    for _ in 0..200 {
+        let (_, job) = send(state.clone(), get(&format!("/api/jobs/{}", id))).await;
+        if job["status"] == status {
+            return job;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    panic!("job {id} never reached {status}");
+}
+
+#[tokio::test]
+async fn research_jobs_run_in_the_background() {
+    let upstreams = MockUpstreams::all();
+    upstreams
+        .research
+        .as_ref()
+        .unwrap()
+        .set_answer("Probably yes", Vec::new());
+    let state = state(&upstreams);
+
+    let request = post("/api/jobs/research", json!({ "query": "Who wins?" }));
+    let (status, job) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(job["kind"], "research");
+    assert_eq!(job["status"], "queued");
+
+    let job = wait_for_job(&state, job["id"].as_str().unwrap(), "succeeded").await;
+    assert_eq!(job["result"]["answer"], "Probably yes");
+    assert!(job["started_at"].is_string() && job["finished_at"].is_string());
+}
+
+#[tokio::test]
+async fn failed_jobs_carry_the_error_body() {
+    let upstreams = MockUpstreams::all();
+    upstreams.research.as_ref().unwrap().fail("research", || {
+        AppError::ExternalApi("Polyfactual API error 500".to_string())
+    });
+    let state = state(&upstreams);
+
+    let request = post("/api/jobs/research", json!({ "query": "Who wins?" }));
+    let (_, job) = send(state.clone(), request).await;
+    let job = wait_for_job(&state, job["id"].as_str().unwrap(), "failed").await;
+    assert_eq!(job["error"]["status"], 502);
+    assert!(job["result"].is_null());
+}
+
+#[tokio::test]
+async fn jobs_are_refused_when_the_queue_is_full() {
+    let upstreams = MockUpstreams::all();
+    upstreams
+        .research
+        .as_ref()
+        .unwrap()
+        .set_delay(std::time::Duration::from_secs(60));
+    let mut state = (*state(&upstreams)).clone();
+    state.jobs = Arc::new(JobQueue::new(1, 1, std::time::Duration::from_secs(60)));
+    let state = Arc::new(state);
+    let submit = || post("/api/jobs/research", json!({ "query": "Who wins?" }));
+
+    let (_, running) = send(state.clone(), submit()).await;
+    wait_for_job(&state, running["id"].as_str().unwrap(), "running").await;
+    let (status, queued) = send(state.clone(), submit()).await;
+    assert_eq!(status, StatusCode::ACCEPTED);
+    assert_eq!(queued["status"], "queued");
+
+    let (status, _) = send(state.clone(), submit()).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn jobs_are_validated_before_queueing() {
+    let upstreams = MockUpstreams::all();
+
+    let request = post("/api/jobs/analyze", json!({ "url": "" }));
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("url is required"));
+
+    let (status, _) = send(state(&upstreams), get("/api/jobs/unknown")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+fn kalshi_market(ticker: &str) -> MarketData {
+    let yes = format!("{}:yes", ticker);
+    let no = format!("{}:no", ticker);
+    MarketData {
+        ticker: Some(ticker.to_string()),
+        slug: None,
+        condition_id: None,
+        platform: Platform::Kalshi,
+        ..mock::binary_market(ticker, [("Yes", &yes, 0.6), ("No", &no, 0.4)])
+    }
+}
+
+fn position(token_id: &str, shares: f64, avg_price: f64, current_price: f64) -> PositionData {
+    PositionData {
+        token_id: token_id.to_string(),
+        outcome: String::new(),
+        shares,
+        avg_price,
+        current_price,
+    }
+}
+
+fn held(outcome: &str, shares: f64, avg_price: f64) -> Position {
+    let price = Price::from_decimal(avg_price).unwrap();
+    Position {
+        token_id: format!("token-{}", outcome),
+        outcome: outcome.to_string(),
+        shares,
+        avg_price: price,
+        current_price: price,
+        unrealized_pnl: 0.0,
+        realized_pnl: None,
+    }
+}
+
+#[test]
+fn pair_status_compares_the_guaranteed_payout_with_the_cost() {
+    let close = |actual: Option<f64>, expected: f64| {
+        assert!(
+            actual.is_some_and(|v| (v - expected).abs() < 1e-9),
+            "{actual:?} != {expected}"
+        )
+    };
+
+    // Matched: ten pairs pay $10 for $9
+    let matched = calculate_pair_status(&[held("Up", 10.0, 0.45), held("Down", 10.0, 0.45)]);
+    assert_eq!(matched.status, PairStatus::ProfitLocked);
+    close(matched.profit_lock, 1.0);
+    assert_eq!(matched.break_even, None);
+    assert_eq!(matched.imbalance, None);
+
+    let even = calculate_pair_status(&[held("Up", 10.0, 0.5), held("Down", 10.0, 0.5)]);
+    assert_eq!(even.status, PairStatus::BreakEven);
+    let overpaid = calculate_pair_status(&[held("Up", 10.0, 0.55), held("Down", 10.0, 0.5)]);
+    assert_eq!(overpaid.status, PairStatus::AtRisk);
+    assert_eq!(overpaid.profit_lock, None);
+    // Fully matched, so no fill can help
+    assert_eq!(overpaid.break_even, None);
+
+    // Imbalanced: six pairs pay $6 for $6.40; four more Down under $0.90
+    // would make every share pay
+    let imbalanced = calculate_pair_status(&[held("Up", 10.0, 0.4), held("Down", 6.0, 0.4)]);
+    assert_eq!(imbalanced.status, PairStatus::AtRisk);
+    close(imbalanced.break_even, 0.9);
+    assert_eq!(
+        imbalanced.imbalance,
+        Some(ShareImbalance {
+            outcome: "Up".to_string(),
+            shares: 4.0,
+        })
+    );
+
+    // Single leg: nothing is guaranteed yet
+    let single = calculate_pair_status(&[held("Up", 10.0, 0.5)]);
+    assert_eq!(single.status, PairStatus::AtRisk);
+    assert_eq!(single.profit_lock, None);
+    close(single.break_even, 0.5);
+    assert_eq!(
+        single.imbalance,
+        Some(ShareImbalance {
+            outcome: "Up".to_string(),
+            shares: 10.0,
+        })
+    );
+
+    let kalshi = calculate_pair_status(&[held("Yes", 2.0, 0.3), held("No", 5.0, 0.3)]);
+    assert_eq!(kalshi.imbalance.unwrap().outcome, "No");
+
+    let none = calculate_pair_status(&[]);
+    assert_eq!(none.status, PairStatus::NoPosition);
+    assert_eq!(none.imbalance, None);
+}
+
+#[tokio::test]
+async fn position_tracker_combines_polymarket_and_kalshi_positions() {
+    let upstreams = MockUpstreams {
+        kalshi: Some(Arc::default()),
+        ..MockUpstreams::default()
+    };
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_market_positions(WALLET, vec![position(TOKEN_YES, 10.0, 0.5, 0.6)]);
+    let kalshi = upstreams.kalshi.clone().unwrap();
+    kalshi.insert_market(kalshi_market("KXRAIN-25DEC"));
+    kalshi.insert_positions(vec![position("KXRAIN-25DEC:no", 10.0, 0.3, 0.4)]);
+
+    let request = post(
+        "/api/position-tracker",
+        json!({
+            "platform": "both",
+            "wallet_address": WALLET,
+            "market_slug": "will-it-rain",
+            "kalshi_ticker": "kxrain-25dec",
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // Yes/No legs count towards the pair like Up/Down
+    assert_eq!(body["polymarket"]["pair_status"], "AT_RISK");
+    assert_eq!(body["polymarket"]["imbalance"]["outcome"], "Yes");
+    assert_eq!(body["kalshi"]["market"]["platform"], "kalshi");
+    assert_eq!(body["kalshi"]["positions"][0]["outcome"], "No");
+    assert_eq!(body["kalshi"]["imbalance"]["outcome"], "No");
+    let net_pnl = body["net_pnl"].as_f64().unwrap();
+    assert!((net_pnl - 2.0).abs() < 1e-9, "{net_pnl}");
+
+    // One platform keeps the flat response
+    let request = post(
+        "/api/position-tracker",
+        json!({ "platform": "kalshi", "kalshi_ticker": "KXRAIN-25DEC" }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["market"]["ticker"], "KXRAIN-25DEC");
+    assert!(body.get("net_pnl").is_none());
+}
+
+#[tokio::test]
+async fn position_tracker_reports_changes_between_polls() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let state = state(&upstreams);
+    let poll = |track_changes: bool| {
+        post(
+            "/api/position-tracker",
+            json!({
+                "wallet_address": WALLET,
+                "market_slug": "will-it-rain",
+                "track_changes": track_changes,
+            }),
+        )
+    };
+    let change = |body: &Value, token_id: &str| -> (f64, f64) {
+        let change = body["changes"]["positions"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["token_id"] == token_id)
+            .unwrap_or_else(|| panic!("no change for {token_id}: {body}"))
+            .clone();
+        (
+            change["shares_delta"].as_f64().unwrap(),
+            change["pnl_delta"].as_f64().unwrap(),
+        )
+    };
+    let close = |(shares, pnl): (f64, f64), expected: (f64, f64)| {
+        assert!(
+            (shares - expected.0).abs() < 1e-9 && (pnl - expected.1).abs() < 1e-9,
+            "{:?} != {:?}",
+            (shares, pnl),
+            expected
+        );
+    };
+
+    upstreams
+        .venue
+        .insert_market_positions(WALLET, vec![position(TOKEN_YES, 10.0, 0.5, 0.6)]);
+    let (status, body) = send(state.clone(), poll(true)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["changes"]["since"].is_null());
+    assert_eq!(body["changes"]["positions"], json!([]));
+    assert_eq!(body["changes"]["new_fills_detected"], false);
+
+    upstreams.venue.insert_market_positions(
+        WALLET,
+        vec![
+            position(TOKEN_YES, 15.0, 0.5, 0.7),
+            position(TOKEN_NO, 5.0, 0.4, 0.3),
+        ],
+    );
+    let (_, body) = send(state.clone(), poll(true)).await;
+    assert!(body["changes"]["since"].is_string());
+    assert!(body["changes"]["elapsed_ms"].is_u64());
+    assert_eq!(body["changes"]["new_fills_detected"], true);
+    close(change(&body, TOKEN_YES), (5.0, 2.0));
+    close(change(&body, TOKEN_NO), (5.0, -0.5));
+
+    // A closed position shows up as its shares and P&L going away
+    upstreams
+        .venue
+        .insert_market_positions(WALLET, vec![position(TOKEN_YES, 15.0, 0.5, 0.6)]);
+    let (_, body) = send(state.clone(), poll(true)).await;
+    assert_eq!(body["changes"]["new_fills_detected"], false);
+    close(change(&body, TOKEN_YES), (0.0, -1.5));
+    close(change(&body, TOKEN_NO), (-5.0, 0.5));
+
+    let (_, body) = send(state, poll(false)).await;
+    assert!(body.get("changes").is_none());
+}
+
+#[tokio::test]
+async fn position_tracker_aggregates_several_wallets() {
+    const OTHER_WALLET: &str = "0x00000000000000000000000000000000000000bb";
+    const DOWN_WALLET: &str = "0x00000000000000000000000000000000000000cc";
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_market_positions(WALLET, vec![position(TOKEN_YES, 10.0, 0.4, 0.6)]);
+    upstreams.venue.insert_market_positions(
+        OTHER_WALLET,
+        vec![
+            position(TOKEN_YES, 30.0, 0.6, 0.6),
+            position(TOKEN_NO, 40.0, 0.3, 0.4),
+        ],
+    );
+    upstreams.venue.fail_wallet(DOWN_WALLET);
+
+    let request = post(
+        "/api/position-tracker",
+        json!({
+            "wallet_addresses": [WALLET, OTHER_WALLET, DOWN_WALLET],
+            "market_slug": "will-it-rain",
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // 40 Yes at a blended 0.55 and 40 No at 0.3 lock 40 - 34 = 6
+    let yes = &body["positions"][0];
+    assert_eq!(yes["shares"], 40.0);
+    assert!(
+        (yes["avg_price"].as_f64().unwrap() - 0.55).abs() < 1e-9,
+        "{yes}"
+    );
+    assert_eq!(body["pair_status"], "PROFIT_LOCKED");
+    assert!((body["profit_lock"].as_f64().unwrap() - 6.0).abs() < 1e-9);
+
+    let wallets = body["wallets"].as_array().unwrap();
+    assert_eq!(wallets.len(), 3);
+    assert_eq!(wallets[0]["pair_status"], "AT_RISK");
+    assert!((wallets[0]["pnl"].as_f64().unwrap() - 2.0).abs() < 1e-9);
+    assert!(wallets[2]["pair_status"].is_null());
+    assert!(wallets[2]["error"].as_str().unwrap().contains("503"));
+    assert!(body["metadata"]["degraded_features"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("wallet_positions")));
+
+    // The request fails only when every wallet does
+    let request = post(
+        "/api/position-tracker",
+        json!({ "wallet_addresses": [DOWN_WALLET], "market_slug": "will-it-rain" }),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+
+    for (body, expected) in [
+        (
+            json!({ "wallet_addresses": [WALLET, "0x12"] }),
+            "wallet_addresses[1] is not a valid address: expected 42-character",
+        ),
+        (
+            json!({ "wallet_addresses": [" "] }),
+            "wallet_addresses[0] is not a valid address",
+        ),
+        (json!({ "wallet_addresses": [] }), "must not be empty"),
+        (
+            json!({ "wallet_address": WALLET, "wallet_addresses": [WALLET] }),
+            "not both",
+        ),
+        (
+            json!({ "wallet_addresses": [WALLET], "include_history": true }),
+            "single wallet_address",
+        ),
+    ] {
+        let (status, body) = send(state(&upstreams), post("/api/position-tracker", body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(error_message(&body).contains(expected), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn position_tracker_checks_fields_against_the_platform() {
+    let upstreams = MockUpstreams::default();
+
+    for (body, expected) in [
+        (json!({}), "wallet_address is required"),
+        (json!({ "platform": "kalshi" }), "kalshi_ticker is required"),
+        (
+            json!({ "platform": "kalshi", "kalshi_ticker": "KXRAIN", "wallet_address": WALLET }),
+            "wallet_address only applies",
+        ),
+        (
+            json!({ "wallet_address": WALLET, "portfolio_id": "main" }),
+            "portfolio_id only applies",
+        ),
+        (
+            json!({ "platform": "kalshi", "kalshi_ticker": "KXRAIN", "interval": "1h" }),
+            "interval only applies",
+        ),
+        (
+            json!({ "wallet_address": WALLET, "interval": "4h" }),
+            "interval",
+        ),
+    ] {
+        let (status, response) = send(state(&upstreams), post("/api/position-tracker", body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error_message(&response).contains(expected), "{response}");
+    }
+
+    // Kalshi positions need the direct client
+    let request = post(
+        "/api/position-tracker",
+        json!({ "platform": "kalshi", "kalshi_ticker": "KXRAIN" }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("KALSHI_API_KEY"), "{body}");
+}
+
+#[tokio::test]
+async fn arb_scan_prices_opposite_sides_and_warns_on_misaligned_pairs() {
+    let upstreams = MockUpstreams::all();
+    let dome = upstreams.market_data.clone().unwrap();
+    dome.insert_market(Platform::Polymarket, "will-it-rain", market("will-it-rain"));
+    dome.insert_market(
+        Platform::Kalshi,
+        "KXRAIN-25DEC",
+        mock::binary_market("kxrain", [("Yes", "y", 0.45), ("No", "n", 0.55)]),
+    );
+    dome.insert_market(
+        Platform::Kalshi,
+        "KXRAIN-UPDOWN",
+        mock::binary_market("kxupdown", [("Up", "u", 0.45), ("Down", "d", 0.55)]),
+    );
+
+    let pair = |second: &str| {
+        json!({
+            "first_url": "https://polymarket.com/event/will-it-rain",
+            "second_url": format!("https://kalshi.com/markets/kxrain/{}", second),
+        })
+    };
+    let mut mapped = pair("kxrain-updown");
+    mapped["outcome_map"] = json!({ "Yes": "Up", "No": "Down" });
+    let request = post(
+        "/api/arb-scan",
+        json!({
+            "pairs": [pair("kxrain-25dec"), pair("kxrain-updown"), mapped, pair("kxrain-missing")],
+            "kalshi_fee_bps": 0,
+            "min_edge_bps": 1000,
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // No at 0.40 plus Yes at 0.45 pays $1 for 0.85, on pairs 0 and 2
+    assert_eq!(body["flagged"], 2);
+    let best = &body["opportunities"][0];
+    assert_eq!(best["first"]["outcome"], "No");
+    assert!((best["edge_bps"].as_f64().unwrap() - 1500.0).abs() < 1e-6);
+    assert!((best["max_sets"].as_f64().unwrap() - 500.0 / 0.85).abs() < 1e-6);
+
+    let warnings = body["warnings"].as_array().unwrap();
+    assert_eq!(warnings.len(), 2, "{body}");
+    assert_eq!(warnings[0]["pair_index"], 1);
+    assert!(warnings[0]["message"]
+        .as_str()
+        .unwrap()
+        .contains("outcome_map"));
+    assert_eq!(warnings[1]["pair_index"], 3);
+}
+
+#[tokio::test]
+async fn portfolio_values_a_wallets_positions() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams.venue.insert_wallet_positions(
+        WALLET,
+        vec![WalletPosition {
+            asset: TOKEN_YES.to_string(),
+            slug: "will-it-rain".to_string(),
+            title: "Will it rain?".to_string(),
+            outcome: "Yes".to_string(),
+            size: 10.0,
+            avg_price: 0.5,
+            cur_price: 0.6,
+        }],
+    );
+
+    let request = post("/api/portfolio", json!({ "wallet_address": WALLET }));
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["totals"]["markets"], 1);
+    assert_eq!(body["totals"]["cost_basis"], 5.0);
+}
+
+#[test]
+fn field_selection_prunes_nested_paths_through_arrays() {
+    let response = json!({
+        "market": { "slug": "will-it-rain", "question": "Will it rain?", "outcomes": [
+            { "name": "Yes", "price": 0.6 },
+            { "name": "No", "price": 0.4 },
+        ] },
+        "positions": [
+            { "outcome": "Yes", "shares": 10.0, "avg_price": 0.5 },
+            { "outcome": "No", "shares": 5.0, "avg_price": 0.3 },
+        ],
+        "pair_status": "unpaired",
+        "trades": null,
+        "metadata": { "timestamp": "2026-10-16T00:00:00Z", "retries": 0 },
+    });
+
+    // Nested paths keep their parents; arrays are pruned element-wise, and
+    // metadata always comes along
+    assert_eq!(
+        select_fields::<PositionTrackerResponse>(
+            response.clone(),
+            "market.slug, positions.shares,market.outcomes.name"
+        )
+        .unwrap(),
+        json!({
+            "market": { "slug": "will-it-rain", "outcomes": [{ "name": "Yes" }, { "name": "No" }] },
+            "positions": [{ "shares": 10.0 }, { "shares": 5.0 }],
+            "metadata": { "timestamp": "2026-10-16T00:00:00Z", "retries": 0 },
+        })
+    );
+    // A whole field wins over one of its children, in either order
+    assert_eq!(
+        select_fields::<PositionTrackerResponse>(response.clone(), "positions.shares,positions")
+            .unwrap()["positions"],
+        response["positions"]
+    );
+    // Metadata can itself be narrowed
+    assert_eq!(
+        select_fields::<PositionTrackerResponse>(response.clone(), "pair_status,metadata.retries")
+            .unwrap(),
+        json!({ "pair_status": "unpaired", "metadata": { "retries": 0 } })
+    );
+    // Paths below a null keep the null
+    assert_eq!(
+        select_fields::<PositionTrackerResponse>(response.clone(), "trades.side").unwrap()
+            ["trades"],
+        Value::Null
+    );
+    // Fields are checked against the type, so one this response leaves out
+    // is just missing
+    assert_eq!(
+        select_fields::<PositionTrackerResponse>(
+            response.clone(),
+            "wallets,changes.since,pair_status"
+        )
+        .unwrap(),
+        json!({
+            "pair_status": "unpaired",
+            "metadata": { "timestamp": "2026-10-16T00:00:00Z", "retries": 0 },
+        })
+    );
+    // No metadata to add when the response has none
+    assert_eq!(
+        select_fields::<Paginated<MarketSummary>>(json!({ "items": [], "limit": 2 }), "limit")
+            .unwrap(),
+        json!({ "limit": 2 })
+    );
+
+    // An empty selection returns the response as it was
+    for empty in ["", " ", ",", " , "] {
+        assert_eq!(
+            select_fields::<PositionTrackerResponse>(response.clone(), empty).unwrap(),
+            response
+        );
+    }
+
+    for (fields, expected) in [
+        (
+            "positon",
+            "Unknown field 'positon'; valid fields are: break_even, changes, imbalance, market, \
+             metadata, pair_status, positions, profit_lock, realized_payout, realized_pnl, \
+             total_invested, trades, wallet_address, wallets",
+        ),
+        (
+            "market.slugg",
+            "Unknown field 'market.slugg'; valid fields under 'market' are: closed, condition_id, \
+             end_date, id, liquidity, outcome_ordering, outcomes, platform, question, \
+             resolved_outcome, slug, ticker, volume",
+        ),
+        (
+            "positions.price",
+            "Unknown field 'positions.price'; valid fields under 'positions' are: avg_price, \
+             current_price, outcome, realized_pnl, shares, token_id, unrealized_pnl",
+        ),
+        (
+            "pair_status.level",
+            "Unknown field 'pair_status.level'; 'pair_status' has no fields",
+        ),
+        ("market..slug", "Invalid field path: market..slug"),
+    ] {
+        match select_fields::<PositionTrackerResponse>(response.clone(), fields) {
+            Err(AppError::Validation(message)) => assert_eq!(message, expected, "{fields}"),
+            other => panic!("{fields}: expected a validation error, got {:?}", other),
+        }
+    }
+}
+
+#[tokio::test]
+async fn portfolio_and_market_data_responses_can_be_trimmed_to_fields() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams.venue.insert_wallet_positions(
+        WALLET,
+        vec![WalletPosition {
+            asset: TOKEN_YES.to_string(),
+            slug: "will-it-rain".to_string(),
+            title: "Will it rain?".to_string(),
+            outcome: "Yes".to_string(),
+            size: 10.0,
+            avg_price: 0.5,
+            cur_price: 0.6,
+        }],
+    );
+
+    let request = post(
+        "/api/portfolio",
+        json!({ "wallet_address": WALLET, "fields": "totals.cost_basis,markets.market_slug" }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let mut keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+    keys.sort();
+    assert_eq!(keys, ["markets", "metadata", "totals"]);
+    assert_eq!(body["totals"], json!({ "cost_basis": 5.0 }));
+    assert_eq!(body["markets"], json!([{ "market_slug": "will-it-rain" }]));
+
+    // The query string applies when the body has no selection
+    let request = post(
+        "/api/portfolio?fields=wallet_address",
+        json!({ "wallet_address": WALLET }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body.get("totals").is_none(), "{body}");
+
+    let uri = format!("/api/portfolio?wallet_address={WALLET}&fields=totals.positions");
+    let (status, body) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["totals"], json!({ "positions": 1 }));
+    assert!(body.get("markets").is_none(), "{body}");
+
+    let uri = format!("/api/portfolio?wallet_address={WALLET}&fields=total");
+    let (status, body) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("valid fields are: markets, metadata, totals"),
+        "{body}"
+    );
+
+    let (status, body) = send(
+        state(&upstreams),
+        get("/api/markets?fields=items.slug,items.outcomes.id"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(
+        body,
+        json!({ "items": [{ "slug": "will-it-rain", "outcomes": [{ "id": TOKEN_YES }, { "id": TOKEN_NO }] }] })
+    );
+}
+
+#[tokio::test]
+async fn portfolio_exports_csv_that_round_trips_through_a_parser() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let title = "Will it rain, or \"snow\", tomorrow?";
+    upstreams.venue.insert_wallet_positions(
+        WALLET,
+        vec![WalletPosition {
+            asset: TOKEN_YES.to_string(),
+            slug: "will-it-rain".to_string(),
+            title: title.to_string(),
+            outcome: "Yes".to_string(),
+            size: 10.0,
+            avg_price: 0.5,
+            cur_price: 0.6,
+        }],
+    );
+    let state = state(&upstreams);
+    let get = Request::get(format!(
+        "/api/portfolio?wallet_address={}&format=csv",
+        WALLET
+    ))
+    .body(Body::empty())
+    .unwrap();
+    let mut post = post("/api/portfolio", json!({ "wallet_address": WALLET }));
+    post.headers_mut()
+        .insert(header::ACCEPT, "text/csv".parse().unwrap());
+
+    for request in [get, post] {
+        let response = create_router()
+            .with_state(state.clone())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/csv; charset=utf-8"
+        );
+        let today = chrono::Utc::now().format("%Y-%m-%d");
+        assert_eq!(
+            response.headers()[header::CONTENT_DISPOSITION],
+            format!("attachment; filename=\"portfolio-{}.csv\"", today).as_str()
+        );
+
+        let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let mut reader = csv::Reader::from_reader(bytes.as_ref());
+        let header: Vec<String> = reader.headers().unwrap().iter().map(String::from).collect();
+        assert_eq!(header, LedgerRow::HEADER);
+        let records: Vec<csv::StringRecord> = reader.records().map(Result::unwrap).collect();
+        assert_eq!(records.len(), 1);
+        let record: Vec<&str> = records[0].iter().collect();
+        assert_eq!(
+            &record[..8],
+            [title, "Yes", "", "10.0000", "0.5000", "0.6000", "", "1.00"]
+        );
+        assert!(chrono::DateTime::parse_from_rfc3339(record[8]).is_ok());
+    }
+}
+
+#[tokio::test]
+async fn portfolio_validates_and_maps_upstream_failures() {
+    let upstreams = MockUpstreams::default();
+
+    let request = post("/api/portfolio", json!({ "wallet_address": "" }));
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    upstreams.venue.fail("get_wallet_positions", || {
+        AppError::ExternalApi("Data API error 503".to_string())
+    });
+    let request = post("/api/portfolio", json!({ "wallet_address": WALLET }));
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn read_only_endpoints_take_their_request_as_query_params() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams.venue.insert_wallet_positions(
+        WALLET,
+        vec![WalletPosition {
+            asset: TOKEN_YES.to_string(),
+            slug: "will-it-rain".to_string(),
+            title: "Will it rain?".to_string(),
+            outcome: "Yes".to_string(),
+            size: 10.0,
+            avg_price: 0.5,
+            cur_price: 0.6,
+        }],
+    );
+    upstreams
+        .venue
+        .insert_market_positions(WALLET, vec![position(TOKEN_YES, 10.0, 0.5, 0.6)]);
+
+    let uri = format!("/api/portfolio?wallet_address={WALLET}&fresh=true");
+    let (status, body) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["totals"]["markets"], 1);
+
+    let uri = format!("/api/position-tracker?wallet_address={WALLET}&market_slug=will-it-rain");
+    let (status, body) = send(state(&upstreams), get(&uri)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["market"]["slug"], "will-it-rain");
+
+    // Validated like the JSON body
+    for uri in [
+        "/api/portfolio".to_string(),
+        format!("/api/portfolio?wallet_address={WALLET}&wallet=typo"),
+        "/api/position-tracker?platform=venus".to_string(),
+    ] {
+        let (status, body) = send(state(&upstreams), get(&uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+        assert!(body["error"].is_string(), "{uri}: {body}");
+    }
+}
+
+#[tokio::test]
+async fn markets_can_be_searched_paged_and_cached() {
+    let upstreams = MockUpstreams::default();
+    for (slug, volume, closed) in [
+        ("fed-cuts-in-march", 50_000.0, false),
+        ("fed-cuts-in-june", 20_000.0, false),
+        ("fed-hikes-in-june", 500.0, false),
+        ("fed-cuts-in-january", 90_000.0, true),
+        ("will-it-rain", 80_000.0, false),
+    ] {
+        let mut market = market(slug);
+        market.volume = Some(volume);
+        market.closed = closed;
+        upstreams.venue.insert_market(market);
+    }
+    let state = Arc::new(AppState {
+        market_search_cache: Arc::new(MarketSearchCache::new(std::time::Duration::from_secs(60))),
+        ..(*state(&upstreams)).clone()
+    });
+
+    let uri = "/api/markets?query=fed&min_volume=10000&limit=1";
+    let (status, body) = send(state.clone(), get(uri)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["limit"], 1);
+    let first = &body["items"][0];
+    assert_eq!(first["slug"], "fed-cuts-in-march");
+    assert_eq!(
+        first["url"],
+        "https://polymarket.com/market/fed-cuts-in-march"
+    );
+    assert_eq!(first["outcomes"][0]["id"], TOKEN_YES);
+
+    let cursor = body["next_cursor"].as_str().unwrap();
+    let (status, body) = send(
+        state.clone(),
+        get(&format!(
+            "/api/markets?query=fed&min_volume=10000&limit=1&cursor={cursor}"
+        )),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["items"][0]["slug"], "fed-cuts-in-june");
+    assert!(body["next_cursor"].is_null(), "{body}");
+
+    // Closed markets only with active=false
+    let (_, body) = send(state.clone(), get("/api/markets?query=january")).await;
+    assert_eq!(body["items"].as_array().unwrap().len(), 0, "{body}");
+    let (_, body) = send(
+        state.clone(),
+        get("/api/markets?query=january&active=false"),
+    )
+    .await;
+    assert_eq!(body["items"][0]["closed"], true, "{body}");
+
+    // Repeated pages come from the cache unless fresh
+    let searches = || {
+        upstreams
+            .venue
+            .calls()
+            .into_iter()
+            .filter(|call| *call == "search_markets")
+            .count()
+    };
+    let before = searches();
+    send(state.clone(), get(uri)).await;
+    assert_eq!(searches(), before);
+    send(state.clone(), get(&format!("{uri}&fresh=true"))).await;
+    assert_eq!(searches(), before + 1);
+
+    for uri in [
+        "/api/markets?min_volume=-1",
+        "/api/markets?query=",
+        "/api/markets?limit=0",
+        "/api/markets?serach=fed",
+    ] {
+        let (status, body) = send(state.clone(), get(uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{uri}: {body}");
+    }
+}
+
+#[tokio::test]
+async fn event_mispricing_prices_an_event() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_event(PolymarketEvent {
+        slug: "rain-totals".to_string(),
+        title: "Rain totals".to_string(),
+        neg_risk: Some(true),
+        markets: vec![market("rain-under-1"), market("rain-over-1")],
+    });
+
+    let request = post(
+        "/api/event-mispricing",
+        json!({ "url": "https://polymarket.com/event/rain-totals" }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["event_slug"], "rain-totals");
+    assert_eq!(body["buckets"].as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn event_mispricing_validates_the_request() {
+    let upstreams = MockUpstreams::default();
+
+    let request = post(
+        "/api/event-mispricing",
+        json!({ "url": "https://kalshi.com/event/rain-totals" }),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let request = post(
+        "/api/event-mispricing",
+        json!({ "url": "https://polymarket.com/event/rain-totals", "budget_usd": 0.0 }),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(upstreams.venue.calls().is_empty());
+
+    let request = post(
+        "/api/event-mispricing",
+        json!({ "url": "https://polymarket.com/event/unknown" }),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+fn bucket_market(slug: &str, yes: f64) -> MarketData {
+    mock::binary_market(
+        slug,
+        [
+            ("Yes", &format!("{slug}-yes"), yes),
+            ("No", &format!("{slug}-no"), 1.0 - yes),
+        ],
+    )
+}
+
+fn buckets(yes_prices: &[f64]) -> Vec<Bucket> {
+    let markets: Vec<MarketData> = yes_prices
+        .iter()
+        .enumerate()
+        .map(|(i, yes)| bucket_market(&format!("bucket-{i}"), *yes))
+        .collect();
+    detect_structure(Some(true), &markets).unwrap()
+}
+
+#[test]
+fn event_structure_is_only_trusted_for_plausible_neg_risk_events() {
+    let markets = vec![bucket_market("a", 0.4), bucket_market("b", 0.6)];
+    assert_eq!(detect_structure(Some(true), &markets).unwrap().len(), 2);
+    assert_eq!(
+        detect_structure(Some(false), &markets).unwrap_err().0,
+        EventStructure::Independent
+    );
+    assert_eq!(
+        detect_structure(None, &markets).unwrap_err().0,
+        EventStructure::StructureUnknown
+    );
+
+    // Buckets that resolved NO drop out; any other closed bucket is suspect
+    let mut resolved = bucket_market("c", 0.01);
+    resolved.closed = true;
+    let with_resolved = [markets.clone(), vec![resolved.clone()]].concat();
+    assert_eq!(
+        detect_structure(Some(true), &with_resolved).unwrap().len(),
+        2
+    );
+    let mut pending = bucket_market("d", 0.5);
+    pending.closed = true;
+    let with_pending = [markets.clone(), vec![pending]].concat();
+    assert!(detect_structure(Some(true), &with_pending).is_err());
+
+    // Too few buckets, a non Yes/No market, or a sum far from 1
+    assert!(detect_structure(Some(true), &[bucket_market("a", 0.4), resolved]).is_err());
+    let mut up_down = bucket_market("e", 0.5);
+    up_down.outcome_ordering = "up_down".to_string();
+    assert!(detect_structure(Some(true), &[markets[0].clone(), up_down]).is_err());
+    let overlapping = [bucket_market("a", 0.7), bucket_market("b", 0.7)];
+    assert!(detect_structure(Some(true), &overlapping).is_err());
+}
+
+#[test]
+fn price_sums_pick_the_side_to_buy() {
+    let (sum, contributions) = price_sum(&buckets(&[0.4, 0.35, 0.3]));
+    assert!((sum - 1.05).abs() < 1e-9);
+    let shares: f64 = contributions.iter().map(|c| c.share_of_sum).sum();
+    assert!((shares - 1.0).abs() < 1e-9);
+    assert!((contributions[0].share_of_sum - 0.4 / 1.05).abs() < 1e-9);
+
+    assert_eq!(direction(1.05), Some(MispricingDirection::BuyNo));
+    assert_eq!(direction(0.9), Some(MispricingDirection::BuyYes));
+    assert_eq!(direction(1.0), None);
+}
+
+#[test]
+fn overpriced_events_are_shorted_with_every_no() {
+    // NO costs 0.60 + 0.65 + 0.70 = 1.95 per set and pays 2 over 3 buckets
+    let overpriced = buckets(&[0.4, 0.35, 0.3]);
+    let trade = size_trade(&overpriced, MispricingDirection::BuyNo, &[0, 0, 0], None).unwrap();
+    assert!(trade.legs.iter().all(|leg| leg.outcome == "No"));
+    assert_eq!(trade.shares_per_leg, 5.0);
+    assert_eq!(trade.cost_usd, 9.75);
+    assert_eq!(trade.payout_usd, 10.0);
+    assert_eq!(trade.net_profit_usd, 0.25);
+
+    let trade = size_trade(
+        &overpriced,
+        MispricingDirection::BuyNo,
+        &[0, 0, 0],
+        Some(100.0),
+    )
+    .unwrap();
+    assert_eq!(trade.shares_per_leg, 51.28);
+    assert!(trade.cost_usd + trade.fees_usd <= 100.0);
+    assert_eq!(trade.payout_usd, 102.56);
+
+    // Fees of 0.4 + 0.35 + 0.3 cents per set at 100 bps still leave an edge
+    let trade = size_trade(
+        &overpriced,
+        MispricingDirection::BuyNo,
+        &[100, 100, 100],
+        None,
+    )
+    .unwrap();
+    assert_eq!(trade.fees_usd, 0.05);
+    assert_eq!(trade.net_profit_usd, 0.2);
+    // At 1000 bps they don't
+    let reason = size_trade(
+        &overpriced,
+        MispricingDirection::BuyNo,
+        &[1000, 1000, 1000],
+        None,
+    )
+    .unwrap_err();
+    assert!(reason.contains("does not cover"), "{reason}");
+
+    let reason = size_trade(
+        &overpriced,
+        MispricingDirection::BuyNo,
+        &[0, 0, 0],
+        Some(5.0),
+    )
+    .unwrap_err();
+    assert!(reason.contains("below the minimum"), "{reason}");
+}
+
+#[test]
+fn underpriced_events_are_bought_outright() {
+    // Every YES at 0.30 costs 0.90 per set and pays 1
+    let underpriced = buckets(&[0.3, 0.3, 0.3]);
+    let trade = size_trade(&underpriced, MispricingDirection::BuyYes, &[0, 0, 0], None).unwrap();
+    assert!(trade.legs.iter().all(|leg| leg.outcome == "Yes"));
+    assert_eq!(trade.shares_per_leg, 5.0);
+    assert_eq!(trade.cost_usd, 4.5);
+    assert_eq!(trade.payout_usd, 5.0);
+    assert_eq!(trade.net_profit_usd, 0.5);
+
+    // Cheap legs need more than 5 shares to clear the $1 minimum
+    let cheap = buckets(&[0.1, 0.1, 0.75]);
+    let trade = size_trade(&cheap, MispricingDirection::BuyYes, &[0, 0, 0], None).unwrap();
+    assert_eq!(trade.shares_per_leg, 10.0);
+
+    // Buying the side that's already rich never pays
+    assert!(size_trade(&underpriced, MispricingDirection::BuyNo, &[0, 0, 0], None).is_err());
+}
+
+#[tokio::test]
+async fn orders_require_a_wallet() {
+    let upstreams = MockUpstreams::default();
+    let order_id = format!("0x{:064x}", 1);
+
+    let (status, _) = send(state(&upstreams), get(&format!("/api/orders/{}", order_id))).await;
+    // Without the header the server's own wallet is used, if configured
+    if std::env::var("WALLET_PRIVATE_KEY").is_err() {
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    let request = signed(Method::GET, "/api/orders/not-an-id", None);
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(upstreams.venue.calls().is_empty());
+}
+
+#[tokio::test]
+async fn orders_are_listed_looked_up_and_cancelled() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let order_ids = [format!("0x{:064x}", 1), format!("0x{:064x}", 2)];
+    for (order_id, token_id) in order_ids.iter().zip([TOKEN_YES, TOKEN_NO]) {
+        upstreams.venue.insert_order(live_order(order_id, token_id));
+    }
+
+    let request = signed(Method::GET, "/api/orders?market_slug=will-it-rain", None);
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["orders"].as_array().unwrap().len(), 2);
+    assert_eq!(body["orders"][0]["outcome"], "Yes");
+
+    let request = signed(Method::GET, &format!("/api/orders/{}", order_ids[0]), None);
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["order"]["order_id"], order_ids[0].as_str());
+
+    let request = signed(
+        Method::DELETE,
+        &format!("/api/orders/{}", order_ids[0]),
+        None,
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["cancelled"], 1);
+
+    // Cancelling again finds it already cancelled
+    let request = signed(
+        Method::POST,
+        "/api/orders/cancel-all",
+        Some(json!({ "order_ids": [order_ids[0]] })),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["results"][0]["status"], "already_cancelled");
+
+    let request = signed(
+        Method::POST,
+        "/api/orders/cancel-all",
+        Some(json!({ "market_slug": "will-it-rain" })),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["cancelled"], 1);
+}
+
+#[tokio::test]
+async fn cancels_are_refused_while_trading_is_disabled() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let order_id = format!("0x{:064x}", 1);
+    upstreams
+        .venue
+        .insert_order(live_order(&order_id, TOKEN_YES));
+    let state = state(&upstreams);
+    state.runtime_config.apply(
+        RuntimeSettingsUpdate {
+            incident_message: None,
+            trading_enabled: Some(false),
+        },
+        "on-call",
+    );
+
+    for request in [
+        signed(Method::DELETE, &format!("/api/orders/{}", order_id), None),
+        signed(
+            Method::POST,
+            "/api/orders/cancel-all",
+            Some(json!({ "market_slug": "will-it-rain" })),
+        ),
+    ] {
+        let (status, body) = send(state.clone(), request).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+        assert_eq!(body["code"], "TRADING_DISABLED");
+        assert_eq!(body["disabled_by"], "on-call", "{body}");
+    }
+    assert!(upstreams.venue.calls().is_empty());
+
+    // Reads keep working
+    let request = signed(Method::GET, &format!("/api/orders/{}", order_id), None);
+    let (status, body) = send(state, request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["order"]["status"], "pending");
+}
+
+/// The wallet `signed` requests act for.
+fn signer_wallet() -> String {
+    ClobSigner::from_private_key(WALLET_KEY)
+        .unwrap()
+        .address()
+        .to_checksum(None)
+}
+
+/// A straddle held by the signing wallet: 20 shares a side bought at 0.50,
+/// with Yes since risen to 0.70.
+fn straddle_upstreams() -> MockUpstreams {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.69, 0.71));
+    upstreams.venue.insert_market_positions(
+        &signer_wallet(),
+        vec![
+            position(TOKEN_YES, 20.0, 0.5, 0.7),
+            position(TOKEN_NO, 20.0, 0.5, 0.3),
+        ],
+    );
+    upstreams
+}
+
+#[tokio::test]
+async fn unwind_leg_sells_part_of_the_winning_side_and_projects_the_rest() {
+    let upstreams = straddle_upstreams();
+
+    let request = signed(
+        Method::POST,
+        "/api/unwind-leg",
+        Some(json!({
+            "market_slug": "will-it-rain",
+            "leg": "winning",
+            "fraction": 0.5,
+            "price_offset": 0.01,
+        })),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["order"]["outcome"], "Yes");
+    assert_eq!(body["order"]["side"], "sell");
+    assert_eq!(body["order"]["price"], 0.68);
+    assert_eq!(body["order"]["size"], 10.0);
+    let orders = upstreams.venue.orders();
+    assert_eq!(orders.len(), 1);
+    assert!(orders
+        .values()
+        .all(|o| o.side == "SELL" && o.asset_id == TOKEN_YES));
+
+    let projection = &body["projection"];
+    let shares: Vec<(&str, f64)> = projection["positions"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| {
+            (
+                p["outcome"].as_str().unwrap(),
+                p["shares"].as_f64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(shares, [("Yes", 10.0), ("No", 20.0)]);
+    // 10 Yes shares + $6.80 from the sale against $20 spent
+    assert_eq!(projection["worst_case_outcome"], "Yes");
+    let pnl = projection["worst_case_pnl"].as_f64().unwrap();
+    assert!((pnl + 3.2).abs() < 1e-9, "{projection}");
+}
+
+#[tokio::test]
+async fn unwind_leg_refuses_legs_it_cannot_sell() {
+    let upstreams = straddle_upstreams();
+    let unwind = |leg: &str, fraction: f64| {
+        signed(
+            Method::POST,
+            "/api/unwind-leg",
+            Some(json!({ "market_slug": "will-it-rain", "leg": leg, "fraction": fraction })),
+        )
+    };
+
+    // Under the 5-share minimum
+    let (status, body) = send(state(&upstreams), unwind("yes", 0.2)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("under the market's 5 share minimum"),
+        "{body}"
+    );
+
+    let (status, body) = send(state(&upstreams), unwind("Maybe", 1.0)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(error_message(&body).contains("one of Yes, No"), "{body}");
+    assert!(upstreams.venue.orders().is_empty());
+
+    // A wallet holding only No can't unwind Yes
+    upstreams
+        .venue
+        .insert_market_positions(&signer_wallet(), vec![position(TOKEN_NO, 20.0, 0.5, 0.3)]);
+    let (status, body) = send(state(&upstreams), unwind("Yes", 1.0)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("held: Yes 0.00, No 20.00"),
+        "{body}"
+    );
+    assert!(upstreams.venue.orders().is_empty());
+}
+
+/// A state whose webhooks are signed and may reach the local mock server.
+fn webhook_state(upstreams: &MockUpstreams) -> Arc<AppState> {
+    let mut state = AppState::clone(&state(upstreams));
+    state.webhooks = Arc::new(WebhookSender::new(
+        HttpClientConfig::default(),
+        Some("whsec_test".to_string()),
+        vec!["127.0.0.1".to_string()],
+    ));
+    Arc::new(state)
+}
+
+#[tokio::test]
+async fn position_monitors_fire_once_their_threshold_is_crossed() {
+    let receiver = MockServer::start().await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&receiver)
+        .await;
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    // Up $4 on Yes
+    upstreams
+        .venue
+        .insert_market_positions(&signer_wallet(), vec![position(TOKEN_YES, 20.0, 0.5, 0.7)]);
+    let state = webhook_state(&upstreams);
+
+    let mut ids = Vec::new();
+    for threshold in [json!({ "pnl_above": 3.0 }), json!({ "pnl_below": -2.0 })] {
+        let mut request = json!({
+            "wallet_address": signer_wallet().to_lowercase(),
+            "market_slug": "will-it-rain",
+            "webhook_url": format!("{}/pnl", receiver.uri()),
+        });
+        request
+            .as_object_mut()
+            .unwrap()
+            .extend(threshold.as_object().unwrap().clone());
+        let (status, body) = send(state.clone(), post("/api/position-monitors", request)).await;
+        assert_eq!(status, StatusCode::OK, "{body}");
+        assert_eq!(body["monitor"]["wallet_address"], signer_wallet());
+        ids.push(body["monitor"]["id"].as_str().unwrap().to_string());
+    }
+
+    check_monitors(&state).await;
+    let delivered = receiver.received_requests().await.unwrap();
+    assert_eq!(delivered.len(), 1);
+    let event: Value = delivered[0].body_json().unwrap();
+    assert_eq!(event["event"], "threshold_triggered");
+    assert_eq!(event["monitor_id"], ids[0].as_str());
+    assert_eq!(
+        event["threshold"],
+        json!({ "kind": "pnl_above", "value": 3.0 })
+    );
+    assert_eq!(event["pnl"], 4.0);
+    assert_eq!(event["positions"][0]["outcome"], "Yes");
+    assert!(delivered[0]
+        .headers
+        .contains_key(predict_os_be::clients::webhook::SIGNATURE_HEADER));
+
+    // The fired monitor is gone; the other keeps watching
+    check_monitors(&state).await;
+    assert_eq!(receiver.received_requests().await.unwrap().len(), 1);
+    let delete = |id: &str| {
+        Request::delete(format!("/api/position-monitors/{id}"))
+            .body(Body::empty())
+            .unwrap()
+    };
+    let (status, _) = send(state.clone(), delete(&ids[0])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(state.clone(), delete(&ids[1])).await;
+    assert_eq!(status, StatusCode::NO_CONTENT);
+}
+
+#[tokio::test]
+async fn position_monitors_refuse_private_webhooks_and_unknown_markets() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let monitor = |slug: &str, webhook_url: &str| {
+        post(
+            "/api/position-monitors",
+            json!({
+                "wallet_address": signer_wallet(),
+                "market_slug": slug,
+                "webhook_url": webhook_url,
+                "pnl_above": 5.0,
+            }),
+        )
+    };
+
+    let signing = webhook_state(&upstreams);
+    let (status, body) = send(
+        signing.clone(),
+        monitor("will-it-rain", "http://169.254.169.254/latest"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("non-public address"),
+        "{body}"
+    );
+    let (status, _) = send(
+        signing.clone(),
+        monitor("no-such-market", "http://127.0.0.1/pnl"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // A stop loss above the take profit
+    let request = post(
+        "/api/position-monitors",
+        json!({
+            "wallet_address": signer_wallet(),
+            "market_slug": "will-it-rain",
+            "webhook_url": "http://127.0.0.1/pnl",
+            "pnl_above": -5.0,
+            "pnl_below": 5.0,
+        }),
+    );
+    let (status, _) = send(signing, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Without WEBHOOK_SECRET deliveries can't be signed
+    let (status, body) = send(
+        state(&upstreams),
+        monitor("will-it-rain", "http://127.0.0.1/pnl"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("WEBHOOK_SECRET"), "{body}");
+}
+
+#[tokio::test]
+async fn analysis_subscriptions_refuse_private_and_unsigned_webhooks() {
+    let upstreams = MockUpstreams::all();
+    let subscribe = |webhook_url: &str| {
+        post(
+            "/api/analysis-subscriptions",
+            json!({
+                "market_url": "https://polymarket.com/event/will-it-rain",
+                "cadence": "weekly",
+                "webhook_url": webhook_url,
+            }),
+        )
+    };
+    let grok = || {
+        mock::app_state(
+            &upstreams,
+            Config {
+                grok_api_key: Some("grok-key".to_string()),
+                ..mock::config()
+            },
+        )
+    };
+
+    let mut signing = AppState::clone(&grok());
+    signing.webhooks = webhook_state(&upstreams).webhooks.clone();
+    let signing = Arc::new(signing);
+    let (status, body) = send(signing.clone(), subscribe("http://169.254.169.254/latest")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("non-public address"),
+        "{body}"
+    );
+    let (status, body) = send(signing, subscribe("http://127.0.0.1/drift")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let (status, body) = send(grok(), subscribe("http://127.0.0.1/drift")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("WEBHOOK_SECRET"), "{body}");
+}
+
+#[test]
+fn subscription_changes_need_a_flip_or_a_big_enough_confidence_move() {
+    let previous = SubscriptionRunPoint {
+        analyzed_at: chrono::Utc::now().to_rfc3339(),
+        analysis_id: "an_previous".to_string(),
+        recommendation: Recommendation::BuyYes,
+        confidence: 0.6,
+        change: None,
+    };
+
+    // The first run is only a baseline
+    assert_eq!(detect_change(None, &Recommendation::BuyNo, 0.9, 0.15), None);
+    for (recommendation, confidence, expected) in [
+        (Recommendation::BuyYes, 0.6, None),
+        (Recommendation::BuyYes, 0.7, None),
+        (Recommendation::BuyYes, 0.46, None),
+        // Exactly the threshold counts, in either direction
+        (
+            Recommendation::BuyYes,
+            0.75,
+            Some(AnalysisDrift::ConfidenceShift),
+        ),
+        (
+            Recommendation::BuyYes,
+            0.45,
+            Some(AnalysisDrift::ConfidenceShift),
+        ),
+        // A flip wins even without a confidence move
+        (
+            Recommendation::NoTrade,
+            0.6,
+            Some(AnalysisDrift::RecommendationChanged),
+        ),
+        (
+            Recommendation::BuyNo,
+            0.95,
+            Some(AnalysisDrift::RecommendationChanged),
+        ),
+    ] {
+        assert_eq!(
+            detect_change(Some(&previous), &recommendation, confidence, 0.15),
+            expected,
+            "{recommendation:?} at {confidence}"
+        );
+    }
+    // Thresholds are per subscription
+    assert_eq!(
+        detect_change(Some(&previous), &Recommendation::BuyYes, 0.7, 0.1),
+        Some(AnalysisDrift::ConfidenceShift)
+    );
+}
+
+#[tokio::test]
+async fn subscription_webhooks_fire_only_when_a_run_changes() {
+    let upstreams = MockUpstreams::all();
+    upstreams.market_data.as_ref().unwrap().insert_market(
+        Platform::Polymarket,
+        "will-it-rain",
+        market("will-it-rain"),
+    );
+    let grok = MockServer::start().await;
+    let completion = |recommendation: &str, confidence: f64| {
+        ResponseTemplate::new(200).set_body_json(json!({
+            "model": "grok-beta",
+            "choices": [{"message": {"role": "assistant", "content": json!({
+                "recommendation": recommendation,
+                "confidence": confidence,
+                "reasoning": "Priced in.",
+                "key_factors": ["Momentum"],
+            }).to_string()}}],
+        }))
+    };
+    // Two runs that agree, then one that flips
+    Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(completion("NO_TRADE", 0.55))
+        .up_to_n_times(2)
+        .mount(&grok)
+        .await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(completion("BUY_YES", 0.6))
+        .mount(&grok)
+        .await;
+    let receiver = MockServer::start().await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&receiver)
+        .await;
+
+    let mut state = AppState::clone(&mock::app_state(
+        &upstreams,
+        Config {
+            grok_api_key: Some("grok-key".to_string()),
+            grok_base_url: Some(grok.uri()),
+            ..mock::config()
+        },
+    ));
+    state.webhooks = webhook_state(&upstreams).webhooks.clone();
+    let state = Arc::new(state);
+    let request = post(
+        "/api/analysis-subscriptions",
+        json!({
+            "market_url": "https://polymarket.com/event/will-it-rain",
+            "cadence": "daily",
+            "webhook_url": format!("{}/drift", receiver.uri()),
+        }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let id = body["subscription"]["id"].as_str().unwrap().to_string();
+
+    let mut now = chrono::Utc::now();
+    for _ in 0..2 {
+        run_due(&state, now).await;
+        now += chrono::Duration::days(1);
+    }
+    // Not due again until tomorrow
+    run_due(&state, now - chrono::Duration::hours(1)).await;
+    assert_eq!(grok.received_requests().await.unwrap().len(), 2);
+    assert!(receiver.received_requests().await.unwrap().is_empty());
+
+    run_due(&state, now).await;
+    let delivered = receiver.received_requests().await.unwrap();
+    assert_eq!(delivered.len(), 1);
+    let alert: Value = delivered[0].body_json().unwrap();
+    assert_eq!(alert["event"], "analysis_changed");
+    assert_eq!(alert["subscription_id"], id.as_str());
+    assert_eq!(alert["change"], "recommendation_changed");
+    assert_eq!(alert["previous"]["recommendation"], "NOTRADE");
+    assert_eq!(alert["current"]["recommendation"], "BUYYES");
+
+    let (status, body) = send(
+        state,
+        get(&format!("/api/analysis-subscriptions/{id}/history")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let changes: Vec<&Value> = body["points"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| &p["change"])
+        .collect();
+    assert_eq!(
+        changes,
+        [&Value::Null, &Value::Null, &json!("recommendation_changed")]
+    );
+}
+
+#[tokio::test]
+async fn analysis_subscriptions_and_their_runs_are_reloaded_from_storage() {
+    let path = std::env::temp_dir().join(format!("subscriptions-{}.db", uuid::Uuid::new_v4()));
+    let url = format!("sqlite://{}", path.display());
+    let created_at = chrono::Utc::now() - chrono::Duration::days(1);
+    let subscription = Subscription {
+        id: "sub_reloaded".to_string(),
+        market_url: "https://polymarket.com/event/will-it-rain".to_string(),
+        cadence: SubscriptionCadence::Weekly,
+        model: Some("openai".to_string()),
+        webhook_url: None,
+        min_confidence_change: 0.2,
+        created_at,
+        next_run_at: created_at,
+        history: Vec::new(),
+    };
+    let run = |analysis_id: &str, recommendation: Recommendation, change| SubscriptionRunPoint {
+        analyzed_at: chrono::Utc::now().to_rfc3339(),
+        analysis_id: analysis_id.to_string(),
+        recommendation,
+        confidence: 0.7,
+        change,
+    };
+
+    let storage = Storage::connect(&url).await.unwrap();
+    storage.save_subscription(&subscription).await.unwrap();
+    for point in [
+        run("an_1", Recommendation::NoTrade, None),
+        run("an_2", Recommendation::NoTrade, None),
+        run(
+            "an_3",
+            Recommendation::BuyYes,
+            Some(AnalysisDrift::RecommendationChanged),
+        ),
+    ] {
+        storage
+            .record_subscription_run(&subscription.id, &point, 2)
+            .await
+            .unwrap();
+    }
+    let next_run_at = created_at + chrono::Duration::weeks(1);
+    storage
+        .reschedule_subscription(&subscription.id, next_run_at)
+        .await
+        .unwrap();
+    drop(storage);
+
+    // As after a restart
+    let storage = Storage::connect(&url).await.unwrap();
+    let loaded = storage.load_subscriptions().await.unwrap();
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(loaded.len(), 1);
+    let reloaded = &loaded[0];
+    assert_eq!(reloaded.cadence, SubscriptionCadence::Weekly);
+    assert_eq!(reloaded.model.as_deref(), Some("openai"));
+    assert_eq!(reloaded.min_confidence_change, 0.2);
+    assert_eq!(reloaded.next_run_at.timestamp(), next_run_at.timestamp());
+    // Only the newest runs are kept
+    let ids: Vec<&str> = reloaded
+        .history
+        .iter()
+        .map(|p| p.analysis_id.as_str())
+        .collect();
+    assert_eq!(ids, ["an_2", "an_3"]);
+    assert_eq!(reloaded.history[1].recommendation, Recommendation::BuyYes);
+    assert_eq!(
+        reloaded.history[1].change,
+        Some(AnalysisDrift::RecommendationChanged)
+    );
+
+    let store = SubscriptionStore::with_subscriptions(loaded);
+    let (view, points) = store.history("sub_reloaded").unwrap();
+    assert_eq!(view.market_url, subscription.market_url);
+    assert_eq!(points.len(), 2);
+    assert!(store.claim_due(chrono::Utc::now()).is_empty());
+}
+
+#[tokio::test]
+async fn limit_order_bot_orders_expire_when_asked() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    for (token_id, price) in [(TOKEN_YES, 0.6), (TOKEN_NO, 0.4)] {
+        let level = |price: f64| BookLevel { price, size: 100.0 };
+        upstreams.venue.insert_order_book(OrderBook::from_levels(
+            token_id,
+            vec![level(price - 0.02)],
+            vec![level(price + 0.02)],
+        ));
+    }
+    let body = |overrides: Value| {
+        let mut body = json!({
+            "market_slug": "will-it-rain",
+            "mode": "simple",
+            "bankroll_usd": 10.0,
+            "wallet_private_key": WALLET_KEY,
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        body
+    };
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(1);
+    let request = post(
+        "/api/limit-order-bot",
+        body(json!({ "expiration": "gtd", "expires_at": expires_at.to_rfc3339() })),
+    );
+    let (status, body_out) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body_out}");
+    let orders = upstreams.venue.orders();
+    assert!(!orders.is_empty());
+    for order in orders.values() {
+        assert_eq!(order.expiration, expires_at.timestamp().to_string());
+    }
+
+    let past = chrono::Utc::now() - chrono::Duration::minutes(1);
+    for (overrides, expected) in [
+        (json!({ "expiration": "gtd" }), "expires_at is required"),
+        (
+            json!({ "expiration": "gtd", "expires_at": past.to_rfc3339() }),
+            "must be in the future",
+        ),
+        (
+            json!({ "expires_at": expires_at.to_rfc3339() }),
+            "only used with gtd",
+        ),
+    ] {
+        let request = post("/api/limit-order-bot", body(overrides));
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(error_message(&body).contains(expected), "{body}");
+    }
+
+    // A margin of a whole window leaves no time before the boundary
+    let mut config = mock::config();
+    config.order_expiry_margin = std::time::Duration::from_secs(15 * 60);
+    let request = post(
+        "/api/limit-order-bot",
+        body(json!({ "expiration": "market_close" })),
+    );
+    let (status, body) = send(mock::app_state(&upstreams, config), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("Too late in the cycle"),
+        "{body}"
+    );
+}
+
+fn planned(token_id: &str, price: f64, size: f64) -> PlannedOrder {
+    PlannedOrder {
+        token_id: token_id.to_string(),
+        outcome: String::new(),
+        side: "buy",
+        price: Price::from_decimal(price).unwrap(),
+        size,
+    }
+}
+
+fn resting(id: &str, token_id: &str, price: f64, size: f64) -> LiveOrder {
+    LiveOrder {
+        id: id.to_string(),
+        token_id: token_id.to_string(),
+        price,
+        size,
+    }
+}
+
+fn ids(orders: &[LiveOrder]) -> Vec<&str> {
+    orders.iter().map(|o| o.id.as_str()).collect()
+}
+
+#[test]
+fn diffs_keep_matching_orders_and_replace_the_rest() {
+    let diff = reconcile(
+        vec![
+            planned(TOKEN_YES, 0.40, 10.0),
+            planned(TOKEN_YES, 0.38, 10.0),
+            planned(TOKEN_NO, 0.50, 10.0),
+        ],
+        vec![
+            // Within half a tick and 5% of the size
+            resting("near", TOKEN_YES, 0.404, 10.3),
+            // Two ticks off
+            resting("stale", TOKEN_YES, 0.42, 10.0),
+            // Right price, wrong token
+            resting("other-token", TOKEN_NO, 0.38, 10.0),
+            // Right price, half the size
+            resting("partial", TOKEN_NO, 0.50, 5.0),
+        ],
+        0.005,
+        0.05,
+    );
+
+    let kept: Vec<&str> = diff.keep.iter().map(|(live, _)| live.id.as_str()).collect();
+    assert_eq!(kept, ["near"]);
+    assert_eq!(diff.keep[0].1, planned(TOKEN_YES, 0.40, 10.0));
+    assert_eq!(ids(&diff.cancel), ["stale", "other-token", "partial"]);
+    assert_eq!(
+        diff.add,
+        vec![
+            planned(TOKEN_YES, 0.38, 10.0),
+            planned(TOKEN_NO, 0.50, 10.0)
+        ]
+    );
+    // $8.80 added against $10.50 cancelled
+    assert_eq!(diff.net_notional_change(), -1.7);
+
+    // Wider tolerances keep what the defaults replace
+    let diff = reconcile(
+        vec![planned(TOKEN_YES, 0.40, 10.0)],
+        vec![resting("stale", TOKEN_YES, 0.42, 10.0)],
+        0.02,
+        0.05,
+    );
+    assert_eq!(diff.keep.len(), 1);
+    assert!(diff.cancel.is_empty() && diff.add.is_empty());
+    assert_eq!(diff.net_notional_change(), 0.0);
+}
+
+#[test]
+fn diffs_cancel_duplicate_resting_orders() {
+    // The closest size wins; each resting order satisfies one planned order
+    let diff = reconcile(
+        vec![planned(TOKEN_YES, 0.40, 10.0)],
+        vec![
+            resting("dup-1", TOKEN_YES, 0.40, 9.8),
+            resting("dup-2", TOKEN_YES, 0.40, 10.0),
+            resting("dup-3", TOKEN_YES, 0.40, 10.0),
+        ],
+        0.005,
+        0.05,
+    );
+    assert_eq!(diff.keep[0].0.id, "dup-2");
+    assert_eq!(ids(&diff.cancel), ["dup-1", "dup-3"]);
+    assert!(diff.add.is_empty());
+
+    let diff = reconcile(
+        vec![
+            planned(TOKEN_YES, 0.40, 10.0),
+            planned(TOKEN_YES, 0.40, 10.0),
+        ],
+        vec![resting("only", TOKEN_YES, 0.40, 10.0)],
+        0.005,
+        0.05,
+    );
+    assert_eq!(diff.keep.len(), 1);
+    assert_eq!(diff.add.len(), 1);
+    assert!(diff.cancel.is_empty());
+
+    // Nothing planned cancels everything resting
+    let diff = reconcile(
+        Vec::new(),
+        vec![resting("only", TOKEN_YES, 0.40, 10.0)],
+        0.005,
+        0.05,
+    );
+    assert_eq!(ids(&diff.cancel), ["only"]);
+    assert_eq!(diff.net_notional_change(), -4.0);
+}
+
+fn targets(requested: &[(&str, Option<f64>)]) -> Vec<OutcomeTarget> {
+    requested
+        .iter()
+        .map(|(outcome, weight)| OutcomeTarget {
+            outcome: outcome.to_string(),
+            weight: *weight,
+        })
+        .collect()
+}
+
+#[test]
+fn default_targets_split_the_bankroll_between_up_and_down() {
+    // Listed alphabetically; Up still comes first
+    let market = mock::binary_market("btc-updown", [("Down", "d", 0.55), ("Up", "u", 0.45)]);
+    let resolved = resolve_targets(&market, None).unwrap();
+    let picked: Vec<(&str, f64)> = resolved
+        .iter()
+        .map(|t| (t.outcome.name.as_str(), t.weight))
+        .collect();
+    assert_eq!(picked, vec![("Up", 0.5), ("Down", 0.5)]);
+
+    // Unrecognised names fall back to the market's order
+    let market = mock::binary_market("colors", [("Red", "r", 0.5), ("Blue", "b", 0.5)]);
+    let resolved = resolve_targets(&market, None).unwrap();
+    assert_eq!(resolved[0].outcome.name, "Red");
+    assert_eq!(resolved[1].outcome.name, "Blue");
+}
+
+#[test]
+fn requested_targets_match_by_name_or_token_and_normalize_weights() {
+    let mut market = mock::binary_market("election", [("Alice", "a", 0.5), ("Bob", "b", 0.3)]);
+    market.outcomes.push(Outcome {
+        id: "c".to_string(),
+        name: "Carol".to_string(),
+        price: Price::from_decimal(0.2).unwrap(),
+        volume: None,
+    });
+
+    let requested = targets(&[(" alice ", Some(3.0)), ("b", None), ("CAROL", Some(1.0))]);
+    let resolved = resolve_targets(&market, Some(&requested)).unwrap();
+    let picked: Vec<(&str, f64)> = resolved
+        .iter()
+        .map(|t| (t.outcome.id.as_str(), t.weight))
+        .collect();
+    assert_eq!(picked, vec![("a", 0.6), ("b", 0.2), ("c", 0.2)]);
+
+    // With no outcomes requested, a three-way market isn't split between
+    // its first two
+    let unchosen = resolve_targets(&market, None).unwrap_err();
+    assert!(matches!(&unchosen, AppError::Validation(message)
+        if message.contains("3 outcomes") && message.contains("Alice, Bob, Carol")));
+
+    let unknown = resolve_targets(&market, Some(&targets(&[("Dave", None)]))).unwrap_err();
+    assert!(matches!(&unknown, AppError::Validation(message)
+        if message.contains("'Dave'") && message.contains("Alice, Bob, Carol")));
+
+    for requested in [
+        targets(&[]),
+        targets(&[("Alice", Some(0.0))]),
+        targets(&[("Alice", Some(f64::NAN))]),
+        targets(&[("Alice", None), ("a", None)]),
+    ] {
+        assert!(matches!(
+            resolve_targets(&market, Some(&requested)),
+            Err(AppError::Validation(_))
+        ));
+    }
+}
+
+fn book(token_id: &str, bid: f64, ask: f64) -> OrderBook {
+    let level = |price: f64| BookLevel { price, size: 100.0 };
+    OrderBook::from_levels(token_id, vec![level(bid)], vec![level(ask)])
+}
+
+#[test]
+fn simple_pricing_refuses_or_falls_back_on_an_empty_or_one_sided_book() {
+    let price = |value: f64| Price::from_decimal(value).unwrap();
+    let reference = price(0.6);
+    let level = |price: f64| BookLevel { price, size: 100.0 };
+    let empty = OrderBook::from_levels(TOKEN_YES, vec![], vec![]);
+    let bids_only = OrderBook::from_levels(TOKEN_YES, vec![level(0.55)], vec![]);
+    let asks_only = OrderBook::from_levels(TOKEN_YES, vec![], vec![level(0.62)]);
+    let decide = |book: &OrderBook, pricing: SimplePricing, strict: bool| {
+        decide_simple_price(book, reference, pricing, 1, 10, strict)
+    };
+
+    for (book, reason) in [
+        (&empty, "order book is empty"),
+        (&bids_only, "order book has no asks"),
+        (&asks_only, "order book has no bids"),
+    ] {
+        assert_eq!(
+            decide(book, SimplePricing::JoinBid, true),
+            Err(reason.to_string())
+        );
+
+        let decision = decide(book, SimplePricing::Last, false).unwrap();
+        assert_eq!(decision.price, reference);
+        assert_eq!(
+            decision.warning.as_deref(),
+            Some(format!("{}; pricing anyway (strict_spread=false)", reason).as_str())
+        );
+    }
+
+    // Each side is used when present, the reference price when it isn't
+    let priced = |book: &OrderBook, pricing: SimplePricing| {
+        decide(book, pricing, false).unwrap().price.value()
+    };
+    assert_eq!(priced(&empty, SimplePricing::JoinBid), 0.6);
+    assert_eq!(priced(&empty, SimplePricing::CrossSpread), 0.6);
+    assert_eq!(priced(&bids_only, SimplePricing::JoinBid), 0.56);
+    assert_eq!(priced(&bids_only, SimplePricing::CrossSpread), 0.6);
+    assert_eq!(priced(&asks_only, SimplePricing::JoinBid), 0.6);
+    assert_eq!(priced(&asks_only, SimplePricing::CrossSpread), 0.62);
+}
+
+#[test]
+fn straddle_cost_is_checked_at_the_limit() {
+    let price = |value: f64| Price::from_decimal(value).unwrap();
+    let up = book(TOKEN_YES, 0.47, 0.50);
+    let down = book(TOKEN_NO, 0.46, 0.49);
+    let sides = [
+        StraddleSide {
+            outcome: "Up",
+            price: price(0.48),
+            book: Some(&up),
+        },
+        StraddleSide {
+            outcome: "Down",
+            price: price(0.51),
+            book: Some(&down),
+        },
+    ];
+
+    // Up fills at its 0.48 limit, Down at the 0.49 ask below its limit
+    let cost = check_straddle(&sides, 0.97, Some(0.03)).unwrap();
+    assert_eq!(cost.combined_cost, 0.97);
+    assert_eq!(cost.legs[0].fill_price, 0.48);
+    assert_eq!(cost.legs[1].fill_price, 0.49);
+    assert_eq!(cost.legs[0].spread, Some(0.03));
+
+    let error = check_straddle(&sides, 0.969, None).unwrap_err();
+    assert!(error.contains("combined cost $0.9700"), "{error}");
+    let error = check_straddle(&sides, 0.99, Some(0.029)).unwrap_err();
+    assert!(error.contains("Up spread of 0.030"), "{error}");
+}
+
+#[test]
+fn straddle_sides_without_a_book_fall_back_to_their_limit_price() {
+    let price = |value: f64| Price::from_decimal(value).unwrap();
+    let up = book(TOKEN_YES, 0.40, 0.45);
+    let sides = [
+        StraddleSide {
+            outcome: "Up",
+            price: price(0.44),
+            book: Some(&up),
+        },
+        StraddleSide {
+            outcome: "Down",
+            price: price(0.55),
+            book: None,
+        },
+    ];
+
+    let cost = check_straddle(&sides, 0.99, Some(0.05)).unwrap();
+    assert_eq!(cost.combined_cost, 0.99);
+    assert_eq!(cost.legs[1].fill_price, 0.55);
+    assert_eq!(cost.legs[1].spread, None);
+}
+
+#[test]
+fn straddles_on_a_crossed_book_are_refused() {
+    let price = |value: f64| Price::from_decimal(value).unwrap();
+    let up = book(TOKEN_YES, 0.52, 0.48);
+    let down = book(TOKEN_NO, 0.40, 0.42);
+    let sides = [
+        StraddleSide {
+            outcome: "Up",
+            price: price(0.45),
+            book: Some(&up),
+        },
+        StraddleSide {
+            outcome: "Down",
+            price: price(0.41),
+            book: Some(&down),
+        },
+    ];
+
+    let error = check_straddle(&sides, 0.99, None).unwrap_err();
+    assert!(error.contains("Up order book is crossed"), "{error}");
+}
+
+#[tokio::test]
+async fn limit_order_bot_refuses_straddles_that_cost_a_dollar() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.45, 0.70));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_NO, 0.30, 0.55));
+    let body = |overrides: Value| {
+        let mut body = json!({
+            "market_slug": "will-it-rain",
+            "mode": "simple",
+            "pricing": "cross_spread",
+            "strict_spread": false,
+            "bankroll_usd": 10.0,
+            "dry_run": true,
+            "wallet_private_key": WALLET_KEY,
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        body
+    };
+
+    let request = post("/api/limit-order-bot", body(json!({})));
+    let (status, body_out) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body_out}");
+    assert!(
+        error_message(&body_out).contains("combined cost $1.2500"),
+        "{body_out}"
+    );
+    assert!(upstreams.venue.orders().is_empty());
+
+    let request = post(
+        "/api/limit-order-bot",
+        body(json!({ "max_combined_price": 1.3 })),
+    );
+    let (status, body_out) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body_out}");
+    assert_eq!(body_out["straddle"]["combined_cost"], 1.25);
+    assert_eq!(body_out["straddle"]["legs"][0]["spread"], 0.25);
+    assert!(body_out["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|line| line.as_str().unwrap().starts_with("Straddle cost: $1.2500")));
+
+    let request = post(
+        "/api/limit-order-bot",
+        body(json!({ "max_combined_price": 1.3, "max_spread": 0.2 })),
+    );
+    let (status, body_out) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body_out}");
+    assert!(
+        error_message(&body_out).contains("Yes spread of 0.250"),
+        "{body_out}"
+    );
+}
+
+#[tokio::test]
+async fn simple_straddles_never_spend_more_than_the_bankroll() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.59, 0.61));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_NO, 0.37, 0.39));
+
+    for bankroll in [2.0, 5.0, 5.9, 6.0, 10.0, 37.5, 250.0] {
+        let request = post(
+            "/api/limit-order-bot",
+            json!({
+                "market_slug": "will-it-rain",
+                "mode": "simple",
+                "bankroll_usd": bankroll,
+                "dry_run": true,
+                "wallet_private_key": WALLET_KEY,
+            }),
+        );
+        let (status, body) = send(state(&upstreams), request).await;
+        if status == StatusCode::BAD_REQUEST {
+            // Half of $5.90 buys under 5 Yes shares at $0.59
+            assert!(bankroll < 5.9 + 1e-9, "{bankroll}: {body}");
+            assert!(
+                error_message(&body).contains("under the 5-share minimum"),
+                "{body}"
+            );
+            continue;
+        }
+        assert_eq!(status, StatusCode::OK, "{bankroll}: {body}");
+        let orders = body["orders"].as_array().unwrap();
+        assert_eq!(orders.len(), 2);
+        let spent: f64 = orders
+            .iter()
+            .map(|o| o["price"].as_f64().unwrap() * o["size"].as_f64().unwrap())
+            .sum();
+        assert!(spent <= bankroll + 1e-9, "{bankroll}: spent {spent}");
+    }
+    assert!(upstreams.venue.orders().is_empty());
+}
+
+#[tokio::test]
+async fn exposure_caps_count_resting_buy_orders() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.59, 0.61));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_NO, 0.37, 0.39));
+    let capped = |market: Option<f64>, total: Option<f64>| {
+        mock::app_state(
+            &upstreams,
+            Config {
+                exposure_caps: ExposureCaps {
+                    max_market_exposure: market,
+                    max_total_exposure: total,
+                    ..ExposureCaps::default()
+                },
+                ..mock::config()
+            },
+        )
+    };
+    let run = |dry_run: bool| {
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "simple",
+            "bankroll_usd": 10.0,
+            "dry_run": dry_run,
+            "wallet_private_key": WALLET_KEY,
+        })
+    };
+    let cancel = |order_id: &str, token_id: &str| {
+        let mut order = live_order(order_id, token_id);
+        order.status = "CANCELED".to_string();
+        upstreams.venue.insert_order(order);
+    };
+    let refusal = |body: &Value, cap: &str| {
+        assert!(
+            error_message(body).starts_with(&format!("Exposure cap exceeded: {cap}")),
+            "{body}"
+        );
+    };
+
+    // Nearly $10 of buys fits under $12 on its own
+    let request = post("/api/limit-order-bot", run(true));
+    let (status, body) = send(capped(Some(12.0), Some(12.0)), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Resting sells don't add exposure
+    let mut sell = live_order("sell-1", TOKEN_YES);
+    sell.side = "SELL".to_string();
+    upstreams.venue.insert_order(sell);
+    let request = post("/api/limit-order-bot", run(true));
+    let (status, body) = send(capped(Some(12.0), Some(12.0)), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // A resting $4 buy in this market counts against both caps
+    upstreams.venue.insert_order(live_order("buy-1", TOKEN_YES));
+    let request = post("/api/limit-order-bot", run(true));
+    let (status, body) = send(capped(Some(12.0), None), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    refusal(&body, "MAX_MARKET_EXPOSURE_USD");
+
+    // One in another market only against the total
+    cancel("buy-1", TOKEN_YES);
+    upstreams.venue.insert_order(live_order("buy-2", "9999"));
+    let request = post("/api/limit-order-bot", run(true));
+    let (status, body) = send(capped(Some(12.0), None), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let request = post("/api/limit-order-bot", run(true));
+    let (status, body) = send(capped(None, Some(12.0)), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    refusal(&body, "MAX_TOTAL_EXPOSURE_USD");
+
+    // Orders a diff cancels before adding its own don't count
+    cancel("buy-2", "9999");
+    upstreams.venue.insert_order(live_order("buy-3", TOKEN_YES));
+    let mut diff = run(false);
+    diff["apply"] = json!(true);
+    let request = post("/api/limit-order-bot/diff", diff);
+    let (status, body) = send(capped(Some(12.0), None), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(upstreams.venue.orders()["buy-3"].status, "CANCELED");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn exposure_caps_count_runs_still_placing() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.59, 0.61));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_NO, 0.37, 0.39));
+    let state = mock::app_state(
+        &upstreams,
+        Config {
+            exposure_caps: ExposureCaps {
+                max_total_exposure: Some(22.0),
+                ..ExposureCaps::default()
+            },
+            ..mock::config()
+        },
+    );
+    let run = || {
+        post(
+            "/api/limit-order-bot",
+            json!({
+                "market_slug": "will-it-rain",
+                "mode": "simple",
+                "bankroll_usd": 10.0,
+                "wallet_private_key": WALLET_KEY,
+            }),
+        )
+    };
+
+    // The first run stalls once an order lands, for long enough that a
+    // second run placing too would fail the test rather than hang it
+    let (reached_tx, reached) = std::sync::mpsc::channel();
+    let (release, release_rx) = std::sync::mpsc::channel::<()>();
+    let stall = std::sync::Mutex::new(Some((reached_tx, release_rx)));
+    upstreams.venue.on_place(move |_| {
+        if let Some((reached, release)) = stall.lock().unwrap().take() {
+            reached.send(()).unwrap();
+            let _ = release.recv_timeout(std::time::Duration::from_secs(5));
+        }
+    });
+    let first = tokio::spawn(send(state.clone(), run()));
+    reached.recv().unwrap();
+
+    // Another $10 fits beside the first run's resting orders, but not once
+    // the notional it reserved counts too
+    let (status, body) = send(state.clone(), run()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).starts_with("Exposure cap exceeded: MAX_TOTAL_EXPOSURE_USD"),
+        "{body}"
+    );
+
+    release.send(()).unwrap();
+    let (status, body) = first.await.unwrap();
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(upstreams.venue.orders().len(), 2);
+
+    // Finished runs release their reservation; only resting orders count
+    for mut order in upstreams.venue.orders().into_values() {
+        order.status = "CANCELED".to_string();
+        upstreams.venue.insert_order(order);
+    }
+    let (status, body) = send(state, run()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+}
+
+#[tokio::test]
+async fn retried_runs_replay_the_first_response_without_placing_again() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.59, 0.61));
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_NO, 0.37, 0.39));
+    let state = state(&upstreams);
+    let run = |key: &str, dry_run: bool| {
+        Request::post("/api/limit-order-bot")
+            .header(header::CONTENT_TYPE, "application/json")
+            .header("idempotency-key", key)
+            .body(Body::from(
+                json!({
+                    "market_slug": "will-it-rain",
+                    "mode": "simple",
+                    "bankroll_usd": 10.0,
+                    "dry_run": dry_run,
+                    "wallet_private_key": WALLET_KEY,
+                })
+                .to_string(),
+            ))
+            .unwrap()
+    };
+
+    let (status, first) = send(state.clone(), run("run-1", false)).await;
+    assert_eq!(status, StatusCode::OK, "{first}");
+    assert_eq!(upstreams.venue.orders().len(), 2);
+
+    let (status, replayed) = send(state.clone(), run("run-1", false)).await;
+    assert_eq!(status, StatusCode::OK, "{replayed}");
+    assert_eq!(replayed, first);
+    assert_eq!(upstreams.venue.orders().len(), 2);
+
+    // Dry runs are keyed apart, so they neither replay nor place
+    let (status, dry) = send(state.clone(), run("run-1", true)).await;
+    assert_eq!(status, StatusCode::OK, "{dry}");
+    assert_ne!(dry["order_ids"], first["order_ids"]);
+    assert_eq!(upstreams.venue.orders().len(), 2);
+
+    // A new key runs again
+    let (status, second) = send(state, run("run-2", false)).await;
+    assert_eq!(status, StatusCode::OK, "{second}");
+    assert_eq!(upstreams.venue.orders().len(), 4);
+}
+
+#[test]
+fn idempotency_keys_conflict_while_in_flight_or_interrupted_until_they_expire() {
+    let ttl = std::time::Duration::from_secs(60);
+    let store = IdempotencyStore::new(ttl);
+    let now = Instant::now();
+    let conflict = |claim: predict_os_be::Result<Claim<'_>>| match claim {
+        Err(AppError::Conflict(message)) => message,
+        other => panic!("expected a conflict, got {:?}", other),
+    };
+
+    // In flight: a second claim conflicts until the run ends
+    let guard = store.claim("a".to_string(), now).unwrap();
+    assert!(conflict(store.claim("a".to_string(), now)).contains("still in progress"));
+    // Another key is unaffected
+    assert!(matches!(
+        store.claim("b".to_string(), now).unwrap(),
+        Claim::New(_)
+    ));
+    // A run that fails before sending anything releases the key
+    drop(guard);
+    let mut guard = match store.claim("a".to_string(), now).unwrap() {
+        Claim::New(guard) => guard,
+        Claim::Replay(_) => panic!("nothing completed under this key"),
+    };
+
+    // Interrupted: orders went out, so the key stays refused
+    guard.mark_orders_sent();
+    drop(guard);
+    let later = now + std::time::Duration::from_secs(1);
+    assert!(conflict(store.claim("a".to_string(), later)).contains("GET /api/orders"));
+
+    // Entries are forgotten once the TTL passes; the interrupted entry dates
+    // from when its guard dropped
+    let expired = Instant::now() + ttl;
+    assert!(matches!(
+        store.claim("a".to_string(), expired).unwrap(),
+        Claim::New(_)
+    ));
+    let _stuck = store.claim("c".to_string(), now).unwrap();
+    assert!(conflict(store.claim("c".to_string(), now + ttl / 2)).contains("still in progress"));
+    assert!(matches!(
+        store.claim("c".to_string(), now + ttl),
+        Ok(Claim::New(_))
+    ));
+}
+
+#[test]
+fn ladder_profiles_shape_the_allocation_and_conserve_the_bankroll() {
+    let notional = |ladder: &[(f64, f64)]| ladder.iter().map(|(p, s)| p * s).collect::<Vec<_>>();
+    let ladder = |profile: LadderProfile, custom: Option<&[f64]>| {
+        let weights = profile.weights(4, custom);
+        PolymarketClient::calculate_ladder_orders(
+            100.0,
+            0.2,
+            0.5,
+            LadderSpacing::Linear,
+            &weights,
+            0.01,
+        )
+    };
+    // Shares are floored to 0.01, so each level may fall short by under a cent
+    // of shares at its price
+    let close = |n: f64, expected: f64| n <= expected + 1e-9 && expected - n < 0.01;
+
+    let flat = notional(&ladder(LadderProfile::Flat, None));
+    assert!(flat.iter().all(|&n| close(n, 25.0)), "{flat:?}");
+
+    let linear = notional(&ladder(LadderProfile::Linear, None));
+    for (&n, expected) in linear.iter().zip([40.0, 30.0, 20.0, 10.0]) {
+        assert!(close(n, expected), "{linear:?}");
+    }
+
+    let exponential = notional(&ladder(LadderProfile::Exponential, None));
+    assert!((exponential[0] / exponential[1] - 2.0).abs() < 1e-3);
+
+    // Too small for 5 shares, the lightest level goes first wherever it is
+    let custom = ladder(LadderProfile::Custom, Some(&[1.0, 0.01, 2.0, 1.0]));
+    assert_eq!(custom.len(), 3);
+    assert!(custom.iter().all(|(price, _)| (price - 0.3).abs() > 1e-9));
+    let total: f64 = notional(&custom).iter().sum();
+    assert!(close(total, 100.0) || (total <= 100.0 && 100.0 - total < 0.03));
+}
+
+fn ladder(
+    bankroll: f64,
+    low: f64,
+    high: f64,
+    spacing: LadderSpacing,
+    weights: &[f64],
+) -> Vec<(f64, f64)> {
+    PolymarketClient::calculate_ladder_orders(bankroll, low, high, spacing, weights, 0.01)
+}
+
+#[test]
+fn ladder_rungs_are_spaced_across_the_band() {
+    let prices = |ladder: Vec<(f64, f64)>| ladder.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+
+    let linear = ladder(100.0, 0.2, 0.5, LadderSpacing::Linear, &[1.0; 4]);
+    assert_eq!(prices(linear), [0.2, 0.3, 0.4, 0.5]);
+
+    // Each rung a constant ratio above the last
+    let geometric = ladder(100.0, 0.1, 0.4, LadderSpacing::Geometric, &[1.0; 3]);
+    assert_eq!(prices(geometric), [0.1, 0.2, 0.4]);
+
+    // Off-tick rungs are snapped to it
+    let snapped = ladder(100.0, 0.301, 0.333, LadderSpacing::Linear, &[1.0; 3]);
+    assert_eq!(prices(snapped), [0.3, 0.32, 0.33]);
+}
+
+#[test]
+fn ladder_allocations_add_up_to_the_bankroll() {
+    // Large enough that even the top exponential rung clears 5 shares
+    for levels in 1..=8 {
+        for profile in [
+            LadderProfile::Flat,
+            LadderProfile::Linear,
+            LadderProfile::Exponential,
+        ] {
+            let weights = profile.weights(levels, None);
+            let orders = ladder(1_000.0, 0.2, 0.6, LadderSpacing::Linear, &weights);
+            assert_eq!(orders.len(), levels, "{profile:?} x {levels}");
+            let total: f64 = orders.iter().map(|(price, shares)| price * shares).sum();
+            assert!(
+                total <= 1_000.0 + 1e-9 && 1_000.0 - total < 0.05,
+                "{profile:?} x {levels}: {total}"
+            );
+        }
+    }
+}
+
+#[test]
+fn ladders_at_the_edges_of_the_price_range_stay_placeable() {
+    for (low, high) in [(0.0, 0.05), (0.005, 0.02), (0.95, 0.999), (0.99, 1.0)] {
+        for spacing in [LadderSpacing::Linear, LadderSpacing::Geometric] {
+            let orders = ladder(50.0, low, high, spacing, &[1.0; 3]);
+            assert_eq!(orders.len(), 3, "{low}-{high}");
+            for (price, shares) in &orders {
+                assert!((0.01..=0.99).contains(price), "{low}-{high}: {price}");
+                assert!(*shares >= 5.0, "{low}-{high}: {shares}");
+            }
+            let total: f64 = orders.iter().map(|(price, shares)| price * shares).sum();
+            assert!(total <= 50.0 + 1e-9, "{low}-{high}: {total}");
+        }
+    }
+}
+
+#[test]
+fn single_and_empty_ladders() {
+    // One rung sits at the bottom of the band and takes the whole bankroll
+    let single = ladder(10.0, 0.25, 0.75, LadderSpacing::Linear, &[1.0]);
+    assert_eq!(single, [(0.25, 40.0)]);
+    let single = ladder(10.0, 0.25, 0.75, LadderSpacing::Geometric, &[3.0]);
+    assert_eq!(single, [(0.25, 40.0)]);
+
+    assert!(ladder(10.0, 0.25, 0.75, LadderSpacing::Linear, &[]).is_empty());
+    // Not even one rung can reach 5 shares
+    assert!(ladder(1.0, 0.25, 0.75, LadderSpacing::Linear, &[1.0; 3]).is_empty());
+    // All-zero weights allocate nothing
+    assert!(ladder(10.0, 0.25, 0.75, LadderSpacing::Linear, &[0.0, 0.0]).is_empty());
+}
+
+#[tokio::test]
+async fn disabling_trading_mid_run_stops_the_remaining_orders() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let state = state(&upstreams);
+    // An operator flips safe mode as soon as the first order lands
+    let weak = Arc::downgrade(&state);
+    upstreams.venue.on_place(move |_| {
+        if let Some(state) = weak.upgrade() {
+            state.runtime_config.apply(
+                RuntimeSettingsUpdate {
+                    incident_message: None,
+                    trading_enabled: Some(false),
+                },
+                "on-call",
+            );
+        }
+    });
+
+    let request = post(
+        "/api/limit-order-bot",
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "bankroll_usd": 20.0,
+            "price_levels": 3,
+            "ladder_min_price": 0.30,
+            "ladder_max_price": 0.34,
+            "ladder_profile": "flat",
+            "wallet_private_key": WALLET_KEY,
+        }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(upstreams.venue.orders().len(), 1);
+    assert_eq!(body["orders_placed"], 1);
+    assert_eq!(body["orders_failed"], 5);
+    for order in body["orders"].as_array().unwrap() {
+        if order["status"] == "failed" {
+            assert_eq!(order["error"], "Trading is disabled", "{order}");
+        }
+    }
+
+    // Later runs are refused outright, naming who disabled trading
+    let request = post(
+        "/api/limit-order-bot",
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "simple",
+            "bankroll_usd": 10.0,
+            "wallet_private_key": WALLET_KEY,
+        }),
+    );
+    let (status, body) = send(state, request).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE, "{body}");
+    assert_eq!(body["code"], "TRADING_DISABLED");
+    assert_eq!(body["disabled_by"], "on-call", "{body}");
+    assert_eq!(upstreams.venue.orders().len(), 1);
+}
+
+#[tokio::test]
+async fn a_failed_rung_is_reported_while_the_rest_of_the_ladder_is_placed() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams.venue.fail_call("place_order", 3, || {
+        AppError::UpstreamRejected("not enough balance".to_string())
+    });
+    let run = || {
+        post(
+            "/api/limit-order-bot",
+            json!({
+                "market_slug": "will-it-rain",
+                "mode": "ladder",
+                "bankroll_usd": 20.0,
+                "price_levels": 3,
+                "ladder_min_price": 0.30,
+                "ladder_max_price": 0.34,
+                "ladder_profile": "flat",
+                "wallet_private_key": WALLET_KEY,
+            }),
+        )
+    };
+
+    let (status, body) = send(state(&upstreams), run()).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["orders_placed"], 5);
+    assert_eq!(body["orders_failed"], 1);
+    let orders = body["orders"].as_array().unwrap();
+    assert_eq!(orders.len(), 6);
+    for (i, order) in orders.iter().enumerate() {
+        if i == 2 {
+            assert_eq!(order["status"], "failed", "{order}");
+            assert!(order["order_id"].is_null(), "{order}");
+            assert!(
+                order["error"]
+                    .as_str()
+                    .unwrap()
+                    .contains("not enough balance"),
+                "{order}"
+            );
+        } else {
+            assert_eq!(order["status"], "pending", "{order}");
+            assert!(order.get("error").is_none(), "{order}");
+        }
+    }
+    // The ids are exactly the orders now resting on the book
+    let mut order_ids: Vec<String> = serde_json::from_value(body["order_ids"].clone()).unwrap();
+    order_ids.sort();
+    let mut resting: Vec<String> = upstreams.venue.orders().into_keys().collect();
+    resting.sort();
+    assert_eq!(order_ids, resting);
+    assert!(body["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|line| line == "1 of 6 orders failed"));
+
+    // Only a run where every order fails is an error
+    upstreams.venue.fail("place_order", || {
+        AppError::UpstreamRejected("not enough balance".to_string())
+    });
+    let (status, body) = send(state(&upstreams), run()).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+    assert_eq!(upstreams.venue.orders().len(), 5);
+}
+
+#[tokio::test]
+async fn verification_flags_an_order_the_exchange_dropped() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    // The second of three orders is accepted but never booked, and the third
+    // fills the moment it lands
+    upstreams.venue.drop_placement(2);
+    let venue = Arc::downgrade(&upstreams.venue);
+    upstreams.venue.on_place(move |order| {
+        if order.id == format!("0x{:064x}", 3) {
+            if let Some(venue) = venue.upgrade() {
+                let mut filled = order.clone();
+                filled.status = "MATCHED".to_string();
+                filled.size_matched = filled.original_size.clone();
+                venue.insert_order(filled);
+            }
+        }
+    });
+
+    let request = post(
+        "/api/limit-order-bot",
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "outcomes": [{ "outcome": "Yes" }],
+            "bankroll_usd": 20.0,
+            "price_levels": 3,
+            "ladder_min_price": 0.30,
+            "ladder_max_price": 0.34,
+            "ladder_profile": "flat",
+            "verify_placement": true,
+            "verify_delay_ms": 0,
+            "wallet_private_key": WALLET_KEY,
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    let orders = body["orders"].as_array().unwrap();
+    assert_eq!(orders.len(), 3);
+    let dropped = orders[1]["order_id"].as_str().unwrap();
+    assert_eq!(orders[1]["status"], "unconfirmed", "{body}");
+    assert_eq!(orders[0]["status"], "pending", "{body}");
+    assert_ne!(orders[2]["status"], "unconfirmed", "{body}");
+    assert_eq!(
+        body["verification"],
+        json!({
+            "checked": 3,
+            "confirmed_open": 1,
+            "filled": 1,
+            "unconfirmed": [dropped],
+            "skipped": 0,
+        })
+    );
+    assert!(!upstreams.venue.orders().contains_key(dropped));
+}
+
+#[tokio::test]
+async fn verification_failures_leave_placed_orders_unconfirmed() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    upstreams.venue.drop_placement(2);
+    upstreams.venue.fail("get_order", || {
+        AppError::ExternalApi("CLOB error 502".to_string())
+    });
+
+    let request = post(
+        "/api/limit-order-bot",
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "outcomes": [{ "outcome": "Yes" }],
+            "bankroll_usd": 20.0,
+            "price_levels": 3,
+            "ladder_min_price": 0.30,
+            "ladder_max_price": 0.34,
+            "ladder_profile": "flat",
+            "verify_placement": true,
+            "verify_delay_ms": 0,
+            "wallet_private_key": WALLET_KEY,
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    assert_eq!(body["orders_placed"], 3, "{body}");
+    assert_eq!(body["order_ids"].as_array().unwrap().len(), 3);
+    let orders = body["orders"].as_array().unwrap();
+    assert_eq!(orders[1]["status"], "unconfirmed", "{body}");
+    assert_eq!(orders[0]["status"], "pending", "{body}");
+    assert_eq!(
+        body["verification"]["unconfirmed"],
+        json!([orders[1]["order_id"]])
+    );
+    assert_eq!(
+        body["metadata"]["degraded_features"],
+        json!(["placement_verification"])
+    );
+}
+
+#[tokio::test]
+async fn ladders_refuse_out_of_range_levels_and_an_empty_plan() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let ladder = |bankroll_usd: f64, price_levels: usize| {
+        let mut request = post(
+            "/api/limit-order-bot",
+            json!({
+                "market_slug": "will-it-rain",
+                "mode": "ladder",
+                "outcomes": [{ "outcome": "Yes" }],
+                "bankroll_usd": bankroll_usd,
+                "price_levels": price_levels,
+                "ladder_min_price": 0.30,
+                "ladder_max_price": 0.34,
+                "wallet_private_key": WALLET_KEY,
+            }),
+        );
+        request
+            .headers_mut()
+            .insert("idempotency-key", "ladder-run-1".parse().unwrap());
+        request
+    };
+
+    for price_levels in [0, 21, 1_000_000] {
+        let (status, body) = send(state(&upstreams), ladder(20.0, price_levels)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{price_levels}: {body}");
+        assert!(
+            error_message(&body).contains("price_levels must be between 1 and 20"),
+            "{body}"
+        );
+    }
+
+    // $1 doesn't buy one 5-share rung at $0.30
+    let state = state(&upstreams);
+    let (status, body) = send(state.clone(), ladder(1.0, 3)).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+    assert!(
+        error_message(&body).contains("No orders to place"),
+        "{body}"
+    );
+    assert!(upstreams.venue.orders().is_empty());
+
+    // The refused run left the idempotency key free for a real one
+    let (status, body) = send(state, ladder(20.0, 3)).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["orders_placed"], 3, "{body}");
+}
+
+#[tokio::test]
+async fn ladder_orders_are_snapped_to_the_tick_within_the_bankroll() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let request = post(
+        "/api/limit-order-bot",
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "bankroll_usd": 20.0,
+            "price_levels": 3,
+            "ladder_min_price": 0.301,
+            "ladder_max_price": 0.333,
+            "ladder_profile": "flat",
+            "dry_run": true,
+            "wallet_private_key": WALLET_KEY,
+        }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+
+    // Rungs are snapped to the tick as the ladder is built, so none needs
+    // adjusting afterwards and the bankroll still covers them all
+    let orders = body["orders"].as_array().unwrap();
+    let mut spent = 0.0;
+    for order in orders {
+        let cents = order["price"].as_f64().unwrap() * 100.0;
+        assert!((cents - cents.round()).abs() < 1e-9, "{order}");
+        spent += order["price"].as_f64().unwrap() * order["size"].as_f64().unwrap();
+    }
+    assert_eq!(orders.len(), 6);
+    assert!(spent <= 20.0 + 1e-9, "{spent}");
+    assert!(body.get("adjustments").is_none(), "{body}");
+}
+
+fn order_result(outcome: &str, price: f64, size: f64, status: OrderStatus) -> OrderResult {
+    OrderResult {
+        token_id: TOKEN_YES.to_string(),
+        outcome: outcome.to_string(),
+        side: "buy".to_string(),
+        price: Price::from_decimal(price).unwrap(),
+        size,
+        order_id: None,
+        status,
+        filled_size: None,
+        error: None,
+    }
+}
+
+#[test]
+fn run_summaries_read_the_same_for_the_same_run() {
+    let slug = "btc-updown-15m-1700000000";
+    let close = chrono::DateTime::parse_from_rfc3339("2023-11-14T22:30:00Z")
+        .unwrap()
+        .to_utc();
+
+    let placed = [
+        order_result("Up", 0.48, 10.0, OrderStatus::Pending),
+        order_result("Down", 0.5, 10.0, OrderStatus::Pending),
+    ];
+    assert_eq!(
+        summarize_run(&OrderMode::Simple, slug, &placed, Some(close)),
+        "Placed 2/2 straddle orders on btc-updown-15m-1700000000 totaling $9.80; window closes 22:30 UTC."
+    );
+
+    let failed = |error: &str| OrderResult {
+        error: Some(error.to_string()),
+        ..order_result("Up", 0.4, 25.0, OrderStatus::Failed)
+    };
+    let partial = [
+        order_result("Up", 0.45, 20.0, OrderStatus::Pending),
+        failed("insufficient balance"),
+        order_result("Up", 0.42, 20.0, OrderStatus::Unconfirmed),
+        failed("insufficient balance"),
+        failed("Trading is disabled"),
+    ];
+    assert_eq!(
+        summarize_run(&OrderMode::Ladder, slug, &partial, Some(close)),
+        "Placed 1/5 ladder orders on btc-updown-15m-1700000000 totaling $9.00 \
+         (3 failed: insufficient balance; Trading is disabled, 1 unconfirmed); \
+         window closes 22:30 UTC."
+    );
+
+    let simulated = [
+        order_result("Up", 0.45, 20.0, OrderStatus::Simulated),
+        order_result("Up", 0.4, 20.0, OrderStatus::Simulated),
+    ];
+    assert_eq!(
+        summarize_run(&OrderMode::Ladder, slug, &simulated, None),
+        "Dry run: would place 2/2 ladder orders on btc-updown-15m-1700000000 totaling $17.00."
+    );
+    assert_eq!(
+        summarize_run(&OrderMode::Exit, slug, &[], None),
+        "Placed 0/0 exit orders on btc-updown-15m-1700000000 totaling $0.00."
+    );
+}
+
+#[tokio::test]
+async fn structured_logs_report_the_run_as_typed_events() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market("will-it-rain"));
+    let body = |structured_logs: bool| {
+        json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "bankroll_usd": 20.0,
+            "price_levels": 2,
+            "ladder_min_price": 0.30,
+            "ladder_max_price": 0.40,
+            "ladder_profile": "flat",
+            "dry_run": true,
+            "wallet_private_key": WALLET_KEY,
+            "structured_logs": structured_logs,
+        })
+    };
+
+    let request = post("/api/limit-order-bot", body(false));
+    let (status, plain) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{plain}");
+    assert!(plain.get("events").is_none(), "{plain}");
+
+    let request = post("/api/limit-order-bot", body(true));
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    // The free-text log is unchanged by the flag
+    assert_eq!(
+        body["logs"].as_array().unwrap().len(),
+        plain["logs"].as_array().unwrap().len()
+    );
+    let events = body["events"].as_array().unwrap();
+    for event in events {
+        let timestamp = event["timestamp"].as_str().unwrap();
+        assert!(
+            chrono::DateTime::parse_from_rfc3339(timestamp).is_ok(),
+            "{event}"
+        );
+    }
+    let kinds: Vec<&str> = events
+        .iter()
+        .map(|e| e["event"].as_str().unwrap())
+        .collect();
+    assert_eq!(kinds[0], "market_resolved");
+    assert_eq!(events[0]["slug"], "will-it-rain");
+
+    let planned: Vec<&Value> = events
+        .iter()
+        .filter(|e| e["event"] == "order_planned")
+        .collect();
+    assert_eq!(planned.len(), 4, "{body}");
+    assert_eq!(planned[0]["level"], 1);
+    assert_eq!(planned[1]["level"], 2);
+    assert_eq!(planned[0]["side"], "buy");
+    assert!(planned[0]["shares"].as_f64().unwrap() > 0.0);
+
+    let placed: Vec<&Value> = events
+        .iter()
+        .filter(|e| e["event"] == "order_placed")
+        .collect();
+    assert_eq!(placed.len(), body["orders"].as_array().unwrap().len());
+    assert!(placed.iter().all(|e| e["status"] == "simulated"));
+}
+
+#[test]
+fn bot_log_events_serialize_as_a_stable_tagged_layout() {
+    let timestamp = chrono::DateTime::parse_from_rfc3339("2026-01-02T03:04:05Z")
+        .unwrap()
+        .with_timezone(&chrono::Utc);
+    let event = |kind| serde_json::to_value(BotLogEvent { timestamp, kind }).unwrap();
+
+    assert_eq!(
+        event(BotLogEventKind::MarketResolved {
+            slug: "will-it-rain".to_string(),
+            question: "Will it rain?".to_string(),
+        }),
+        json!({
+            "timestamp": "2026-01-02T03:04:05Z",
+            "event": "market_resolved",
+            "slug": "will-it-rain",
+            "question": "Will it rain?",
+        })
+    );
+    assert_eq!(
+        event(BotLogEventKind::OrderPlanned {
+            outcome: "Up".to_string(),
+            side: "buy".to_string(),
+            price: 0.37,
+            shares: 12.5,
+            level: Some(2),
+        }),
+        json!({
+            "timestamp": "2026-01-02T03:04:05Z",
+            "event": "order_planned",
+            "outcome": "Up",
+            "side": "buy",
+            "price": 0.37,
+            "shares": 12.5,
+            "level": 2,
+        })
+    );
+    assert_eq!(
+        event(BotLogEventKind::OrderPlaced {
+            outcome: "Up".to_string(),
+            order_id: Some("0xabc".to_string()),
+            status: OrderStatus::Pending,
+        }),
+        json!({
+            "timestamp": "2026-01-02T03:04:05Z",
+            "event": "order_placed",
+            "outcome": "Up",
+            "order_id": "0xabc",
+            "status": "pending",
+        })
+    );
+    assert_eq!(
+        event(BotLogEventKind::OrderFailed {
+            outcome: "Down".to_string(),
+            reason: "insufficient balance".to_string(),
+        }),
+        json!({
+            "timestamp": "2026-01-02T03:04:05Z",
+            "event": "order_failed",
+            "outcome": "Down",
+            "reason": "insufficient balance",
+        })
+    );
+    assert_eq!(
+        event(BotLogEventKind::CapTriggered {
+            cap: "MAX_TOTAL_EXPOSURE_USD".to_string(),
+            limit: 100.0,
+            attempted: 120.5,
+        }),
+        json!({
+            "timestamp": "2026-01-02T03:04:05Z",
+            "event": "cap_triggered",
+            "cap": "MAX_TOTAL_EXPOSURE_USD",
+            "limit": 100.0,
+            "attempted": 120.5,
+        })
+    );
+}
+
+#[tokio::test]
+async fn ladder_weights_must_match_the_profile_and_levels() {
+    let upstreams = MockUpstreams::default();
+    let body = |overrides: Value| {
+        let mut body = json!({
+            "market_slug": "will-it-rain",
+            "mode": "ladder",
+            "bankroll_usd": 10.0,
+            "dry_run": true,
+            "wallet_private_key": WALLET_KEY,
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        body
+    };
+
+    for (overrides, expected) in [
+        (json!({ "ladder_profile": "custom" }), "weights is required"),
+        (
+            json!({ "ladder_profile": "custom", "weights": [1, 2, 3, 4], "price_levels": 6 }),
+            "weights has 4 entries but price_levels is 6",
+        ),
+        (
+            json!({ "ladder_profile": "custom", "weights": [1, -1, 1, 1, 1] }),
+            "must not be negative",
+        ),
+        (
+            json!({ "ladder_profile": "flat", "weights": [1, 1, 1, 1, 1] }),
+            "only used with the custom ladder_profile",
+        ),
+    ] {
+        let request = post("/api/limit-order-bot", body(overrides));
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(error_message(&body).contains(expected), "{body}");
+    }
+    assert!(upstreams.venue.calls().is_empty());
+}
+
+#[tokio::test]
+async fn cancel_all_validates_targets_and_maps_upstream_failures() {
+    let upstreams = MockUpstreams::default();
+
+    let request = signed(
+        Method::POST,
+        "/api/orders/cancel-all",
+        Some(json!({ "market_slug": "will-it-rain", "order_ids": ["0x01"] })),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    upstreams.venue.fail("cancel_orders", || {
+        AppError::UpstreamRejected("CLOB API rejected the request".to_string())
+    });
+    let request = signed(
+        Method::POST,
+        "/api/orders/cancel-all",
+        Some(json!({ "order_ids": ["0x01"] })),
+    );
+    let (status, _) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn analyze_event_markets_needs_an_ai_provider() {
+    let upstreams = MockUpstreams::all();
+    upstreams.market_data.as_ref().unwrap().insert_market(
+        Platform::Polymarket,
+        "will-it-rain",
+        market("will-it-rain"),
+    );
+
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": "https://polymarket.com/event/will-it-rain" }),
+    );
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("not configured"));
+    assert!(upstreams.market_data.unwrap().calls().is_empty());
+}
+
+#[tokio::test]
+async fn analyze_event_markets_fetches_kalshi_markets_directly_when_configured() {
+    let upstreams = MockUpstreams {
+        kalshi: Some(Arc::default()),
+        ..MockUpstreams::default()
+    };
+    let config = Config {
+        grok_api_key: Some("grok-key".to_string()),
+        ..mock::config()
+    };
+    let state = mock::app_state(&upstreams, config);
+
+    // Without Dome, a Kalshi link still reaches the market lookup
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": "https://kalshi.com/markets/kxfed/kxfed-25dec" }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+    assert!(error_message(&body).contains("KXFED-25DEC"));
+    assert_eq!(upstreams.kalshi.unwrap().calls(), ["get_market"]);
+
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": "https://polymarket.com/event/will-it-rain" }),
+    );
+    let (status, body) = send(state, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("DOME_API_KEY"));
+}
+
+#[tokio::test]
+async fn refresh_refetches_kalshi_analyses_from_kalshi() {
+    let grok = MockServer::start().await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "grok-beta",
+            "choices": [{"message": {"role": "assistant", "content": json!({
+                "recommendation": "NO_TRADE",
+                "confidence": 0.5,
+                "reasoning": "Fairly priced.",
+                "key_factors": ["Priced in"],
+            }).to_string()}}],
+        })))
+        .mount(&grok)
+        .await;
+    // Kalshi only, without Dome
+    let upstreams = MockUpstreams {
+        kalshi: Some(Arc::default()),
+        ..MockUpstreams::default()
+    };
+    let kalshi = upstreams.kalshi.clone().unwrap();
+    kalshi.insert_market(kalshi_market("KXRAIN-25DEC"));
+    let state = mock::app_state(
+        &upstreams,
+        Config {
+            grok_api_key: Some("grok-key".to_string()),
+            grok_base_url: Some(grok.uri()),
+            ..mock::config()
+        },
+    );
+
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": "https://kalshi.com/markets/kxrain/kxrain-25dec", "model": "grok" }),
+    );
+    let (status, body) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let analysis_id = body["analysis_id"].as_str().unwrap().to_string();
+
+    // Yes moves from 0.6 to 0.5, matched by its `TICKER:yes` id
+    let yes = "KXRAIN-25DEC:yes";
+    let no = "KXRAIN-25DEC:no";
+    kalshi.insert_market(MarketData {
+        ticker: Some("KXRAIN-25DEC".to_string()),
+        slug: None,
+        condition_id: None,
+        platform: Platform::Kalshi,
+        ..mock::binary_market("KXRAIN-25DEC", [("Yes", yes, 0.5), ("No", no, 0.5)])
+    });
+    let request = post(
+        "/api/analyze-event-markets/refresh",
+        json!({ "analysis_id": analysis_id, "price_threshold": 0.2 }),
+    );
+    let (status, body) = send(state, request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["stale"], false);
+    let delta = body["movement"]["max_price_delta"].as_f64().unwrap();
+    assert!((delta - 0.1).abs() < 1e-9, "{body}");
+    assert_eq!(kalshi.calls(), ["get_market", "get_market"]);
+}
+
+#[tokio::test]
+async fn streamed_batch_results_arrive_in_completion_order() {
+    let upstreams = MockUpstreams::all();
+    let grok = MockServer::start().await;
+    let completion = |delay_ms: u64| {
+        ResponseTemplate::new(200)
+            .set_body_json(json!({
+                "model": "grok-beta",
+                "choices": [{"message": {"role": "assistant", "content": json!({
+                    "recommendation": "NO_TRADE",
+                    "confidence": 0.5,
+                    "reasoning": "Fairly priced.",
+                    "key_factors": ["Priced in"],
+                }).to_string()}}],
+            }))
+            .set_delay(std::time::Duration::from_millis(delay_ms))
+    };
+    // Latencies staggered against the order the markets are requested in.
+    // Grok's shared limiter starts one call a second, so each gap outlasts
+    // the two seconds the calls may start apart in whatever order.
+    for (slug, delay_ms) in [("slow", 5000), ("medium", 2500), ("fast", 0)] {
+        let market_data = upstreams.market_data.as_ref().unwrap();
+        market_data.insert_market(Platform::Polymarket, slug, market(slug));
+        Mock::given(wiremock::matchers::method("POST"))
+            .and(wiremock::matchers::path("/chat/completions"))
+            .and(wiremock::matchers::body_string_contains(format!(
+                "Mock market {}\\n",
+                slug
+            )))
+            .respond_with(completion(delay_ms))
+            .expect(1)
+            .mount(&grok)
+            .await;
+    }
+    let state = mock::app_state(
+        &upstreams,
+        Config {
+            grok_api_key: Some("grok-key".to_string()),
+            grok_base_url: Some(grok.uri()),
+            ..mock::config()
+        },
+    );
+
+    let url = |slug: &str| format!("https://polymarket.com/event/{}", slug);
+    let request = post(
+        "/api/analyze-event-markets/batch?stream=true",
+        json!({ "urls": [url("slow"), url("medium"), url("fast"), url("missing")] }),
+    );
+    let response = create_router()
+        .with_state(state)
+        .oneshot(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[header::CONTENT_TYPE],
+        "application/x-ndjson"
+    );
+
+    // Read line by line as the body arrives
+    let mut body = response.into_body().into_data_stream();
+    let mut buffered = Vec::new();
+    let mut lines = Vec::new();
+    while let Some(chunk) = body.next().await {
+        buffered.extend_from_slice(&chunk.unwrap());
+        while let Some(end) = buffered.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = buffered.drain(..=end).collect();
+            lines.push(serde_json::from_slice::<Value>(&line).unwrap());
+        }
+    }
+    assert!(buffered.is_empty());
+
+    let (summary, results) = lines.split_last().unwrap();
+    let arrived: Vec<(&str, u64)> = results
+        .iter()
+        .map(|line| {
+            assert_eq!(line["type"], "result");
+            (
+                line["url"].as_str().unwrap(),
+                line["index"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    // The lookup failure first, then each analysis as it finished
+    assert_eq!(
+        arrived,
+        [
+            (url("missing").as_str(), 3),
+            (url("fast").as_str(), 2),
+            (url("medium").as_str(), 1),
+            (url("slow").as_str(), 0),
+        ]
+    );
+    assert!(results[0]["error"].is_string());
+    assert_eq!(results[1]["analysis"]["recommendation"], "NOTRADE");
+    assert_eq!(summary["type"], "summary");
+    assert_eq!(summary["total"], 4);
+    assert_eq!(summary["succeeded"], 3);
+    assert_eq!(summary["failed"], 1);
+}
+
+#[test]
+fn lttb_keeps_the_endpoints_within_the_threshold() {
+    // A flat series with one spike in the middle
+    let points: Vec<(i64, f64)> = (0..1000)
+        .map(|t| (t, if t == 500 { 0.9 } else { 0.5 }))
+        .collect();
+    let sampled = downsample_lttb(&points, 100);
+    assert_eq!(sampled.len(), 100);
+    assert_eq!(sampled.first(), Some(&(0, 0.5)));
+    assert_eq!(sampled.last(), Some(&(999, 0.5)));
+    assert!(sampled.contains(&(500, 0.9)));
+    assert!(sampled.windows(2).all(|pair| pair[0].0 < pair[1].0));
+
+    let short = &points[..40];
+    assert_eq!(downsample_lttb(short, 100), short);
+    assert_eq!(downsample_lttb(&points[..100], 100), &points[..100]);
+    assert!(downsample_lttb(&[], 100).is_empty());
+    assert_eq!(downsample_lttb(&points, 2), [(0, 0.5), (999, 0.5)]);
+}
+
+#[tokio::test]
+async fn analyze_event_markets_leaves_out_a_chart_it_cannot_fetch() {
+    let grok = MockServer::start().await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "grok-beta",
+            "choices": [{"message": {"role": "assistant", "content": json!({
+                "recommendation": "NO_TRADE",
+                "confidence": 0.5,
+                "reasoning": "Fairly priced.",
+                "key_factors": ["Priced in"],
+            }).to_string()}}],
+        })))
+        .mount(&grok)
+        .await;
+    let analyze = |upstreams: &MockUpstreams| {
+        let state = mock::app_state(
+            upstreams,
+            Config {
+                grok_api_key: Some("grok-key".to_string()),
+                grok_base_url: Some(grok.uri()),
+                ..mock::config()
+            },
+        );
+        let request = post(
+            "/api/analyze-event-markets",
+            json!({
+                "url": "https://polymarket.com/event/will-it-rain",
+                "model": "grok",
+                "include_chart": true,
+            }),
+        );
+        send(state, request)
+    };
+    let upstreams = || {
+        let upstreams = MockUpstreams::all();
+        upstreams.market_data.as_ref().unwrap().insert_market(
+            Platform::Polymarket,
+            "will-it-rain",
+            market("will-it-rain"),
+        );
+        upstreams
+    };
+
+    let charted = upstreams();
+    let history: Vec<(i64, f64)> = (0..500).map(|t| (t * 60, 0.5)).collect();
+    charted.venue.insert_price_history(TOKEN_YES, history);
+    let (status, body) = analyze(&charted).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let chart = body["chart"].as_array().unwrap();
+    assert_eq!(chart.len(), 100);
+    assert_eq!(chart[0], json!([0, 0.5]));
+    assert_eq!(chart[99], json!([499 * 60, 0.5]));
+
+    let failing = upstreams();
+    failing.venue.fail("get_price_history", || {
+        AppError::ExternalApi("CLOB API error 500".to_string())
+    });
+    let (status, body) = analyze(&failing).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body.get("chart").is_none(), "{body}");
+    assert!(body["metadata"]["degraded_features"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("chart")));
+}
+
+#[tokio::test]
+async fn analyze_event_markets_rejects_prompt_context_with_a_custom_prompt() {
+    let upstreams = MockUpstreams::all();
+
+    for flag in ["include_history", "include_orderbook"] {
+        let mut body = json!({
+            "url": "https://polymarket.com/event/will-it-rain",
+            "custom_prompt": "Is this market mispriced?",
+        });
+        body[flag] = json!(true);
+        let request = post("/api/analyze-event-markets", body);
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error_message(&body).contains(flag), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn analyze_event_markets_caps_the_requested_timeout() {
+    let upstreams = MockUpstreams::all();
+
+    for timeout_secs in [0, 601] {
+        let body = json!({
+            "url": "https://polymarket.com/event/will-it-rain",
+            "timeout_secs": timeout_secs,
+        });
+        let request = post("/api/analyze-event-markets", body);
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(
+            error_message(&body).contains("timeout_secs must be between 1 and 600"),
+            "{body}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn analyze_all_markets_needs_an_event_url_and_no_per_market_extras() {
+    let upstreams = MockUpstreams::all();
+
+    let body = json!({
+        "market": { "platform": "polymarket", "identifier": "will-it-rain" },
+        "analyze_all_markets": true,
+    });
+    let request = post("/api/analyze-event-markets", body);
+    let (status, body) = send(state(&upstreams), request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error_message(&body).contains("event url"), "{body}");
+
+    for flag in ["compare", "include_chart", "include_orderbook"] {
+        let mut body = json!({
+            "url": "https://polymarket.com/event/rain-this-week",
+            "analyze_all_markets": true,
+        });
+        body[flag] = json!(true);
+        let request = post("/api/analyze-event-markets", body);
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error_message(&body).contains(flag), "{body}");
+    }
+}
+
+#[test]
+fn market_movement_is_the_largest_price_move_and_the_volume_growth() {
+    let mut before = market("fed-cut");
+    before.volume = Some(1000.0);
+    let snapshot = MarketSnapshot::capture(&before);
+    let priced = |yes: f64, no: f64, volume: Option<f64>| {
+        let mut market =
+            mock::binary_market("fed-cut", [("Yes", TOKEN_YES, yes), ("No", TOKEN_NO, no)]);
+        market.volume = volume;
+        market
+    };
+
+    // Unchanged: no movement either way
+    let movement = compute_movement(&snapshot, &before, 0.05, 0.5);
+    assert_eq!(movement.max_price_delta, 0.0);
+    assert_eq!(movement.volume_growth, Some(0.0));
+    assert!(!exceeds_threshold(&movement));
+
+    // The larger of the two outcome moves counts, in either direction
+    let movement = compute_movement(&snapshot, &priced(0.57, 0.47, Some(1200.0)), 0.05, 0.5);
+    assert!(
+        (movement.max_price_delta - 0.07).abs() < 1e-9,
+        "{movement:?}"
+    );
+    assert!(
+        (movement.volume_growth.unwrap() - 0.2).abs() < 1e-9,
+        "{movement:?}"
+    );
+    assert!(exceeds_threshold(&movement));
+    // The same move under a per-request threshold above it
+    assert!(!exceeds_threshold(&compute_movement(
+        &snapshot,
+        &priced(0.57, 0.47, Some(1200.0)),
+        0.1,
+        0.5
+    )));
+
+    // Volume alone can trip it, at the threshold inclusive
+    let movement = compute_movement(&snapshot, &priced(0.6, 0.4, Some(1500.0)), 0.05, 0.5);
+    assert!(exceeds_threshold(&movement));
+
+    // Outcomes missing from the snapshot and volume without a baseline are ignored
+    let mut relisted = priced(0.9, 0.1, Some(5000.0));
+    relisted.outcomes[0].id = "3333".to_string();
+    let unpriced = MarketSnapshot {
+        volume: Some(0.0),
+        ..snapshot.clone()
+    };
+    let movement = compute_movement(&unpriced, &relisted, 0.05, 0.5);
+    assert!(
+        (movement.max_price_delta - 0.3).abs() < 1e-9,
+        "{movement:?}"
+    );
+    assert_eq!(movement.volume_growth, None);
+}
+
+#[test]
+fn risk_gate_clamps_confidence_and_downgrades_weak_trades() {
+    let analysis = |recommendation, confidence| AiAnalysis {
+        recommendation,
+        confidence,
+        reasoning: String::new(),
+        key_factors: Vec::new(),
+        target_outcome: None,
+    };
+
+    let mut weak = analysis(Recommendation::BuyYes, 0.51);
+    let overrides = apply_risk_gate(&mut weak, 0.6);
+    assert_eq!(weak.recommendation, Recommendation::NoTrade);
+    assert_eq!(overrides.len(), 1);
+    assert!(overrides[0].contains("BUY_YES downgraded"), "{overrides:?}");
+
+    let mut garbage = analysis(Recommendation::BuyNo, 7.5);
+    let overrides = apply_risk_gate(&mut garbage, 0.6);
+    assert_eq!(garbage.confidence, 1.0);
+    assert_eq!(garbage.recommendation, Recommendation::BuyNo);
+    assert!(overrides[0].contains("clamped"), "{overrides:?}");
+
+    // Yes at 0.6 with 0.8 confidence: Kelly is (0.8 - 0.6) / 0.4 = 0.5
+    let market = market("will-it-rain");
+    let strong = analysis(Recommendation::BuyYes, 0.8);
+    assert_eq!(suggested_size(&strong, &market, 100.0), 50.0);
+    assert_eq!(suggested_size(&weak, &market, 100.0), 0.0);
+}
+
+#[test]
+fn target_outcomes_resolve_to_token_ids() {
+    let market = mock::binary_market(
+        "btc-updown-15m",
+        [("Up", TOKEN_YES, 0.55), ("Down", TOKEN_NO, 0.45)],
+    );
+    let analysis = |recommendation, target: Option<&str>| AiAnalysis {
+        recommendation,
+        confidence: 0.7,
+        reasoning: String::new(),
+        key_factors: Vec::new(),
+        target_outcome: target.map(str::to_string),
+    };
+    let resolve = |mut analysis: AiAnalysis| {
+        let mut overrides = Vec::new();
+        let target = resolve_target(&mut analysis, &market, &mut overrides);
+        (
+            target.map(|t| (t.outcome.id, t.matched)),
+            analysis,
+            overrides,
+        )
+    };
+
+    let (target, _, overrides) = resolve(analysis(Recommendation::BuyYes, Some("Up")));
+    assert_eq!(target, Some((TOKEN_YES.to_string(), TargetMatch::Exact)));
+    assert!(overrides.is_empty());
+
+    // A fuzzy name wins over a contradicting side, which is realigned
+    let (target, resolved, overrides) = resolve(analysis(Recommendation::BuyYes, Some("down")));
+    assert_eq!(target, Some((TOKEN_NO.to_string(), TargetMatch::Fuzzy)));
+    assert_eq!(resolved.recommendation, Recommendation::BuyNo);
+    assert!(overrides[0].contains("changed to BUY_NO"), "{overrides:?}");
+
+    let (target, _, _) = resolve(analysis(Recommendation::BuyNo, None));
+    assert_eq!(target, Some((TOKEN_NO.to_string(), TargetMatch::Inferred)));
+
+    let (target, resolved, overrides) = resolve(analysis(Recommendation::BuyYes, Some("Yes")));
+    assert_eq!(target, None);
+    assert_eq!(resolved.recommendation, Recommendation::NoTrade);
+    assert!(resolved.key_factors[0].contains("not one of 'Up', 'Down'"));
+    assert_eq!(overrides, resolved.key_factors);
+}
+
+#[tokio::test]
+async fn analyze_event_markets_validates_risk_settings() {
+    let upstreams = MockUpstreams::all();
+
+    for (field, value) in [
+        ("min_confidence", json!(1.5)),
+        ("max_position_usd", json!(0.0)),
+        ("question", json!("Buy? ".repeat(250))),
+    ] {
+        let mut body = json!({ "url": "https://polymarket.com/event/will-it-rain" });
+        body[field] = value;
+        let request = post("/api/analyze-event-markets", body);
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(error_message(&body).contains(field), "{body}");
+    }
+}
+
+#[tokio::test]
+async fn analyze_and_trade_refuses_what_it_cannot_trade() {
+    let upstreams = MockUpstreams::all();
+    let body = |overrides: Value| {
+        let mut body = json!({
+            "url": "https://polymarket.com/event/will-it-rain",
+            "min_confidence": 0.7,
+            "mode": "simple",
+            "bankroll_usd": 10.0,
+            "dry_run": true,
+            "wallet_private_key": WALLET_KEY,
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        body
+    };
+
+    for (overrides, expected) in [
+        (json!({ "mode": "exit" }), "only buys"),
+        (json!({ "min_confidence": 1.2 }), "min_confidence"),
+        (
+            json!({ "url": "https://kalshi.com/markets/kxfed/kxfed-25dec" }),
+            "only supported on Polymarket",
+        ),
+        (json!({}), "not configured"),
+    ] {
+        let request = post("/api/analyze-and-trade", body(overrides));
+        let (status, body) = send(state(&upstreams), request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(error_message(&body).contains(expected), "{body}");
+    }
+    assert!(upstreams.venue.calls().is_empty());
+}
+
+#[test]
+fn portfolio_stakes_are_fractional_kelly_within_the_caps_and_bankroll() {
+    assert!((kelly_fraction(0.8, 0.6) - 0.5).abs() < 1e-9);
+    assert_eq!(kelly_fraction(0.5, 0.6), 0.0);
+    assert_eq!(kelly_fraction(0.8, 0.0), 0.0);
+    assert_eq!(kelly_fraction(0.8, 1.0), 0.0);
+
+    let candidate = |probability: f64, price: f64| Candidate { probability, price };
+
+    // A quarter of full Kelly (0.4) on a $100 bankroll
+    let sized = allocate(&[candidate(0.7, 0.5)], 100.0, 25.0, 0.25);
+    assert_eq!(sized[0].stake, 10.0);
+    assert_eq!(sized[0].constraint, PortfolioConstraint::Kelly);
+
+    // Full Kelly of 0.8 is held to the 25% cap, leaving the rest unallocated
+    let capped = allocate(
+        &[candidate(0.9, 0.5), candidate(0.7, 0.5)],
+        100.0,
+        25.0,
+        1.0,
+    );
+    assert_eq!(capped[0].stake, 25.0);
+    assert_eq!(capped[0].constraint, PortfolioConstraint::MarketCap);
+    assert!((capped[0].kelly - 0.8).abs() < 1e-9);
+    assert_eq!(capped[1].stake, 25.0);
+    assert_eq!(capped[1].constraint, PortfolioConstraint::MarketCap);
+
+    // Three $80 stakes are scaled to fit, then floored to cents
+    let scaled = allocate(&[candidate(0.9, 0.5); 3], 100.0, 100.0, 1.0);
+    for allocation in &scaled {
+        assert_eq!(allocation.stake, 33.33);
+        assert_eq!(allocation.constraint, PortfolioConstraint::Bankroll);
+    }
+
+    // $1.6665 rounds down, never up
+    let floored = allocate(&[candidate(0.7, 0.4)], 10.0, 100.0, 0.3333);
+    assert_eq!(floored[0].stake, 1.66);
+
+    let mixed = [
+        candidate(0.9, 0.3),
+        candidate(0.8, 0.55),
+        candidate(0.95, 0.1),
+    ];
+    for bankroll in [0.07, 1.0, 3.33, 99.99, 1000.01, 12345.67] {
+        let total: f64 = allocate(&mixed, bankroll, 100.0, 1.0)
+            .iter()
+            .map(|a| a.stake)
+            .sum();
+        assert!(total <= bankroll + 1e-9, "{total} > {bankroll}");
+    }
+}
+
+#[tokio::test]
+async fn construct_portfolio_sizes_analyzed_markets_and_executes_them() {
+    let upstreams = MockUpstreams::all();
+    let rain = market("will-it-rain");
+    upstreams.market_data.as_ref().unwrap().insert_market(
+        Platform::Polymarket,
+        "will-it-rain",
+        rain.clone(),
+    );
+    upstreams.venue.insert_market(rain);
+    upstreams
+        .venue
+        .insert_order_book(book(TOKEN_YES, 0.59, 0.61));
+    let grok = MockServer::start().await;
+    Mock::given(wiremock::matchers::method("POST"))
+        .and(wiremock::matchers::path("/chat/completions"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "model": "grok-beta",
+            "choices": [{"message": {"role": "assistant", "content": json!({
+                "recommendation": "BUY_YES",
+                "confidence": 0.8,
+                "reasoning": "Forecast says rain.",
+                "key_factors": ["Forecast"],
+            }).to_string()}}],
+        })))
+        .mount(&grok)
+        .await;
+    let state = mock::app_state(
+        &upstreams,
+        Config {
+            grok_api_key: Some("grok-key".to_string()),
+            grok_base_url: Some(grok.uri()),
+            ..mock::config()
+        },
+    );
+    let body = |overrides: Value| {
+        let mut body = json!({
+            "analyses": [
+                { "market_url": "https://polymarket.com/event/will-it-rain" },
+                { "analysis_id": "an_missing" },
+            ],
+            "bankroll_usd": 100.0,
+            "model": "grok",
+        });
+        body.as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        body
+    };
+
+    // Kelly of (0.8 - 0.6) / 0.4 = 0.5, at the default quarter
+    let request = post("/api/construct-portfolio", body(json!({})));
+    let (status, body_out) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body_out}");
+    let position = &body_out["positions"][0];
+    assert_eq!(position["token_id"], TOKEN_YES);
+    assert_eq!(position["target_stake_usd"], 12.5);
+    assert_eq!(position["limiting_constraint"], "kelly");
+    assert_eq!(body_out["skipped"][0]["analysis_id"], "an_missing");
+    assert_eq!(body_out["total_allocated_usd"], 12.5);
+    assert_eq!(body_out["unallocated_usd"], 87.5);
+    assert!(body_out.get("executions").is_none());
+
+    let request = post(
+        "/api/construct-portfolio",
+        body(json!({ "execute": true, "dry_run": true, "wallet_private_key": WALLET_KEY })),
+    );
+    let (status, body_out) = send(state.clone(), request).await;
+    assert_eq!(status, StatusCode::OK, "{body_out}");
+    let execution = &body_out["executions"][0];
+    assert!(execution.get("error").is_none(), "{body_out}");
+    assert_eq!(execution["stake_usd"], 12.5);
+    assert!(execution["orders_placed"].as_u64().unwrap() > 0);
+    assert!(execution["orders"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .all(|order| order["token_id"] == TOKEN_YES));
+    assert_eq!(body_out["metadata"]["dry_run"], true);
+    assert!(upstreams.venue.orders().is_empty());
+
+    // Refused before analyzing when the bot would refuse it
+    let request = post("/api/construct-portfolio", body(json!({ "execute": true })));
+    let (status, body_out) = send(state, request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{body_out}");
+    assert!(
+        error_message(&body_out).contains("Wallet credentials are required"),
+        "{body_out}"
+    );
+}
+
+fn candle(minutes_ago: i64, close: f64) -> Candle {
+    Candle {
+        timestamp: chrono::Utc::now() - chrono::Duration::minutes(minutes_ago),
+        open: close,
+        high: close,
+        low: close,
+        close,
+        volume: 100.0,
+    }
+}
+
+#[tokio::test]
+async fn market_history_returns_candles_and_a_summary() {
+    let upstreams = MockUpstreams::all();
+    let market_data = upstreams.market_data.as_ref().unwrap();
+    let rain = market("will-it-rain");
+    market_data.insert_candles(
+        Platform::Polymarket,
+        rain.condition_id.as_deref().unwrap(),
+        vec![candle(60, 0.5), candle(30, 0.58), candle(0, 0.6)],
+    );
+    market_data.insert_market(Platform::Polymarket, "will-it-rain", rain);
+
+    let (status, body) = send(
+        state(&upstreams),
+        get("/api/market-history?url=https://polymarket.com/event/will-it-rain&interval=1m&lookback=2h"),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["interval"], "1m");
+    assert_eq!(body["candles"].as_array().unwrap().len(), 3);
+    let summary = &body["summary"];
+    assert_eq!(summary["last_price"], 0.6);
+    assert!((summary["change_1h"].as_f64().unwrap() - 0.1).abs() < 1e-9);
+    assert!((summary["realized_volatility"].as_f64().unwrap() - 0.03).abs() < 1e-9);
+}
+
+#[tokio::test]
+async fn market_history_validates_the_interval_and_lookback() {
+    let upstreams = MockUpstreams::all();
+    let url = "https://polymarket.com/event/will-it-rain";
+
+    for (query, expected) in [
+        ("interval=5m", "expected 1m, 1h or 1d"),
+        ("lookback=2x", "Invalid lookback"),
+        ("lookback=0h", "Invalid lookback"),
+        ("interval=1h&lookback=30m", "at least one 1h candle"),
+        ("interval=1m&lookback=2d", "at most 1440"),
+    ] {
+        let uri = format!("/api/market-history?url={}&{}", url, query);
+        let (status, body) = send(state(&upstreams), get(&uri)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{query}");
+        assert!(error_message(&body).contains(expected), "{query}: {body}");
+    }
+    assert!(upstreams.market_data.unwrap().calls().is_empty());
+}
+
+#[test]
+fn leaderboard_ranks_pnl_change_over_the_period() {
+    let now = "2026-10-16T12:00:00Z"
+        .parse::<chrono::DateTime<chrono::Utc>>()
+        .unwrap();
+    let period_start = now - chrono::Duration::days(7);
+    let snapshot =
+        |days_ago: i64, realized_pnl: f64, unrealized_pnl: f64, volume: f64| WalletSnapshot {
+            taken_at: now - chrono::Duration::days(days_ago),
+            realized_pnl,
+            unrealized_pnl,
+            volume,
+        };
+    let mut history = std::collections::HashMap::new();
+    // Baseline is the last snapshot before the period, not the oldest;
+    // one taken after `now` is ignored
+    history.insert(
+        "alpha".to_string(),
+        vec![
+            snapshot(10, 100.0, 0.0, 1000.0),
+            snapshot(8, 110.0, 0.0, 1100.0),
+            snapshot(1, 150.0, 10.0, 1500.0),
+            snapshot(-1, 1000.0, 0.0, 9000.0),
+        ],
+    );
+    // Joined three days ago
+    history.insert(
+        "beta".to_string(),
+        vec![snapshot(3, 0.0, 0.0, 0.0), snapshot(0, 20.0, 60.0, 200.0)],
+    );
+    history.insert(
+        "gamma".to_string(),
+        vec![snapshot(9, 50.0, 0.0, 10.0), snapshot(2, 40.0, -20.0, 60.0)],
+    );
+    history.insert(
+        "idle".to_string(),
+        vec![snapshot(9, 5.0, 1.0, 10.0), snapshot(1, 5.0, 1.0, 10.0)],
+    );
+    let labels: Vec<String> = ["alpha", "beta", "gamma", "idle", "ghost"]
+        .map(String::from)
+        .to_vec();
+
+    let (entries, excluded) = build_leaderboard(&history, &labels, period_start, now);
+
+    let ranking: Vec<(usize, &str, f64)> = entries
+        .iter()
+        .map(|e| (e.rank, e.label.as_str(), e.pnl_change))
+        .collect();
+    assert_eq!(
+        ranking,
+        [(1, "beta", 80.0), (2, "alpha", 50.0), (3, "gamma", -30.0)]
+    );
+
+    let beta = &entries[0];
+    assert!(beta.joined_mid_period);
+    assert_eq!(beta.volume_traded, 200.0);
+    // One point a day from the first snapshot
+    let dates: Vec<&str> = beta.sparkline.iter().map(|p| p.date.as_str()).collect();
+    assert_eq!(
+        dates,
+        ["2026-10-13", "2026-10-14", "2026-10-15", "2026-10-16"]
+    );
+    assert_eq!(beta.sparkline.last().unwrap().pnl, 80.0);
+
+    let alpha = &entries[1];
+    assert!(!alpha.joined_mid_period);
+    assert_eq!(alpha.realized_pnl_change, 40.0);
+    assert_eq!(alpha.unrealized_pnl_change, 10.0);
+    assert_eq!(alpha.volume_traded, 400.0);
+    assert_eq!(alpha.sparkline.len(), 8);
+    assert_eq!(alpha.sparkline[0].pnl, 0.0);
+
+    let excluded: Vec<(&str, &str)> = excluded
+        .iter()
+        .map(|e| (e.label.as_str(), e.reason.as_str()))
+        .collect();
+    assert_eq!(
+        excluded,
+        [
+            ("idle", "No trading activity in this period"),
+            ("ghost", "No snapshots recorded in this period"),
+        ]
+    );
+}
@@ -0,0 +1,148 @@
+//! `CORS_ALLOWED_ORIGINS` parsing and the preflight answers it produces.
+
+use axum::body::Body;
+use axum::http::{header, HeaderMap, Method, Request, StatusCode};
+use tower::ServiceExt;
+
+use predict_os_be::api::cors::CorsOrigins;
+use predict_os_be::api::create_router;
+use predict_os_be::mock::{self, MockUpstreams};
+
+const APP: &str = "https://app.example.com";
+
+async fn preflight(origins: &CorsOrigins, origin: &str) -> (StatusCode, HeaderMap) {
+    let state = mock::app_state(&MockUpstreams::default(), mock::config());
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/api/limit-order-bot")
+        .header(header::ORIGIN, origin)
+        .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+        .header(
+            header::ACCESS_CONTROL_REQUEST_HEADERS,
+            "authorization,content-type,idempotency-key,x-wallet-private-key,x-request-id,\
+             x-response-version",
+        )
+        .body(Body::empty())
+        .unwrap();
+    let response = create_router()
+        .layer(origins.layer())
+        .with_state(state)
+        .oneshot(request)
+        .await
+        .expect("router is infallible");
+    (response.status(), response.headers().clone())
+}
+
+fn header_value(headers: &HeaderMap, name: header::HeaderName) -> &str {
+    headers
+        .get(name)
+        .map(|v| v.to_str().unwrap())
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn allowed_origins_get_the_cors_headers() {
+    let origins: CorsOrigins = format!("{}, http://localhost:3000", APP).parse().unwrap();
+
+    let (status, headers) = preflight(&origins, APP).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        header_value(&headers, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+        APP
+    );
+    let methods = header_value(&headers, header::ACCESS_CONTROL_ALLOW_METHODS);
+    for method in ["GET", "POST", "DELETE"] {
+        assert!(methods.contains(method), "{methods}");
+    }
+    let allowed = header_value(&headers, header::ACCESS_CONTROL_ALLOW_HEADERS);
+    for name in [
+        "authorization",
+        "content-type",
+        "idempotency-key",
+        "if-none-match",
+        "x-wallet-private-key",
+        "x-request-id",
+        "x-response-version",
+    ] {
+        assert!(allowed.contains(name), "{allowed}");
+    }
+
+    // Actual responses let the page read the request id and rate-limit backoff
+    let state = mock::app_state(&MockUpstreams::default(), mock::config());
+    let request = Request::builder()
+        .uri("/api/orders/0xabc")
+        .header(header::ORIGIN, APP)
+        .body(Body::empty())
+        .unwrap();
+    let response = create_router()
+        .layer(origins.layer())
+        .with_state(state)
+        .oneshot(request)
+        .await
+        .expect("router is infallible");
+    let exposed = header_value(response.headers(), header::ACCESS_CONTROL_EXPOSE_HEADERS);
+    for name in ["etag", "x-request-id", "retry-after"] {
+        assert!(exposed.contains(name), "{exposed}");
+    }
+
+    let (_, headers) = preflight(&origins, "http://localhost:3000").await;
+    assert_eq!(
+        header_value(&headers, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+        "http://localhost:3000"
+    );
+}
+
+#[tokio::test]
+async fn other_origins_get_no_cors_headers() {
+    let origins: CorsOrigins = APP.parse().unwrap();
+
+    for origin in [
+        "https://evil.example.com",
+        "http://app.example.com",
+        "https://app.example.com:8443",
+    ] {
+        let (_, headers) = preflight(&origins, origin).await;
+        assert!(
+            !headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            "{origin} was allowed"
+        );
+    }
+}
+
+#[tokio::test]
+async fn wildcard_and_unset_allow_any_origin() {
+    for origins in [CorsOrigins::Any, CorsOrigins::Unset] {
+        let (_, headers) = preflight(&origins, "https://evil.example.com").await;
+        assert_eq!(
+            header_value(&headers, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            "*",
+            "{origins}"
+        );
+    }
+}
+
+#[test]
+fn origins_are_normalized() {
+    let origins: CorsOrigins = "https://App.Example.com/ , http://localhost:3000"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        origins,
+        CorsOrigins::List(vec![APP.to_string(), "http://localhost:3000".to_string()])
+    );
+    assert_eq!("*".parse::<CorsOrigins>().unwrap(), CorsOrigins::Any);
+    assert_eq!(" ".parse::<CorsOrigins>().unwrap(), CorsOrigins::Unset);
+}
+
+#[test]
+fn invalid_origins_are_rejected() {
+    for (value, reason) in [
+        ("app.example.com", "not an origin"),
+        ("ftp://app.example.com", "http or https"),
+        ("https://app.example.com/app", "path"),
+        (&format!("*,{}", APP), "can't be combined"),
+    ] {
+        let err = value.parse::<CorsOrigins>().unwrap_err();
+        assert!(err.contains(reason), "{value}: {err}");
+    }
+}
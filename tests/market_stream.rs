@@ -0,0 +1,171 @@
+//! `/ws/market/:slug` over a real socket, against the mock venue.
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use std::sync::Arc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use predict_os_be::api::{create_router, middleware, AppState};
+use predict_os_be::mock::{self, MockUpstreams};
+use predict_os_be::types::MarketData;
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+const SLUG: &str = "btc-updown-15m-1700000000";
+
+fn market(yes: f64, no: f64) -> MarketData {
+    mock::binary_market(SLUG, [("Up", "1111", yes), ("Down", "2222", no)])
+}
+
+/// Serves the router, behind the API token check, on a local port and
+/// returns its `ws://` base.
+async fn serve(state: Arc<AppState>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let router = create_router()
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::require_api_token,
+        ))
+        .with_state(state);
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("ws://{}", address)
+}
+
+async fn next_json(socket: &mut Socket) -> Value {
+    let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+        .await
+        .expect("a message within 5s")
+        .expect("socket is open")
+        .expect("message is readable");
+    match message {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        other => panic!("expected a JSON text message, got {other:?}"),
+    }
+}
+
+/// The next two price messages, keyed by outcome.
+async fn prices(socket: &mut Socket) -> Vec<(String, f64)> {
+    let mut prices = Vec::new();
+    for _ in 0..2 {
+        let message = next_json(socket).await;
+        assert_eq!(message["type"], "price", "{message}");
+        assert!(message["ts"].is_string());
+        prices.push((
+            message["outcome"].as_str().unwrap().to_string(),
+            message["price"].as_f64().unwrap(),
+        ));
+    }
+    prices.sort_by(|a, b| a.0.cmp(&b.0));
+    prices
+}
+
+#[tokio::test]
+async fn subscribers_get_prices_changes_and_the_close() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market(0.6, 0.4));
+    let base = serve(mock::app_state(&upstreams, mock::config())).await;
+    let url = format!("{}/ws/market/{}", base, SLUG);
+
+    let (mut first, _) = connect_async(&url).await.unwrap();
+    let (mut second, _) = connect_async(&url).await.unwrap();
+    for socket in [&mut first, &mut second] {
+        let expected = [("Down".to_string(), 0.4), ("Up".to_string(), 0.6)];
+        assert_eq!(prices(socket).await, expected);
+    }
+
+    upstreams.venue.insert_market(market(0.7, 0.3));
+    for socket in [&mut first, &mut second] {
+        let expected = [("Down".to_string(), 0.3), ("Up".to_string(), 0.7)];
+        assert_eq!(prices(socket).await, expected);
+    }
+
+    let mut resolved = market(1.0, 0.0);
+    resolved.closed = true;
+    resolved.resolved_outcome = Some("Up".to_string());
+    upstreams.venue.insert_market(resolved);
+    for socket in [&mut first, &mut second] {
+        let mut message = next_json(socket).await;
+        while message["type"] == "price" {
+            message = next_json(socket).await;
+        }
+        assert_eq!(message["type"], "market_closed");
+        assert_eq!(message["slug"], SLUG);
+        assert_eq!(message["resolved_outcome"], "Up");
+        match socket.next().await {
+            Some(Ok(Message::Close(Some(frame)))) => assert_eq!(frame.code, CloseCode::Normal),
+            other => panic!("expected a close frame, got {other:?}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn an_already_closed_market_closes_straight_away() {
+    let upstreams = MockUpstreams::default();
+    let mut closed = market(1.0, 0.0);
+    closed.closed = true;
+    upstreams.venue.insert_market(closed);
+    let base = serve(mock::app_state(&upstreams, mock::config())).await;
+
+    let (mut socket, _) = connect_async(format!("{}/ws/market/{}", base, SLUG))
+        .await
+        .unwrap();
+    assert_eq!(next_json(&mut socket).await["type"], "market_closed");
+}
+
+#[tokio::test]
+async fn unknown_markets_are_refused_before_the_upgrade() {
+    let upstreams = MockUpstreams::default();
+    let base = serve(mock::app_state(&upstreams, mock::config())).await;
+
+    match connect_async(format!("{}/ws/market/no-such-market", base)).await {
+        Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 404),
+        other => panic!("expected a 404, got {:?}", other.map(|(_, r)| r.status())),
+    }
+}
+
+#[tokio::test]
+async fn with_api_tokens_the_stream_needs_one_in_a_header_or_subprotocol() {
+    let upstreams = MockUpstreams::default();
+    upstreams.venue.insert_market(market(0.6, 0.4));
+    let config = predict_os_be::config::Config {
+        api_auth_tokens: vec!["stream-token".to_string()],
+        ..mock::config()
+    };
+    let base = serve(mock::app_state(&upstreams, config)).await;
+    let url = format!("{}/ws/market/{}", base, SLUG);
+    let with_header = |name: &'static str, value: &str| {
+        let mut request = url.as_str().into_client_request().unwrap();
+        request.headers_mut().insert(name, value.parse().unwrap());
+        request
+    };
+
+    for refused in [
+        url.as_str().into_client_request().unwrap(),
+        with_header("authorization", "Bearer wrong-token"),
+        with_header("sec-websocket-protocol", "bearer, wrong-token"),
+    ] {
+        match connect_async(refused).await {
+            Err(tungstenite::Error::Http(response)) => assert_eq!(response.status(), 401),
+            other => panic!("expected a 401, got {:?}", other.map(|(_, r)| r.status())),
+        }
+    }
+
+    let (mut socket, _) = connect_async(with_header("authorization", "Bearer stream-token"))
+        .await
+        .unwrap();
+    assert_eq!(prices(&mut socket).await.len(), 2);
+
+    // As a browser sends it, with the chosen subprotocol echoed back
+    let (mut socket, response) = connect_async(with_header(
+        "sec-websocket-protocol",
+        "bearer, stream-token",
+    ))
+    .await
+    .unwrap();
+    assert_eq!(response.headers()["sec-websocket-protocol"], "bearer");
+    assert_eq!(prices(&mut socket).await.len(), 2);
+}
@@ -0,0 +1,151 @@
+//! Recurring up/down market windows and slugs, which follow the Eastern
+//! wall clock Polymarket names them by rather than UTC, checked at the DST
+//! changes where fixed-length arithmetic on UTC goes wrong.
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use predict_os_be::clients::polymarket::{MarketWindow, MARKET_TIMEZONE};
+use predict_os_be::clients::PolymarketClient;
+use predict_os_be::types::MarketInterval;
+
+fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+}
+
+fn window(interval: MarketInterval, at: DateTime<Utc>) -> MarketWindow {
+    PolymarketClient::market_window_at(interval, MARKET_TIMEZONE, at)
+}
+
+fn slug(interval: MarketInterval, at: DateTime<Utc>) -> String {
+    PolymarketClient::build_updown_slug("btc", interval, window(interval, at).current)
+}
+
+#[test]
+fn fifteen_minute_windows_keep_the_unix_slug() {
+    let at = utc(2025, 11, 14, 16, 52);
+    let w = window(MarketInterval::FifteenMinutes, at);
+    assert_eq!(w.current, utc(2025, 11, 14, 16, 45));
+    assert_eq!(w.next, utc(2025, 11, 14, 17, 0));
+    assert_eq!(
+        slug(MarketInterval::FifteenMinutes, at),
+        format!("btc-updown-15m-{}", w.current.timestamp())
+    );
+
+    // Quarter hours on the local clock, even where the offset isn't whole hours
+    let kolkata = PolymarketClient::market_window_at(
+        MarketInterval::FifteenMinutes,
+        chrono_tz::Asia::Kolkata,
+        at,
+    );
+    // 16:52 UTC is 22:22 IST, so the window opened at 22:15 IST
+    assert_eq!(kolkata.current, utc(2025, 11, 14, 16, 45));
+    let hourly =
+        PolymarketClient::market_window_at(MarketInterval::OneHour, chrono_tz::Asia::Kolkata, at);
+    assert_eq!(hourly.current, utc(2025, 11, 14, 16, 30));
+    assert_eq!(hourly.next, utc(2025, 11, 14, 17, 30));
+}
+
+#[test]
+fn spring_forward_skips_the_two_am_hour() {
+    // 2026-03-08: 01:59 EST is followed by 03:00 EDT
+    let before = window(MarketInterval::OneHour, utc(2026, 3, 8, 6, 30));
+    assert_eq!(before.current, utc(2026, 3, 8, 6, 0)); // 1am EST
+    assert_eq!(before.next, utc(2026, 3, 8, 7, 0)); // 3am EDT
+    assert_eq!(
+        slug(MarketInterval::OneHour, utc(2026, 3, 8, 6, 30)),
+        "bitcoin-up-or-down-march-8-1am-et"
+    );
+    assert_eq!(
+        slug(MarketInterval::OneHour, utc(2026, 3, 8, 7, 30)),
+        "bitcoin-up-or-down-march-8-3am-et"
+    );
+
+    // The daily window from noon EST on the 7th to noon EDT on the 8th is
+    // 23 hours long, and named for the day it ends
+    let day = window(MarketInterval::OneDay, utc(2026, 3, 8, 12, 0));
+    assert_eq!(day.current, utc(2026, 3, 7, 17, 0));
+    assert_eq!(day.next, utc(2026, 3, 8, 16, 0));
+    assert_eq!(day.next - day.current, chrono::Duration::hours(23));
+    assert_eq!(
+        slug(MarketInterval::OneDay, utc(2026, 3, 8, 12, 0)),
+        "bitcoin-up-or-down-on-march-8"
+    );
+
+    // Still the 7th in New York, but past noon, so already the window
+    // ending on the 8th
+    assert_eq!(
+        slug(MarketInterval::OneDay, utc(2026, 3, 8, 4, 30)),
+        "bitcoin-up-or-down-on-march-8"
+    );
+    // From noon on the 8th, the window ending on the 9th
+    let after = window(MarketInterval::OneDay, utc(2026, 3, 8, 16, 0));
+    assert_eq!(after.current, day.next);
+    assert_eq!(
+        slug(MarketInterval::OneDay, utc(2026, 3, 8, 16, 0)),
+        "bitcoin-up-or-down-on-march-9"
+    );
+}
+
+#[test]
+fn fall_back_repeats_the_one_am_hour() {
+    // 2026-11-01: 01:59 EDT is followed by 01:00 EST
+    let first = window(MarketInterval::OneHour, utc(2026, 11, 1, 5, 30));
+    let second = window(MarketInterval::OneHour, utc(2026, 11, 1, 6, 30));
+    assert_eq!(first.current, utc(2026, 11, 1, 5, 0));
+    assert_eq!(first.next, second.current);
+    assert_eq!(second.next, utc(2026, 11, 1, 7, 0));
+    for at in [utc(2026, 11, 1, 5, 30), utc(2026, 11, 1, 6, 30)] {
+        assert_eq!(
+            slug(MarketInterval::OneHour, at),
+            "bitcoin-up-or-down-november-1-1am-et"
+        );
+    }
+
+    // Quarter hours stay on the local clock through the change
+    let quarter = window(MarketInterval::FifteenMinutes, utc(2026, 11, 1, 5, 50));
+    assert_eq!(quarter.current, utc(2026, 11, 1, 5, 45));
+    assert_eq!(quarter.next, utc(2026, 11, 1, 6, 0));
+
+    // Noon EDT on October 31st to noon EST on November 1st is 25 hours
+    let day = window(MarketInterval::OneDay, utc(2026, 11, 1, 12, 0));
+    assert_eq!(day.current, utc(2026, 10, 31, 16, 0));
+    assert_eq!(day.next, utc(2026, 11, 1, 17, 0));
+    assert_eq!(day.next - day.current, chrono::Duration::hours(25));
+    assert_eq!(
+        slug(MarketInterval::OneDay, utc(2026, 11, 1, 12, 0)),
+        "bitcoin-up-or-down-on-november-1"
+    );
+}
+
+#[test]
+fn daily_windows_run_noon_to_noon_on_the_local_clock() {
+    // Chile springs forward at local midnight on 2025-09-07, between the
+    // noons of the 6th (UTC-4) and the 7th (UTC-3)
+    let santiago = chrono_tz::America::Santiago;
+    let day = PolymarketClient::market_window_at(
+        MarketInterval::OneDay,
+        santiago,
+        utc(2025, 9, 7, 12, 0),
+    );
+    assert_eq!(day.current, utc(2025, 9, 6, 16, 0));
+    assert_eq!(day.next, utc(2025, 9, 7, 15, 0));
+
+    // Noon itself opens the next window
+    let at_noon = window(MarketInterval::OneDay, utc(2025, 11, 14, 17, 0));
+    assert_eq!(at_noon.current, utc(2025, 11, 14, 17, 0));
+    assert_eq!(at_noon.next, utc(2025, 11, 15, 17, 0));
+}
+
+#[test]
+fn intervals_parse_from_their_short_names() {
+    for (name, interval) in [
+        ("15m", MarketInterval::FifteenMinutes),
+        ("1h", MarketInterval::OneHour),
+        ("1d", MarketInterval::OneDay),
+    ] {
+        let parsed: MarketInterval = serde_json::from_value(serde_json::json!(name)).unwrap();
+        assert_eq!(parsed, interval);
+    }
+    assert_eq!(MarketInterval::default(), MarketInterval::FifteenMinutes);
+    assert!(serde_json::from_value::<MarketInterval>(serde_json::json!("4h")).is_err());
+}
@@ -0,0 +1,160 @@
+//! The OpenAPI document served on `/api/openapi.json`.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use serde::Serialize;
+use serde_json::Value;
+use tower::ServiceExt;
+
+use predict_os_be::api::create_router;
+use predict_os_be::error::ErrorCode;
+use predict_os_be::mock::{self, MockUpstreams};
+use predict_os_be::types::{OrderMode, OrderStatus, PairStatus, Recommendation};
+
+async fn fetch(uri: &str) -> (StatusCode, Vec<u8>) {
+    let state = mock::app_state(&MockUpstreams::default(), mock::config());
+    let response = create_router()
+        .with_state(state)
+        .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        .await
+        .expect("router is infallible");
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body is readable");
+    (status, bytes.to_vec())
+}
+
+async fn spec() -> Value {
+    let (status, bytes) = fetch("/api/openapi.json").await;
+    assert_eq!(status, StatusCode::OK);
+    serde_json::from_slice(&bytes).expect("spec is JSON")
+}
+
+fn schema<'a>(spec: &'a Value, name: &str) -> &'a Value {
+    &spec["components"]["schemas"][name]
+}
+
+/// The names serde gives `variants`, in order.
+fn serialized<T: Serialize>(variants: &[T]) -> Vec<Value> {
+    variants
+        .iter()
+        .map(|v| serde_json::to_value(v).unwrap())
+        .collect()
+}
+
+/// Every `$ref` under `value`.
+fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(target)) = map.get("$ref") {
+                found.push(target);
+            }
+            map.values().for_each(|v| refs(v, found));
+        }
+        Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+        _ => {}
+    }
+}
+
+#[tokio::test]
+async fn spec_is_valid_openapi_covering_the_documented_routes() {
+    let (_, bytes) = fetch("/api/openapi.json").await;
+    let parsed: utoipa::openapi::OpenApi =
+        serde_json::from_slice(&bytes).expect("spec parses as OpenAPI");
+    assert!(matches!(
+        parsed.openapi,
+        utoipa::openapi::OpenApiVersion::Version31
+    ));
+
+    let spec = spec().await;
+    for path in [
+        "/api/analyze-event-markets",
+        "/api/polyfactual-research",
+        "/api/position-tracker",
+        "/api/limit-order-bot",
+    ] {
+        let operation = &spec["paths"][path]["post"];
+        assert!(operation["requestBody"].is_object(), "{path} has no body");
+        assert!(
+            operation["responses"]["200"].is_object(),
+            "{path} has no 200"
+        );
+    }
+
+    let mut found = Vec::new();
+    refs(&spec, &mut found);
+    for target in found {
+        let name = target
+            .strip_prefix("#/components/schemas/")
+            .unwrap_or_else(|| panic!("unexpected $ref {target}"));
+        assert!(schema(&spec, name).is_object(), "dangling $ref {target}");
+    }
+}
+
+#[tokio::test]
+async fn enum_schemas_match_their_serde_names() {
+    let spec = spec().await;
+    let variants = |name: &str| schema(&spec, name)["enum"].as_array().unwrap().clone();
+
+    assert_eq!(
+        variants("Recommendation"),
+        serialized(&[
+            Recommendation::BuyYes,
+            Recommendation::BuyNo,
+            Recommendation::NoTrade
+        ])
+    );
+    assert_eq!(
+        variants("OrderMode"),
+        serialized(&[OrderMode::Simple, OrderMode::Ladder, OrderMode::Exit])
+    );
+    assert_eq!(
+        variants("PairStatus"),
+        serialized(&[
+            PairStatus::ProfitLocked,
+            PairStatus::BreakEven,
+            PairStatus::AtRisk,
+            PairStatus::NoPosition
+        ])
+    );
+    assert_eq!(
+        variants("OrderStatus"),
+        serialized(&[
+            OrderStatus::Pending,
+            OrderStatus::PartiallyFilled,
+            OrderStatus::Filled,
+            OrderStatus::Cancelled,
+            OrderStatus::Failed,
+            OrderStatus::Unconfirmed,
+            OrderStatus::Simulated
+        ])
+    );
+    assert_eq!(variants("ErrorCode"), serialized(&ErrorCode::ALL));
+}
+
+#[tokio::test]
+async fn optional_fields_are_nullable_and_not_required() {
+    let spec = spec().await;
+    let required = |name: &str| schema(&spec, name)["required"].as_array().unwrap().clone();
+
+    let slug = &schema(&spec, "MarketData")["properties"]["slug"];
+    assert_eq!(slug["type"], serde_json::json!(["string", "null"]));
+    assert!(!required("MarketData").contains(&"slug".into()));
+    assert!(required("MarketData").contains(&"question".into()));
+
+    // A nullable reference is a oneOf with null
+    let key = &schema(&spec, "LimitOrderBotRequest")["properties"]["wallet_private_key"];
+    assert!(key["oneOf"]
+        .as_array()
+        .unwrap()
+        .contains(&serde_json::json!({ "type": "null" })));
+    assert!(required("LimitOrderBotRequest").contains(&"mode".into()));
+    assert!(!required("LimitOrderBotRequest").contains(&"dry_run".into()));
+}
+
+#[tokio::test]
+async fn swagger_ui_is_served() {
+    let (status, _) = fetch("/api/docs/").await;
+    assert_eq!(status, StatusCode::OK);
+}
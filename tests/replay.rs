@@ -0,0 +1,451 @@
+//! Handlers driven through `create_router()` with the real upstream
+//! clients, answered from the responses recorded in
+//! `tests/fixtures/replay/` (as with `UPSTREAM_REPLAY_DIR`) so nothing
+//! reaches the network:
+//!
+//! ```text
+//! cargo test --test replay
+//! ```
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Mutex, MutexGuard};
+use tower::ServiceExt;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+use predict_os_be::api::{create_router, AppState};
+use predict_os_be::clients::kalshi::KalshiCredentials;
+use predict_os_be::clients::polymarket::{MarketSearch, PolymarketUrls};
+use predict_os_be::clients::recorder::{redact_json, redact_url};
+use predict_os_be::clients::{
+    build_http_client, DomeClient, HttpClientConfig, KalshiClient, PolyfactualClient,
+    PolymarketClient, ReplayMode, UpstreamSettings,
+};
+use predict_os_be::config::Config;
+use predict_os_be::mock::{self, MockUpstreams};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const WALLET: &str = "0x00000000000000000000000000000000000000aa";
+/// Recorded in `dome/`; Grok's recorded answer for it is a 429
+const EVENT_URL: &str = "https://polymarket.com/event/fed-decision-in-december";
+
+/// The replay and record directories are process-wide, so tests that set
+/// them take turns.
+static UPSTREAM: Mutex<()> = Mutex::const_new(());
+
+fn replay_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/replay")
+}
+
+/// Serves upstream calls from `dir` until the guard is dropped.
+async fn replaying(dir: PathBuf) -> MutexGuard<'static, ()> {
+    let guard = UPSTREAM.lock().await;
+    install(ReplayMode::Replay(dir));
+    guard
+}
+
+fn install(replay: ReplayMode) {
+    UpstreamSettings {
+        replay,
+        ..UpstreamSettings::default()
+    }
+    .install();
+}
+
+fn http() -> reqwest::Client {
+    build_http_client(&HttpClientConfig::default()).unwrap()
+}
+
+/// The production clients behind every route; only the credentials are
+/// made up, and they never leave the process.
+fn state() -> Arc<AppState> {
+    let config = Config {
+        grok_api_key: Some("replay-key".to_string()),
+        openai_api_key: Some("replay-key".to_string()),
+        ..mock::config()
+    };
+    let state = mock::app_state(&MockUpstreams::all(), config);
+    let dome = DomeClient::new(Some("replay-key".to_string()), 2, TIMEOUT, http(), None);
+    let polyfactual = PolyfactualClient::new(
+        Some("replay-key".to_string()),
+        TIMEOUT,
+        state.config.polyfactual_max_query_length,
+        state.config.polyfactual_max_citations,
+        http(),
+        None,
+    );
+    Arc::new(AppState {
+        polymarket_client: Arc::new(PolymarketClient::new(
+            None,
+            1,
+            TIMEOUT,
+            http(),
+            PolymarketUrls::default(),
+        )),
+        dome_client: Some(Arc::new(dome.unwrap())),
+        polyfactual_client: Some(Arc::new(polyfactual.unwrap())),
+        ..(*state).clone()
+    })
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, Value) {
+    let response = create_router()
+        .with_state(state())
+        .oneshot(request)
+        .await
+        .expect("router is infallible");
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body is readable");
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| Value::String(String::from_utf8_lossy(&bytes).into_owned()));
+    (status, body)
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::get(uri).body(Body::empty()).unwrap()
+}
+
+fn post(uri: &str, body: Value) -> Request<Body> {
+    Request::post(uri)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn markets_are_searched_on_replayed_gamma_pages() {
+    let _env = replaying(replay_dir()).await;
+
+    let (status, body) = send(get("/api/markets?query=fed&limit=2")).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    let markets = body["items"].as_array().unwrap();
+    assert_eq!(markets.len(), 2);
+    assert!(markets.iter().all(|m| m["slug"].is_string()));
+}
+
+#[tokio::test]
+async fn portfolio_values_replayed_wallet_positions() {
+    let _env = replaying(replay_dir()).await;
+
+    let request = post("/api/portfolio", json!({ "wallet_address": WALLET }));
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["totals"]["markets"], 2);
+    assert!(body["markets"][0]["market"].is_object(), "{body}");
+}
+
+#[tokio::test]
+async fn research_is_answered_from_a_replayed_polyfactual_run() {
+    let _env = replaying(replay_dir()).await;
+
+    let request = post(
+        "/api/polyfactual-research",
+        json!({ "query": "Will the Fed cut rates in December?" }),
+    );
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(!body["answer"].as_str().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn event_markets_are_analyzed_from_replayed_dome_and_openai_responses() {
+    let _env = replaying(replay_dir()).await;
+
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": EVENT_URL, "model": "openai" }),
+    );
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert!(body["analysis"]["recommendation"].is_string(), "{body}");
+    assert_eq!(body["metadata"]["requested_provider"], "openai");
+    assert_eq!(body["metadata"]["provider"], "openai");
+}
+
+#[tokio::test]
+async fn a_rate_limited_grok_analysis_falls_back_to_openai_with_a_warning() {
+    let _env = replaying(replay_dir()).await;
+
+    let request = post("/api/analyze-event-markets", json!({ "url": EVENT_URL }));
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["metadata"]["requested_provider"], "grok");
+    assert_eq!(body["metadata"]["provider"], "openai");
+    let model = body["metadata"]["model_used"].as_str().unwrap();
+    assert_eq!(
+        body["warnings"][0],
+        format!(
+            "grok: 429 rate limited after 3 attempts; analyzed with openai ({}) instead",
+            model
+        )
+    );
+}
+
+#[tokio::test]
+async fn a_pinned_provider_can_opt_out_of_the_fallback() {
+    let _env = replaying(replay_dir()).await;
+
+    let request = post(
+        "/api/analyze-event-markets",
+        json!({ "url": EVENT_URL, "model": "grok", "allow_fallback": false }),
+    );
+    let (status, body) = send(request).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS, "{body}");
+    assert_eq!(body["code"], "UPSTREAM_RATE_LIMITED");
+}
+
+#[tokio::test]
+async fn a_call_without_a_fixture_fails_naming_the_file_to_record() {
+    let _env = replaying(replay_dir()).await;
+
+    let (status, body) = send(get("/api/markets?query=no-such-recording")).await;
+    assert_eq!(status, StatusCode::BAD_GATEWAY, "{body}");
+    let message = body["error"].as_str().unwrap_or_default().to_string();
+    assert!(message.contains("no replay fixture for GET"), "{body}");
+    assert!(
+        message.contains("tests/fixtures/replay/gamma/get-"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn recorded_responses_replay_without_the_upstream() {
+    let dir = std::env::temp_dir().join(format!("replay-{}", uuid::Uuid::new_v4()));
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/markets"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+            "id": "1",
+            "slug": "will-it-rain",
+            "question": "Will it rain?",
+            "outcomes": "[\"Yes\", \"No\"]",
+            "outcomePrices": "[\"0.6\", \"0.4\"]",
+            "clobTokenIds": "[\"1111\", \"2222\"]",
+        }])))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = PolymarketClient::new(
+        Some("gamma-key".to_string()),
+        1,
+        TIMEOUT,
+        http(),
+        PolymarketUrls {
+            gamma: server.uri(),
+            data_api: server.uri(),
+            clob: server.uri(),
+        },
+    );
+    let search = MarketSearch {
+        query: None,
+        active_only: true,
+        tag: None,
+        min_volume: None,
+        min_liquidity: None,
+        offset: 0,
+        limit: 10,
+    };
+
+    let guard = UPSTREAM.lock().await;
+    install(ReplayMode::Record(dir.clone()));
+    let recorded = client.search_markets(&search).await.unwrap();
+    install(ReplayMode::Off);
+    drop(guard);
+    drop(server);
+
+    let fixtures: Vec<_> = std::fs::read_dir(dir.join("gamma")).unwrap().collect();
+    assert_eq!(fixtures.len(), 1);
+    let fixture = std::fs::read_to_string(fixtures[0].as_ref().unwrap().path()).unwrap();
+    assert!(!fixture.contains("gamma-key"));
+
+    let _env = replaying(dir.clone()).await;
+    let replayed = client.search_markets(&search).await.unwrap();
+    assert_eq!(replayed.markets.len(), recorded.markets.len());
+    assert_eq!(replayed.markets[0].slug, recorded.markets[0].slug);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn recorded_fixtures_keep_no_credentials() {
+    let dir = std::env::temp_dir().join(format!("replay-{}", uuid::Uuid::new_v4()));
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/login"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "member_id": "m-1", "token": "session-token" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/portfolio/positions"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "cursor": "", "market_positions": [] })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = |password: &str| {
+        KalshiClient::new(
+            Some(KalshiCredentials::Login {
+                email: "trader@example.com".to_string(),
+                password: password.to_string(),
+            }),
+            TIMEOUT,
+            http(),
+            Some(server.uri()),
+        )
+        .unwrap()
+    };
+
+    let guard = UPSTREAM.lock().await;
+    install(ReplayMode::Record(dir.clone()));
+    client("hunter2").get_positions(None).await.unwrap();
+    install(ReplayMode::Off);
+    drop(guard);
+
+    let fixtures: Vec<String> = std::fs::read_dir(dir.join("kalshi"))
+        .unwrap()
+        .map(|entry| std::fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect();
+    assert_eq!(fixtures.len(), 2);
+    for fixture in &fixtures {
+        assert!(!fixture.contains("session-token"), "{fixture}");
+        assert!(!fixture.contains("hunter2"), "{fixture}");
+    }
+    assert!(fixtures
+        .iter()
+        .any(|fixture| fixture.contains(r#""token": "[REDACTED]""#)));
+
+    // The login is keyed without the password, so any replays it
+    let _env = replaying(dir.clone()).await;
+    let positions = client("another-password")
+        .get_positions(None)
+        .await
+        .unwrap();
+    assert!(positions.is_empty());
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[tokio::test]
+async fn admins_fetch_recorded_parse_failures_by_id() {
+    let dir = std::env::temp_dir().join(format!("recordings-{}", uuid::Uuid::new_v4()));
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/markets"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("{\"id\": "))
+        .mount(&server)
+        .await;
+    let client = PolymarketClient::new(
+        None,
+        1,
+        TIMEOUT,
+        http(),
+        PolymarketUrls {
+            gamma: server.uri(),
+            data_api: server.uri(),
+            clob: server.uri(),
+        },
+    );
+    let state = mock::app_state(
+        &MockUpstreams::default(),
+        Config {
+            admin_api_token: Some("admin-token".to_string()),
+            ..mock::config()
+        },
+    );
+    let fetch = |id: &str| {
+        let request = Request::get(format!("/api/admin/recordings/{}", id))
+            .header("x-admin-token", "admin-token")
+            .body(Body::empty())
+            .unwrap();
+        let router = create_router().with_state(state.clone());
+        async move {
+            let response = router.oneshot(request).await.unwrap();
+            let status = response.status();
+            let bytes = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<Value>(&bytes).unwrap())
+        }
+    };
+
+    let _guard = UPSTREAM.lock().await;
+    UpstreamSettings {
+        record_failures: true,
+        recordings_dir: dir.clone(),
+        ..UpstreamSettings::default()
+    }
+    .install();
+
+    let error = client
+        .get_market_by_slug("will-it-rain")
+        .await
+        .unwrap_err()
+        .to_string();
+    let id = error
+        .split("(recording ")
+        .nth(1)
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or_else(|| panic!("no recording id in {}", error))
+        .to_string();
+
+    let (status, body) = fetch(&id).await;
+    assert_eq!(status, StatusCode::OK, "{body}");
+    assert_eq!(body["id"], id.as_str());
+    assert_eq!(body["status"], 200);
+    assert_eq!(body["body"], "{\"id\": ");
+    assert!(body["url"].as_str().unwrap().contains("/markets"), "{body}");
+
+    // Ids are UUIDs, so a path can't reach outside the directory
+    for bad in ["not-a-uuid", "..%2F..%2Fetc%2Fpasswd"] {
+        let (status, body) = fetch(bad).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{body}");
+        assert!(
+            body["error"]
+                .as_str()
+                .unwrap()
+                .contains("Invalid recording id"),
+            "{body}"
+        );
+    }
+
+    let (status, body) = fetch(&uuid::Uuid::new_v4().to_string()).await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{body}");
+
+    install(ReplayMode::Off);
+    std::fs::remove_dir_all(dir).ok();
+}
+
+#[test]
+fn credential_fields_are_redacted_at_any_depth() {
+    let mut body = json!({
+        "apiKey": "k",
+        "session": { "access_token": "t", "token_id": "123", "expires_in": 60 },
+        "orders": [{ "owner": "o", "secret": "s", "passphrase": null }],
+        "usage": { "total_tokens": 12 },
+    });
+    assert!(redact_json(&mut body));
+    assert_eq!(
+        body,
+        json!({
+            "apiKey": "[REDACTED]",
+            "session": { "access_token": "[REDACTED]", "token_id": "123", "expires_in": 60 },
+            "orders": [{ "owner": "o", "secret": "[REDACTED]", "passphrase": null }],
+            "usage": { "total_tokens": 12 },
+        })
+    );
+    assert!(!redact_json(&mut json!({ "market": "will-it-rain" })));
+
+    assert_eq!(
+        redact_url("https://api.example.com/book?token_id=42&api_key=k&apiKey=k2"),
+        "https://api.example.com/book?token_id=42&api_key=%5BREDACTED%5D&apiKey=%5BREDACTED%5D"
+    );
+}
@@ -0,0 +1,100 @@
+//! Order rounding to the market's tick size and the exchange minimums,
+//! checked over arbitrary planned prices, sizes and ladders.
+
+use proptest::prelude::*;
+
+use predict_os_be::clients::clob_signing::{round_order, MIN_ORDER_NOTIONAL_USD};
+use predict_os_be::clients::PolymarketClient;
+use predict_os_be::types::LadderSpacing;
+
+fn tick() -> impl Strategy<Value = f64> {
+    prop_oneof![Just(0.1), Just(0.01), Just(0.001), Just(0.0001)]
+}
+
+proptest! {
+    #[test]
+    fn rounded_prices_are_exact_tick_multiples(
+        price in 0.0..=1.0f64,
+        size in 5.0..10_000.0f64,
+        tick in tick(),
+    ) {
+        if let Ok((rounded, _)) = round_order(price, size, tick, 0.0) {
+            let ticks = rounded.value() / tick;
+            prop_assert!((ticks - ticks.round()).abs() < 1e-9, "{} at tick {}", rounded.value(), tick);
+            // The float is the shortest decimal for that multiple
+            let decimals = (-tick.log10()).round() as usize;
+            let printed = format!("{:.*}", decimals, rounded.value());
+            prop_assert_eq!(printed.parse::<f64>().unwrap(), rounded.value());
+            prop_assert!(rounded.value() >= tick && rounded.value() <= 1.0 - tick + 1e-12);
+            prop_assert!((rounded.value() - price.clamp(tick, 1.0 - tick)).abs() <= tick / 2.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn sizes_are_floored_to_cents_and_small_notionals_dropped(
+        price in 0.01..0.99f64,
+        size in 0.0..100.0f64,
+    ) {
+        match round_order(price, size, 0.01, MIN_ORDER_NOTIONAL_USD) {
+            Ok((rounded, shares)) => {
+                prop_assert!(shares <= size + 1e-9 && size - shares < 0.01 + 1e-9);
+                prop_assert!(((shares * 100.0) - (shares * 100.0).round()).abs() < 1e-6);
+                prop_assert!(rounded.value() * shares >= MIN_ORDER_NOTIONAL_USD);
+            }
+            Err(reason) => {
+                let shares = (size * 100.0 + 1e-9).floor() / 100.0;
+                prop_assert!(shares <= 0.0 || reason.contains("minimum"), "{}", reason);
+            }
+        }
+    }
+}
+
+#[test]
+fn awkward_ladder_floats_round_to_placeable_orders() {
+    let (price, size) = round_order(0.37333333, 5.000001, 0.01, MIN_ORDER_NOTIONAL_USD).unwrap();
+    assert_eq!(price.value(), 0.37);
+    assert_eq!(size, 5.0);
+
+    let (price, _) = round_order(0.0004, 5000.0, 0.001, MIN_ORDER_NOTIONAL_USD).unwrap();
+    assert_eq!(price.value(), 0.001);
+
+    let reason = round_order(0.1, 5.0, 0.01, MIN_ORDER_NOTIONAL_USD).unwrap_err();
+    assert!(reason.contains("under the $1.00 minimum"), "{reason}");
+    assert_eq!(
+        round_order(0.5, 0.004, 0.01, 0.0).unwrap_err(),
+        "size rounds to zero"
+    );
+}
+
+fn ladder_weights() -> impl Strategy<Value = Vec<f64>> {
+    prop::collection::vec(0.0..10.0f64, 1..=10)
+}
+
+proptest! {
+    #[test]
+    fn ladder_rungs_stay_within_the_bankroll_once_rounded(
+        bankroll in 5.0..5_000.0f64,
+        low in 0.01..0.98f64,
+        width in 0.0..0.5f64,
+        geometric in any::<bool>(),
+        weights in ladder_weights(),
+        tick in tick(),
+    ) {
+        let high = (low + width).min(0.99);
+        let spacing = if geometric { LadderSpacing::Geometric } else { LadderSpacing::Linear };
+        let ladder =
+            PolymarketClient::calculate_ladder_orders(bankroll, low, high, spacing, &weights, tick);
+
+        let mut spent = 0.0;
+        for (price, shares) in ladder {
+            // Already on the tick and in whole cents of shares: rounding
+            // for placement changes nothing
+            let (rounded, size) = round_order(price, shares, tick, 0.0).unwrap();
+            prop_assert_eq!(rounded.value(), price);
+            prop_assert_eq!(size, shares);
+            prop_assert!(shares >= 5.0);
+            spent += rounded.value() * size;
+        }
+        prop_assert!(spent <= bankroll + 1e-9, "spent {} of {}", spent, bankroll);
+    }
+}
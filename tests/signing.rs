@@ -0,0 +1,279 @@
+//! Order signing and CLOB request authentication against fixed fixtures:
+//! a fixed key, salt and expiry always produce the same payload, signature
+//! and headers, so any change to what gets signed shows up here. Also the
+//! per-signer salt allocator those orders draw from.
+
+use alloy_primitives::{address, Address, Signature, U256};
+use alloy_sol_types::{eip712_domain, sol, SolStruct};
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use predict_os_be::clients::clob_signing::{
+    build_signed_order, l2_signature, order_amounts, ApiCredentials, ClobSigner, MarketParams,
+    OrderSide,
+};
+use predict_os_be::clients::salt::{signer_fingerprint, SaltAllocator, MAX_SALT};
+use predict_os_be::types::Price;
+
+const KEY: &str = "0x0101010101010101010101010101010101010101010101010101010101010101";
+const SIGNER: Address = address!("1a642f0E3c3aF545E7AcBD38b07251B3990914F1");
+const TOKEN_ID: &str =
+    "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+const SALT: u64 = 1_234_567_890;
+const EXPIRATION: u64 = 1_700_000_000;
+/// `base64url("secret-key-for-tests")`
+const SECRET: &str = "c2VjcmV0LWtleS1mb3ItdGVzdHM=";
+
+sol! {
+    // The exchange's order struct, restated so the hash below is derived
+    // independently of the signing code
+    struct Order {
+        uint256 salt;
+        address maker;
+        address signer;
+        address taker;
+        uint256 tokenId;
+        uint256 makerAmount;
+        uint256 takerAmount;
+        uint256 expiration;
+        uint256 nonce;
+        uint256 feeRateBps;
+        uint8 side;
+        uint8 signatureType;
+    }
+}
+
+fn signer() -> ClobSigner {
+    ClobSigner::from_private_key(KEY).unwrap()
+}
+
+fn cents(cents: u8) -> Price {
+    Price::from_cents(cents).unwrap()
+}
+
+#[test]
+fn amounts_are_scaled_to_six_decimals_on_each_side() {
+    // A buy gives USDC for shares, a sell shares for USDC
+    assert_eq!(
+        order_amounts(OrderSide::Buy, cents(45), 10.0, 0.01).unwrap(),
+        (4_500_000, 10_000_000)
+    );
+    assert_eq!(
+        order_amounts(OrderSide::Sell, cents(45), 10.0, 0.01).unwrap(),
+        (10_000_000, 4_500_000)
+    );
+    // Sizes floor to 0.01 shares; the notional is rounded to the unit
+    assert_eq!(
+        order_amounts(OrderSide::Buy, cents(33), 15.679, 0.01).unwrap(),
+        (5_171_100, 15_670_000)
+    );
+    assert_eq!(
+        order_amounts(
+            OrderSide::Buy,
+            Price::from_decimal(0.125).unwrap(),
+            8.0,
+            0.001
+        )
+        .unwrap(),
+        (1_000_000, 8_000_000)
+    );
+    assert!(order_amounts(OrderSide::Buy, cents(45), 0.004, 0.01).is_err());
+}
+
+#[test]
+fn signed_orders_match_the_fixture() {
+    let order = build_signed_order(
+        &signer(),
+        TOKEN_ID,
+        OrderSide::Buy,
+        cents(45),
+        10.0,
+        SALT,
+        EXPIRATION,
+        &MarketParams::default(),
+    )
+    .unwrap();
+
+    let payload = serde_json::to_value(&order).unwrap();
+    assert_eq!(
+        payload,
+        serde_json::json!({
+            "salt": SALT,
+            "maker": SIGNER.to_checksum(None),
+            "signer": SIGNER.to_checksum(None),
+            "taker": "0x0000000000000000000000000000000000000000",
+            "tokenId": TOKEN_ID,
+            "makerAmount": "4500000",
+            "takerAmount": "10000000",
+            "expiration": "1700000000",
+            "nonce": "0",
+            "feeRateBps": "0",
+            "side": "BUY",
+            "signatureType": 0,
+            "signature": order.signature,
+        })
+    );
+    assert_eq!(
+        order.signature,
+        "0x6b9bbb4ea3d2356e50fad8c460cbc05bd70c2601daad037e921aa0c5cb85e45f\
+         6b55c21bc8fd35bd9aa373a9b572316468c13823cf586de9946ae4c507b324741c"
+    );
+
+    // The signature recovers to the wallet over the exchange's typed data
+    let domain = eip712_domain! {
+        name: "Polymarket CTF Exchange",
+        version: "1",
+        chain_id: 137,
+        verifying_contract: address!("4bFb41d5B3570DeFd03C39a9A4D8dE6Bd8B8982E"),
+    };
+    let hash = Order {
+        salt: U256::from(SALT),
+        maker: SIGNER,
+        signer: SIGNER,
+        taker: Address::ZERO,
+        tokenId: U256::from_str(TOKEN_ID).unwrap(),
+        makerAmount: U256::from(4_500_000u64),
+        takerAmount: U256::from(10_000_000u64),
+        expiration: U256::from(EXPIRATION),
+        nonce: U256::ZERO,
+        feeRateBps: U256::ZERO,
+        side: 0,
+        signatureType: 0,
+    }
+    .eip712_signing_hash(&domain);
+    let signature = Signature::from_str(&order.signature).unwrap();
+    assert_eq!(
+        signature.recover_address_from_prehash(&hash).unwrap(),
+        SIGNER
+    );
+}
+
+#[test]
+fn neg_risk_orders_are_signed_for_the_neg_risk_exchange() {
+    let params = MarketParams {
+        neg_risk: true,
+        ..MarketParams::default()
+    };
+    let order = build_signed_order(
+        &signer(),
+        TOKEN_ID,
+        OrderSide::Sell,
+        cents(45),
+        10.0,
+        SALT,
+        0,
+        &params,
+    )
+    .unwrap();
+    assert_eq!(order.maker_amount, "10000000");
+    assert_eq!(order.taker_amount, "4500000");
+
+    let domain = eip712_domain! {
+        name: "Polymarket CTF Exchange",
+        version: "1",
+        chain_id: 137,
+        verifying_contract: address!("C5d563A36AE78145C45a50134d48A1215220f80a"),
+    };
+    let hash = Order {
+        salt: U256::from(SALT),
+        maker: SIGNER,
+        signer: SIGNER,
+        taker: Address::ZERO,
+        tokenId: U256::from_str(TOKEN_ID).unwrap(),
+        makerAmount: U256::from(10_000_000u64),
+        takerAmount: U256::from(4_500_000u64),
+        expiration: U256::ZERO,
+        nonce: U256::ZERO,
+        feeRateBps: U256::ZERO,
+        side: 1,
+        signatureType: 0,
+    }
+    .eip712_signing_hash(&domain);
+    let signature = Signature::from_str(&order.signature).unwrap();
+    assert_eq!(
+        signature.recover_address_from_prehash(&hash).unwrap(),
+        SIGNER
+    );
+}
+
+#[test]
+fn l2_headers_carry_the_hmac_of_the_request() {
+    // Expected values computed with Python's hmac over the same inputs
+    let body = r#"{"order":{"salt":42}}"#;
+    assert_eq!(
+        l2_signature(SECRET, 1_700_000_000, "POST", "/order", body).unwrap(),
+        "FW79u8b9Lg0Q43tBQhf6fDIqUDTqYtuse5QlUE4P75Y="
+    );
+    assert_eq!(
+        l2_signature(SECRET, 1_700_000_000, "GET", "/data/orders", "").unwrap(),
+        "u-1kUB5gq-IgPOTtRfJWQH2JALKxxVru0pUIvbEeoG4="
+    );
+    assert!(l2_signature("not base64!", 1, "GET", "/", "").is_err());
+
+    let credentials = ApiCredentials {
+        api_key: "key-1".to_string(),
+        secret: SECRET.to_string(),
+        passphrase: "pass-1".to_string(),
+    };
+    let headers = credentials
+        .l2_headers(SIGNER, 1_700_000_000, "POST", "/order", body)
+        .unwrap();
+    assert_eq!(
+        headers,
+        vec![
+            ("POLY_ADDRESS", SIGNER.to_checksum(None)),
+            (
+                "POLY_SIGNATURE",
+                "FW79u8b9Lg0Q43tBQhf6fDIqUDTqYtuse5QlUE4P75Y=".to_string()
+            ),
+            ("POLY_TIMESTAMP", "1700000000".to_string()),
+            ("POLY_API_KEY", "key-1".to_string()),
+            ("POLY_PASSPHRASE", "pass-1".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn concurrent_salts_are_unique_increasing_and_exact_as_doubles() {
+    let allocator = Arc::new(SaltAllocator::new());
+    let threads: Vec<_> = (0..8)
+        .map(|thread| {
+            let allocator = allocator.clone();
+            std::thread::spawn(move || {
+                let signer = if thread % 2 == 0 { "a" } else { "b" };
+                let salts: Vec<u64> = (0..125).map(|_| allocator.next_salt(signer)).collect();
+                (signer, salts)
+            })
+        })
+        .collect();
+
+    let mut seen = HashSet::new();
+    for thread in threads {
+        let (signer, salts) = thread.join().unwrap();
+        // Each thread sees its signer's counter only move forward
+        assert!(salts.windows(2).all(|pair| pair[0] < pair[1]), "{signer}");
+        for salt in salts {
+            assert!(salt <= MAX_SALT, "{salt}");
+            assert_eq!(salt as f64 as u64, salt);
+            assert!(seen.insert((signer, salt)), "{signer} reused {salt}");
+        }
+    }
+    assert_eq!(seen.len(), 1000);
+
+    let stats = allocator.stats();
+    assert_eq!(stats.signers, 2);
+    assert_eq!(stats.total_allocated, 1000);
+    assert!(stats.min_headroom > 1 << 50);
+}
+
+#[test]
+fn signer_fingerprints_are_a_stable_hash_of_the_key() {
+    // The first 8 bytes of sha256 over the lowercased address
+    let fingerprint = signer_fingerprint(&SIGNER.to_checksum(None));
+    assert_eq!(fingerprint, "cd87006fa890f2a7");
+    assert_eq!(
+        signer_fingerprint(&format!(" {} ", SIGNER.to_checksum(None).to_uppercase())),
+        fingerprint
+    );
+}
@@ -0,0 +1,398 @@
+//! Request body validation through `AppJson`: every endpoint that takes a
+//! body rejects unknown fields, wrong types, missing fields and empty
+//! required strings with a 400 naming the field, before any upstream call.
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use predict_os_be::api::create_router;
+use predict_os_be::mock::{self, MockUpstreams};
+use predict_os_be::types::checksum_address;
+use predict_os_be::AppError;
+
+const WALLET: &str = "0x00000000000000000000000000000000000000aa";
+
+struct Endpoint {
+    path: &'static str,
+    /// A body that passes validation
+    valid: Value,
+    /// A field and a value of the wrong type for it
+    wrong_type: (&'static str, Value),
+    /// A field the request can't do without, when it has one
+    required: Option<&'static str>,
+    /// A field, an empty value for it, and the error it should produce
+    empty: (&'static str, Value, &'static str),
+}
+
+fn endpoints() -> Vec<Endpoint> {
+    let bot = json!({ "mode": "simple", "bankroll_usd": 10.0, "market_slug": "btc-updown-15m" });
+    vec![
+        Endpoint {
+            path: "/api/analyze-event-markets",
+            valid: json!({ "url": "https://polymarket.com/event/will-it-rain" }),
+            wrong_type: ("compare", json!("yes")),
+            required: None,
+            empty: ("url", json!(" "), "url is required"),
+        },
+        Endpoint {
+            path: "/api/analyze-event-markets/batch",
+            valid: json!({ "urls": ["https://polymarket.com/event/will-it-rain"] }),
+            wrong_type: ("urls", json!("https://polymarket.com/event/will-it-rain")),
+            required: Some("urls"),
+            empty: ("urls", json!([""]), "URLs must not be empty"),
+        },
+        Endpoint {
+            path: "/api/analyze-event-markets/refresh",
+            valid: json!({ "analysis_id": "an_1" }),
+            wrong_type: ("price_threshold", json!("5%")),
+            required: Some("analysis_id"),
+            empty: ("analysis_id", json!(""), "analysis_id is required"),
+        },
+        Endpoint {
+            path: "/api/analyze-and-trade",
+            valid: json!({
+                "url": "https://polymarket.com/event/will-it-rain",
+                "min_confidence": 0.7,
+                "mode": "simple",
+                "bankroll_usd": 10.0,
+            }),
+            wrong_type: ("min_confidence", json!("high")),
+            required: Some("min_confidence"),
+            empty: ("url", json!(""), "url is required"),
+        },
+        Endpoint {
+            path: "/api/analysis-subscriptions",
+            valid: json!({
+                "market_url": "https://polymarket.com/event/will-it-rain",
+                "cadence": "daily",
+            }),
+            wrong_type: ("cadence", json!("hourly")),
+            required: Some("market_url"),
+            empty: ("market_url", json!(""), "market_url is required"),
+        },
+        Endpoint {
+            path: "/api/position-monitors",
+            valid: json!({
+                "wallet_address": WALLET,
+                "market_slug": "will-it-rain",
+                "webhook_url": "https://hooks.example.com/pnl",
+                "pnl_below": -5.0,
+            }),
+            wrong_type: ("pnl_above", json!("5")),
+            required: Some("webhook_url"),
+            empty: ("market_slug", json!(""), "market_slug is required"),
+        },
+        Endpoint {
+            path: "/api/construct-portfolio",
+            valid: json!({ "analyses": [{ "analysis_id": "an_1" }], "bankroll_usd": 100.0 }),
+            wrong_type: ("bankroll_usd", json!("100")),
+            required: Some("bankroll_usd"),
+            empty: (
+                "analyses",
+                json!([{ "market_url": "" }]),
+                "market_url is required",
+            ),
+        },
+        Endpoint {
+            path: "/api/event-mispricing",
+            valid: json!({ "url": "https://polymarket.com/event/fed-decision" }),
+            wrong_type: ("budget_usd", json!(true)),
+            required: Some("url"),
+            empty: ("url", json!(""), "url is required"),
+        },
+        Endpoint {
+            path: "/api/arb-scan",
+            valid: json!({
+                "first_url": "https://polymarket.com/event/will-it-rain",
+                "second_url": "https://kalshi.com/markets/kxrain/kxrain-25dec",
+            }),
+            wrong_type: ("min_edge_bps", json!("50")),
+            required: None,
+            empty: ("second_url", json!(""), "second_url is required"),
+        },
+        Endpoint {
+            path: "/api/polyfactual-research",
+            valid: json!({ "query": "Will it rain?" }),
+            wrong_type: ("query", json!(42)),
+            required: Some("query"),
+            empty: ("query", json!("  "), "query is required"),
+        },
+        Endpoint {
+            path: "/api/position-tracker",
+            valid: json!({ "wallet_address": WALLET }),
+            wrong_type: ("include_history", json!("yes")),
+            required: None,
+            empty: ("wallet_address", json!(""), "wallet_address is required"),
+        },
+        Endpoint {
+            path: "/api/portfolio",
+            valid: json!({ "wallet_address": WALLET }),
+            wrong_type: ("wallet_address", json!(["0xaa"])),
+            required: Some("wallet_address"),
+            empty: ("wallet_address", json!(""), "wallet_address is required"),
+        },
+        Endpoint {
+            path: "/api/limit-order-bot",
+            valid: bot.clone(),
+            wrong_type: ("bankroll_usd", json!("ten")),
+            required: Some("mode"),
+            empty: ("market_slug", json!(""), "market_slug is required"),
+        },
+        Endpoint {
+            path: "/api/limit-order-bot/diff",
+            valid: bot,
+            wrong_type: ("price_tolerance", json!("0.01")),
+            required: Some("mode"),
+            empty: ("market_slug", json!(""), "market_slug is required"),
+        },
+        Endpoint {
+            path: "/api/orders/cancel-all",
+            valid: json!({ "order_ids": ["0x01"] }),
+            wrong_type: ("order_ids", json!("0x01")),
+            required: None,
+            empty: ("market_slug", json!(""), "market_slug is required"),
+        },
+        Endpoint {
+            path: "/api/unwind-leg",
+            valid: json!({ "market_slug": "will-it-rain", "leg": "winning", "fraction": 0.5 }),
+            wrong_type: ("fraction", json!("half")),
+            required: Some("leg"),
+            empty: ("leg", json!(" "), "leg is required"),
+        },
+    ]
+}
+
+async fn send(request: Request<Body>) -> (StatusCode, String) {
+    let state = mock::app_state(&MockUpstreams::all(), mock::config());
+    let response = create_router()
+        .with_state(state)
+        .oneshot(request)
+        .await
+        .expect("router is infallible");
+    let status = response.status();
+    let bytes = to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body is readable");
+    let body: Value = serde_json::from_slice(&bytes).expect("error bodies are JSON");
+    (
+        status,
+        body["error"].as_str().unwrap_or_default().to_string(),
+    )
+}
+
+async fn post(path: &str, body: &Value) -> (StatusCode, String) {
+    let request = Request::post(path)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap();
+    send(request).await
+}
+
+fn with(body: &Value, field: &str, value: Value) -> Value {
+    let mut body = body.clone();
+    body[field] = value;
+    body
+}
+
+#[tokio::test]
+async fn unknown_fields_are_rejected() {
+    for endpoint in endpoints() {
+        let body = with(&endpoint.valid, "unexpected_field", json!(1));
+        let (status, error) = post(endpoint.path, &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", endpoint.path);
+        assert!(
+            error.contains("Unknown field 'unexpected_field'"),
+            "{}: {}",
+            endpoint.path,
+            error
+        );
+    }
+}
+
+#[tokio::test]
+async fn misspelled_fields_get_a_suggestion() {
+    let body = json!({ "mode": "simple", "bankrol_usd": 10.0 });
+    let (status, error) = post("/api/limit-order-bot", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error.contains("'bankrol_usd' (did you mean 'bankroll_usd'?)"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn unknown_nested_fields_are_rejected_with_their_path() {
+    let body = json!({
+        "mode": "simple",
+        "bankroll_usd": 10.0,
+        "outcomes": [{ "outcome": "Up", "wieght": 2.0 }],
+    });
+    let (status, error) = post("/api/limit-order-bot", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.contains("at 'outcomes[0].wieght'"), "{error}");
+    assert!(error.contains("unknown field `wieght`"), "{error}");
+}
+
+#[tokio::test]
+async fn wrong_types_name_the_field_and_expected_type() {
+    for endpoint in endpoints() {
+        let (field, value) = endpoint.wrong_type.clone();
+        let body = with(&endpoint.valid, field, value);
+        let (status, error) = post(endpoint.path, &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", endpoint.path);
+        assert!(
+            error.contains(&format!("at '{}'", field)) && error.contains("expected"),
+            "{}: {}",
+            endpoint.path,
+            error
+        );
+    }
+}
+
+#[tokio::test]
+async fn missing_required_fields_are_named() {
+    for endpoint in endpoints() {
+        let Some(field) = endpoint.required else {
+            continue;
+        };
+        let mut body = endpoint.valid.clone();
+        body.as_object_mut().unwrap().remove(field);
+        let (status, error) = post(endpoint.path, &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", endpoint.path);
+        assert!(
+            error.contains(&format!("missing field `{}`", field)),
+            "{}: {}",
+            endpoint.path,
+            error
+        );
+    }
+
+    let (status, error) = post("/api/analyze-event-markets", &json!({})).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.contains("exactly one of url or market"), "{error}");
+}
+
+#[tokio::test]
+async fn empty_required_strings_are_rejected() {
+    for endpoint in endpoints() {
+        let (field, value, expected) = endpoint.empty;
+        let body = with(&endpoint.valid, field, value);
+        let (status, error) = post(endpoint.path, &body).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST, "{}", endpoint.path);
+        assert!(error.contains(expected), "{}: {}", endpoint.path, error);
+    }
+}
+
+#[tokio::test]
+async fn diff_accepts_the_bot_fields_alongside_its_own() {
+    let body = json!({
+        "mode": "simple",
+        "bankroll_usd": 10.0,
+        "dry_run": true,
+        "apply": true,
+        "price_tolerance": 0.01,
+    });
+    let (status, error) = post("/api/limit-order-bot/diff", &body).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error.contains("apply and dry_run cannot both be set"),
+        "{error}"
+    );
+}
+
+#[tokio::test]
+async fn malformed_json_and_missing_content_type_are_validation_errors() {
+    let request = Request::post("/api/polyfactual-research")
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from("{\"query\": "))
+        .unwrap();
+    let (status, error) = send(request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(
+        error.contains("Failed to parse the request body as JSON"),
+        "{error}"
+    );
+
+    let request = Request::post("/api/polyfactual-research")
+        .body(Body::from(json!({ "query": "q" }).to_string()))
+        .unwrap();
+    let (status, error) = send(request).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.contains("Content-Type: application/json"), "{error}");
+}
+
+#[test]
+fn wallet_addresses_are_checksummed_or_rejected_with_the_problem() {
+    const CHECKSUMMED: &str = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed";
+    let problem = |raw: &str| match checksum_address("wallet_address", raw) {
+        Err(AppError::Validation(message)) => message,
+        other => panic!("{raw}: {other:?}"),
+    };
+
+    assert_eq!(
+        checksum_address("wallet_address", CHECKSUMMED).unwrap(),
+        CHECKSUMMED
+    );
+    assert_eq!(
+        checksum_address("wallet_address", &CHECKSUMMED.to_lowercase()).unwrap(),
+        CHECKSUMMED
+    );
+    assert_eq!(
+        checksum_address(
+            "wallet_address",
+            &format!(" {} ", CHECKSUMMED.to_uppercase().replace("0X", "0x"))
+        )
+        .unwrap(),
+        CHECKSUMMED
+    );
+
+    assert_eq!(
+        problem("0x123"),
+        "wallet_address is not a valid address: expected 42-character 0x-prefixed hex address, got 5 characters"
+    );
+    assert!(
+        problem("0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeg").contains("'g' is not a hex digit")
+    );
+    // Right length without the prefix, e.g. a Solana-style string
+    assert!(problem(&format!("zz{}", &CHECKSUMMED[2..])).contains("0x-prefixed"));
+}
+
+#[tokio::test]
+async fn wallet_addresses_are_checked_before_any_upstream_call() {
+    let upstreams = MockUpstreams::default();
+    let app = |request| {
+        create_router()
+            .with_state(mock::app_state(&upstreams, mock::config()))
+            .oneshot(request)
+    };
+
+    let request = Request::get("/api/portfolio?wallet_address=0x123")
+        .body(Body::empty())
+        .unwrap();
+    let response = app(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let (status, error) = post(
+        "/api/position-tracker",
+        &json!({ "wallet_address": "So11111111111111111111111111111111111111112" }),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(error.contains("got 43 characters"), "{error}");
+    assert!(upstreams.venue.calls().is_empty());
+
+    // Lowercase input comes back checksummed
+    let request =
+        Request::get("/api/portfolio?wallet_address=0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed")
+            .body(Body::empty())
+            .unwrap();
+    let response = app(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: Value =
+        serde_json::from_slice(&to_bytes(response.into_body(), usize::MAX).await.unwrap()).unwrap();
+    assert_eq!(
+        body["wallet_address"],
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed"
+    );
+}